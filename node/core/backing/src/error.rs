@@ -55,6 +55,9 @@ pub enum Error {
 	#[error("a channel was closed before receipt in try_join!")]
 	JoinMultiple(#[source] oneshot::Canceled),
 
+	#[error("MinimumBackingVotes channel closed before receipt")]
+	MinimumBackingVotes(#[source] oneshot::Canceled),
+
 	#[error("Obtaining erasure chunks failed")]
 	ObtainErasureChunks(#[from] erasure_coding::Error),
 
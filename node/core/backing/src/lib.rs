@@ -19,7 +19,7 @@
 #![deny(unused_crate_dependencies)]
 
 use std::{
-	collections::{HashMap, HashSet},
+	collections::{BTreeSet, HashMap, HashSet},
 	sync::Arc,
 };
 
@@ -40,12 +40,12 @@ use polkadot_node_subsystem::{
 		CandidateValidationMessage, CollatorProtocolMessage, ProvisionableData, ProvisionerMessage,
 		RuntimeApiRequest, StatementDistributionMessage,
 	},
-	overseer, ActiveLeavesUpdate, FromOrchestra, OverseerSignal, PerLeafSpan, SpawnedSubsystem,
-	Stage, SubsystemError,
+	overseer, ActiveLeavesUpdate, FromOrchestra, OverseerSignal, PerLeafSpan, RuntimeApiError,
+	SpawnedSubsystem, Stage, SubsystemError,
 };
 use polkadot_node_subsystem_util::{
-	self as util, request_from_runtime, request_session_index_for_child, request_validator_groups,
-	request_validators, Validator,
+	self as util, request_from_runtime, request_minimum_backing_votes,
+	request_session_index_for_child, request_validator_groups, request_validators, Validator,
 };
 use polkadot_primitives::{
 	BackedCandidate, CandidateCommitments, CandidateHash, CandidateReceipt, CollatorId,
@@ -319,6 +319,21 @@ async fn handle_active_leaves_update<Context>(
 	let session_index = try_runtime_api!(session_index);
 	let cores = try_runtime_api!(cores);
 
+	// The runtime tells how many votes it requires of each size of backing group.
+	let mut minimum_votes_by_group_size = HashMap::new();
+	for group_size in validator_groups.iter().map(|g| g.len()).collect::<BTreeSet<_>>() {
+		let votes = request_minimum_backing_votes(parent, group_size as u32, ctx.sender())
+			.await
+			.await
+			.map_err(Error::MinimumBackingVotes)?;
+		let votes = match votes {
+			// Runtimes that don't report it require the legacy minimum.
+			Err(RuntimeApiError::NotSupported { .. }) => Ok(minimum_votes(group_size)),
+			votes => votes.map(|votes| votes as usize),
+		};
+		minimum_votes_by_group_size.insert(group_size, try_runtime_api!(votes));
+	}
+
 	drop(_span);
 	let _span = span.child("validator-construction");
 
@@ -361,7 +376,7 @@ async fn handle_active_leaves_update<Context>(
 		}
 	}
 
-	let table_context = TableContext { groups, validators, validator };
+	let table_context = TableContext { groups, validators, validator, minimum_votes_by_group_size };
 
 	let (assignment, required_collator) = match assignment {
 		None => {
@@ -452,9 +467,9 @@ struct AttestingData {
 	backing: Vec<ValidatorIndex>,
 }
 
-/// How many votes we need to consider a candidate backed.
+/// How many votes we need to consider a candidate backed, if the runtime doesn't report it.
 ///
-/// WARNING: This has to be kept in sync with the runtime check in the inclusion module.
+/// This is what the inclusion module of such runtimes requires.
 fn minimum_votes(n_validators: usize) -> usize {
 	std::cmp::min(2, n_validators)
 }
@@ -464,6 +479,8 @@ struct TableContext {
 	validator: Option<Validator>,
 	groups: HashMap<ParaId, Vec<ValidatorIndex>>,
 	validators: Vec<ValidatorId>,
+	/// The number of votes the runtime requires of backing groups, by their size.
+	minimum_votes_by_group_size: HashMap<usize, usize>,
 }
 
 impl TableContextTrait for TableContext {
@@ -486,7 +503,12 @@ impl TableContextTrait for TableContext {
 	}
 
 	fn requisite_votes(&self, group: &ParaId) -> usize {
-		self.groups.get(group).map_or(usize::MAX, |g| minimum_votes(g.len()))
+		self.groups.get(group).map_or(usize::MAX, |g| {
+			self.minimum_votes_by_group_size
+				.get(&g.len())
+				.copied()
+				.unwrap_or_else(|| minimum_votes(g.len()))
+		})
 	}
 }
 
@@ -261,6 +261,20 @@ async fn test_startup(virtual_overseer: &mut VirtualOverseer, test_state: &TestS
 			tx.send(Ok(test_state.availability_cores.clone())).unwrap();
 		}
 	);
+
+	// Check that subsystem job issues a request for the minimum backing votes of each group size.
+	let group_sizes =
+		test_state.validator_groups.0.iter().map(|g| g.len()).collect::<BTreeSet<_>>();
+	for group_size in group_sizes {
+		assert_matches!(
+			virtual_overseer.recv().await,
+			AllMessages::RuntimeApi(
+				RuntimeApiMessage::Request(parent, RuntimeApiRequest::MinimumBackingVotes(size, tx))
+			) if parent == test_state.relay_parent && size as usize == group_size => {
+				tx.send(Ok(minimum_votes(group_size) as u32)).unwrap();
+			}
+		);
+	}
 }
 
 // Test that a `CandidateBackingMessage::Second` issues validation work
@@ -1328,6 +1342,7 @@ fn candidate_backing_reorders_votes() {
 		validator: None,
 		groups: validator_groups,
 		validators: validator_public.clone(),
+		minimum_votes_by_group_size: HashMap::new(),
 	};
 
 	let fake_attestation = |idx: u32| {
@@ -1634,3 +1649,24 @@ fn observes_backing_even_if_not_validator() {
 		virtual_overseer
 	});
 }
+
+#[test]
+fn requisite_votes_follow_the_runtime() {
+	let chain_a = ParaId::from(1);
+	let chain_b = ParaId::from(2);
+	let mut groups = HashMap::new();
+	groups.insert(chain_a, vec![0, 1, 2, 3].into_iter().map(ValidatorIndex).collect());
+	groups.insert(chain_b, vec![4].into_iter().map(ValidatorIndex).collect());
+
+	let table_context = TableContext {
+		validator: None,
+		groups,
+		validators: Vec::new(),
+		minimum_votes_by_group_size: vec![(4, 3)].into_iter().collect(),
+	};
+
+	assert_eq!(table_context.requisite_votes(&chain_a), 3);
+	// Groups of sizes the runtime was not asked about require the legacy minimum.
+	assert_eq!(table_context.requisite_votes(&chain_b), 1);
+	assert_eq!(table_context.requisite_votes(&ParaId::from(3)), usize::MAX);
+}
@@ -36,9 +36,11 @@ use polkadot_node_subsystem::{
 	SpawnedSubsystem, SubsystemError, SubsystemResult, SubsystemSender,
 };
 use polkadot_node_subsystem_util::{self as util, Validator};
-use polkadot_primitives::{AvailabilityBitfield, CoreState, Hash, ValidatorIndex};
+use polkadot_primitives::{
+	vstaging::CoreBitfieldLayout, AvailabilityBitfield, CoreIndex, CoreState, Hash, ValidatorIndex,
+};
 use sp_keystore::{Error as KeystoreError, KeystorePtr};
-use std::{collections::HashMap, iter::FromIterator, time::Duration};
+use std::{collections::HashMap, time::Duration};
 use wasm_timer::{Delay, Instant};
 
 mod metrics;
@@ -73,6 +75,9 @@ pub enum Error {
 
 	#[error("Keystore failed: {0:?}")]
 	Keystore(KeystoreError),
+
+	#[error("The runtime uses version {0} of the core bitfield layout, which is not supported")]
+	UnsupportedCoreBitfieldLayout(u32),
 }
 
 /// If there is a candidate pending availability, query the Availability Store
@@ -135,6 +140,36 @@ async fn get_availability_cores(
 	}
 }
 
+/// Ensure the runtime maps availability cores to the bits of bitfields like this node does.
+/// Runtimes that don't report the version of their layout use the first one.
+async fn ensure_core_bitfield_layout(
+	relay_parent: Hash,
+	sender: &mut impl SubsystemSender<overseer::BitfieldSigningOutgoingMessages>,
+) -> Result<(), Error> {
+	let (tx, rx) = oneshot::channel();
+	sender
+		.send_message(
+			RuntimeApiMessage::Request(
+				relay_parent,
+				RuntimeApiRequest::CoreBitfieldLayoutVersion(tx),
+			)
+			.into(),
+		)
+		.await;
+	let version = match rx.await? {
+		Ok(version) => version,
+		Err(RuntimeApiError::NotSupported { .. }) => 1,
+		Err(runtime_err) => return Err(runtime_err.into()),
+	};
+
+	if version == CoreBitfieldLayout::VERSION {
+		Ok(())
+	} else {
+		Err(Error::UnsupportedCoreBitfieldLayout(version))
+	}
+}
+
+/// - check that the runtime uses the core bitfield layout we know about
 /// - get the list of core states from the runtime
 /// - for each core, concurrently determine chunk availability (see `get_core_availability`)
 /// - return the bitfield if there were no errors at any point in this process
@@ -145,6 +180,8 @@ async fn construct_availability_bitfield(
 	validator_idx: ValidatorIndex,
 	sender: &mut impl SubsystemSender<overseer::BitfieldSigningOutgoingMessages>,
 ) -> Result<AvailabilityBitfield, Error> {
+	ensure_core_bitfield_layout(relay_parent, sender).await?;
+
 	// get the set of availability cores from the runtime
 	let availability_cores = {
 		let _span = span.child("get-availability-cores");
@@ -167,16 +204,23 @@ async fn construct_availability_bitfield(
 	)
 	.await?;
 
-	let core_bits = FromIterator::from_iter(results.into_iter());
+	let layout = CoreBitfieldLayout::new(availability_cores.len());
+	let bitfield = layout.bitfield(
+		results
+			.into_iter()
+			.enumerate()
+			.filter(|(_, available)| *available)
+			.map(|(core, _)| CoreIndex(core as u32)),
+	);
 	gum::debug!(
 		target: LOG_TARGET,
 		?relay_parent,
 		"Signing Bitfield for {core_count} cores: {core_bits}",
 		core_count = availability_cores.len(),
-		core_bits = core_bits,
+		core_bits = bitfield.0,
 	);
 
-	Ok(AvailabilityBitfield(core_bits))
+	Ok(bitfield)
 }
 
 /// The bitfield signing subsystem.
@@ -302,6 +346,16 @@ where
 			gum::warn!(target: LOG_TARGET, err = ?runtime_err, "Encountered a runtime API error");
 			return Ok(())
 		},
+		Err(Error::UnsupportedCoreBitfieldLayout(version)) => {
+			// Signing bitfields the runtime would read differently could only hurt.
+			gum::warn!(
+				target: LOG_TARGET,
+				version,
+				relay_parent = ?leaf.hash,
+				"Skip bitfield signing for an unsupported core bitfield layout",
+			);
+			return Ok(())
+		},
 		Err(err) => return Err(err),
 		Ok(bitfield) => bitfield,
 	};
@@ -309,17 +363,36 @@ where
 	drop(span_availability);
 	let span_signing = span.child("signing");
 
-	let signed_bitfield =
-		match validator.sign(keystore, bitfield).map_err(|e| Error::Keystore(e))? {
-			Some(b) => b,
-			None => {
-				gum::error!(
-					target: LOG_TARGET,
-					"Key was found at construction, but while signing it could not be found.",
-				);
-				return Ok(())
-			},
-		};
+	// The runtime may require the bitfield to be signed over the layout of the occupied cores.
+	let signing_context = match util::bitfield_signing_context(
+		leaf.hash,
+		validator.signing_context().session_index,
+		&mut sender,
+	)
+	.await
+	{
+		Err(util::Error::RuntimeApi(runtime_err)) => {
+			// Don't take down the node on runtime API errors.
+			gum::warn!(target: LOG_TARGET, err = ?runtime_err, "Encountered a runtime API error");
+			return Ok(())
+		},
+		Err(err) => return Err(Error::Util(err)),
+		Ok(signing_context) => signing_context,
+	};
+
+	let signed_bitfield = match validator
+		.sign_with_context(keystore, bitfield, &signing_context)
+		.map_err(|e| Error::Keystore(e))?
+	{
+		Some(b) => b,
+		None => {
+			gum::error!(
+				target: LOG_TARGET,
+				"Key was found at construction, but while signing it could not be found.",
+			);
+			return Ok(())
+		},
+	};
 
 	metrics.on_bitfield_signed();
 
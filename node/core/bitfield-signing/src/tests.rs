@@ -55,6 +55,14 @@ fn construct_availability_bitfield_works() {
 		loop {
 			futures::select! {
 				m = receiver.next() => match m.unwrap() {
+					AllMessages::RuntimeApi(
+						RuntimeApiMessage::Request(_, RuntimeApiRequest::CoreBitfieldLayoutVersion(tx)),
+					) => {
+						tx.send(Err(RuntimeApiError::NotSupported {
+							runtime_api_name: "core_bitfield_layout_version",
+						}))
+						.unwrap();
+					},
 					AllMessages::RuntimeApi(
 						RuntimeApiMessage::Request(rp, RuntimeApiRequest::AvailabilityCores(tx)),
 					) => {
@@ -83,3 +91,41 @@ fn construct_availability_bitfield_works() {
 		}
 	});
 }
+
+#[test]
+fn construct_availability_bitfield_rejects_unknown_core_bitfield_layout() {
+	block_on(async move {
+		let relay_parent = Hash::default();
+
+		let (mut sender, mut receiver) = polkadot_node_subsystem_test_helpers::sender_receiver();
+		let future = construct_availability_bitfield(
+			relay_parent,
+			&jaeger::Span::Disabled,
+			ValidatorIndex(1u32),
+			&mut sender,
+		)
+		.fuse();
+		pin_mut!(future);
+
+		loop {
+			futures::select! {
+				m = receiver.next() => match m.unwrap() {
+					AllMessages::RuntimeApi(
+						RuntimeApiMessage::Request(rp, RuntimeApiRequest::CoreBitfieldLayoutVersion(tx)),
+					) => {
+						assert_eq!(relay_parent, rp);
+						tx.send(Ok(CoreBitfieldLayout::VERSION + 1)).unwrap();
+					},
+					o => panic!("Unknown message: {:?}", o),
+				},
+				r = future => match r {
+					Err(Error::UnsupportedCoreBitfieldLayout(version)) => {
+						assert_eq!(version, CoreBitfieldLayout::VERSION + 1);
+						break
+					},
+					r => panic!("Unexpected result: {:?}", r),
+				},
+			}
+		}
+	});
+}
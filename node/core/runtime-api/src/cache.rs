@@ -20,11 +20,12 @@ use lru::LruCache;
 use sp_consensus_babe::Epoch;
 
 use polkadot_primitives::{
-	AuthorityDiscoveryId, BlockNumber, CandidateCommitments, CandidateEvent, CandidateHash,
-	CommittedCandidateReceipt, CoreState, DisputeState, ExecutorParams, GroupRotationInfo, Hash,
-	Id as ParaId, InboundDownwardMessage, InboundHrmpMessage, OccupiedCoreAssumption,
-	PersistedValidationData, PvfCheckStatement, ScrapedOnChainVotes, SessionIndex, SessionInfo,
-	ValidationCode, ValidationCodeHash, ValidatorId, ValidatorIndex, ValidatorSignature,
+	vstaging, AuthorityDiscoveryId, BlockNumber, CandidateCommitments, CandidateEvent,
+	CandidateHash, CommittedCandidateReceipt, CoreState, DisputeState, ExecutorParams,
+	GroupRotationInfo, Hash, Id as ParaId, InboundDownwardMessage, InboundHrmpMessage,
+	OccupiedCoreAssumption, PersistedValidationData, PvfCheckStatement, ScrapedOnChainVotes,
+	SessionIndex, SessionInfo, ValidationCode, ValidationCodeHash, ValidatorId, ValidatorIndex,
+	ValidatorSignature,
 };
 
 /// For consistency we have the same capacity for all caches. We use 128 as we'll only need that
@@ -63,6 +64,11 @@ pub(crate) struct RequestResultCache {
 		LruCache<(Hash, ParaId, OccupiedCoreAssumption), Option<ValidationCodeHash>>,
 	version: LruCache<Hash, u32>,
 	disputes: LruCache<Hash, Vec<(SessionIndex, CandidateHash, DisputeState<BlockNumber>)>>,
+	api_features: LruCache<Hash, vstaging::ApiFeatures>,
+	core_bitfield_layout_version: LruCache<Hash, u32>,
+	bitfield_core_layout_hash: LruCache<Hash, Option<Hash>>,
+	minimum_backing_votes: LruCache<(Hash, u32), u32>,
+	availability_threshold: LruCache<(Hash, u32), u32>,
 }
 
 impl Default for RequestResultCache {
@@ -90,6 +96,11 @@ impl Default for RequestResultCache {
 			validation_code_hash: LruCache::new(DEFAULT_CACHE_CAP),
 			version: LruCache::new(DEFAULT_CACHE_CAP),
 			disputes: LruCache::new(DEFAULT_CACHE_CAP),
+			api_features: LruCache::new(DEFAULT_CACHE_CAP),
+			core_bitfield_layout_version: LruCache::new(DEFAULT_CACHE_CAP),
+			bitfield_core_layout_hash: LruCache::new(DEFAULT_CACHE_CAP),
+			minimum_backing_votes: LruCache::new(DEFAULT_CACHE_CAP),
+			availability_threshold: LruCache::new(DEFAULT_CACHE_CAP),
 		}
 	}
 }
@@ -385,6 +396,53 @@ impl RequestResultCache {
 	) {
 		self.disputes.put(relay_parent, value);
 	}
+
+	pub(crate) fn api_features(&mut self, relay_parent: &Hash) -> Option<&vstaging::ApiFeatures> {
+		self.api_features.get(relay_parent)
+	}
+
+	pub(crate) fn cache_api_features(&mut self, relay_parent: Hash, value: vstaging::ApiFeatures) {
+		self.api_features.put(relay_parent, value);
+	}
+
+	pub(crate) fn core_bitfield_layout_version(&mut self, relay_parent: &Hash) -> Option<&u32> {
+		self.core_bitfield_layout_version.get(relay_parent)
+	}
+
+	pub(crate) fn cache_core_bitfield_layout_version(&mut self, relay_parent: Hash, value: u32) {
+		self.core_bitfield_layout_version.put(relay_parent, value);
+	}
+
+	pub(crate) fn bitfield_core_layout_hash(
+		&mut self,
+		relay_parent: &Hash,
+	) -> Option<&Option<Hash>> {
+		self.bitfield_core_layout_hash.get(relay_parent)
+	}
+
+	pub(crate) fn cache_bitfield_core_layout_hash(
+		&mut self,
+		relay_parent: Hash,
+		value: Option<Hash>,
+	) {
+		self.bitfield_core_layout_hash.put(relay_parent, value);
+	}
+
+	pub(crate) fn minimum_backing_votes(&mut self, key: (Hash, u32)) -> Option<&u32> {
+		self.minimum_backing_votes.get(&key)
+	}
+
+	pub(crate) fn cache_minimum_backing_votes(&mut self, key: (Hash, u32), value: u32) {
+		self.minimum_backing_votes.put(key, value);
+	}
+
+	pub(crate) fn availability_threshold(&mut self, key: (Hash, u32)) -> Option<&u32> {
+		self.availability_threshold.get(&key)
+	}
+
+	pub(crate) fn cache_availability_threshold(&mut self, key: (Hash, u32), value: u32) {
+		self.availability_threshold.put(key, value);
+	}
 }
 
 pub(crate) enum RequestResult {
@@ -422,4 +480,9 @@ pub(crate) enum RequestResult {
 	ValidationCodeHash(Hash, ParaId, OccupiedCoreAssumption, Option<ValidationCodeHash>),
 	Version(Hash, u32),
 	Disputes(Hash, Vec<(SessionIndex, CandidateHash, DisputeState<BlockNumber>)>),
+	ApiFeatures(Hash, vstaging::ApiFeatures),
+	CoreBitfieldLayoutVersion(Hash, u32),
+	BitfieldCoreLayoutHash(Hash, Option<Hash>),
+	MinimumBackingVotes(Hash, u32, u32),
+	AvailabilityThreshold(Hash, u32, u32),
 }
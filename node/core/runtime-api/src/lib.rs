@@ -157,6 +157,18 @@ where
 				self.requests_cache.cache_version(relay_parent, version),
 			Disputes(relay_parent, disputes) =>
 				self.requests_cache.cache_disputes(relay_parent, disputes),
+			ApiFeatures(relay_parent, features) =>
+				self.requests_cache.cache_api_features(relay_parent, features),
+			CoreBitfieldLayoutVersion(relay_parent, version) =>
+				self.requests_cache.cache_core_bitfield_layout_version(relay_parent, version),
+			BitfieldCoreLayoutHash(relay_parent, layout_hash) =>
+				self.requests_cache.cache_bitfield_core_layout_hash(relay_parent, layout_hash),
+			MinimumBackingVotes(relay_parent, group_size, votes) => self
+				.requests_cache
+				.cache_minimum_backing_votes((relay_parent, group_size), votes),
+			AvailabilityThreshold(relay_parent, n_validators, threshold) => self
+				.requests_cache
+				.cache_availability_threshold((relay_parent, n_validators), threshold),
 		}
 	}
 
@@ -271,6 +283,18 @@ where
 					.map(|sender| Request::ValidationCodeHash(para, assumption, sender)),
 			Request::Disputes(sender) =>
 				query!(disputes(), sender).map(|sender| Request::Disputes(sender)),
+			Request::ApiFeatures(sender) =>
+				query!(api_features(), sender).map(|sender| Request::ApiFeatures(sender)),
+			Request::CoreBitfieldLayoutVersion(sender) => query!(core_bitfield_layout_version(), sender)
+				.map(|sender| Request::CoreBitfieldLayoutVersion(sender)),
+			Request::BitfieldCoreLayoutHash(sender) => query!(bitfield_core_layout_hash(), sender)
+				.map(|sender| Request::BitfieldCoreLayoutHash(sender)),
+			Request::MinimumBackingVotes(group_size, sender) =>
+				query!(minimum_backing_votes(group_size), sender)
+					.map(|sender| Request::MinimumBackingVotes(group_size, sender)),
+			Request::AvailabilityThreshold(n_validators, sender) =>
+				query!(availability_threshold(n_validators), sender)
+					.map(|sender| Request::AvailabilityThreshold(n_validators, sender)),
 		}
 	}
 
@@ -490,5 +514,35 @@ where
 			query!(ValidationCodeHash, validation_code_hash(para, assumption), ver = 2, sender),
 		Request::Disputes(sender) =>
 			query!(Disputes, disputes(), ver = Request::DISPUTES_RUNTIME_REQUIREMENT, sender),
+		Request::ApiFeatures(sender) => query!(
+			ApiFeatures,
+			api_features(),
+			ver = Request::API_FEATURES_RUNTIME_REQUIREMENT,
+			sender
+		),
+		Request::CoreBitfieldLayoutVersion(sender) => query!(
+			CoreBitfieldLayoutVersion,
+			core_bitfield_layout_version(),
+			ver = Request::CORE_BITFIELD_LAYOUT_VERSION_RUNTIME_REQUIREMENT,
+			sender
+		),
+		Request::BitfieldCoreLayoutHash(sender) => query!(
+			BitfieldCoreLayoutHash,
+			bitfield_core_layout_hash(),
+			ver = Request::BITFIELD_CORE_LAYOUT_HASH_RUNTIME_REQUIREMENT,
+			sender
+		),
+		Request::MinimumBackingVotes(group_size, sender) => query!(
+			MinimumBackingVotes,
+			minimum_backing_votes(group_size),
+			ver = Request::MINIMUM_BACKING_VOTES_RUNTIME_REQUIREMENT,
+			sender
+		),
+		Request::AvailabilityThreshold(n_validators, sender) => query!(
+			AvailabilityThreshold,
+			availability_threshold(n_validators),
+			ver = Request::AVAILABILITY_THRESHOLD_RUNTIME_REQUIREMENT,
+			sender
+		),
 	}
 }
@@ -32,12 +32,14 @@ use polkadot_node_network_protocol::{
 	v1 as protocol_v1, OurView, PeerId, UnifiedReputationChange as Rep, Versioned, View,
 };
 use polkadot_node_subsystem::{
-	jaeger, messages::*, overseer, ActiveLeavesUpdate, FromOrchestra, OverseerSignal, PerLeafSpan,
-	SpawnedSubsystem, SubsystemError, SubsystemResult,
+	errors::RuntimeApiError, jaeger, messages::*, overseer, ActiveLeavesUpdate, FromOrchestra,
+	OverseerSignal, PerLeafSpan, SpawnedSubsystem, SubsystemError, SubsystemResult,
 };
 use polkadot_node_subsystem_util::{self as util};
 
-use polkadot_primitives::{Hash, SignedAvailabilityBitfield, SigningContext, ValidatorId};
+use polkadot_primitives::{
+	vstaging::BitfieldSigningParent, Hash, SignedAvailabilityBitfield, SigningContext, ValidatorId,
+};
 use rand::{CryptoRng, Rng, SeedableRng};
 use std::collections::{HashMap, HashSet};
 
@@ -105,6 +107,10 @@ struct PerRelayParentData {
 	/// Signing context for a particular relay parent.
 	signing_context: SigningContext,
 
+	/// The hash of the core layout bitfields for a particular relay parent are signed over, if the
+	/// runtime binds them to it.
+	core_layout_hash: Option<Hash>,
+
 	/// Set of validators for a particular relay parent.
 	validator_set: Vec<ValidatorId>,
 
@@ -129,11 +135,13 @@ impl PerRelayParentData {
 	/// Create a new instance.
 	fn new(
 		signing_context: SigningContext,
+		core_layout_hash: Option<Hash>,
 		validator_set: Vec<ValidatorId>,
 		span: PerLeafSpan,
 	) -> Self {
 		Self {
 			signing_context,
+			core_layout_hash,
 			validator_set,
 			span,
 			one_per_validator: Default::default(),
@@ -158,6 +166,17 @@ impl PerRelayParentData {
 				.map(|pubkeys| !pubkeys.contains(signed_by))
 				.unwrap_or(true)
 	}
+
+	/// The signing context the bitfields for a particular relay parent are checked against.
+	fn bitfield_signing_context(&self) -> SigningContext<BitfieldSigningParent> {
+		SigningContext {
+			session_index: self.signing_context.session_index,
+			parent_hash: BitfieldSigningParent {
+				parent_hash: self.signing_context.parent_hash,
+				core_layout_hash: self.core_layout_hash,
+			},
+		}
+	}
 }
 
 const LOG_TARGET: &str = "parachain::bitfield-distribution";
@@ -242,7 +261,7 @@ impl BitfieldDistribution {
 
 						// query validator set and signing context per relay_parent once only
 						match query_basics(&mut ctx, relay_parent).await {
-							Ok(Some((validator_set, signing_context))) => {
+							Ok(Some((validator_set, signing_context, core_layout_hash))) => {
 								// If our runtime API fails, we don't take down the node,
 								// but we might alter peers' reputations erroneously as a result
 								// of not having the correct bookkeeping. If we have lost a race
@@ -250,7 +269,12 @@ impl BitfieldDistribution {
 								// us anything to do with this relay-parent anyway.
 								let _ = state.per_relay_parent.insert(
 									relay_parent,
-									PerRelayParentData::new(signing_context, validator_set, span),
+									PerRelayParentData::new(
+										signing_context,
+										core_layout_hash,
+										validator_set,
+										span,
+									),
 								);
 							},
 							Err(err) => {
@@ -486,7 +510,7 @@ async fn process_incoming_peer_message<Context>(
 
 	// Use the (untrusted) validator index provided by the signed payload
 	// and see if that one actually signed the availability bitset.
-	let signing_context = job_data.signing_context.clone();
+	let signing_context = job_data.bitfield_signing_context();
 	let validator = if let Some(validator) = validator_set.get(validator_index.0 as usize) {
 		validator.clone()
 	} else {
@@ -744,14 +768,16 @@ impl<Context> BitfieldDistribution {
 	}
 }
 
-/// Query our validator set and signing context for a particular relay parent.
+/// Query our validator set, signing context and the core layout bitfields are signed over for a
+/// particular relay parent.
 #[overseer::contextbounds(BitfieldDistribution, prefix=self::overseer)]
 async fn query_basics<Context>(
 	ctx: &mut Context,
 	relay_parent: Hash,
-) -> SubsystemResult<Option<(Vec<ValidatorId>, SigningContext)>> {
+) -> SubsystemResult<Option<(Vec<ValidatorId>, SigningContext, Option<Hash>)>> {
 	let (validators_tx, validators_rx) = oneshot::channel();
 	let (session_tx, session_rx) = oneshot::channel();
+	let (layout_tx, layout_rx) = oneshot::channel();
 
 	// query validators
 	ctx.send_message(RuntimeApiMessage::Request(
@@ -767,10 +793,24 @@ async fn query_basics<Context>(
 	))
 	.await;
 
-	match (validators_rx.await?, session_rx.await?) {
-		(Ok(validators), Ok(session_index)) =>
-			Ok(Some((validators, SigningContext { parent_hash: relay_parent, session_index }))),
-		(Err(err), _) | (_, Err(err)) => {
+	// query the core layout, runtimes that don't support it yet don't bind bitfields to it
+	ctx.send_message(RuntimeApiMessage::Request(
+		relay_parent,
+		RuntimeApiRequest::BitfieldCoreLayoutHash(layout_tx),
+	))
+	.await;
+	let core_layout_hash = match layout_rx.await? {
+		Err(RuntimeApiError::NotSupported { .. }) => Ok(None),
+		core_layout_hash => core_layout_hash,
+	};
+
+	match (validators_rx.await?, session_rx.await?, core_layout_hash) {
+		(Ok(validators), Ok(session_index), Ok(core_layout_hash)) => Ok(Some((
+			validators,
+			SigningContext { parent_hash: relay_parent, session_index },
+			core_layout_hash,
+		))),
+		(Err(err), _, _) | (_, Err(err), _) | (_, _, Err(err)) => {
 			gum::warn!(
 				target: LOG_TARGET,
 				?relay_parent,
@@ -74,6 +74,7 @@ fn prewarmed_state(
 			relay_parent.clone() =>
 				PerRelayParentData {
 					signing_context,
+					core_layout_hash: None,
 					validator_set: vec![validator.clone()],
 					one_per_validator: hashmap! {
 						validator.clone() => known_message.clone(),
@@ -108,6 +109,7 @@ fn state_with_view(
 				relay_parent.clone(),
 				PerRelayParentData {
 					signing_context: signing_context.clone(),
+					core_layout_hash: None,
 					validator_set: vec![validator.clone().into()],
 					one_per_validator: hashmap! {},
 					message_received_from_peer: hashmap! {},
@@ -242,6 +244,111 @@ fn receive_invalid_signature() {
 	});
 }
 
+#[test]
+fn receive_bitfield_bound_to_core_layout() {
+	let hash_a: Hash = [0; 32].into();
+	let core_layout_hash: Hash = [2; 32].into();
+
+	let peer_a = PeerId::random();
+	let peer_b = PeerId::random();
+	assert_ne!(peer_a, peer_b);
+
+	let (mut state, signing_context, keystore, validator) =
+		state_with_view(our_view![hash_a], hash_a.clone());
+	state.per_relay_parent.get_mut(&hash_a).unwrap().core_layout_hash = Some(core_layout_hash);
+
+	let payload = AvailabilityBitfield(bitvec![u8, bitvec::order::Lsb0; 1u8; 32]);
+	let unbound_signed = Signed::<AvailabilityBitfield>::sign(
+		&keystore,
+		payload.clone(),
+		&signing_context,
+		ValidatorIndex(0),
+		&validator,
+	)
+	.ok()
+	.flatten()
+	.expect("should be signed");
+	let bound_signing_context = SigningContext {
+		session_index: signing_context.session_index,
+		parent_hash: BitfieldSigningParent {
+			parent_hash: hash_a,
+			core_layout_hash: Some(core_layout_hash),
+		},
+	};
+	let bound_signed = Signed::<AvailabilityBitfield>::sign(
+		&keystore,
+		payload,
+		&bound_signing_context,
+		ValidatorIndex(0),
+		&validator,
+	)
+	.ok()
+	.flatten()
+	.expect("should be signed");
+
+	let unbound_msg =
+		BitfieldGossipMessage { relay_parent: hash_a.clone(), signed_availability: unbound_signed };
+	let bound_msg = BitfieldGossipMessage {
+		relay_parent: hash_a.clone(),
+		signed_availability: bound_signed.clone(),
+	};
+
+	let pool = sp_core::testing::TaskExecutor::new();
+	let (mut ctx, mut handle) = make_subsystem_context::<BitfieldDistributionMessage, _>(pool);
+	let mut rng = dummy_rng();
+
+	executor::block_on(async move {
+		// a bitfield signed without the core layout is rejected
+		launch!(handle_network_msg(
+			&mut ctx,
+			&mut state,
+			&Default::default(),
+			NetworkBridgeEvent::PeerMessage(peer_a.clone(), unbound_msg.into_network_message()),
+			&mut rng,
+		));
+
+		assert_matches!(
+			handle.recv().await,
+			AllMessages::NetworkBridgeTx(
+				NetworkBridgeTxMessage::ReportPeer(peer, rep)
+			) => {
+				assert_eq!(peer, peer_a);
+				assert_eq!(rep, COST_SIGNATURE_INVALID)
+			}
+		);
+
+		// while one signed over it is accepted
+		launch!(handle_network_msg(
+			&mut ctx,
+			&mut state,
+			&Default::default(),
+			NetworkBridgeEvent::PeerMessage(peer_b.clone(), bound_msg.into_network_message()),
+			&mut rng,
+		));
+
+		assert_matches!(
+			handle.recv().await,
+			AllMessages::Provisioner(ProvisionerMessage::ProvisionableData(
+				_,
+				ProvisionableData::Bitfield(hash, signed)
+			)) => {
+				assert_eq!(hash, hash_a);
+				assert_eq!(signed, bound_signed)
+			}
+		);
+
+		assert_matches!(
+			handle.recv().await,
+			AllMessages::NetworkBridgeTx(
+				NetworkBridgeTxMessage::ReportPeer(peer, rep)
+			) => {
+				assert_eq!(peer, peer_b);
+				assert_eq!(rep, BENEFIT_VALID_MESSAGE_FIRST)
+			}
+		);
+	});
+}
+
 #[test]
 fn receive_invalid_validator_index() {
 	let _ = env_logger::builder()
@@ -905,6 +1012,7 @@ fn need_message_works() {
 	let signing_context = SigningContext { session_index: 1, parent_hash: Hash::repeat_byte(0x00) };
 	let mut state = PerRelayParentData::new(
 		signing_context,
+		None,
 		validator_set.clone(),
 		PerLeafSpan::new(Arc::new(Span::Disabled), "foo"),
 	);
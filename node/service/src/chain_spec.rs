@@ -1608,6 +1608,7 @@ pub fn rococo_testnet_genesis(
 		configuration: rococo_runtime::ConfigurationConfig {
 			config: polkadot_runtime_parachains::configuration::HostConfiguration {
 				max_validators_per_core: Some(1),
+				minimum_backing_votes: 1,
 				..default_parachains_host_configuration()
 			},
 		},
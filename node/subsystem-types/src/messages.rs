@@ -39,7 +39,7 @@ use polkadot_node_primitives::{
 	SignedDisputeStatement, SignedFullStatement, ValidationResult,
 };
 use polkadot_primitives::{
-	AuthorityDiscoveryId, BackedCandidate, BlockNumber, CandidateEvent, CandidateHash,
+	vstaging, AuthorityDiscoveryId, BackedCandidate, BlockNumber, CandidateEvent, CandidateHash,
 	CandidateIndex, CandidateReceipt, CollatorId, CommittedCandidateReceipt, CoreState,
 	DisputeState, ExecutorParams, GroupIndex, GroupRotationInfo, Hash, Header as BlockHeader,
 	Id as ParaId, InboundDownwardMessage, InboundHrmpMessage, MultiDisputeStatementSet,
@@ -603,6 +603,20 @@ pub enum RuntimeApiRequest {
 	),
 	/// Returns all on-chain disputes at given block number. Available in `v3`.
 	Disputes(RuntimeApiSender<Vec<(SessionIndex, CandidateHash, DisputeState<BlockNumber>)>>),
+	/// Get the features of the parachain host API the runtime supports. Available in `v5`.
+	ApiFeatures(RuntimeApiSender<vstaging::ApiFeatures>),
+	/// Get the version of the mapping between availability cores and the bits of availability
+	/// bitfields the runtime uses. Available in `v5`.
+	CoreBitfieldLayoutVersion(RuntimeApiSender<u32>),
+	/// Get the hash of the core layout the availability bitfields of the children of the block
+	/// have to be signed over, if any. Available in `v5`.
+	BitfieldCoreLayoutHash(RuntimeApiSender<Option<Hash>>),
+	/// Get the number of backing votes the runtime requires of a backing group of the given size.
+	/// Available in `v5`.
+	MinimumBackingVotes(u32, RuntimeApiSender<u32>),
+	/// Get the number of availability votes the runtime requires for a candidate to be included,
+	/// given the number of validators. Available in `v5`.
+	AvailabilityThreshold(u32, RuntimeApiSender<u32>),
 }
 
 impl RuntimeApiRequest {
@@ -613,6 +627,21 @@ impl RuntimeApiRequest {
 
 	/// `ExecutorParams`
 	pub const EXECUTOR_PARAMS_RUNTIME_REQUIREMENT: u32 = 4;
+
+	/// `ApiFeatures`
+	pub const API_FEATURES_RUNTIME_REQUIREMENT: u32 = 5;
+
+	/// `CoreBitfieldLayoutVersion`
+	pub const CORE_BITFIELD_LAYOUT_VERSION_RUNTIME_REQUIREMENT: u32 = 5;
+
+	/// `BitfieldCoreLayoutHash`
+	pub const BITFIELD_CORE_LAYOUT_HASH_RUNTIME_REQUIREMENT: u32 = 5;
+
+	/// `MinimumBackingVotes`
+	pub const MINIMUM_BACKING_VOTES_RUNTIME_REQUIREMENT: u32 = 5;
+
+	/// `AvailabilityThreshold`
+	pub const AVAILABILITY_THRESHOLD_RUNTIME_REQUIREMENT: u32 = 5;
 }
 
 /// A message to the Runtime API subsystem.
@@ -16,7 +16,7 @@
 
 use async_trait::async_trait;
 use polkadot_primitives::{
-	runtime_api::ParachainHost, Block, BlockNumber, CandidateCommitments, CandidateEvent,
+	runtime_api::ParachainHost, vstaging, Block, BlockNumber, CandidateCommitments, CandidateEvent,
 	CandidateHash, CommittedCandidateReceipt, CoreState, DisputeState, ExecutorParams,
 	GroupRotationInfo, Hash, Id, InboundDownwardMessage, InboundHrmpMessage,
 	OccupiedCoreAssumption, PersistedValidationData, PvfCheckStatement, ScrapedOnChainVotes,
@@ -189,6 +189,32 @@ pub trait RuntimeApiSubsystemClient {
 		session_index: SessionIndex,
 	) -> Result<Option<ExecutorParams>, ApiError>;
 
+	/***** Staging *****/
+
+	/// Returns the features of the parachain host API the runtime supports.
+	/// This is a staging method! Do not use on production runtimes!
+	async fn api_features(&self, at: Hash) -> Result<vstaging::ApiFeatures, ApiError>;
+
+	/// Returns the version of the mapping between availability cores and the bits of availability
+	/// bitfields the runtime uses.
+	/// This is a staging method! Do not use on production runtimes!
+	async fn core_bitfield_layout_version(&self, at: Hash) -> Result<u32, ApiError>;
+
+	/// Returns the hash of the core layout the availability bitfields of the children of the block
+	/// have to be signed over, if any.
+	/// This is a staging method! Do not use on production runtimes!
+	async fn bitfield_core_layout_hash(&self, at: Hash) -> Result<Option<Hash>, ApiError>;
+
+	/// Returns the number of backing votes the runtime requires of a backing group of the given
+	/// size.
+	/// This is a staging method! Do not use on production runtimes!
+	async fn minimum_backing_votes(&self, at: Hash, group_size: u32) -> Result<u32, ApiError>;
+
+	/// Returns the number of availability votes the runtime requires for a candidate to be
+	/// included, given the number of validators.
+	/// This is a staging method! Do not use on production runtimes!
+	async fn availability_threshold(&self, at: Hash, n_validators: u32) -> Result<u32, ApiError>;
+
 	// === BABE API ===
 
 	/// Returns information regarding the current epoch.
@@ -374,4 +400,24 @@ where
 	) -> Result<Vec<(SessionIndex, CandidateHash, DisputeState<BlockNumber>)>, ApiError> {
 		self.runtime_api().disputes(at)
 	}
+
+	async fn api_features(&self, at: Hash) -> Result<vstaging::ApiFeatures, ApiError> {
+		self.runtime_api().api_features(at)
+	}
+
+	async fn core_bitfield_layout_version(&self, at: Hash) -> Result<u32, ApiError> {
+		self.runtime_api().core_bitfield_layout_version(at)
+	}
+
+	async fn bitfield_core_layout_hash(&self, at: Hash) -> Result<Option<Hash>, ApiError> {
+		self.runtime_api().bitfield_core_layout_hash(at)
+	}
+
+	async fn minimum_backing_votes(&self, at: Hash, group_size: u32) -> Result<u32, ApiError> {
+		self.runtime_api().minimum_backing_votes(at, group_size)
+	}
+
+	async fn availability_threshold(&self, at: Hash, n_validators: u32) -> Result<u32, ApiError> {
+		self.runtime_api().availability_threshold(at, n_validators)
+	}
 }
@@ -29,7 +29,10 @@ use polkadot_node_subsystem::{
 	messages::{RuntimeApiMessage, RuntimeApiRequest, RuntimeApiSender},
 	overseer, SubsystemSender,
 };
-use polkadot_primitives::ExecutorParams;
+use polkadot_primitives::{
+	vstaging::{ApiFeatures, BitfieldSigningParent},
+	ExecutorParams,
+};
 
 pub use overseer::{
 	gen::{OrchestraError as OverseerError, Timeout},
@@ -214,6 +217,11 @@ specialize_requests! {
 		-> Option<ValidationCodeHash>; ValidationCodeHash;
 	fn request_on_chain_votes() -> Option<ScrapedOnChainVotes>; FetchOnChainVotes;
 	fn request_session_executor_params(session_index: SessionIndex) -> Option<ExecutorParams>; SessionExecutorParams;
+	fn request_api_features() -> ApiFeatures; ApiFeatures;
+	fn request_core_bitfield_layout_version() -> u32; CoreBitfieldLayoutVersion;
+	fn request_bitfield_core_layout_hash() -> Option<Hash>; BitfieldCoreLayoutHash;
+	fn request_minimum_backing_votes(group_size: u32) -> u32; MinimumBackingVotes;
+	fn request_availability_threshold(n_validators: u32) -> u32; AvailabilityThreshold;
 }
 
 /// Requests executor parameters from the runtime effective at given relay-parent. First obtains
@@ -264,6 +272,56 @@ pub async fn executor_params_at_relay_parent(
 	}
 }
 
+/// Requests the features of the parachain host API supported by the runtime at the given
+/// relay-parent.
+/// Runtimes that don't support the `ApiFeatures` API call yet are assumed to have the features
+/// that come with the version of their API.
+/// Returns an error if failed to communicate to the runtime.
+pub async fn api_features_at_relay_parent(
+	relay_parent: Hash,
+	sender: &mut impl overseer::SubsystemSender<RuntimeApiMessage>,
+) -> Result<ApiFeatures, Error> {
+	match request_api_features(relay_parent, sender).await.await {
+		Err(err) => Err(Error::Oneshot(err)),
+		Ok(Err(RuntimeApiError::NotSupported { .. })) => {
+			let version =
+				request_from_runtime(relay_parent, sender, RuntimeApiRequest::Version).await;
+			match version.await {
+				Err(err) => Err(Error::Oneshot(err)),
+				Ok(Err(err)) => Err(Error::RuntimeApi(err)),
+				Ok(Ok(version)) => Ok(ApiFeatures::from_api_version(version)),
+			}
+		},
+		Ok(Err(err)) => Err(Error::RuntimeApi(err)),
+		Ok(Ok(features)) => Ok(features),
+	}
+}
+
+/// Requests the signing context of the availability bitfields of the children of the given
+/// relay-parent, in the given session. It binds the bitfields to the layout of the occupied cores
+/// if the runtime requires it.
+/// Runtimes that don't support the `BitfieldCoreLayoutHash` API call yet don't bind bitfields to
+/// the core layout.
+/// Returns an error if failed to communicate to the runtime.
+pub async fn bitfield_signing_context(
+	relay_parent: Hash,
+	session_index: SessionIndex,
+	sender: &mut impl overseer::SubsystemSender<RuntimeApiMessage>,
+) -> Result<SigningContext<BitfieldSigningParent>, Error> {
+	let core_layout_hash = match request_bitfield_core_layout_hash(relay_parent, sender).await.await
+	{
+		Err(err) => return Err(Error::Oneshot(err)),
+		Ok(Err(RuntimeApiError::NotSupported { .. })) => None,
+		Ok(Err(err)) => return Err(Error::RuntimeApi(err)),
+		Ok(Ok(core_layout_hash)) => core_layout_hash,
+	};
+
+	Ok(SigningContext {
+		session_index,
+		parent_hash: BitfieldSigningParent { parent_hash: relay_parent, core_layout_hash },
+	})
+}
+
 /// From the given set of validators, find the first key we can sign with, if any.
 pub fn signing_key(validators: &[ValidatorId], keystore: &KeystorePtr) -> Option<ValidatorId> {
 	signing_key_and_index(validators, keystore).map(|(k, _)| k)
@@ -421,4 +479,15 @@ impl Validator {
 	) -> Result<Option<Signed<Payload, RealPayload>>, KeystoreError> {
 		Signed::sign(&keystore, payload, &self.signing_context, self.index, &self.key)
 	}
+
+	/// Sign a payload with this validator in the given signing context, e.g. one that binds
+	/// availability bitfields to the core layout.
+	pub fn sign_with_context<Payload: EncodeAs<RealPayload>, RealPayload: Encode, H: Encode>(
+		&self,
+		keystore: KeystorePtr,
+		payload: Payload,
+		signing_context: &SigningContext<H>,
+	) -> Result<Option<Signed<Payload, RealPayload>>, KeystoreError> {
+		Signed::sign(&keystore, payload, signing_context, self.index, &self.key)
+	}
 }
@@ -0,0 +1,249 @@
+// Copyright (C) Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Golden vectors for the data that validators and collators sign.
+//!
+//! The runtime checks signatures over exactly these bytes, so other node implementations can
+//! check their payloads against them byte for byte. A vector must never be changed to make a test
+//! pass: a change here breaks every signature made by nodes that are not upgraded along with the
+//! runtime.
+//!
+//! All vectors are built from the same inputs: session 7, a parent hash of `0x11` bytes, a core
+//! layout hash of `0x22` bytes and a candidate hash of `0x33` bytes.
+
+use crate::{
+	v4::{
+		collator_signature_payload, AvailabilityBitfield, CandidateDescriptor, CandidateHash,
+		CollatorPair, CompactStatement, Hash, SigningContext, UncheckedSigned,
+		UncheckedSignedAvailabilityBitfield, ValidatorIndex, ValidatorPair, ValidityAttestation,
+	},
+	vstaging::{
+		BitfieldSigningParent, CompactAvailabilityBitfield, NoncedAvailabilityBitfield,
+		UncheckedSignedCompactAvailabilityBitfield, UncheckedSignedNoncedAvailabilityBitfield,
+	},
+};
+use hex_literal::hex;
+use parity_scale_codec::Compact;
+use primitives::Pair;
+
+/// A bitfield of 10 bits, `1011000011`, signed in the plain signing context.
+const BITFIELD_PAYLOAD: [u8; 39] = hex!(
+	// the number of bits, compact encoded.
+	"28"
+	// the bits, least significant first, padded with zeros.
+	"0d03"
+	// the session index.
+	"07000000"
+	// the parent hash.
+	"1111111111111111111111111111111111111111111111111111111111111111"
+);
+
+/// The same bitfield, signed over the core layout as well.
+const CORE_LAYOUT_BOUND_BITFIELD_PAYLOAD: [u8; 71] = hex!(
+	"28"
+	"0d03"
+	"07000000"
+	"1111111111111111111111111111111111111111111111111111111111111111"
+	// the core layout hash.
+	"2222222222222222222222222222222222222222222222222222222222222222"
+);
+
+/// The same bitfield with a nonce of 5.
+const NONCED_BITFIELD_PAYLOAD: [u8; 47] = hex!(
+	"28"
+	"0d03"
+	// the nonce.
+	"0500000000000000"
+	"07000000"
+	"1111111111111111111111111111111111111111111111111111111111111111"
+);
+
+/// A `Seconded` statement about the candidate.
+const SECONDED_STATEMENT_PAYLOAD: [u8; 73] = hex!(
+	// "BKNG"
+	"424b4e47"
+	// the kind of statement.
+	"01"
+	// the candidate hash.
+	"3333333333333333333333333333333333333333333333333333333333333333"
+	"07000000"
+	"1111111111111111111111111111111111111111111111111111111111111111"
+);
+
+/// A `Valid` statement about the candidate.
+const VALID_STATEMENT_PAYLOAD: [u8; 73] = hex!(
+	"424b4e47"
+	"02"
+	"3333333333333333333333333333333333333333333333333333333333333333"
+	"07000000"
+	"1111111111111111111111111111111111111111111111111111111111111111"
+);
+
+/// The collator signature payload of a candidate of para 2000.
+const COLLATOR_SIGNATURE_PAYLOAD: [u8; 132] = hex!(
+	// the relay parent.
+	"1111111111111111111111111111111111111111111111111111111111111111"
+	// the para id.
+	"d0070000"
+	// the persisted validation data hash.
+	"4444444444444444444444444444444444444444444444444444444444444444"
+	// the PoV hash.
+	"5555555555555555555555555555555555555555555555555555555555555555"
+	// the validation code hash.
+	"6666666666666666666666666666666666666666666666666666666666666666"
+);
+
+fn signing_context() -> SigningContext {
+	SigningContext { session_index: 7, parent_hash: Hash::repeat_byte(0x11) }
+}
+
+fn bitfield() -> AvailabilityBitfield {
+	AvailabilityBitfield(bitvec::bitvec![u8, bitvec::order::Lsb0; 1, 0, 1, 1, 0, 0, 0, 0, 1, 1])
+}
+
+fn candidate_hash() -> CandidateHash {
+	CandidateHash(Hash::repeat_byte(0x33))
+}
+
+fn validator_pair() -> ValidatorPair {
+	ValidatorPair::from_seed(&[1; 32])
+}
+
+#[test]
+fn bitfield_signing_payload() {
+	let payload =
+		UncheckedSignedAvailabilityBitfield::signing_payload(&bitfield(), &signing_context());
+	assert_eq!(payload, BITFIELD_PAYLOAD);
+
+	// Without a core layout hash, the signing parent encodes like the parent hash alone.
+	let context = SigningContext {
+		session_index: 7,
+		parent_hash: BitfieldSigningParent {
+			parent_hash: Hash::repeat_byte(0x11),
+			core_layout_hash: None,
+		},
+	};
+	let payload = UncheckedSignedAvailabilityBitfield::signing_payload(&bitfield(), &context);
+	assert_eq!(payload, BITFIELD_PAYLOAD);
+
+	let context = SigningContext {
+		session_index: 7,
+		parent_hash: BitfieldSigningParent {
+			parent_hash: Hash::repeat_byte(0x11),
+			core_layout_hash: Some(Hash::repeat_byte(0x22)),
+		},
+	};
+	let payload = UncheckedSignedAvailabilityBitfield::signing_payload(&bitfield(), &context);
+	assert_eq!(payload, CORE_LAYOUT_BOUND_BITFIELD_PAYLOAD);
+
+	// The runtime accepts signatures over exactly these bytes.
+	let pair = validator_pair();
+	let signed = UncheckedSignedAvailabilityBitfield::new(
+		bitfield(),
+		ValidatorIndex(0),
+		pair.sign(&BITFIELD_PAYLOAD),
+	);
+	assert!(signed.check_signature(&signing_context(), &pair.public()).is_ok());
+}
+
+#[test]
+fn compact_bitfield_signing_payload() {
+	// Compact bitfields are signed as the bitfield they are equivalent to.
+	let sparse = CompactAvailabilityBitfield::Sparse {
+		len: 10,
+		set_bits: vec![Compact(0), Compact(2), Compact(3), Compact(8), Compact(9)],
+	};
+	let full = CompactAvailabilityBitfield::Full(bitfield());
+	for compact in [sparse, full] {
+		let payload = UncheckedSignedCompactAvailabilityBitfield::signing_payload(
+			&compact,
+			&signing_context(),
+		);
+		assert_eq!(payload, BITFIELD_PAYLOAD);
+	}
+}
+
+#[test]
+fn nonced_bitfield_signing_payload() {
+	let nonced = NoncedAvailabilityBitfield { bitfield: bitfield(), nonce: 5 };
+	let payload =
+		UncheckedSignedNoncedAvailabilityBitfield::signing_payload(&nonced, &signing_context());
+	assert_eq!(payload, NONCED_BITFIELD_PAYLOAD);
+
+	let pair = validator_pair();
+	let signed = UncheckedSignedNoncedAvailabilityBitfield::new(
+		nonced,
+		ValidatorIndex(0),
+		pair.sign(&NONCED_BITFIELD_PAYLOAD),
+	);
+	assert!(signed.check_signature(&signing_context(), &pair.public()).is_ok());
+}
+
+#[test]
+fn statement_signing_payload() {
+	let seconded = CompactStatement::Seconded(candidate_hash());
+	let valid = CompactStatement::Valid(candidate_hash());
+	assert_eq!(seconded.signing_payload(&signing_context()), SECONDED_STATEMENT_PAYLOAD);
+	assert_eq!(valid.signing_payload(&signing_context()), VALID_STATEMENT_PAYLOAD);
+	assert_eq!(
+		UncheckedSigned::<CompactStatement>::signing_payload(&seconded, &signing_context()),
+		SECONDED_STATEMENT_PAYLOAD,
+	);
+
+	// The runtime checks the backing votes of a candidate over the same payloads.
+	let signature = validator_pair().sign(&SECONDED_STATEMENT_PAYLOAD);
+	assert_eq!(
+		ValidityAttestation::Implicit(signature.clone())
+			.signed_payload(candidate_hash(), &signing_context()),
+		SECONDED_STATEMENT_PAYLOAD,
+	);
+	assert_eq!(
+		ValidityAttestation::Explicit(signature)
+			.signed_payload(candidate_hash(), &signing_context()),
+		VALID_STATEMENT_PAYLOAD,
+	);
+}
+
+#[test]
+fn collator_signature_payload_vector() {
+	let pair = CollatorPair::from_seed(&[2; 32]);
+	let mut descriptor = CandidateDescriptor {
+		para_id: 2000.into(),
+		relay_parent: Hash::repeat_byte(0x11),
+		collator: pair.public(),
+		persisted_validation_data_hash: Hash::repeat_byte(0x44),
+		pov_hash: Hash::repeat_byte(0x55),
+		erasure_root: Default::default(),
+		signature: pair.sign(&COLLATOR_SIGNATURE_PAYLOAD),
+		para_head: Default::default(),
+		validation_code_hash: Hash::repeat_byte(0x66).into(),
+	};
+
+	let payload = collator_signature_payload(
+		&descriptor.relay_parent,
+		&descriptor.para_id,
+		&descriptor.persisted_validation_data_hash,
+		&descriptor.pov_hash,
+		&descriptor.validation_code_hash,
+	);
+	assert_eq!(payload, COLLATOR_SIGNATURE_PAYLOAD);
+	assert!(descriptor.check_collator_signature().is_ok());
+
+	// The erasure root and the para head are not part of the payload.
+	descriptor.erasure_root = Hash::repeat_byte(0x77);
+	descriptor.para_head = Hash::repeat_byte(0x88);
+	assert!(descriptor.check_collator_signature().is_ok());
+}
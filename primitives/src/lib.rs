@@ -31,6 +31,9 @@ pub mod vstaging;
 // unstable functions.
 pub mod runtime_api;
 
+#[cfg(test)]
+mod conformance_tests;
+
 // Current primitives not requiring versioning are exported here.
 // Primitives requiring versioning must not be exported and must be referred by an exact version.
 pub use v4::{
@@ -111,9 +111,10 @@
 //! from the stable primitives.
 
 use crate::{
-	BlockNumber, CandidateCommitments, CandidateEvent, CandidateHash, CommittedCandidateReceipt,
-	CoreState, DisputeState, ExecutorParams, GroupRotationInfo, OccupiedCoreAssumption,
-	PersistedValidationData, PvfCheckStatement, ScrapedOnChainVotes, SessionIndex, SessionInfo,
+	vstaging, BackedCandidate, BlockNumber, CandidateCommitments, CandidateEvent, CandidateHash,
+	CommittedCandidateReceipt, CoreIndex, CoreState, DisputeState, ExecutorParams, GroupIndex,
+	GroupRotationInfo, Header, OccupiedCoreAssumption, PersistedValidationData, PvfCheckStatement,
+	ScrapedOnChainVotes, SessionIndex, SessionInfo, UncheckedSignedAvailabilityBitfields,
 	ValidatorId, ValidatorIndex, ValidatorSignature,
 };
 use parity_scale_codec::{Decode, Encode};
@@ -218,5 +219,165 @@ sp_api::decl_runtime_apis! {
 
 		/// Returns execution parameters for the session.
 		fn session_executor_params(session_index: SessionIndex) -> Option<ExecutorParams>;
+
+		/***** STAGING *****/
+
+		/// Returns the candidates included in the relay chain block with the given number, as long
+		/// as that block is within the configured retention window.
+		#[api_version(5)]
+		fn included_candidates(block_number: N) -> Vec<vstaging::IncludedCandidate<H>>;
+
+		/// Returns the persisted validation data for the given `ParaId` along with the relay chain
+		/// storage keys the para needs to follow its relay chain context.
+		///
+		/// All returned keys are read by the call, so a storage proof recorded while executing it
+		/// proves them against the relay parent's state root.
+		#[api_version(5)]
+		fn persisted_validation_data_with_relay_state(para_id: ppp::Id, assumption: OccupiedCoreAssumption)
+			-> Option<(PersistedValidationData<H, N>, Vec<Vec<u8>>)>;
+
+		/// Returns the current lifecycle state of the given para, if it is known to the relay chain.
+		#[api_version(5)]
+		fn para_lifecycle(para_id: ppp::Id) -> Option<vstaging::ParaLifecycle>;
+
+		/// Returns the number of candidates backed and included so far in the current session, per
+		/// para and per backing group.
+		#[api_version(5)]
+		fn candidate_statistics() -> vstaging::SessionCandidateStatistics;
+
+		/// Returns the number of candidates included so far in the current session, per collator.
+		#[api_version(5)]
+		fn collator_head_counts() -> vstaging::SessionCollatorHeadCounts;
+
+		/// Returns the receipts of the candidates pending availability for all paras, ordered by
+		/// the index of the core they occupy.
+		#[api_version(5)]
+		fn candidates_pending_availability() -> Vec<CommittedCandidateReceipt<H>>;
+
+		/// Returns the validator group responsible for each availability core in the next block,
+		/// as used to check the backing of candidates.
+		#[api_version(5)]
+		fn group_responsibilities() -> Vec<(CoreIndex, GroupIndex)>;
+
+		/// Returns the heads of the given para noted within the head data retention period, with
+		/// the block number they were noted at, ascending by block number.
+		#[api_version(5)]
+		fn past_para_heads(para_id: ppp::Id) -> Vec<(N, ppp::HeadData)>;
+
+		/// Returns the active host configuration along with the session it became active in, and
+		/// the configuration for the next session if a change is pending for it.
+		#[api_version(5)]
+		fn host_configuration() -> vstaging::HostConfigurationInfo;
+
+		/// Returns a proof that the given validator key is part of the validator set of the given
+		/// session, which stays verifiable in later sessions.
+		///
+		/// A proof can only be generated in the session it is about, so this returns `None` for
+		/// any other session.
+		#[api_version(5)]
+		fn key_ownership_proof(session_index: SessionIndex, validator_id: ValidatorId)
+			-> Option<vstaging::OpaqueKeyOwnershipProof>;
+
+		/// Returns the number of the relay chain block the candidate was included in and the core
+		/// it occupied, if it was included within the last `dispute_period` sessions.
+		#[api_version(5)]
+		fn candidate_included_at(candidate_hash: CandidateHash) -> Option<(N, CoreIndex)>;
+
+		/// Returns the availability votes collected for the candidate occupying the given core, along
+		/// with the number of votes it needs. `None` if the core is not occupied.
+		#[api_version(5)]
+		fn availability_votes(core: CoreIndex) -> Option<vstaging::CoreAvailabilityVotes>;
+
+		/// Returns the paras that are going to be scheduled on each core for the next
+		/// `scheduling_lookahead` times it is free, starting with the one scheduled next.
+		#[api_version(5)]
+		fn claim_queue() -> BTreeMap<CoreIndex, Vec<ppp::Id>>;
+
+		/// Returns the number of blocks each parathread has been waiting for one of its candidates
+		/// to be included as of the next block, since its first claim after its last included
+		/// candidate, sorted by para id.
+		#[api_version(5)]
+		fn parathread_starvation() -> Vec<(ppp::Id, N)>;
+
+		/// Returns the number of paras whose candidate pending availability and commitments were
+		/// found out of sync and removed by the runtime.
+		#[api_version(5)]
+		fn storage_inconsistencies_repaired() -> u32;
+
+		/// Simulates the inclusion of the given availability bitfields and backed candidates in a
+		/// child of the block whose state this is invoked on, and returns whether each of them
+		/// would be accepted. `parent_header` must be the header of that block. The state is left
+		/// untouched.
+		///
+		/// Bitfields are checked one by one and the accepted ones are applied before the
+		/// candidates, which are then checked one by one in the given order, each on top of the
+		/// ones accepted before it.
+		#[api_version(5)]
+		fn simulate_inclusion(
+			parent_header: Header,
+			bitfields: UncheckedSignedAvailabilityBitfields,
+			backed_candidates: Vec<BackedCandidate<H>>,
+		) -> vstaging::InclusionSimulation;
+
+		/// Returns the features of this API the runtime supports, so that nodes can tell which
+		/// calls and behaviours are available without relying on the API version alone.
+		#[api_version(5)]
+		fn api_features() -> vstaging::ApiFeatures;
+
+		/// Returns the storage roots of the recent relay chain blocks kept for
+		/// `relay_storage_roots_retention` blocks, ascending by block number.
+		#[api_version(5)]
+		fn relay_storage_roots() -> Vec<(N, H)>;
+
+		/// Diagnoses the given backed candidate as if it was included in a child of the block whose
+		/// state this is invoked on, and returns the outcome of each check it is subject to.
+		/// `parent_header` must be the header of that block. The state is left untouched.
+		#[api_version(5)]
+		fn inclusion_diagnostics(
+			parent_header: Header,
+			backed_candidate: BackedCandidate<H>,
+		) -> vstaging::InclusionDiagnostics;
+
+		/// Returns the validators of past sessions that lost a dispute and are yet to be slashed,
+		/// by session and disputed candidate. A slash is applied once a report with a key
+		/// ownership proof of the offender is submitted with `submit_report_dispute_lost`.
+		#[api_version(5)]
+		fn unapplied_slashes() -> Vec<(SessionIndex, CandidateHash, vstaging::slashing::PendingSlashes)>;
+
+		/// Submits an unsigned extrinsic reporting a lost dispute, along with a key ownership proof
+		/// of the offender generated with `key_ownership_proof`, to apply its slash.
+		///
+		/// Returns `None` if the extrinsic could not be submitted.
+		#[api_version(5)]
+		fn submit_report_dispute_lost(
+			dispute_proof: vstaging::slashing::DisputeProof,
+			key_ownership_proof: vstaging::OpaqueKeyOwnershipProof,
+		) -> Option<()>;
+
+		/// Returns where the assignment of each core occupied by a candidate pending availability
+		/// came from, ordered by core index.
+		#[api_version(5)]
+		fn core_assignment_sources() -> Vec<(CoreIndex, vstaging::CoreAssignmentSource)>;
+
+		/// Returns the version of the mapping between availability cores and the bits of the
+		/// availability bitfields the runtime processes. See `vstaging::CoreBitfieldLayout`.
+		#[api_version(5)]
+		fn core_bitfield_layout_version() -> u32;
+
+		/// Returns the hash of the core layout the availability bitfields of the children of this
+		/// block have to be signed over, on top of the session and the parent hash, or `None` if
+		/// they are signed without it. See `vstaging::BitfieldSigningParent`.
+		#[api_version(5)]
+		fn bitfield_core_layout_hash() -> Option<pcp::v2::Hash>;
+
+		/// Returns the number of backing votes a candidate of a backing group of the given size
+		/// needs to be backed, as enforced by the runtime.
+		#[api_version(5)]
+		fn minimum_backing_votes(group_size: u32) -> u32;
+
+		/// Returns the number of availability votes a candidate needs to be included, given the
+		/// number of validators, as enforced by the runtime.
+		#[api_version(5)]
+		fn availability_threshold(n_validators: u32) -> u32;
 	}
 }
@@ -17,7 +17,7 @@
 //! `V1` Primitives.
 
 use bitvec::vec::BitVec;
-use parity_scale_codec::{Decode, Encode};
+use parity_scale_codec::{Decode, Encode, MaxEncodedLen};
 use scale_info::TypeInfo;
 use sp_std::{
 	marker::PhantomData,
@@ -103,7 +103,19 @@ pub trait TypeIndex {
 }
 
 /// Index of the validator is used as a lightweight replacement of the `ValidatorId` when appropriate.
-#[derive(Eq, Ord, PartialEq, PartialOrd, Copy, Clone, Encode, Decode, TypeInfo, RuntimeDebug)]
+#[derive(
+	Eq,
+	Ord,
+	PartialEq,
+	PartialOrd,
+	Copy,
+	Clone,
+	Encode,
+	Decode,
+	TypeInfo,
+	MaxEncodedLen,
+	RuntimeDebug,
+)]
 #[cfg_attr(feature = "std", derive(Serialize, Deserialize, Hash))]
 pub struct ValidatorIndex(pub u32);
 
@@ -455,6 +467,18 @@ pub struct CandidateDescriptor<H = Hash> {
 	pub validation_code_hash: ValidationCodeHash,
 }
 
+impl<H: MaxEncodedLen> MaxEncodedLen for CandidateDescriptor<H> {
+	fn max_encoded_len() -> usize {
+		Id::max_encoded_len()
+			.saturating_add(H::max_encoded_len())
+			.saturating_add(CollatorId::max_encoded_len())
+			.saturating_add(Hash::max_encoded_len().saturating_mul(4))
+			// An sr25519 signature always encodes to 64 bytes.
+			.saturating_add(<[u8; 64]>::max_encoded_len())
+			.saturating_add(ValidationCodeHash::max_encoded_len())
+	}
+}
+
 impl<H: AsRef<[u8]>> CandidateDescriptor<H> {
 	/// Check the signature of the collator within this descriptor.
 	pub fn check_collator_signature(&self) -> Result<(), ()> {
@@ -749,9 +773,20 @@ pub fn check_candidate_backing<H: AsRef<[u8]> + Clone + Encode>(
 
 /// The unique (during session) index of a core.
 #[derive(
-	Encode, Decode, Default, PartialOrd, Ord, Eq, PartialEq, Clone, Copy, TypeInfo, RuntimeDebug,
+	Encode,
+	Decode,
+	Default,
+	PartialOrd,
+	Ord,
+	Eq,
+	PartialEq,
+	Clone,
+	Copy,
+	TypeInfo,
+	MaxEncodedLen,
+	RuntimeDebug,
 )]
-#[cfg_attr(feature = "std", derive(Hash))]
+#[cfg_attr(feature = "std", derive(Hash, serde::Serialize, serde::Deserialize))]
 pub struct CoreIndex(pub u32);
 
 impl From<u32> for CoreIndex {
@@ -767,7 +802,7 @@ impl TypeIndex for CoreIndex {
 }
 
 /// The unique (during session) index of a validator group.
-#[derive(Encode, Decode, Default, Clone, Copy, Debug, PartialEq, Eq, TypeInfo)]
+#[derive(Encode, Decode, Default, Clone, Copy, Debug, PartialEq, Eq, TypeInfo, MaxEncodedLen)]
 #[cfg_attr(feature = "std", derive(Hash))]
 pub struct GroupIndex(pub u32);
 
@@ -235,7 +235,28 @@ impl<Payload: EncodeAs<RealPayload>, RealPayload: Encode> UncheckedSigned<Payloa
 		}
 	}
 
-	fn payload_data<H: Encode>(payload: &Payload, context: &SigningContext<H>) -> Vec<u8> {
+	/// Map the payload with `f`, keeping the validator index and the signature.
+	///
+	/// The signature stays the one over the original payload, so this should only be used once
+	/// that signature has been checked, or by code that does not check signatures.
+	pub fn unchecked_map_payload<P, R>(
+		self,
+		f: impl FnOnce(Payload) -> P,
+	) -> UncheckedSigned<P, R> {
+		UncheckedSigned {
+			signature: self.signature,
+			validator_index: self.validator_index,
+			payload: f(self.payload),
+			real_payload: sp_std::marker::PhantomData,
+		}
+	}
+
+	/// The data that is signed for the payload in the given context: the payload encoded as
+	/// `RealPayload`, followed by the encoded context.
+	///
+	/// Signatures are both made and checked over exactly these bytes, so other implementations
+	/// can use it to check that they sign the same data.
+	pub fn signing_payload<H: Encode>(payload: &Payload, context: &SigningContext<H>) -> Vec<u8> {
 		// equivalent to (`real_payload`, context).encode()
 		let mut out = payload.encode_as();
 		out.extend(context.encode());
@@ -251,7 +272,7 @@ impl<Payload: EncodeAs<RealPayload>, RealPayload: Encode> UncheckedSigned<Payloa
 		validator_index: ValidatorIndex,
 		key: &ValidatorId,
 	) -> Result<Option<Self>, KeystoreError> {
-		let data = Self::payload_data(&payload, context);
+		let data = Self::signing_payload(&payload, context);
 		let signature =
 			keystore.sr25519_sign(ValidatorId::ID, key.as_ref(), &data)?.map(|sig| Self {
 				payload,
@@ -269,7 +290,7 @@ impl<Payload: EncodeAs<RealPayload>, RealPayload: Encode> UncheckedSigned<Payloa
 		context: &SigningContext<H>,
 		key: &ValidatorId,
 	) -> Result<(), ()> {
-		let data = Self::payload_data(&self.payload, context);
+		let data = Self::signing_payload(&self.payload, context);
 		if self.signature.verify(data.as_slice(), key) {
 			Ok(())
 		} else {
@@ -286,7 +307,7 @@ impl<Payload: EncodeAs<RealPayload>, RealPayload: Encode> UncheckedSigned<Payloa
 		validator_index: ValidatorIndex,
 	) -> Self {
 		use application_crypto::RuntimeAppPublic;
-		let data = Self::payload_data(&payload, context);
+		let data = Self::signing_payload(&payload, context);
 		let signature = public.sign(&data).unwrap();
 
 		Self { payload, validator_index, signature, real_payload: sp_std::marker::PhantomData }
@@ -20,10 +20,13 @@
 pub use crate::v4::*;
 use sp_std::prelude::*;
 
-use parity_scale_codec::{Decode, Encode};
+use parity_scale_codec::{Compact, Decode, Encode, MaxEncodedLen};
 use primitives::RuntimeDebug;
+use runtime_primitives::DispatchError;
 use scale_info::TypeInfo;
 
+pub mod slashing;
+
 /// Candidate's acceptance limitations for asynchronous backing per relay parent.
 #[derive(RuntimeDebug, Copy, Clone, PartialEq, Encode, Decode, TypeInfo)]
 #[cfg_attr(feature = "std", derive(serde::Serialize, serde::Deserialize))]
@@ -40,3 +43,960 @@ pub struct AsyncBackingParams {
 	/// When async backing is disabled, the only valid value is 0.
 	pub allowed_ancestry_len: u32,
 }
+
+/// A candidate that was included in a relay chain block, as recorded on-chain.
+#[derive(RuntimeDebug, Clone, PartialEq, Encode, Decode, TypeInfo, MaxEncodedLen)]
+pub struct IncludedCandidate<H = Hash> {
+	/// The hash of the included candidate.
+	pub candidate_hash: CandidateHash,
+	/// The para the candidate belongs to.
+	pub para_id: Id,
+	/// The relay parent the candidate was built against.
+	pub relay_parent: H,
+	/// The core the candidate occupied while pending availability.
+	pub core_index: CoreIndex,
+}
+
+/// Where the assignment of a para to an availability core came from.
+#[derive(RuntimeDebug, Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo, MaxEncodedLen)]
+pub enum CoreAssignmentSource {
+	/// The para is a parachain, leasing a core of its own.
+	#[codec(index = 0)]
+	Lease,
+	/// A claim on a parathread core, placed with a deposit or on behalf of the parathread.
+	#[codec(index = 1)]
+	ParathreadClaim,
+	/// An on-demand order for a parathread core, paid at the spot price.
+	#[codec(index = 2)]
+	OnDemandOrder,
+}
+
+/// The hash of the layout of the occupied cores, given as the candidates occupying them in the
+/// order of their cores.
+pub fn core_layout_hash(occupied: &[(CoreIndex, CandidateHash)]) -> Hash {
+	BlakeTwo256::hash_of(&occupied)
+}
+
+/// The parent hash in the signing context of an availability bitfield, along with the hash of the
+/// core layout the bitfield was signed over, if any. See [`core_layout_hash`].
+///
+/// Without a core layout hash, this encodes like the parent hash alone, so that the signature is
+/// the same as over a plain [`SigningContext`].
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub struct BitfieldSigningParent<H = Hash> {
+	/// Hash of the parent.
+	pub parent_hash: H,
+	/// Hash of the layout of the occupied cores.
+	pub core_layout_hash: Option<Hash>,
+}
+
+impl<H: Encode> Encode for BitfieldSigningParent<H> {
+	fn size_hint(&self) -> usize {
+		self.parent_hash.size_hint() + self.core_layout_hash.map_or(0, |_| 32)
+	}
+
+	fn encode_to<T: parity_scale_codec::Output + ?Sized>(&self, dest: &mut T) {
+		self.parent_hash.encode_to(dest);
+		if let Some(core_layout_hash) = &self.core_layout_hash {
+			core_layout_hash.encode_to(dest);
+		}
+	}
+}
+
+/// The availability votes collected so far for the candidate occupying a core.
+#[derive(RuntimeDebug, Clone, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct CoreAvailabilityVotes {
+	/// The hash of the candidate occupying the core.
+	pub candidate_hash: CandidateHash,
+	/// The number of validators that signalled availability of the candidate.
+	pub votes: u32,
+	/// The number of votes the candidate needs to become available.
+	pub threshold: u32,
+}
+
+/// The number of candidates backed and included, counted over a session.
+#[derive(RuntimeDebug, Copy, Clone, Default, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct CandidateCounts {
+	/// The number of candidates backed.
+	#[codec(compact)]
+	pub backed: u32,
+	/// The number of candidates included.
+	#[codec(compact)]
+	pub included: u32,
+}
+
+/// What happened to the parachain candidates and cores in a block, as reported by the
+/// `ParaActivity` event of the statistics pallet.
+#[derive(RuntimeDebug, Copy, Clone, Default, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct ParaActivity {
+	/// The number of candidates backed.
+	pub backed: u32,
+	/// The number of candidates included.
+	pub included: u32,
+	/// The number of candidates that timed out while pending availability.
+	pub timed_out: u32,
+	/// The number of availability bitfields that passed the checks.
+	pub bitfields: u32,
+	/// The number of cores freed, for any reason.
+	pub freed_cores: u32,
+}
+
+/// The candidates backed and included during a session, per para and per backing group.
+#[derive(RuntimeDebug, Clone, PartialEq, Encode, Decode, TypeInfo)]
+pub struct SessionCandidateStatistics {
+	/// The session the candidates were counted in.
+	pub session_index: SessionIndex,
+	/// The counts per para, sorted by para ID.
+	pub paras: Vec<(Id, CandidateCounts)>,
+	/// The counts per backing group, sorted by group index.
+	pub groups: Vec<(GroupIndex, CandidateCounts)>,
+}
+
+/// The candidates included during a session, per collator.
+#[derive(RuntimeDebug, Clone, PartialEq, Encode, Decode, TypeInfo)]
+pub struct SessionCollatorHeadCounts {
+	/// The session the candidates were counted in.
+	pub session_index: SessionIndex,
+	/// The number of included candidates per collator, sorted by collator ID.
+	///
+	/// Candidates with v2 descriptors are not counted, as these do not name their collator.
+	pub collators: Vec<(CollatorId, u32)>,
+}
+
+/// Whether the availability bitfields and backed candidates given to a simulated inclusion would
+/// be accepted, or the error they would be rejected with, in the order they were given.
+#[derive(RuntimeDebug, Clone, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct InclusionSimulation {
+	/// The outcome for each availability bitfield.
+	pub bitfields: Vec<Result<(), DispatchError>>,
+	/// The outcome for each backed candidate.
+	pub candidates: Vec<Result<(), DispatchError>>,
+}
+
+/// The outcome of each check the runtime applies to a backed candidate before including it, as
+/// of a child of the block it is diagnosed in.
+///
+/// Unlike the inclusion itself, the checks don't stop at the first failure, so that all reasons
+/// for a candidate to be rejected are reported at once.
+#[derive(RuntimeDebug, Clone, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct InclusionDiagnostics {
+	/// Whether the collator signature of a v1 descriptor is valid. v2 descriptors carry no
+	/// collator signature, so this is always `true` for them.
+	pub collator_signature_ok: bool,
+	/// The core scheduled for the para that the candidate could occupy, if any. For a v2
+	/// descriptor, this is the core it commits to, if scheduled for the para.
+	pub scheduled_core: Option<CoreIndex>,
+	/// Whether the para may upgrade its code, if the candidate attempts to.
+	pub upgrade_allowed: bool,
+	/// The hash of the persisted validation data the candidate is expected to commit to. `None`
+	/// if its relay parent is not allowed or no persisted validation data can be created for
+	/// the para.
+	pub expected_validation_data_hash: Option<Hash>,
+	/// The hash of the persisted validation data the candidate commits to.
+	pub validation_data_hash: Hash,
+	/// The number of valid backing votes of enabled validators of the group assigned to the
+	/// scheduled core. `None` without a scheduled core, or if the votes are malformed or carry
+	/// an invalid signature.
+	pub backing_votes: Option<u32>,
+	/// The number of backing votes the group assigned to the scheduled core has to cast. `None`
+	/// without a scheduled core.
+	pub backing_threshold: Option<u32>,
+}
+
+/// The possible states of a para, to take into account delayed lifecycle changes.
+///
+/// If the para is in a "transition state", it is expected that the parachain is
+/// queued in the `ActionsQueue` to transition it into a stable state. Its lifecycle
+/// state will be used to determine the state transition to apply to the para.
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, TypeInfo)]
+pub enum ParaLifecycle {
+	/// Para is new and is onboarding as a Parathread or Parachain.
+	Onboarding,
+	/// Para is a Parathread.
+	Parathread,
+	/// Para is a Parachain.
+	Parachain,
+	/// Para is a Parathread which is upgrading to a Parachain.
+	UpgradingParathread,
+	/// Para is a Parachain which is downgrading to a Parathread.
+	DowngradingParachain,
+	/// Parathread is queued to be offboarded.
+	OffboardingParathread,
+	/// Parachain is queued to be offboarded.
+	OffboardingParachain,
+}
+
+impl ParaLifecycle {
+	/// Returns true if parachain is currently onboarding. To learn if the
+	/// parachain is onboarding as a parachain or parathread, look at the
+	/// `UpcomingGenesis` storage item.
+	pub fn is_onboarding(&self) -> bool {
+		matches!(self, ParaLifecycle::Onboarding)
+	}
+
+	/// Returns true if para is in a stable state, i.e. it is currently
+	/// a parachain or parathread, and not in any transition state.
+	pub fn is_stable(&self) -> bool {
+		matches!(self, ParaLifecycle::Parathread | ParaLifecycle::Parachain)
+	}
+
+	/// Returns true if para is currently treated as a parachain.
+	/// This also includes transitioning states, so you may want to combine
+	/// this check with `is_stable` if you specifically want `Paralifecycle::Parachain`.
+	pub fn is_parachain(&self) -> bool {
+		matches!(
+			self,
+			ParaLifecycle::Parachain |
+				ParaLifecycle::DowngradingParachain |
+				ParaLifecycle::OffboardingParachain
+		)
+	}
+
+	/// Returns true if para is currently treated as a parathread.
+	/// This also includes transitioning states, so you may want to combine
+	/// this check with `is_stable` if you specifically want `Paralifecycle::Parathread`.
+	pub fn is_parathread(&self) -> bool {
+		matches!(
+			self,
+			ParaLifecycle::Parathread |
+				ParaLifecycle::UpgradingParathread |
+				ParaLifecycle::OffboardingParathread
+		)
+	}
+
+	/// Returns true if para is currently offboarding.
+	pub fn is_offboarding(&self) -> bool {
+		matches!(self, ParaLifecycle::OffboardingParathread | ParaLifecycle::OffboardingParachain)
+	}
+
+	/// Returns true if para is in any transitionary state.
+	pub fn is_transitioning(&self) -> bool {
+		!Self::is_stable(self)
+	}
+}
+
+/// The host configuration in effect, as seen by node-side subsystems.
+#[derive(Clone, Encode, Decode, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(PartialEq))]
+pub struct HostConfigurationInfo {
+	/// The active host configuration.
+	pub active: AbridgedHostConfiguration,
+	/// The session in which the active configuration became active.
+	pub active_since: SessionIndex,
+	/// The configuration that will become active in the next session, if it changes.
+	pub next_session: Option<AbridgedHostConfiguration>,
+}
+
+/// An opaque proof that a validator key was part of the validator set of a session.
+///
+/// Generated by the runtime in the session it is about, and checked against the historical
+/// session roots afterwards.
+#[derive(Decode, Encode, PartialEq, Eq, Clone, RuntimeDebug, TypeInfo)]
+pub struct OpaqueKeyOwnershipProof(Vec<u8>);
+
+impl OpaqueKeyOwnershipProof {
+	/// Create a new `OpaqueKeyOwnershipProof` from the given encoded key ownership proof.
+	pub fn new(inner: Vec<u8>) -> OpaqueKeyOwnershipProof {
+		OpaqueKeyOwnershipProof(inner)
+	}
+
+	/// Try to decode this `OpaqueKeyOwnershipProof` into the given concrete key ownership proof
+	/// type.
+	pub fn decode<T: Decode>(self) -> Option<T> {
+		Decode::decode(&mut &self.0[..]).ok()
+	}
+}
+
+/// The features of the `ParachainHost` runtime API a runtime supports, as reported by its
+/// `api_features` call.
+///
+/// Each feature is a bit. Bits a node doesn't know about are ignored, so runtimes can announce new
+/// features without breaking older nodes.
+#[derive(Decode, Encode, PartialEq, Eq, Clone, Copy, Default, RuntimeDebug, TypeInfo)]
+pub struct ApiFeatures(pub u64);
+
+impl ApiFeatures {
+	/// The `disputes` call, and dispute statements in the paras inherent.
+	pub const DISPUTES: ApiFeatures = ApiFeatures(1 << 0);
+	/// The `session_info` call.
+	pub const SESSION_INFO: ApiFeatures = ApiFeatures(1 << 1);
+	/// The `session_executor_params` call.
+	pub const EXECUTOR_PARAMS: ApiFeatures = ApiFeatures(1 << 2);
+	/// Candidates can be backed on any of the relay parents allowed by
+	/// `max_candidate_relay_parent_depth`, not only on the parent of the block including them.
+	/// Only set while the configuration allows more than the parent.
+	pub const ASYNC_BACKING: ApiFeatures = ApiFeatures(1 << 3);
+
+	/// The features of a runtime that doesn't report them, derived from the version of its
+	/// `ParachainHost` API.
+	pub fn from_api_version(version: u32) -> Self {
+		let mut features = ApiFeatures::default();
+		if version >= 2 {
+			features = features.with(Self::SESSION_INFO);
+		}
+		if version >= 3 {
+			features = features.with(Self::DISPUTES);
+		}
+		if version >= 4 {
+			features = features.with(Self::EXECUTOR_PARAMS);
+		}
+		features
+	}
+
+	/// Whether all of the given features are supported.
+	pub fn contains(&self, features: ApiFeatures) -> bool {
+		self.0 & features.0 == features.0
+	}
+
+	/// These features along with the given ones.
+	pub fn with(self, features: ApiFeatures) -> Self {
+		ApiFeatures(self.0 | features.0)
+	}
+}
+
+/// The mapping between availability cores and the bits of availability bitfields, shared by the
+/// validators signing bitfields and the runtime processing them.
+///
+/// Nodes check the [`CoreBitfieldLayout::VERSION`] they were built with against the one reported
+/// by the `core_bitfield_layout_version` runtime API, so that a change of the mapping can't make
+/// either side silently misread the bitfields of the other.
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub struct CoreBitfieldLayout {
+	n_cores: usize,
+}
+
+impl CoreBitfieldLayout {
+	/// The version of the layout. Version 1 maps each core to the bit at its core index.
+	pub const VERSION: u32 = 1;
+
+	/// The layout of bitfields over the given number of availability cores.
+	pub const fn new(n_cores: usize) -> Self {
+		CoreBitfieldLayout { n_cores }
+	}
+
+	/// The number of bits of a bitfield in this layout.
+	pub const fn n_bits(&self) -> usize {
+		self.n_cores
+	}
+
+	/// The bit standing for the given core, if the core is part of the layout.
+	pub const fn bit_of(&self, core: CoreIndex) -> Option<usize> {
+		let bit = core.0 as usize;
+		if bit < self.n_cores {
+			Some(bit)
+		} else {
+			None
+		}
+	}
+
+	/// The core the given bit stands for, if the bit is part of the layout.
+	pub const fn core_of(&self, bit: usize) -> Option<CoreIndex> {
+		if bit < self.n_cores {
+			Some(CoreIndex(bit as u32))
+		} else {
+			None
+		}
+	}
+
+	/// A bitfield in this layout with the bits of the given cores set. Cores that are not part of
+	/// the layout are ignored.
+	pub fn bitfield(&self, available: impl IntoIterator<Item = CoreIndex>) -> AvailabilityBitfield {
+		let mut bits = bitvec::bitvec![u8, bitvec::order::Lsb0; 0; self.n_bits()];
+		for bit in available.into_iter().filter_map(|core| self.bit_of(core)) {
+			bits.set(bit, true);
+		}
+		AvailabilityBitfield(bits)
+	}
+}
+
+// Version 1 of the layout keeps bits in the order of core indices. Changing the mapping requires
+// bumping `CoreBitfieldLayout::VERSION` along with these assertions.
+const _: () = {
+	assert!(CoreBitfieldLayout::VERSION == 1);
+	let layout = CoreBitfieldLayout::new(3);
+	assert!(matches!(layout.bit_of(CoreIndex(0)), Some(0)));
+	assert!(matches!(layout.bit_of(CoreIndex(2)), Some(2)));
+	assert!(layout.bit_of(CoreIndex(3)).is_none());
+	assert!(matches!(layout.core_of(1), Some(CoreIndex(1))));
+	assert!(layout.core_of(3).is_none());
+};
+
+/// The version of a [`CandidateDescriptor`].
+#[derive(PartialEq, Eq, Clone, Copy, RuntimeDebug)]
+pub enum CandidateDescriptorVersion {
+	/// The original descriptor, signed by the collator.
+	V1,
+	/// A descriptor committing to the core index and the session index of the candidate.
+	V2,
+	/// A descriptor that is neither v1 nor v2, which is invalid for now.
+	Unknown,
+}
+
+/// The layout of the v2 fields within the collator ID of a v2 descriptor.
+const V2_CORE_INDEX: core::ops::Range<usize> = 0..2;
+const V2_SESSION_INDEX: core::ops::Range<usize> = 2..6;
+const V2_RESERVED: core::ops::RangeFrom<usize> = 6..;
+
+/// Access to the fields of v2 candidate descriptors.
+///
+/// A v2 descriptor keeps the encoding of a v1 descriptor, so that it can be used wherever a v1
+/// descriptor is expected. Instead of a collator ID and signature, it carries the core index and
+/// the session index in the collator ID field, and an all-zero signature. The remaining bytes of
+/// the collator ID are reserved and must be zero.
+pub trait CandidateDescriptorV2Ext {
+	/// The version of the descriptor.
+	fn version(&self) -> CandidateDescriptorVersion;
+
+	/// The core index the candidate is meant to occupy, if this is a v2 descriptor.
+	fn core_index(&self) -> Option<CoreIndex>;
+
+	/// The session the candidate is meant to be backed in, if this is a v2 descriptor.
+	fn session_index(&self) -> Option<SessionIndex>;
+
+	/// Turn the descriptor into a v2 descriptor for the given core and session.
+	fn set_v2_fields(&mut self, core_index: CoreIndex, session_index: SessionIndex);
+}
+
+impl<H> CandidateDescriptorV2Ext for CandidateDescriptor<H> {
+	fn version(&self) -> CandidateDescriptorVersion {
+		// A valid sr25519 signature is never all zeroes.
+		if self.signature.as_ref().iter().any(|b| *b != 0) {
+			return CandidateDescriptorVersion::V1
+		}
+
+		if self.collator.as_ref()[V2_RESERVED].iter().all(|b| *b == 0) {
+			CandidateDescriptorVersion::V2
+		} else {
+			CandidateDescriptorVersion::Unknown
+		}
+	}
+
+	fn core_index(&self) -> Option<CoreIndex> {
+		if self.version() != CandidateDescriptorVersion::V2 {
+			return None
+		}
+
+		let mut bytes = [0u8; 2];
+		bytes.copy_from_slice(&self.collator.as_ref()[V2_CORE_INDEX]);
+		Some(CoreIndex(u16::from_le_bytes(bytes).into()))
+	}
+
+	fn session_index(&self) -> Option<SessionIndex> {
+		if self.version() != CandidateDescriptorVersion::V2 {
+			return None
+		}
+
+		let mut bytes = [0u8; 4];
+		bytes.copy_from_slice(&self.collator.as_ref()[V2_SESSION_INDEX]);
+		Some(SessionIndex::from_le_bytes(bytes))
+	}
+
+	fn set_v2_fields(&mut self, core_index: CoreIndex, session_index: SessionIndex) {
+		let mut collator = [0u8; 32];
+		collator[V2_CORE_INDEX].copy_from_slice(&(core_index.0 as u16).to_le_bytes());
+		collator[V2_SESSION_INDEX].copy_from_slice(&session_index.to_le_bytes());
+
+		self.collator = primitives::sr25519::Public::from_raw(collator).into();
+		self.signature = primitives::sr25519::Signature::from_raw([0u8; 64]).into();
+	}
+}
+
+/// An [`AvailabilityBitfield`] in the smaller of two encodings.
+///
+/// With large validator sets, most bitfields have few bits set, so listing the indices of the set
+/// bits is much smaller than the bitfield itself. Both encodings carry the same information, and a
+/// signature over the [`AvailabilityBitfield`] stays valid for the compact form.
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, TypeInfo)]
+pub enum CompactAvailabilityBitfield {
+	/// The bitfield as is.
+	#[codec(index = 0)]
+	Full(AvailabilityBitfield),
+	/// The length of the bitfield and the ascending indices of its set bits.
+	#[codec(index = 1)]
+	Sparse {
+		/// The number of bits in the bitfield.
+		#[codec(compact)]
+		len: u32,
+		/// The indices of the set bits, ascending and without duplicates.
+		set_bits: Vec<Compact<u32>>,
+	},
+}
+
+impl CompactAvailabilityBitfield {
+	/// The number of bits in the bitfield.
+	pub fn len(&self) -> usize {
+		match self {
+			Self::Full(bitfield) => bitfield.0.len(),
+			Self::Sparse { len, .. } => *len as usize,
+		}
+	}
+
+	/// Whether the bitfield has no bits.
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Whether the set bits of a sparse bitfield are ascending, unique and within its length.
+	///
+	/// Bitfields that are not well-formed are not equivalent to any [`AvailabilityBitfield`].
+	pub fn is_well_formed(&self) -> bool {
+		match self {
+			Self::Full(_) => true,
+			Self::Sparse { len, set_bits } => {
+				set_bits.windows(2).all(|w| w[0].0 < w[1].0) &&
+					set_bits.last().map_or(true, |last| last.0 < *len)
+			},
+		}
+	}
+}
+
+impl From<AvailabilityBitfield> for CompactAvailabilityBitfield {
+	fn from(bitfield: AvailabilityBitfield) -> Self {
+		let sparse = Self::Sparse {
+			len: bitfield.0.len() as u32,
+			set_bits: bitfield.0.iter_ones().map(|i| Compact(i as u32)).collect(),
+		};
+
+		if sparse.encoded_size() < bitfield.encoded_size() {
+			sparse
+		} else {
+			Self::Full(bitfield)
+		}
+	}
+}
+
+impl From<&CompactAvailabilityBitfield> for AvailabilityBitfield {
+	fn from(compact: &CompactAvailabilityBitfield) -> Self {
+		match compact {
+			CompactAvailabilityBitfield::Full(bitfield) => bitfield.clone(),
+			CompactAvailabilityBitfield::Sparse { len, set_bits } => {
+				let mut bits = bitvec::bitvec![u8, bitvec::order::Lsb0; 0; *len as usize];
+				for i in set_bits.iter().map(|i| i.0 as usize).filter(|i| *i < bits.len()) {
+					bits.set(i, true);
+				}
+				AvailabilityBitfield(bits)
+			},
+		}
+	}
+}
+
+impl EncodeAs<AvailabilityBitfield> for CompactAvailabilityBitfield {
+	fn encode_as(&self) -> Vec<u8> {
+		AvailabilityBitfield::from(self).encode()
+	}
+}
+
+/// A compact bitfield, signed over the equivalent [`AvailabilityBitfield`], with the signature not
+/// yet checked.
+pub type UncheckedSignedCompactAvailabilityBitfield =
+	UncheckedSigned<CompactAvailabilityBitfield, AvailabilityBitfield>;
+
+/// A set of unchecked signed compact bitfields. Should be sorted by validator index, ascending.
+pub type UncheckedSignedCompactAvailabilityBitfields =
+	Vec<UncheckedSignedCompactAvailabilityBitfield>;
+
+/// An [`AvailabilityBitfield`] along with a nonce, which a validator increases with every bitfield
+/// it signs within a session.
+///
+/// The nonce is part of the signed payload, so that replayed bitfields can be told apart from new
+/// ones before checking their signatures.
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, TypeInfo)]
+pub struct NoncedAvailabilityBitfield {
+	/// The bitfield.
+	pub bitfield: AvailabilityBitfield,
+	/// The nonce of the bitfield.
+	pub nonce: u64,
+}
+
+/// A nonced bitfield, signed including its nonce, with the signature not yet checked.
+pub type UncheckedSignedNoncedAvailabilityBitfield = UncheckedSigned<NoncedAvailabilityBitfield>;
+
+/// A set of unchecked signed nonced bitfields. Should be sorted by validator index, ascending.
+pub type UncheckedSignedNoncedAvailabilityBitfields =
+	Vec<UncheckedSignedNoncedAvailabilityBitfield>;
+
+/// Re-encode signed bitfields in their compact form, keeping their signatures.
+#[cfg(feature = "std")]
+pub fn compact_signed_bitfields(
+	bitfields: UncheckedSignedAvailabilityBitfields,
+) -> UncheckedSignedCompactAvailabilityBitfields {
+	bitfields
+		.into_iter()
+		.map(|signed| {
+			let validator_index = signed.unchecked_validator_index();
+			let signature = signed.unchecked_signature().clone();
+			UncheckedSigned::new(signed.unchecked_into_payload().into(), validator_index, signature)
+		})
+		.collect()
+}
+
+/// Expand signed compact bitfields into [`AvailabilityBitfield`]s, keeping their signatures.
+pub fn expand_signed_bitfields(
+	bitfields: &[UncheckedSignedCompactAvailabilityBitfield],
+) -> UncheckedSignedAvailabilityBitfields {
+	bitfields.iter().map(|signed| signed.unchecked_convert_payload()).collect()
+}
+
+/// The relay chain state a candidate is checked against by [`check_candidate`].
+///
+/// The runtime fills this in from its own storage, while node-side subsystems can obtain the
+/// same values through runtime APIs, so that both apply exactly the same checks.
+#[derive(PartialEq, Eq, Clone, RuntimeDebug)]
+pub struct CandidateCheckParams {
+	/// The hash of the persisted validation data the candidate must commit to.
+	pub persisted_validation_data_hash: Hash,
+	/// The current validation code hash of the para.
+	pub validation_code_hash: ValidationCodeHash,
+	/// The session the candidate is backed in.
+	pub session_index: SessionIndex,
+	/// Whether v1 candidate descriptors are rejected.
+	pub v1_descriptors_rejected: bool,
+	/// Whether the para may upgrade its validation code. Only consulted for candidates that
+	/// upgrade it.
+	pub code_upgrade_allowed: bool,
+	/// The maximum size of the head data produced by the candidate.
+	pub max_head_data_size: u32,
+	/// The maximum size of the validation code the candidate may upgrade to.
+	pub max_code_size: u32,
+}
+
+/// The reason a candidate failed [`check_candidate`].
+#[derive(PartialEq, Eq, Clone, Copy, RuntimeDebug)]
+pub enum CandidateCheckError {
+	/// The candidate does not commit to the expected persisted validation data.
+	ValidationDataHashMismatch,
+	/// The candidate descriptor version is unknown or no longer accepted.
+	InvalidDescriptorVersion,
+	/// The collator signature of a v1 descriptor is invalid.
+	NotCollatorSigned,
+	/// The session index of a v2 descriptor is not the current one.
+	InvalidSessionIndex,
+	/// The candidate was not built against the current validation code of the para.
+	InvalidValidationCodeHash,
+	/// The para head in the descriptor does not match the head data in the commitments.
+	ParaHeadMismatch,
+	/// The head data is larger than allowed.
+	HeadDataTooLarge,
+	/// The candidate upgrades the validation code while the para may not.
+	PrematureCodeUpgrade,
+	/// The new validation code is larger than allowed.
+	NewCodeTooLarge,
+	/// The backing votes are malformed or carry invalid signatures.
+	InvalidBacking,
+}
+
+/// Check the descriptor and the commitments of a candidate against the relay chain state.
+///
+/// The checks are applied in order: persisted validation data, descriptor version, validation
+/// code, para head and finally the outputs, see [`check_candidate_outputs`].
+pub fn check_candidate_receipt<H: AsRef<[u8]>>(
+	candidate: &CommittedCandidateReceipt<H>,
+	params: &CandidateCheckParams,
+) -> Result<(), CandidateCheckError> {
+	let descriptor = &candidate.descriptor;
+	if descriptor.persisted_validation_data_hash != params.persisted_validation_data_hash {
+		return Err(CandidateCheckError::ValidationDataHashMismatch)
+	}
+
+	match descriptor.version() {
+		CandidateDescriptorVersion::V1 => {
+			if params.v1_descriptors_rejected {
+				return Err(CandidateCheckError::InvalidDescriptorVersion)
+			}
+			descriptor
+				.check_collator_signature()
+				.map_err(|_| CandidateCheckError::NotCollatorSigned)?;
+		},
+		CandidateDescriptorVersion::V2 => {
+			if descriptor.session_index() != Some(params.session_index) {
+				return Err(CandidateCheckError::InvalidSessionIndex)
+			}
+		},
+		CandidateDescriptorVersion::Unknown => {
+			return Err(CandidateCheckError::InvalidDescriptorVersion)
+		},
+	}
+
+	if descriptor.validation_code_hash != params.validation_code_hash {
+		return Err(CandidateCheckError::InvalidValidationCodeHash)
+	}
+
+	if descriptor.para_head != candidate.commitments.head_data.hash() {
+		return Err(CandidateCheckError::ParaHeadMismatch)
+	}
+
+	check_candidate_outputs(
+		&candidate.commitments.head_data,
+		candidate.commitments.new_validation_code.as_ref(),
+		params.code_upgrade_allowed,
+		params.max_head_data_size,
+		params.max_code_size,
+	)
+}
+
+/// Check the head data and the validation code upgrade produced by a candidate.
+///
+/// The messaging outputs depend on the state of the message queues and are left to the runtime.
+pub fn check_candidate_outputs(
+	head_data: &HeadData,
+	new_validation_code: Option<&ValidationCode>,
+	code_upgrade_allowed: bool,
+	max_head_data_size: u32,
+	max_code_size: u32,
+) -> Result<(), CandidateCheckError> {
+	if head_data.0.len() > max_head_data_size as usize {
+		return Err(CandidateCheckError::HeadDataTooLarge)
+	}
+
+	if let Some(new_validation_code) = new_validation_code {
+		if !code_upgrade_allowed {
+			return Err(CandidateCheckError::PrematureCodeUpgrade)
+		}
+		if new_validation_code.0.len() > max_code_size as usize {
+			return Err(CandidateCheckError::NewCodeTooLarge)
+		}
+	}
+
+	Ok(())
+}
+
+/// Check a backed candidate the way the runtime does before accepting it.
+///
+/// On top of [`check_candidate_receipt`], this checks the signatures of the backing votes with
+/// [`check_candidate_backing`] and returns the number of valid votes. Whether that is enough to
+/// back the candidate is up to the caller.
+pub fn check_candidate<H: AsRef<[u8]> + Clone + Encode>(
+	backed: &BackedCandidate<H>,
+	params: &CandidateCheckParams,
+	signing_context: &SigningContext<H>,
+	group_len: usize,
+	validator_lookup: impl Fn(usize) -> Option<ValidatorId>,
+) -> Result<usize, CandidateCheckError> {
+	check_candidate_receipt(&backed.candidate, params)?;
+	check_candidate_backing(backed, signing_context, group_len, validator_lookup)
+		.map_err(|_| CandidateCheckError::InvalidBacking)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn dummy_descriptor() -> CandidateDescriptor {
+		CandidateDescriptor {
+			para_id: 1.into(),
+			relay_parent: Default::default(),
+			collator: primitives::sr25519::Public::from_raw([1; 32]).into(),
+			persisted_validation_data_hash: Default::default(),
+			pov_hash: Default::default(),
+			erasure_root: Default::default(),
+			signature: primitives::sr25519::Signature::from_raw([1; 64]).into(),
+			para_head: Default::default(),
+			validation_code_hash: ValidationCode(vec![1, 2, 3]).hash(),
+		}
+	}
+
+	fn check_params(candidate: &CommittedCandidateReceipt) -> CandidateCheckParams {
+		CandidateCheckParams {
+			persisted_validation_data_hash: candidate.descriptor.persisted_validation_data_hash,
+			validation_code_hash: candidate.descriptor.validation_code_hash,
+			session_index: 7,
+			v1_descriptors_rejected: false,
+			code_upgrade_allowed: true,
+			max_head_data_size: 4,
+			max_code_size: 4,
+		}
+	}
+
+	fn v2_candidate() -> CommittedCandidateReceipt {
+		let mut candidate = CommittedCandidateReceipt {
+			descriptor: dummy_descriptor(),
+			commitments: CandidateCommitments {
+				head_data: HeadData(vec![1, 2]),
+				..Default::default()
+			},
+		};
+		candidate.descriptor.para_head = candidate.commitments.head_data.hash();
+		candidate.descriptor.set_v2_fields(CoreIndex(0), 7);
+		candidate
+	}
+
+	#[test]
+	fn v1_descriptor_has_no_v2_fields() {
+		let descriptor = dummy_descriptor();
+
+		assert_eq!(descriptor.version(), CandidateDescriptorVersion::V1);
+		assert_eq!(descriptor.core_index(), None);
+		assert_eq!(descriptor.session_index(), None);
+	}
+
+	#[test]
+	fn v2_fields_roundtrip() {
+		let mut descriptor = dummy_descriptor();
+		descriptor.set_v2_fields(CoreIndex(300), 7);
+
+		assert_eq!(descriptor.version(), CandidateDescriptorVersion::V2);
+		assert_eq!(descriptor.core_index(), Some(CoreIndex(300)));
+		assert_eq!(descriptor.session_index(), Some(7));
+
+		let decoded = CandidateDescriptor::decode(&mut &descriptor.encode()[..]).unwrap();
+		assert_eq!(decoded.core_index(), Some(CoreIndex(300)));
+	}
+
+	#[test]
+	fn nonzero_reserved_bytes_are_unknown_version() {
+		let mut descriptor = dummy_descriptor();
+		descriptor.set_v2_fields(CoreIndex(0), 0);
+
+		let mut collator = [0u8; 32];
+		collator[31] = 1;
+		descriptor.collator = primitives::sr25519::Public::from_raw(collator).into();
+
+		assert_eq!(descriptor.version(), CandidateDescriptorVersion::Unknown);
+		assert_eq!(descriptor.core_index(), None);
+	}
+
+	#[test]
+	fn candidate_receipt_checks() {
+		let candidate = v2_candidate();
+		let mut params = check_params(&candidate);
+		assert_eq!(check_candidate_receipt(&candidate, &params), Ok(()));
+
+		let check_with = |f: fn(&mut CandidateCheckParams)| {
+			let mut params = params.clone();
+			f(&mut params);
+			check_candidate_receipt(&candidate, &params)
+		};
+		assert_eq!(
+			check_with(|p| p.persisted_validation_data_hash = Hash::repeat_byte(1)),
+			Err(CandidateCheckError::ValidationDataHashMismatch),
+		);
+		assert_eq!(
+			check_with(|p| p.session_index = 8),
+			Err(CandidateCheckError::InvalidSessionIndex),
+		);
+		assert_eq!(
+			check_with(|p| p.validation_code_hash = ValidationCode(vec![4]).hash()),
+			Err(CandidateCheckError::InvalidValidationCodeHash),
+		);
+		assert_eq!(
+			check_with(|p| p.max_head_data_size = 1),
+			Err(CandidateCheckError::HeadDataTooLarge),
+		);
+		// The upgrade restrictions only apply to candidates that upgrade the code.
+		assert_eq!(check_with(|p| p.code_upgrade_allowed = false), Ok(()));
+
+		let mut upgrading = candidate;
+		upgrading.commitments.new_validation_code = Some(ValidationCode(vec![1; 5]));
+		assert_eq!(
+			check_candidate_receipt(&upgrading, &params),
+			Err(CandidateCheckError::NewCodeTooLarge),
+		);
+		params.code_upgrade_allowed = false;
+		assert_eq!(
+			check_candidate_receipt(&upgrading, &params),
+			Err(CandidateCheckError::PrematureCodeUpgrade),
+		);
+	}
+
+	#[test]
+	fn candidate_descriptor_version_checks() {
+		let mut candidate = v2_candidate();
+		candidate.descriptor.para_head = HeadData(vec![3]).hash();
+		let params = check_params(&candidate);
+		assert_eq!(
+			check_candidate_receipt(&candidate, &params),
+			Err(CandidateCheckError::ParaHeadMismatch),
+		);
+
+		// The dummy collator signature does not verify.
+		let mut v1 = candidate;
+		v1.descriptor = dummy_descriptor();
+		assert_eq!(
+			check_candidate_receipt(&v1, &params),
+			Err(CandidateCheckError::NotCollatorSigned),
+		);
+		let params_rejecting_v1 = CandidateCheckParams { v1_descriptors_rejected: true, ..params };
+		assert_eq!(
+			check_candidate_receipt(&v1, &params_rejecting_v1),
+			Err(CandidateCheckError::InvalidDescriptorVersion),
+		);
+	}
+
+	#[test]
+	fn sparse_bitfields_are_compacted() {
+		let mut bits = bitvec::bitvec![u8, bitvec::order::Lsb0; 0; 1000];
+		bits.set(3, true);
+		bits.set(700, true);
+		let bitfield = AvailabilityBitfield(bits);
+
+		let compact = CompactAvailabilityBitfield::from(bitfield.clone());
+		assert!(matches!(compact, CompactAvailabilityBitfield::Sparse { .. }));
+		assert!(compact.is_well_formed());
+		assert!(compact.encoded_size() < bitfield.encoded_size());
+		assert_eq!(AvailabilityBitfield::from(&compact), bitfield);
+		assert_eq!(compact.encode_as(), bitfield.encode());
+	}
+
+	#[test]
+	fn dense_bitfields_stay_full() {
+		let bitfield = AvailabilityBitfield(bitvec::bitvec![u8, bitvec::order::Lsb0; 1; 100]);
+
+		let compact = CompactAvailabilityBitfield::from(bitfield.clone());
+		assert_eq!(compact, CompactAvailabilityBitfield::Full(bitfield.clone()));
+		assert_eq!(AvailabilityBitfield::from(&compact), bitfield);
+	}
+
+	#[test]
+	fn unordered_or_out_of_range_bits_are_malformed() {
+		let unordered =
+			CompactAvailabilityBitfield::Sparse { len: 10, set_bits: vec![Compact(5), Compact(2)] };
+		let out_of_range =
+			CompactAvailabilityBitfield::Sparse { len: 10, set_bits: vec![Compact(10)] };
+
+		assert!(!unordered.is_well_formed());
+		assert!(!out_of_range.is_well_formed());
+	}
+
+	#[test]
+	fn bitfield_signing_parent_only_extends_the_context_with_a_core_layout() {
+		let parent_hash = Hash::repeat_byte(1);
+		let plain = SigningContext { session_index: 2, parent_hash };
+		let unbound = SigningContext {
+			session_index: 2,
+			parent_hash: BitfieldSigningParent { parent_hash, core_layout_hash: None },
+		};
+		assert_eq!(unbound.encode(), plain.encode());
+
+		let layout_hash = core_layout_hash(&[(CoreIndex(0), CandidateHash(Hash::repeat_byte(3)))]);
+		let bound = SigningContext {
+			session_index: 2,
+			parent_hash: BitfieldSigningParent { parent_hash, core_layout_hash: Some(layout_hash) },
+		};
+		assert_eq!(bound.encode(), (plain, layout_hash).encode());
+		assert_eq!(bound.encode().len(), bound.size_hint());
+	}
+
+	#[test]
+	fn api_features_are_derived_from_older_api_versions() {
+		assert_eq!(ApiFeatures::from_api_version(1), ApiFeatures::default());
+		let v3 = ApiFeatures::from_api_version(3);
+		assert!(v3.contains(ApiFeatures::SESSION_INFO.with(ApiFeatures::DISPUTES)));
+		assert!(!v3.contains(ApiFeatures::EXECUTOR_PARAMS));
+		assert!(!ApiFeatures::from_api_version(4).contains(ApiFeatures::ASYNC_BACKING));
+
+		// Unknown bits don't hide the known ones.
+		assert!(ApiFeatures(u64::MAX).contains(ApiFeatures::ASYNC_BACKING));
+	}
+
+	#[test]
+	fn core_bitfield_layout_sets_the_bits_of_available_cores() {
+		let layout = CoreBitfieldLayout::new(4);
+		let bitfield = layout.bitfield([CoreIndex(1), CoreIndex(3), CoreIndex(4)]);
+		assert_eq!(bitfield.0.len(), 4);
+		assert_eq!(bitfield.0.iter_ones().collect::<Vec<_>>(), vec![1, 3]);
+		assert_eq!(
+			bitfield.0.iter_ones().map(|bit| layout.core_of(bit)).collect::<Vec<_>>(),
+			vec![Some(CoreIndex(1)), Some(CoreIndex(3))],
+		);
+	}
+}
@@ -550,8 +550,8 @@ mod tests {
 	use pallet_balances;
 	use primitives::{BlockNumber, Header};
 	use runtime_parachains::{
-		configuration as parachains_configuration, paras as parachains_paras,
-		shared as parachains_shared,
+		configuration as parachains_configuration, origin as parachains_origin,
+		paras as parachains_paras, shared as parachains_shared,
 	};
 	use sp_core::H256;
 	use sp_runtime::{
@@ -574,6 +574,7 @@ mod tests {
 			Configuration: parachains_configuration::{Pallet, Call, Storage, Config<T>},
 			ParasShared: parachains_shared::{Pallet, Call, Storage},
 			Parachains: parachains_paras::{Pallet, Call, Storage, Config, Event},
+			ParachainsOrigin: parachains_origin::{Pallet, Origin},
 			Slots: slots::{Pallet, Call, Storage, Event<T>},
 			AssignedSlots: assigned_slots::{Pallet, Call, Storage, Event<T>},
 		}
@@ -647,12 +648,17 @@ mod tests {
 
 	impl parachains_paras::Config for Test {
 		type RuntimeEvent = RuntimeEvent;
+		type RuntimeOrigin = RuntimeOrigin;
 		type WeightInfo = parachains_paras::TestWeightInfo;
 		type UnsignedPriority = ParasUnsignedPriority;
 		type NextSessionRotation = crate::mock::TestNextSessionRotation;
 	}
 
-	impl parachains_shared::Config for Test {}
+	impl parachains_shared::Config for Test {
+		type DisabledValidators = ();
+	}
+
+	impl parachains_origin::Config for Test {}
 
 	parameter_types! {
 		pub const LeasePeriod: BlockNumber = 3;
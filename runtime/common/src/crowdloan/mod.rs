@@ -52,8 +52,9 @@
 pub mod migration;
 
 use crate::{
+	impls::ReservedParaDeposit,
 	slot_range::SlotRange,
-	traits::{Auctioneer, Registrar},
+	traits::{Auctioneer, ParaDeposit, Registrar},
 };
 use frame_support::{
 	ensure,
@@ -62,7 +63,7 @@ use frame_support::{
 	traits::{
 		Currency,
 		ExistenceRequirement::{self, AllowDeath, KeepAlive},
-		Get, ReservableCurrency,
+		Get,
 	},
 	Identity, PalletId,
 };
@@ -84,6 +85,7 @@ type LeasePeriodOf<T> = <<T as Config>::Auctioneer as Auctioneer<
 	<T as frame_system::Config>::BlockNumber,
 >>::LeasePeriod;
 type BalanceOf<T> = <CurrencyOf<T> as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+type DepositOf<T> = ReservedParaDeposit<CurrencyOf<T>>;
 
 #[allow(dead_code)]
 type NegativeImbalanceOf<T> =
@@ -420,7 +422,7 @@ pub mod pallet {
 			let deposit = T::SubmissionDeposit::get();
 
 			frame_system::Pallet::<T>::inc_providers(&Self::fund_account_id(fund_index));
-			CurrencyOf::<T>::reserve(&depositor, deposit)?;
+			DepositOf::<T>::reserve(&depositor, deposit)?;
 
 			Funds::<T>::insert(
 				index,
@@ -577,7 +579,7 @@ pub mod pallet {
 			debug_assert!(Self::contribution_iterator(fund.fund_index).count().is_zero());
 
 			frame_system::Pallet::<T>::dec_providers(&Self::fund_account_id(fund.fund_index))?;
-			CurrencyOf::<T>::unreserve(&fund.depositor, fund.deposit);
+			DepositOf::<T>::refund(&fund.depositor, fund.deposit);
 			Funds::<T>::remove(index);
 			Self::deposit_event(Event::<T>::Dissolved { para_id: index });
 			Ok(())
@@ -863,7 +865,7 @@ mod tests {
 
 	use frame_support::{
 		assert_noop, assert_ok, parameter_types,
-		traits::{ConstU32, OnFinalize, OnInitialize},
+		traits::{ConstU32, OnFinalize, OnInitialize, ReservableCurrency},
 	};
 	use primitives::Id as ParaId;
 	use sp_core::H256;
@@ -16,8 +16,11 @@
 
 //! Auxiliary `struct`/`enum`s for polkadot runtime.
 
-use crate::NegativeImbalance;
-use frame_support::traits::{Currency, Imbalance, OnUnbalanced};
+use crate::{traits::ParaDeposit, NegativeImbalance};
+use frame_support::{
+	dispatch::DispatchResult,
+	traits::{Currency, Imbalance, OnUnbalanced, ReservableCurrency},
+};
 use primitives::Balance;
 use sp_runtime::Perquintill;
 
@@ -59,6 +62,31 @@ where
 	}
 }
 
+/// A [`ParaDeposit`] holding deposits as reserved balance of `C`, and passing slashed deposits
+/// to `S`.
+pub struct ReservedParaDeposit<C, S = ()>(sp_std::marker::PhantomData<(C, S)>);
+impl<AccountId, C, S> ParaDeposit<AccountId> for ReservedParaDeposit<C, S>
+where
+	C: ReservableCurrency<AccountId>,
+	S: OnUnbalanced<C::NegativeImbalance>,
+{
+	type Balance = C::Balance;
+
+	fn reserve(who: &AccountId, amount: Self::Balance) -> DispatchResult {
+		C::reserve(who, amount)
+	}
+
+	fn refund(who: &AccountId, amount: Self::Balance) -> Self::Balance {
+		C::unreserve(who, amount)
+	}
+
+	fn slash(who: &AccountId, amount: Self::Balance) -> Self::Balance {
+		let (slashed, remaining) = C::slash_reserved(who, amount);
+		S::on_unbalanced(slashed);
+		remaining
+	}
+}
+
 pub fn era_payout(
 	total_staked: Balance,
 	total_stakable: Balance,
@@ -257,6 +285,26 @@ mod tests {
 		});
 	}
 
+	#[test]
+	fn reserved_para_deposits_are_refunded_and_slashed() {
+		type Deposit = ReservedParaDeposit<Balances, Treasury>;
+
+		new_test_ext().execute_with(|| {
+			let _ = Balances::deposit_creating(&TEST_ACCOUNT, 100);
+
+			assert!(Deposit::reserve(&TEST_ACCOUNT, 200).is_err());
+			assert_eq!(Deposit::reserve(&TEST_ACCOUNT, 50), Ok(()));
+			assert_eq!(Balances::reserved_balance(TEST_ACCOUNT), 50);
+
+			assert_eq!(Deposit::slash(&TEST_ACCOUNT, 20), 0);
+			assert_eq!(Balances::free_balance(Treasury::account_id()), 20);
+
+			assert_eq!(Deposit::refund(&TEST_ACCOUNT, 30), 0);
+			assert_eq!(Balances::reserved_balance(TEST_ACCOUNT), 0);
+			assert_eq!(Balances::free_balance(TEST_ACCOUNT), 80);
+		});
+	}
+
 	#[test]
 	fn compute_inflation_should_give_sensible_results() {
 		assert_eq!(
@@ -32,7 +32,10 @@ use runtime_parachains::{
 };
 use sp_std::{prelude::*, result};
 
-use crate::traits::{OnSwap, Registrar};
+use crate::{
+	impls::ReservedParaDeposit,
+	traits::{OnSwap, ParaDeposit, Registrar},
+};
 pub use pallet::*;
 use parity_scale_codec::{Decode, Encode};
 use runtime_parachains::paras::ParaKind;
@@ -54,6 +57,7 @@ pub struct ParaInfo<Account, Balance> {
 
 type BalanceOf<T> =
 	<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+type DepositOf<T> = ReservedParaDeposit<<T as Config>::Currency>;
 
 pub trait WeightInfo {
 	fn reserve() -> Weight;
@@ -138,6 +142,7 @@ pub mod pallet {
 		Registered { para_id: ParaId, manager: T::AccountId },
 		Deregistered { para_id: ParaId },
 		Reserved { para_id: ParaId, who: T::AccountId },
+		Swapped { para_id: ParaId, other_id: ParaId },
 	}
 
 	#[pallet::error]
@@ -320,6 +325,7 @@ pub mod pallet {
 					return Err(Error::<T>::CannotSwap.into())
 				}
 				PendingSwap::<T>::remove(other);
+				Self::deposit_event(Event::<T>::Swapped { para_id: id, other_id: other });
 			} else {
 				PendingSwap::<T>::insert(id, other);
 			}
@@ -551,7 +557,7 @@ impl<T: Config> Pallet<T> {
 		ensure!(paras::Pallet::<T>::lifecycle(id).is_none(), Error::<T>::AlreadyRegistered);
 
 		let deposit = deposit_override.unwrap_or_else(T::ParaDeposit::get);
-		<T as Config>::Currency::reserve(&who, deposit)?;
+		DepositOf::<T>::reserve(&who, deposit)?;
 		let info = ParaInfo { manager: who.clone(), deposit, locked: false };
 
 		Paras::<T>::insert(id, info);
@@ -583,9 +589,9 @@ impl<T: Config> Pallet<T> {
 		let deposit = deposit_override.unwrap_or(deposit);
 
 		if let Some(additional) = deposit.checked_sub(&deposited) {
-			<T as Config>::Currency::reserve(&who, additional)?;
+			DepositOf::<T>::reserve(&who, additional)?;
 		} else if let Some(rebate) = deposited.checked_sub(&deposit) {
-			<T as Config>::Currency::unreserve(&who, rebate);
+			DepositOf::<T>::refund(&who, rebate);
 		};
 		let info = ParaInfo { manager: who.clone(), deposit, locked: false };
 
@@ -608,7 +614,7 @@ impl<T: Config> Pallet<T> {
 			.map_err(|_| Error::<T>::CannotDeregister)?;
 
 		if let Some(info) = Paras::<T>::take(&id) {
-			<T as Config>::Currency::unreserve(&info.manager, info.deposit);
+			DepositOf::<T>::refund(&info.manager, info.deposit);
 		}
 
 		PendingSwap::<T>::remove(id);
@@ -756,7 +762,9 @@ mod tests {
 		type MaxFreezes = ConstU32<1>;
 	}
 
-	impl shared::Config for Test {}
+	impl shared::Config for Test {
+		type DisabledValidators = ();
+	}
 
 	impl origin::Config for Test {}
 
@@ -766,6 +774,7 @@ mod tests {
 
 	impl paras::Config for Test {
 		type RuntimeEvent = RuntimeEvent;
+		type RuntimeOrigin = RuntimeOrigin;
 		type WeightInfo = paras::TestWeightInfo;
 		type UnsignedPriority = ParasUnsignedPriority;
 		type NextSessionRotation = crate::mock::TestNextSessionRotation;
@@ -1122,6 +1131,10 @@ mod tests {
 			// Both paras initiate a swap
 			assert_ok!(Registrar::swap(para_origin(para_1), para_1, para_2,));
 			assert_ok!(Registrar::swap(para_origin(para_2), para_2, para_1,));
+			System::assert_last_event(RuntimeEvent::Registrar(paras_registrar::Event::Swapped {
+				para_id: para_2,
+				other_id: para_1,
+			}));
 
 			run_to_session(6);
 
@@ -1401,6 +1414,7 @@ mod benchmarking {
 			Registrar::<T>::swap(parachain_origin.into(), parachain, parathread)?;
 		}: _(RawOrigin::Signed(caller.clone()), parathread, parachain)
 		verify {
+			assert_last_event::<T>(Event::<T>::Swapped { para_id: parathread, other_id: parachain }.into());
 			next_scheduled_session::<T>();
 			// Swapped!
 			assert_eq!(paras::Pallet::<T>::lifecycle(parachain), Some(ParaLifecycle::Parathread));
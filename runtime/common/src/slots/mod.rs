@@ -23,7 +23,10 @@
 
 pub mod migration;
 
-use crate::traits::{LeaseError, Leaser, Registrar};
+use crate::{
+	impls::ReservedParaDeposit,
+	traits::{LeaseError, Leaser, ParaDeposit, Registrar},
+};
 use frame_support::{
 	pallet_prelude::*,
 	traits::{Currency, ReservableCurrency},
@@ -38,6 +41,7 @@ use sp_std::prelude::*;
 type BalanceOf<T> =
 	<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
 type LeasePeriodOf<T> = <T as frame_system::Config>::BlockNumber;
+type DepositOf<T> = ReservedParaDeposit<<T as Config>::Currency>;
 
 pub trait WeightInfo {
 	fn force_lease() -> Weight;
@@ -133,6 +137,8 @@ pub mod pallet {
 			extra_reserved: BalanceOf<T>,
 			total_amount: BalanceOf<T>,
 		},
+		/// The leases of a para ran out, so it was downgraded to a parathread.
+		LeaseEnded { para_id: ParaId },
 	}
 
 	#[pallet::error]
@@ -191,7 +197,7 @@ pub mod pallet {
 
 			// Refund any deposits for these leases
 			for (who, deposit) in deposits {
-				let err_amount = T::Currency::unreserve(&who, deposit);
+				let err_amount = DepositOf::<T>::refund(&who, deposit);
 				debug_assert!(err_amount.is_zero());
 			}
 
@@ -248,7 +254,7 @@ impl<T: Config> Pallet<T> {
 				//
 				// Unreserve whatever is left.
 				if let Some((who, value)) = &lease_periods[0] {
-					T::Currency::unreserve(&who, *value);
+					DepositOf::<T>::refund(&who, *value);
 				}
 
 				// Remove the now-empty lease list.
@@ -271,7 +277,7 @@ impl<T: Config> Pallet<T> {
 					// If this is less than what we were holding for this leaser's now-ended lease, then
 					// unreserve it.
 					if let Some(rebate) = ended_lease.1.checked_sub(&now_held) {
-						T::Currency::unreserve(&ended_lease.0, rebate);
+						DepositOf::<T>::refund(&ended_lease.0, rebate);
 					}
 				}
 
@@ -296,6 +302,7 @@ impl<T: Config> Pallet<T> {
 				// outgoing.
 				let res = T::Registrar::make_parathread(*para);
 				debug_assert!(res.is_ok());
+				Self::deposit_event(Event::<T>::LeaseEnded { para_id: *para });
 			}
 		}
 
@@ -396,7 +403,7 @@ impl<T: Config> Leaser<T::BlockNumber> for Pallet<T> {
 			//  If so, then we can deduct those from the amount that we need to reserve.
 			let maybe_additional = amount.checked_sub(&Self::deposit_held(para, &leaser));
 			if let Some(ref additional) = maybe_additional {
-				T::Currency::reserve(&leaser, *additional)
+				DepositOf::<T>::reserve(&leaser, *additional)
 					.map_err(|_| LeaseError::ReserveFailed)?;
 			}
 
@@ -652,6 +659,8 @@ mod tests {
 			run_to_block(20);
 			assert_eq!(Slots::deposit_held(1.into(), &1), 0);
 			assert_eq!(Balances::reserved_balance(1), 0);
+			assert!(System::events().iter().any(|record| record.event ==
+				RuntimeEvent::Slots(Event::LeaseEnded { para_id: 1.into() })));
 
 			assert_eq!(
 				TestRegistrar::<Test>::operations(),
@@ -258,3 +258,19 @@ pub trait OnSwap {
 	/// such as leases, deposits held and thread/chain nature are swapped.
 	fn on_swap(one: ParaId, other: ParaId);
 }
+
+/// A handler for the deposits held for paras, so that the registrar, lease and crowdloan modules
+/// reserve, refund and slash them in the same way.
+pub trait ParaDeposit<AccountId> {
+	/// The balance of the deposits.
+	type Balance;
+
+	/// Hold `amount` of the funds of `who` as a deposit.
+	fn reserve(who: &AccountId, amount: Self::Balance) -> DispatchResult;
+
+	/// Return `amount` of the deposit of `who`, and return the amount which could not be returned.
+	fn refund(who: &AccountId, amount: Self::Balance) -> Self::Balance;
+
+	/// Slash `amount` of the deposit of `who`, and return the amount which could not be slashed.
+	fn slash(who: &AccountId, amount: Self::Balance) -> Self::Balance;
+}
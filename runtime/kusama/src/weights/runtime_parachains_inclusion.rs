@@ -0,0 +1,56 @@
+// Copyright (C) Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+//! Weights for `runtime_parachains::inclusion`
+//!
+//! The inclusion module is not benchmarked for this runtime yet, so its calls charge a full block,
+//! which keeps them out of it, and the weights used by the paras inherent charge nothing, as before
+//! the inherent used them. This file must be replaced by the output of the benchmark CLI before
+//! they are enabled:
+//!
+//! ./target/production/polkadot benchmark pallet --chain=kusama-dev --steps=50 --repeat=20
+//!     --pallet=runtime_parachains::inclusion --extrinsic=* --execution=wasm
+//!     --wasm-execution=compiled --header=./file_header.txt
+//!     --output=./runtime/kusama/src/weights/runtime_parachains_inclusion.rs
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::Weight};
+use sp_std::marker::PhantomData;
+
+/// Weight functions for `runtime_parachains::inclusion`.
+pub struct WeightInfo<T>(PhantomData<T>);
+impl<T: frame_system::Config> runtime_parachains::inclusion::WeightInfo for WeightInfo<T> {
+	/// Not benchmarked for this runtime: only its proof size is used, which is not limited on the
+	/// relay chain.
+	fn process_bitfields(_c: u32, ) -> Weight {
+		Weight::zero()
+	}
+	/// Not benchmarked for this runtime: only its proof size is used, which is not limited on the
+	/// relay chain.
+	fn process_candidates(_c: u32, ) -> Weight {
+		Weight::zero()
+	}
+	/// Not benchmarked for this runtime: charges a full block, so that the call can't be included.
+	fn force_clear_pending() -> Weight {
+		T::BlockWeights::get().max_block
+	}
+	/// Not benchmarked for this runtime: charges a full block, so that the call can't be included.
+	fn disallow_candidate() -> Weight {
+		T::BlockWeights::get().max_block
+	}
+}
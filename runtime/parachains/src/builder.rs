@@ -22,6 +22,7 @@ use crate::{
 };
 use bitvec::{order::Lsb0 as BitOrderLsb0, vec::BitVec};
 use frame_support::pallet_prelude::*;
+use primitives::vstaging::CoreAssignmentSource;
 use primitives::{
 	collator_signature_payload, AvailabilityBitfield, BackedCandidate, CandidateCommitments,
 	CandidateDescriptor, CandidateHash, CollatorId, CollatorSignature, CommittedCandidateReceipt,
@@ -260,8 +261,8 @@ impl<T: paras_inherent::Config> BenchBuilder<T> {
 		core_idx: CoreIndex,
 		candidate_hash: CandidateHash,
 		availability_votes: BitVec<u8, BitOrderLsb0>,
-	) -> inclusion::CandidatePendingAvailability<T::Hash, T::BlockNumber> {
-		inclusion::CandidatePendingAvailability::<T::Hash, T::BlockNumber>::new(
+	) -> inclusion::CandidatePendingAvailability<T::Hash, T::BlockNumber, T::MaxValidators> {
+		inclusion::CandidatePendingAvailability::new(
 			core_idx,                          // core
 			candidate_hash,                    // hash
 			Self::candidate_descriptor_mock(), // candidate descriptor
@@ -270,6 +271,7 @@ impl<T: paras_inherent::Config> BenchBuilder<T> {
 			Zero::zero(),                      // relay parent
 			One::one(),                        // relay chain block this was backed in
 			group_idx,                         // backing group
+			CoreAssignmentSource::Lease,       // assignment source
 		)
 	}
 
@@ -299,8 +301,8 @@ impl<T: paras_inherent::Config> BenchBuilder<T> {
 			processed_downward_messages: 0,
 			hrmp_watermark: 0u32.into(),
 		};
-		inclusion::PendingAvailability::<T>::insert(para_id, candidate_availability);
-		inclusion::PendingAvailabilityCommitments::<T>::insert(&para_id, commitments);
+		inclusion::PendingAvailability::<T>::insert(para_id, core_idx, candidate_availability);
+		inclusion::PendingAvailabilityCommitments::<T>::insert(&para_id, core_idx, commitments);
 	}
 
 	/// Create an `AvailabilityBitfield` where `concluding` is a map where each key is a core index
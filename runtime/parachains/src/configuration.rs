@@ -24,10 +24,13 @@ use frame_system::pallet_prelude::*;
 use parity_scale_codec::{Decode, Encode};
 use polkadot_parachain::primitives::{MAX_HORIZONTAL_MESSAGE_NUM, MAX_UPWARD_MESSAGE_NUM};
 use primitives::{
-	vstaging::AsyncBackingParams, Balance, ExecutorParams, SessionIndex, MAX_CODE_SIZE,
-	MAX_HEAD_DATA_SIZE, MAX_POV_SIZE,
+	vstaging::AsyncBackingParams, AbridgedHostConfiguration, Balance, CoreIndex, ExecutorParams,
+	Id as ParaId, SessionIndex, MAX_CODE_SIZE, MAX_HEAD_DATA_SIZE, MAX_POV_SIZE,
+};
+use sp_runtime::{
+	traits::{UniqueSaturatedInto, Zero},
+	Perbill,
 };
-use sp_runtime::traits::Zero;
 use sp_std::prelude::*;
 
 #[cfg(test)]
@@ -42,6 +45,10 @@ pub mod migration;
 
 const LOG_TARGET: &str = "runtime::configuration";
 
+/// The number of backing statements that was required to back a candidate before
+/// `minimum_backing_votes` became configurable.
+pub const LEGACY_MIN_BACKING_VOTES: u32 = 2;
+
 /// All configuration of the runtime with respect to parachains and parathreads.
 #[derive(Clone, Encode, Decode, PartialEq, sp_core::RuntimeDebug, scale_info::TypeInfo)]
 #[cfg_attr(feature = "std", derive(serde::Serialize, serde::Deserialize))]
@@ -245,6 +252,142 @@ pub struct HostConfiguration<BlockNumber> {
 	/// This value should be greater than [`chain_availability_period`] and
 	/// [`thread_availability_period`].
 	pub minimum_validation_upgrade_delay: BlockNumber,
+	/// The deposit a collator must reserve to place a parathread claim. It is returned once the claim
+	/// leaves the queue.
+	pub parathread_claim_deposit: Balance,
+	/// How many relay chain blocks worth of included candidates are kept on-chain for lookup.
+	///
+	/// Zero disables recording included candidates.
+	pub included_candidates_retention: BlockNumber,
+	/// The fraction of validators, rounded up, that must signal availability of a candidate before
+	/// it is included.
+	///
+	/// The default is slightly above two thirds, which yields a supermajority for any realistic
+	/// validator set size.
+	pub availability_threshold: Perbill,
+	/// The minimum number of validators that must signal availability of a candidate, regardless of
+	/// `availability_threshold`. Capped at the number of validators.
+	pub minimum_availability_votes: u32,
+	/// How many blocks before the parent block a candidate's relay parent may be.
+	///
+	/// Zero means candidates must be built on top of the parent block. Must not exceed
+	/// [`shared::MAX_CANDIDATE_RELAY_PARENT_DEPTH`].
+	pub max_candidate_relay_parent_depth: u32,
+	/// Whether candidates must use v2 descriptors, which commit to the core index and session index.
+	///
+	/// v2 descriptors are checked whenever they are used. Until this is enabled, v1 descriptors
+	/// are accepted as well.
+	pub v1_candidate_descriptors_rejected: bool,
+	/// Whether block authors may submit availability bitfields in their compact encoding.
+	pub compact_availability_bitfields: bool,
+	/// How many blocks an availability vote for a candidate pending availability stays valid.
+	///
+	/// A vote only counts while the validator's latest bitfield, submitted within this many blocks,
+	/// still confirms the candidate's core. Zero disables expiry, so votes count until the candidate
+	/// is included or times out.
+	pub availability_vote_ttl: BlockNumber,
+	/// How many blocks the past head-data of paras is kept on-chain for lookup.
+	///
+	/// Zero disables keeping past head-data.
+	pub head_data_retention_period: BlockNumber,
+	/// The availability cores each listed para may be scheduled on, sorted by para id.
+	///
+	/// Paras which are not listed may be scheduled on any core.
+	pub core_affinity: Vec<(ParaId, Vec<CoreIndex>)>,
+	/// The amount added to the effective bid of a queued parathread claim for every block it has been
+	/// waiting, so that low bids are eventually scheduled.
+	pub parathread_bid_aging_bonus: Balance,
+	/// The minimum number of valid backing statements required to consider a parachain candidate backed.
+	///
+	/// Groups smaller than this need a statement from every member.
+	pub minimum_backing_votes: u32,
+	/// Whether candidates pending availability at the end of a session are carried over into the next
+	/// one instead of being dropped.
+	///
+	/// This only happens if every validator of the ending session is still active, so that their
+	/// availability votes can be remapped to the new validator indices.
+	pub carry_over_pending_availability: bool,
+	/// The maximum weight spent on enacting available candidates in a single block, counting every
+	/// candidate at the upper bound of its enactment weight. Available candidates beyond it keep their
+	/// core and are enacted in the following blocks.
+	///
+	/// At least one candidate is enacted per block. Zero disables the limit.
+	pub max_enactment_weight_per_block: Weight,
+	/// Whether the head data and validation code of candidates pending availability are stored once by
+	/// hash, apart from the rest of their commitments, and put back together at enactment.
+	///
+	/// This keeps the commitments read on every block small and shares identical blobs between
+	/// candidates.
+	pub pending_commitments_by_reference: bool,
+	/// The deposit that the sender should provide for each message of capacity of an HRMP channel, on
+	/// top of `hrmp_sender_deposit`.
+	pub hrmp_sender_deposit_per_message: Balance,
+	/// The deposit that the sender should provide for each byte an HRMP channel can hold, i.e. for
+	/// each byte of `max_capacity * max_message_size`, on top of `hrmp_sender_deposit`.
+	pub hrmp_sender_deposit_per_byte: Balance,
+	/// The fraction of the sender's deposit that is slashed when an HRMP channel is force-closed by
+	/// Root.
+	pub hrmp_force_close_slash: Perbill,
+	/// The maximum amount of weight the upward messages of a single para may use for dispatch in a
+	/// block, so that one para can't starve the others. Messages that require more than this are
+	/// stashed as overweight. Zero means no limit other than `ump_service_total_weight`.
+	pub ump_max_weight_per_para: Weight,
+	/// The fraction of the claim deposit that is slashed when a parathread claim runs out of retries
+	/// without its candidate being included.
+	pub parathread_claim_slash: Perbill,
+	/// Overrides of select configuration fields for the listed paras, sorted by para id.
+	///
+	/// Paras which are not listed use the global values.
+	pub para_config_overrides: Vec<(ParaId, ParaConfigOverrides<BlockNumber>)>,
+	/// The number of blocks after the block at which an accepted code upgrade is expected, by which
+	/// the para has to get a candidate included that applies it. The upgrade is aborted otherwise,
+	/// and the para may schedule a new one.
+	///
+	/// Zero disables expiry.
+	pub validation_upgrade_expiry: BlockNumber,
+	/// The number of consecutive sessions a validator has to be an availability no-show, i.e. not
+	/// submit a single availability bitfield while cores are occupied, before it is slashed for it.
+	/// Until then its no-shows are reported as non-slashing offences.
+	///
+	/// Zero disables slashing for availability no-shows.
+	pub availability_no_show_slash_threshold: u32,
+	/// The spot price of an on-demand order while the parathread claim queue is empty.
+	pub on_demand_base_fee: Balance,
+	/// The maximum number of candidates that may be backed in a single relay chain block, if any.
+	pub max_candidates_per_block: Option<u32>,
+	/// Whether availability bitfields are signed over the layout of the occupied cores, on top of
+	/// the session and the parent hash, so that they cannot be counted towards other candidates.
+	///
+	/// Validators learn the layout through the `bitfield_core_layout_hash` runtime API, which
+	/// nodes use from v0.9.41 on. Older nodes keep signing bitfields without it, and these are
+	/// rejected, so this may only be enabled once all validators run v0.9.41 or later.
+	pub core_layout_bound_bitfields: bool,
+	/// Whether a para may have candidates pending availability on several cores at once, one per
+	/// core it is scheduled on, and built on different relay parents within
+	/// `max_candidate_relay_parent_depth`. They are queued by relay parent, oldest first. This is
+	/// groundwork: the candidates are not chained onto each other.
+	pub multi_core_paras: bool,
+	/// The paras that are notified of the hash of the validator set of each new session, sorted by
+	/// para id.
+	///
+	/// The hash is kept by the session info module under a storage key that is included in the
+	/// relay chain state proof of these paras.
+	pub validator_set_hash_subscribers: Vec<ParaId>,
+	/// The amount added to the effective bid of the queued claims of a parathread for every block
+	/// it has been waiting for one of its candidates to be included, across claims that timed out
+	/// or expired, so that parathreads losing the race for the parathread cores eventually get
+	/// priority.
+	pub parathread_starvation_bonus: Balance,
+	/// How many recent relay chain blocks' storage roots are kept on-chain, for relay-state proofs
+	/// against blocks older than the parent.
+	///
+	/// Zero disables recording storage roots.
+	pub relay_storage_roots_retention: BlockNumber,
+	/// The number of blocks after a session change during which availability bitfields signed in
+	/// the previous session are still accepted from validators that remain active.
+	///
+	/// Zero only accepts bitfields signed in the current session.
+	pub bitfield_grace_blocks: BlockNumber,
 }
 
 impl<BlockNumber: Default + From<u32>> Default for HostConfiguration<BlockNumber> {
@@ -299,10 +442,58 @@ impl<BlockNumber: Default + From<u32>> Default for HostConfiguration<BlockNumber
 			pvf_voting_ttl: 2u32.into(),
 			minimum_validation_upgrade_delay: 2.into(),
 			executor_params: Default::default(),
+			parathread_claim_deposit: Default::default(),
+			included_candidates_retention: Default::default(),
+			availability_threshold: Perbill::from_parts(666_666_667),
+			minimum_availability_votes: Default::default(),
+			max_candidate_relay_parent_depth: Default::default(),
+			v1_candidate_descriptors_rejected: false,
+			compact_availability_bitfields: false,
+			availability_vote_ttl: Default::default(),
+			head_data_retention_period: Default::default(),
+			core_affinity: Vec::new(),
+			parathread_bid_aging_bonus: Default::default(),
+			minimum_backing_votes: LEGACY_MIN_BACKING_VOTES,
+			carry_over_pending_availability: false,
+			max_enactment_weight_per_block: Weight::zero(),
+			pending_commitments_by_reference: false,
+			hrmp_sender_deposit_per_message: Default::default(),
+			hrmp_sender_deposit_per_byte: Default::default(),
+			hrmp_force_close_slash: Perbill::zero(),
+			ump_max_weight_per_para: Weight::zero(),
+			parathread_claim_slash: Perbill::zero(),
+			para_config_overrides: Vec::new(),
+			validation_upgrade_expiry: Default::default(),
+			availability_no_show_slash_threshold: 0,
+			on_demand_base_fee: Default::default(),
+			max_candidates_per_block: None,
+			core_layout_bound_bitfields: false,
+			multi_core_paras: false,
+			validator_set_hash_subscribers: Vec::new(),
+			parathread_starvation_bonus: Default::default(),
+			relay_storage_roots_retention: Default::default(),
+			bitfield_grace_blocks: Default::default(),
 		}
 	}
 }
 
+/// Values which take precedence over the global ones of [`HostConfiguration`] for a single para.
+///
+/// Fields set to `None` fall back to the global value.
+#[derive(
+	Clone, Default, Encode, Decode, PartialEq, sp_core::RuntimeDebug, scale_info::TypeInfo,
+)]
+#[cfg_attr(feature = "std", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParaConfigOverrides<BlockNumber> {
+	/// Overrides `max_head_data_size`.
+	pub max_head_data_size: Option<u32>,
+	/// Overrides `validation_upgrade_cooldown`.
+	pub validation_upgrade_cooldown: Option<BlockNumber>,
+	/// Overrides `chain_availability_period` or `thread_availability_period`, depending on whether
+	/// the para is scheduled as a parachain or as a parathread.
+	pub availability_period: Option<BlockNumber>,
+}
+
 /// Enumerates the possible inconsistencies of `HostConfiguration`.
 #[derive(Debug)]
 pub enum InconsistentError<BlockNumber> {
@@ -314,12 +505,19 @@ pub enum InconsistentError<BlockNumber> {
 	ZeroThreadAvailabilityPeriod,
 	/// `no_show_slots` is set to zero.
 	ZeroNoShowSlots,
+	/// `max_validators_per_core` is set to `Some(0)`.
+	ZeroMaxValidatorsPerCore,
+	/// `dispute_period` is set to zero.
+	ZeroDisputePeriod,
 	/// `max_code_size` exceeds the hard limit of `MAX_CODE_SIZE`.
 	MaxCodeSizeExceedHardLimit { max_code_size: u32 },
 	/// `max_head_data_size` exceeds the hard limit of `MAX_HEAD_DATA_SIZE`.
 	MaxHeadDataSizeExceedHardLimit { max_head_data_size: u32 },
 	/// `max_pov_size` exceeds the hard limit of `MAX_POV_SIZE`.
 	MaxPovSizeExceedHardLimit { max_pov_size: u32 },
+	/// `max_candidate_relay_parent_depth` exceeds the hard limit of
+	/// [`shared::MAX_CANDIDATE_RELAY_PARENT_DEPTH`].
+	MaxCandidateRelayParentDepthExceedHardLimit { max_candidate_relay_parent_depth: u32 },
 	/// `minimum_validation_upgrade_delay` is less than `chain_availability_period`.
 	MinimumValidationUpgradeDelayLessThanChainAvailabilityPeriod {
 		minimum_validation_upgrade_delay: BlockNumber,
@@ -342,6 +540,28 @@ pub enum InconsistentError<BlockNumber> {
 	MaxHrmpOutboundChannelsExceeded,
 	/// Maximum number of HRMP inbound channels exceeded.
 	MaxHrmpInboundChannelsExceeded,
+	/// `availability_threshold` is too low for candidates to be recoverable.
+	AvailabilityThresholdTooLow { availability_threshold: Perbill },
+	/// `core_affinity` is not sorted by para id or lists a para more than once.
+	UnsortedCoreAffinity,
+	/// `validator_set_hash_subscribers` is not sorted by para id or lists a para more than once.
+	UnsortedValidatorSetHashSubscribers,
+	/// `minimum_backing_votes` is set to zero.
+	ZeroMinimumBackingVotes,
+	/// `minimum_backing_votes` exceeds `max_validators_per_core`, so no group could ever reach it.
+	MinimumBackingVotesUnachievable { minimum_backing_votes: u32, max_validators_per_core: u32 },
+	/// `para_config_overrides` is not sorted by para id or lists a para more than once.
+	UnsortedParaConfigOverrides,
+	/// The overridden `max_head_data_size` of a para exceeds the hard limit of `MAX_HEAD_DATA_SIZE`.
+	ParaMaxHeadDataSizeExceedHardLimit { para_id: ParaId, max_head_data_size: u32 },
+	/// The overridden availability period of a para is zero.
+	ZeroParaAvailabilityPeriod { para_id: ParaId },
+	/// `minimum_validation_upgrade_delay` is less than the overridden availability period of a para.
+	MinimumValidationUpgradeDelayLessThanParaAvailabilityPeriod {
+		para_id: ParaId,
+		minimum_validation_upgrade_delay: BlockNumber,
+		availability_period: BlockNumber,
+	},
 }
 
 impl<BlockNumber> HostConfiguration<BlockNumber>
@@ -372,6 +592,18 @@ where
 			return Err(ZeroNoShowSlots)
 		}
 
+		// A limit of zero validators per core would otherwise be silently ignored by the
+		// scheduler.
+		if self.max_validators_per_core == Some(0) {
+			return Err(ZeroMaxValidatorsPerCore)
+		}
+
+		// Candidates included in the last blocks of a session could not be disputed at all once
+		// the session changes.
+		if self.dispute_period.is_zero() {
+			return Err(ZeroDisputePeriod)
+		}
+
 		if self.max_code_size > MAX_CODE_SIZE {
 			return Err(MaxCodeSizeExceedHardLimit { max_code_size: self.max_code_size })
 		}
@@ -386,6 +618,12 @@ where
 			return Err(MaxPovSizeExceedHardLimit { max_pov_size: self.max_pov_size })
 		}
 
+		if self.max_candidate_relay_parent_depth > shared::MAX_CANDIDATE_RELAY_PARENT_DEPTH {
+			return Err(MaxCandidateRelayParentDepthExceedHardLimit {
+				max_candidate_relay_parent_depth: self.max_candidate_relay_parent_depth,
+			})
+		}
+
 		if self.minimum_validation_upgrade_delay <= self.chain_availability_period {
 			return Err(MinimumValidationUpgradeDelayLessThanChainAvailabilityPeriod {
 				minimum_validation_upgrade_delay: self.minimum_validation_upgrade_delay.clone(),
@@ -431,6 +669,66 @@ where
 			return Err(MaxHrmpInboundChannelsExceeded)
 		}
 
+		// Erasure coding needs a third of the chunks to recover a candidate, so signalling
+		// availability with fewer than that gives no guarantee at all.
+		if self.availability_threshold < Perbill::from_rational(1u32, 3u32) {
+			return Err(AvailabilityThresholdTooLow {
+				availability_threshold: self.availability_threshold,
+			})
+		}
+
+		if !self.core_affinity.windows(2).all(|pair| pair[0].0 < pair[1].0) {
+			return Err(UnsortedCoreAffinity)
+		}
+
+		if !self.validator_set_hash_subscribers.windows(2).all(|pair| pair[0] < pair[1]) {
+			return Err(UnsortedValidatorSetHashSubscribers)
+		}
+
+		if self.minimum_backing_votes.is_zero() {
+			return Err(ZeroMinimumBackingVotes)
+		}
+
+		if let Some(max_validators_per_core) = self.max_validators_per_core {
+			if self.minimum_backing_votes > max_validators_per_core {
+				return Err(MinimumBackingVotesUnachievable {
+					minimum_backing_votes: self.minimum_backing_votes,
+					max_validators_per_core,
+				})
+			}
+		}
+
+		if !self.para_config_overrides.windows(2).all(|pair| pair[0].0 < pair[1].0) {
+			return Err(UnsortedParaConfigOverrides)
+		}
+
+		for (para_id, overrides) in &self.para_config_overrides {
+			if let Some(max_head_data_size) = overrides.max_head_data_size {
+				if max_head_data_size > MAX_HEAD_DATA_SIZE {
+					return Err(ParaMaxHeadDataSizeExceedHardLimit {
+						para_id: *para_id,
+						max_head_data_size,
+					})
+				}
+			}
+
+			if let Some(availability_period) = &overrides.availability_period {
+				if availability_period.is_zero() {
+					return Err(ZeroParaAvailabilityPeriod { para_id: *para_id })
+				}
+
+				if self.minimum_validation_upgrade_delay <= *availability_period {
+					return Err(MinimumValidationUpgradeDelayLessThanParaAvailabilityPeriod {
+						para_id: *para_id,
+						minimum_validation_upgrade_delay: self
+							.minimum_validation_upgrade_delay
+							.clone(),
+						availability_period: availability_period.clone(),
+					})
+				}
+			}
+		}
+
 		Ok(())
 	}
 
@@ -446,6 +744,87 @@ where
 	}
 }
 
+impl<BlockNumber> HostConfiguration<BlockNumber> {
+	/// Whether the given para may be scheduled on the given availability core.
+	pub fn core_affinity_allows(&self, para_id: ParaId, core: CoreIndex) -> bool {
+		self.core_affinity
+			.binary_search_by_key(&para_id, |(id, _)| *id)
+			.map_or(true, |i| self.core_affinity[i].1.contains(&core))
+	}
+
+	/// The overrides of the given para, if it has any.
+	fn para_config_overrides_of(
+		&self,
+		para_id: ParaId,
+	) -> Option<&ParaConfigOverrides<BlockNumber>> {
+		self.para_config_overrides
+			.binary_search_by_key(&para_id, |(id, _)| *id)
+			.ok()
+			.map(|i| &self.para_config_overrides[i].1)
+	}
+
+	/// The maximum head data size of the given para.
+	pub fn max_head_data_size_of(&self, para_id: ParaId) -> u32 {
+		self.para_config_overrides_of(para_id)
+			.and_then(|overrides| overrides.max_head_data_size)
+			.unwrap_or(self.max_head_data_size)
+	}
+
+	/// The deposit the sender of an HRMP channel with the given parameters should provide.
+	pub fn hrmp_sender_deposit_for(&self, max_capacity: u32, max_message_size: u32) -> Balance {
+		let max_bytes = Balance::from(max_capacity).saturating_mul(max_message_size.into());
+		let per_message = self.hrmp_sender_deposit_per_message.saturating_mul(max_capacity.into());
+		let per_byte = self.hrmp_sender_deposit_per_byte.saturating_mul(max_bytes);
+		self.hrmp_sender_deposit.saturating_add(per_message).saturating_add(per_byte)
+	}
+}
+
+impl<BlockNumber: Clone> HostConfiguration<BlockNumber> {
+	/// The validation upgrade cooldown of the given para.
+	pub fn validation_upgrade_cooldown_of(&self, para_id: ParaId) -> BlockNumber {
+		self.para_config_overrides_of(para_id)
+			.and_then(|overrides| overrides.validation_upgrade_cooldown.clone())
+			.unwrap_or_else(|| self.validation_upgrade_cooldown.clone())
+	}
+
+	/// The availability period of the given para when scheduled as a parachain.
+	pub fn chain_availability_period_of(&self, para_id: ParaId) -> BlockNumber {
+		self.para_config_overrides_of(para_id)
+			.and_then(|overrides| overrides.availability_period.clone())
+			.unwrap_or_else(|| self.chain_availability_period.clone())
+	}
+
+	/// The availability period of the given para when scheduled as a parathread.
+	pub fn thread_availability_period_of(&self, para_id: ParaId) -> BlockNumber {
+		self.para_config_overrides_of(para_id)
+			.and_then(|overrides| overrides.availability_period.clone())
+			.unwrap_or_else(|| self.thread_availability_period.clone())
+	}
+}
+
+impl<BlockNumber> HostConfiguration<BlockNumber>
+where
+	BlockNumber: Clone + UniqueSaturatedInto<primitives::BlockNumber>,
+{
+	/// Returns the abridged version of this configuration, as exposed to parachains.
+	pub fn abridged(&self) -> AbridgedHostConfiguration {
+		AbridgedHostConfiguration {
+			max_code_size: self.max_code_size,
+			max_head_data_size: self.max_head_data_size,
+			max_upward_queue_count: self.max_upward_queue_count,
+			max_upward_queue_size: self.max_upward_queue_size,
+			max_upward_message_size: self.max_upward_message_size,
+			max_upward_message_num_per_candidate: self.max_upward_message_num_per_candidate,
+			hrmp_max_message_num_per_candidate: self.hrmp_max_message_num_per_candidate,
+			validation_upgrade_cooldown: self
+				.validation_upgrade_cooldown
+				.clone()
+				.unique_saturated_into(),
+			validation_upgrade_delay: self.validation_upgrade_delay.clone().unique_saturated_into(),
+		}
+	}
+}
+
 pub trait WeightInfo {
 	fn set_config_with_block_number() -> Weight;
 	fn set_config_with_u32() -> Weight;
@@ -508,6 +887,11 @@ pub mod pallet {
 	pub(crate) type ActiveConfig<T: Config> =
 		StorageValue<_, HostConfiguration<T::BlockNumber>, ValueQuery>;
 
+	/// The session in which the active configuration became active.
+	#[pallet::storage]
+	#[pallet::getter(fn active_config_session)]
+	pub(crate) type ActiveConfigSession<T: Config> = StorageValue<_, SessionIndex, ValueQuery>;
+
 	/// Pending configuration changes.
 	///
 	/// This is a list of configuration changes, each with a session index at which it should
@@ -750,6 +1134,7 @@ pub mod pallet {
 		}
 
 		/// Set the dispute period, in number of sessions to keep for disputes.
+		/// Must be at least 1.
 		#[pallet::call_index(14)]
 		#[pallet::weight((
 			T::WeightInfo::set_config_with_u32(),
@@ -1183,6 +1568,482 @@ pub mod pallet {
 				config.executor_params = new;
 			})
 		}
+
+		/// Sets the amount of funds that must be reserved for placing a parathread claim.
+		#[pallet::call_index(47)]
+		#[pallet::weight((
+			T::WeightInfo::set_config_with_balance(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_parathread_claim_deposit(origin: OriginFor<T>, new: Balance) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.parathread_claim_deposit = new;
+			})
+		}
+
+		/// Set the number of blocks for which included candidates are kept on-chain.
+		#[pallet::call_index(48)]
+		#[pallet::weight((
+			T::WeightInfo::set_config_with_block_number(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_included_candidates_retention(
+			origin: OriginFor<T>,
+			new: T::BlockNumber,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.included_candidates_retention = new;
+			})
+		}
+
+		/// Set the fraction of validators that must signal availability of a candidate.
+		#[pallet::call_index(49)]
+		#[pallet::weight((
+			T::WeightInfo::set_config_with_u32(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_availability_threshold(origin: OriginFor<T>, new: Perbill) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.availability_threshold = new;
+			})
+		}
+
+		/// Set the minimum number of validators that must signal availability of a candidate.
+		#[pallet::call_index(50)]
+		#[pallet::weight((
+			T::WeightInfo::set_config_with_u32(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_minimum_availability_votes(origin: OriginFor<T>, new: u32) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.minimum_availability_votes = new;
+			})
+		}
+
+		/// Set how many blocks before the parent block a candidate's relay parent may be, at most
+		/// [`shared::MAX_CANDIDATE_RELAY_PARENT_DEPTH`].
+		#[pallet::call_index(51)]
+		#[pallet::weight((
+			T::WeightInfo::set_config_with_u32(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_max_candidate_relay_parent_depth(
+			origin: OriginFor<T>,
+			new: u32,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.max_candidate_relay_parent_depth = new;
+			})
+		}
+
+		/// Set whether candidates with v1 descriptors are rejected.
+		#[pallet::call_index(52)]
+		#[pallet::weight((
+			// Using u32 here is a little bit of cheating, but that should be fine.
+			T::WeightInfo::set_config_with_u32(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_v1_candidate_descriptors_rejected(
+			origin: OriginFor<T>,
+			new: bool,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.v1_candidate_descriptors_rejected = new;
+			})
+		}
+
+		/// Set whether availability bitfields may be submitted in their compact encoding.
+		#[pallet::call_index(53)]
+		#[pallet::weight((
+			// Using u32 here is a little bit of cheating, but that should be fine.
+			T::WeightInfo::set_config_with_u32(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_compact_availability_bitfields(
+			origin: OriginFor<T>,
+			new: bool,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.compact_availability_bitfields = new;
+			})
+		}
+
+		/// Set how many blocks an availability vote stays valid.
+		#[pallet::call_index(54)]
+		#[pallet::weight((
+			T::WeightInfo::set_config_with_block_number(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_availability_vote_ttl(
+			origin: OriginFor<T>,
+			new: T::BlockNumber,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.availability_vote_ttl = new;
+			})
+		}
+
+		/// Set how many blocks the past head-data of paras is kept for.
+		#[pallet::call_index(55)]
+		#[pallet::weight((
+			T::WeightInfo::set_config_with_block_number(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_head_data_retention_period(
+			origin: OriginFor<T>,
+			new: T::BlockNumber,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.head_data_retention_period = new;
+			})
+		}
+
+		/// Set the availability cores each listed para may be scheduled on.
+		#[pallet::call_index(56)]
+		#[pallet::weight((
+			T::WeightInfo::set_config_with_executor_params(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_core_affinity(
+			origin: OriginFor<T>,
+			new: Vec<(ParaId, Vec<CoreIndex>)>,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.core_affinity = new;
+			})
+		}
+
+		/// Set the amount by which the effective bid of a queued parathread claim grows per block.
+		#[pallet::call_index(57)]
+		#[pallet::weight((
+			T::WeightInfo::set_config_with_balance(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_parathread_bid_aging_bonus(
+			origin: OriginFor<T>,
+			new: Balance,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.parathread_bid_aging_bonus = new;
+			})
+		}
+
+		/// Set the minimum number of valid backing statements required to consider a candidate backed.
+		#[pallet::call_index(58)]
+		#[pallet::weight((
+			T::WeightInfo::set_config_with_u32(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_minimum_backing_votes(origin: OriginFor<T>, new: u32) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.minimum_backing_votes = new;
+			})
+		}
+
+		/// Set whether candidates pending availability are carried over across session boundaries.
+		#[pallet::call_index(59)]
+		#[pallet::weight((
+			// Using u32 here is a little bit of cheating, but that should be fine.
+			T::WeightInfo::set_config_with_u32(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_carry_over_pending_availability(
+			origin: OriginFor<T>,
+			new: bool,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.carry_over_pending_availability = new;
+			})
+		}
+
+		/// Set the maximum weight spent on enacting available candidates in a single block.
+		#[pallet::call_index(60)]
+		#[pallet::weight((
+			T::WeightInfo::set_config_with_weight(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_max_enactment_weight_per_block(
+			origin: OriginFor<T>,
+			new: Weight,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.max_enactment_weight_per_block = new;
+			})
+		}
+
+		/// Set whether the heavy commitments of candidates pending availability are stored by reference.
+		#[pallet::call_index(61)]
+		#[pallet::weight((
+			// Using u32 here is a little bit of cheating, but that should be fine.
+			T::WeightInfo::set_config_with_u32(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_pending_commitments_by_reference(
+			origin: OriginFor<T>,
+			new: bool,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.pending_commitments_by_reference = new;
+			})
+		}
+
+		/// Sets the deposit required from the sender for each message of capacity of an HRMP channel.
+		#[pallet::call_index(62)]
+		#[pallet::weight((
+			T::WeightInfo::set_config_with_balance(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_hrmp_sender_deposit_per_message(
+			origin: OriginFor<T>,
+			new: Balance,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.hrmp_sender_deposit_per_message = new;
+			})
+		}
+
+		/// Sets the deposit required from the sender for each byte an HRMP channel can hold.
+		#[pallet::call_index(63)]
+		#[pallet::weight((
+			T::WeightInfo::set_config_with_balance(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_hrmp_sender_deposit_per_byte(
+			origin: OriginFor<T>,
+			new: Balance,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.hrmp_sender_deposit_per_byte = new;
+			})
+		}
+
+		/// Sets the fraction of the sender's deposit slashed when an HRMP channel is force-closed.
+		#[pallet::call_index(64)]
+		#[pallet::weight((
+			T::WeightInfo::set_config_with_u32(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_hrmp_force_close_slash(origin: OriginFor<T>, new: Perbill) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.hrmp_force_close_slash = new;
+			})
+		}
+
+		/// Sets the maximum amount of weight the upward messages of a single para may use in a block.
+		#[pallet::call_index(65)]
+		#[pallet::weight((
+			T::WeightInfo::set_config_with_weight(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_ump_max_weight_per_para(origin: OriginFor<T>, new: Weight) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.ump_max_weight_per_para = new;
+			})
+		}
+
+		/// Sets the fraction of the claim deposit slashed when a parathread claim runs out of retries.
+		#[pallet::call_index(66)]
+		#[pallet::weight((
+			T::WeightInfo::set_config_with_u32(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_parathread_claim_slash(origin: OriginFor<T>, new: Perbill) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.parathread_claim_slash = new;
+			})
+		}
+
+		/// Set the overrides of select configuration fields for the listed paras.
+		#[pallet::call_index(68)]
+		#[pallet::weight((
+			T::WeightInfo::set_config_with_executor_params(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_para_config_overrides(
+			origin: OriginFor<T>,
+			new: Vec<(ParaId, ParaConfigOverrides<BlockNumber>)>,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.para_config_overrides = new;
+			})
+		}
+
+		/// Set the number of blocks after which an accepted code upgrade that was not applied expires.
+		#[pallet::call_index(69)]
+		#[pallet::weight((
+			T::WeightInfo::set_config_with_block_number(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_validation_upgrade_expiry(
+			origin: OriginFor<T>,
+			new: T::BlockNumber,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.validation_upgrade_expiry = new;
+			})
+		}
+
+		/// Set the number of consecutive sessions of availability no-shows after which a validator
+		/// is slashed.
+		#[pallet::call_index(70)]
+		#[pallet::weight((
+			T::WeightInfo::set_config_with_u32(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_availability_no_show_slash_threshold(
+			origin: OriginFor<T>,
+			new: u32,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.availability_no_show_slash_threshold = new;
+			})
+		}
+
+		/// Set the spot price of an on-demand order while the parathread claim queue is empty.
+		#[pallet::call_index(71)]
+		#[pallet::weight((
+			T::WeightInfo::set_config_with_balance(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_on_demand_base_fee(origin: OriginFor<T>, new: Balance) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.on_demand_base_fee = new;
+			})
+		}
+
+		/// Set the maximum number of candidates that may be backed in a single relay chain block.
+		#[pallet::call_index(72)]
+		#[pallet::weight((
+			T::WeightInfo::set_config_with_option_u32(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_max_candidates_per_block(
+			origin: OriginFor<T>,
+			new: Option<u32>,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.max_candidates_per_block = new;
+			})
+		}
+
+		/// Set whether availability bitfields are signed over the layout of the occupied cores.
+		#[pallet::call_index(73)]
+		#[pallet::weight((
+			// Using u32 here is a little bit of cheating, but that should be fine.
+			T::WeightInfo::set_config_with_u32(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_core_layout_bound_bitfields(origin: OriginFor<T>, new: bool) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.core_layout_bound_bitfields = new;
+			})
+		}
+
+		/// Set whether a para may occupy several availability cores at once.
+		#[pallet::call_index(74)]
+		#[pallet::weight((
+			// Using u32 here is a little bit of cheating, but that should be fine.
+			T::WeightInfo::set_config_with_u32(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_multi_core_paras(origin: OriginFor<T>, new: bool) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.multi_core_paras = new;
+			})
+		}
+
+		/// Set the paras that are notified of the hash of the validator set of each new session.
+		#[pallet::call_index(75)]
+		#[pallet::weight((
+			T::WeightInfo::set_config_with_executor_params(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_validator_set_hash_subscribers(
+			origin: OriginFor<T>,
+			new: Vec<ParaId>,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.validator_set_hash_subscribers = new;
+			})
+		}
+
+		/// Set the amount by which the effective bid of a parathread claim grows per block the
+		/// parathread has been waiting for one of its candidates to be included.
+		#[pallet::call_index(76)]
+		#[pallet::weight((
+			T::WeightInfo::set_config_with_balance(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_parathread_starvation_bonus(
+			origin: OriginFor<T>,
+			new: Balance,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.parathread_starvation_bonus = new;
+			})
+		}
+
+		/// Set the number of blocks for which relay chain storage roots are kept on-chain.
+		#[pallet::call_index(77)]
+		#[pallet::weight((
+			T::WeightInfo::set_config_with_block_number(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_relay_storage_roots_retention(
+			origin: OriginFor<T>,
+			new: T::BlockNumber,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.relay_storage_roots_retention = new;
+			})
+		}
+
+		/// Set the number of blocks after a session change during which bitfields signed in the
+		/// previous session are accepted.
+		#[pallet::call_index(79)]
+		#[pallet::weight((
+			T::WeightInfo::set_config_with_block_number(),
+			DispatchClass::Operational,
+		))]
+		pub fn set_bitfield_grace_blocks(
+			origin: OriginFor<T>,
+			new: T::BlockNumber,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::schedule_config_update(|config| {
+				config.bitfield_grace_blocks = new;
+			})
+		}
 	}
 
 	#[pallet::hooks]
@@ -1249,6 +2110,7 @@ impl<T: Config> Pallet<T> {
 		if let Some(ref new_config) = new_config {
 			// Apply the new configuration.
 			ActiveConfig::<T>::put(new_config);
+			ActiveConfigSession::<T>::put(*session_index);
 		}
 
 		<PendingConfigs<T>>::put(future);
@@ -1256,6 +2118,16 @@ impl<T: Config> Pallet<T> {
 		SessionChangeOutcome { prev_config, new_config }
 	}
 
+	/// Returns the configuration pending to become active in the given session, if any.
+	pub(crate) fn pending_config(
+		session_index: SessionIndex,
+	) -> Option<HostConfiguration<T::BlockNumber>> {
+		<PendingConfigs<T>>::get()
+			.into_iter()
+			.find(|(apply_at_session, _)| *apply_at_session == session_index)
+			.map(|(_, config)| config)
+	}
+
 	/// Return the session index that should be used for any future scheduled changes.
 	fn scheduled_session() -> SessionIndex {
 		shared::Pallet::<T>::scheduled_session()
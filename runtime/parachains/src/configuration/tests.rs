@@ -87,6 +87,31 @@ fn config_changes_after_2_session_boundary() {
 	})
 }
 
+#[test]
+fn active_config_session_and_pending_config_are_tracked() {
+	new_test_ext(Default::default()).execute_with(|| {
+		assert_eq!(Configuration::active_config_session(), 0);
+		assert_ok!(Configuration::set_validation_upgrade_delay(RuntimeOrigin::root(), 100));
+		let config = PendingConfigs::<Test>::get()[0].1.clone();
+
+		on_new_session(1);
+
+		assert_eq!(Configuration::active_config_session(), 0);
+		assert_eq!(Configuration::pending_config(2), Some(config.clone()));
+		assert_eq!(Configuration::pending_config(3), None);
+
+		on_new_session(2);
+
+		assert_eq!(Configuration::active_config_session(), 2);
+		assert_eq!(Configuration::pending_config(2), None);
+		assert_eq!(Configuration::config(), config);
+
+		// sessions without a configuration change keep the activation session.
+		on_new_session(3);
+		assert_eq!(Configuration::active_config_session(), 2);
+	});
+}
+
 #[test]
 fn consecutive_changes_within_one_session() {
 	new_test_ext(Default::default()).execute_with(|| {
@@ -227,6 +252,21 @@ fn invariants() {
 			Configuration::set_no_show_slots(RuntimeOrigin::root(), 0),
 			Error::<Test>::InvalidNewValue
 		);
+		assert_err!(
+			Configuration::set_max_validators_per_core(RuntimeOrigin::root(), Some(0)),
+			Error::<Test>::InvalidNewValue
+		);
+		assert_err!(
+			Configuration::set_dispute_period(RuntimeOrigin::root(), 0),
+			Error::<Test>::InvalidNewValue
+		);
+		assert_err!(
+			Configuration::set_max_candidate_relay_parent_depth(
+				RuntimeOrigin::root(),
+				shared::MAX_CANDIDATE_RELAY_PARENT_DEPTH + 1
+			),
+			Error::<Test>::InvalidNewValue
+		);
 
 		ActiveConfig::<Test>::put(HostConfiguration {
 			chain_availability_period: 10,
@@ -251,6 +291,70 @@ fn invariants() {
 			Configuration::set_validation_upgrade_delay(RuntimeOrigin::root(), 0),
 			Error::<Test>::InvalidNewValue
 		);
+
+		assert_err!(
+			Configuration::set_availability_threshold(
+				RuntimeOrigin::root(),
+				Perbill::from_percent(30)
+			),
+			Error::<Test>::InvalidNewValue
+		);
+
+		assert_err!(
+			Configuration::set_core_affinity(
+				RuntimeOrigin::root(),
+				vec![(ParaId::from(2), vec![CoreIndex(0)]), (ParaId::from(1), vec![CoreIndex(1)])]
+			),
+			Error::<Test>::InvalidNewValue
+		);
+
+		assert_err!(
+			Configuration::set_validator_set_hash_subscribers(
+				RuntimeOrigin::root(),
+				vec![ParaId::from(1), ParaId::from(1)]
+			),
+			Error::<Test>::InvalidNewValue
+		);
+
+		assert_err!(
+			Configuration::set_minimum_backing_votes(RuntimeOrigin::root(), 0),
+			Error::<Test>::InvalidNewValue
+		);
+
+		assert_err!(
+			Configuration::set_para_config_overrides(
+				RuntimeOrigin::root(),
+				vec![
+					(ParaId::from(2), ParaConfigOverrides::default()),
+					(ParaId::from(1), ParaConfigOverrides::default()),
+				]
+			),
+			Error::<Test>::InvalidNewValue
+		);
+
+		assert_err!(
+			Configuration::set_para_config_overrides(
+				RuntimeOrigin::root(),
+				vec![(
+					ParaId::from(1),
+					ParaConfigOverrides { availability_period: Some(0), ..Default::default() }
+				)]
+			),
+			Error::<Test>::InvalidNewValue
+		);
+
+		ActiveConfig::<Test>::put(HostConfiguration {
+			max_validators_per_core: Some(3),
+			..Default::default()
+		});
+		assert_err!(
+			Configuration::set_minimum_backing_votes(RuntimeOrigin::root(), 4),
+			Error::<Test>::InvalidNewValue
+		);
+		assert_err!(
+			Configuration::set_max_validators_per_core(RuntimeOrigin::root(), Some(1)),
+			Error::<Test>::InvalidNewValue
+		);
 	});
 }
 
@@ -327,6 +431,44 @@ fn setting_pending_config_members() {
 			pvf_voting_ttl: 3,
 			minimum_validation_upgrade_delay: 20,
 			executor_params: Default::default(),
+			parathread_claim_deposit: 100,
+			included_candidates_retention: 20,
+			availability_threshold: Perbill::from_percent(80),
+			minimum_availability_votes: 3,
+			max_candidate_relay_parent_depth: 2,
+			v1_candidate_descriptors_rejected: true,
+			compact_availability_bitfields: true,
+			availability_vote_ttl: 7,
+			head_data_retention_period: 9,
+			core_affinity: vec![(ParaId::from(100), vec![CoreIndex(0), CoreIndex(2)])],
+			parathread_bid_aging_bonus: 7,
+			minimum_backing_votes: 3,
+			carry_over_pending_availability: true,
+			max_enactment_weight_per_block: Weight::from_parts(30000, 30000),
+			pending_commitments_by_reference: true,
+			hrmp_sender_deposit_per_message: 500,
+			hrmp_sender_deposit_per_byte: 3,
+			hrmp_force_close_slash: Perbill::from_percent(10),
+			ump_max_weight_per_para: Weight::from_parts(40_000, 40_000),
+			parathread_claim_slash: Perbill::from_percent(20),
+			para_config_overrides: vec![(
+				ParaId::from(1000),
+				ParaConfigOverrides {
+					max_head_data_size: Some(32_768),
+					validation_upgrade_cooldown: Some(100),
+					availability_period: Some(8),
+				},
+			)],
+			validation_upgrade_expiry: 40,
+			availability_no_show_slash_threshold: 3,
+			on_demand_base_fee: 11,
+			max_candidates_per_block: Some(12),
+			core_layout_bound_bitfields: true,
+			multi_core_paras: true,
+			validator_set_hash_subscribers: vec![ParaId::from(100)],
+			parathread_starvation_bonus: 11,
+			relay_storage_roots_retention: 12,
+			bitfield_grace_blocks: 13,
 		};
 
 		Configuration::set_validation_upgrade_cooldown(
@@ -500,7 +642,152 @@ fn setting_pending_config_members() {
 		.unwrap();
 		Configuration::set_pvf_voting_ttl(RuntimeOrigin::root(), new_config.pvf_voting_ttl)
 			.unwrap();
-
+		Configuration::set_parathread_claim_deposit(
+			RuntimeOrigin::root(),
+			new_config.parathread_claim_deposit,
+		)
+		.unwrap();
+		Configuration::set_included_candidates_retention(
+			RuntimeOrigin::root(),
+			new_config.included_candidates_retention,
+		)
+		.unwrap();
+		Configuration::set_availability_threshold(
+			RuntimeOrigin::root(),
+			new_config.availability_threshold,
+		)
+		.unwrap();
+		Configuration::set_minimum_availability_votes(
+			RuntimeOrigin::root(),
+			new_config.minimum_availability_votes,
+		)
+		.unwrap();
+		Configuration::set_max_candidate_relay_parent_depth(
+			RuntimeOrigin::root(),
+			new_config.max_candidate_relay_parent_depth,
+		)
+		.unwrap();
+		Configuration::set_v1_candidate_descriptors_rejected(
+			RuntimeOrigin::root(),
+			new_config.v1_candidate_descriptors_rejected,
+		)
+		.unwrap();
+		Configuration::set_compact_availability_bitfields(
+			RuntimeOrigin::root(),
+			new_config.compact_availability_bitfields,
+		)
+		.unwrap();
+		Configuration::set_availability_vote_ttl(
+			RuntimeOrigin::root(),
+			new_config.availability_vote_ttl,
+		)
+		.unwrap();
+		Configuration::set_head_data_retention_period(
+			RuntimeOrigin::root(),
+			new_config.head_data_retention_period,
+		)
+		.unwrap();
+		Configuration::set_core_affinity(RuntimeOrigin::root(), new_config.core_affinity.clone())
+			.unwrap();
+		Configuration::set_parathread_bid_aging_bonus(
+			RuntimeOrigin::root(),
+			new_config.parathread_bid_aging_bonus,
+		)
+		.unwrap();
+		Configuration::set_minimum_backing_votes(
+			RuntimeOrigin::root(),
+			new_config.minimum_backing_votes,
+		)
+		.unwrap();
+		Configuration::set_carry_over_pending_availability(
+			RuntimeOrigin::root(),
+			new_config.carry_over_pending_availability,
+		)
+		.unwrap();
+		Configuration::set_max_enactment_weight_per_block(
+			RuntimeOrigin::root(),
+			new_config.max_enactment_weight_per_block,
+		)
+		.unwrap();
+		Configuration::set_pending_commitments_by_reference(
+			RuntimeOrigin::root(),
+			new_config.pending_commitments_by_reference,
+		)
+		.unwrap();
+		Configuration::set_hrmp_sender_deposit_per_message(
+			RuntimeOrigin::root(),
+			new_config.hrmp_sender_deposit_per_message,
+		)
+		.unwrap();
+		Configuration::set_hrmp_sender_deposit_per_byte(
+			RuntimeOrigin::root(),
+			new_config.hrmp_sender_deposit_per_byte,
+		)
+		.unwrap();
+		Configuration::set_hrmp_force_close_slash(
+			RuntimeOrigin::root(),
+			new_config.hrmp_force_close_slash,
+		)
+		.unwrap();
+		Configuration::set_ump_max_weight_per_para(
+			RuntimeOrigin::root(),
+			new_config.ump_max_weight_per_para,
+		)
+		.unwrap();
+		Configuration::set_parathread_claim_slash(
+			RuntimeOrigin::root(),
+			new_config.parathread_claim_slash,
+		)
+		.unwrap();
+		Configuration::set_para_config_overrides(
+			RuntimeOrigin::root(),
+			new_config.para_config_overrides.clone(),
+		)
+		.unwrap();
+		Configuration::set_validation_upgrade_expiry(
+			RuntimeOrigin::root(),
+			new_config.validation_upgrade_expiry,
+		)
+		.unwrap();
+		Configuration::set_availability_no_show_slash_threshold(
+			RuntimeOrigin::root(),
+			new_config.availability_no_show_slash_threshold,
+		)
+		.unwrap();
+		Configuration::set_on_demand_base_fee(RuntimeOrigin::root(), new_config.on_demand_base_fee)
+			.unwrap();
+		Configuration::set_max_candidates_per_block(
+			RuntimeOrigin::root(),
+			new_config.max_candidates_per_block,
+		)
+		.unwrap();
+		Configuration::set_core_layout_bound_bitfields(
+			RuntimeOrigin::root(),
+			new_config.core_layout_bound_bitfields,
+		)
+		.unwrap();
+		Configuration::set_multi_core_paras(RuntimeOrigin::root(), new_config.multi_core_paras)
+			.unwrap();
+		Configuration::set_validator_set_hash_subscribers(
+			RuntimeOrigin::root(),
+			new_config.validator_set_hash_subscribers.clone(),
+		)
+		.unwrap();
+		Configuration::set_parathread_starvation_bonus(
+			RuntimeOrigin::root(),
+			new_config.parathread_starvation_bonus,
+		)
+		.unwrap();
+		Configuration::set_relay_storage_roots_retention(
+			RuntimeOrigin::root(),
+			new_config.relay_storage_roots_retention,
+		)
+		.unwrap();
+		Configuration::set_bitfield_grace_blocks(
+			RuntimeOrigin::root(),
+			new_config.bitfield_grace_blocks,
+		)
+		.unwrap();
 		assert_eq!(PendingConfigs::<Test>::get(), vec![(shared::SESSION_DELAY, new_config)],);
 	})
 }
@@ -546,5 +833,6 @@ fn verify_externally_accessible() {
 				validation_upgrade_delay: ground_truth.validation_upgrade_delay,
 			},
 		);
+		assert_eq!(abridged_config, ground_truth.abridged());
 	});
 }
@@ -42,18 +42,26 @@
 //! producer can submit an unsigned transaction with `KeyOwnershipProof` of an
 //! offender and submit it to the runtime to produce an offence.
 
-use crate::{disputes, initializer::ValidatorSetCount, session_info::IdentificationTuple};
+use crate::{
+	disputes, inclusion, initializer::ValidatorSetCount, session_info::IdentificationTuple,
+};
 use frame_support::{
 	dispatch::Pays,
 	traits::{Defensive, Get, KeyOwnerProofSystem, ValidatorSet, ValidatorSetWithIdentification},
 	weights::Weight,
 };
 
-use parity_scale_codec::{Decode, Encode};
-use primitives::{CandidateHash, SessionIndex, ValidatorId, ValidatorIndex};
+use parity_scale_codec::Encode;
+pub use primitives::vstaging::slashing::{
+	DisputeProof, DisputesTimeSlot, PendingSlashes, SlashingOffenceKind,
+};
+use primitives::{
+	CandidateHash, DisputeStatement, EncodeAs, SessionIndex, SigningContext, UncheckedSigned,
+	ValidatorId, ValidatorIndex, ValidatorSignature,
+};
 use scale_info::TypeInfo;
 use sp_runtime::{
-	traits::Convert,
+	traits::{BlakeTwo256, Convert, Hash as HashT},
 	transaction_validity::{
 		InvalidTransaction, TransactionPriority, TransactionSource, TransactionValidity,
 		TransactionValidityError, ValidTransaction,
@@ -63,10 +71,7 @@ use sp_runtime::{
 use sp_session::{GetSessionNumber, GetValidatorCount};
 use sp_staking::offence::{DisableStrategy, Kind, Offence, OffenceError, ReportOffence};
 use sp_std::{
-	collections::{
-		btree_map::{BTreeMap, Entry},
-		btree_set::BTreeSet,
-	},
+	collections::{btree_map::Entry, btree_set::BTreeSet},
 	prelude::*,
 };
 
@@ -76,11 +81,19 @@ const LOG_TARGET: &str = "runtime::parachains::slashing";
 // via `HostConfiguration` in the future.
 const SLASH_FOR_INVALID: Perbill = Perbill::from_percent(100);
 const SLASH_AGAINST_VALID: Perbill = Perbill::zero();
+// The most a backing equivocation is slashed, once a third of the validator set seconded
+// conflicting candidates in the same time slot, see `slash_fraction`.
+const SLASH_FOR_BACKING_EQUIVOCATION: Perbill = Perbill::from_percent(100);
+const SLASH_FOR_AVAILABILITY_NO_SHOW: Perbill = Perbill::zero();
+const SLASH_FOR_REPEATED_AVAILABILITY_NO_SHOW: Perbill = Perbill::from_percent(1);
 const DEFENSIVE_PROOF: &'static str = "disputes module should bail on old session";
 
 #[cfg(feature = "runtime-benchmarks")]
 pub mod benchmarking;
 
+#[cfg(test)]
+mod tests;
+
 /// The benchmarking configuration.
 pub trait BenchmarkingConfiguration {
 	const MAX_VALIDATORS: u32;
@@ -92,21 +105,6 @@ impl<const M: u32> BenchmarkingConfiguration for BenchConfig<M> {
 	const MAX_VALIDATORS: u32 = M;
 }
 
-/// Timeslots should uniquely identify offences and are used for the offence
-/// deduplication.
-#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Encode, Decode, TypeInfo, RuntimeDebug)]
-pub struct DisputesTimeSlot {
-	// The order of these matters for `derive(Ord)`.
-	session_index: SessionIndex,
-	candidate_hash: CandidateHash,
-}
-
-impl DisputesTimeSlot {
-	pub fn new(session_index: SessionIndex, candidate_hash: CandidateHash) -> Self {
-		Self { session_index, candidate_hash }
-	}
-}
-
 /// An offence that is filed when a series of validators lost a dispute.
 #[derive(RuntimeDebug, TypeInfo)]
 #[cfg_attr(feature = "std", derive(Clone, PartialEq, Eq))]
@@ -119,7 +117,7 @@ pub struct SlashingOffence<KeyOwnerIdentification> {
 	/// needed for slashing.
 	pub offenders: Vec<KeyOwnerIdentification>,
 	/// What fraction of the total exposure that should be slashed for
-	/// this offence.
+	/// this offence. The most that is slashed for a backing equivocation.
 	pub slash_fraction: Perbill,
 	/// Whether the candidate was valid or invalid.
 	pub kind: SlashingOffenceKind,
@@ -155,11 +153,28 @@ where
 			// in the future we might change it based on number of disputes initiated:
 			// <https://github.com/paritytech/polkadot/issues/5946>
 			SlashingOffenceKind::AgainstValid => DisableStrategy::Never,
+			// an equivocator is only disabled if it is actually slashed.
+			SlashingOffenceKind::BackingEquivocation => DisableStrategy::WhenSlashed,
+			SlashingOffenceKind::AvailabilityNoShow |
+			SlashingOffenceKind::RepeatedAvailabilityNoShow => DisableStrategy::Never,
 		}
 	}
 
-	fn slash_fraction(&self, _offenders: u32) -> Perbill {
-		self.slash_fraction
+	fn slash_fraction(&self, offenders: u32) -> Perbill {
+		match self.kind {
+			// an isolated equivocation does little harm, so the slash grows with the share of the
+			// validator set that equivocated in the same time slot, up to the full
+			// `slash_fraction` once a third of it did.
+			SlashingOffenceKind::BackingEquivocation => {
+				let validator_set_count = self.validator_set_count.max(1);
+				Perbill::from_rational(
+					offenders.saturating_mul(3).min(validator_set_count),
+					validator_set_count,
+				)
+				.min(self.slash_fraction)
+			},
+			_ => self.slash_fraction,
+		}
 	}
 }
 
@@ -175,6 +190,10 @@ impl<KeyOwnerIdentification> SlashingOffence<KeyOwnerIdentification> {
 		let slash_fraction = match kind {
 			SlashingOffenceKind::ForInvalid => SLASH_FOR_INVALID,
 			SlashingOffenceKind::AgainstValid => SLASH_AGAINST_VALID,
+			SlashingOffenceKind::BackingEquivocation => SLASH_FOR_BACKING_EQUIVOCATION,
+			SlashingOffenceKind::AvailabilityNoShow => SLASH_FOR_AVAILABILITY_NO_SHOW,
+			SlashingOffenceKind::RepeatedAvailabilityNoShow =>
+				SLASH_FOR_REPEATED_AVAILABILITY_NO_SHOW,
 		};
 		Self { time_slot, validator_set_count, offenders, slash_fraction, kind }
 	}
@@ -247,6 +266,17 @@ where
 			return
 		}
 
+		Self::report_or_defer(session_index, candidate_hash, kind, to_punish);
+	}
+
+	/// Report an offence for the given validators if they can be identified in the current
+	/// session, otherwise store it as unapplied so that it can be reported later.
+	fn report_or_defer(
+		session_index: SessionIndex,
+		candidate_hash: CandidateHash,
+		kind: SlashingOffenceKind,
+		to_punish: Vec<ValidatorIndex>,
+	) {
 		let session_info = crate::session_info::Pallet::<T>::session_info(session_index);
 		let session_info = match session_info.defensive_proof(DEFENSIVE_PROOF) {
 			Some(info) => info,
@@ -263,7 +293,7 @@ where
 				offenders,
 				kind,
 			);
-			// This is the first time we report an offence for this dispute,
+			// This is the first time we report an offence for this candidate,
 			// so it is not a duplicate.
 			let _ = T::HandleReports::report_offence(offence);
 			return
@@ -323,37 +353,49 @@ where
 	}
 }
 
-#[derive(PartialEq, Eq, Clone, Copy, Encode, Decode, RuntimeDebug, TypeInfo)]
-pub enum SlashingOffenceKind {
-	#[codec(index = 0)]
-	ForInvalid,
-	#[codec(index = 1)]
-	AgainstValid,
-}
+impl<T> inclusion::BackingEquivocationHandler for SlashValidatorsForDisputes<Pallet<T>>
+where
+	T: Config<KeyOwnerIdentification = IdentificationTuple<T>>,
+{
+	fn punish_backing_equivocation(
+		session_index: SessionIndex,
+		candidate_hash: CandidateHash,
+		offenders: impl IntoIterator<Item = ValidatorIndex>,
+	) {
+		let offenders: BTreeSet<_> = offenders.into_iter().collect();
+		if offenders.is_empty() {
+			return
+		}
 
-/// We store most of the information about a lost dispute on chain. This struct
-/// is required to identify and verify it.
-#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, TypeInfo)]
-pub struct DisputeProof {
-	/// Time slot when the dispute occured.
-	pub time_slot: DisputesTimeSlot,
-	/// The dispute outcome.
-	pub kind: SlashingOffenceKind,
-	/// The index of the validator who lost a dispute.
-	pub validator_index: ValidatorIndex,
-	/// The parachain session key of the validator.
-	pub validator_id: ValidatorId,
+		let kind = SlashingOffenceKind::BackingEquivocation;
+		Self::report_or_defer(session_index, candidate_hash, kind, offenders.into_iter().collect());
+	}
 }
 
-/// Slashes that are waiting to be applied once we have validator key
-/// identification.
-#[derive(Encode, Decode, RuntimeDebug, TypeInfo)]
-pub struct PendingSlashes {
-	/// Indices and keys of the validators who lost a dispute and are pending
-	/// slashes.
-	pub keys: BTreeMap<ValidatorIndex, ValidatorId>,
-	/// The dispute outcome.
-	pub kind: SlashingOffenceKind,
+impl<T> inclusion::AvailabilityNoShowHandler for SlashValidatorsForDisputes<Pallet<T>>
+where
+	T: Config<KeyOwnerIdentification = IdentificationTuple<T>>,
+{
+	fn punish_availability_no_shows(
+		session_index: SessionIndex,
+		offenders: impl IntoIterator<Item = ValidatorIndex>,
+		repeated: bool,
+	) {
+		let offenders: BTreeSet<_> = offenders.into_iter().collect();
+		if offenders.is_empty() {
+			return
+		}
+
+		let kind = if repeated {
+			SlashingOffenceKind::RepeatedAvailabilityNoShow
+		} else {
+			SlashingOffenceKind::AvailabilityNoShow
+		};
+		// No-shows are not about a particular candidate, so the offences of a session are keyed
+		// by a fixed hash per kind instead.
+		let candidate_hash = CandidateHash(BlakeTwo256::hash_of(&(b"availability-no-show", kind)));
+		Self::report_or_defer(session_index, candidate_hash, kind, offenders.into_iter().collect());
+	}
 }
 
 /// A trait that defines methods to report an offence (after the slashing report
@@ -495,6 +537,8 @@ pub mod pallet {
 		ValidatorIndexIdMismatch,
 		/// The given slashing report is valid but already previously reported.
 		DuplicateSlashingReport,
+		/// The signature of a validator of a past session is invalid.
+		InvalidHistoricalSignature,
 	}
 
 	#[pallet::call]
@@ -512,11 +556,13 @@ pub mod pallet {
 			ensure_none(origin)?;
 
 			// check the membership proof to extract the offender's id
-			let key = (primitives::PARACHAIN_KEY_TYPE_ID, dispute_proof.validator_id.clone());
-			let offender = T::KeyOwnerProofSystem::check_proof(key, key_owner_proof)
-				.ok_or(Error::<T>::InvalidKeyOwnershipProof)?;
-
 			let session_index = dispute_proof.time_slot.session_index;
+			let offender = Self::check_key_ownership(
+				session_index,
+				dispute_proof.validator_id.clone(),
+				key_owner_proof,
+			)?;
+
 			let validator_set_count = crate::session_info::Pallet::<T>::session_info(session_index)
 				.ok_or(Error::<T>::InvalidSessionIndex)?
 				.discovery_keys
@@ -603,6 +649,78 @@ impl<T: Config> Pallet<T> {
 		let old_session = session_index - config.dispute_period - 1;
 		let _ = <UnappliedSlashes<T>>::clear_prefix(old_session, REMOVE_LIMIT, None);
 	}
+
+	/// The slashes of past sessions that are waiting for a key ownership proof of their offenders
+	/// to be applied.
+	pub(crate) fn unapplied_slashes() -> Vec<(SessionIndex, CandidateHash, PendingSlashes)> {
+		<UnappliedSlashes<T>>::iter().collect()
+	}
+
+	/// Submit an unsigned `report_dispute_lost_unsigned` extrinsic applying the slash of the given
+	/// lost dispute. Must only be called from an offchain context.
+	pub(crate) fn submit_unsigned_slashing_report(
+		dispute_proof: DisputeProof,
+		key_ownership_proof: T::KeyOwnerProof,
+	) -> Option<()> {
+		<T::HandleReports as HandleReports<T>>::submit_unsigned_slashing_report(
+			dispute_proof,
+			key_ownership_proof,
+		)
+		.ok()
+	}
+
+	/// Check that `validator_id` was part of the validator set of the given session, and return
+	/// its identification.
+	///
+	/// The proof is checked against the historical session roots, so this works for past
+	/// sessions as long as their roots are kept, independent of the session info pruning.
+	pub fn check_key_ownership(
+		session_index: SessionIndex,
+		validator_id: ValidatorId,
+		key_owner_proof: T::KeyOwnerProof,
+	) -> Result<T::KeyOwnerIdentification, Error<T>> {
+		if key_owner_proof.session() != session_index {
+			return Err(Error::<T>::InvalidKeyOwnershipProof)
+		}
+
+		let key = (primitives::PARACHAIN_KEY_TYPE_ID, validator_id);
+		T::KeyOwnerProofSystem::check_proof(key, key_owner_proof)
+			.ok_or(Error::<T>::InvalidKeyOwnershipProof)
+	}
+
+	/// Check a signed payload, like a bitfield or a backing statement, of a validator of the
+	/// session of the signing context, which may be a past one.
+	pub fn check_historical_signature<Payload, RealPayload, H>(
+		signed: &UncheckedSigned<Payload, RealPayload>,
+		context: &SigningContext<H>,
+		validator_id: ValidatorId,
+		key_owner_proof: T::KeyOwnerProof,
+	) -> Result<T::KeyOwnerIdentification, Error<T>>
+	where
+		Payload: EncodeAs<RealPayload>,
+		RealPayload: Encode,
+		H: Encode,
+	{
+		signed
+			.check_signature(context, &validator_id)
+			.map_err(|_| Error::<T>::InvalidHistoricalSignature)?;
+		Self::check_key_ownership(context.session_index, validator_id, key_owner_proof)
+	}
+
+	/// Check a dispute statement of a validator of the given session, which may be a past one.
+	pub fn check_historical_dispute_statement(
+		session_index: SessionIndex,
+		candidate_hash: CandidateHash,
+		statement: &DisputeStatement,
+		validator_id: ValidatorId,
+		validator_signature: &ValidatorSignature,
+		key_owner_proof: T::KeyOwnerProof,
+	) -> Result<T::KeyOwnerIdentification, Error<T>> {
+		statement
+			.check_signature(&validator_id, candidate_hash, session_index, validator_signature)
+			.map_err(|_| Error::<T>::InvalidHistoricalSignature)?;
+		Self::check_key_ownership(session_index, validator_id, key_owner_proof)
+	}
 }
 
 /// Methods for the `ValidateUnsigned` implementation:
@@ -634,6 +752,9 @@ impl<T: Config> Pallet<T> {
 			let tag_prefix = match dispute_proof.kind {
 				SlashingOffenceKind::ForInvalid => "DisputeForInvalid",
 				SlashingOffenceKind::AgainstValid => "DisputeAgainstValid",
+				SlashingOffenceKind::BackingEquivocation => "BackingEquivocation",
+				SlashingOffenceKind::AvailabilityNoShow => "AvailabilityNoShow",
+				SlashingOffenceKind::RepeatedAvailabilityNoShow => "RepeatedAvailabilityNoShow",
 			};
 
 			ValidTransaction::with_tag_prefix(tag_prefix)
@@ -664,10 +785,12 @@ fn is_known_offence<T: Config>(
 	key_owner_proof: &T::KeyOwnerProof,
 ) -> Result<(), TransactionValidityError> {
 	// check the membership proof to extract the offender's id
-	let key = (primitives::PARACHAIN_KEY_TYPE_ID, dispute_proof.validator_id.clone());
-
-	let offender = T::KeyOwnerProofSystem::check_proof(key, key_owner_proof.clone())
-		.ok_or(InvalidTransaction::BadProof)?;
+	let offender = Pallet::<T>::check_key_ownership(
+		dispute_proof.time_slot.session_index,
+		dispute_proof.validator_id.clone(),
+		key_owner_proof.clone(),
+	)
+	.map_err(|_| InvalidTransaction::BadProof)?;
 
 	// check if the offence has already been reported,
 	// and if so then we can discard the report.
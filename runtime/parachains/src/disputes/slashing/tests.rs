@@ -0,0 +1,39 @@
+// Copyright (C) Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::*;
+
+fn new_offence(validator_set_count: u32, kind: SlashingOffenceKind) -> SlashingOffence<u64> {
+	SlashingOffence::new(0, CandidateHash::default(), validator_set_count, vec![1], kind)
+}
+
+#[test]
+fn backing_equivocations_are_slashed_in_proportion_to_the_equivocators() {
+	let offence = new_offence(300, SlashingOffenceKind::BackingEquivocation);
+
+	assert_eq!(offence.slash_fraction(1), Perbill::from_percent(1));
+	assert_eq!(offence.slash_fraction(10), Perbill::from_percent(10));
+	// A third of the validator set or more is slashed in full.
+	assert_eq!(offence.slash_fraction(100), SLASH_FOR_BACKING_EQUIVOCATION);
+	assert_eq!(offence.slash_fraction(300), SLASH_FOR_BACKING_EQUIVOCATION);
+
+	// Equivocators are only disabled when slashed, unlike those backing invalid candidates.
+	assert_eq!(offence.disable_strategy(), DisableStrategy::WhenSlashed);
+	assert_eq!(
+		new_offence(300, SlashingOffenceKind::ForInvalid).disable_strategy(),
+		DisableStrategy::Always,
+	);
+}
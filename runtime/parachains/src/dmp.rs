@@ -50,7 +50,7 @@ use frame_support::pallet_prelude::*;
 use primitives::{DownwardMessage, Hash, Id as ParaId, InboundDownwardMessage};
 use sp_core::MAX_POSSIBLE_ALLOCATION;
 use sp_runtime::{
-	traits::{BlakeTwo256, Hash as HashT, SaturatedConversion},
+	traits::{BlakeTwo256, Hash as HashT, One, SaturatedConversion, Zero},
 	FixedU128, Saturating,
 };
 use sp_std::{fmt, prelude::*};
@@ -136,6 +136,14 @@ pub mod pallet {
 	pub(crate) type DownwardMessageQueueHeads<T: Config> =
 		StorageMap<_, Twox64Concat, ParaId, Hash, ValueQuery>;
 
+	/// The number of downward messages enqueued for a para in a given relay-chain block.
+	///
+	/// Only kept for the blocks that can still serve as a relay parent of a candidate, so that the
+	/// length of the queue as it was visible at the relay parent can be reconstructed.
+	#[pallet::storage]
+	pub(crate) type DownwardMessagesSentAt<T: Config> =
+		StorageDoubleMap<_, Twox64Concat, T::BlockNumber, Twox64Concat, ParaId, u32, ValueQuery>;
+
 	/// Initialization value for the DeliveryFee factor.
 	#[pallet::type_value]
 	pub fn InitialFactor() -> FixedU128 {
@@ -150,8 +158,18 @@ pub mod pallet {
 /// Routines and getters related to downward message passing.
 impl<T: Config> Pallet<T> {
 	/// Block initialization logic, called by initializer.
-	pub(crate) fn initializer_initialize(_now: T::BlockNumber) -> Weight {
-		Weight::zero()
+	pub(crate) fn initializer_initialize(now: T::BlockNumber) -> Weight {
+		// The oldest relay parent a candidate can be built on in this block is
+		// `now - 1 - max_candidate_relay_parent_depth`, so only the messages enqueued after it
+		// are needed.
+		let depth = configuration::ActiveConfig::<T>::get().max_candidate_relay_parent_depth;
+		let stale = now.saturating_sub(depth.saturating_add(1).into());
+		let removed = if stale.is_zero() {
+			0
+		} else {
+			DownwardMessagesSentAt::<T>::clear_prefix(stale, u32::MAX, None).unique
+		};
+		T::DbWeight::get().reads_writes(1, removed.into())
 	}
 
 	/// Block finalization logic, called by initializer.
@@ -162,6 +180,9 @@ impl<T: Config> Pallet<T> {
 		_notification: &initializer::SessionChangeNotification<T::BlockNumber>,
 		outgoing_paras: &[ParaId],
 	) {
+		// The allowed relay parents are reset on a new session, so none of the recorded blocks can
+		// be used as a relay parent anymore.
+		let _ = DownwardMessagesSentAt::<T>::clear(u32::MAX, None);
 		Self::perform_outgoing_para_cleanup(outgoing_paras);
 	}
 
@@ -179,6 +200,15 @@ impl<T: Config> Pallet<T> {
 		DownwardMessageQueueHeads::<T>::remove(outgoing_para);
 	}
 
+	/// Remove the downward message queue of the given offboarded para, along with its delivery fee
+	/// factor.
+	pub(crate) fn clean_up_offboarded_para(para: ParaId) -> Weight {
+		Self::clean_dmp_after_outgoing(&para);
+		DeliveryFeeFactor::<T>::remove(&para);
+
+		T::DbWeight::get().writes(3)
+	}
+
 	/// Determine whether enqueuing a downward message to a specific recipient para would result
 	/// in an error. If this returns `Ok(())` the caller can be certain that a call to
 	/// `queue_downward_message` with the same parameters will be successful.
@@ -233,6 +263,8 @@ impl<T: Config> Pallet<T> {
 			*head = new_head;
 		});
 
+		DownwardMessagesSentAt::<T>::mutate(inbound.sent_at, para, |n| *n = n.saturating_add(1));
+
 		let q_len = DownwardMessageQueues::<T>::mutate(para, |v| {
 			v.push(inbound);
 			v.len()
@@ -250,12 +282,17 @@ impl<T: Config> Pallet<T> {
 		Ok(())
 	}
 
-	/// Checks if the number of processed downward messages is valid.
+	/// Checks if the number of processed downward messages is valid for a candidate built on top
+	/// of the relay-chain block with the given number.
+	///
+	/// Messages enqueued after the relay parent were not visible to the candidate, so they are
+	/// neither required to be processed nor allowed to be.
 	pub(crate) fn check_processed_downward_messages(
 		para: ParaId,
+		relay_parent_number: T::BlockNumber,
 		processed_downward_messages: u32,
 	) -> Result<(), ProcessedDownwardMessagesAcceptanceErr> {
-		let dmq_length = Self::dmq_length(para);
+		let dmq_length = Self::dmq_length_at(para, relay_parent_number);
 
 		if dmq_length > 0 && processed_downward_messages == 0 {
 			return Err(ProcessedDownwardMessagesAcceptanceErr::AdvancementRule)
@@ -309,6 +346,25 @@ impl<T: Config> Pallet<T> {
 			.saturated_into::<u32>()
 	}
 
+	/// Returns the number of pending downward messages addressed to the given para that were
+	/// already enqueued at the end of the relay-chain block with the given number.
+	///
+	/// The relay parent is expected to be within the allowed ancestry, otherwise the messages
+	/// enqueued after it may be undercounted.
+	pub(crate) fn dmq_length_at(para: ParaId, relay_parent_number: T::BlockNumber) -> u32 {
+		let now = <frame_system::Pallet<T>>::block_number();
+		let mut sent_after = 0u32;
+		let mut block = relay_parent_number.saturating_add(One::one());
+		while block <= now {
+			sent_after = sent_after.saturating_add(DownwardMessagesSentAt::<T>::get(block, para));
+			block = block.saturating_add(One::one());
+		}
+
+		// Messages sent after the relay parent can only have been pruned if messages sent before
+		// it were pruned first, so this may undercount but never overcount.
+		Self::dmq_length(para).saturating_sub(sent_after)
+	}
+
 	fn dmq_max_length(max_downward_message_size: u32) -> u32 {
 		MAX_POSSIBLE_ALLOCATION.checked_div(max_downward_message_size).unwrap_or(0)
 	}
@@ -125,21 +125,83 @@ fn check_processed_downward_messages() {
 	let a = ParaId::from(1312);
 
 	new_test_ext(default_genesis_config()).execute_with(|| {
+		let rp = System::block_number();
+
 		// processed_downward_messages=0 is allowed when the DMQ is empty.
-		assert!(Dmp::check_processed_downward_messages(a, 0).is_ok());
+		assert!(Dmp::check_processed_downward_messages(a, rp, 0).is_ok());
 
 		queue_downward_message(a, vec![1, 2, 3]).unwrap();
 		queue_downward_message(a, vec![4, 5, 6]).unwrap();
 		queue_downward_message(a, vec![7, 8, 9]).unwrap();
 
 		// 0 doesn't pass if the DMQ has msgs.
-		assert!(!Dmp::check_processed_downward_messages(a, 0).is_ok());
+		assert!(!Dmp::check_processed_downward_messages(a, rp, 0).is_ok());
 		// a candidate can consume up to 3 messages
-		assert!(Dmp::check_processed_downward_messages(a, 1).is_ok());
-		assert!(Dmp::check_processed_downward_messages(a, 2).is_ok());
-		assert!(Dmp::check_processed_downward_messages(a, 3).is_ok());
+		assert!(Dmp::check_processed_downward_messages(a, rp, 1).is_ok());
+		assert!(Dmp::check_processed_downward_messages(a, rp, 2).is_ok());
+		assert!(Dmp::check_processed_downward_messages(a, rp, 3).is_ok());
 		// there is no 4 messages in the queue
-		assert!(!Dmp::check_processed_downward_messages(a, 4).is_ok());
+		assert!(!Dmp::check_processed_downward_messages(a, rp, 4).is_ok());
+	});
+}
+
+#[test]
+fn check_processed_downward_messages_at_relay_parent() {
+	let a = ParaId::from(1312);
+
+	new_test_ext(default_genesis_config()).execute_with(|| {
+		run_to_block(2, None);
+		queue_downward_message(a, vec![1, 2, 3]).unwrap();
+
+		run_to_block(3, None);
+		queue_downward_message(a, vec![4, 5, 6]).unwrap();
+		queue_downward_message(a, vec![7, 8, 9]).unwrap();
+
+		assert_eq!(Dmp::dmq_length_at(a, 1), 0);
+		assert_eq!(Dmp::dmq_length_at(a, 2), 1);
+		assert_eq!(Dmp::dmq_length_at(a, 3), 3);
+
+		// nothing was visible at block 1, so nothing can be processed.
+		assert!(Dmp::check_processed_downward_messages(a, 1, 0).is_ok());
+		assert!(!Dmp::check_processed_downward_messages(a, 1, 1).is_ok());
+		// only the first message was visible at block 2.
+		assert!(!Dmp::check_processed_downward_messages(a, 2, 0).is_ok());
+		assert!(Dmp::check_processed_downward_messages(a, 2, 1).is_ok());
+		assert!(!Dmp::check_processed_downward_messages(a, 2, 2).is_ok());
+		// all of them are visible at block 3.
+		assert!(Dmp::check_processed_downward_messages(a, 3, 3).is_ok());
+
+		// processing the first message doesn't change what was visible later on.
+		Dmp::prune_dmq(a, 1);
+		assert_eq!(Dmp::dmq_length_at(a, 2), 0);
+		assert_eq!(Dmp::dmq_length_at(a, 3), 2);
+	});
+}
+
+#[test]
+fn downward_messages_sent_at_is_pruned() {
+	let a = ParaId::from(1312);
+
+	let mut genesis = default_genesis_config();
+	genesis.configuration.config.max_candidate_relay_parent_depth = 4;
+
+	new_test_ext(genesis).execute_with(|| {
+		run_to_block(2, None);
+		queue_downward_message(a, vec![1, 2, 3]).unwrap();
+		assert_eq!(DownwardMessagesSentAt::<Test>::get(2, a), 1);
+
+		// kept while the relay parent of a candidate can still be older than block 2.
+		run_to_block(6, None);
+		assert_eq!(DownwardMessagesSentAt::<Test>::get(2, a), 1);
+
+		run_to_block(7, None);
+		assert_eq!(DownwardMessagesSentAt::<Test>::get(2, a), 0);
+
+		// everything is dropped on a new session, while the messages themselves stay queued.
+		queue_downward_message(a, vec![4, 5, 6]).unwrap();
+		run_to_block(8, Some(vec![8]));
+		assert_eq!(DownwardMessagesSentAt::<Test>::get(7, a), 0);
+		assert_eq!(Dmp::dmq_length(a), 2);
 	});
 }
 
@@ -27,7 +27,10 @@ use primitives::{
 	SessionIndex,
 };
 use scale_info::TypeInfo;
-use sp_runtime::traits::{AccountIdConversion, BlakeTwo256, Hash as HashT, UniqueSaturatedInto};
+use sp_runtime::{
+	traits::{AccountIdConversion, BlakeTwo256, Hash as HashT, UniqueSaturatedInto, Zero},
+	Perbill,
+};
 use sp_std::{
 	collections::{btree_map::BTreeMap, btree_set::BTreeSet},
 	fmt, mem,
@@ -60,6 +63,7 @@ pub trait WeightInfo {
 	fn hrmp_cancel_open_request(c: u32) -> Weight;
 	fn clean_open_channel_requests(c: u32) -> Weight;
 	fn force_open_hrmp_channel() -> Weight;
+	fn force_close_hrmp_channel() -> Weight;
 }
 
 /// A weight info that is only suitable for testing.
@@ -93,6 +97,9 @@ impl WeightInfo for TestWeightInfo {
 	fn force_open_hrmp_channel() -> Weight {
 		Weight::MAX
 	}
+	fn force_close_hrmp_channel() -> Weight {
+		Weight::MAX
+	}
 }
 
 /// A description of a request to open an HRMP channel.
@@ -276,6 +283,14 @@ pub mod pallet {
 		/// An HRMP channel was opened via Root origin.
 		/// `[sender, recipient, proposed_max_capacity, proposed_max_message_size]`
 		HrmpChannelForceOpened(ParaId, ParaId, u32, u32),
+		/// An HRMP channel was closed via Root origin. `[channel_id]`
+		HrmpChannelForceClosed(HrmpChannelId),
+		/// A deposit was reserved for an HRMP channel. `[para, channel_id, amount]`
+		DepositReserved(ParaId, HrmpChannelId, Balance),
+		/// A deposit for an HRMP channel was returned. `[para, channel_id, amount]`
+		DepositRefunded(ParaId, HrmpChannelId, Balance),
+		/// A part of a deposit for an HRMP channel was slashed. `[para, channel_id, amount]`
+		DepositSlashed(ParaId, HrmpChannelId, Balance),
 	}
 
 	#[pallet::error]
@@ -618,6 +633,24 @@ pub mod pallet {
 			));
 			Ok(())
 		}
+
+		/// Close an HRMP channel immediately using the Root origin, e.g. when one of its
+		/// participants abuses it.
+		///
+		/// A `hrmp_force_close_slash` portion of the sender's deposit is slashed and the rest of
+		/// the deposits is returned.
+		#[pallet::call_index(8)]
+		#[pallet::weight(<T as Config>::WeightInfo::force_close_hrmp_channel())]
+		pub fn force_close_hrmp_channel(
+			origin: OriginFor<T>,
+			channel_id: HrmpChannelId,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			let config = configuration::Pallet::<T>::config();
+			Self::force_close_channel(&channel_id, config.hrmp_force_close_slash)?;
+			Self::deposit_event(Event::HrmpChannelForceClosed(channel_id));
+			Ok(())
+		}
 	}
 }
 
@@ -727,10 +760,7 @@ impl<T: Config> Pallet<T> {
 
 			// Return the deposit of the sender, but only if it is not the para being offboarded.
 			if !outgoing.contains(&req_id.sender) {
-				T::Currency::unreserve(
-					&req_id.sender.into_account_truncating(),
-					req_data.sender_deposit.unique_saturated_into(),
-				);
+				Self::refund_deposit(req_id.sender, &req_id, req_data.sender_deposit);
 			}
 
 			// If the request was confirmed, then it means it was confirmed in the finished session.
@@ -740,10 +770,7 @@ impl<T: Config> Pallet<T> {
 			// We still want to refund the deposit only if the para is not being offboarded.
 			if req_data.confirmed {
 				if !outgoing.contains(&req_id.recipient) {
-					T::Currency::unreserve(
-						&req_id.recipient.into_account_truncating(),
-						config.hrmp_recipient_deposit.unique_saturated_into(),
-					);
+					Self::refund_deposit(req_id.recipient, &req_id, config.hrmp_recipient_deposit);
 				}
 				Self::decrease_accepted_channel_request_count(req_id.recipient);
 			}
@@ -772,6 +799,21 @@ impl<T: Config> Pallet<T> {
 		}
 	}
 
+	/// Close the channels of the given offboarded para that are still open, and remove its
+	/// watermark and channel digests.
+	pub(crate) fn clean_up_offboarded_para(para: ParaId) -> Weight {
+		let ingress_count =
+			HrmpIngressChannelsIndex::<T>::decode_len(&para).unwrap_or_default() as u32;
+		let egress_count =
+			HrmpEgressChannelsIndex::<T>::decode_len(&para).unwrap_or_default() as u32;
+		Self::clean_hrmp_after_outgoing(&para);
+		HrmpWatermarks::<T>::remove(&para);
+		HrmpChannelDigests::<T>::remove(&para);
+
+		<T as Config>::WeightInfo::force_clean_hrmp(ingress_count, egress_count)
+			.saturating_add(T::DbWeight::get().writes(2))
+	}
+
 	/// Iterate over all open channel requests and:
 	///
 	/// - prune the stale requests
@@ -857,14 +899,8 @@ impl<T: Config> Pallet<T> {
 		if let Some(HrmpChannel { sender_deposit, recipient_deposit, .. }) =
 			HrmpChannels::<T>::take(channel_id)
 		{
-			T::Currency::unreserve(
-				&channel_id.sender.into_account_truncating(),
-				sender_deposit.unique_saturated_into(),
-			);
-			T::Currency::unreserve(
-				&channel_id.recipient.into_account_truncating(),
-				recipient_deposit.unique_saturated_into(),
-			);
+			Self::refund_deposit(channel_id.sender, channel_id, sender_deposit);
+			Self::refund_deposit(channel_id.recipient, channel_id, recipient_deposit);
 		}
 
 		HrmpChannelContents::<T>::remove(channel_id);
@@ -881,6 +917,58 @@ impl<T: Config> Pallet<T> {
 		});
 	}
 
+	/// Close the designated HRMP channel right away, slashing the given portion of the sender's
+	/// deposit and returning the rest of the deposits.
+	fn force_close_channel(channel_id: &HrmpChannelId, slash: Perbill) -> DispatchResult {
+		let mut channel =
+			HrmpChannels::<T>::get(channel_id).ok_or(Error::<T>::CloseHrmpChannelDoesntExist)?;
+
+		let to_slash = slash * channel.sender_deposit;
+		if !to_slash.is_zero() {
+			let (_, unslashed) = T::Currency::slash_reserved(
+				&channel_id.sender.into_account_truncating(),
+				to_slash.unique_saturated_into(),
+			);
+			let slashed = to_slash.saturating_sub(unslashed.unique_saturated_into());
+			channel.sender_deposit = channel.sender_deposit.saturating_sub(to_slash);
+			HrmpChannels::<T>::insert(channel_id, channel);
+			Self::deposit_event(Event::DepositSlashed(
+				channel_id.sender,
+				channel_id.clone(),
+				slashed,
+			));
+		}
+
+		// The channel is closed now, so a close request for it would be stale.
+		if HrmpCloseChannelRequests::<T>::take(channel_id).is_some() {
+			HrmpCloseChannelRequestsList::<T>::mutate(|list| list.retain(|id| id != channel_id));
+		}
+
+		Self::close_hrmp_channel(channel_id);
+		Ok(())
+	}
+
+	/// Reserve the deposit of the given para for the given channel.
+	fn reserve_deposit(
+		para: ParaId,
+		channel_id: &HrmpChannelId,
+		amount: Balance,
+	) -> DispatchResult {
+		T::Currency::reserve(&para.into_account_truncating(), amount.unique_saturated_into())?;
+		if !amount.is_zero() {
+			Self::deposit_event(Event::DepositReserved(para, channel_id.clone(), amount));
+		}
+		Ok(())
+	}
+
+	/// Return the deposit of the given para for the given channel.
+	fn refund_deposit(para: ParaId, channel_id: &HrmpChannelId, amount: Balance) {
+		T::Currency::unreserve(&para.into_account_truncating(), amount.unique_saturated_into());
+		if !amount.is_zero() {
+			Self::deposit_event(Event::DepositRefunded(para, channel_id.clone(), amount));
+		}
+	}
+
 	/// Check that the candidate of the given recipient controls the HRMP watermark properly.
 	pub(crate) fn check_hrmp_watermark(
 		recipient: ParaId,
@@ -1172,10 +1260,9 @@ impl<T: Config> Pallet<T> {
 			Error::<T>::OpenHrmpChannelLimitExceeded,
 		);
 
-		T::Currency::reserve(
-			&origin.into_account_truncating(),
-			config.hrmp_sender_deposit.unique_saturated_into(),
-		)?;
+		let sender_deposit =
+			config.hrmp_sender_deposit_for(proposed_max_capacity, proposed_max_message_size);
+		Self::reserve_deposit(origin, &channel_id, sender_deposit)?;
 
 		// mutating storage directly now -- shall not bail henceforth.
 
@@ -1185,7 +1272,7 @@ impl<T: Config> Pallet<T> {
 			HrmpOpenChannelRequest {
 				confirmed: false,
 				_age: 0,
-				sender_deposit: config.hrmp_sender_deposit,
+				sender_deposit,
 				max_capacity: proposed_max_capacity,
 				max_message_size: proposed_max_message_size,
 				max_total_size: config.hrmp_channel_max_total_size,
@@ -1244,10 +1331,7 @@ impl<T: Config> Pallet<T> {
 			Error::<T>::AcceptHrmpChannelLimitExceeded,
 		);
 
-		T::Currency::reserve(
-			&origin.into_account_truncating(),
-			config.hrmp_recipient_deposit.unique_saturated_into(),
-		)?;
+		Self::reserve_deposit(origin, &channel_id, config.hrmp_recipient_deposit)?;
 
 		// persist the updated open channel request and then increment the number of accepted
 		// channels.
@@ -1298,10 +1382,7 @@ impl<T: Config> Pallet<T> {
 
 		// Unreserve the sender's deposit. The recipient could not have left their deposit because
 		// we ensured that the request is not confirmed.
-		T::Currency::unreserve(
-			&channel_id.sender.into_account_truncating(),
-			open_channel_req.sender_deposit.unique_saturated_into(),
-		);
+		Self::refund_deposit(channel_id.sender, &channel_id, open_channel_req.sender_deposit);
 
 		Ok(())
 	}
@@ -70,9 +70,10 @@ where
 {
 	let config = Configuration::<T>::config();
 	let ed = T::Currency::minimum_balance();
-	let deposit: BalanceOf<T> = config.hrmp_sender_deposit.unique_saturated_into();
 	let capacity = config.hrmp_channel_max_capacity;
 	let message_size = config.hrmp_channel_max_message_size;
+	let deposit: BalanceOf<T> =
+		config.hrmp_sender_deposit_for(capacity, message_size).unique_saturated_into();
 
 	let sender: ParaId = from.into();
 	let sender_origin: crate::Origin = from.into();
@@ -149,12 +150,13 @@ frame_benchmarking::benchmarks! {
 
 		// make sure para is registered, and has enough balance.
 		let ed = T::Currency::minimum_balance();
-		let deposit: BalanceOf<T> = Configuration::<T>::config().hrmp_sender_deposit.unique_saturated_into();
-		register_parachain_with_balance::<T>(sender_id, deposit + ed);
-		register_parachain_with_balance::<T>(recipient_id, deposit + ed);
-
 		let capacity = Configuration::<T>::config().hrmp_channel_max_capacity;
 		let message_size = Configuration::<T>::config().hrmp_channel_max_message_size;
+		let deposit: BalanceOf<T> = Configuration::<T>::config()
+			.hrmp_sender_deposit_for(capacity, message_size)
+			.unique_saturated_into();
+		register_parachain_with_balance::<T>(sender_id, deposit + ed);
+		register_parachain_with_balance::<T>(recipient_id, deposit + ed);
 	}: _(sender_origin, recipient_id, capacity, message_size)
 	verify {
 		assert_last_event::<T>(
@@ -198,7 +200,9 @@ frame_benchmarking::benchmarks! {
 		Configuration::<T>::initializer_on_new_session(&Shared::<T>::scheduled_session());
 
 		let config = Configuration::<T>::config();
-		let deposit: BalanceOf<T> = config.hrmp_sender_deposit.unique_saturated_into();
+		let deposit: BalanceOf<T> = config
+			.hrmp_sender_deposit_for(config.hrmp_channel_max_capacity, config.hrmp_channel_max_message_size)
+			.unique_saturated_into();
 
 		let para: ParaId = 1u32.into();
 		let para_origin: crate::Origin = 1u32.into();
@@ -305,16 +309,16 @@ frame_benchmarking::benchmarks! {
 
 		// make sure para is registered, and has enough balance.
 		let ed = T::Currency::minimum_balance();
-		let sender_deposit: BalanceOf<T> =
-			Configuration::<T>::config().hrmp_sender_deposit.unique_saturated_into();
+		let capacity = Configuration::<T>::config().hrmp_channel_max_capacity;
+		let message_size = Configuration::<T>::config().hrmp_channel_max_message_size;
+		let sender_deposit: BalanceOf<T> = Configuration::<T>::config()
+			.hrmp_sender_deposit_for(capacity, message_size)
+			.unique_saturated_into();
 		let recipient_deposit: BalanceOf<T> =
 			Configuration::<T>::config().hrmp_recipient_deposit.unique_saturated_into();
 		register_parachain_with_balance::<T>(sender_id, sender_deposit + ed);
 		register_parachain_with_balance::<T>(recipient_id, recipient_deposit + ed);
 
-		let capacity = Configuration::<T>::config().hrmp_channel_max_capacity;
-		let message_size = Configuration::<T>::config().hrmp_channel_max_message_size;
-
 		// make sure this channel doesn't exist
 		let channel_id = HrmpChannelId { sender: sender_id, recipient: recipient_id };
 		assert!(HrmpOpenChannelRequests::<T>::get(&channel_id).is_none());
@@ -325,6 +329,24 @@ frame_benchmarking::benchmarks! {
 			Event::<T>::HrmpChannelForceOpened(sender_id, recipient_id, capacity, message_size).into()
 		);
 	}
+
+	force_close_hrmp_channel {
+		// slash a part of the sender's deposit and drop the outstanding close request.
+		assert_ok!(Configuration::<T>::set_hrmp_force_close_slash(
+			frame_system::RawOrigin::Root.into(),
+			Perbill::from_percent(50),
+		));
+		Configuration::<T>::initializer_on_new_session(&Shared::<T>::scheduled_session());
+
+		let [(sender, _), (recipient, _)] =
+			establish_para_connection::<T>(1, 2, ParachainSetupStep::CloseRequested);
+		let channel_id = HrmpChannelId { sender, recipient };
+	}: _(frame_system::Origin::<T>::Root, channel_id.clone())
+	verify {
+		assert!(HrmpChannels::<T>::get(&channel_id).is_none());
+		assert!(HrmpCloseChannelRequestsList::<T>::get().is_empty());
+		assert_last_event::<T>(Event::<T>::HrmpChannelForceClosed(channel_id).into());
+	}
 }
 
 frame_benchmarking::impl_benchmark_test_suite!(
@@ -24,6 +24,7 @@ use crate::{
 };
 use frame_support::{assert_noop, assert_ok, traits::Currency as _};
 use primitives::BlockNumber;
+use sp_runtime::DispatchError;
 use std::collections::BTreeMap;
 
 fn run_to_block(to: BlockNumber, new_session: Option<Vec<BlockNumber>>) {
@@ -296,6 +297,74 @@ fn send_recv_messages() {
 	});
 }
 
+#[test]
+fn misbehaving_outbound_messages_and_watermarks_are_rejected() {
+	use assert_matches::assert_matches;
+
+	let para_a = 32.into();
+	let para_b = 64.into();
+	let para_c = 96.into();
+
+	new_test_ext(GenesisConfigBuilder::default().build()).execute_with(|| {
+		register_parachain(para_a);
+		register_parachain(para_b);
+		register_parachain(para_c);
+
+		run_to_block(5, Some(vec![4, 5]));
+		Hrmp::init_open_channel(para_a, para_b, 2, 8).unwrap();
+		Hrmp::accept_open_channel(para_b, para_a).unwrap();
+		run_to_block(6, Some(vec![6]));
+		assert!(channel_exists(para_a, para_b));
+
+		let config = Configuration::config();
+		let msg = |recipient, data: &[u8]| OutboundHrmpMessage { recipient, data: data.to_vec() };
+
+		// More messages than permitted per candidate.
+		let msgs = vec![msg(para_b, b"a"), msg(para_b, b"b"), msg(para_b, b"c")];
+		assert_matches!(
+			Hrmp::check_outbound_hrmp(&config, para_a, &msgs),
+			Err(OutboundHrmpAcceptanceErr::MoreMessagesThanPermitted { sent: 3, permitted: 2 })
+		);
+
+		// Two messages to the same recipient.
+		let msgs = vec![msg(para_b, b"a"), msg(para_b, b"b")];
+		assert_matches!(
+			Hrmp::check_outbound_hrmp(&config, para_a, &msgs),
+			Err(OutboundHrmpAcceptanceErr::NotSorted { idx: 1 })
+		);
+
+		// A message without an open channel.
+		let msgs = vec![msg(para_c, b"a")];
+		assert_matches!(
+			Hrmp::check_outbound_hrmp(&config, para_a, &msgs),
+			Err(OutboundHrmpAcceptanceErr::NoSuchChannel { idx: 0, .. })
+		);
+
+		// A message larger than the channel allows.
+		let msgs = vec![msg(para_b, b"too large")];
+		assert_matches!(
+			Hrmp::check_outbound_hrmp(&config, para_a, &msgs),
+			Err(OutboundHrmpAcceptanceErr::MaxMessageSizeExceeded {
+				idx: 0,
+				msg_size: 9,
+				max_size: 8
+			})
+		);
+
+		// The watermark can neither be ahead of the relay parent nor land on a block without
+		// messages.
+		assert_matches!(
+			Hrmp::check_hrmp_watermark(para_b, 6, 7),
+			Err(HrmpWatermarkAcceptanceErr::AheadRelayParent { .. })
+		);
+		assert_matches!(
+			Hrmp::check_hrmp_watermark(para_b, 6, 5),
+			Err(HrmpWatermarkAcceptanceErr::LandsOnBlockWithNoMessages { new_watermark: 5 })
+		);
+		assert!(Hrmp::check_hrmp_watermark(para_b, 6, 6).is_ok());
+	});
+}
+
 #[test]
 fn hrmp_mqc_head_fixture() {
 	let para_a = 2000.into();
@@ -548,6 +617,105 @@ fn refund_deposit_on_normal_closure() {
 	});
 }
 
+#[test]
+fn sender_deposit_is_priced_by_capacity() {
+	let para_a = 32.into();
+	let para_b = 64.into();
+	let channel_id = HrmpChannelId { sender: para_a, recipient: para_b };
+
+	let mut genesis = GenesisConfigBuilder::default();
+	genesis.hrmp_sender_deposit = 20;
+	genesis.hrmp_recipient_deposit = 15;
+	let mut genesis = genesis.build();
+	genesis.configuration.config.hrmp_sender_deposit_per_message = 5;
+	genesis.configuration.config.hrmp_sender_deposit_per_byte = 1;
+	new_test_ext(genesis).execute_with(|| {
+		register_parachain_with_balance(para_a, 100);
+		register_parachain_with_balance(para_b, 110);
+		run_to_block(5, Some(vec![4, 5]));
+
+		// 20 as a base, 5 for each of the 2 messages and 1 for each of the 2 * 8 bytes.
+		Hrmp::init_open_channel(para_a, para_b, 2, 8).unwrap();
+		Hrmp::accept_open_channel(para_b, para_a).unwrap();
+		assert_eq!(<Test as Config>::Currency::free_balance(&para_a.into_account_truncating()), 54);
+		assert_eq!(<Test as Config>::Currency::free_balance(&para_b.into_account_truncating()), 95);
+		assert!(System::events().iter().any(|record| record.event ==
+			MockEvent::Hrmp(Event::DepositReserved(para_a, channel_id.clone(), 46))));
+		assert!(System::events().iter().any(|record| record.event ==
+			MockEvent::Hrmp(Event::DepositReserved(para_b, channel_id.clone(), 15))));
+		run_to_block(8, Some(vec![8]));
+
+		// Closing the channel returns the deposits in full.
+		Hrmp::close_channel(para_a, channel_id.clone()).unwrap();
+		run_to_block(10, Some(vec![10]));
+		assert_eq!(
+			<Test as Config>::Currency::free_balance(&para_a.into_account_truncating()),
+			100
+		);
+		assert_eq!(
+			<Test as Config>::Currency::free_balance(&para_b.into_account_truncating()),
+			110
+		);
+		assert!(System::events().iter().any(|record| record.event ==
+			MockEvent::Hrmp(Event::DepositRefunded(para_a, channel_id.clone(), 46))));
+		assert!(System::events().iter().any(|record| record.event ==
+			MockEvent::Hrmp(Event::DepositRefunded(para_b, channel_id.clone(), 15))));
+	});
+}
+
+#[test]
+fn force_close_channel_slashes_sender_deposit() {
+	let para_a = 32.into();
+	let para_b = 64.into();
+	let channel_id = HrmpChannelId { sender: para_a, recipient: para_b };
+
+	let mut genesis = GenesisConfigBuilder::default();
+	genesis.hrmp_sender_deposit = 20;
+	genesis.hrmp_recipient_deposit = 15;
+	let mut genesis = genesis.build();
+	genesis.configuration.config.hrmp_force_close_slash = Perbill::from_percent(50);
+	new_test_ext(genesis).execute_with(|| {
+		register_parachain_with_balance(para_a, 100);
+		register_parachain_with_balance(para_b, 110);
+		run_to_block(5, Some(vec![4, 5]));
+		Hrmp::init_open_channel(para_a, para_b, 2, 8).unwrap();
+		Hrmp::accept_open_channel(para_b, para_a).unwrap();
+		run_to_block(8, Some(vec![8]));
+
+		assert_noop!(
+			Hrmp::force_close_hrmp_channel(RuntimeOrigin::signed(1), channel_id.clone()),
+			DispatchError::BadOrigin,
+		);
+		assert_noop!(
+			Hrmp::force_close_hrmp_channel(
+				RuntimeOrigin::root(),
+				HrmpChannelId { sender: para_b, recipient: para_a },
+			),
+			Error::<Test>::CloseHrmpChannelDoesntExist,
+		);
+
+		// An outstanding close request is dropped along with the channel.
+		Hrmp::close_channel(para_b, channel_id.clone()).unwrap();
+		Hrmp::force_close_hrmp_channel(RuntimeOrigin::root(), channel_id.clone()).unwrap();
+		assert!(!channel_exists(para_a, para_b));
+		assert!(HrmpCloseChannelRequestsList::<Test>::get().is_empty());
+		Hrmp::assert_storage_consistency_exhaustive();
+
+		// Half of the sender's deposit is gone, the recipient's is returned in full.
+		assert_eq!(<Test as Config>::Currency::free_balance(&para_a.into_account_truncating()), 90);
+		assert_eq!(
+			<Test as Config>::Currency::free_balance(&para_b.into_account_truncating()),
+			110
+		);
+		assert!(System::events().iter().any(|record| record.event ==
+			MockEvent::Hrmp(Event::DepositSlashed(para_a, channel_id.clone(), 10))));
+		assert!(System::events().iter().any(|record| record.event ==
+			MockEvent::Hrmp(Event::DepositRefunded(para_a, channel_id.clone(), 10))));
+		assert!(System::events().iter().any(|record| record.event ==
+			MockEvent::Hrmp(Event::HrmpChannelForceClosed(channel_id.clone()))));
+	});
+}
+
 #[test]
 fn refund_deposit_on_offboarding() {
 	let para_a = 32.into();
@@ -0,0 +1,135 @@
+// Copyright (C) Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::*;
+use crate::{builder::BenchBuilder, paras_inherent};
+use frame_benchmarking::{benchmarks, impl_benchmark_test_suite, BenchmarkError};
+use frame_system::RawOrigin;
+use sp_runtime::traits::Header as HeaderT;
+use sp_std::collections::btree_map::BTreeMap;
+
+/// Back a candidate on each of the first `cores` cores with votes from the whole backing group.
+fn cores_with_full_backing<T: paras_inherent::Config>(cores: u32) -> BTreeMap<u32, u32> {
+	let votes = BenchBuilder::<T>::fallback_max_validators_per_core();
+	(0..cores).map(|core| (core, votes)).collect()
+}
+
+benchmarks! {
+	where_clause {
+		where T: paras_inherent::Config,
+	}
+
+	// Variant over `c`, the number of occupied cores. Every active validator submits a bitfield
+	// marking all of them available, so that the candidate on each core is enacted.
+	process_bitfields {
+		let c in 1..BenchBuilder::<T>::new().max_cores();
+
+		let scenario = BenchBuilder::<T>::new()
+			.set_backed_and_concluding_cores(cores_with_full_backing::<T>(c))
+			.build();
+
+		let expected_bits = <scheduler::Pallet<T>>::availability_cores().len();
+		let bitfields = scenario.data.bitfields;
+		assert_eq!(bitfields.len(), shared::Pallet::<T>::active_validator_keys().len());
+	}: {
+		Pallet::<T>::process_bitfields(
+			expected_bits,
+			bitfields,
+			DisputedBitfield::zeros(expected_bits),
+			<scheduler::Pallet<T>>::core_para,
+			FullCheck::Yes,
+		)
+		.map_err(|_| BenchmarkError::Stop("availability bitfields were rejected"))?;
+	}
+	verify {
+		assert_eq!(PendingAvailability::<T>::iter().count(), 0);
+	}
+
+	// Variant over `c`, the number of backed candidates, each on its own core and carrying a
+	// validity vote from every member of its backing group.
+	process_candidates {
+		let c in 1..BenchBuilder::<T>::new().max_cores();
+
+		let scenario = BenchBuilder::<T>::new()
+			.set_backed_and_concluding_cores(cores_with_full_backing::<T>(c))
+			.build();
+
+		// Free the cores the way the paras inherent does before candidates are backed on them.
+		let expected_bits = <scheduler::Pallet<T>>::availability_cores().len();
+		let (freed, _) = Pallet::<T>::process_bitfields(
+			expected_bits,
+			scenario.data.bitfields,
+			DisputedBitfield::zeros(expected_bits),
+			<scheduler::Pallet<T>>::core_para,
+			FullCheck::Yes,
+		)
+		.map_err(|_| BenchmarkError::Stop("availability bitfields were rejected"))?;
+
+		let now = frame_system::Pallet::<T>::block_number();
+		<scheduler::Pallet<T>>::clear();
+		<scheduler::Pallet<T>>::schedule(
+			freed.into_iter().map(|(core, _)| (core, FreedReason::Concluded)),
+			now,
+		);
+
+		let parent_storage_root = *scenario.data.parent_header.state_root();
+		<shared::Pallet<T>>::add_allowed_relay_parent(
+			frame_system::Pallet::<T>::parent_hash(),
+			parent_storage_root,
+			now - One::one(),
+			<configuration::Pallet<T>>::config().max_candidate_relay_parent_depth,
+		);
+
+		let backed_candidates = scenario.data.backed_candidates;
+		assert_eq!(backed_candidates.len(), c as usize);
+		let scheduled = <scheduler::Pallet<T>>::scheduled();
+	}: {
+		Pallet::<T>::process_candidates(
+			parent_storage_root,
+			backed_candidates,
+			scheduled,
+			<scheduler::Pallet<T>>::group_validators,
+		)?;
+	}
+	verify {
+		assert_eq!(PendingAvailability::<T>::iter().count(), c as usize);
+	}
+
+	force_clear_pending {
+		let _ = BenchBuilder::<T>::new()
+			.set_backed_and_concluding_cores(cores_with_full_backing::<T>(1))
+			.build();
+
+		let para = ParaId::from(0u32);
+		assert!(PendingAvailability::<T>::iter_key_prefix(&para).next().is_some());
+	}: _(RawOrigin::Root, para)
+	verify {
+		assert!(PendingAvailability::<T>::iter_key_prefix(&para).next().is_none());
+	}
+
+	disallow_candidate {
+		let candidate_hash = CandidateHash(Default::default());
+	}: _(RawOrigin::Root, candidate_hash)
+	verify {
+		assert!(Pallet::<T>::is_disallowed(&candidate_hash));
+	}
+}
+
+impl_benchmark_test_suite!(
+	Pallet,
+	crate::mock::new_test_ext(Default::default()),
+	crate::mock::Test
+);
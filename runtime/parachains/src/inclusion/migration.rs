@@ -0,0 +1,255 @@
+// Copyright (C) Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Storage migration(s) related to the inclusion pallet.
+
+use frame_support::traits::StorageVersion;
+
+/// The current storage version.
+pub const STORAGE_VERSION: StorageVersion = StorageVersion::new(2);
+
+pub mod v1 {
+	use super::*;
+	use crate::inclusion::{
+		Config, Pallet, PendingAvailabilityCommitments, PendingCommitmentsRefs, LOG_TARGET,
+	};
+	use bitvec::{order::Lsb0 as BitOrderLsb0, vec::BitVec};
+	use frame_support::{
+		pallet_prelude::*, storage_alias, traits::OnRuntimeUpgrade, weights::Weight,
+	};
+	use primitives::{
+		CandidateCommitments, CandidateDescriptor, CandidateHash, CoreIndex, GroupIndex, Hash,
+		Id as ParaId,
+	};
+	use sp_std::{collections::btree_map::BTreeMap, prelude::*};
+
+	/// A backed candidate pending availability, as stored up to version 1.
+	#[derive(Encode, Decode)]
+	pub struct CandidatePendingAvailability<H, N> {
+		pub(in crate::inclusion) core: CoreIndex,
+		pub(in crate::inclusion) hash: CandidateHash,
+		pub(in crate::inclusion) descriptor: CandidateDescriptor<H>,
+		pub(in crate::inclusion) availability_votes: BitVec<u8, BitOrderLsb0>,
+		pub(in crate::inclusion) backers: BitVec<u8, BitOrderLsb0>,
+		pub(in crate::inclusion) relay_parent_number: N,
+		pub(in crate::inclusion) backed_in_number: N,
+		pub(in crate::inclusion) backing_group: GroupIndex,
+	}
+
+	#[storage_alias]
+	pub(super) type PendingAvailability<T: Config> = StorageDoubleMap<
+		Pallet<T>,
+		Twox64Concat,
+		ParaId,
+		Twox64Concat,
+		CoreIndex,
+		CandidatePendingAvailability<
+			<T as frame_system::Config>::Hash,
+			<T as frame_system::Config>::BlockNumber,
+		>,
+	>;
+
+	/// The storage items of version 0, keyed by para only.
+	mod v0 {
+		use super::*;
+
+		#[storage_alias]
+		pub(super) type PendingAvailability<T: Config> = StorageMap<
+			Pallet<T>,
+			Twox64Concat,
+			ParaId,
+			CandidatePendingAvailability<
+				<T as frame_system::Config>::Hash,
+				<T as frame_system::Config>::BlockNumber,
+			>,
+		>;
+
+		#[storage_alias]
+		pub(super) type PendingAvailabilityCommitments<T: Config> =
+			StorageMap<Pallet<T>, Twox64Concat, ParaId, CandidateCommitments>;
+
+		#[storage_alias]
+		pub(super) type PendingCommitmentsRefs<T: Config> =
+			StorageMap<Pallet<T>, Twox64Concat, ParaId, (Hash, Option<Hash>)>;
+	}
+
+	/// Keys the candidates pending availability, their commitments and the references to their
+	/// commitment blobs by the core they occupy, on top of their para.
+	pub struct MigrateToV1<T>(sp_std::marker::PhantomData<T>);
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+		fn on_runtime_upgrade() -> Weight {
+			let mut weight: Weight = Weight::zero();
+
+			if StorageVersion::get::<Pallet<T>>() < 1 {
+				log::info!(target: LOG_TARGET, "Migrating inclusion storage to v1");
+				weight += migrate_to_v1::<T>();
+				StorageVersion::new(1).put::<Pallet<T>>();
+				weight = weight.saturating_add(T::DbWeight::get().reads_writes(1, 1));
+			} else {
+				log::info!(target: LOG_TARGET, "Inclusion storage up to date - no need for migration");
+			}
+
+			weight
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, &'static str> {
+			ensure!(
+				StorageVersion::get::<Pallet<T>>() == 0,
+				"Storage version should be less than `1` before the migration",
+			);
+			Ok((v0::PendingAvailability::<T>::iter().count() as u32).encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), &'static str> {
+			ensure!(
+				StorageVersion::get::<Pallet<T>>() >= 1,
+				"Storage version should be at least `1` after the migration"
+			);
+			let pending = u32::decode(&mut &state[..]).map_err(|_| "Invalid migration state")?;
+			ensure!(
+				PendingAvailability::<T>::iter().count() == pending as usize,
+				"Candidates pending availability should be kept by the migration"
+			);
+			Ok(())
+		}
+	}
+
+	/// Migrates the pallet storage from version 0 to version 1, without checking or setting the
+	/// `StorageVersion`.
+	pub fn migrate_to_v1<T: Config>() -> Weight {
+		// Everything is read before anything is written, as the new keys extend the old ones.
+		let pending = v0::PendingAvailability::<T>::drain().collect::<Vec<_>>();
+		let mut commitments =
+			v0::PendingAvailabilityCommitments::<T>::drain().collect::<BTreeMap<_, _>>();
+		let mut refs = v0::PendingCommitmentsRefs::<T>::drain().collect::<BTreeMap<_, _>>();
+		let entries = (pending.len() + commitments.len() + refs.len()) as u64;
+
+		for (para_id, candidate) in pending {
+			let core = candidate.core;
+			if let Some(candidate_commitments) = commitments.remove(&para_id) {
+				PendingAvailabilityCommitments::<T>::insert(&para_id, core, candidate_commitments);
+			}
+			if let Some(candidate_refs) = refs.remove(&para_id) {
+				PendingCommitmentsRefs::<T>::insert(&para_id, core, candidate_refs);
+			}
+			PendingAvailability::<T>::insert(&para_id, core, candidate);
+		}
+
+		// Commitments without a candidate pending availability have no core to be stored under.
+		// They are dropped, along with their references to the blobs.
+		let mut blobs = 0;
+		for (head_data, validation_code) in refs.into_values() {
+			for hash in sp_std::iter::once(head_data).chain(validation_code) {
+				let _ = Pallet::<T>::take_commitments_blob(&hash);
+				blobs += 1;
+			}
+		}
+
+		T::DbWeight::get().reads_writes(entries + blobs, 2 * entries + blobs)
+	}
+}
+
+pub mod v2 {
+	use super::*;
+	use crate::{
+		inclusion::{
+			CandidatePendingAvailability, Config, Pallet, PendingAvailability, LOG_TARGET,
+		},
+		scheduler::{self, OnDemandOrders},
+	};
+	use frame_support::{pallet_prelude::*, traits::OnRuntimeUpgrade, weights::Weight};
+	use primitives::{vstaging::CoreAssignmentSource, CoreOccupied};
+	use sp_std::prelude::*;
+
+	/// Records where the assignment of the core each candidate pending availability occupies came
+	/// from.
+	pub struct MigrateToV2<T>(sp_std::marker::PhantomData<T>);
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV2<T> {
+		fn on_runtime_upgrade() -> Weight {
+			let mut weight: Weight = Weight::zero();
+
+			if StorageVersion::get::<Pallet<T>>() == 1 {
+				log::info!(target: LOG_TARGET, "Migrating inclusion storage to v2");
+				weight += migrate_to_v2::<T>();
+				STORAGE_VERSION.put::<Pallet<T>>();
+				weight = weight.saturating_add(T::DbWeight::get().reads_writes(1, 1));
+			} else {
+				log::info!(target: LOG_TARGET, "Inclusion storage up to date - no need for migration");
+			}
+
+			weight
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, &'static str> {
+			ensure!(
+				StorageVersion::get::<Pallet<T>>() < STORAGE_VERSION,
+				"Storage version should be less than `2` before the migration",
+			);
+			Ok(Vec::new())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(_state: Vec<u8>) -> Result<(), &'static str> {
+			ensure!(
+				StorageVersion::get::<Pallet<T>>() == STORAGE_VERSION,
+				"Storage version should be `2` after the migration"
+			);
+			Ok(())
+		}
+	}
+
+	/// Migrates the pallet storage from version 1 to version 2, without checking or setting the
+	/// `StorageVersion`.
+	///
+	/// Candidates on a core occupied by a parachain were assigned by its lease. All other
+	/// candidates were assigned by a parathread claim, or by an on-demand order if the para still
+	/// has one in the scheduler.
+	pub fn migrate_to_v2<T: Config>() -> Weight {
+		let cores = scheduler::AvailabilityCores::<T>::get();
+		let mut entries = 0u64;
+
+		PendingAvailability::<T>::translate::<super::v1::CandidatePendingAvailability<_, _>, _>(
+			|para_id, _, candidate| {
+				entries += 1;
+				let assignment_source = match cores.get(candidate.core.0 as usize) {
+					Some(Some(CoreOccupied::Parachain)) => CoreAssignmentSource::Lease,
+					_ if OnDemandOrders::<T>::contains_key(&para_id) =>
+						CoreAssignmentSource::OnDemandOrder,
+					_ => CoreAssignmentSource::ParathreadClaim,
+				};
+
+				Some(CandidatePendingAvailability {
+					core: candidate.core,
+					hash: candidate.hash,
+					descriptor: candidate.descriptor,
+					// Candidates of validator sets above `MaxValidators` are dropped, and their
+					// commitments are removed as out of sync on finalization.
+					availability_votes: candidate.availability_votes.try_into().ok()?,
+					backers: candidate.backers.try_into().ok()?,
+					relay_parent_number: candidate.relay_parent_number,
+					backed_in_number: candidate.backed_in_number,
+					backing_group: candidate.backing_group,
+					assignment_source,
+				})
+			},
+		);
+
+		T::DbWeight::get().reads_writes(1 + 2 * entries, entries)
+	}
+}
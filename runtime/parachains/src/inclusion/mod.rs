@@ -21,39 +21,130 @@
 //! to included.
 
 use crate::{
-	configuration, disputes, dmp, hrmp, paras, paras_inherent::DisputedBitfield,
-	scheduler::CoreAssignment, shared, ump,
+	configuration, disputes, dmp, hrmp, paras,
+	paras_inherent::{DisputedBitfield, IndexedRetain},
+	scheduler::{self, CoreAssignment, FreedReason},
+	session_info, shared, statistics, ump,
+	util::BoundedBitVec,
 };
-use bitvec::{order::Lsb0 as BitOrderLsb0, vec::BitVec};
-use frame_support::pallet_prelude::*;
-use parity_scale_codec::{Decode, Encode};
+use bitvec::{order::Lsb0 as BitOrderLsb0, slice::BitSlice, vec::BitVec};
+use frame_support::{
+	pallet_prelude::*,
+	storage::{with_transaction, TransactionOutcome, TryAppendMap},
+};
+use frame_system::pallet_prelude::*;
+use parity_scale_codec::{Decode, Encode, MaxEncodedLen};
 use primitives::{
-	supermajority_threshold, AvailabilityBitfield, BackedCandidate, CandidateCommitments,
-	CandidateDescriptor, CandidateHash, CandidateReceipt, CommittedCandidateReceipt, CoreIndex,
-	GroupIndex, Hash, HeadData, Id as ParaId, SigningContext, UncheckedSignedAvailabilityBitfields,
-	ValidatorId, ValidatorIndex, ValidityAttestation,
+	vstaging::{
+		check_candidate_outputs, check_candidate_receipt, core_layout_hash,
+		expand_signed_bitfields, BitfieldSigningParent, CandidateCheckError, CandidateCheckParams,
+		CandidateDescriptorV2Ext, CandidateDescriptorVersion, CoreAssignmentSource,
+		CoreAvailabilityVotes, CoreBitfieldLayout, IncludedCandidate, InclusionDiagnostics,
+		UncheckedSignedCompactAvailabilityBitfields, UncheckedSignedNoncedAvailabilityBitfields,
+	},
+	AvailabilityBitfield, BackedCandidate, CandidateCommitments, CandidateDescriptor,
+	CandidateHash, CandidateReceipt, CommittedCandidateReceipt, CoreIndex, GroupIndex, Hash,
+	HeadData, Id as ParaId, SessionIndex, SigningContext, UncheckedSignedAvailabilityBitfield,
+	UncheckedSignedAvailabilityBitfields, ValidationCode, ValidatorId, ValidatorIndex,
+	ValidityAttestation,
 };
 use scale_info::TypeInfo;
-use sp_runtime::{traits::One, DispatchError};
-use sp_std::{collections::btree_set::BTreeSet, prelude::*};
+use sp_runtime::{
+	traits::{BlakeTwo256, Hash as HashT, One, Saturating, Zero},
+	DispatchError,
+};
+use sp_std::{
+	collections::{btree_map::BTreeMap, btree_set::BTreeSet},
+	prelude::*,
+};
 
 pub use pallet::*;
 
+pub mod migration;
+
 #[cfg(test)]
 pub(crate) mod tests;
 
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+
+pub trait WeightInfo {
+	/// Variant over `c`, the number of cores whose candidates are made available by a bitfield from
+	/// every active validator and enacted.
+	///
+	/// Its proof size, which covers the validator set, the host configuration and the candidates
+	/// pending availability, is charged by the paras inherent.
+	fn process_bitfields(c: u32) -> Weight;
+	/// Variant over `c`, the number of backed candidates with a validity vote from every member of
+	/// their backing group.
+	///
+	/// Its proof size, which covers the validator groups, the host configuration and the state of
+	/// the paras the candidates are checked against, is charged by the paras inherent.
+	fn process_candidates(c: u32) -> Weight;
+	fn force_clear_pending() -> Weight;
+	fn disallow_candidate() -> Weight;
+}
+
+/// fallback implementation
+pub struct TestWeightInfo;
+impl WeightInfo for TestWeightInfo {
+	// Charged by the paras inherent, whose test weights are based off of the `max_block` weight for
+	// the mock.
+	fn process_bitfields(_c: u32) -> Weight {
+		Weight::zero()
+	}
+
+	fn process_candidates(_c: u32) -> Weight {
+		Weight::zero()
+	}
+
+	fn force_clear_pending() -> Weight {
+		Weight::MAX
+	}
+
+	fn disallow_candidate() -> Weight {
+		Weight::MAX
+	}
+}
+
 /// A bitfield signed by a validator indicating that it is keeping its piece of the erasure-coding
 /// for any backed candidates referred to by a `1` bit available.
 ///
 /// The bitfield's signature should be checked at the point of submission. Afterwards it can be
 /// dropped.
-#[derive(Encode, Decode, TypeInfo)]
+#[derive(Encode, Decode, TypeInfo, MaxEncodedLen)]
 #[cfg_attr(test, derive(Debug))]
-pub struct AvailabilityBitfieldRecord<N> {
-	bitfield: AvailabilityBitfield, // one bit per core.
-	submitted_at: N,                // for accounting, as meaning of bits may change over time.
+#[scale_info(skip_type_params(S))]
+pub struct AvailabilityBitfieldRecord<N, S> {
+	bitfield: BoundedBitVec<S>, // one bit per core, for at most `S` cores.
+	submitted_at: N,            // for accounting, as meaning of bits may change over time.
+	core_layout_version: u32,   // the core layout the bits refer to.
 }
 
+/// The range of validator indices whose bitfields and bitfield nonces may still be left over from
+/// a previous session, and are swept a few at a time after the session change.
+#[derive(Encode, Decode, TypeInfo, MaxEncodedLen, Clone, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub struct StaleEntriesCursor<N> {
+	/// The next validator index to sweep.
+	next: u32,
+	/// One past the last validator index to sweep.
+	end: u32,
+	/// The first block of the current session. Bitfields submitted before it are stale.
+	session_start: N,
+}
+
+/// The maximum number of validator indices whose stale entries are swept in a single block.
+pub const MAX_STALE_ENTRIES_SWEPT_PER_BLOCK: u32 = 64;
+
+/// The storage reads and writes of noting a new para head when enacting a candidate.
+pub const ENACT_HEAD_NOTE_DB_OPS: (u64, u64) = (4, 4);
+/// The storage reads and writes of scheduling the code upgrade of a candidate when enacting it.
+pub const ENACT_UPGRADE_SCHEDULE_DB_OPS: (u64, u64) = (6, 7);
+/// The storage reads and writes of enacting the message queue commitments of a candidate: pruning
+/// the downward and inbound HRMP queues and queueing the upward and outbound HRMP messages.
+pub const ENACT_MESSAGE_QUEUES_DB_OPS: (u64, u64) = (10, 8);
+
 /// Determines if all checks should be applied or if a subset was already completed
 /// in a code path that will be executed afterwards or was already executed before.
 #[derive(Clone, Copy, Encode, Decode, PartialEq, Eq, RuntimeDebug, TypeInfo)]
@@ -67,10 +158,11 @@ pub(crate) enum FullCheck {
 	Skip,
 }
 
-/// A backed candidate pending availability.
-#[derive(Encode, Decode, PartialEq, TypeInfo)]
+/// A backed candidate pending availability, in a validator set of at most `S` validators.
+#[derive(Encode, Decode, TypeInfo, MaxEncodedLen)]
 #[cfg_attr(test, derive(Debug))]
-pub struct CandidatePendingAvailability<H, N> {
+#[scale_info(skip_type_params(S))]
+pub struct CandidatePendingAvailability<H, N, S> {
 	/// The availability core this is assigned to.
 	core: CoreIndex,
 	/// The candidate hash.
@@ -78,20 +170,37 @@ pub struct CandidatePendingAvailability<H, N> {
 	/// The candidate descriptor.
 	descriptor: CandidateDescriptor<H>,
 	/// The received availability votes. One bit per validator.
-	availability_votes: BitVec<u8, BitOrderLsb0>,
+	availability_votes: BoundedBitVec<S>,
 	/// The backers of the candidate pending availability.
-	backers: BitVec<u8, BitOrderLsb0>,
+	backers: BoundedBitVec<S>,
 	/// The block number of the relay-parent of the receipt.
 	relay_parent_number: N,
 	/// The block number of the relay-chain block this was backed in.
 	backed_in_number: N,
 	/// The group index backing this block.
 	backing_group: GroupIndex,
+	/// Where the assignment of the core to the para came from.
+	assignment_source: CoreAssignmentSource,
+}
+
+// Not derived, as that would require `S: PartialEq`.
+impl<H: PartialEq, N: PartialEq, S> PartialEq for CandidatePendingAvailability<H, N, S> {
+	fn eq(&self, other: &Self) -> bool {
+		self.core == other.core &&
+			self.hash == other.hash &&
+			self.descriptor == other.descriptor &&
+			self.availability_votes == other.availability_votes &&
+			self.backers == other.backers &&
+			self.relay_parent_number == other.relay_parent_number &&
+			self.backed_in_number == other.backed_in_number &&
+			self.backing_group == other.backing_group &&
+			self.assignment_source == other.assignment_source
+	}
 }
 
-impl<H, N> CandidatePendingAvailability<H, N> {
+impl<H, N, S: Get<u32>> CandidatePendingAvailability<H, N, S> {
 	/// Get the availability votes on the candidate.
-	pub(crate) fn availability_votes(&self) -> &BitVec<u8, BitOrderLsb0> {
+	pub(crate) fn availability_votes(&self) -> &BitSlice<u8, BitOrderLsb0> {
 		&self.availability_votes
 	}
 
@@ -115,7 +224,7 @@ impl<H, N> CandidatePendingAvailability<H, N> {
 		&self.descriptor
 	}
 
-	#[cfg(any(feature = "runtime-benchmarks", test))]
+	#[cfg(any(feature = "runtime-benchmarks", feature = "testing", test))]
 	pub(crate) fn new(
 		core: CoreIndex,
 		hash: CandidateHash,
@@ -125,16 +234,18 @@ impl<H, N> CandidatePendingAvailability<H, N> {
 		relay_parent_number: N,
 		backed_in_number: N,
 		backing_group: GroupIndex,
+		assignment_source: CoreAssignmentSource,
 	) -> Self {
 		Self {
 			core,
 			hash,
 			descriptor,
-			availability_votes,
-			backers,
+			availability_votes: availability_votes.try_into().expect("too many validators"),
+			backers: backers.try_into().expect("too many validators"),
 			relay_parent_number,
 			backed_in_number,
 			backing_group,
+			assignment_source,
 		}
 	}
 }
@@ -149,6 +260,100 @@ pub trait RewardValidators {
 	fn reward_bitfields(validators: impl IntoIterator<Item = ValidatorIndex>);
 }
 
+/// A hook for punishing validators who seconded more than one candidate at the same relay parent.
+pub trait BackingEquivocationHandler {
+	/// Punish the given validators for seconding `candidate_hash` after having already seconded a
+	/// different candidate at the same relay parent.
+	fn punish_backing_equivocation(
+		session_index: SessionIndex,
+		candidate_hash: CandidateHash,
+		offenders: impl IntoIterator<Item = ValidatorIndex>,
+	);
+}
+
+impl BackingEquivocationHandler for () {
+	fn punish_backing_equivocation(
+		_: SessionIndex,
+		_: CandidateHash,
+		_: impl IntoIterator<Item = ValidatorIndex>,
+	) {
+	}
+}
+
+/// A hook for punishing validators who did not submit a single availability bitfield during a
+/// session in which cores were occupied.
+pub trait AvailabilityNoShowHandler {
+	/// Punish the given validators for being availability no-shows in the given session.
+	/// `repeated` is set if they were no-shows for at least
+	/// `config.availability_no_show_slash_threshold` consecutive sessions, and should be slashed.
+	fn punish_availability_no_shows(
+		session_index: SessionIndex,
+		offenders: impl IntoIterator<Item = ValidatorIndex>,
+		repeated: bool,
+	);
+}
+
+impl AvailabilityNoShowHandler for () {
+	fn punish_availability_no_shows(
+		_: SessionIndex,
+		_: impl IntoIterator<Item = ValidatorIndex>,
+		_: bool,
+	) {
+	}
+}
+
+/// Hooks notified of candidates moving through the inclusion pallet, e.g. for collecting metrics
+/// without parsing events. Every hook does nothing by default.
+///
+/// Implemented for tuples, whose members are notified in order.
+pub trait InclusionHooks {
+	/// A candidate of the given para is about to be backed on the given core, e.g. for taking a
+	/// deposit. An error rejects all candidates of the block, none of which is kept in storage.
+	fn on_candidate_backing(
+		_para_id: ParaId,
+		_candidate_hash: CandidateHash,
+		_core: CoreIndex,
+	) -> DispatchResult {
+		Ok(())
+	}
+	/// A candidate of the given para was backed on the given core.
+	fn on_candidate_backed(_para_id: ParaId, _candidate_hash: CandidateHash, _core: CoreIndex) {}
+	/// A candidate of the given para became available and was enacted.
+	fn on_candidate_included(_para_id: ParaId, _candidate_hash: CandidateHash, _core: CoreIndex) {}
+	/// A candidate of the given para was removed from its core without becoming available.
+	fn on_candidate_timed_out(_para_id: ParaId, _candidate_hash: CandidateHash, _core: CoreIndex) {}
+	/// The given number of availability bitfields passed the checks of the block.
+	fn on_bitfields_processed(_count: u32) {}
+}
+
+#[impl_trait_for_tuples::impl_for_tuples(30)]
+impl InclusionHooks for Tuple {
+	fn on_candidate_backing(
+		para_id: ParaId,
+		candidate_hash: CandidateHash,
+		core: CoreIndex,
+	) -> DispatchResult {
+		for_tuples!( #( Tuple::on_candidate_backing(para_id, candidate_hash, core)?; )* );
+		Ok(())
+	}
+
+	fn on_candidate_backed(para_id: ParaId, candidate_hash: CandidateHash, core: CoreIndex) {
+		for_tuples!( #( Tuple::on_candidate_backed(para_id, candidate_hash, core); )* );
+	}
+
+	fn on_candidate_included(para_id: ParaId, candidate_hash: CandidateHash, core: CoreIndex) {
+		for_tuples!( #( Tuple::on_candidate_included(para_id, candidate_hash, core); )* );
+	}
+
+	fn on_candidate_timed_out(para_id: ParaId, candidate_hash: CandidateHash, core: CoreIndex) {
+		for_tuples!( #( Tuple::on_candidate_timed_out(para_id, candidate_hash, core); )* );
+	}
+
+	fn on_bitfields_processed(count: u32) {
+		for_tuples!( #( Tuple::on_bitfields_processed(count); )* );
+	}
+}
+
 /// Helper return type for `process_candidates`.
 #[derive(Encode, Decode, PartialEq, TypeInfo)]
 #[cfg_attr(test, derive(Debug))]
@@ -167,16 +372,17 @@ impl<H> Default for ProcessedCandidates<H> {
 	}
 }
 
-/// Number of backing votes we need for a valid backing.
+/// Number of backing votes we need for a valid backing, given the configured
+/// `minimum_backing_votes`. Groups smaller than that need a vote from every member.
 ///
-/// WARNING: This check has to be kept in sync with the node side check in the backing
-/// subsystem.
-pub fn minimum_backing_votes(n_validators: usize) -> usize {
+/// The backing subsystem of nodes learns it through the `minimum_backing_votes` runtime API,
+/// falling back to a copy of the legacy check for runtimes that don't support it.
+pub fn minimum_backing_votes(n_validators: usize, configured_minimum: u32) -> usize {
 	// For considerations on this value see:
 	// https://github.com/paritytech/polkadot/pull/1656#issuecomment-999734650
 	// and
 	// https://github.com/paritytech/polkadot/issues/4386
-	sp_std::cmp::min(n_validators, 2)
+	sp_std::cmp::min(n_validators, configured_minimum as usize)
 }
 
 #[frame_support::pallet]
@@ -184,7 +390,7 @@ pub mod pallet {
 	use super::*;
 
 	#[pallet::pallet]
-	#[pallet::without_storage_info]
+	#[pallet::storage_version(migration::STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
 	#[pallet::config]
@@ -196,10 +402,29 @@ pub mod pallet {
 		+ ump::Config
 		+ hrmp::Config
 		+ configuration::Config
+		+ scheduler::Config
+		+ session_info::Config
+		+ statistics::Config
 	{
 		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
 		type DisputesHandler: disputes::DisputesHandler<Self::BlockNumber>;
 		type RewardValidators: RewardValidators;
+		type BackingEquivocationHandler: BackingEquivocationHandler;
+		type AvailabilityNoShowHandler: AvailabilityNoShowHandler;
+		type InclusionHooks: InclusionHooks;
+
+		/// The maximum number of active validators in a session, bounding the per-validator
+		/// bitvecs and lists kept in storage.
+		#[pallet::constant]
+		type MaxValidators: Get<u32>;
+
+		/// The maximum number of availability cores, bounding the availability bitfields and the
+		/// per-core lists kept in storage.
+		#[pallet::constant]
+		type MaxAvailabilityCores: Get<u32>;
+
+		/// Weight information for extrinsics and block processing in this pallet.
+		type WeightInfo: WeightInfo;
 	}
 
 	#[pallet::event]
@@ -207,10 +432,28 @@ pub mod pallet {
 	pub enum Event<T: Config> {
 		/// A candidate was backed. `[candidate, head_data]`
 		CandidateBacked(CandidateReceipt<T::Hash>, HeadData, CoreIndex, GroupIndex),
-		/// A candidate was included. `[candidate, head_data]`
-		CandidateIncluded(CandidateReceipt<T::Hash>, HeadData, CoreIndex, GroupIndex),
-		/// A candidate timed out. `[candidate, head_data]`
-		CandidateTimedOut(CandidateReceipt<T::Hash>, HeadData, CoreIndex),
+		/// A candidate was included, the given number of blocks after it was backed.
+		/// `[candidate, head_data, core, group, blocks_to_availability, assignment_source]`
+		CandidateIncluded(
+			CandidateReceipt<T::Hash>,
+			HeadData,
+			CoreIndex,
+			GroupIndex,
+			T::BlockNumber,
+			CoreAssignmentSource,
+		),
+		/// A candidate timed out after being pending availability for the given number of blocks,
+		/// with the given number of availability votes.
+		/// `[candidate, head_data, core, blocks_pending, availability_votes]`
+		CandidateTimedOut(CandidateReceipt<T::Hash>, HeadData, CoreIndex, T::BlockNumber, u32),
+		/// A backed candidate was not included in the block, because no persisted validation data
+		/// could be created for its para. `[para_id, candidate_hash]`
+		CandidateDroppedMissingValidationData(ParaId, CandidateHash),
+		/// A candidate was added to the candidates that can never be included. `[candidate_hash]`
+		CandidateDisallowed(CandidateHash),
+		/// A candidate pending availability without commitments, or commitments without a
+		/// candidate pending availability, were removed for the para. `[para_id]`
+		StorageInconsistencyRepaired(ParaId),
 	}
 
 	#[pallet::error]
@@ -225,6 +468,9 @@ pub mod pallet {
 		UnexpectedRelayParent,
 		/// Availability bitfield has unexpected size.
 		WrongBitfieldSize,
+		/// Availability bitfield is sized to the core layout before the last change of the number
+		/// of cores.
+		OutdatedBitfieldSize,
 		/// Bitfield consists of zeros only.
 		BitfieldAllZeros,
 		/// Multiple bitfields submitted by same validator or validators out of order by index.
@@ -235,11 +481,16 @@ pub mod pallet {
 		InvalidBitfieldSignature,
 		/// Candidate submitted but para not scheduled.
 		UnscheduledCandidate,
-		/// Candidate scheduled despite pending candidate already existing for the para.
+		/// Candidate scheduled despite pending candidate already existing for the para, or for the
+		/// core if `multi_core_paras` is set.
 		CandidateScheduledBeforeParaFree,
+		/// More than one candidate was backed for the same para.
+		DuplicateCandidateForPara,
+		/// More candidates were backed than `max_candidates_per_block` allows.
+		TooManyCandidates,
 		/// Candidate included with the wrong collator.
 		WrongCollator,
-		/// Scheduled cores out of order.
+		/// A core is scheduled more than once.
 		ScheduledOutOfOrder,
 		/// Head data exceeds the configured maximum.
 		HeadDataTooLarge,
@@ -276,77 +527,791 @@ pub mod pallet {
 		/// either intentionally or as part of a concluded
 		/// invalid dispute.
 		BitfieldReferencesFreedCore,
+		/// There is no candidate pending availability for the para.
+		NoPendingCandidate,
+		/// The candidate descriptor version is invalid or no longer accepted.
+		InvalidDescriptorVersion,
+		/// The core index in a v2 candidate descriptor is not the core the candidate was scheduled on.
+		InvalidAssignedCore,
+		/// The session index in a v2 candidate descriptor is not the current session.
+		InvalidSessionIndex,
+		/// Compact availability bitfields are not enabled.
+		CompactBitfieldsDisabled,
+		/// A compact availability bitfield has unordered, duplicate or out of range bits.
+		MalformedCompactBitfield,
+		/// The core affinity of the para does not allow the core it was scheduled on.
+		DisallowedCore,
+		/// The candidate lost a dispute or was disallowed by governance.
+		DisallowedCandidate,
+		/// The para of the candidate is frozen by governance.
+		ParaFrozen,
+		/// There are more active validators than `MaxValidators`.
+		TooManyValidators,
 	}
 
 	/// The latest bitfield for each validator, referred to by their index in the validator set.
 	#[pallet::storage]
-	pub(crate) type AvailabilityBitfields<T: Config> =
-		StorageMap<_, Twox64Concat, ValidatorIndex, AvailabilityBitfieldRecord<T::BlockNumber>>;
+	pub(crate) type AvailabilityBitfields<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		ValidatorIndex,
+		AvailabilityBitfieldRecord<T::BlockNumber, T::MaxAvailabilityCores>,
+	>;
+
+	/// The session and nonce of the latest nonced bitfield for each validator.
+	///
+	/// Only the nonce of the current session is meaningful, entries of previous sessions are
+	/// ignored until they are swept.
+	#[pallet::storage]
+	pub(crate) type AvailabilityBitfieldNonces<T: Config> =
+		StorageMap<_, Twox64Concat, ValidatorIndex, (SessionIndex, u64)>;
 
-	/// Candidates pending availability by `ParaId`.
+	/// The validator indices whose entries in `AvailabilityBitfields` and
+	/// `AvailabilityBitfieldNonces` may be stale since the last session change.
+	///
+	/// Stale bitfields are never taken into account while this is set, and the entries are
+	/// removed a bounded number at a time in `initializer_initialize`.
 	#[pallet::storage]
-	pub(crate) type PendingAvailability<T: Config> =
-		StorageMap<_, Twox64Concat, ParaId, CandidatePendingAvailability<T::Hash, T::BlockNumber>>;
+	pub(crate) type StaleValidatorEntries<T: Config> =
+		StorageValue<_, StaleEntriesCursor<T::BlockNumber>>;
 
-	/// The commitments of candidates pending availability, by `ParaId`.
+	/// The active validators that submitted an availability bitfield in a block of the current
+	/// session while cores were occupied, by validator index.
+	///
+	/// `None` if no cores were occupied in any block of the session so far.
 	#[pallet::storage]
+	pub(crate) type BitfieldParticipation<T: Config> =
+		StorageValue<_, BoundedBitVec<T::MaxValidators>>;
+
+	/// The validators that were availability no-shows in the previous sessions, along with the
+	/// number of consecutive sessions they were no-shows for.
+	#[pallet::storage]
+	pub(crate) type AvailabilityNoShowStreaks<T: Config> =
+		StorageValue<_, BoundedVec<(ValidatorId, u32), T::MaxValidators>, ValueQuery>;
+
+	/// Candidates pending availability by `ParaId` and the core they occupy.
+	///
+	/// A para has at most one candidate pending availability unless `multi_core_paras` is set.
+	#[pallet::storage]
+	pub(crate) type PendingAvailability<T: Config> = StorageDoubleMap<
+		_,
+		Twox64Concat,
+		ParaId,
+		Twox64Concat,
+		CoreIndex,
+		CandidatePendingAvailability<T::Hash, T::BlockNumber, T::MaxValidators>,
+	>;
+
+	/// The commitments of candidates pending availability, by `ParaId` and the core they occupy.
+	#[pallet::storage]
+	#[pallet::unbounded]
 	pub(crate) type PendingAvailabilityCommitments<T: Config> =
-		StorageMap<_, Twox64Concat, ParaId, CandidateCommitments>;
+		StorageDoubleMap<_, Twox64Concat, ParaId, Twox64Concat, CoreIndex, CandidateCommitments>;
+
+	/// The head data and validation code of candidates pending availability that were backed while
+	/// `config.pending_commitments_by_reference` was set, by hash, along with the number of
+	/// candidates referring to them.
+	#[pallet::storage]
+	#[pallet::unbounded]
+	pub(crate) type PendingCommitmentsBlobs<T: Config> =
+		StorageMap<_, Identity, Hash, (u32, Vec<u8>)>;
+
+	/// The hashes of the head data and of the validation code, if any, that were stripped from the
+	/// `PendingAvailabilityCommitments` of a para and core and stored in `PendingCommitmentsBlobs`.
+	#[pallet::storage]
+	pub(crate) type PendingCommitmentsRefs<T: Config> =
+		StorageDoubleMap<_, Twox64Concat, ParaId, Twox64Concat, CoreIndex, (Hash, Option<Hash>)>;
+
+	/// Candidates pending availability that are already available, but whose enactment was
+	/// deferred because `max_enactment_weight_per_block` was exhausted. Oldest first.
+	///
+	/// They keep their core and are neither timed out nor required to collect votes again.
+	#[pallet::storage]
+	pub(crate) type ReadyToEnact<T: Config> =
+		StorageValue<_, BoundedVec<CandidateHash, T::MaxAvailabilityCores>, ValueQuery>;
+
+	/// Candidates included in recent relay chain blocks, by the number of the block they were
+	/// included in.
+	///
+	/// Entries are kept for `included_candidates_retention` blocks.
+	#[pallet::storage]
+	pub(crate) type IncludedCandidates<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		T::BlockNumber,
+		BoundedVec<IncludedCandidate<T::Hash>, T::MaxAvailabilityCores>,
+		ValueQuery,
+	>;
+
+	/// The relay chain block number a candidate was included in and the core it occupied, by
+	/// candidate hash.
+	///
+	/// Entries are kept for `dispute_period` sessions after the session of inclusion.
+	#[pallet::storage]
+	pub(crate) type CandidateIncludedAt<T: Config> =
+		StorageMap<_, Identity, CandidateHash, (T::BlockNumber, CoreIndex)>;
+
+	/// The hashes of the candidates included in each session, used for pruning
+	/// `CandidateIncludedAt`.
+	#[pallet::storage]
+	#[pallet::unbounded]
+	pub(crate) type CandidatesIncludedInSession<T: Config> =
+		StorageMap<_, Twox64Concat, SessionIndex, Vec<CandidateHash>, ValueQuery>;
+
+	/// Candidates that can never be included, with the session they were disallowed in.
+	///
+	/// Entries are kept for `dispute_period` sessions after the session they were added in.
+	#[pallet::storage]
+	pub(crate) type DisallowedCandidates<T: Config> =
+		StorageMap<_, Identity, CandidateHash, SessionIndex>;
+
+	/// The candidate seconded by each validator, by the number of the relay parent it was
+	/// seconded at.
+	///
+	/// Entries are kept for as long as candidates may still be built on that relay parent.
+	#[pallet::storage]
+	pub(crate) type SecondedCandidates<T: Config> = StorageDoubleMap<
+		_,
+		Twox64Concat,
+		T::BlockNumber,
+		Twox64Concat,
+		ValidatorIndex,
+		CandidateHash,
+	>;
+
+	/// The number of paras whose candidate pending availability and commitments were found out of
+	/// sync and removed, since genesis.
+	#[pallet::storage]
+	pub(crate) type StorageInconsistenciesRepaired<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+	/// The hash of the layout of the occupied cores at the start of the block, which availability
+	/// bitfields are signed over if `core_layout_bound_bitfields` is enabled. Only present within
+	/// a block.
+	#[pallet::storage]
+	pub(crate) type BitfieldCoreLayoutHash<T: Config> = StorageValue<_, Hash>;
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		#[cfg(feature = "try-runtime")]
+		fn try_state(_n: T::BlockNumber) -> Result<(), &'static str> {
+			Self::do_try_state()
+		}
+	}
 
 	#[pallet::call]
-	impl<T: Config> Pallet<T> {}
+	impl<T: Config> Pallet<T> {
+		/// Drop the candidates pending availability for the given para and free their cores.
+		///
+		/// This allows recovering a para whose candidate will never become available without
+		/// waiting for the next session change. The candidates are treated as timed out.
+		#[pallet::call_index(0)]
+		#[pallet::weight(<T as Config>::WeightInfo::force_clear_pending())]
+		pub fn force_clear_pending(origin: OriginFor<T>, para: ParaId) -> DispatchResult {
+			ensure_root(origin)?;
+
+			let pending = <PendingAvailability<T>>::drain_prefix(&para).collect::<Vec<_>>();
+			ensure!(!pending.is_empty(), Error::<T>::NoPendingCandidate);
+			for (_, pending) in pending {
+				Self::time_out_pending_candidate(para, pending);
+			}
+			Ok(())
+		}
+
+		/// Prevent the candidate with the given hash from ever being included.
+		///
+		/// This is meant for candidates that lost a dispute on another fork of the relay chain.
+		/// The candidate stays disallowed for `dispute_period` sessions.
+		#[pallet::call_index(1)]
+		#[pallet::weight(<T as Config>::WeightInfo::disallow_candidate())]
+		pub fn disallow_candidate(
+			origin: OriginFor<T>,
+			candidate_hash: CandidateHash,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			Self::note_disallowed_candidate(candidate_hash);
+			Ok(())
+		}
+	}
 }
 
 const LOG_TARGET: &str = "runtime::inclusion";
 
+/// Enter a `TRACE` span with the given name and fields for the rest of the enclosing scope.
+///
+/// Compiles to nothing unless the `runtime-tracing` feature is enabled. The spans of the Wasm
+/// runtime are only collected by nodes run with `--tracing-targets wasm_tracing=trace`.
+macro_rules! trace_span {
+	($name:literal $(, $($fields:tt)*)?) => {
+		#[cfg(feature = "runtime-tracing")]
+		sp_tracing::enter_span!(sp_tracing::span!(
+			target: LOG_TARGET,
+			sp_tracing::Level::TRACE,
+			$name
+			$(, $($fields)*)?
+		));
+	};
+}
+
 impl<T: Config> Pallet<T> {
 	/// Block initialization logic, called by initializer.
-	pub(crate) fn initializer_initialize(_now: T::BlockNumber) -> Weight {
-		Weight::zero()
+	pub(crate) fn initializer_initialize(now: T::BlockNumber) -> Weight {
+		let config = <configuration::Pallet<T>>::config();
+		let mut weight = T::DbWeight::get().reads(1);
+
+		// The layout is noted before any candidate of this block is dropped, as validators signed
+		// their bitfields over the layout of the parent block.
+		weight = weight.saturating_add(Self::note_core_layout(&config));
+
+		// Candidates at block `now` may be built on relay parents down to
+		// `now - 1 - max_candidate_relay_parent_depth`, anything older can no longer conflict.
+		let seconded_horizon =
+			T::BlockNumber::from(config.max_candidate_relay_parent_depth.saturating_add(2));
+		if now >= seconded_horizon {
+			let removal =
+				<SecondedCandidates<T>>::clear_prefix(now - seconded_horizon, u32::MAX, None);
+			weight = weight.saturating_add(
+				T::DbWeight::get().reads_writes(removal.loops.into(), removal.unique.into()),
+			);
+		}
+
+		let retention = config.included_candidates_retention;
+		if !retention.is_zero() && now >= retention {
+			<IncludedCandidates<T>>::remove(now - retention);
+			weight = weight.saturating_add(T::DbWeight::get().writes(1));
+		}
+
+		// Candidates built against code that governance rolled back in the previous block must
+		// not be enacted. Newer candidates are checked against the current code anyway.
+		let rollbacks = <paras::Pallet<T>>::take_code_rollbacks();
+		weight = weight.saturating_add(T::DbWeight::get().reads_writes(1, 1));
+		for (para, rolled_back_code_hash) in rollbacks {
+			weight = weight.saturating_add(T::DbWeight::get().reads(1));
+			let stale = <PendingAvailability<T>>::iter_prefix(&para)
+				.filter(|(_, pending)| {
+					pending.descriptor.validation_code_hash == rolled_back_code_hash
+				})
+				.collect::<Vec<_>>();
+
+			for (core, pending) in stale {
+				<PendingAvailability<T>>::remove(&para, core);
+				Self::time_out_pending_candidate(para, pending);
+				weight = weight.saturating_add(T::DbWeight::get().reads_writes(3, 5));
+			}
+		}
+
+		// Reserve the weight of looking for candidates pending availability that are out of sync
+		// with their commitments on finalization, there is at most one of each per core.
+		let n_cores = scheduler::AvailabilityCores::<T>::decode_len().unwrap_or(0) as u64;
+		weight = weight.saturating_add(T::DbWeight::get().reads(1 + 2 * n_cores));
+
+		weight.saturating_add(Self::sweep_stale_validator_entries())
+	}
+
+	/// Time out the candidates of the given offboarded para that are still pending availability,
+	/// freeing their cores.
+	pub(crate) fn clean_up_offboarded_para(para: ParaId) -> Weight {
+		let pending = <PendingAvailability<T>>::drain_prefix(&para).collect::<Vec<_>>();
+		let n_pending = pending.len() as u64;
+		for (_, pending) in pending {
+			Self::time_out_pending_candidate(para, pending);
+		}
+
+		T::DbWeight::get().reads_writes(1 + 3 * n_pending, 5 * n_pending)
+	}
+
+	/// Drop a candidate that was taken out of `PendingAvailability`, along with its commitments,
+	/// and free its core as if it timed out.
+	fn time_out_pending_candidate(
+		para: ParaId,
+		pending: CandidatePendingAvailability<T::Hash, T::BlockNumber, T::MaxValidators>,
+	) {
+		let core = pending.core;
+
+		if let Some(commitments) = Self::take_pending_commitments(&para, core) {
+			let (blocks_pending, availability_votes) = Self::timeout_diagnostics(&pending);
+			let candidate = CandidateReceipt {
+				descriptor: pending.descriptor,
+				commitments_hash: commitments.hash(),
+			};
+
+			Self::deposit_event(Event::<T>::CandidateTimedOut(
+				candidate,
+				commitments.head_data,
+				core,
+				blocks_pending,
+				availability_votes,
+			));
+			T::InclusionHooks::on_candidate_timed_out(para, pending.hash, core);
+			<statistics::Pallet<T>>::note_timed_out();
+		}
+
+		<scheduler::Pallet<T>>::free_cores(sp_std::iter::once((core, FreedReason::TimedOut)));
+	}
+
+	/// The number of blocks the given candidate has been pending availability for, and the number
+	/// of availability votes it has collected.
+	fn timeout_diagnostics(
+		pending: &CandidatePendingAvailability<T::Hash, T::BlockNumber, T::MaxValidators>,
+	) -> (T::BlockNumber, u32) {
+		let now = <frame_system::Pallet<T>>::block_number();
+		(
+			now.saturating_sub(pending.backed_in_number),
+			pending.availability_votes.count_ones() as u32,
+		)
+	}
+
+	/// Remove the bitfields and nonces left over from a previous session for the next
+	/// `MAX_STALE_ENTRIES_SWEPT_PER_BLOCK` validator indices, if any are queued.
+	fn sweep_stale_validator_entries() -> Weight {
+		let mut cursor = match <StaleValidatorEntries<T>>::get() {
+			Some(cursor) => cursor,
+			None => return T::DbWeight::get().reads(1),
+		};
+
+		let session_index = shared::Pallet::<T>::session_index();
+		let until = cursor.end.min(cursor.next.saturating_add(MAX_STALE_ENTRIES_SWEPT_PER_BLOCK));
+		for validator_index in (cursor.next..until).map(ValidatorIndex) {
+			if <AvailabilityBitfields<T>>::get(&validator_index)
+				.map_or(false, |record| record.submitted_at < cursor.session_start)
+			{
+				<AvailabilityBitfields<T>>::remove(&validator_index);
+			}
+			// Entries that do not decode predate the session tagging and are stale as well.
+			if <AvailabilityBitfieldNonces<T>>::get(&validator_index)
+				.map_or(true, |(session, _)| session != session_index)
+			{
+				<AvailabilityBitfieldNonces<T>>::remove(&validator_index);
+			}
+		}
+
+		let swept = u64::from(until - cursor.next);
+		cursor.next = until;
+		if cursor.next >= cursor.end {
+			<StaleValidatorEntries<T>>::kill();
+		} else {
+			<StaleValidatorEntries<T>>::put(cursor);
+		}
+
+		T::DbWeight::get().reads_writes(2 + 2 * swept, 1 + 2 * swept)
+	}
+
+	/// Whether the bitfield was submitted before the last session change and not swept yet.
+	fn is_stale_bitfield(
+		record: &AvailabilityBitfieldRecord<T::BlockNumber, T::MaxAvailabilityCores>,
+		stale_entries: &Option<StaleEntriesCursor<T::BlockNumber>>,
+	) -> bool {
+		stale_entries
+			.as_ref()
+			.map_or(false, |cursor| record.submitted_at < cursor.session_start)
 	}
 
 	/// Block finalization logic, called by initializer.
-	pub(crate) fn initializer_finalize() {}
+	pub(crate) fn initializer_finalize() {
+		Self::repair_pending_availability();
+		<BitfieldCoreLayoutHash<T>>::kill();
+	}
+
+	/// Note the hash of the layout of the occupied cores for the bitfields of this block, if they
+	/// are bound to it.
+	pub(crate) fn note_core_layout(
+		config: &configuration::HostConfiguration<T::BlockNumber>,
+	) -> Weight {
+		if !config.core_layout_bound_bitfields {
+			return Weight::zero()
+		}
+
+		let (layout_hash, n_occupied) = Self::occupied_core_layout_hash();
+		<BitfieldCoreLayoutHash<T>>::put(layout_hash);
+
+		T::DbWeight::get().reads_writes(n_occupied as u64 + 1, 1)
+	}
+
+	/// The hash of the core layout the availability bitfields of the next block have to be signed
+	/// over, if they are bound to it.
+	///
+	/// Nodes query this at the parent of the block, whose state the layout is noted from.
+	pub(crate) fn next_bitfield_core_layout_hash() -> Option<Hash> {
+		<configuration::Pallet<T>>::config()
+			.core_layout_bound_bitfields
+			.then(|| Self::occupied_core_layout_hash().0)
+	}
+
+	/// The hash of the layout of the currently occupied cores, along with their number.
+	fn occupied_core_layout_hash() -> (Hash, usize) {
+		let mut occupied = <PendingAvailability<T>>::iter_values()
+			.map(|pending| (pending.core, pending.hash))
+			.collect::<Vec<_>>();
+		occupied.sort_unstable_by_key(|(core, _)| *core);
+		(core_layout_hash(&occupied), occupied.len())
+	}
+
+	/// The previous session, if this block is within the `bitfield_grace_blocks` after the session
+	/// change and bitfields signed in the previous session are still accepted.
+	fn bitfield_grace_session(session_index: SessionIndex) -> Option<SessionIndex> {
+		let grace_blocks = <configuration::Pallet<T>>::config().bitfield_grace_blocks;
+		let session_start = <scheduler::Pallet<T>>::session_start_block();
+		let now = <frame_system::Pallet<T>>::block_number();
+		if grace_blocks.is_zero() || now >= session_start.saturating_add(grace_blocks) {
+			return None
+		}
+		session_index.checked_sub(1)
+	}
+
+	/// The signing context availability bitfields of this block are checked against.
+	pub(crate) fn bitfield_signing_context(
+		parent_hash: T::Hash,
+		session_index: SessionIndex,
+	) -> SigningContext<BitfieldSigningParent<T::Hash>> {
+		let core_layout_hash = <BitfieldCoreLayoutHash<T>>::get();
+		SigningContext {
+			session_index,
+			parent_hash: BitfieldSigningParent { parent_hash, core_layout_hash },
+		}
+	}
+
+	/// Remove the candidates pending availability without commitments and the commitments without
+	/// a candidate pending availability, freeing the cores of the former.
+	///
+	/// No code path is expected to leave such entries behind, but they would otherwise keep the
+	/// para from being backed again.
+	fn repair_pending_availability() {
+		let missing_commitments = <PendingAvailability<T>>::iter_keys()
+			.filter(|(para_id, core)| {
+				!<PendingAvailabilityCommitments<T>>::contains_key(para_id, core)
+			})
+			.collect::<Vec<_>>();
+		let missing_candidates = <PendingAvailabilityCommitments<T>>::iter_keys()
+			.filter(|(para_id, core)| !<PendingAvailability<T>>::contains_key(para_id, core))
+			.collect::<Vec<_>>();
+
+		let repaired = missing_commitments.len() + missing_candidates.len();
+		if repaired == 0 {
+			return
+		}
+
+		for (para_id, core) in missing_commitments {
+			log::warn!(
+				target: LOG_TARGET,
+				"Removing the candidate pending availability of para {:?} without commitments",
+				para_id,
+			);
+			<PendingAvailability<T>>::remove(&para_id, core);
+			<scheduler::Pallet<T>>::free_cores(sp_std::iter::once((core, FreedReason::TimedOut)));
+			Self::deposit_event(Event::<T>::StorageInconsistencyRepaired(para_id));
+		}
+		for (para_id, core) in missing_candidates {
+			log::warn!(
+				target: LOG_TARGET,
+				"Removing the commitments of para {:?} without a candidate pending availability",
+				para_id,
+			);
+			// This also drops the references to the head data and validation code blobs.
+			let _ = Self::take_pending_commitments(&para_id, core);
+			Self::deposit_event(Event::<T>::StorageInconsistencyRepaired(para_id));
+		}
+
+		<StorageInconsistenciesRepaired<T>>::mutate(|count| {
+			*count = count.saturating_add(repaired as u32)
+		});
+	}
+
+	/// The number of paras whose candidate pending availability and commitments were found out of
+	/// sync and removed, since genesis.
+	pub(crate) fn storage_inconsistencies_repaired() -> u32 {
+		<StorageInconsistenciesRepaired<T>>::get()
+	}
 
 	/// Handle an incoming session change.
 	pub(crate) fn initializer_on_new_session(
-		_notification: &crate::initializer::SessionChangeNotification<T::BlockNumber>,
+		notification: &crate::initializer::SessionChangeNotification<T::BlockNumber>,
 	) {
 		// unlike most drain methods, drained elements are not cleared on `Drop` of the iterator
 		// and require consumption.
-		for _ in <PendingAvailabilityCommitments<T>>::drain() {}
-		for _ in <PendingAvailability<T>>::drain() {}
-		for _ in <AvailabilityBitfields<T>>::drain() {}
+		let commitments = <PendingAvailabilityCommitments<T>>::iter_keys()
+			.collect::<Vec<_>>()
+			.into_iter()
+			.filter_map(|(para_id, core)| {
+				Self::take_pending_commitments(&para_id, core).map(|c| ((para_id, core), c))
+			})
+			.collect::<BTreeMap<_, _>>();
+		let pending = <PendingAvailability<T>>::drain()
+			.map(|(para_id, _, candidate)| (para_id, candidate))
+			.collect::<Vec<_>>();
+		if notification.new_config.carry_over_pending_availability {
+			Self::carry_over_pending_availability(notification, pending, commitments);
+		}
+		<ReadyToEnact<T>>::kill();
+
+		Self::punish_availability_no_shows(notification);
+
+		// Bitfields and nonces are keyed by validator index, which is only meaningful within a
+		// session. Rather than draining them all at once, the indices of the previous session are
+		// queued to be swept over the next blocks, and the leftovers are ignored in the meantime.
+		let end = <StaleValidatorEntries<T>>::get()
+			.map_or(0, |cursor| cursor.end)
+			.max(notification.prev_validators.len() as u32);
+		<StaleValidatorEntries<T>>::put(StaleEntriesCursor {
+			next: 0,
+			end,
+			session_start: <frame_system::Pallet<T>>::block_number() + One::one(),
+		});
+
+		// `SecondedCandidates` is not cleared: relay parents of the previous session are no longer
+		// allowed once `shared::AllowedRelayParents` is reset, so its entries are never read again
+		// and are pruned by `initializer_initialize` like any other.
+
+		// The retention period may have shrunk with the new configuration, in which case the
+		// per-block pruning would never reach the entries that are now out of the window.
+		let now = <frame_system::Pallet<T>>::block_number();
+		let cutoff = now.saturating_sub(notification.new_config.included_candidates_retention);
+		let stale = <IncludedCandidates<T>>::iter_keys()
+			.filter(|at| *at <= cutoff)
+			.collect::<Vec<_>>();
+		for at in stale {
+			<IncludedCandidates<T>>::remove(at);
+		}
+
+		// Inclusion records are only needed for as long as the candidate may be disputed.
+		let dispute_period = notification.new_config.dispute_period;
+		if let Some(old_session) = notification.session_index.checked_sub(dispute_period + 1) {
+			for candidate_hash in <CandidatesIncludedInSession<T>>::take(old_session) {
+				<CandidateIncludedAt<T>>::remove(candidate_hash);
+			}
+
+			let expired = <DisallowedCandidates<T>>::iter()
+				.filter(|(_, session)| *session <= old_session)
+				.map(|(candidate_hash, _)| candidate_hash)
+				.collect::<Vec<_>>();
+			for candidate_hash in expired {
+				<DisallowedCandidates<T>>::remove(candidate_hash);
+			}
+		}
+	}
+
+	/// Report the validators of the previous session that did not submit a single availability
+	/// bitfield while cores were occupied to the `AvailabilityNoShowHandler`.
+	///
+	/// Those that were no-shows for `availability_no_show_slash_threshold` consecutive sessions
+	/// are reported separately, to be slashed. Sessions without occupied cores don't count.
+	fn punish_availability_no_shows(
+		notification: &crate::initializer::SessionChangeNotification<T::BlockNumber>,
+	) {
+		let participation = match <BitfieldParticipation<T>>::take() {
+			Some(participation) => participation,
+			None => return,
+		};
+
+		let threshold = notification.prev_config.availability_no_show_slash_threshold;
+		let streaks =
+			<AvailabilityNoShowStreaks<T>>::take().into_iter().collect::<BTreeMap<_, _>>();
+		let mut new_streaks = Vec::new();
+		let (mut no_shows, mut repeated) = (Vec::new(), Vec::new());
+		for (index, key) in notification.prev_validators.iter().enumerate() {
+			if participation.get(index).map_or(false, |bit| *bit) {
+				continue
+			}
+
+			let streak = streaks.get(key).map_or(1, |streak| streak.saturating_add(1));
+			if threshold != 0 && streak >= threshold {
+				repeated.push(ValidatorIndex(index as u32));
+			} else {
+				no_shows.push(ValidatorIndex(index as u32));
+			}
+			new_streaks.push((key.clone(), streak));
+		}
+		<AvailabilityNoShowStreaks<T>>::put(BoundedVec::truncate_from(new_streaks));
+
+		let session_index = notification.session_index.saturating_sub(1);
+		if !no_shows.is_empty() {
+			T::AvailabilityNoShowHandler::punish_availability_no_shows(
+				session_index,
+				no_shows,
+				false,
+			);
+		}
+		if !repeated.is_empty() {
+			T::AvailabilityNoShowHandler::punish_availability_no_shows(
+				session_index,
+				repeated,
+				true,
+			);
+		}
+	}
+
+	/// Put the candidates that were pending availability at the end of the previous session back
+	/// in place, with their availability votes and backers remapped to the new validator indices.
+	///
+	/// Nothing is carried over unless every validator of the previous session is still active.
+	/// Candidates whose core is gone or no longer assigned to their para are dropped.
+	fn carry_over_pending_availability(
+		notification: &crate::initializer::SessionChangeNotification<T::BlockNumber>,
+		pending: Vec<(
+			ParaId,
+			CandidatePendingAvailability<T::Hash, T::BlockNumber, T::MaxValidators>,
+		)>,
+		mut commitments: BTreeMap<(ParaId, CoreIndex), CandidateCommitments>,
+	) {
+		let new_indices = notification
+			.prev_validators
+			.iter()
+			.map(|key| notification.validators.iter().position(|new_key| new_key == key))
+			.collect::<Option<Vec<_>>>();
+		let new_indices = match new_indices {
+			Some(new_indices) => new_indices,
+			None => return,
+		};
+
+		let remap = |bits: &BitSlice<u8, BitOrderLsb0>| {
+			let mut remapped = bitvec::bitvec![u8, BitOrderLsb0; 0; notification.validators.len()];
+			for new_index in bits.iter_ones().filter_map(|old_index| new_indices.get(old_index)) {
+				remapped.set(*new_index, true);
+			}
+			remapped
+		};
+
+		for (para_id, mut candidate) in pending {
+			let candidate_commitments = match commitments.remove(&(para_id, candidate.core)) {
+				Some(candidate_commitments) => candidate_commitments,
+				None => continue,
+			};
+			if !<scheduler::Pallet<T>>::reoccupy_parachain_core(candidate.core, para_id) {
+				continue
+			}
+
+			// The new validator set can't be smaller than the old one, but it may exceed the bound.
+			match (
+				remap(&candidate.availability_votes).try_into(),
+				remap(&candidate.backers).try_into(),
+			) {
+				(Ok(availability_votes), Ok(backers)) => {
+					candidate.availability_votes = availability_votes;
+					candidate.backers = backers;
+				},
+				_ => continue,
+			}
+			let core = candidate.core;
+			<PendingAvailability<T>>::insert(&para_id, core, candidate);
+			Self::insert_pending_commitments(
+				&para_id,
+				core,
+				candidate_commitments,
+				notification.new_config.pending_commitments_by_reference,
+			);
+		}
+	}
+
+	/// Check the internal consistency of the inclusion storage.
+	///
+	/// Every candidate pending availability must have its commitments stored and vice versa, must
+	/// occupy an existing core and must track votes for exactly the active validators. Bitfields
+	/// can only be stored for active validators.
+	#[cfg(any(feature = "try-runtime", test))]
+	pub(crate) fn do_try_state() -> Result<(), &'static str> {
+		let n_validators = shared::Pallet::<T>::active_validator_keys().len();
+		let n_cores = <scheduler::Pallet<T>>::availability_cores().len();
+
+		for (para_id, core, pending) in <PendingAvailability<T>>::iter() {
+			ensure!(
+				<PendingAvailabilityCommitments<T>>::contains_key(&para_id, core),
+				"candidate pending availability without commitments",
+			);
+			ensure!(pending.descriptor.para_id == para_id, "candidate stored under the wrong para");
+			ensure!(pending.core == core, "candidate stored under the wrong core");
+			ensure!((pending.core.0 as usize) < n_cores, "candidate occupies an unknown core");
+			ensure!(
+				pending.availability_votes.len() == n_validators,
+				"availability votes do not match the validator count",
+			);
+			ensure!(
+				pending.backers.len() == n_validators,
+				"backers do not match the validator count",
+			);
+		}
+
+		for (para_id, core) in <PendingAvailabilityCommitments<T>>::iter_keys() {
+			ensure!(
+				<PendingAvailability<T>>::contains_key(&para_id, core),
+				"commitments without a candidate pending availability",
+			);
+		}
+
+		for (para_id, core, (head_data, validation_code)) in <PendingCommitmentsRefs<T>>::iter() {
+			ensure!(
+				<PendingAvailabilityCommitments<T>>::contains_key(&para_id, core),
+				"commitment references without commitments",
+			);
+			ensure!(
+				sp_std::iter::once(head_data)
+					.chain(validation_code)
+					.all(|hash| <PendingCommitmentsBlobs<T>>::contains_key(&hash)),
+				"commitment references to a missing blob",
+			);
+		}
+
+		let stale_entries = <StaleValidatorEntries<T>>::get();
+		for (validator_index, record) in <AvailabilityBitfields<T>>::iter() {
+			if Self::is_stale_bitfield(&record, &stale_entries) {
+				continue
+			}
+			ensure!(
+				(validator_index.0 as usize) < n_validators,
+				"bitfield stored for an unknown validator",
+			);
+		}
+
+		Ok(())
 	}
 
-	/// Extract the freed cores based on cores that became available.
+	/// Extract the freed cores based on cores that became available, along with the weight of
+	/// enacting the candidates on them.
 	///
 	/// Updates storage items `PendingAvailability` and `AvailabilityBitfields`.
 	pub(crate) fn update_pending_availability_and_get_freed_cores<F>(
 		expected_bits: usize,
 		validators: &[ValidatorId],
 		signed_bitfields: UncheckedSignedAvailabilityBitfields,
+		late_bitfields: Vec<LateBitfield>,
 		core_lookup: F,
 		enact_candidate: bool,
-	) -> Vec<(CoreIndex, CandidateHash)>
+	) -> (Vec<(CoreIndex, CandidateHash)>, Weight)
 	where
 		F: Fn(CoreIndex) -> Option<ParaId>,
 	{
-		let mut assigned_paras_record = (0..expected_bits)
-			.map(|bit_index| core_lookup(CoreIndex::from(bit_index as u32)))
-			.map(|opt_para_id| {
-				opt_para_id.map(|para_id| (para_id, PendingAvailability::<T>::get(&para_id)))
+		trace_span!(
+			"update_pending_availability",
+			n_bitfields = signed_bitfields.len() + late_bitfields.len(),
+		);
+		let layout = CoreBitfieldLayout::new(expected_bits);
+		let mut assigned_paras_record = (0..layout.n_bits())
+			.map(|bit_index| {
+				let core = layout.core_of(bit_index)?;
+				core_lookup(core)
+					.map(|para_id| (para_id, PendingAvailability::<T>::get(&para_id, core)))
 			})
 			.collect::<Vec<_>>();
 
 		let now = <frame_system::Pallet<T>>::block_number();
-		for (checked_bitfield, validator_index) in
-			signed_bitfields.into_iter().map(|signed_bitfield| {
+		let core_layout_version = <scheduler::Pallet<T>>::core_layout_version();
+		let disabled =
+			shared::Pallet::<T>::disabled_validators().into_iter().collect::<BTreeSet<_>>();
+		let late_bitfields = late_bitfields
+			.into_iter()
+			.map(|late| (late.signed.unchecked_into_payload(), late.validator_index));
+		for (checked_bitfield, validator_index) in signed_bitfields
+			.into_iter()
+			.map(|signed_bitfield| {
 				// extracting unchecked data, since it's checked in `fn sanitize_bitfields` already.
 				let validator_idx = signed_bitfield.unchecked_validator_index();
 				let checked_bitfield = signed_bitfield.unchecked_into_payload();
 				(checked_bitfield, validator_idx)
-			}) {
+			})
+			.chain(late_bitfields)
+		{
+			// Disabled validators are ignored for the rest of the session.
+			if disabled.contains(&validator_index) {
+				continue
+			}
+
 			for (bit_idx, _) in checked_bitfield.0.iter().enumerate().filter(|(_, is_av)| **is_av) {
 				let pending_availability = if let Some((_, pending_availability)) =
 					assigned_paras_record[bit_idx].as_mut()
@@ -371,126 +1336,533 @@ impl<T: Config> Pallet<T> {
 				}
 			}
 
+			let bitfield = match BoundedBitVec::try_from(checked_bitfield.0) {
+				Ok(bitfield) => bitfield,
+				Err(_) => {
+					// defensive: there are never more cores than `MaxAvailabilityCores`.
+					log::warn!(
+						target: LOG_TARGET,
+						"Not storing the oversized bitfield of validator {:?}",
+						validator_index,
+					);
+					continue
+				},
+			};
 			let record =
-				AvailabilityBitfieldRecord { bitfield: checked_bitfield, submitted_at: now };
+				AvailabilityBitfieldRecord { bitfield, submitted_at: now, core_layout_version };
 
 			<AvailabilityBitfields<T>>::insert(&validator_index, record);
 		}
 
-		let threshold = availability_threshold(validators.len());
+		let config = <configuration::Pallet<T>>::config();
+		let threshold = availability_threshold(&config, validators.len());
+		trace_span!("enact_available_candidates", threshold);
 
-		let mut freed_cores = Vec::with_capacity(expected_bits);
-		for (para_id, pending_availability) in assigned_paras_record
+		// With a vote TTL, a vote only counts while the validator's latest bitfield is recent enough
+		// and still confirms the core.
+		let fresh_bitfields = if config.availability_vote_ttl.is_zero() {
+			None
+		} else {
+			let stale_entries = <StaleValidatorEntries<T>>::get();
+			let fresh_bitfields = (0..validators.len())
+				.map(|validator_index| {
+					<AvailabilityBitfields<T>>::get(&ValidatorIndex(validator_index as u32))
+						.filter(|record| !Self::is_stale_bitfield(record, &stale_entries))
+						.filter(|record| record.core_layout_version == core_layout_version)
+						.filter(|record| {
+							record.submitted_at.saturating_add(config.availability_vote_ttl) > now
+						})
+						.map(|record| record.bitfield)
+				})
+				.collect::<Vec<_>>();
+			Some(fresh_bitfields)
+		};
+
+		// Candidates deferred in earlier blocks go first, in the order they became available.
+		let ready_to_enact = <ReadyToEnact<T>>::take();
+		let mut pending = assigned_paras_record
 			.into_iter()
 			.flatten()
 			.filter_map(|(id, p)| p.map(|p| (id, p)))
-		{
-			if pending_availability.availability_votes.count_ones() >= threshold {
-				<PendingAvailability<T>>::remove(&para_id);
-				let commitments = match PendingAvailabilityCommitments::<T>::take(&para_id) {
-					Some(commitments) => commitments,
-					None => {
-						log::warn!(
-							target: LOG_TARGET,
-							"Inclusion::process_bitfields: PendingAvailability and PendingAvailabilityCommitments
-							are out of sync, did someone mess with the storage?",
-						);
-						continue
-					},
-				};
+			.collect::<Vec<_>>();
+		pending.sort_by_key(|(_, pending_availability)| {
+			ready_to_enact
+				.iter()
+				.position(|hash| *hash == pending_availability.hash)
+				.unwrap_or(ready_to_enact.len())
+		});
+
+		// The budget is consumed at the upper bound of the enactment weight, so that block
+		// authoring, which does not enact candidates, defers the very same candidates.
+		let enactment_budget = config.max_enactment_weight_per_block;
+		let mut budget_used = Weight::zero();
+		let mut deferred = Vec::new();
+
+		let mut freed_cores = Vec::with_capacity(expected_bits);
+		let mut enactment_weight = Weight::zero();
+		for (para_id, mut pending_availability) in pending {
+			if let Some(fresh_bitfields) = &fresh_bitfields {
+				let bit = layout.bit_of(pending_availability.core);
+				for (validator_index, mut vote) in
+					pending_availability.availability_votes.iter_mut().enumerate()
+				{
+					let confirmed = fresh_bitfields
+						.get(validator_index)
+						.and_then(|bitfield| bitfield.as_ref())
+						.and_then(|bitfield| bitfield.get(bit?).map(|bit| *bit))
+						.unwrap_or(false);
+					if !confirmed {
+						*vote = false;
+					}
+				}
+			}
+
+			// This also discards the votes cast before a validator was disabled.
+			for validator_index in &disabled {
+				if let Some(mut vote) =
+					pending_availability.availability_votes.get_mut(validator_index.0 as usize)
+				{
+					*vote = false;
+				}
+			}
+
+			let available = ready_to_enact.contains(&pending_availability.hash) ||
+				pending_availability.availability_votes.count_ones() >= threshold;
+			if available {
+				let max_weight = Self::max_enactment_weight();
+				if !enactment_budget.is_zero() &&
+					!budget_used.is_zero() &&
+					budget_used.saturating_add(max_weight).any_gt(enactment_budget)
+				{
+					deferred.push(pending_availability.hash);
+					<PendingAvailability<T>>::insert(
+						&para_id,
+						pending_availability.core,
+						&pending_availability,
+					);
+					continue
+				}
+				budget_used = budget_used.saturating_add(max_weight);
+
+				<PendingAvailability<T>>::remove(&para_id, pending_availability.core);
+				let commitments =
+					match Self::take_pending_commitments(&para_id, pending_availability.core) {
+						Some(commitments) => commitments,
+						None => {
+							log::warn!(
+								target: LOG_TARGET,
+								"Inclusion::process_bitfields: PendingAvailability and PendingAvailabilityCommitments
+								are out of sync, did someone mess with the storage?",
+							);
+							continue
+						},
+					};
 
 				if enact_candidate {
 					let receipt = CommittedCandidateReceipt {
 						descriptor: pending_availability.descriptor,
 						commitments,
 					};
-					let _weight = Self::enact_candidate(
+					enactment_weight += Self::enact_candidate(
 						pending_availability.relay_parent_number,
+						pending_availability.backed_in_number,
 						receipt,
-						pending_availability.backers,
-						pending_availability.availability_votes,
+						pending_availability.backers.into_inner(),
+						pending_availability.availability_votes.into_inner(),
 						pending_availability.core,
 						pending_availability.backing_group,
+						pending_availability.assignment_source,
 					);
 				}
 
 				freed_cores.push((pending_availability.core, pending_availability.hash));
 			} else {
-				<PendingAvailability<T>>::insert(&para_id, &pending_availability);
+				<PendingAvailability<T>>::insert(
+					&para_id,
+					pending_availability.core,
+					&pending_availability,
+				);
 			}
 		}
 
-		freed_cores
+		if !deferred.is_empty() {
+			// Each deferred candidate occupies a core of its own.
+			<ReadyToEnact<T>>::put(BoundedVec::truncate_from(deferred));
+		}
+
+		(freed_cores, enactment_weight)
+	}
+
+	/// Expand a set of incoming bitfields in their compact encoding, so that they can be passed to
+	/// [`Self::process_bitfields`].
+	///
+	/// Fails if compact bitfields are not enabled, or if any of the bitfields does not have
+	/// `expected_bits` bits or is malformed. Signatures are not checked here.
+	pub fn expand_compact_bitfields(
+		expected_bits: usize,
+		signed_bitfields: UncheckedSignedCompactAvailabilityBitfields,
+	) -> Result<UncheckedSignedAvailabilityBitfields, Error<T>> {
+		ensure!(
+			<configuration::Pallet<T>>::config().compact_availability_bitfields,
+			Error::<T>::CompactBitfieldsDisabled,
+		);
+
+		for signed_bitfield in &signed_bitfields {
+			let bitfield = signed_bitfield.unchecked_payload();
+			ensure!(bitfield.len() == expected_bits, Error::<T>::WrongBitfieldSize);
+			ensure!(bitfield.is_well_formed(), Error::<T>::MalformedCompactBitfield);
+		}
+
+		Ok(expand_signed_bitfields(&signed_bitfields))
+	}
+
+	/// Log what a rejected backed candidate or availability bitfield was about, and return the
+	/// error it is rejected with.
+	pub(crate) fn rejected(
+		error: Error<T>,
+		para_id: Option<ParaId>,
+		core: Option<CoreIndex>,
+		validator_index: Option<ValidatorIndex>,
+	) -> Error<T> {
+		log::debug!(
+			target: LOG_TARGET,
+			"Rejected with {:?}: para {:?}, core {:?}, validator {:?}",
+			error,
+			para_id,
+			core,
+			validator_index,
+		);
+		error
+	}
+
+	/// Whether any candidate is pending availability, including those that are available but whose
+	/// enactment was deferred.
+	///
+	/// Cheap enough to let blocks without parachain activity skip bitfield processing.
+	pub(crate) fn is_anything_pending() -> bool {
+		<PendingAvailability<T>>::iter_keys().next().is_some()
+	}
+
+	/// Report the bitfields of the given validators that passed the checks of the block to the
+	/// `InclusionHooks`.
+	///
+	/// While cores are occupied, also note that the validators participated in availability
+	/// distribution in the current session.
+	fn note_bitfields_processed(
+		n_validators: usize,
+		validator_indices: Vec<ValidatorIndex>,
+		cores_occupied: bool,
+	) {
+		T::InclusionHooks::on_bitfields_processed(validator_indices.len() as u32);
+
+		// Bitfields are only expected from validators while there are candidates to make
+		// available.
+		if !cores_occupied {
+			return
+		}
+
+		<BitfieldParticipation<T>>::mutate(|participation| {
+			let participation = participation.get_or_insert_with(|| {
+				// Validators beyond `MaxValidators` are never tracked.
+				let n_bits = n_validators.min(T::MaxValidators::get() as usize);
+				BoundedBitVec::try_from(BitVec::repeat(false, n_bits)).unwrap_or_default()
+			});
+			for validator_index in validator_indices {
+				let index = validator_index.0 as usize;
+				if index < participation.len() {
+					participation.set(index, true);
+				}
+			}
+		});
 	}
 
 	/// Process a set of incoming bitfields.
 	///
 	/// Returns a `Vec` of `CandidateHash`es and their respective `AvailabilityCore`s that became available,
-	/// and cores free.
+	/// and cores free, along with the weight of enacting the available candidates.
 	pub(crate) fn process_bitfields(
 		expected_bits: usize,
 		signed_bitfields: UncheckedSignedAvailabilityBitfields,
 		disputed_bitfield: DisputedBitfield,
 		core_lookup: impl Fn(CoreIndex) -> Option<ParaId>,
 		full_check: FullCheck,
-	) -> Result<Vec<(CoreIndex, CandidateHash)>, crate::inclusion::Error<T>> {
-		let validators = shared::Pallet::<T>::active_validator_keys();
-		let session_index = shared::Pallet::<T>::session_index();
-		let parent_hash = frame_system::Pallet::<T>::parent_hash();
+	) -> Result<(Vec<(CoreIndex, CandidateHash)>, Weight), crate::inclusion::Error<T>> {
+		// Nothing to check, and nothing that could become available, so there is no need to load
+		// the context.
+		if signed_bitfields.is_empty() && !Self::is_anything_pending() {
+			Self::note_bitfields_processed(0, Vec::new(), false);
+			return Ok((Vec::new(), Weight::zero()))
+		}
 
-		let checked_bitfields = crate::paras_inherent::assure_sanity_bitfields::<T>(
+		Self::process_bitfields_in(
+			&SignatureCheckContext::load(),
+			expected_bits,
 			signed_bitfields,
+			Vec::new(),
 			disputed_bitfield,
-			expected_bits,
-			parent_hash,
-			session_index,
-			&validators[..],
+			core_lookup,
 			full_check,
-		)?;
+		)
+	}
+
+	/// Process a set of incoming bitfields like [`Self::process_bitfields`], checking them against
+	/// a context already loaded for this block.
+	///
+	/// The `late_bitfields`, whose signatures were already checked, are processed along with them
+	/// after being sanitized with [`Self::sanitize_late_bitfields`].
+	pub(crate) fn process_bitfields_in(
+		context: &SignatureCheckContext<T>,
+		expected_bits: usize,
+		signed_bitfields: UncheckedSignedAvailabilityBitfields,
+		late_bitfields: Vec<LateBitfield>,
+		disputed_bitfield: DisputedBitfield,
+		core_lookup: impl Fn(CoreIndex) -> Option<ParaId>,
+		full_check: FullCheck,
+	) -> Result<(Vec<(CoreIndex, CandidateHash)>, Weight), crate::inclusion::Error<T>> {
+		// Nothing to check, and nothing that could become available.
+		if signed_bitfields.is_empty() && late_bitfields.is_empty() && !Self::is_anything_pending()
+		{
+			Self::note_bitfields_processed(0, Vec::new(), false);
+			return Ok((Vec::new(), Weight::zero()))
+		}
+
+		let validators = context.validators();
+
+		let late_bitfields = Self::sanitize_late_bitfields(
+			late_bitfields,
+			&signed_bitfields,
+			expected_bits,
+			&disputed_bitfield,
+		);
+		let checked_bitfields = {
+			trace_span!("check_bitfields", n_bitfields = signed_bitfields.len());
+			crate::paras_inherent::assure_sanity_bitfields::<T>(
+				signed_bitfields,
+				disputed_bitfield,
+				expected_bits,
+				context.parent_hash(),
+				context.session_index(),
+				validators,
+				full_check,
+			)?
+		};
+		Self::note_bitfields_processed(
+			validators.len(),
+			checked_bitfields
+				.iter()
+				.map(|bitfield| bitfield.unchecked_validator_index())
+				.chain(late_bitfields.iter().map(|late| late.validator_index))
+				.collect(),
+			(0..expected_bits as u32).any(|core| core_lookup(CoreIndex(core)).is_some()),
+		);
 
-		let freed_cores = Self::update_pending_availability_and_get_freed_cores::<_>(
+		let freed_cores_and_weight = Self::update_pending_availability_and_get_freed_cores::<_>(
 			expected_bits,
-			&validators[..],
+			validators,
 			checked_bitfields,
+			late_bitfields,
 			core_lookup,
 			true,
 		);
 
-		Ok(freed_cores)
+		Ok(freed_cores_and_weight)
+	}
+
+	/// Filter the late bitfields accepted along with the `bitfields` signed in this session.
+	///
+	/// Late bitfields must be sorted by the index of their validator in the previous session and
+	/// sized to `expected_bits`, and may not signal the availability of a disputed core. The late
+	/// bitfield of a validator that also signed a bitfield in this session is superseded by it.
+	/// Late bitfields that do not comply are dropped rather than rejected.
+	pub(crate) fn sanitize_late_bitfields(
+		late_bitfields: Vec<LateBitfield>,
+		bitfields: &UncheckedSignedAvailabilityBitfields,
+		expected_bits: usize,
+		disputed_bitfield: &DisputedBitfield,
+	) -> Vec<LateBitfield> {
+		let signed_now = bitfields
+			.iter()
+			.map(|bitfield| bitfield.unchecked_validator_index())
+			.collect::<BTreeSet<_>>();
+
+		let mut last_index = None;
+		late_bitfields
+			.into_iter()
+			.filter(|late| {
+				let previous_index = late.signed.unchecked_validator_index();
+				if !last_index.map_or(true, |last_index| last_index < previous_index) {
+					return false
+				}
+				last_index = Some(previous_index);
+
+				let bitfield = &late.signed.unchecked_payload().0;
+				bitfield.len() == expected_bits &&
+					(bitfield.clone() & disputed_bitfield.0.clone()).not_any() &&
+					!signed_now.contains(&late.validator_index)
+			})
+			.collect()
+	}
+
+	/// Process a set of incoming bitfields which carry a nonce, like [`Self::process_bitfields`].
+	///
+	/// Bitfields with a nonce not greater than the last one accepted from the same validator in
+	/// this session are replays, and are dropped before their signatures are checked.
+	pub(crate) fn process_nonced_bitfields(
+		expected_bits: usize,
+		signed_bitfields: UncheckedSignedNoncedAvailabilityBitfields,
+		disputed_bitfield: DisputedBitfield,
+		core_lookup: impl Fn(CoreIndex) -> Option<ParaId>,
+		full_check: FullCheck,
+	) -> Result<(Vec<(CoreIndex, CandidateHash)>, Weight), crate::inclusion::Error<T>> {
+		let context = SignatureCheckContext::<T>::load();
+		let session_index = context.session_index();
+
+		let mut bitfields = Vec::with_capacity(signed_bitfields.len());
+		for signed_bitfield in signed_bitfields {
+			let validator_index = signed_bitfield.unchecked_validator_index();
+			let nonce = signed_bitfield.unchecked_payload().nonce;
+			if <AvailabilityBitfieldNonces<T>>::get(&validator_index)
+				.map_or(false, |(session, last)| session == session_index && nonce <= last)
+			{
+				log::debug!(
+					target: LOG_TARGET,
+					"Dropping replayed bitfield of validator {:?} with nonce {}",
+					validator_index,
+					nonce,
+				);
+				continue
+			}
+
+			if let FullCheck::Yes = full_check {
+				let rejected = |error| Self::rejected(error, None, None, Some(validator_index));
+				let validator_public = context
+					.validators()
+					.get(validator_index.0 as usize)
+					.ok_or_else(|| rejected(Error::<T>::ValidatorIndexOutOfBounds))?;
+				signed_bitfield
+					.check_signature(context.bitfield_signing_context(), validator_public)
+					.map_err(|_| rejected(Error::<T>::InvalidBitfieldSignature))?;
+			}
+
+			<AvailabilityBitfieldNonces<T>>::insert(&validator_index, (session_index, nonce));
+			bitfields.push(signed_bitfield.unchecked_map_payload(|payload| payload.bitfield));
+		}
+
+		// signatures over the nonced payloads were checked above, if at all.
+		Self::process_bitfields_in(
+			&context,
+			expected_bits,
+			bitfields,
+			Vec::new(),
+			disputed_bitfield,
+			core_lookup,
+			FullCheck::Skip,
+		)
 	}
 
 	/// Process candidates that have been backed. Provide the relay storage root, a set of candidates
 	/// and scheduled cores.
 	///
-	/// Both should be sorted ascending by core index, and the candidates should be a subset of
-	/// scheduled cores. If these conditions are not met, the execution of the function fails.
+	/// Both may be in any order, and are matched up by the para and, for v2 descriptors, the core
+	/// of each candidate. The candidates should be a subset of scheduled cores, at most one per
+	/// para unless `multi_core_paras` is set and at most `max_candidates_per_block` in total, and
+	/// no core should be scheduled twice. If these conditions are not met, the execution of the
+	/// function fails.
 	pub(crate) fn process_candidates<GV>(
 		parent_storage_root: T::Hash,
 		candidates: Vec<BackedCandidate<T::Hash>>,
 		scheduled: Vec<CoreAssignment>,
 		group_validators: GV,
 	) -> Result<ProcessedCandidates<T::Hash>, DispatchError>
+	where
+		GV: Fn(GroupIndex) -> Option<Vec<ValidatorIndex>>,
+	{
+		Self::process_candidates_in(
+			&SignatureCheckContext::load(),
+			parent_storage_root,
+			candidates,
+			scheduled,
+			group_validators,
+		)
+	}
+
+	/// Process candidates that have been backed like [`Self::process_candidates`], checking their
+	/// backing statements against a context already loaded for this block.
+	pub(crate) fn process_candidates_in<GV>(
+		context: &SignatureCheckContext<T>,
+		parent_storage_root: T::Hash,
+		mut candidates: Vec<BackedCandidate<T::Hash>>,
+		mut scheduled: Vec<CoreAssignment>,
+		group_validators: GV,
+	) -> Result<ProcessedCandidates<T::Hash>, DispatchError>
 	where
 		GV: Fn(GroupIndex) -> Option<Vec<ValidatorIndex>>,
 	{
 		ensure!(candidates.len() <= scheduled.len(), Error::<T>::UnscheduledCandidate);
 
-		if scheduled.is_empty() {
+		if candidates.is_empty() || scheduled.is_empty() {
 			return Ok(ProcessedCandidates::default())
 		}
+		trace_span!("process_candidates", n_candidates = candidates.len());
 
-		let validators = shared::Pallet::<T>::active_validator_keys();
-		let parent_hash = <frame_system::Pallet<T>>::parent_hash();
-
-		// At the moment we assume (and in fact enforce, below) that the relay-parent is always one
-		// before of the block where we include a candidate (i.e. this code path).
+		// Bring both into the order of the cores, so that they can be matched up in one pass.
+		// Candidates for paras that are not scheduled go last and are rejected below.
+		scheduled.sort_by_key(|assignment| assignment.core);
+		candidates.sort_by_key(|candidate| {
+			let para_id = candidate.descriptor().para_id;
+			let core_index = candidate.descriptor().core_index();
+			scheduled
+				.iter()
+				.position(|assignment| {
+					assignment.para_id == para_id &&
+						core_index.map_or(true, |core| core == assignment.core)
+				})
+				.or_else(|| scheduled.iter().position(|assignment| assignment.para_id == para_id))
+				.unwrap_or(usize::MAX)
+		});
+
+		let validators = context.validators();
+		let disabled = shared::Pallet::<T>::disabled_validators();
+		let parent_hash = context.parent_hash();
+
+		// The relay-parent of a candidate is either the parent of the block where we include it
+		// (i.e. this code path), or one of the recent blocks before it that are still allowed.
 		let now = <frame_system::Pallet<T>>::block_number();
-		let relay_parent_number = now - One::one();
-		let check_ctx = CandidateCheckContext::<T>::new(now, relay_parent_number);
+		let check_ctx = CandidateCheckContext::<T>::new(now);
+		let configured_backing_votes = check_ctx.config.minimum_backing_votes;
+
+		ensure!(
+			check_ctx
+				.config
+				.max_candidates_per_block
+				.map_or(true, |max| candidates.len() <= max as usize),
+			Error::<T>::TooManyCandidates,
+		);
+		// Candidates of the same para are next to each other after sorting. With multiple cores
+		// per para, each of them has to match a scheduled core of its own instead.
+		let multi_core_paras = check_ctx.config.multi_core_paras;
+		if !multi_core_paras {
+			if let Some(pair) = candidates
+				.windows(2)
+				.find(|pair| pair[0].descriptor().para_id == pair[1].descriptor().para_id)
+			{
+				let para_id = pair[1].descriptor().para_id;
+				Err(Self::rejected(
+					Error::<T>::DuplicateCandidateForPara,
+					Some(para_id),
+					None,
+					None,
+				))?;
+			}
+		}
 
 		// Collect candidate receipts with backers.
 		let mut candidate_receipt_with_backing_validator_indices =
 			Vec::with_capacity(candidates.len());
+		// Collect the validators that seconded each candidate.
+		let mut seconded = Vec::with_capacity(candidates.len());
+		// Collect the indices of the candidates that are dropped without failing the block.
+		let mut dropped = Vec::new();
 
 		// Do all checks before writing storage.
 		let core_indices_and_backers = {
@@ -508,9 +1880,6 @@ impl<T: Config> Pallet<T> {
 				Ok(())
 			};
 
-			let signing_context =
-				SigningContext { parent_hash, session_index: shared::Pallet::<T>::session_index() };
-
 			// We combine an outer loop over candidates with an inner loop over the scheduled,
 			// where each iteration of the outer loop picks up at the position
 			// in scheduled just after the past iteration left off.
@@ -524,12 +1893,24 @@ impl<T: Config> Pallet<T> {
 			'next_backed_candidate: for (candidate_idx, backed_candidate) in
 				candidates.iter().enumerate()
 			{
-				match check_ctx.verify_backed_candidate(
-					parent_hash,
-					parent_storage_root,
-					candidate_idx,
-					backed_candidate,
-				)? {
+				let para_id = backed_candidate.descriptor().para_id;
+				trace_span!("match_candidate", para_id = ?para_id);
+				let rejected = |error, core| Self::rejected(error, Some(para_id), core, None);
+
+				ensure!(
+					!Self::is_disallowed(&backed_candidate.hash()),
+					rejected(Error::<T>::DisallowedCandidate, None),
+				);
+
+				let relay_parent_number = match check_ctx
+					.verify_backed_candidate(
+						parent_hash,
+						parent_storage_root,
+						candidate_idx,
+						backed_candidate,
+					)
+					.map_err(|err| rejected(err, None))?
+				{
 					Err(FailedToCreatePVD) => {
 						log::debug!(
 							target: LOG_TARGET,
@@ -538,41 +1919,81 @@ impl<T: Config> Pallet<T> {
 							parent_hash,
 						);
 						// We don't want to error out here because it will
-						// brick the relay-chain. So we return early without
-						// doing anything.
-						return Ok(ProcessedCandidates::default())
+						// brick the relay-chain. So we only drop this candidate,
+						// leaving its core free.
+						Self::deposit_event(Event::<T>::CandidateDroppedMissingValidationData(
+							backed_candidate.descriptor().para_id,
+							backed_candidate.hash(),
+						));
+						dropped.push(candidate_idx);
+						continue 'next_backed_candidate
 					},
 					Ok(rpn) => rpn,
-				}
+				};
 
-				let para_id = backed_candidate.descriptor().para_id;
+				let signing_context =
+					context.candidate_signing_context(backed_candidate.descriptor().relay_parent);
 				let mut backers = bitvec::bitvec![u8, BitOrderLsb0; 0; validators.len()];
 
 				for (i, assignment) in scheduled[skip..].iter().enumerate() {
 					check_assignment_in_order(assignment)?;
 
 					if para_id == assignment.para_id {
-						if let Some(required_collator) = assignment.required_collator() {
+						if let Some(core_index) = backed_candidate.descriptor().core_index() {
+							// Another core of the para may still be the one the candidate is for.
+							if multi_core_paras && core_index != assignment.core {
+								continue
+							}
 							ensure!(
-								required_collator == &backed_candidate.descriptor().collator,
-								Error::<T>::WrongCollator,
+								core_index == assignment.core,
+								rejected(Error::<T>::InvalidAssignedCore, Some(assignment.core)),
 							);
 						}
+						let core = Some(assignment.core);
+						ensure!(
+							check_ctx.config.core_affinity_allows(para_id, assignment.core),
+							rejected(Error::<T>::DisallowedCore, core),
+						);
+						ensure!(
+							!<paras::Pallet<T>>::is_frozen(&para_id),
+							rejected(Error::<T>::ParaFrozen, core),
+						);
+
+						ensure!(
+							<scheduler::Pallet<T>>::is_allowed_collator(
+								assignment,
+								&backed_candidate.descriptor().collator,
+							),
+							rejected(Error::<T>::WrongCollator, core),
+						);
 
+						let para_free = if multi_core_paras {
+							!<PendingAvailability<T>>::contains_key(&para_id, assignment.core) &&
+								!<PendingAvailabilityCommitments<T>>::contains_key(
+									&para_id,
+									assignment.core,
+								)
+						} else {
+							<PendingAvailability<T>>::iter_key_prefix(&para_id).next().is_none()
+								&& <PendingAvailabilityCommitments<T>>::iter_key_prefix(&para_id)
+									.next()
+									.is_none()
+						};
 						ensure!(
-							<PendingAvailability<T>>::get(&para_id).is_none() &&
-								<PendingAvailabilityCommitments<T>>::get(&para_id).is_none(),
-							Error::<T>::CandidateScheduledBeforeParaFree,
+							para_free,
+							rejected(Error::<T>::CandidateScheduledBeforeParaFree, core),
 						);
 
 						// account for already skipped, and then skip this one.
 						skip = i + skip + 1;
 
 						let group_vals = group_validators(assignment.group_idx)
-							.ok_or_else(|| Error::<T>::InvalidGroupIndex)?;
+							.ok_or_else(|| rejected(Error::<T>::InvalidGroupIndex, core))?;
 
 						// check the signatures in the backing and that it is a majority.
 						{
+							let backing_threshold =
+								minimum_backing_votes(group_vals.len(), configured_backing_votes);
 							let maybe_amount_validated = primitives::check_candidate_backing(
 								&backed_candidate,
 								&signing_context,
@@ -587,18 +2008,43 @@ impl<T: Config> Pallet<T> {
 
 							match maybe_amount_validated {
 								Ok(amount_validated) => ensure!(
-									amount_validated >= minimum_backing_votes(group_vals.len()),
-									Error::<T>::InsufficientBacking,
+									amount_validated >= backing_threshold,
+									rejected(Error::<T>::InsufficientBacking, core),
 								),
 								Err(()) => {
-									Err(Error::<T>::InvalidBacking)?;
+									Err(rejected(Error::<T>::InvalidBacking, core))?;
 								},
 							}
 
+							// Statements of disabled validators don't count towards the backing
+							// threshold. Rather than failing the block, only the candidate is
+							// dropped, leaving its core free.
+							let enabled_backers = backed_candidate
+								.validator_indices
+								.iter()
+								.enumerate()
+								.filter(|(_, signed)| **signed)
+								.filter(|(bit_idx, _)| {
+									group_vals
+										.get(*bit_idx)
+										.map_or(false, |vi| !disabled.contains(vi))
+								})
+								.count();
+							if enabled_backers < backing_threshold {
+								log::debug!(
+									target: LOG_TARGET,
+									"Candidate {} lacks backing from enabled validators",
+									candidate_idx,
+								);
+								dropped.push(candidate_idx);
+								continue 'next_backed_candidate
+							}
+
 							let mut backer_idx_and_attestation =
 								Vec::<(ValidatorIndex, ValidityAttestation)>::with_capacity(
 									backed_candidate.validator_indices.count_ones(),
 								);
+							let mut seconders = Vec::new();
 							let candidate_receipt = backed_candidate.receipt();
 
 							for ((bit_idx, _), attestation) in backed_candidate
@@ -611,10 +2057,20 @@ impl<T: Config> Pallet<T> {
 								let val_idx = group_vals
 									.get(bit_idx)
 									.expect("this query succeeded above; qed");
+								if let ValidityAttestation::Implicit(_) = attestation {
+									seconders.push(*val_idx);
+								}
 								backer_idx_and_attestation.push((*val_idx, attestation));
 
-								backers.set(val_idx.0 as _, true);
+								if !disabled.contains(val_idx) {
+									backers.set(val_idx.0 as _, true);
+								}
 							}
+							seconded.push((
+								relay_parent_number,
+								backed_candidate.hash(),
+								seconders,
+							));
 							candidate_receipt_with_backing_validator_indices
 								.push((candidate_receipt, backer_idx_and_attestation));
 						}
@@ -623,6 +2079,8 @@ impl<T: Config> Pallet<T> {
 							assignment.core,
 							backers,
 							assignment.group_idx,
+							relay_parent_number,
+							<scheduler::Pallet<T>>::assignment_source(assignment),
 						));
 						continue 'next_backed_candidate
 					}
@@ -631,7 +2089,7 @@ impl<T: Config> Pallet<T> {
 				// end of loop reached means that the candidate didn't appear in the non-traversed
 				// section of the `scheduled` slice. either it was not scheduled or didn't appear in
 				// `candidates` in the correct order.
-				ensure!(false, Error::<T>::UnscheduledCandidate);
+				ensure!(false, rejected(Error::<T>::UnscheduledCandidate, None));
 			}
 
 			// check remainder of scheduled cores, if any.
@@ -642,44 +2100,82 @@ impl<T: Config> Pallet<T> {
 			core_indices_and_backers
 		};
 
-		// one more sweep for actually writing to storage.
-		let core_indices = core_indices_and_backers.iter().map(|(c, _, _)| *c).collect();
-		for (candidate, (core, backers, group)) in
-			candidates.into_iter().zip(core_indices_and_backers)
-		{
-			let para_id = candidate.descriptor().para_id;
+		candidates.indexed_retain(|candidate_idx, _| !dropped.contains(&candidate_idx));
 
-			// initialize all availability votes to 0.
-			let availability_votes: BitVec<u8, BitOrderLsb0> =
-				bitvec::bitvec![u8, BitOrderLsb0; 0; validators.len()];
+		// one more sweep for actually writing to storage. It runs in a transaction, so that a
+		// failure part way through leaves none of the candidates behind.
+		let core_indices = core_indices_and_backers.iter().map(|(c, ..)| *c).collect();
+		with_transaction(|| {
+			for (candidate, (core, backers, group, relay_parent_number, assignment_source)) in
+				candidates.into_iter().zip(core_indices_and_backers)
+			{
+				let para_id = candidate.descriptor().para_id;
+				let candidate_hash = candidate.candidate.hash();
+				if let Err(err) =
+					T::InclusionHooks::on_candidate_backing(para_id, candidate_hash, core)
+				{
+					return TransactionOutcome::Rollback(Err(err))
+				}
 
-			Self::deposit_event(Event::<T>::CandidateBacked(
-				candidate.candidate.to_plain(),
-				candidate.candidate.commitments.head_data.clone(),
-				core,
-				group,
-			));
+				// initialize all availability votes to 0.
+				let availability_votes =
+					BoundedBitVec::try_from(bitvec::bitvec![u8, BitOrderLsb0; 0; validators.len()]);
+				let bounded = (availability_votes, BoundedBitVec::try_from(backers.to_bitvec()));
+				let (availability_votes, backers) = match bounded {
+					(Ok(availability_votes), Ok(backers)) => (availability_votes, backers),
+					_ => {
+						let error = Error::<T>::TooManyValidators.into();
+						return TransactionOutcome::Rollback(Err(error))
+					},
+				};
+
+				Self::deposit_event(Event::<T>::CandidateBacked(
+					candidate.candidate.to_plain(),
+					candidate.candidate.commitments.head_data.clone(),
+					core,
+					group,
+				));
+				<statistics::Pallet<T>>::note_backed(para_id, group);
 
-			let candidate_hash = candidate.candidate.hash();
+				T::InclusionHooks::on_candidate_backed(para_id, candidate_hash, core);
 
-			let (descriptor, commitments) =
-				(candidate.candidate.descriptor, candidate.candidate.commitments);
+				let (descriptor, commitments) =
+					(candidate.candidate.descriptor, candidate.candidate.commitments);
 
-			<PendingAvailability<T>>::insert(
-				&para_id,
-				CandidatePendingAvailability {
+				<PendingAvailability<T>>::insert(
+					&para_id,
 					core,
-					hash: candidate_hash,
-					descriptor,
-					availability_votes,
+					CandidatePendingAvailability {
+						core,
+						hash: candidate_hash,
+						descriptor,
+						availability_votes,
+						relay_parent_number,
+						backers,
+						backed_in_number: check_ctx.now,
+						backing_group: group,
+						assignment_source,
+					},
+				);
+				Self::insert_pending_commitments(
+					&para_id,
+					core,
+					commitments,
+					check_ctx.config.pending_commitments_by_reference,
+				);
+			}
+
+			for (relay_parent_number, candidate_hash, seconders) in seconded {
+				Self::note_seconded(
+					context.session_index(),
 					relay_parent_number,
-					backers: backers.to_bitvec(),
-					backed_in_number: check_ctx.now,
-					backing_group: group,
-				},
-			);
-			<PendingAvailabilityCommitments<T>>::insert(&para_id, commitments);
-		}
+					candidate_hash,
+					seconders,
+				);
+			}
+
+			TransactionOutcome::Commit(Ok(()))
+		})?;
 
 		Ok(ProcessedCandidates::<T::Hash> {
 			core_indices,
@@ -687,6 +2183,125 @@ impl<T: Config> Pallet<T> {
 		})
 	}
 
+	/// Diagnose a backed candidate against the given scheduled cores, reporting the outcome of the
+	/// checks of [`Self::process_candidates`] one by one instead of failing on the first one.
+	pub(crate) fn diagnose_candidate<GV>(
+		context: &SignatureCheckContext<T>,
+		parent_storage_root: T::Hash,
+		backed_candidate: &BackedCandidate<T::Hash>,
+		scheduled: &[CoreAssignment],
+		group_validators: GV,
+	) -> InclusionDiagnostics
+	where
+		GV: Fn(GroupIndex) -> Option<Vec<ValidatorIndex>>,
+	{
+		let descriptor = backed_candidate.descriptor();
+		let para_id = descriptor.para_id;
+		let check_ctx = CandidateCheckContext::<T>::new(<frame_system::Pallet<T>>::block_number());
+
+		let collator_signature_ok = match descriptor.version() {
+			CandidateDescriptorVersion::V1 => descriptor.check_collator_signature().is_ok(),
+			CandidateDescriptorVersion::V2 => true,
+			CandidateDescriptorVersion::Unknown => false,
+		};
+
+		let assignment = scheduled.iter().find(|assignment| {
+			assignment.para_id == para_id &&
+				descriptor.core_index().map_or(true, |core| core == assignment.core)
+		});
+
+		let disabled = shared::Pallet::<T>::disabled_validators();
+		let group_vals = assignment.and_then(|assignment| group_validators(assignment.group_idx));
+		let backing = group_vals.map(|group_vals| {
+			let threshold =
+				minimum_backing_votes(group_vals.len(), check_ctx.config.minimum_backing_votes);
+			let votes = primitives::check_candidate_backing(
+				backed_candidate,
+				&context.candidate_signing_context(descriptor.relay_parent),
+				group_vals.len(),
+				|intra_group_vi| {
+					group_vals
+						.get(intra_group_vi)
+						.and_then(|vi| context.validators().get(vi.0 as usize))
+						.cloned()
+				},
+			)
+			.ok()
+			// All votes are valid at this point, only the ones of disabled validators
+			// don't count.
+			.map(|_| {
+				backed_candidate
+					.validator_indices
+					.iter_ones()
+					.filter(|bit_idx| {
+						group_vals.get(*bit_idx).map_or(false, |vi| !disabled.contains(vi))
+					})
+					.count() as u32
+			});
+			(votes, threshold as u32)
+		});
+
+		InclusionDiagnostics {
+			collator_signature_ok,
+			scheduled_core: assignment.map(|assignment| assignment.core),
+			upgrade_allowed: CandidateCheckContext::<T>::code_upgrade_allowed(
+				para_id,
+				&backed_candidate.candidate.commitments.new_validation_code,
+			),
+			expected_validation_data_hash: check_ctx.expected_validation_data_hash(
+				context.parent_hash(),
+				parent_storage_root,
+				para_id,
+				descriptor.relay_parent,
+			),
+			validation_data_hash: descriptor.persisted_validation_data_hash,
+			backing_votes: backing.and_then(|(votes, _)| votes),
+			backing_threshold: backing.map(|(_, threshold)| threshold),
+		}
+	}
+
+	/// The weight of recording the candidate seconded by `seconders` validators, which reads and
+	/// may write one `SecondedCandidates` entry per seconder.
+	pub(crate) fn note_seconded_weight(seconders: usize) -> Weight {
+		T::DbWeight::get().reads_writes(seconders as u64, seconders as u64)
+	}
+
+	/// Record the candidate seconded by each of the given validators at the given relay parent.
+	///
+	/// Validators that already seconded a different candidate at the same relay parent are
+	/// reported to the `BackingEquivocationHandler`.
+	fn note_seconded(
+		session_index: SessionIndex,
+		relay_parent_number: T::BlockNumber,
+		candidate_hash: CandidateHash,
+		seconders: Vec<ValidatorIndex>,
+	) {
+		let mut equivocators = Vec::new();
+		for validator in seconders {
+			match <SecondedCandidates<T>>::get(relay_parent_number, validator) {
+				Some(seconded) if seconded != candidate_hash => equivocators.push(validator),
+				Some(_) => {},
+				None => {
+					<SecondedCandidates<T>>::insert(relay_parent_number, validator, candidate_hash)
+				},
+			}
+		}
+
+		if !equivocators.is_empty() {
+			log::debug!(
+				target: LOG_TARGET,
+				"Validators {:?} seconded candidate {:?} after seconding another candidate at the same relay parent",
+				equivocators,
+				candidate_hash,
+			);
+			T::BackingEquivocationHandler::punish_backing_equivocation(
+				session_index,
+				candidate_hash,
+				equivocators,
+			);
+		}
+	}
+
 	/// Run the acceptance criteria checks on the given candidate commitments.
 	pub(crate) fn check_validation_outputs_for_runtime_api(
 		para_id: ParaId,
@@ -696,10 +2311,11 @@ impl<T: Config> Pallet<T> {
 		// `relay_parent_number` is equal to `now`.
 		let now = <frame_system::Pallet<T>>::block_number();
 		let relay_parent_number = now;
-		let check_ctx = CandidateCheckContext::<T>::new(now, relay_parent_number);
+		let check_ctx = CandidateCheckContext::<T>::new(now);
 
 		if let Err(err) = check_ctx.check_validation_outputs(
 			para_id,
+			relay_parent_number,
 			&validation_outputs.head_data,
 			&validation_outputs.new_validation_code,
 			validation_outputs.processed_downward_messages,
@@ -721,12 +2337,15 @@ impl<T: Config> Pallet<T> {
 
 	fn enact_candidate(
 		relay_parent_number: T::BlockNumber,
+		backed_in_number: T::BlockNumber,
 		receipt: CommittedCandidateReceipt<T::Hash>,
 		backers: BitVec<u8, BitOrderLsb0>,
 		availability_votes: BitVec<u8, BitOrderLsb0>,
 		core_index: CoreIndex,
 		backing_group: GroupIndex,
+		assignment_source: CoreAssignmentSource,
 	) -> Weight {
+		trace_span!("enact_candidate", para_id = ?receipt.descriptor.para_id, core = core_index.0);
 		let plain = receipt.to_plain();
 		let commitments = receipt.commitments;
 		let config = <configuration::Pallet<T>>::config();
@@ -747,48 +2366,96 @@ impl<T: Config> Pallet<T> {
 				.map(|(i, _)| ValidatorIndex(i as _)),
 		);
 
-		// initial weight is config read.
+		// The weight is charged per enacted facet of the candidate, so that it only depends on
+		// the candidate itself. Initial weight is config read.
 		let mut weight = T::DbWeight::get().reads_writes(1, 0);
 		if let Some(new_code) = commitments.new_validation_code {
-			weight += <paras::Pallet<T>>::schedule_code_upgrade(
+			let _ = <paras::Pallet<T>>::schedule_code_upgrade(
 				receipt.descriptor.para_id,
 				new_code,
 				relay_parent_number,
 				&config,
 			);
+			weight += db_ops_weight::<T>(ENACT_UPGRADE_SCHEDULE_DB_OPS);
 		}
 
 		// enact the messaging facet of the candidate.
-		weight += <dmp::Pallet<T>>::prune_dmq(
+		let _ = <dmp::Pallet<T>>::prune_dmq(
 			receipt.descriptor.para_id,
 			commitments.processed_downward_messages,
 		);
-		weight += <ump::Pallet<T>>::receive_upward_messages(
+		let _ = <ump::Pallet<T>>::receive_upward_messages(
 			receipt.descriptor.para_id,
 			commitments.upward_messages,
 		);
-		weight += <hrmp::Pallet<T>>::prune_hrmp(
+		let _ = <hrmp::Pallet<T>>::prune_hrmp(
 			receipt.descriptor.para_id,
 			T::BlockNumber::from(commitments.hrmp_watermark),
 		);
-		weight += <hrmp::Pallet<T>>::queue_outbound_hrmp(
+		let _ = <hrmp::Pallet<T>>::queue_outbound_hrmp(
 			receipt.descriptor.para_id,
 			commitments.horizontal_messages,
 		);
+		weight += db_ops_weight::<T>(ENACT_MESSAGE_QUEUES_DB_OPS);
+
+		let now = <frame_system::Pallet<T>>::block_number();
+		if !config.included_candidates_retention.is_zero() {
+			// At most one candidate is included per core and block.
+			let _ = <IncludedCandidates<T>>::try_append(
+				now,
+				IncludedCandidate {
+					candidate_hash: plain.hash(),
+					para_id: plain.descriptor.para_id,
+					relay_parent: plain.descriptor.relay_parent,
+					core_index,
+				},
+			);
+			weight += T::DbWeight::get().writes(1);
+		}
+
+		let candidate_hash = plain.hash();
+		<CandidateIncludedAt<T>>::insert(candidate_hash, (now, core_index));
+		<CandidatesIncludedInSession<T>>::append(
+			shared::Pallet::<T>::session_index(),
+			candidate_hash,
+		);
+		weight += T::DbWeight::get().writes(2);
 
+		<statistics::Pallet<T>>::note_included(receipt.descriptor.para_id, backing_group);
+		// v2 descriptors reuse the collator field for the core and session index.
+		if receipt.descriptor.version() == CandidateDescriptorVersion::V1 {
+			<statistics::Pallet<T>>::note_collator_head(receipt.descriptor.collator.clone());
+		}
 		Self::deposit_event(Event::<T>::CandidateIncluded(
 			plain,
 			commitments.head_data.clone(),
 			core_index,
 			backing_group,
+			now.saturating_sub(backed_in_number),
+			assignment_source,
 		));
+		T::InclusionHooks::on_candidate_included(
+			receipt.descriptor.para_id,
+			candidate_hash,
+			core_index,
+		);
 
-		weight +
-			<paras::Pallet<T>>::note_new_head(
-				receipt.descriptor.para_id,
-				commitments.head_data,
-				relay_parent_number,
-			)
+		let _ = <paras::Pallet<T>>::note_new_head(
+			receipt.descriptor.para_id,
+			commitments.head_data,
+			relay_parent_number,
+		);
+		weight + db_ops_weight::<T>(ENACT_HEAD_NOTE_DB_OPS)
+	}
+
+	/// The weight of enacting a single candidate, assuming it schedules a code upgrade.
+	///
+	/// This is an upper bound of the weight returned by `enact_candidate`.
+	pub(crate) fn max_enactment_weight() -> Weight {
+		T::DbWeight::get().reads_writes(1, 3) +
+			db_ops_weight::<T>(ENACT_HEAD_NOTE_DB_OPS) +
+			db_ops_weight::<T>(ENACT_UPGRADE_SCHEDULE_DB_OPS) +
+			db_ops_weight::<T>(ENACT_MESSAGE_QUEUES_DB_OPS)
 	}
 
 	/// Cleans up all paras pending availability that the predicate returns true for.
@@ -803,19 +2470,25 @@ impl<T: Config> Pallet<T> {
 		let mut cleaned_up_ids = Vec::new();
 		let mut cleaned_up_cores = Vec::new();
 
-		for (para_id, pending_record) in <PendingAvailability<T>>::iter() {
-			if pred(pending_record.core, pending_record.backed_in_number) {
-				cleaned_up_ids.push(para_id);
-				cleaned_up_cores.push(pending_record.core);
+		// Candidates awaiting a deferred enactment are already available and never time out.
+		let ready_to_enact = <ReadyToEnact<T>>::get();
+		for (para_id, core, pending_record) in <PendingAvailability<T>>::iter() {
+			if ready_to_enact.contains(&pending_record.hash) {
+				continue
+			}
+			if pred(core, pending_record.backed_in_number) {
+				cleaned_up_ids.push((para_id, core));
+				cleaned_up_cores.push(core);
 			}
 		}
 
-		for para_id in cleaned_up_ids {
-			let pending = <PendingAvailability<T>>::take(&para_id);
-			let commitments = <PendingAvailabilityCommitments<T>>::take(&para_id);
+		for (para_id, core) in cleaned_up_ids {
+			let pending = <PendingAvailability<T>>::take(&para_id, core);
+			let commitments = Self::take_pending_commitments(&para_id, core);
 
 			if let (Some(pending), Some(commitments)) = (pending, commitments) {
 				// defensive: this should always be true.
+				let (blocks_pending, availability_votes) = Self::timeout_diagnostics(&pending);
 				let candidate = CandidateReceipt {
 					descriptor: pending.descriptor,
 					commitments_hash: commitments.hash(),
@@ -825,7 +2498,11 @@ impl<T: Config> Pallet<T> {
 					candidate,
 					commitments.head_data,
 					pending.core,
+					blocks_pending,
+					availability_votes,
 				));
+				T::InclusionHooks::on_candidate_timed_out(para_id, pending.hash, pending.core);
+				<statistics::Pallet<T>>::note_timed_out();
 			}
 		}
 
@@ -839,30 +2516,35 @@ impl<T: Config> Pallet<T> {
 		let mut cleaned_up_ids = Vec::new();
 		let mut cleaned_up_cores = Vec::new();
 
-		for (para_id, pending_record) in <PendingAvailability<T>>::iter() {
+		for (para_id, core, pending_record) in <PendingAvailability<T>>::iter() {
 			if disputed.contains(&pending_record.hash) {
-				cleaned_up_ids.push(para_id);
-				cleaned_up_cores.push(pending_record.core);
+				cleaned_up_ids.push((para_id, core));
+				cleaned_up_cores.push(core);
 			}
 		}
 
-		for para_id in cleaned_up_ids {
-			let _ = <PendingAvailability<T>>::take(&para_id);
-			let _ = <PendingAvailabilityCommitments<T>>::take(&para_id);
+		for (para_id, core) in cleaned_up_ids {
+			let _ = <PendingAvailability<T>>::take(&para_id, core);
+			let _ = Self::take_pending_commitments(&para_id, core);
 		}
 
 		cleaned_up_cores
 	}
 
 	/// Forcibly enact the candidate with the given ID as though it had been deemed available
-	/// by bitfields.
+	/// by bitfields. Of several candidates of the para, the oldest one is enacted, see
+	/// `oldest_pending_core`.
 	///
 	/// Is a no-op if there is no candidate pending availability for this para-id.
 	/// This should generally not be used but it is useful during execution of Runtime APIs,
 	/// where the changes to the state are expected to be discarded directly after.
 	pub(crate) fn force_enact(para: ParaId) {
-		let pending = <PendingAvailability<T>>::take(&para);
-		let commitments = <PendingAvailabilityCommitments<T>>::take(&para);
+		let core = match Self::oldest_pending_core(&para) {
+			Some(core) => core,
+			None => return,
+		};
+		let pending = <PendingAvailability<T>>::take(&para, core);
+		let commitments = Self::take_pending_commitments(&para, core);
 
 		if let (Some(pending), Some(commitments)) = (pending, commitments) {
 			let candidate =
@@ -870,43 +2552,216 @@ impl<T: Config> Pallet<T> {
 
 			Self::enact_candidate(
 				pending.relay_parent_number,
+				pending.backed_in_number,
 				candidate,
-				pending.backers,
-				pending.availability_votes,
+				pending.backers.into_inner(),
+				pending.availability_votes.into_inner(),
 				pending.core,
 				pending.backing_group,
+				pending.assignment_source,
 			);
 		}
 	}
 
+	/// The core of the oldest candidate the given para has pending availability, if any.
+	///
+	/// With `multi_core_paras`, the candidates of a para pending availability form a queue ordered
+	/// by their relay parent, then by the core they occupy. This is the head of that queue.
+	fn oldest_pending_core(para: &ParaId) -> Option<CoreIndex> {
+		<PendingAvailability<T>>::iter_prefix(para)
+			.min_by_key(|(core, pending)| (pending.relay_parent_number, core.0))
+			.map(|(core, _)| core)
+	}
+
+	/// Store the commitments of a candidate pending availability for the given para and core.
+	///
+	/// With `by_reference`, the head data and the validation code are moved to
+	/// `PendingCommitmentsBlobs` and only their hashes are kept for the para and core.
+	fn insert_pending_commitments(
+		para: &ParaId,
+		core: CoreIndex,
+		mut commitments: CandidateCommitments,
+		by_reference: bool,
+	) {
+		if by_reference {
+			let head_data =
+				Self::put_commitments_blob(sp_std::mem::take(&mut commitments.head_data.0));
+			let validation_code = commitments
+				.new_validation_code
+				.take()
+				.map(|code| Self::put_commitments_blob(code.0));
+			<PendingCommitmentsRefs<T>>::insert(para, core, (head_data, validation_code));
+		}
+		<PendingAvailabilityCommitments<T>>::insert(para, core, commitments);
+	}
+
+	/// Remove the commitments of the candidate pending availability for the given para and core,
+	/// putting back the head data and validation code that are stored by reference.
+	fn take_pending_commitments(para: &ParaId, core: CoreIndex) -> Option<CandidateCommitments> {
+		let mut commitments = <PendingAvailabilityCommitments<T>>::take(para, core)?;
+		if let Some((head_data, validation_code)) = <PendingCommitmentsRefs<T>>::take(para, core) {
+			commitments.head_data = HeadData(Self::take_commitments_blob(&head_data)?);
+			commitments.new_validation_code = match validation_code {
+				Some(hash) => Some(ValidationCode(Self::take_commitments_blob(&hash)?)),
+				None => None,
+			};
+		}
+		Some(commitments)
+	}
+
+	/// Returns the full commitments of the candidate pending availability for the given para and
+	/// core.
+	fn pending_commitments(para: &ParaId, core: CoreIndex) -> Option<CandidateCommitments> {
+		let mut commitments = <PendingAvailabilityCommitments<T>>::get(para, core)?;
+		if let Some((head_data, validation_code)) = <PendingCommitmentsRefs<T>>::get(para, core) {
+			commitments.head_data = HeadData(<PendingCommitmentsBlobs<T>>::get(&head_data)?.1);
+			commitments.new_validation_code = match validation_code {
+				Some(hash) => Some(ValidationCode(<PendingCommitmentsBlobs<T>>::get(&hash)?.1)),
+				None => None,
+			};
+		}
+		Some(commitments)
+	}
+
+	/// Store a blob referred to by the commitments of a candidate pending availability, or add a
+	/// reference to it if it is already stored. Returns its hash.
+	fn put_commitments_blob(blob: Vec<u8>) -> Hash {
+		let hash = BlakeTwo256::hash(&blob);
+		<PendingCommitmentsBlobs<T>>::mutate(&hash, |entry| match entry {
+			Some((refs, _)) => *refs = refs.saturating_add(1),
+			None => *entry = Some((1, blob)),
+		});
+		hash
+	}
+
+	/// Drop a reference to a blob of the commitments of a candidate pending availability, removing
+	/// it once it is no longer referred to. Returns the blob.
+	fn take_commitments_blob(hash: &Hash) -> Option<Vec<u8>> {
+		<PendingCommitmentsBlobs<T>>::mutate_exists(hash, |entry| {
+			let (refs, blob) = entry.as_mut()?;
+			if *refs > 1 {
+				*refs -= 1;
+				Some(blob.clone())
+			} else {
+				entry.take().map(|(_, blob)| blob)
+			}
+		})
+	}
+
 	/// Returns the `CommittedCandidateReceipt` pending availability for the para provided, if any.
+	/// Of several candidates of the para, the oldest one is returned, see `oldest_pending_core`.
 	pub(crate) fn candidate_pending_availability(
 		para: ParaId,
 	) -> Option<CommittedCandidateReceipt<T::Hash>> {
-		<PendingAvailability<T>>::get(&para)
+		let core = Self::oldest_pending_core(&para)?;
+		<PendingAvailability<T>>::get(&para, core)
 			.map(|p| p.descriptor)
-			.and_then(|d| <PendingAvailabilityCommitments<T>>::get(&para).map(move |c| (d, c)))
+			.and_then(|d| Self::pending_commitments(&para, core).map(move |c| (d, c)))
 			.map(|(d, c)| CommittedCandidateReceipt { descriptor: d, commitments: c })
 	}
 
+	/// Returns the `CommittedCandidateReceipt`s pending availability for all paras, ordered by the
+	/// index of the core they occupy.
+	pub(crate) fn candidates_pending_availability() -> Vec<CommittedCandidateReceipt<T::Hash>> {
+		let mut pending: Vec<_> = <PendingAvailability<T>>::iter()
+			.filter_map(|(para, core, p)| {
+				Self::pending_commitments(&para, core).map(|commitments| {
+					(p.core, CommittedCandidateReceipt { descriptor: p.descriptor, commitments })
+				})
+			})
+			.collect();
+		pending.sort_unstable_by_key(|(core, _)| core.0);
+		pending.into_iter().map(|(_, receipt)| receipt).collect()
+	}
+
 	/// Returns the metadata around the candidate pending availability for the
-	/// para provided, if any.
+	/// para and core provided, if any.
 	pub(crate) fn pending_availability(
 		para: ParaId,
-	) -> Option<CandidatePendingAvailability<T::Hash, T::BlockNumber>> {
-		<PendingAvailability<T>>::get(&para)
+		core: CoreIndex,
+	) -> Option<CandidatePendingAvailability<T::Hash, T::BlockNumber, T::MaxValidators>> {
+		<PendingAvailability<T>>::get(&para, core)
+	}
+
+	/// Whether the para provided has a candidate pending availability on any core.
+	pub(crate) fn is_pending_availability(para: ParaId) -> bool {
+		Self::oldest_pending_core(&para).is_some()
+	}
+
+	/// Returns the availability votes collected for the candidate occupying the given core, if any.
+	pub(crate) fn availability_votes(core: CoreIndex) -> Option<CoreAvailabilityVotes> {
+		let para_id = <scheduler::Pallet<T>>::core_para(core)?;
+		let pending = <PendingAvailability<T>>::get(&para_id, core)?;
+		let config = <configuration::Pallet<T>>::config();
+		let n_validators = pending.availability_votes.len();
+
+		Some(CoreAvailabilityVotes {
+			candidate_hash: pending.hash,
+			votes: pending.availability_votes.count_ones() as u32,
+			threshold: availability_threshold(&config, n_validators) as u32,
+		})
+	}
+
+	/// Returns where the assignment of each occupied core came from, ordered by core index.
+	pub(crate) fn core_assignment_sources() -> Vec<(CoreIndex, CoreAssignmentSource)> {
+		let mut sources = <PendingAvailability<T>>::iter_values()
+			.map(|pending| (pending.core, pending.assignment_source))
+			.collect::<Vec<_>>();
+		sources.sort_by_key(|(core, _)| *core);
+		sources
+	}
+
+	/// Returns the candidates included in the relay chain block with the given number, if that
+	/// block is still within the retention window.
+	pub(crate) fn included_candidates(at: T::BlockNumber) -> Vec<IncludedCandidate<T::Hash>> {
+		<IncludedCandidates<T>>::get(at).into_inner()
+	}
+
+	/// Returns the relay chain block number the candidate was included in and the core it
+	/// occupied, if it was included within the last `dispute_period` sessions.
+	pub(crate) fn candidate_included_at(
+		candidate_hash: &CandidateHash,
+	) -> Option<(T::BlockNumber, CoreIndex)> {
+		<CandidateIncludedAt<T>>::get(candidate_hash)
+	}
+
+	/// Disallow the inclusion of the candidate for the current and the next `dispute_period`
+	/// sessions.
+	pub(crate) fn note_disallowed_candidate(candidate_hash: CandidateHash) {
+		if <DisallowedCandidates<T>>::contains_key(&candidate_hash) {
+			return
+		}
+
+		<DisallowedCandidates<T>>::insert(candidate_hash, shared::Pallet::<T>::session_index());
+		Self::deposit_event(Event::<T>::CandidateDisallowed(candidate_hash));
+	}
+
+	/// Whether the candidate can never be included.
+	pub(crate) fn is_disallowed(candidate_hash: &CandidateHash) -> bool {
+		<DisallowedCandidates<T>>::contains_key(candidate_hash)
 	}
 }
 
-const fn availability_threshold(n_validators: usize) -> usize {
-	supermajority_threshold(n_validators)
+/// The number of availability votes a candidate needs before it can be included.
+pub(crate) fn availability_threshold<BlockNumber>(
+	config: &configuration::HostConfiguration<BlockNumber>,
+	n_validators: usize,
+) -> usize {
+	config
+		.availability_threshold
+		.mul_ceil(n_validators)
+		.max(config.minimum_availability_votes as usize)
+		.min(n_validators)
+}
+
+/// The weight of the given storage reads and writes.
+fn db_ops_weight<T: frame_system::Config>((reads, writes): (u64, u64)) -> Weight {
+	T::DbWeight::get().reads_writes(reads, writes)
 }
 
 #[derive(derive_more::From, Debug)]
 enum AcceptanceCheckErr<BlockNumber> {
-	HeadDataTooLarge,
-	PrematureCodeUpgrade,
-	NewCodeTooLarge,
+	Candidate(CandidateCheckError),
 	ProcessedDownwardMessages(dmp::ProcessedDownwardMessagesAcceptanceErr),
 	UpwardMessages(ump::AcceptanceCheckErr),
 	HrmpWatermark(hrmp::HrmpWatermarkAcceptanceErr<BlockNumber>),
@@ -919,9 +2774,7 @@ impl<BlockNumber> AcceptanceCheckErr<BlockNumber> {
 	fn strip_into_dispatch_err<T: Config>(self) -> Error<T> {
 		use AcceptanceCheckErr::*;
 		match self {
-			HeadDataTooLarge => Error::<T>::HeadDataTooLarge,
-			PrematureCodeUpgrade => Error::<T>::PrematureCodeUpgrade,
-			NewCodeTooLarge => Error::<T>::NewCodeTooLarge,
+			Candidate(err) => err.into(),
 			ProcessedDownwardMessages(_) => Error::<T>::IncorrectDownwardMessageHandling,
 			UpwardMessages(_) => Error::<T>::InvalidUpwardMessages,
 			HrmpWatermark(_) => Error::<T>::HrmpWatermarkMishandling,
@@ -930,11 +2783,28 @@ impl<BlockNumber> AcceptanceCheckErr<BlockNumber> {
 	}
 }
 
+impl<T: Config> From<CandidateCheckError> for Error<T> {
+	fn from(err: CandidateCheckError) -> Self {
+		use CandidateCheckError::*;
+		match err {
+			ValidationDataHashMismatch => Error::<T>::ValidationDataHashMismatch,
+			InvalidDescriptorVersion => Error::<T>::InvalidDescriptorVersion,
+			NotCollatorSigned => Error::<T>::NotCollatorSigned,
+			InvalidSessionIndex => Error::<T>::InvalidSessionIndex,
+			InvalidValidationCodeHash => Error::<T>::InvalidValidationCodeHash,
+			ParaHeadMismatch => Error::<T>::ParaHeadMismatch,
+			HeadDataTooLarge => Error::<T>::HeadDataTooLarge,
+			PrematureCodeUpgrade => Error::<T>::PrematureCodeUpgrade,
+			NewCodeTooLarge => Error::<T>::NewCodeTooLarge,
+			InvalidBacking => Error::<T>::InvalidBacking,
+		}
+	}
+}
+
 /// A collection of data required for checking a candidate.
 pub(crate) struct CandidateCheckContext<T: Config> {
 	config: configuration::HostConfiguration<T::BlockNumber>,
 	now: T::BlockNumber,
-	relay_parent_number: T::BlockNumber,
 }
 
 /// An error indicating that creating Persisted Validation Data failed
@@ -942,90 +2812,120 @@ pub(crate) struct CandidateCheckContext<T: Config> {
 pub(crate) struct FailedToCreatePVD;
 
 impl<T: Config> CandidateCheckContext<T> {
-	pub(crate) fn new(now: T::BlockNumber, relay_parent_number: T::BlockNumber) -> Self {
-		Self { config: <configuration::Pallet<T>>::config(), now, relay_parent_number }
+	pub(crate) fn new(now: T::BlockNumber) -> Self {
+		Self { config: <configuration::Pallet<T>>::config(), now }
 	}
 
 	/// Execute verification of the candidate.
 	///
 	/// Assures:
 	///  * correct expected relay parent reference
-	///  * collator signature check passes
-	///  * code hash of commitments matches current code hash
-	///  * para head in the descriptor and commitments match
+	///  * the candidate passes `check_candidate_receipt` against the current relay chain state,
+	///    the same checks node-side backing applies
+	///  * the messaging outputs pass the acceptance criteria
+	///
+	/// Returns the number of the candidate's relay parent.
 	pub(crate) fn verify_backed_candidate(
 		&self,
 		parent_hash: <T as frame_system::Config>::Hash,
 		parent_storage_root: T::Hash,
 		candidate_idx: usize,
 		backed_candidate: &BackedCandidate<<T as frame_system::Config>::Hash>,
-	) -> Result<Result<(), FailedToCreatePVD>, Error<T>> {
+	) -> Result<Result<T::BlockNumber, FailedToCreatePVD>, Error<T>> {
 		let para_id = backed_candidate.descriptor().para_id;
-		let now = <frame_system::Pallet<T>>::block_number();
-		let relay_parent_number = now - One::one();
-
-		{
-			// this should never fail because the para is registered
-			let persisted_validation_data = match crate::util::make_persisted_validation_data::<T>(
-				para_id,
-				relay_parent_number,
-				parent_storage_root,
-			) {
-				Some(l) => l,
-				None => return Ok(Err(FailedToCreatePVD)),
-			};
-
-			let expected = persisted_validation_data.hash();
-
-			ensure!(
-				expected == backed_candidate.descriptor().persisted_validation_data_hash,
-				Error::<T>::ValidationDataHashMismatch,
-			);
-		}
-
-		// we require that the candidate is in the context of the parent block.
-		ensure!(
-			backed_candidate.descriptor().relay_parent == parent_hash,
-			Error::<T>::CandidateNotInParentContext,
-		);
-		ensure!(
-			backed_candidate.descriptor().check_collator_signature().is_ok(),
-			Error::<T>::NotCollatorSigned,
-		);
-
-		let validation_code_hash = <paras::Pallet<T>>::current_code_hash(para_id)
-			// A candidate for a parachain without current validation code is not scheduled.
-			.ok_or_else(|| Error::<T>::UnscheduledCandidate)?;
-		ensure!(
-			backed_candidate.descriptor().validation_code_hash == validation_code_hash,
-			Error::<T>::InvalidValidationCodeHash,
-		);
+		let relay_parent = backed_candidate.descriptor().relay_parent;
 
-		ensure!(
-			backed_candidate.descriptor().para_head ==
-				backed_candidate.candidate.commitments.head_data.hash(),
-			Error::<T>::ParaHeadMismatch,
-		);
+		let (relay_parent_number, relay_parent_storage_root) = self
+			.relay_parent_context(parent_hash, parent_storage_root, relay_parent)
+			.ok_or(Error::<T>::CandidateNotInParentContext)?;
 
-		if let Err(err) = self.check_validation_outputs(
+		// this should never fail because the para is registered
+		let persisted_validation_data = match crate::util::make_persisted_validation_data::<T>(
 			para_id,
-			&backed_candidate.candidate.commitments.head_data,
-			&backed_candidate.candidate.commitments.new_validation_code,
-			backed_candidate.candidate.commitments.processed_downward_messages,
-			&backed_candidate.candidate.commitments.upward_messages,
-			T::BlockNumber::from(backed_candidate.candidate.commitments.hrmp_watermark),
-			&backed_candidate.candidate.commitments.horizontal_messages,
+			relay_parent_number,
+			relay_parent_storage_root,
 		) {
+			Some(l) => l,
+			None => return Ok(Err(FailedToCreatePVD)),
+		};
+
+		let commitments = &backed_candidate.candidate.commitments;
+		let params = CandidateCheckParams {
+			persisted_validation_data_hash: persisted_validation_data.hash(),
+			validation_code_hash: <paras::Pallet<T>>::current_code_hash(para_id)
+				// A candidate for a parachain without current validation code is not scheduled.
+				.ok_or_else(|| Error::<T>::UnscheduledCandidate)?,
+			session_index: shared::Pallet::<T>::session_index(),
+			v1_descriptors_rejected: self.config.v1_candidate_descriptors_rejected,
+			code_upgrade_allowed: Self::code_upgrade_allowed(
+				para_id,
+				&commitments.new_validation_code,
+			),
+			max_head_data_size: self.config.max_head_data_size_of(para_id),
+			max_code_size: self.config.max_code_size,
+		};
+
+		let outputs_check = check_candidate_receipt(&backed_candidate.candidate, &params)
+			.map_err(AcceptanceCheckErr::from)
+			.and_then(|()| {
+				self.check_messaging_outputs(
+					para_id,
+					relay_parent_number,
+					commitments.processed_downward_messages,
+					&commitments.upward_messages,
+					T::BlockNumber::from(commitments.hrmp_watermark),
+					&commitments.horizontal_messages,
+				)
+			});
+		if let Err(err) = outputs_check {
 			log::debug!(
 				target: LOG_TARGET,
-				"Validation outputs checking during inclusion of a candidate {} for parachain `{}` failed: {:?}",
+				"Checking candidate {} for parachain `{}` during inclusion failed: {:?}",
 				candidate_idx,
 				u32::from(para_id),
 				err,
 			);
 			Err(err.strip_into_dispatch_err::<T>())?;
 		};
-		Ok(Ok(()))
+		Ok(Ok(relay_parent_number))
+	}
+
+	/// The number and the storage root of the given relay parent, if candidates may be built on
+	/// it.
+	///
+	/// We require that a candidate is in the context of the parent block, or of one of the recent
+	/// blocks before it that are still allowed as relay parents.
+	fn relay_parent_context(
+		&self,
+		parent_hash: T::Hash,
+		parent_storage_root: T::Hash,
+		relay_parent: T::Hash,
+	) -> Option<(T::BlockNumber, T::Hash)> {
+		if relay_parent == parent_hash {
+			Some((self.now - One::one(), parent_storage_root))
+		} else {
+			shared::Pallet::<T>::allowed_relay_parent(relay_parent)
+				.map(|allowed| (allowed.number, allowed.state_root))
+		}
+	}
+
+	/// The hash of the persisted validation data a candidate of the given para built on the given
+	/// relay parent has to commit to, if it can be created.
+	fn expected_validation_data_hash(
+		&self,
+		parent_hash: T::Hash,
+		parent_storage_root: T::Hash,
+		para_id: ParaId,
+		relay_parent: T::Hash,
+	) -> Option<Hash> {
+		let (relay_parent_number, relay_parent_storage_root) =
+			self.relay_parent_context(parent_hash, parent_storage_root, relay_parent)?;
+		crate::util::make_persisted_validation_data::<T>(
+			para_id,
+			relay_parent_number,
+			relay_parent_storage_root,
+		)
+		.map(|persisted_validation_data| persisted_validation_data.hash())
 	}
 
 	/// Check the given outputs after candidate validation on whether it passes the acceptance
@@ -1033,6 +2933,7 @@ impl<T: Config> CandidateCheckContext<T> {
 	fn check_validation_outputs(
 		&self,
 		para_id: ParaId,
+		relay_parent_number: T::BlockNumber,
 		head_data: &HeadData,
 		new_validation_code: &Option<primitives::ValidationCode>,
 		processed_downward_messages: u32,
@@ -1040,29 +2941,179 @@ impl<T: Config> CandidateCheckContext<T> {
 		hrmp_watermark: T::BlockNumber,
 		horizontal_messages: &[primitives::OutboundHrmpMessage<ParaId>],
 	) -> Result<(), AcceptanceCheckErr<T::BlockNumber>> {
-		ensure!(
-			head_data.0.len() <= self.config.max_head_data_size as _,
-			AcceptanceCheckErr::HeadDataTooLarge,
-		);
+		check_candidate_outputs(
+			head_data,
+			new_validation_code.as_ref(),
+			Self::code_upgrade_allowed(para_id, new_validation_code),
+			self.config.max_head_data_size_of(para_id),
+			self.config.max_code_size,
+		)?;
 
-		// if any, the code upgrade attempt is allowed.
-		if let Some(new_validation_code) = new_validation_code {
-			ensure!(
-				<paras::Pallet<T>>::can_upgrade_validation_code(para_id),
-				AcceptanceCheckErr::PrematureCodeUpgrade,
-			);
-			ensure!(
-				new_validation_code.0.len() <= self.config.max_code_size as _,
-				AcceptanceCheckErr::NewCodeTooLarge,
-			);
-		}
+		self.check_messaging_outputs(
+			para_id,
+			relay_parent_number,
+			processed_downward_messages,
+			upward_messages,
+			hrmp_watermark,
+			horizontal_messages,
+		)
+	}
+
+	/// Whether the para may upgrade its code, if the candidate attempts to.
+	fn code_upgrade_allowed(
+		para_id: ParaId,
+		new_validation_code: &Option<primitives::ValidationCode>,
+	) -> bool {
+		new_validation_code.is_none() || <paras::Pallet<T>>::can_upgrade_validation_code(para_id)
+	}
 
-		// check if the candidate passes the messaging acceptance criteria
-		<dmp::Pallet<T>>::check_processed_downward_messages(para_id, processed_downward_messages)?;
+	/// Check whether the messaging outputs of a candidate pass the acceptance criteria.
+	fn check_messaging_outputs(
+		&self,
+		para_id: ParaId,
+		relay_parent_number: T::BlockNumber,
+		processed_downward_messages: u32,
+		upward_messages: &[primitives::UpwardMessage],
+		hrmp_watermark: T::BlockNumber,
+		horizontal_messages: &[primitives::OutboundHrmpMessage<ParaId>],
+	) -> Result<(), AcceptanceCheckErr<T::BlockNumber>> {
+		<dmp::Pallet<T>>::check_processed_downward_messages(
+			para_id,
+			relay_parent_number,
+			processed_downward_messages,
+		)?;
 		<ump::Pallet<T>>::check_upward_messages(&self.config, para_id, upward_messages)?;
-		<hrmp::Pallet<T>>::check_hrmp_watermark(para_id, self.relay_parent_number, hrmp_watermark)?;
+		<hrmp::Pallet<T>>::check_hrmp_watermark(para_id, relay_parent_number, hrmp_watermark)?;
 		<hrmp::Pallet<T>>::check_outbound_hrmp(&self.config, para_id, horizontal_messages)?;
 
 		Ok(())
 	}
 }
+
+/// The validator keys, validator groups and signing contexts the signatures of a block are
+/// checked against.
+///
+/// None of them change within a block, so the inherent reads them from storage once and shares
+/// them between the checks of all its bitfields and candidates.
+pub(crate) struct SignatureCheckContext<T: Config> {
+	validators: Vec<ValidatorId>,
+	validator_groups: Vec<Vec<ValidatorIndex>>,
+	session_index: SessionIndex,
+	bitfield_signing_context: SigningContext<BitfieldSigningParent<T::Hash>>,
+	/// The validators of the previous session and the signing context of their bitfields, within
+	/// the `bitfield_grace_blocks` after a session change.
+	previous_session: Option<(Vec<ValidatorId>, SigningContext<BitfieldSigningParent<T::Hash>>)>,
+}
+
+impl<T: Config> SignatureCheckContext<T> {
+	/// Read the context of the current block from storage.
+	pub(crate) fn load() -> Self {
+		let parent_hash = <frame_system::Pallet<T>>::parent_hash();
+		let session_index = shared::Pallet::<T>::session_index();
+		let bitfield_signing_context =
+			Pallet::<T>::bitfield_signing_context(parent_hash, session_index);
+		let previous_session =
+			Pallet::<T>::bitfield_grace_session(session_index).and_then(|previous_session| {
+				let validators =
+					<session_info::Pallet<T>>::session_info(previous_session)?.validators.to_vec();
+				let signing_context =
+					Pallet::<T>::bitfield_signing_context(parent_hash, previous_session);
+				Some((validators, signing_context))
+			});
+		Self {
+			validators: shared::Pallet::<T>::active_validator_keys(),
+			validator_groups: <scheduler::Pallet<T>>::validator_groups(),
+			session_index,
+			bitfield_signing_context,
+			previous_session,
+		}
+	}
+
+	/// The keys of the active validators, by validator index.
+	pub(crate) fn validators(&self) -> &[ValidatorId] {
+		&self.validators
+	}
+
+	pub(crate) fn session_index(&self) -> SessionIndex {
+		self.session_index
+	}
+
+	pub(crate) fn parent_hash(&self) -> T::Hash {
+		self.bitfield_signing_context.parent_hash.parent_hash
+	}
+
+	/// The signing context availability bitfields of this block are checked against.
+	pub(crate) fn bitfield_signing_context(
+		&self,
+	) -> &SigningContext<BitfieldSigningParent<T::Hash>> {
+		&self.bitfield_signing_context
+	}
+
+	/// The signing context of the statements backing a candidate built on `relay_parent`.
+	pub(crate) fn candidate_signing_context(
+		&self,
+		relay_parent: T::Hash,
+	) -> SigningContext<T::Hash> {
+		SigningContext { parent_hash: relay_parent, session_index: self.session_index }
+	}
+
+	/// The validators of the given group, like [`scheduler::Pallet::group_validators`].
+	pub(crate) fn group_validators(&self, group_index: GroupIndex) -> Option<Vec<ValidatorIndex>> {
+		self.validator_groups.get(group_index.0 as usize).cloned()
+	}
+
+	/// Whether bitfields signed in the previous session are accepted in this block.
+	pub(crate) fn accepts_late_bitfields(&self) -> bool {
+		self.previous_session.is_some()
+	}
+
+	/// Tell the bitfields signed in this session apart from the late ones signed in the previous
+	/// session, by checking all of their signatures.
+	///
+	/// Returns the bitfields signed in this session, the late bitfields of the validators that are
+	/// still active, and the validator indices of the bitfields signed in neither session. Late
+	/// bitfields of validators that are no longer active are dropped.
+	pub(crate) fn split_late_bitfields(
+		&self,
+		bitfields: UncheckedSignedAvailabilityBitfields,
+	) -> (UncheckedSignedAvailabilityBitfields, Vec<LateBitfield>, Vec<ValidatorIndex>) {
+		let mut current = Vec::with_capacity(bitfields.len());
+		let mut late = Vec::new();
+		let mut invalid = Vec::new();
+		for bitfield in bitfields {
+			let validator_index = bitfield.unchecked_validator_index();
+			let signed_now = self.validators.get(validator_index.0 as usize).map_or(false, |key| {
+				bitfield.check_signature(&self.bitfield_signing_context, key).is_ok()
+			});
+			if signed_now {
+				current.push(bitfield);
+				continue
+			}
+
+			let previous_key = self.previous_session.as_ref().and_then(|(validators, context)| {
+				let key = validators.get(validator_index.0 as usize)?;
+				bitfield.check_signature(context, key).ok().map(|_| key)
+			});
+			match previous_key {
+				Some(key) => {
+					if let Some(position) = self.validators.iter().position(|k| k == key) {
+						let validator_index = ValidatorIndex(position as u32);
+						late.push(LateBitfield { signed: bitfield, validator_index });
+					}
+				},
+				None => invalid.push(validator_index),
+			}
+		}
+		(current, late, invalid)
+	}
+}
+
+/// An availability bitfield signed in the previous session, accepted within the
+/// `bitfield_grace_blocks` after a session change.
+#[derive(Clone)]
+pub(crate) struct LateBitfield {
+	/// The bitfield as signed in the previous session.
+	pub(crate) signed: UncheckedSignedAvailabilityBitfield,
+	/// The index of its validator in the current session.
+	pub(crate) validator_index: ValidatorIndex,
+}
@@ -19,24 +19,33 @@ use crate::{
 	configuration::HostConfiguration,
 	initializer::SessionChangeNotification,
 	mock::{
-		new_test_ext, Configuration, MockGenesisConfig, ParaInclusion, Paras, ParasShared, System,
-		Test,
+		inclusion_hook_calls, new_test_ext, set_failing_candidate_backing, Configuration,
+		InclusionHookCall, MockGenesisConfig, ParaInclusion, Paras, ParasShared, RuntimeOrigin,
+		Statistics, System, Test,
 	},
 	paras::{ParaGenesisArgs, ParaKind},
 	paras_inherent::DisputedBitfield,
-	scheduler::AssignmentKind,
+	scheduler::{self, AssignmentKind},
 };
 use assert_matches::assert_matches;
-use frame_support::assert_noop;
+use frame_support::{
+	assert_err, assert_noop, assert_ok,
+	storage::{with_transaction, TransactionOutcome},
+};
 use keyring::Sr25519Keyring;
 use primitives::{
+	vstaging::{
+		compact_signed_bitfields, CandidateCounts, CompactAvailabilityBitfield,
+		CoreAssignmentSource, NoncedAvailabilityBitfield,
+	},
 	BlockNumber, CandidateCommitments, CandidateDescriptor, CollatorId,
-	CompactStatement as Statement, Hash, SignedAvailabilityBitfield, SignedStatement,
-	UncheckedSignedAvailabilityBitfield, ValidationCode, ValidatorId, ValidityAttestation,
-	PARACHAIN_KEY_TYPE_ID,
+	CompactStatement as Statement, CoreOccupied, Hash, Signed, SignedAvailabilityBitfield,
+	SignedStatement, UncheckedSignedAvailabilityBitfield, ValidationCode, ValidatorId,
+	ValidityAttestation, PARACHAIN_KEY_TYPE_ID,
 };
 use sc_keystore::LocalKeystore;
 use sp_keystore::{Keystore, KeystorePtr};
+use sp_runtime::Perbill;
 use std::sync::Arc;
 use test_helpers::{
 	dummy_candidate_receipt, dummy_collator, dummy_collator_signature, dummy_hash,
@@ -111,7 +120,8 @@ pub(crate) fn back_candidate(
 	kind: BackingKind,
 ) -> BackedCandidate {
 	let mut validator_indices = bitvec::bitvec![u8, BitOrderLsb0; 0; group.len()];
-	let threshold = minimum_backing_votes(group.len());
+	let threshold =
+		minimum_backing_votes(group.len(), Configuration::config().minimum_backing_votes);
 
 	let signing = match kind {
 		BackingKind::Unanimous => group.len(),
@@ -200,15 +210,26 @@ fn default_bitfield() -> AvailabilityBitfield {
 	AvailabilityBitfield(bitvec::bitvec![u8, BitOrderLsb0; 0; expected_bits()])
 }
 
-fn default_availability_votes() -> BitVec<u8, BitOrderLsb0> {
+type MaxValidators = <Test as Config>::MaxValidators;
+type MaxAvailabilityCores = <Test as Config>::MaxAvailabilityCores;
+
+fn default_bitfield_record_bits() -> BoundedBitVec<MaxAvailabilityCores> {
+	default_bitfield().0.try_into().unwrap()
+}
+
+fn default_availability_votes() -> BoundedBitVec<MaxValidators> {
 	bitvec::bitvec![u8, BitOrderLsb0; 0; ParasShared::active_validator_keys().len()]
+		.try_into()
+		.unwrap()
 }
 
-fn default_backing_bitfield() -> BitVec<u8, BitOrderLsb0> {
+fn default_backing_bitfield() -> BoundedBitVec<MaxValidators> {
 	bitvec::bitvec![u8, BitOrderLsb0; 0; ParasShared::active_validator_keys().len()]
+		.try_into()
+		.unwrap()
 }
 
-fn backing_bitfield(v: &[usize]) -> BitVec<u8, BitOrderLsb0> {
+fn backing_bitfield(v: &[usize]) -> BoundedBitVec<MaxValidators> {
 	let mut b = default_backing_bitfield();
 	for i in v {
 		b.set(*i, true);
@@ -220,12 +241,12 @@ pub(crate) fn validator_pubkeys(val_ids: &[Sr25519Keyring]) -> Vec<ValidatorId>
 	val_ids.iter().map(|v| v.public().into()).collect()
 }
 
-pub(crate) fn sign_bitfield(
+pub(crate) fn sign_bitfield<H: Encode>(
 	keystore: &KeystorePtr,
 	key: &Sr25519Keyring,
 	validator_index: ValidatorIndex,
 	bitfield: AvailabilityBitfield,
-	signing_context: &SigningContext,
+	signing_context: &SigningContext<H>,
 ) -> SignedAvailabilityBitfield {
 	SignedAvailabilityBitfield::sign(
 		&keystore,
@@ -316,6 +337,7 @@ fn collect_pending_cleans_up_pending() {
 		let default_candidate = TestCandidateBuilder::default().build();
 		<PendingAvailability<Test>>::insert(
 			chain_a,
+			CoreIndex::from(0),
 			CandidatePendingAvailability {
 				core: CoreIndex::from(0),
 				hash: default_candidate.hash(),
@@ -325,15 +347,18 @@ fn collect_pending_cleans_up_pending() {
 				backed_in_number: 0,
 				backers: default_backing_bitfield(),
 				backing_group: GroupIndex::from(0),
+				assignment_source: CoreAssignmentSource::Lease,
 			},
 		);
 		PendingAvailabilityCommitments::<Test>::insert(
 			chain_a,
+			CoreIndex::from(0),
 			default_candidate.commitments.clone(),
 		);
 
 		<PendingAvailability<Test>>::insert(
 			&chain_b,
+			CoreIndex::from(1),
 			CandidatePendingAvailability {
 				core: CoreIndex::from(1),
 				hash: default_candidate.hash(),
@@ -343,23 +368,28 @@ fn collect_pending_cleans_up_pending() {
 				backed_in_number: 0,
 				backers: default_backing_bitfield(),
 				backing_group: GroupIndex::from(1),
+				assignment_source: CoreAssignmentSource::Lease,
 			},
 		);
-		PendingAvailabilityCommitments::<Test>::insert(chain_b, default_candidate.commitments);
+		PendingAvailabilityCommitments::<Test>::insert(
+			chain_b,
+			CoreIndex::from(1),
+			default_candidate.commitments,
+		);
 
 		run_to_block(5, |_| None);
 
-		assert!(<PendingAvailability<Test>>::get(&chain_a).is_some());
-		assert!(<PendingAvailability<Test>>::get(&chain_b).is_some());
-		assert!(<PendingAvailabilityCommitments<Test>>::get(&chain_a).is_some());
-		assert!(<PendingAvailabilityCommitments<Test>>::get(&chain_b).is_some());
+		assert!(<PendingAvailability<Test>>::get(&chain_a, CoreIndex::from(0)).is_some());
+		assert!(<PendingAvailability<Test>>::get(&chain_b, CoreIndex::from(1)).is_some());
+		assert!(<PendingAvailabilityCommitments<Test>>::get(&chain_a, CoreIndex::from(0)).is_some());
+		assert!(<PendingAvailabilityCommitments<Test>>::get(&chain_b, CoreIndex::from(1)).is_some());
 
 		ParaInclusion::collect_pending(|core, _since| core == CoreIndex::from(0));
 
-		assert!(<PendingAvailability<Test>>::get(&chain_a).is_none());
-		assert!(<PendingAvailability<Test>>::get(&chain_b).is_some());
-		assert!(<PendingAvailabilityCommitments<Test>>::get(&chain_a).is_none());
-		assert!(<PendingAvailabilityCommitments<Test>>::get(&chain_b).is_some());
+		assert!(<PendingAvailability<Test>>::get(&chain_a, CoreIndex::from(0)).is_none());
+		assert!(<PendingAvailability<Test>>::get(&chain_b, CoreIndex::from(1)).is_some());
+		assert!(<PendingAvailabilityCommitments<Test>>::get(&chain_a, CoreIndex::from(0)).is_none());
+		assert!(<PendingAvailabilityCommitments<Test>>::get(&chain_b, CoreIndex::from(1)).is_some());
 	});
 }
 
@@ -413,15 +443,17 @@ fn bitfield_checks() {
 				let receipt = dummy_candidate_receipt(dummy_hash());
 				PendingAvailability::<Test>::insert(
 					p_id,
+					CoreIndex(0),
 					CandidatePendingAvailability {
 						availability_votes: default_availability_votes(),
 						core: CoreIndex(0),
 						hash: receipt.hash(),
 						descriptor: receipt.descriptor,
-						backers: BitVec::default(),
+						backers: Default::default(),
 						relay_parent_number: BlockNumber::from(0_u32),
 						backed_in_number: BlockNumber::from(0_u32),
 						backing_group: GroupIndex(0),
+						assignment_source: CoreAssignmentSource::Lease,
 					},
 				)
 			}
@@ -474,6 +506,32 @@ fn bitfield_checks() {
 			);
 		}
 
+		// sized to the core layout before the number of cores changed.
+		{
+			let mut bare_bitfield = default_bitfield();
+			bare_bitfield.0.push(false);
+			let signed = sign_bitfield(
+				&keystore,
+				&validators[0],
+				ValidatorIndex(0),
+				bare_bitfield,
+				&signing_context,
+			);
+
+			scheduler::PreviousCoreCount::<Test>::put(expected_bits() as u32 + 1);
+			assert_matches!(
+				ParaInclusion::process_bitfields(
+					expected_bits(),
+					vec![signed.into()],
+					DisputedBitfield::zeros(expected_bits()),
+					&core_lookup,
+					FullCheck::Yes,
+				),
+				Err(Error::<Test>::OutdatedBitfieldSize)
+			);
+			scheduler::PreviousCoreCount::<Test>::kill();
+		}
+
 		// duplicate.
 		{
 			set_pending_av.clone()();
@@ -492,7 +550,7 @@ fn bitfield_checks() {
 			.into();
 
 			assert_eq!(
-				<PendingAvailability<Test>>::get(chain_a)
+				<PendingAvailability<Test>>::get(chain_a, CoreIndex::from(0))
 					.unwrap()
 					.availability_votes
 					.count_ones(),
@@ -513,7 +571,7 @@ fn bitfield_checks() {
 			);
 
 			assert_eq!(
-				<PendingAvailability<Test>>::get(chain_a)
+				<PendingAvailability<Test>>::get(chain_a, CoreIndex::from(0))
 					.unwrap()
 					.availability_votes
 					.count_ones(),
@@ -552,7 +610,7 @@ fn bitfield_checks() {
 			.into();
 
 			assert_eq!(
-				<PendingAvailability<Test>>::get(chain_a)
+				<PendingAvailability<Test>>::get(chain_a, CoreIndex::from(0))
 					.unwrap()
 					.availability_votes
 					.count_ones(),
@@ -573,7 +631,7 @@ fn bitfield_checks() {
 			);
 
 			assert_eq!(
-				<PendingAvailability<Test>>::get(chain_a)
+				<PendingAvailability<Test>>::get(chain_a, CoreIndex::from(0))
 					.unwrap()
 					.availability_votes
 					.count_ones(),
@@ -602,7 +660,7 @@ fn bitfield_checks() {
 				DisputedBitfield::zeros(expected_bits()),
 				&core_lookup,
 				FullCheck::Yes,
-			), Ok(x) => { assert!(x.is_empty())});
+			), Ok((x, _)) => { assert!(x.is_empty())});
 		}
 
 		// empty bitfield signed: always ok, but kind of useless.
@@ -622,7 +680,7 @@ fn bitfield_checks() {
 				DisputedBitfield::zeros(expected_bits()),
 				&core_lookup,
 				FullCheck::Yes,
-			), Ok(x) => { assert!(x.is_empty())});
+			), Ok((x, _)) => { assert!(x.is_empty())});
 		}
 
 		// bitfield signed with pending bit signed.
@@ -634,6 +692,7 @@ fn bitfield_checks() {
 			let default_candidate = TestCandidateBuilder::default().build();
 			<PendingAvailability<Test>>::insert(
 				chain_a,
+				CoreIndex::from(0),
 				CandidatePendingAvailability {
 					core: CoreIndex::from(0),
 					hash: default_candidate.hash(),
@@ -643,9 +702,14 @@ fn bitfield_checks() {
 					backed_in_number: 0,
 					backers: default_backing_bitfield(),
 					backing_group: GroupIndex::from(0),
+					assignment_source: CoreAssignmentSource::Lease,
 				},
 			);
-			PendingAvailabilityCommitments::<Test>::insert(chain_a, default_candidate.commitments);
+			PendingAvailabilityCommitments::<Test>::insert(
+				chain_a,
+				CoreIndex::from(0),
+				default_candidate.commitments,
+			);
 
 			*bare_bitfield.0.get_mut(0).unwrap() = true;
 			let signed = sign_bitfield(
@@ -662,10 +726,10 @@ fn bitfield_checks() {
 				DisputedBitfield::zeros(expected_bits()),
 				&core_lookup,
 				FullCheck::Yes,
-			), Ok(v) => { assert!(v.is_empty())} );
+			), Ok((v, _)) => { assert!(v.is_empty())} );
 
-			<PendingAvailability<Test>>::remove(chain_a);
-			PendingAvailabilityCommitments::<Test>::remove(chain_a);
+			<PendingAvailability<Test>>::remove(chain_a, CoreIndex::from(0));
+			PendingAvailabilityCommitments::<Test>::remove(chain_a, CoreIndex::from(0));
 		}
 
 		// bitfield signed with pending bit signed, but no commitments.
@@ -677,6 +741,7 @@ fn bitfield_checks() {
 			let default_candidate = TestCandidateBuilder::default().build();
 			<PendingAvailability<Test>>::insert(
 				chain_a,
+				CoreIndex::from(0),
 				CandidatePendingAvailability {
 					core: CoreIndex::from(0),
 					hash: default_candidate.hash(),
@@ -686,6 +751,7 @@ fn bitfield_checks() {
 					backed_in_number: 0,
 					backers: default_backing_bitfield(),
 					backing_group: GroupIndex::from(0),
+					assignment_source: CoreAssignmentSource::Lease,
 				},
 			);
 
@@ -705,16 +771,40 @@ fn bitfield_checks() {
 				DisputedBitfield::zeros(expected_bits()),
 				&core_lookup,
 				FullCheck::Yes,
-			), Ok(v) => { assert!(v.is_empty()) });
+			), Ok((v, _)) => { assert!(v.is_empty()) });
 		}
 	});
 }
 
 #[test]
 fn availability_threshold_is_supermajority() {
-	assert_eq!(3, availability_threshold(4));
-	assert_eq!(5, availability_threshold(6));
-	assert_eq!(7, availability_threshold(9));
+	let config = HostConfiguration::<BlockNumber>::default();
+	assert_eq!(3, availability_threshold(&config, 4));
+	assert_eq!(5, availability_threshold(&config, 6));
+	assert_eq!(7, availability_threshold(&config, 9));
+
+	for n_validators in 1..=1000 {
+		assert_eq!(
+			primitives::supermajority_threshold(n_validators),
+			availability_threshold(&config, n_validators),
+		);
+	}
+}
+
+#[test]
+fn availability_threshold_follows_configuration() {
+	let config = HostConfiguration::<BlockNumber> {
+		availability_threshold: Perbill::from_percent(50),
+		..Default::default()
+	};
+	assert_eq!(2, availability_threshold(&config, 4));
+	assert_eq!(3, availability_threshold(&config, 5));
+
+	// The absolute minimum kicks in for small validator sets, but never exceeds their size.
+	let config = HostConfiguration::<BlockNumber> { minimum_availability_votes: 4, ..config };
+	assert_eq!(4, availability_threshold(&config, 5));
+	assert_eq!(3, availability_threshold(&config, 3));
+	assert_eq!(5, availability_threshold(&config, 10));
 }
 
 #[test]
@@ -769,6 +859,7 @@ fn supermajority_bitfields_trigger_availability() {
 
 		<PendingAvailability<Test>>::insert(
 			chain_a,
+			CoreIndex::from(0),
 			CandidatePendingAvailability {
 				core: CoreIndex::from(0),
 				hash: candidate_a.hash(),
@@ -778,9 +869,14 @@ fn supermajority_bitfields_trigger_availability() {
 				backed_in_number: 0,
 				backers: backing_bitfield(&[3, 4]),
 				backing_group: GroupIndex::from(0),
+				assignment_source: CoreAssignmentSource::Lease,
 			},
 		);
-		PendingAvailabilityCommitments::<Test>::insert(chain_a, candidate_a.clone().commitments);
+		PendingAvailabilityCommitments::<Test>::insert(
+			chain_a,
+			CoreIndex::from(0),
+			candidate_a.clone().commitments,
+		);
 
 		let candidate_b = TestCandidateBuilder {
 			para_id: chain_b,
@@ -791,6 +887,7 @@ fn supermajority_bitfields_trigger_availability() {
 
 		<PendingAvailability<Test>>::insert(
 			chain_b,
+			CoreIndex::from(1),
 			CandidatePendingAvailability {
 				core: CoreIndex::from(1),
 				hash: candidate_b.hash(),
@@ -800,9 +897,14 @@ fn supermajority_bitfields_trigger_availability() {
 				backed_in_number: 0,
 				backers: backing_bitfield(&[0, 2]),
 				backing_group: GroupIndex::from(1),
+				assignment_source: CoreAssignmentSource::Lease,
 			},
 		);
-		PendingAvailabilityCommitments::<Test>::insert(chain_b, candidate_b.commitments);
+		PendingAvailabilityCommitments::<Test>::insert(
+			chain_b,
+			CoreIndex::from(1),
+			candidate_b.commitments,
+		);
 
 		// this bitfield signals that a and b are available.
 		let a_and_b_available = {
@@ -821,7 +923,7 @@ fn supermajority_bitfields_trigger_availability() {
 			bare_bitfield
 		};
 
-		let threshold = availability_threshold(validators.len());
+		let threshold = availability_threshold(&Configuration::config(), validators.len());
 
 		// 4 of 5 first value >= 2/3
 		assert_eq!(threshold, 4);
@@ -861,29 +963,41 @@ fn supermajority_bitfields_trigger_availability() {
 				&core_lookup,
 				FullCheck::Yes,
 			),
-			Ok(v) => {
+			Ok((v, _)) => {
 				assert_eq!(vec![(CoreIndex(0), candidate_a.hash())], v);
 			}
 		);
 
 		// chain A had 4 signing off, which is >= threshold.
 		// chain B has 3 signing off, which is < threshold.
-		assert!(<PendingAvailability<Test>>::get(&chain_a).is_none());
-		assert!(<PendingAvailabilityCommitments<Test>>::get(&chain_a).is_none());
-		assert!(<PendingAvailabilityCommitments<Test>>::get(&chain_b).is_some());
-		assert_eq!(<PendingAvailability<Test>>::get(&chain_b).unwrap().availability_votes, {
-			// check that votes from first 3 were tracked.
-
-			let mut votes = default_availability_votes();
-			*votes.get_mut(0).unwrap() = true;
-			*votes.get_mut(1).unwrap() = true;
-			*votes.get_mut(2).unwrap() = true;
-
-			votes
-		});
+		assert!(<PendingAvailability<Test>>::get(&chain_a, CoreIndex::from(0)).is_none());
+		assert!(<PendingAvailabilityCommitments<Test>>::get(&chain_a, CoreIndex::from(0)).is_none());
+		assert!(<PendingAvailabilityCommitments<Test>>::get(&chain_b, CoreIndex::from(1)).is_some());
+		assert_eq!(
+			<PendingAvailability<Test>>::get(&chain_b, CoreIndex::from(1))
+				.unwrap()
+				.availability_votes,
+			{
+				// check that votes from first 3 were tracked.
+
+				let mut votes = default_availability_votes();
+				*votes.get_mut(0).unwrap() = true;
+				*votes.get_mut(1).unwrap() = true;
+				*votes.get_mut(2).unwrap() = true;
+
+				votes
+			}
+		);
 
 		// and check that chain head was enacted.
 		assert_eq!(Paras::para_head(&chain_a), Some(vec![1, 2, 3, 4].into()));
+		assert_eq!(
+			inclusion_hook_calls(),
+			vec![
+				InclusionHookCall::BitfieldsProcessed(4),
+				InclusionHookCall::CandidateIncluded(chain_a, candidate_a.hash(), CoreIndex(0)),
+			]
+		);
 
 		// Check that rewards are applied.
 		{
@@ -903,6 +1017,15 @@ fn supermajority_bitfields_trigger_availability() {
 			assert_eq!(rewards.get(&ValidatorIndex(3)).unwrap(), &1);
 			assert_eq!(rewards.get(&ValidatorIndex(4)).unwrap(), &1);
 		}
+
+		// Check that the inclusion is counted for the para and its backing group.
+		{
+			let included = CandidateCounts { backed: 0, included: 1 };
+			let statistics = Statistics::candidate_statistics();
+
+			assert_eq!(statistics.paras, vec![(chain_a, included)]);
+			assert_eq!(statistics.groups, vec![(GroupIndex::from(0), included)]);
+		}
 	});
 }
 
@@ -1056,16 +1179,17 @@ fn candidate_checks() {
 				BackingKind::Threshold,
 			);
 
-			// out-of-order manifests as unscheduled.
-			assert_noop!(
-				ParaInclusion::process_candidates(
+			// out-of-order candidates and cores are matched up by para.
+			let processed = with_transaction(|| {
+				TransactionOutcome::Rollback(ParaInclusion::process_candidates(
 					Default::default(),
 					vec![backed_b, backed_a],
-					vec![chain_a_assignment.clone(), chain_b_assignment.clone()],
+					vec![chain_b_assignment.clone(), chain_a_assignment.clone()],
 					&group_validators,
-				),
-				Error::<Test>::UnscheduledCandidate
-			);
+				))
+			})
+			.expect("candidates in any order are accepted");
+			assert_eq!(processed.core_indices, vec![CoreIndex::from(0), CoreIndex::from(1)]);
 		}
 
 		// candidate not backed.
@@ -1239,6 +1363,7 @@ fn candidate_checks() {
 			let candidate = TestCandidateBuilder::default().build();
 			<PendingAvailability<Test>>::insert(
 				&chain_a,
+				CoreIndex::from(0),
 				CandidatePendingAvailability {
 					core: CoreIndex::from(0),
 					hash: candidate.hash(),
@@ -1248,9 +1373,14 @@ fn candidate_checks() {
 					backed_in_number: 4,
 					backers: default_backing_bitfield(),
 					backing_group: GroupIndex::from(0),
+					assignment_source: CoreAssignmentSource::Lease,
 				},
 			);
-			<PendingAvailabilityCommitments<Test>>::insert(&chain_a, candidate.commitments);
+			<PendingAvailabilityCommitments<Test>>::insert(
+				&chain_a,
+				CoreIndex::from(0),
+				candidate.commitments,
+			);
 
 			assert_noop!(
 				ParaInclusion::process_candidates(
@@ -1262,8 +1392,8 @@ fn candidate_checks() {
 				Error::<Test>::CandidateScheduledBeforeParaFree
 			);
 
-			<PendingAvailability<Test>>::remove(&chain_a);
-			<PendingAvailabilityCommitments<Test>>::remove(&chain_a);
+			<PendingAvailability<Test>>::remove(&chain_a, CoreIndex::from(0));
+			<PendingAvailabilityCommitments<Test>>::remove(&chain_a, CoreIndex::from(0));
 		}
 
 		// messed up commitments storage - do not panic - reject.
@@ -1281,7 +1411,11 @@ fn candidate_checks() {
 			collator_sign_candidate(Sr25519Keyring::One, &mut candidate);
 
 			// this is not supposed to happen
-			<PendingAvailabilityCommitments<Test>>::insert(&chain_a, candidate.commitments.clone());
+			<PendingAvailabilityCommitments<Test>>::insert(
+				&chain_a,
+				CoreIndex::from(0),
+				candidate.commitments.clone(),
+			);
 
 			let backed = back_candidate(
 				candidate,
@@ -1302,7 +1436,7 @@ fn candidate_checks() {
 				Error::<Test>::CandidateScheduledBeforeParaFree
 			);
 
-			<PendingAvailabilityCommitments<Test>>::remove(&chain_a);
+			<PendingAvailabilityCommitments<Test>>::remove(&chain_a, CoreIndex::from(0));
 		}
 
 		// interfering code upgrade - reject
@@ -1450,6 +1584,39 @@ fn candidate_checks() {
 				Error::<Test>::ParaHeadMismatch
 			);
 		}
+
+		// code upgrade after the interfering one was canceled - accept
+		{
+			let mut candidate = TestCandidateBuilder {
+				para_id: chain_a,
+				relay_parent: System::parent_hash(),
+				pov_hash: Hash::repeat_byte(1),
+				new_validation_code: Some(vec![5, 6, 7, 8].into()),
+				persisted_validation_data_hash: make_vdata_hash(chain_a).unwrap(),
+				hrmp_watermark: RELAY_PARENT_NUM,
+				..Default::default()
+			}
+			.build();
+
+			collator_sign_candidate(Sr25519Keyring::One, &mut candidate);
+
+			let backed = back_candidate(
+				candidate,
+				&validators,
+				group_validators(GroupIndex::from(0)).unwrap().as_ref(),
+				&keystore,
+				&signing_context,
+				BackingKind::Threshold,
+			);
+
+			assert_ok!(Paras::cancel_code_upgrade(RuntimeOrigin::root(), chain_a));
+			assert_ok!(ParaInclusion::process_candidates(
+				Default::default(),
+				vec![backed],
+				vec![chain_a_assignment.clone()],
+				&group_validators,
+			));
+		}
 	});
 }
 
@@ -1626,6 +1793,14 @@ fn backing_works() {
 			occupied_cores,
 			vec![CoreIndex::from(0), CoreIndex::from(1), CoreIndex::from(2)]
 		);
+		assert_eq!(
+			inclusion_hook_calls(),
+			vec![
+				InclusionHookCall::CandidateBacked(chain_a, candidate_a.hash(), CoreIndex(0)),
+				InclusionHookCall::CandidateBacked(chain_b, candidate_b.hash(), CoreIndex(1)),
+				InclusionHookCall::CandidateBacked(thread_a, candidate_c.hash(), CoreIndex(2)),
+			]
+		);
 
 		// Transform the votes into the setup we expect
 		let expected = {
@@ -1676,11 +1851,14 @@ fn backing_works() {
 		);
 
 		let backers = {
-			let num_backers = minimum_backing_votes(group_validators(GroupIndex(0)).unwrap().len());
+			let num_backers = minimum_backing_votes(
+				group_validators(GroupIndex(0)).unwrap().len(),
+				Configuration::config().minimum_backing_votes,
+			);
 			backing_bitfield(&(0..num_backers).collect::<Vec<_>>())
 		};
 		assert_eq!(
-			<PendingAvailability<Test>>::get(&chain_a),
+			<PendingAvailability<Test>>::get(&chain_a, CoreIndex::from(0)),
 			Some(CandidatePendingAvailability {
 				core: CoreIndex::from(0),
 				hash: candidate_a.hash(),
@@ -1690,19 +1868,23 @@ fn backing_works() {
 				backed_in_number: System::block_number(),
 				backers,
 				backing_group: GroupIndex::from(0),
+				assignment_source: CoreAssignmentSource::Lease,
 			})
 		);
 		assert_eq!(
-			<PendingAvailabilityCommitments<Test>>::get(&chain_a),
+			<PendingAvailabilityCommitments<Test>>::get(&chain_a, CoreIndex::from(0)),
 			Some(candidate_a.commitments),
 		);
 
 		let backers = {
-			let num_backers = minimum_backing_votes(group_validators(GroupIndex(0)).unwrap().len());
+			let num_backers = minimum_backing_votes(
+				group_validators(GroupIndex(0)).unwrap().len(),
+				Configuration::config().minimum_backing_votes,
+			);
 			backing_bitfield(&(0..num_backers).map(|v| v + 2).collect::<Vec<_>>())
 		};
 		assert_eq!(
-			<PendingAvailability<Test>>::get(&chain_b),
+			<PendingAvailability<Test>>::get(&chain_b, CoreIndex::from(1)),
 			Some(CandidatePendingAvailability {
 				core: CoreIndex::from(1),
 				hash: candidate_b.hash(),
@@ -1712,15 +1894,16 @@ fn backing_works() {
 				backed_in_number: System::block_number(),
 				backers,
 				backing_group: GroupIndex::from(1),
+				assignment_source: CoreAssignmentSource::Lease,
 			})
 		);
 		assert_eq!(
-			<PendingAvailabilityCommitments<Test>>::get(&chain_b),
+			<PendingAvailabilityCommitments<Test>>::get(&chain_b, CoreIndex::from(1)),
 			Some(candidate_b.commitments),
 		);
 
 		assert_eq!(
-			<PendingAvailability<Test>>::get(&thread_a),
+			<PendingAvailability<Test>>::get(&thread_a, CoreIndex::from(2)),
 			Some(CandidatePendingAvailability {
 				core: CoreIndex::from(2),
 				hash: candidate_c.hash(),
@@ -1730,12 +1913,29 @@ fn backing_works() {
 				backed_in_number: System::block_number(),
 				backers: backing_bitfield(&[4]),
 				backing_group: GroupIndex::from(2),
+				assignment_source: CoreAssignmentSource::ParathreadClaim,
 			})
 		);
 		assert_eq!(
-			<PendingAvailabilityCommitments<Test>>::get(&thread_a),
+			<PendingAvailabilityCommitments<Test>>::get(&thread_a, CoreIndex::from(2)),
 			Some(candidate_c.commitments),
 		);
+
+		// Check that backing is counted for each para and backing group.
+		let backed = CandidateCounts { backed: 1, included: 0 };
+		let statistics = Statistics::candidate_statistics();
+		assert_eq!(
+			statistics.paras,
+			vec![(chain_a, backed), (chain_b, backed), (thread_a, backed)],
+		);
+		assert_eq!(
+			statistics.groups,
+			vec![
+				(GroupIndex::from(0), backed),
+				(GroupIndex::from(1), backed),
+				(GroupIndex::from(2), backed),
+			],
+		);
 	});
 }
 
@@ -1822,11 +2022,14 @@ fn can_include_candidate_with_ok_code_upgrade() {
 		assert_eq!(occupied_cores, vec![CoreIndex::from(0)]);
 
 		let backers = {
-			let num_backers = minimum_backing_votes(group_validators(GroupIndex(0)).unwrap().len());
+			let num_backers = minimum_backing_votes(
+				group_validators(GroupIndex(0)).unwrap().len(),
+				Configuration::config().minimum_backing_votes,
+			);
 			backing_bitfield(&(0..num_backers).collect::<Vec<_>>())
 		};
 		assert_eq!(
-			<PendingAvailability<Test>>::get(&chain_a),
+			<PendingAvailability<Test>>::get(&chain_a, CoreIndex::from(0)),
 			Some(CandidatePendingAvailability {
 				core: CoreIndex::from(0),
 				hash: candidate_a.hash(),
@@ -1836,10 +2039,11 @@ fn can_include_candidate_with_ok_code_upgrade() {
 				backed_in_number: System::block_number(),
 				backers,
 				backing_group: GroupIndex::from(0),
+				assignment_source: CoreAssignmentSource::Lease,
 			})
 		);
 		assert_eq!(
-			<PendingAvailabilityCommitments<Test>>::get(&chain_a),
+			<PendingAvailabilityCommitments<Test>>::get(&chain_a, CoreIndex::from(0)),
 			Some(candidate_a.commitments),
 		);
 	});
@@ -1887,22 +2091,35 @@ fn session_change_wipes() {
 
 		<AvailabilityBitfields<Test>>::insert(
 			&ValidatorIndex(0),
-			AvailabilityBitfieldRecord { bitfield: default_bitfield(), submitted_at: 9 },
+			AvailabilityBitfieldRecord {
+				bitfield: default_bitfield_record_bits(),
+				submitted_at: 9,
+				core_layout_version: 0,
+			},
 		);
 
 		<AvailabilityBitfields<Test>>::insert(
 			&ValidatorIndex(1),
-			AvailabilityBitfieldRecord { bitfield: default_bitfield(), submitted_at: 9 },
+			AvailabilityBitfieldRecord {
+				bitfield: default_bitfield_record_bits(),
+				submitted_at: 9,
+				core_layout_version: 0,
+			},
 		);
 
 		<AvailabilityBitfields<Test>>::insert(
 			&ValidatorIndex(4),
-			AvailabilityBitfieldRecord { bitfield: default_bitfield(), submitted_at: 9 },
+			AvailabilityBitfieldRecord {
+				bitfield: default_bitfield_record_bits(),
+				submitted_at: 9,
+				core_layout_version: 0,
+			},
 		);
 
 		let candidate = TestCandidateBuilder::default().build();
 		<PendingAvailability<Test>>::insert(
 			&chain_a,
+			CoreIndex::from(0),
 			CandidatePendingAvailability {
 				core: CoreIndex::from(0),
 				hash: candidate.hash(),
@@ -1912,12 +2129,18 @@ fn session_change_wipes() {
 				backed_in_number: 6,
 				backers: default_backing_bitfield(),
 				backing_group: GroupIndex::from(0),
+				assignment_source: CoreAssignmentSource::Lease,
 			},
 		);
-		<PendingAvailabilityCommitments<Test>>::insert(&chain_a, candidate.commitments.clone());
+		<PendingAvailabilityCommitments<Test>>::insert(
+			&chain_a,
+			CoreIndex::from(0),
+			candidate.commitments.clone(),
+		);
 
 		<PendingAvailability<Test>>::insert(
 			&chain_b,
+			CoreIndex::from(1),
 			CandidatePendingAvailability {
 				core: CoreIndex::from(1),
 				hash: candidate.hash(),
@@ -1927,9 +2150,14 @@ fn session_change_wipes() {
 				backed_in_number: 7,
 				backers: default_backing_bitfield(),
 				backing_group: GroupIndex::from(1),
+				assignment_source: CoreAssignmentSource::Lease,
 			},
 		);
-		<PendingAvailabilityCommitments<Test>>::insert(&chain_b, candidate.commitments);
+		<PendingAvailabilityCommitments<Test>>::insert(
+			&chain_b,
+			CoreIndex::from(1),
+			candidate.commitments,
+		);
 
 		run_to_block(11, |_| None);
 
@@ -1939,14 +2167,15 @@ fn session_change_wipes() {
 		assert!(<AvailabilityBitfields<Test>>::get(&ValidatorIndex(1)).is_some());
 		assert!(<AvailabilityBitfields<Test>>::get(&ValidatorIndex(4)).is_some());
 
-		assert!(<PendingAvailability<Test>>::get(&chain_a).is_some());
-		assert!(<PendingAvailability<Test>>::get(&chain_b).is_some());
-		assert!(<PendingAvailabilityCommitments<Test>>::get(&chain_a).is_some());
-		assert!(<PendingAvailabilityCommitments<Test>>::get(&chain_b).is_some());
+		assert!(<PendingAvailability<Test>>::get(&chain_a, CoreIndex::from(0)).is_some());
+		assert!(<PendingAvailability<Test>>::get(&chain_b, CoreIndex::from(1)).is_some());
+		assert!(<PendingAvailabilityCommitments<Test>>::get(&chain_a, CoreIndex::from(0)).is_some());
+		assert!(<PendingAvailabilityCommitments<Test>>::get(&chain_b, CoreIndex::from(1)).is_some());
 
 		run_to_block(12, |n| match n {
 			12 => Some(SessionChangeNotification {
 				validators: validator_public_new.clone(),
+				prev_validators: validator_public.clone(),
 				queued: Vec::new(),
 				prev_config: default_config(),
 				new_config: default_config(),
@@ -1962,13 +2191,2891 @@ fn session_change_wipes() {
 		assert!(<AvailabilityBitfields<Test>>::get(&ValidatorIndex(1)).is_none());
 		assert!(<AvailabilityBitfields<Test>>::get(&ValidatorIndex(4)).is_none());
 
-		assert!(<PendingAvailability<Test>>::get(&chain_a).is_none());
-		assert!(<PendingAvailability<Test>>::get(&chain_b).is_none());
-		assert!(<PendingAvailabilityCommitments<Test>>::get(&chain_a).is_none());
-		assert!(<PendingAvailabilityCommitments<Test>>::get(&chain_b).is_none());
+		assert!(<PendingAvailability<Test>>::get(&chain_a, CoreIndex::from(0)).is_none());
+		assert!(<PendingAvailability<Test>>::get(&chain_b, CoreIndex::from(1)).is_none());
+		assert!(<PendingAvailabilityCommitments<Test>>::get(&chain_a, CoreIndex::from(0)).is_none());
+		assert!(<PendingAvailabilityCommitments<Test>>::get(&chain_b, CoreIndex::from(1)).is_none());
 
 		assert!(<AvailabilityBitfields<Test>>::iter().collect::<Vec<_>>().is_empty());
 		assert!(<PendingAvailability<Test>>::iter().collect::<Vec<_>>().is_empty());
 		assert!(<PendingAvailabilityCommitments<Test>>::iter().collect::<Vec<_>>().is_empty());
 	});
 }
+
+#[test]
+fn stale_validator_entries_are_swept_lazily() {
+	let validators = vec![Sr25519Keyring::Alice, Sr25519Keyring::Bob];
+	let validator_public = validator_pubkeys(&validators);
+	let n_prev_validators = MAX_STALE_ENTRIES_SWEPT_PER_BLOCK + 10;
+
+	new_test_ext(genesis_config(vec![])).execute_with(|| {
+		shared::Pallet::<Test>::set_active_validators_ascending(validator_public.clone());
+		shared::Pallet::<Test>::set_session_index(5);
+
+		run_to_block(10, |_| None);
+
+		let record = |submitted_at| AvailabilityBitfieldRecord {
+			bitfield: default_bitfield_record_bits(),
+			submitted_at,
+			core_layout_version: 0,
+		};
+		for i in 0..n_prev_validators {
+			<AvailabilityBitfields<Test>>::insert(&ValidatorIndex(i), record(9));
+			<AvailabilityBitfieldNonces<Test>>::insert(&ValidatorIndex(i), (5, 1));
+		}
+
+		run_to_block(11, |n| match n {
+			11 => Some(SessionChangeNotification {
+				validators: validator_public.clone(),
+				prev_validators: vec![validator_public[0].clone(); n_prev_validators as usize],
+				queued: Vec::new(),
+				prev_config: default_config(),
+				new_config: default_config(),
+				random_seed: Default::default(),
+				session_index: 6,
+			}),
+			_ => None,
+		});
+
+		// Only the first batch was swept in the first block of the session.
+		let last = ValidatorIndex(n_prev_validators - 1);
+		let first_unswept = ValidatorIndex(MAX_STALE_ENTRIES_SWEPT_PER_BLOCK);
+		assert!(<AvailabilityBitfields<Test>>::get(&ValidatorIndex(0)).is_none());
+		assert!(<AvailabilityBitfieldNonces<Test>>::get(&ValidatorIndex(0)).is_none());
+		assert!(<AvailabilityBitfields<Test>>::get(&first_unswept).is_some());
+		assert!(<AvailabilityBitfieldNonces<Test>>::get(&last).is_some());
+		assert_eq!(
+			<StaleValidatorEntries<Test>>::get(),
+			Some(StaleEntriesCursor {
+				next: MAX_STALE_ENTRIES_SWEPT_PER_BLOCK,
+				end: n_prev_validators,
+				session_start: 11,
+			}),
+		);
+
+		// Entries written in the new session are kept.
+		<AvailabilityBitfields<Test>>::insert(&last, record(11));
+		<AvailabilityBitfieldNonces<Test>>::insert(&last, (6, 1));
+
+		run_to_block(12, |_| None);
+
+		assert!(<StaleValidatorEntries<Test>>::get().is_none());
+		assert!(<AvailabilityBitfields<Test>>::get(&first_unswept).is_none());
+		assert!(<AvailabilityBitfieldNonces<Test>>::get(&first_unswept).is_none());
+		assert!(<AvailabilityBitfields<Test>>::get(&last).is_some());
+		assert_eq!(<AvailabilityBitfieldNonces<Test>>::get(&last), Some((6, 1)));
+		assert_eq!(<AvailabilityBitfields<Test>>::iter().count(), 1);
+	});
+}
+
+#[test]
+fn session_change_carries_over_pending_availability() {
+	let chain_a = ParaId::from(1_u32);
+	let chain_b = ParaId::from(2_u32);
+
+	let paras = vec![(chain_a, ParaKind::Parachain), (chain_b, ParaKind::Parachain)];
+	let validators = vec![
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+	];
+	let validator_public = validator_pubkeys(&validators);
+
+	new_test_ext(genesis_config(paras)).execute_with(|| {
+		shared::Pallet::<Test>::set_active_validators_ascending(validator_public.clone());
+		run_to_block(10, |_| None);
+
+		let candidate = TestCandidateBuilder::default().build();
+		<PendingAvailability<Test>>::insert(
+			&chain_a,
+			CoreIndex::from(0),
+			CandidatePendingAvailability {
+				core: CoreIndex::from(0),
+				hash: candidate.hash(),
+				descriptor: candidate.descriptor.clone(),
+				// Bob signalled availability, Alice and Charlie backed it.
+				availability_votes: backing_bitfield(&[1]),
+				relay_parent_number: 8,
+				backed_in_number: 9,
+				backers: backing_bitfield(&[0, 2]),
+				backing_group: GroupIndex::from(0),
+				assignment_source: CoreAssignmentSource::Lease,
+			},
+		);
+		<PendingAvailabilityCommitments<Test>>::insert(
+			&chain_a,
+			CoreIndex::from(0),
+			candidate.commitments,
+		);
+
+		let mut config = default_config();
+		config.carry_over_pending_availability = true;
+		let session_change = |validators: Vec<ValidatorId>, prev_validators, session_index| {
+			let notification = SessionChangeNotification {
+				validators: validators.clone(),
+				prev_validators,
+				prev_config: config.clone(),
+				new_config: config.clone(),
+				session_index,
+				..Default::default()
+			};
+			shared::Pallet::<Test>::set_active_validators_ascending(validators);
+			scheduler::Pallet::<Test>::initializer_on_new_session(&notification);
+			ParaInclusion::initializer_on_new_session(&notification);
+		};
+
+		// Eve joins and the others are reordered, so the votes move to their new indices.
+		let new_validators = validator_pubkeys(&[
+			Sr25519Keyring::Eve,
+			Sr25519Keyring::Dave,
+			Sr25519Keyring::Charlie,
+			Sr25519Keyring::Bob,
+			Sr25519Keyring::Alice,
+		]);
+		session_change(new_validators.clone(), validator_public.clone(), 1);
+
+		let pending = <PendingAvailability<Test>>::get(&chain_a, CoreIndex::from(0)).unwrap();
+		assert_eq!(pending.availability_votes, bitvec::bitvec![u8, BitOrderLsb0; 0, 0, 0, 1, 0]);
+		assert_eq!(pending.backers, bitvec::bitvec![u8, BitOrderLsb0; 0, 0, 1, 0, 1]);
+		assert!(<PendingAvailabilityCommitments<Test>>::get(&chain_a, CoreIndex::from(0)).is_some());
+		assert_eq!(
+			scheduler::Pallet::<Test>::availability_cores()[0],
+			Some(CoreOccupied::Parachain),
+		);
+		assert_eq!(scheduler::Pallet::<Test>::availability_cores()[1], None);
+
+		// Once a validator leaves, the votes can no longer be remapped.
+		session_change(validator_pubkeys(&[Sr25519Keyring::Alice]), new_validators, 2);
+
+		assert!(<PendingAvailability<Test>>::get(&chain_a, CoreIndex::from(0)).is_none());
+		assert!(<PendingAvailabilityCommitments<Test>>::get(&chain_a, CoreIndex::from(0)).is_none());
+		assert_eq!(scheduler::Pallet::<Test>::availability_cores()[0], None);
+	});
+}
+
+#[test]
+fn code_upgrade_is_refused_while_restriction_signal_is_present() {
+	use primitives::{well_known_keys, UpgradeRestriction};
+
+	let chain_a = ParaId::from(1_u32);
+	let paras = vec![(chain_a, ParaKind::Parachain)];
+
+	new_test_ext(genesis_config(paras)).execute_with(|| {
+		let commitments = CandidateCommitments {
+			new_validation_code: Some(vec![5, 6, 7].into()),
+			hrmp_watermark: System::block_number(),
+			..Default::default()
+		};
+
+		assert!(ParaInclusion::check_validation_outputs_for_runtime_api(
+			chain_a,
+			commitments.clone(),
+		));
+
+		// The restriction is signalled to the para under a well-known key and a candidate that
+		// ignores it must not pass the acceptance criteria.
+		sp_io::storage::set(
+			&well_known_keys::upgrade_restriction_signal(chain_a),
+			&UpgradeRestriction::Present.encode(),
+		);
+		assert!(!ParaInclusion::check_validation_outputs_for_runtime_api(
+			chain_a,
+			commitments.clone(),
+		));
+
+		// The same outputs without a code upgrade are still fine.
+		assert!(ParaInclusion::check_validation_outputs_for_runtime_api(
+			chain_a,
+			CandidateCommitments { new_validation_code: None, ..commitments },
+		));
+	});
+}
+
+#[test]
+fn included_candidates_are_kept_for_the_retention_period() {
+	let chain_a = ParaId::from(1_u32);
+
+	let mut genesis = genesis_config(vec![(chain_a, ParaKind::Parachain)]);
+	genesis.configuration.config.included_candidates_retention = 3;
+
+	new_test_ext(genesis).execute_with(|| {
+		run_to_block(5, |_| None);
+
+		let candidate = TestCandidateBuilder {
+			para_id: chain_a,
+			relay_parent: System::parent_hash(),
+			..Default::default()
+		}
+		.build();
+		ParaInclusion::enact_candidate(
+			4,
+			4,
+			candidate.clone(),
+			default_backing_bitfield().into_inner(),
+			default_availability_votes().into_inner(),
+			CoreIndex::from(0),
+			GroupIndex::from(0),
+			CoreAssignmentSource::Lease,
+		);
+
+		let expected = vec![IncludedCandidate {
+			candidate_hash: candidate.hash(),
+			para_id: chain_a,
+			relay_parent: System::parent_hash(),
+			core_index: CoreIndex::from(0),
+		}];
+		assert_eq!(ParaInclusion::included_candidates(5), expected);
+		System::assert_has_event(
+			Event::<Test>::CandidateIncluded(
+				candidate.to_plain(),
+				candidate.commitments.head_data.clone(),
+				CoreIndex::from(0),
+				GroupIndex::from(0),
+				1,
+				CoreAssignmentSource::Lease,
+			)
+			.into(),
+		);
+
+		run_to_block(7, |_| None);
+		assert_eq!(ParaInclusion::included_candidates(5), expected);
+
+		// Block 5 falls out of the window once block 8 is initialized.
+		run_to_block(8, |_| None);
+		assert!(ParaInclusion::included_candidates(5).is_empty());
+	});
+}
+
+#[test]
+fn candidate_inclusion_is_indexed_for_the_dispute_period() {
+	let chain_a = ParaId::from(1_u32);
+
+	let mut genesis = genesis_config(vec![(chain_a, ParaKind::Parachain)]);
+	genesis.configuration.config.dispute_period = 1;
+
+	new_test_ext(genesis).execute_with(|| {
+		run_to_block(5, |_| None);
+
+		let candidate = TestCandidateBuilder {
+			para_id: chain_a,
+			relay_parent: System::parent_hash(),
+			..Default::default()
+		}
+		.build();
+		ParaInclusion::enact_candidate(
+			4,
+			4,
+			candidate.clone(),
+			default_backing_bitfield().into_inner(),
+			default_availability_votes().into_inner(),
+			CoreIndex::from(1),
+			GroupIndex::from(0),
+			CoreAssignmentSource::Lease,
+		);
+
+		let candidate_hash = candidate.hash();
+		assert_eq!(ParaInclusion::candidate_included_at(&candidate_hash), Some((5, CoreIndex(1))));
+
+		let new_session = |session_index| {
+			let mut config = default_config();
+			config.dispute_period = 1;
+			SessionChangeNotification {
+				validators: Vec::new(),
+				prev_validators: Vec::new(),
+				queued: Vec::new(),
+				prev_config: config.clone(),
+				new_config: config,
+				random_seed: Default::default(),
+				session_index,
+			}
+		};
+
+		run_to_block(6, |n| (n == 6).then(|| new_session(1)));
+		assert_eq!(ParaInclusion::candidate_included_at(&candidate_hash), Some((5, CoreIndex(1))));
+
+		// Session 0 is out of the dispute window once session 2 starts.
+		run_to_block(7, |n| (n == 7).then(|| new_session(2)));
+		assert_eq!(ParaInclusion::candidate_included_at(&candidate_hash), None);
+		assert!(<CandidatesIncludedInSession<Test>>::get(0).is_empty());
+	});
+}
+
+#[test]
+fn force_clear_pending_times_out_candidate_and_frees_core() {
+	let chain_a = ParaId::from(1_u32);
+
+	new_test_ext(genesis_config(vec![(chain_a, ParaKind::Parachain)])).execute_with(|| {
+		run_to_block(5, |_| None);
+
+		let candidate = TestCandidateBuilder { para_id: chain_a, ..Default::default() }.build();
+		<PendingAvailability<Test>>::insert(
+			chain_a,
+			CoreIndex::from(0),
+			CandidatePendingAvailability {
+				core: CoreIndex::from(0),
+				hash: candidate.hash(),
+				descriptor: candidate.descriptor.clone(),
+				availability_votes: default_availability_votes(),
+				relay_parent_number: 4,
+				backed_in_number: 4,
+				backers: default_backing_bitfield(),
+				backing_group: GroupIndex::from(0),
+				assignment_source: CoreAssignmentSource::Lease,
+			},
+		);
+		<PendingAvailabilityCommitments<Test>>::insert(
+			chain_a,
+			CoreIndex::from(0),
+			candidate.commitments.clone(),
+		);
+		scheduler::AvailabilityCores::<Test>::put(vec![Some(CoreOccupied::Parachain)]);
+
+		assert_noop!(
+			ParaInclusion::force_clear_pending(RuntimeOrigin::signed(1), chain_a),
+			DispatchError::BadOrigin,
+		);
+		assert_ok!(ParaInclusion::force_clear_pending(RuntimeOrigin::root(), chain_a));
+
+		assert!(<PendingAvailability<Test>>::get(&chain_a, CoreIndex::from(0)).is_none());
+		assert!(<PendingAvailabilityCommitments<Test>>::get(&chain_a, CoreIndex::from(0)).is_none());
+		assert_eq!(scheduler::AvailabilityCores::<Test>::get(), vec![None]);
+		System::assert_last_event(
+			Event::<Test>::CandidateTimedOut(
+				candidate.to_plain(),
+				candidate.commitments.head_data,
+				CoreIndex::from(0),
+				1,
+				0,
+			)
+			.into(),
+		);
+		assert_eq!(
+			inclusion_hook_calls(),
+			vec![InclusionHookCall::CandidateTimedOut(chain_a, candidate.hash(), CoreIndex(0))]
+		);
+
+		assert_noop!(
+			ParaInclusion::force_clear_pending(RuntimeOrigin::root(), chain_a),
+			Error::<Test>::NoPendingCandidate,
+		);
+	});
+}
+
+#[test]
+fn candidates_built_against_rolled_back_code_are_dropped() {
+	let chain_a = ParaId::from(1_u32);
+	let chain_b = ParaId::from(2_u32);
+	let paras = vec![(chain_a, ParaKind::Parachain), (chain_b, ParaKind::Parachain)];
+
+	new_test_ext(genesis_config(paras)).execute_with(|| {
+		run_to_block(5, |_| None);
+
+		let new_code = ValidationCode(vec![9, 9]);
+		assert_ok!(Paras::force_set_current_code(RuntimeOrigin::root(), chain_a, new_code.clone()));
+
+		let candidate_a = TestCandidateBuilder {
+			para_id: chain_a,
+			validation_code: new_code,
+			..Default::default()
+		}
+		.build();
+		let candidate_b = TestCandidateBuilder { para_id: chain_b, ..Default::default() }.build();
+		for (core, candidate) in [&candidate_a, &candidate_b].into_iter().enumerate() {
+			let para_id = candidate.descriptor.para_id;
+			<PendingAvailability<Test>>::insert(
+				para_id,
+				CoreIndex::from(core as u32),
+				CandidatePendingAvailability {
+					core: CoreIndex::from(core as u32),
+					hash: candidate.hash(),
+					descriptor: candidate.descriptor.clone(),
+					availability_votes: default_availability_votes(),
+					relay_parent_number: 5,
+					backed_in_number: 5,
+					backers: default_backing_bitfield(),
+					backing_group: GroupIndex::from(core as u32),
+					assignment_source: CoreAssignmentSource::Lease,
+				},
+			);
+			<PendingAvailabilityCommitments<Test>>::insert(
+				para_id,
+				CoreIndex::from(core as u32),
+				candidate.commitments.clone(),
+			);
+		}
+		scheduler::AvailabilityCores::<Test>::put(vec![
+			Some(CoreOccupied::Parachain),
+			Some(CoreOccupied::Parachain),
+		]);
+
+		run_to_block(6, |_| None);
+		assert_ok!(Paras::force_rollback_code(
+			RuntimeOrigin::root(),
+			chain_a,
+			dummy_validation_code().hash(),
+		));
+
+		// The candidate of `chain_a` is dropped at the start of the next block.
+		assert!(<PendingAvailability<Test>>::get(&chain_a, CoreIndex::from(0)).is_some());
+		run_to_block(7, |_| None);
+
+		assert!(<PendingAvailability<Test>>::get(&chain_a, CoreIndex::from(0)).is_none());
+		assert!(<PendingAvailabilityCommitments<Test>>::get(&chain_a, CoreIndex::from(0)).is_none());
+		assert!(<PendingAvailability<Test>>::get(&chain_b, CoreIndex::from(1)).is_some());
+		assert_eq!(
+			scheduler::AvailabilityCores::<Test>::get(),
+			vec![None, Some(CoreOccupied::Parachain)],
+		);
+		System::assert_has_event(
+			Event::<Test>::CandidateTimedOut(
+				candidate_a.to_plain(),
+				candidate_a.commitments.head_data,
+				CoreIndex::from(0),
+				2,
+				0,
+			)
+			.into(),
+		);
+	});
+}
+
+#[test]
+fn candidate_on_allowed_older_relay_parent_is_backed() {
+	let chain_a = ParaId::from(1_u32);
+
+	let validators = vec![
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+		Sr25519Keyring::Ferdie,
+	];
+	let keystore: KeystorePtr = Arc::new(LocalKeystore::in_memory());
+	for validator in validators.iter() {
+		Keystore::sr25519_generate_new(
+			&*keystore,
+			PARACHAIN_KEY_TYPE_ID,
+			Some(&validator.to_seed()),
+		)
+		.unwrap();
+	}
+	let validator_public = validator_pubkeys(&validators);
+
+	new_test_ext(genesis_config(vec![(chain_a, ParaKind::Parachain)])).execute_with(|| {
+		shared::Pallet::<Test>::set_active_validators_ascending(validator_public.clone());
+		shared::Pallet::<Test>::set_session_index(5);
+
+		run_to_block(5, |_| None);
+
+		// A relay parent a few blocks before the parent block, still within the allowed window.
+		const RELAY_PARENT_NUM: BlockNumber = 2;
+		let relay_parent = Hash::repeat_byte(42);
+		let relay_parent_storage_root = Hash::repeat_byte(43);
+		shared::Pallet::<Test>::add_allowed_relay_parent(
+			relay_parent,
+			relay_parent_storage_root,
+			RELAY_PARENT_NUM,
+			2,
+		);
+
+		let signing_context = SigningContext { parent_hash: relay_parent, session_index: 5 };
+
+		let group_validators = |group_index: GroupIndex| {
+			match group_index {
+				group_index if group_index == GroupIndex::from(0) => Some(vec![0, 1]),
+				_ => panic!("Group index out of bounds for 1 parachain core"),
+			}
+			.map(|vs| vs.into_iter().map(ValidatorIndex).collect::<Vec<_>>())
+		};
+
+		let chain_a_assignment = CoreAssignment {
+			core: CoreIndex::from(0),
+			para_id: chain_a,
+			kind: AssignmentKind::Parachain,
+			group_idx: GroupIndex::from(0),
+		};
+
+		let persisted_validation_data_hash = crate::util::make_persisted_validation_data::<Test>(
+			chain_a,
+			RELAY_PARENT_NUM,
+			relay_parent_storage_root,
+		)
+		.unwrap()
+		.hash();
+		let mut candidate = TestCandidateBuilder {
+			para_id: chain_a,
+			relay_parent,
+			pov_hash: Hash::repeat_byte(1),
+			persisted_validation_data_hash,
+			hrmp_watermark: RELAY_PARENT_NUM,
+			..Default::default()
+		}
+		.build();
+		collator_sign_candidate(Sr25519Keyring::One, &mut candidate);
+
+		let backed = back_candidate(
+			candidate.clone(),
+			&validators,
+			group_validators(GroupIndex::from(0)).unwrap().as_ref(),
+			&keystore,
+			&signing_context,
+			BackingKind::Threshold,
+		);
+
+		ParaInclusion::process_candidates(
+			Default::default(),
+			vec![backed],
+			vec![chain_a_assignment],
+			&group_validators,
+		)
+		.expect("candidate on an allowed relay parent is accepted");
+
+		assert_eq!(
+			<PendingAvailability<Test>>::get(&chain_a, CoreIndex::from(0))
+				.map(|p| p.relay_parent_number),
+			Some(RELAY_PARENT_NUM),
+		);
+	});
+}
+
+#[test]
+fn persisted_validation_data_follows_occupied_core_assumption() {
+	use crate::runtime_api_impl::v4::persisted_validation_data;
+	use primitives::OccupiedCoreAssumption;
+
+	let chain_a = ParaId::from(1_u32);
+
+	new_test_ext(genesis_config(vec![(chain_a, ParaKind::Parachain)])).execute_with(|| {
+		run_to_block(5, |_| None);
+
+		let candidate = TestCandidateBuilder {
+			para_id: chain_a,
+			head_data: vec![1, 2, 3].into(),
+			..Default::default()
+		}
+		.build();
+		<PendingAvailability<Test>>::insert(
+			chain_a,
+			CoreIndex::from(0),
+			CandidatePendingAvailability {
+				core: CoreIndex::from(0),
+				hash: candidate.hash(),
+				descriptor: candidate.descriptor.clone(),
+				availability_votes: default_availability_votes(),
+				relay_parent_number: 4,
+				backed_in_number: 4,
+				backers: default_backing_bitfield(),
+				backing_group: GroupIndex::from(0),
+				assignment_source: CoreAssignmentSource::Lease,
+			},
+		);
+		<PendingAvailabilityCommitments<Test>>::insert(
+			chain_a,
+			CoreIndex::from(0),
+			candidate.commitments.clone(),
+		);
+
+		let parent_head = |assumption| {
+			persisted_validation_data::<Test>(chain_a, assumption).map(|pvd| pvd.parent_head)
+		};
+
+		// The core is occupied, so there is nothing to build on while assuming it is free.
+		assert_eq!(parent_head(OccupiedCoreAssumption::Free), None);
+		// A timed out candidate leaves the para head untouched.
+		assert_eq!(parent_head(OccupiedCoreAssumption::TimedOut), Some(Vec::new().into()));
+		// An included candidate advances the para head.
+		assert_eq!(
+			parent_head(OccupiedCoreAssumption::Included),
+			Some(candidate.commitments.head_data),
+		);
+	});
+}
+
+#[test]
+fn seconding_conflicting_candidates_is_reported() {
+	use crate::mock::PUNISH_BACKING_EQUIVOCATION;
+
+	let first = CandidateHash(Hash::repeat_byte(1));
+	let second = CandidateHash(Hash::repeat_byte(2));
+	let reported = || PUNISH_BACKING_EQUIVOCATION.with(|r| r.borrow().clone());
+
+	new_test_ext(genesis_config(vec![])).execute_with(|| {
+		ParaInclusion::note_seconded(5, 3, first, vec![ValidatorIndex(0), ValidatorIndex(1)]);
+		// Seconding the same candidate again is not an equivocation.
+		ParaInclusion::note_seconded(5, 3, first, vec![ValidatorIndex(0)]);
+		// Neither is seconding another candidate at a different relay parent.
+		ParaInclusion::note_seconded(5, 4, second, vec![ValidatorIndex(0)]);
+		assert!(reported().is_empty());
+
+		ParaInclusion::note_seconded(5, 3, second, vec![ValidatorIndex(1), ValidatorIndex(2)]);
+		assert_eq!(reported(), vec![(5, second, vec![ValidatorIndex(1)])]);
+		assert_eq!(<SecondedCandidates<Test>>::get(3, ValidatorIndex(2)), Some(second));
+
+		// With a zero relay parent depth, nothing can be built on block 3 from block 5 on.
+		ParaInclusion::initializer_initialize(5);
+		assert_eq!(<SecondedCandidates<Test>>::iter_prefix(3).count(), 0);
+		assert_eq!(<SecondedCandidates<Test>>::get(4, ValidatorIndex(0)), Some(second));
+	});
+}
+
+#[test]
+fn validators_not_submitting_bitfields_are_reported_as_no_shows() {
+	use crate::mock::PUNISH_AVAILABILITY_NO_SHOWS;
+
+	let chain_a = ParaId::from(1_u32);
+	let validators = vec![
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+	];
+	let keystore: KeystorePtr = Arc::new(LocalKeystore::in_memory());
+	for validator in validators.iter() {
+		Keystore::sr25519_generate_new(
+			&*keystore,
+			PARACHAIN_KEY_TYPE_ID,
+			Some(&validator.to_seed()),
+		)
+		.unwrap();
+	}
+	let validator_public = validator_pubkeys(&validators);
+	let reported = || PUNISH_AVAILABILITY_NO_SHOWS.with(|r| r.borrow().clone());
+
+	let mut config = default_config();
+	config.availability_no_show_slash_threshold = 2;
+
+	new_test_ext(genesis_config(vec![(chain_a, ParaKind::Parachain)])).execute_with(|| {
+		shared::Pallet::<Test>::set_active_validators_ascending(validator_public.clone());
+
+		let candidate = TestCandidateBuilder { para_id: chain_a, ..Default::default() }.build();
+		let submit_bitfields = |session_index, signers: &[usize], core_occupied| {
+			shared::Pallet::<Test>::set_session_index(session_index);
+			let signing_context =
+				SigningContext { parent_hash: System::parent_hash(), session_index };
+			if core_occupied {
+				<PendingAvailability<Test>>::insert(
+					chain_a,
+					CoreIndex::from(0),
+					CandidatePendingAvailability {
+						core: CoreIndex::from(0),
+						hash: candidate.hash(),
+						descriptor: candidate.descriptor.clone(),
+						availability_votes: default_availability_votes(),
+						relay_parent_number: 0,
+						backed_in_number: 0,
+						backers: backing_bitfield(&[0, 1]),
+						backing_group: GroupIndex::from(0),
+						assignment_source: CoreAssignmentSource::Lease,
+					},
+				);
+			}
+			let signed_bitfields = signers
+				.iter()
+				.map(|i| {
+					sign_bitfield(
+						&keystore,
+						&validators[*i],
+						ValidatorIndex(*i as _),
+						default_bitfield(),
+						&signing_context,
+					)
+					.into()
+				})
+				.collect();
+			let core_lookup = |core| (core_occupied && core == CoreIndex::from(0)).then(|| chain_a);
+			ParaInclusion::process_bitfields(
+				expected_bits(),
+				signed_bitfields,
+				DisputedBitfield::zeros(expected_bits()),
+				&core_lookup,
+				FullCheck::Yes,
+			)
+			.unwrap();
+		};
+		let end_session = |session_index| {
+			ParaInclusion::initializer_on_new_session(&SessionChangeNotification {
+				validators: validator_public.clone(),
+				prev_validators: validator_public.clone(),
+				queued: Vec::new(),
+				prev_config: config.clone(),
+				new_config: config.clone(),
+				random_seed: Default::default(),
+				session_index: session_index + 1,
+			});
+		};
+
+		// Nobody is expected to submit bitfields while no cores are occupied.
+		submit_bitfields(4, &[0], false);
+		end_session(4);
+		assert!(reported().is_empty());
+
+		submit_bitfields(5, &[0, 1], true);
+		end_session(5);
+		assert_eq!(reported(), vec![(5, vec![ValidatorIndex(2), ValidatorIndex(3)], false)]);
+
+		// Dave was a no-show for the second session in a row, which is slashable.
+		submit_bitfields(6, &[0, 1, 2], true);
+		end_session(6);
+		assert_eq!(reported().len(), 2);
+		assert_eq!(reported().last(), Some(&(6, vec![ValidatorIndex(3)], true)));
+		assert_eq!(
+			<AvailabilityNoShowStreaks<Test>>::get(),
+			vec![(validator_public[3].clone(), 2)],
+		);
+	});
+}
+
+#[test]
+fn blocks_without_parachain_activity_take_the_fast_path() {
+	new_test_ext(genesis_config(vec![])).execute_with(|| {
+		assert!(!ParaInclusion::is_anything_pending());
+
+		// Neither the validators nor the cores are looked at.
+		let (freed, weight) = ParaInclusion::process_bitfields(
+			expected_bits(),
+			Vec::new(),
+			DisputedBitfield::zeros(expected_bits()),
+			|_| -> Option<ParaId> { panic!("cores must not be looked up") },
+			FullCheck::Yes,
+		)
+		.unwrap();
+		assert!(freed.is_empty());
+		assert_eq!(weight, Weight::zero());
+		assert_eq!(inclusion_hook_calls(), vec![InclusionHookCall::BitfieldsProcessed(0)]);
+
+		let processed = ParaInclusion::process_candidates(
+			Default::default(),
+			Vec::new(),
+			vec![CoreAssignment {
+				core: CoreIndex::from(0),
+				para_id: ParaId::from(1_u32),
+				kind: AssignmentKind::Parachain,
+				group_idx: GroupIndex::from(0),
+			}],
+			|_| -> Option<Vec<ValidatorIndex>> { panic!("groups must not be looked up") },
+		)
+		.unwrap();
+		assert_eq!(processed, ProcessedCandidates::default());
+	});
+}
+
+#[test]
+fn signature_check_context_matches_storage() {
+	let validators = vec![Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie];
+	let validator_public = validator_pubkeys(&validators);
+
+	new_test_ext(genesis_config(vec![])).execute_with(|| {
+		shared::Pallet::<Test>::set_active_validators_ascending(validator_public.clone());
+		shared::Pallet::<Test>::set_session_index(5);
+		run_to_block(3, |_| None);
+		scheduler::ValidatorGroups::<Test>::set(vec![
+			vec![ValidatorIndex(0), ValidatorIndex(1)],
+			vec![ValidatorIndex(2)],
+		]);
+
+		let context = SignatureCheckContext::<Test>::load();
+		assert_eq!(context.validators(), &validator_public[..]);
+		assert_eq!(context.session_index(), 5);
+		assert_eq!(context.parent_hash(), System::parent_hash());
+		assert_eq!(
+			context.bitfield_signing_context(),
+			&ParaInclusion::bitfield_signing_context(System::parent_hash(), 5),
+		);
+		assert_eq!(
+			context.candidate_signing_context(dummy_hash()),
+			SigningContext { parent_hash: dummy_hash(), session_index: 5 },
+		);
+		for group in 0..3 {
+			assert_eq!(
+				context.group_validators(GroupIndex(group)),
+				scheduler::Pallet::<Test>::group_validators(GroupIndex(group)),
+			);
+		}
+	});
+}
+
+#[test]
+fn late_bitfields_are_accepted_within_the_grace_period() {
+	let chain_a = ParaId::from(1_u32);
+
+	let mut genesis = genesis_config(vec![(chain_a, ParaKind::Parachain)]);
+	genesis.configuration.config.bitfield_grace_blocks = 2;
+
+	let validators = vec![
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+		Sr25519Keyring::Eve,
+		Sr25519Keyring::Ferdie,
+	];
+	let keystore: KeystorePtr = Arc::new(LocalKeystore::in_memory());
+	for validator in validators.iter() {
+		Keystore::sr25519_generate_new(
+			&*keystore,
+			PARACHAIN_KEY_TYPE_ID,
+			Some(&validator.to_seed()),
+		)
+		.unwrap();
+	}
+	// Ferdie leaves at the session change, Eve joins and the others are shuffled.
+	let previous_validators = vec![
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+		Sr25519Keyring::Ferdie,
+	];
+	let current_validators = vec![
+		Sr25519Keyring::Dave,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Eve,
+	];
+
+	new_test_ext(genesis).execute_with(|| {
+		shared::Pallet::<Test>::set_active_validators_ascending(validator_pubkeys(
+			&current_validators,
+		));
+		shared::Pallet::<Test>::set_session_index(5);
+		session_info::Sessions::<Test>::insert(
+			4,
+			primitives::SessionInfo {
+				active_validator_indices: Vec::new(),
+				random_seed: [0u8; 32],
+				dispute_period: 6,
+				validators: validator_pubkeys(&previous_validators).into(),
+				discovery_keys: Vec::new(),
+				assignment_keys: Vec::new(),
+				validator_groups: Vec::new().into(),
+				n_cores: 1,
+				zeroth_delay_tranche_width: 0,
+				relay_vrf_modulo_samples: 0,
+				n_delay_tranches: 0,
+				no_show_slots: 0,
+				needed_approvals: 0,
+			},
+		);
+		run_to_block(2, |_| None);
+		scheduler::SessionStartBlock::<Test>::put(2);
+
+		let candidate = TestCandidateBuilder {
+			para_id: chain_a,
+			head_data: vec![1, 2, 3, 4].into(),
+			..Default::default()
+		}
+		.build();
+		<PendingAvailability<Test>>::insert(
+			chain_a,
+			CoreIndex::from(0),
+			CandidatePendingAvailability {
+				core: CoreIndex::from(0),
+				hash: candidate.hash(),
+				descriptor: candidate.clone().descriptor,
+				availability_votes: default_availability_votes(),
+				relay_parent_number: 0,
+				backed_in_number: 0,
+				backers: backing_bitfield(&[0, 1]),
+				backing_group: GroupIndex::from(0),
+				assignment_source: CoreAssignmentSource::Lease,
+			},
+		);
+		PendingAvailabilityCommitments::<Test>::insert(
+			chain_a,
+			CoreIndex::from(0),
+			candidate.clone().commitments,
+		);
+
+		let available = {
+			let mut bare_bitfield = default_bitfield();
+			*bare_bitfield.0.get_mut(0).unwrap() = true;
+			bare_bitfield
+		};
+		let sign = |key, validator_index, session_index| -> UncheckedSignedAvailabilityBitfield {
+			let signing_context =
+				ParaInclusion::bitfield_signing_context(System::parent_hash(), session_index);
+			sign_bitfield(
+				&keystore,
+				key,
+				ValidatorIndex(validator_index),
+				available.clone(),
+				&signing_context,
+			)
+			.into()
+		};
+
+		// Alice and Bob sign in this session, the others right before the session change.
+		let signed_bitfields = vec![
+			sign(&Sr25519Keyring::Alice, 2, 5),
+			sign(&Sr25519Keyring::Bob, 3, 5),
+			sign(&Sr25519Keyring::Charlie, 2, 4),
+			sign(&Sr25519Keyring::Dave, 3, 4),
+			sign(&Sr25519Keyring::Ferdie, 4, 4),
+			sign(&Sr25519Keyring::Eve, 4, 3),
+		];
+
+		let context = SignatureCheckContext::<Test>::load();
+		assert!(context.accepts_late_bitfields());
+		let (current, late, invalid) = context.split_late_bitfields(signed_bitfields.clone());
+		assert_eq!(current, signed_bitfields[..2].to_vec());
+		// Ferdie is no longer active, and Eve signed for neither session.
+		assert_eq!(
+			late.iter().map(|late| late.validator_index).collect::<Vec<_>>(),
+			vec![ValidatorIndex(1), ValidatorIndex(0)],
+		);
+		assert_eq!(invalid, vec![ValidatorIndex(4)]);
+
+		// With the late votes of Charlie and Dave, the candidate reaches the threshold.
+		let (freed, _) = ParaInclusion::process_bitfields_in(
+			&context,
+			expected_bits(),
+			current,
+			late,
+			DisputedBitfield::zeros(expected_bits()),
+			|core| (core == CoreIndex::from(0)).then_some(chain_a),
+			FullCheck::Skip,
+		)
+		.unwrap();
+		assert_eq!(freed, vec![(CoreIndex::from(0), candidate.hash())]);
+
+		// After the grace period, only bitfields of this session are accepted.
+		run_to_block(4, |_| None);
+		assert!(!SignatureCheckContext::<Test>::load().accepts_late_bitfields());
+	});
+}
+
+#[test]
+fn random_bitfields_preserve_pending_availability_invariants() {
+	use rand::{Rng, SeedableRng};
+	use rand_chacha::ChaCha20Rng;
+	use std::collections::BTreeMap;
+
+	let paras = vec![
+		(ParaId::from(1_u32), ParaKind::Parachain),
+		(ParaId::from(2_u32), ParaKind::Parachain),
+		(ParaId::from(3_u32), ParaKind::Parachain),
+	];
+	let validators = vec![
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+		Sr25519Keyring::Ferdie,
+	];
+	let keystore: KeystorePtr = Arc::new(LocalKeystore::in_memory());
+	for validator in validators.iter() {
+		Keystore::sr25519_generate_new(
+			&*keystore,
+			PARACHAIN_KEY_TYPE_ID,
+			Some(&validator.to_seed()),
+		)
+		.unwrap();
+	}
+	let validator_public = validator_pubkeys(&validators);
+
+	// The parathread core is never occupied, so bits set for it must be ignored.
+	let core_lookup = |core: CoreIndex| paras.get(core.0 as usize).map(|(para, _)| *para);
+
+	for seed in 0..16 {
+		new_test_ext(genesis_config(paras.clone())).execute_with(|| {
+			shared::Pallet::<Test>::set_active_validators_ascending(validator_public.clone());
+			shared::Pallet::<Test>::set_session_index(5);
+
+			let signing_context =
+				SigningContext { parent_hash: System::parent_hash(), session_index: 5 };
+			let mut rng = ChaCha20Rng::seed_from_u64(seed);
+
+			for (core, (para_id, _)) in paras.iter().enumerate() {
+				if !rng.gen_bool(0.5) {
+					continue
+				}
+
+				let candidate = TestCandidateBuilder {
+					para_id: *para_id,
+					head_data: vec![core as u8].into(),
+					..Default::default()
+				}
+				.build();
+				<PendingAvailability<Test>>::insert(
+					para_id,
+					CoreIndex::from(core as u32),
+					CandidatePendingAvailability {
+						core: CoreIndex::from(core as u32),
+						hash: candidate.hash(),
+						descriptor: candidate.descriptor.clone(),
+						availability_votes: default_availability_votes(),
+						relay_parent_number: 0,
+						backed_in_number: 0,
+						backers: default_backing_bitfield(),
+						backing_group: GroupIndex::from(core as u32),
+						assignment_source: CoreAssignmentSource::Lease,
+					},
+				);
+				<PendingAvailabilityCommitments<Test>>::insert(
+					para_id,
+					CoreIndex::from(core as u32),
+					candidate.commitments,
+				);
+			}
+
+			for _ in 0..4 {
+				let before = <PendingAvailability<Test>>::iter()
+					.map(|(_, _, pending)| {
+						(pending.core, (pending.hash, pending.availability_votes))
+					})
+					.collect::<BTreeMap<_, _>>();
+
+				let mut signed_bitfields = Vec::new();
+				for (i, key) in validators.iter().enumerate() {
+					if !rng.gen_bool(0.8) {
+						continue
+					}
+
+					let mut bitfield = default_bitfield();
+					for bit in 0..expected_bits() {
+						bitfield.0.set(bit, rng.gen_bool(0.5));
+					}
+					signed_bitfields.push(
+						sign_bitfield(
+							&keystore,
+							key,
+							ValidatorIndex(i as _),
+							bitfield,
+							&signing_context,
+						)
+						.into(),
+					);
+				}
+
+				let freed = ParaInclusion::process_bitfields(
+					expected_bits(),
+					signed_bitfields,
+					DisputedBitfield::zeros(expected_bits()),
+					&core_lookup,
+					FullCheck::Yes,
+				)
+				.unwrap()
+				.0;
+
+				// Candidates and their commitments are always removed together.
+				assert_eq!(
+					<PendingAvailability<Test>>::iter_keys().collect::<BTreeSet<_>>(),
+					<PendingAvailabilityCommitments<Test>>::iter_keys().collect::<BTreeSet<_>>(),
+				);
+
+				// Only previously occupied cores are freed, with the candidate they held.
+				for (core, hash) in &freed {
+					assert_eq!(before.get(core).map(|(h, _)| h), Some(hash));
+				}
+
+				// Every other candidate stays pending and keeps all of its previous votes.
+				let after = <PendingAvailability<Test>>::iter()
+					.map(|(_, _, pending)| (pending.core, pending.availability_votes))
+					.collect::<BTreeMap<_, _>>();
+				assert_eq!(after.len() + freed.len(), before.len());
+				for (core, votes) in &after {
+					let (_, votes_before) = &before[core];
+					assert!(votes_before.iter_ones().all(|i| votes[i]));
+				}
+			}
+		});
+	}
+}
+
+#[test]
+fn try_state_detects_inconsistent_pending_availability() {
+	let chain_a = ParaId::from(1_u32);
+	let validators = vec![Sr25519Keyring::Alice, Sr25519Keyring::Bob];
+
+	new_test_ext(genesis_config(vec![(chain_a, ParaKind::Parachain)])).execute_with(|| {
+		shared::Pallet::<Test>::set_active_validators_ascending(validator_pubkeys(&validators));
+		scheduler::AvailabilityCores::<Test>::put(vec![Some(CoreOccupied::Parachain)]);
+
+		let candidate = TestCandidateBuilder { para_id: chain_a, ..Default::default() }.build();
+		<PendingAvailability<Test>>::insert(
+			chain_a,
+			CoreIndex::from(0),
+			CandidatePendingAvailability {
+				core: CoreIndex::from(0),
+				hash: candidate.hash(),
+				descriptor: candidate.descriptor.clone(),
+				availability_votes: default_availability_votes(),
+				relay_parent_number: 0,
+				backed_in_number: 0,
+				backers: default_backing_bitfield(),
+				backing_group: GroupIndex::from(0),
+				assignment_source: CoreAssignmentSource::Lease,
+			},
+		);
+		<PendingAvailabilityCommitments<Test>>::insert(
+			chain_a,
+			CoreIndex::from(0),
+			candidate.commitments,
+		);
+		assert_ok!(ParaInclusion::do_try_state());
+
+		<PendingAvailabilityCommitments<Test>>::remove(chain_a, CoreIndex::from(0));
+		assert!(ParaInclusion::do_try_state().is_err());
+	});
+}
+
+#[test]
+fn candidates_pending_availability_are_ordered_by_core() {
+	let chain_a = ParaId::from(1_u32);
+	let chain_b = ParaId::from(2_u32);
+	let chain_c = ParaId::from(3_u32);
+	let validators = vec![Sr25519Keyring::Alice, Sr25519Keyring::Bob];
+
+	let paras = vec![
+		(chain_a, ParaKind::Parachain),
+		(chain_b, ParaKind::Parachain),
+		(chain_c, ParaKind::Parachain),
+	];
+	new_test_ext(genesis_config(paras)).execute_with(|| {
+		shared::Pallet::<Test>::set_active_validators_ascending(validator_pubkeys(&validators));
+
+		let mut candidates = Vec::new();
+		for (para_id, core) in [(chain_a, 2), (chain_b, 0), (chain_c, 1)] {
+			let candidate = TestCandidateBuilder { para_id, ..Default::default() }.build();
+			<PendingAvailability<Test>>::insert(
+				para_id,
+				CoreIndex::from(core),
+				CandidatePendingAvailability {
+					core: CoreIndex::from(core),
+					hash: candidate.hash(),
+					descriptor: candidate.descriptor.clone(),
+					availability_votes: default_availability_votes(),
+					relay_parent_number: 0,
+					backed_in_number: 0,
+					backers: default_backing_bitfield(),
+					backing_group: GroupIndex::from(core),
+					assignment_source: CoreAssignmentSource::Lease,
+				},
+			);
+			candidates.push(candidate);
+		}
+
+		// `chain_c` has no commitments and is skipped.
+		<PendingAvailabilityCommitments<Test>>::insert(
+			chain_a,
+			CoreIndex::from(2),
+			candidates[0].commitments.clone(),
+		);
+		<PendingAvailabilityCommitments<Test>>::insert(
+			chain_b,
+			CoreIndex::from(0),
+			candidates[1].commitments.clone(),
+		);
+
+		assert_eq!(
+			ParaInclusion::candidates_pending_availability(),
+			vec![candidates[1].clone(), candidates[0].clone()],
+		);
+	});
+}
+
+#[test]
+fn v2_descriptors_are_checked_against_core_and_session() {
+	let chain_a = ParaId::from(1_u32);
+
+	let validators = vec![Sr25519Keyring::Alice, Sr25519Keyring::Bob];
+	let keystore: KeystorePtr = Arc::new(LocalKeystore::in_memory());
+	for validator in validators.iter() {
+		Keystore::sr25519_generate_new(
+			&*keystore,
+			PARACHAIN_KEY_TYPE_ID,
+			Some(&validator.to_seed()),
+		)
+		.unwrap();
+	}
+	let validator_public = validator_pubkeys(&validators);
+
+	let mut genesis = genesis_config(vec![(chain_a, ParaKind::Parachain)]);
+	genesis.configuration.config.v1_candidate_descriptors_rejected = true;
+
+	new_test_ext(genesis).execute_with(|| {
+		shared::Pallet::<Test>::set_active_validators_ascending(validator_public.clone());
+		shared::Pallet::<Test>::set_session_index(5);
+
+		run_to_block(5, |_| None);
+
+		const RELAY_PARENT_NUM: BlockNumber = 2;
+		let relay_parent = Hash::repeat_byte(42);
+		let relay_parent_storage_root = Hash::repeat_byte(43);
+		shared::Pallet::<Test>::add_allowed_relay_parent(
+			relay_parent,
+			relay_parent_storage_root,
+			RELAY_PARENT_NUM,
+			2,
+		);
+
+		let signing_context = SigningContext { parent_hash: relay_parent, session_index: 5 };
+
+		let group_validators = |group_index: GroupIndex| {
+			match group_index {
+				group_index if group_index == GroupIndex::from(0) => Some(vec![0, 1]),
+				_ => panic!("Group index out of bounds for 1 parachain core"),
+			}
+			.map(|vs| vs.into_iter().map(ValidatorIndex).collect::<Vec<_>>())
+		};
+
+		let chain_a_assignment = CoreAssignment {
+			core: CoreIndex::from(0),
+			para_id: chain_a,
+			kind: AssignmentKind::Parachain,
+			group_idx: GroupIndex::from(0),
+		};
+
+		let persisted_validation_data_hash = crate::util::make_persisted_validation_data::<Test>(
+			chain_a,
+			RELAY_PARENT_NUM,
+			relay_parent_storage_root,
+		)
+		.unwrap()
+		.hash();
+
+		let make_backed = |v2_fields: Option<(CoreIndex, SessionIndex)>| {
+			let mut candidate = TestCandidateBuilder {
+				para_id: chain_a,
+				relay_parent,
+				pov_hash: Hash::repeat_byte(1),
+				persisted_validation_data_hash,
+				hrmp_watermark: RELAY_PARENT_NUM,
+				..Default::default()
+			}
+			.build();
+			match v2_fields {
+				Some((core_index, session_index)) => {
+					candidate.descriptor.set_v2_fields(core_index, session_index)
+				},
+				None => collator_sign_candidate(Sr25519Keyring::One, &mut candidate),
+			}
+
+			back_candidate(
+				candidate,
+				&validators,
+				group_validators(GroupIndex::from(0)).unwrap().as_ref(),
+				&keystore,
+				&signing_context,
+				BackingKind::Threshold,
+			)
+		};
+
+		// v1 descriptors are rejected by the configuration.
+		assert_noop!(
+			ParaInclusion::process_candidates(
+				Default::default(),
+				vec![make_backed(None)],
+				vec![chain_a_assignment.clone()],
+				&group_validators,
+			),
+			Error::<Test>::InvalidDescriptorVersion,
+		);
+
+		// the core index must match the assignment.
+		assert_noop!(
+			ParaInclusion::process_candidates(
+				Default::default(),
+				vec![make_backed(Some((CoreIndex::from(1), 5)))],
+				vec![chain_a_assignment.clone()],
+				&group_validators,
+			),
+			Error::<Test>::InvalidAssignedCore,
+		);
+
+		// the session index must be the current one.
+		assert_noop!(
+			ParaInclusion::process_candidates(
+				Default::default(),
+				vec![make_backed(Some((CoreIndex::from(0), 4)))],
+				vec![chain_a_assignment.clone()],
+				&group_validators,
+			),
+			Error::<Test>::InvalidSessionIndex,
+		);
+
+		ParaInclusion::process_candidates(
+			Default::default(),
+			vec![make_backed(Some((CoreIndex::from(0), 5)))],
+			vec![chain_a_assignment],
+			&group_validators,
+		)
+		.expect("v2 candidate for the assigned core and current session is accepted");
+
+		assert!(<PendingAvailability<Test>>::get(&chain_a, CoreIndex::from(0)).is_some());
+	});
+}
+
+#[test]
+fn candidates_on_cores_outside_the_para_affinity_are_rejected() {
+	let chain_a = ParaId::from(1_u32);
+
+	let validators = vec![Sr25519Keyring::Alice, Sr25519Keyring::Bob];
+	let keystore: KeystorePtr = Arc::new(LocalKeystore::in_memory());
+	for validator in validators.iter() {
+		Keystore::sr25519_generate_new(
+			&*keystore,
+			PARACHAIN_KEY_TYPE_ID,
+			Some(&validator.to_seed()),
+		)
+		.unwrap();
+	}
+	let validator_public = validator_pubkeys(&validators);
+
+	let mut genesis = genesis_config(vec![(chain_a, ParaKind::Parachain)]);
+	genesis.configuration.config.core_affinity = vec![(chain_a, vec![CoreIndex::from(1)])];
+
+	new_test_ext(genesis).execute_with(|| {
+		shared::Pallet::<Test>::set_active_validators_ascending(validator_public.clone());
+		shared::Pallet::<Test>::set_session_index(5);
+
+		run_to_block(5, |_| None);
+
+		const RELAY_PARENT_NUM: BlockNumber = 2;
+		let relay_parent = Hash::repeat_byte(42);
+		let relay_parent_storage_root = Hash::repeat_byte(43);
+		shared::Pallet::<Test>::add_allowed_relay_parent(
+			relay_parent,
+			relay_parent_storage_root,
+			RELAY_PARENT_NUM,
+			2,
+		);
+
+		let signing_context = SigningContext { parent_hash: relay_parent, session_index: 5 };
+
+		let group_validators = |group_index: GroupIndex| {
+			match group_index {
+				group_index if group_index == GroupIndex::from(0) => Some(vec![0, 1]),
+				_ => panic!("Group index out of bounds for 1 parachain core"),
+			}
+			.map(|vs| vs.into_iter().map(ValidatorIndex).collect::<Vec<_>>())
+		};
+
+		let mut candidate = TestCandidateBuilder {
+			para_id: chain_a,
+			relay_parent,
+			pov_hash: Hash::repeat_byte(1),
+			persisted_validation_data_hash: crate::util::make_persisted_validation_data::<Test>(
+				chain_a,
+				RELAY_PARENT_NUM,
+				relay_parent_storage_root,
+			)
+			.unwrap()
+			.hash(),
+			hrmp_watermark: RELAY_PARENT_NUM,
+			..Default::default()
+		}
+		.build();
+		collator_sign_candidate(Sr25519Keyring::One, &mut candidate);
+
+		let backed = back_candidate(
+			candidate,
+			&validators,
+			group_validators(GroupIndex::from(0)).unwrap().as_ref(),
+			&keystore,
+			&signing_context,
+			BackingKind::Threshold,
+		);
+
+		assert_err!(
+			ParaInclusion::process_candidates(
+				Default::default(),
+				vec![backed],
+				vec![CoreAssignment {
+					core: CoreIndex::from(0),
+					para_id: chain_a,
+					kind: AssignmentKind::Parachain,
+					group_idx: GroupIndex::from(0),
+				}],
+				&group_validators,
+			),
+			Error::<Test>::DisallowedCore,
+		);
+	});
+}
+
+#[test]
+fn disallowed_candidates_are_rejected_until_the_dispute_period_passes() {
+	let chain_a = ParaId::from(1_u32);
+
+	let validators = vec![Sr25519Keyring::Alice, Sr25519Keyring::Bob];
+	let keystore: KeystorePtr = Arc::new(LocalKeystore::in_memory());
+	for validator in validators.iter() {
+		Keystore::sr25519_generate_new(
+			&*keystore,
+			PARACHAIN_KEY_TYPE_ID,
+			Some(&validator.to_seed()),
+		)
+		.unwrap();
+	}
+	let validator_public = validator_pubkeys(&validators);
+
+	new_test_ext(genesis_config(vec![(chain_a, ParaKind::Parachain)])).execute_with(|| {
+		shared::Pallet::<Test>::set_active_validators_ascending(validator_public.clone());
+		shared::Pallet::<Test>::set_session_index(5);
+
+		run_to_block(5, |_| None);
+
+		const RELAY_PARENT_NUM: BlockNumber = 2;
+		let relay_parent = Hash::repeat_byte(42);
+		let relay_parent_storage_root = Hash::repeat_byte(43);
+		shared::Pallet::<Test>::add_allowed_relay_parent(
+			relay_parent,
+			relay_parent_storage_root,
+			RELAY_PARENT_NUM,
+			2,
+		);
+
+		let signing_context = SigningContext { parent_hash: relay_parent, session_index: 5 };
+
+		let group_validators = |group_index: GroupIndex| {
+			match group_index {
+				group_index if group_index == GroupIndex::from(0) => Some(vec![0, 1]),
+				_ => panic!("Group index out of bounds for 1 parachain core"),
+			}
+			.map(|vs| vs.into_iter().map(ValidatorIndex).collect::<Vec<_>>())
+		};
+
+		let mut candidate = TestCandidateBuilder {
+			para_id: chain_a,
+			relay_parent,
+			pov_hash: Hash::repeat_byte(1),
+			persisted_validation_data_hash: crate::util::make_persisted_validation_data::<Test>(
+				chain_a,
+				RELAY_PARENT_NUM,
+				relay_parent_storage_root,
+			)
+			.unwrap()
+			.hash(),
+			hrmp_watermark: RELAY_PARENT_NUM,
+			..Default::default()
+		}
+		.build();
+		collator_sign_candidate(Sr25519Keyring::One, &mut candidate);
+		let candidate_hash = candidate.hash();
+
+		let backed = back_candidate(
+			candidate,
+			&validators,
+			group_validators(GroupIndex::from(0)).unwrap().as_ref(),
+			&keystore,
+			&signing_context,
+			BackingKind::Threshold,
+		);
+
+		assert_noop!(
+			ParaInclusion::disallow_candidate(RuntimeOrigin::signed(1), candidate_hash),
+			DispatchError::BadOrigin,
+		);
+		assert_ok!(ParaInclusion::disallow_candidate(RuntimeOrigin::root(), candidate_hash));
+		assert!(ParaInclusion::is_disallowed(&candidate_hash));
+
+		assert_noop!(
+			ParaInclusion::process_candidates(
+				Default::default(),
+				vec![backed],
+				vec![CoreAssignment {
+					core: CoreIndex::from(0),
+					para_id: chain_a,
+					kind: AssignmentKind::Parachain,
+					group_idx: GroupIndex::from(0),
+				}],
+				&group_validators,
+			),
+			Error::<Test>::DisallowedCandidate,
+		);
+
+		let new_session = |session_index| {
+			let mut config = default_config();
+			config.dispute_period = 2;
+			SessionChangeNotification {
+				validators: validator_public.clone(),
+				prev_validators: validator_public.clone(),
+				queued: Vec::new(),
+				prev_config: config.clone(),
+				new_config: config,
+				random_seed: Default::default(),
+				session_index,
+			}
+		};
+
+		ParaInclusion::initializer_on_new_session(&new_session(7));
+		assert!(ParaInclusion::is_disallowed(&candidate_hash));
+
+		ParaInclusion::initializer_on_new_session(&new_session(8));
+		assert!(!ParaInclusion::is_disallowed(&candidate_hash));
+	});
+}
+
+#[test]
+fn compact_bitfields_expand_with_valid_signatures() {
+	let chain_a = ParaId::from(1_u32);
+	let validators = vec![Sr25519Keyring::Alice];
+	let keystore: KeystorePtr = Arc::new(LocalKeystore::in_memory());
+	Keystore::sr25519_generate_new(
+		&*keystore,
+		PARACHAIN_KEY_TYPE_ID,
+		Some(&validators[0].to_seed()),
+	)
+	.unwrap();
+	let validator_public = validator_pubkeys(&validators);
+	let signing_context = SigningContext { parent_hash: Hash::repeat_byte(1), session_index: 5 };
+
+	let mut bits = bitvec::bitvec![u8, BitOrderLsb0; 0; 200];
+	bits.set(42, true);
+	let signed = sign_bitfield(
+		&keystore,
+		&validators[0],
+		ValidatorIndex(0),
+		AvailabilityBitfield(bits),
+		&signing_context,
+	)
+	.into_unchecked();
+	let compact = compact_signed_bitfields(vec![signed.clone()]);
+	assert!(matches!(compact[0].unchecked_payload(), CompactAvailabilityBitfield::Sparse { .. }));
+
+	new_test_ext(genesis_config(vec![(chain_a, ParaKind::Parachain)])).execute_with(|| {
+		assert_noop!(
+			ParaInclusion::expand_compact_bitfields(200, compact.clone()),
+			Error::<Test>::CompactBitfieldsDisabled,
+		);
+	});
+
+	let mut genesis = genesis_config(vec![(chain_a, ParaKind::Parachain)]);
+	genesis.configuration.config.compact_availability_bitfields = true;
+
+	new_test_ext(genesis).execute_with(|| {
+		assert_noop!(
+			ParaInclusion::expand_compact_bitfields(100, compact.clone()),
+			Error::<Test>::WrongBitfieldSize,
+		);
+
+		let expanded = ParaInclusion::expand_compact_bitfields(200, compact).unwrap();
+		assert_eq!(expanded, vec![signed]);
+		assert!(expanded[0]
+			.clone()
+			.try_into_checked(&signing_context, &validator_public[0])
+			.is_ok());
+	});
+}
+
+#[test]
+fn replayed_nonced_bitfields_are_dropped() {
+	let chain_a = ParaId::from(1_u32);
+	let validators = vec![Sr25519Keyring::Alice, Sr25519Keyring::Bob];
+	let keystore: KeystorePtr = Arc::new(LocalKeystore::in_memory());
+	for validator in validators.iter() {
+		Keystore::sr25519_generate_new(
+			&*keystore,
+			PARACHAIN_KEY_TYPE_ID,
+			Some(&validator.to_seed()),
+		)
+		.unwrap();
+	}
+	let validator_public = validator_pubkeys(&validators);
+
+	new_test_ext(genesis_config(vec![(chain_a, ParaKind::Parachain)])).execute_with(|| {
+		shared::Pallet::<Test>::set_active_validators_ascending(validator_public.clone());
+		shared::Pallet::<Test>::set_session_index(5);
+
+		let signing_context =
+			SigningContext { parent_hash: System::parent_hash(), session_index: 5 };
+		let core_lookup = |_| Some(chain_a);
+
+		let sign_nonced = |validator: usize, nonce: u64, context: &SigningContext| {
+			Signed::sign(
+				&keystore,
+				NoncedAvailabilityBitfield {
+					bitfield: AvailabilityBitfield(bitvec::bitvec![u8, BitOrderLsb0; 0; 1]),
+					nonce,
+				},
+				context,
+				ValidatorIndex(validator as _),
+				&validators[validator].public().into(),
+			)
+			.unwrap()
+			.unwrap()
+			.into_unchecked()
+		};
+		let process = |bitfields| {
+			ParaInclusion::process_nonced_bitfields(
+				1,
+				bitfields,
+				DisputedBitfield::zeros(1),
+				&core_lookup,
+				FullCheck::Yes,
+			)
+		};
+
+		assert!(process(vec![sign_nonced(0, 1, &signing_context)]).is_ok());
+		assert_eq!(AvailabilityBitfieldNonces::<Test>::get(&ValidatorIndex(0)), Some((5, 1)));
+
+		// replayed and stale bitfields are dropped before their signatures are checked.
+		let wrong_context = SigningContext { parent_hash: Hash::repeat_byte(1), session_index: 5 };
+		<AvailabilityBitfields<Test>>::remove(&ValidatorIndex(0));
+		assert!(process(vec![sign_nonced(0, 1, &signing_context)]).is_ok());
+		assert!(process(vec![sign_nonced(0, 0, &wrong_context)]).is_ok());
+		assert!(<AvailabilityBitfields<Test>>::get(&ValidatorIndex(0)).is_none());
+		assert_eq!(AvailabilityBitfieldNonces::<Test>::get(&ValidatorIndex(0)), Some((5, 1)));
+
+		// fresh bitfields are still checked.
+		assert_noop!(
+			process(vec![sign_nonced(0, 2, &wrong_context)]),
+			Error::<Test>::InvalidBitfieldSignature,
+		);
+
+		assert!(process(vec![
+			sign_nonced(0, 2, &signing_context),
+			sign_nonced(1, 7, &signing_context),
+		])
+		.is_ok());
+		assert!(<AvailabilityBitfields<Test>>::get(&ValidatorIndex(0)).is_some());
+		assert_eq!(AvailabilityBitfieldNonces::<Test>::get(&ValidatorIndex(0)), Some((5, 2)));
+		assert_eq!(AvailabilityBitfieldNonces::<Test>::get(&ValidatorIndex(1)), Some((5, 7)));
+
+		// nonces of a previous session do not hold back the new one.
+		shared::Pallet::<Test>::set_session_index(6);
+		let next_context = SigningContext { parent_hash: System::parent_hash(), session_index: 6 };
+		assert!(process(vec![sign_nonced(1, 1, &next_context)]).is_ok());
+		assert_eq!(AvailabilityBitfieldNonces::<Test>::get(&ValidatorIndex(1)), Some((6, 1)));
+	});
+}
+
+#[test]
+fn bitfields_are_checked_against_the_core_layout_if_bound_to_it() {
+	let chain_a = ParaId::from(1_u32);
+	let validators = vec![Sr25519Keyring::Alice];
+	let keystore: KeystorePtr = Arc::new(LocalKeystore::in_memory());
+	Keystore::sr25519_generate_new(
+		&*keystore,
+		PARACHAIN_KEY_TYPE_ID,
+		Some(&validators[0].to_seed()),
+	)
+	.unwrap();
+	let validator_public = validator_pubkeys(&validators);
+
+	let mut genesis = genesis_config(vec![(chain_a, ParaKind::Parachain)]);
+	genesis.configuration.config.core_layout_bound_bitfields = true;
+
+	new_test_ext(genesis).execute_with(|| {
+		shared::Pallet::<Test>::set_active_validators_ascending(validator_public.clone());
+		shared::Pallet::<Test>::set_session_index(5);
+
+		let parent_hash = System::parent_hash();
+		let context_over = |core_layout_hash| SigningContext {
+			session_index: 5,
+			parent_hash: BitfieldSigningParent { parent_hash, core_layout_hash },
+		};
+		let process = |signing_context: &SigningContext<BitfieldSigningParent>| {
+			let signed = sign_bitfield(
+				&keystore,
+				&validators[0],
+				ValidatorIndex(0),
+				AvailabilityBitfield(bitvec::bitvec![u8, BitOrderLsb0; 0; 1]),
+				signing_context,
+			);
+			ParaInclusion::process_bitfields(
+				1,
+				vec![signed.into_unchecked()],
+				DisputedBitfield::zeros(1),
+				|_| Some(chain_a),
+				FullCheck::Yes,
+			)
+		};
+
+		let layout_hash = core_layout_hash(&[]);
+		let other_layout_hash =
+			core_layout_hash(&[(CoreIndex(0), CandidateHash(Hash::repeat_byte(1)))]);
+
+		// Nodes learn the layout the next block notes from the state of its parent.
+		assert_eq!(ParaInclusion::next_bitfield_core_layout_hash(), Some(layout_hash));
+		ParaInclusion::note_core_layout(&Configuration::config());
+		assert_eq!(BitfieldCoreLayoutHash::<Test>::get(), Some(layout_hash));
+
+		assert!(process(&context_over(Some(layout_hash))).is_ok());
+		assert_noop!(
+			process(&context_over(Some(other_layout_hash))),
+			Error::<Test>::InvalidBitfieldSignature,
+		);
+		assert_noop!(process(&context_over(None)), Error::<Test>::InvalidBitfieldSignature);
+
+		// The layout only binds the bitfields of the block it was noted in.
+		ParaInclusion::initializer_finalize();
+		assert!(BitfieldCoreLayoutHash::<Test>::get().is_none());
+		assert!(process(&context_over(None)).is_ok());
+	});
+}
+
+#[test]
+fn candidate_without_validation_data_is_dropped() {
+	let chain_a = ParaId::from(1_u32);
+	// not registered, so no persisted validation data can be created for it.
+	let chain_x = ParaId::from(99_u32);
+
+	let validators = vec![
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+	];
+	let keystore: KeystorePtr = Arc::new(LocalKeystore::in_memory());
+	for validator in validators.iter() {
+		Keystore::sr25519_generate_new(
+			&*keystore,
+			PARACHAIN_KEY_TYPE_ID,
+			Some(&validator.to_seed()),
+		)
+		.unwrap();
+	}
+	let validator_public = validator_pubkeys(&validators);
+
+	new_test_ext(genesis_config(vec![(chain_a, ParaKind::Parachain)])).execute_with(|| {
+		shared::Pallet::<Test>::set_active_validators_ascending(validator_public.clone());
+		shared::Pallet::<Test>::set_session_index(5);
+
+		run_to_block(5, |_| None);
+
+		let signing_context =
+			SigningContext { parent_hash: System::parent_hash(), session_index: 5 };
+
+		let group_validators = |group_index: GroupIndex| {
+			match group_index {
+				group_index if group_index == GroupIndex::from(0) => Some(vec![0, 1]),
+				group_index if group_index == GroupIndex::from(1) => Some(vec![2, 3]),
+				_ => panic!("Group index out of bounds for 2 parachain cores"),
+			}
+			.map(|vs| vs.into_iter().map(ValidatorIndex).collect::<Vec<_>>())
+		};
+
+		let chain_x_assignment = CoreAssignment {
+			core: CoreIndex::from(0),
+			para_id: chain_x,
+			kind: AssignmentKind::Parachain,
+			group_idx: GroupIndex::from(0),
+		};
+		let chain_a_assignment = CoreAssignment {
+			core: CoreIndex::from(1),
+			para_id: chain_a,
+			kind: AssignmentKind::Parachain,
+			group_idx: GroupIndex::from(1),
+		};
+
+		let mut candidate_x = TestCandidateBuilder {
+			para_id: chain_x,
+			relay_parent: System::parent_hash(),
+			pov_hash: Hash::repeat_byte(1),
+			hrmp_watermark: 4,
+			..Default::default()
+		}
+		.build();
+		collator_sign_candidate(Sr25519Keyring::One, &mut candidate_x);
+
+		let mut candidate_a = TestCandidateBuilder {
+			para_id: chain_a,
+			relay_parent: System::parent_hash(),
+			pov_hash: Hash::repeat_byte(2),
+			persisted_validation_data_hash: crate::util::make_persisted_validation_data::<Test>(
+				chain_a,
+				4,
+				Default::default(),
+			)
+			.unwrap()
+			.hash(),
+			hrmp_watermark: 4,
+			..Default::default()
+		}
+		.build();
+		collator_sign_candidate(Sr25519Keyring::Two, &mut candidate_a);
+
+		let backed_x = back_candidate(
+			candidate_x.clone(),
+			&validators,
+			group_validators(GroupIndex::from(0)).unwrap().as_ref(),
+			&keystore,
+			&signing_context,
+			BackingKind::Threshold,
+		);
+		let backed_a = back_candidate(
+			candidate_a,
+			&validators,
+			group_validators(GroupIndex::from(1)).unwrap().as_ref(),
+			&keystore,
+			&signing_context,
+			BackingKind::Threshold,
+		);
+
+		let processed = ParaInclusion::process_candidates(
+			Default::default(),
+			vec![backed_x, backed_a],
+			vec![chain_x_assignment, chain_a_assignment],
+			&group_validators,
+		)
+		.expect("the other candidate is still processed");
+
+		assert_eq!(processed.core_indices, vec![CoreIndex::from(1)]);
+		assert!(<PendingAvailability<Test>>::get(&chain_x, CoreIndex::from(0)).is_none());
+		assert!(<PendingAvailability<Test>>::get(&chain_a, CoreIndex::from(1)).is_some());
+		System::assert_has_event(
+			Event::<Test>::CandidateDroppedMissingValidationData(chain_x, candidate_x.hash())
+				.into(),
+		);
+	});
+}
+
+#[test]
+fn stale_availability_votes_expire() {
+	let chain_a = ParaId::from(1_u32);
+
+	let validators = vec![
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+		Sr25519Keyring::Ferdie,
+	];
+	let keystore: KeystorePtr = Arc::new(LocalKeystore::in_memory());
+	for validator in validators.iter() {
+		Keystore::sr25519_generate_new(
+			&*keystore,
+			PARACHAIN_KEY_TYPE_ID,
+			Some(&validator.to_seed()),
+		)
+		.unwrap();
+	}
+	let validator_public = validator_pubkeys(&validators);
+
+	let mut genesis = genesis_config(vec![(chain_a, ParaKind::Parachain)]);
+	genesis.configuration.config.availability_vote_ttl = 2;
+
+	new_test_ext(genesis).execute_with(|| {
+		shared::Pallet::<Test>::set_active_validators_ascending(validator_public.clone());
+		shared::Pallet::<Test>::set_session_index(5);
+
+		let signing_context =
+			SigningContext { parent_hash: System::parent_hash(), session_index: 5 };
+		let core_lookup = |core| match core {
+			core if core == CoreIndex::from(0) => Some(chain_a),
+			_ => None,
+		};
+
+		let candidate = TestCandidateBuilder { para_id: chain_a, ..Default::default() }.build();
+		<PendingAvailability<Test>>::insert(
+			chain_a,
+			CoreIndex::from(0),
+			CandidatePendingAvailability {
+				core: CoreIndex::from(0),
+				hash: candidate.hash(),
+				descriptor: candidate.descriptor.clone(),
+				availability_votes: default_availability_votes(),
+				relay_parent_number: 0,
+				backed_in_number: 0,
+				backers: backing_bitfield(&[0, 1]),
+				backing_group: GroupIndex::from(0),
+				assignment_source: CoreAssignmentSource::Lease,
+			},
+		);
+		<PendingAvailabilityCommitments<Test>>::insert(
+			chain_a,
+			CoreIndex::from(0),
+			candidate.commitments.clone(),
+		);
+
+		let a_available = {
+			let mut bare_bitfield = default_bitfield();
+			*bare_bitfield.0.get_mut(0).unwrap() = true;
+			bare_bitfield
+		};
+		let sign = |signers: &[usize]| -> UncheckedSignedAvailabilityBitfields {
+			signers
+				.iter()
+				.map(|i| {
+					sign_bitfield(
+						&keystore,
+						&validators[*i],
+						ValidatorIndex(*i as _),
+						a_available.clone(),
+						&signing_context,
+					)
+					.into()
+				})
+				.collect()
+		};
+		let process = |signed_bitfields| {
+			ParaInclusion::process_bitfields(
+				expected_bits(),
+				signed_bitfields,
+				DisputedBitfield::zeros(expected_bits()),
+				&core_lookup,
+				FullCheck::Yes,
+			)
+			.unwrap()
+			.0
+		};
+
+		// 4 of 5 votes are needed.
+		System::set_block_number(1);
+		assert!(process(sign(&[0, 1, 2])).is_empty());
+
+		// the first votes are stale by the time the fourth one comes in.
+		System::set_block_number(3);
+		assert!(process(sign(&[3])).is_empty());
+		assert_eq!(
+			<PendingAvailability<Test>>::get(&chain_a, CoreIndex::from(0))
+				.unwrap()
+				.availability_votes,
+			backing_bitfield(&[3]),
+		);
+
+		// re-confirmed votes count again.
+		assert_eq!(process(sign(&[0, 1, 2])), vec![(CoreIndex::from(0), candidate.hash())]);
+		assert!(<PendingAvailability<Test>>::get(&chain_a, CoreIndex::from(0)).is_none());
+	});
+}
+
+#[test]
+fn disabled_validators_do_not_count_towards_thresholds() {
+	let chain_a = ParaId::from(1_u32);
+
+	// The block number of the relay-parent for testing.
+	const RELAY_PARENT_NUM: BlockNumber = 4;
+
+	let validators = vec![
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+		Sr25519Keyring::Ferdie,
+	];
+	let keystore: KeystorePtr = Arc::new(LocalKeystore::in_memory());
+	for validator in validators.iter() {
+		Keystore::sr25519_generate_new(
+			&*keystore,
+			PARACHAIN_KEY_TYPE_ID,
+			Some(&validator.to_seed()),
+		)
+		.unwrap();
+	}
+	let validator_public = validator_pubkeys(&validators);
+
+	new_test_ext(genesis_config(vec![(chain_a, ParaKind::Parachain)])).execute_with(|| {
+		shared::Pallet::<Test>::set_active_validators_ascending(validator_public.clone());
+		shared::Pallet::<Test>::set_session_index(5);
+
+		run_to_block(5, |_| None);
+
+		let signing_context =
+			SigningContext { parent_hash: System::parent_hash(), session_index: 5 };
+
+		let group_validators = |group_index: GroupIndex| {
+			match group_index {
+				group_index if group_index == GroupIndex::from(0) => Some(vec![0, 1, 2, 3, 4]),
+				_ => panic!("Group index out of bounds for 1 parachain"),
+			}
+			.map(|vs| vs.into_iter().map(ValidatorIndex).collect::<Vec<_>>())
+		};
+
+		// Validators 0 and 1 back the candidate, which is just enough.
+		let mut candidate = TestCandidateBuilder {
+			para_id: chain_a,
+			relay_parent: System::parent_hash(),
+			pov_hash: Hash::repeat_byte(1),
+			persisted_validation_data_hash: make_vdata_hash(chain_a).unwrap(),
+			hrmp_watermark: RELAY_PARENT_NUM,
+			..Default::default()
+		}
+		.build();
+		collator_sign_candidate(Sr25519Keyring::One, &mut candidate);
+
+		let backed = back_candidate(
+			candidate,
+			&validators,
+			group_validators(GroupIndex::from(0)).unwrap().as_ref(),
+			&keystore,
+			&signing_context,
+			BackingKind::Threshold,
+		);
+
+		crate::mock::set_disabled_validators(vec![1]);
+		assert_eq!(shared::Pallet::<Test>::disabled_validators(), vec![ValidatorIndex(1)]);
+
+		let ProcessedCandidates { core_indices: occupied_cores, .. } =
+			ParaInclusion::process_candidates(
+				Default::default(),
+				vec![backed],
+				vec![CoreAssignment {
+					core: CoreIndex::from(0),
+					para_id: chain_a,
+					kind: AssignmentKind::Parachain,
+					group_idx: GroupIndex::from(0),
+				}],
+				&group_validators,
+			)
+			.expect("the candidate is dropped without failing the block");
+		assert!(occupied_cores.is_empty());
+		assert!(<PendingAvailability<Test>>::get(&chain_a, CoreIndex::from(0)).is_none());
+
+		// Availability votes of disabled validators don't count either, including those cast
+		// before they were disabled.
+		crate::mock::set_disabled_validators(vec![]);
+
+		let candidate = TestCandidateBuilder { para_id: chain_a, ..Default::default() }.build();
+		<PendingAvailability<Test>>::insert(
+			chain_a,
+			CoreIndex::from(0),
+			CandidatePendingAvailability {
+				core: CoreIndex::from(0),
+				hash: candidate.hash(),
+				descriptor: candidate.descriptor.clone(),
+				availability_votes: default_availability_votes(),
+				relay_parent_number: 0,
+				backed_in_number: 0,
+				backers: backing_bitfield(&[0, 2]),
+				backing_group: GroupIndex::from(0),
+				assignment_source: CoreAssignmentSource::Lease,
+			},
+		);
+		<PendingAvailabilityCommitments<Test>>::insert(
+			chain_a,
+			CoreIndex::from(0),
+			candidate.commitments.clone(),
+		);
+
+		let core_lookup = |core| match core {
+			core if core == CoreIndex::from(0) => Some(chain_a),
+			_ => None,
+		};
+		let a_available = {
+			let mut bare_bitfield = default_bitfield();
+			*bare_bitfield.0.get_mut(0).unwrap() = true;
+			bare_bitfield
+		};
+		let sign = |signers: &[usize]| -> UncheckedSignedAvailabilityBitfields {
+			signers
+				.iter()
+				.map(|i| {
+					sign_bitfield(
+						&keystore,
+						&validators[*i],
+						ValidatorIndex(*i as _),
+						a_available.clone(),
+						&signing_context,
+					)
+					.into()
+				})
+				.collect()
+		};
+		let process = |signed_bitfields| {
+			ParaInclusion::process_bitfields(
+				expected_bits(),
+				signed_bitfields,
+				DisputedBitfield::zeros(expected_bits()),
+				&core_lookup,
+				FullCheck::Yes,
+			)
+			.unwrap()
+			.0
+		};
+
+		// 4 of 5 votes are needed.
+		assert!(process(sign(&[0])).is_empty());
+
+		crate::mock::set_disabled_validators(vec![0, 4]);
+		assert!(process(sign(&[1, 2, 3, 4])).is_empty());
+		assert_eq!(
+			<PendingAvailability<Test>>::get(&chain_a, CoreIndex::from(0))
+				.unwrap()
+				.availability_votes,
+			backing_bitfield(&[1, 2, 3]),
+		);
+		assert!(<AvailabilityBitfields<Test>>::get(&ValidatorIndex(4)).is_none());
+
+		crate::mock::set_disabled_validators(vec![4]);
+		assert_eq!(process(sign(&[0])), vec![(CoreIndex::from(0), candidate.hash())]);
+	});
+}
+
+#[test]
+fn minimum_backing_votes_is_capped_at_group_size() {
+	assert_eq!(minimum_backing_votes(5, configuration::LEGACY_MIN_BACKING_VOTES), 2);
+	assert_eq!(minimum_backing_votes(5, 3), 3);
+	// small groups need every member to back the candidate.
+	assert_eq!(minimum_backing_votes(2, 3), 2);
+}
+
+#[test]
+fn backing_threshold_follows_the_size_of_each_group() {
+	let chain_a = ParaId::from(1_u32);
+	let chain_b = ParaId::from(2_u32);
+
+	let validators = vec![
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+		Sr25519Keyring::Ferdie,
+	];
+	let keystore: KeystorePtr = Arc::new(LocalKeystore::in_memory());
+	for validator in validators.iter() {
+		Keystore::sr25519_generate_new(
+			&*keystore,
+			PARACHAIN_KEY_TYPE_ID,
+			Some(&validator.to_seed()),
+		)
+		.unwrap();
+	}
+	let validator_public = validator_pubkeys(&validators);
+
+	let mut genesis =
+		genesis_config(vec![(chain_a, ParaKind::Parachain), (chain_b, ParaKind::Parachain)]);
+	genesis.configuration.config.minimum_backing_votes = 3;
+
+	new_test_ext(genesis).execute_with(|| {
+		shared::Pallet::<Test>::set_active_validators_ascending(validator_public.clone());
+		shared::Pallet::<Test>::set_session_index(5);
+
+		run_to_block(5, |_| None);
+
+		const RELAY_PARENT_NUM: BlockNumber = 4;
+		let relay_parent = System::parent_hash();
+		let relay_parent_storage_root = Default::default();
+		let signing_context = SigningContext { parent_hash: relay_parent, session_index: 5 };
+
+		// five validators on two cores: one group of three and one of two.
+		let group_validators = |group_index: GroupIndex| {
+			match group_index {
+				group_index if group_index == GroupIndex::from(0) => Some(vec![0, 1, 2]),
+				group_index if group_index == GroupIndex::from(1) => Some(vec![3, 4]),
+				_ => panic!("Group index out of bounds for 2 parachain cores"),
+			}
+			.map(|vs| vs.into_iter().map(ValidatorIndex).collect::<Vec<_>>())
+		};
+
+		let assignment = |para_id, core: u32| CoreAssignment {
+			core: CoreIndex::from(core),
+			para_id,
+			kind: AssignmentKind::Parachain,
+			group_idx: GroupIndex::from(core),
+		};
+
+		let backed = |para_id, group: GroupIndex, kind| {
+			let mut candidate = TestCandidateBuilder {
+				para_id,
+				relay_parent,
+				pov_hash: Hash::repeat_byte(1),
+				persisted_validation_data_hash: make_vdata_hash(para_id).unwrap(),
+				hrmp_watermark: RELAY_PARENT_NUM,
+				..Default::default()
+			}
+			.build();
+			collator_sign_candidate(Sr25519Keyring::One, &mut candidate);
+
+			back_candidate(
+				candidate,
+				&validators,
+				group_validators(group).unwrap().as_ref(),
+				&keystore,
+				&signing_context,
+				kind,
+			)
+		};
+
+		// two votes are not enough in the group of three.
+		assert_noop!(
+			ParaInclusion::process_candidates(
+				relay_parent_storage_root,
+				vec![backed(chain_a, GroupIndex::from(0), BackingKind::Lacking)],
+				vec![assignment(chain_a, 0)],
+				&group_validators,
+			),
+			Error::<Test>::InsufficientBacking,
+		);
+
+		// the configured minimum is reached in the group of three, and capped at the size of the
+		// group of two.
+		ParaInclusion::process_candidates(
+			relay_parent_storage_root,
+			vec![
+				backed(chain_a, GroupIndex::from(0), BackingKind::Threshold),
+				backed(chain_b, GroupIndex::from(1), BackingKind::Threshold),
+			],
+			vec![assignment(chain_a, 0), assignment(chain_b, 1)],
+			&group_validators,
+		)
+		.expect("candidates backed by the threshold of their group are accepted");
+
+		let backers = |para_id, core: u32| {
+			<PendingAvailability<Test>>::get(&para_id, CoreIndex::from(core))
+				.unwrap()
+				.backers
+		};
+		assert_eq!(backers(chain_a, 0).count_ones(), 3);
+		assert_eq!(backers(chain_b, 1).count_ones(), 2);
+	});
+}
+
+#[test]
+fn candidates_are_limited_per_para_and_per_block() {
+	let chain_a = ParaId::from(1_u32);
+	let chain_b = ParaId::from(2_u32);
+
+	const RELAY_PARENT_NUM: BlockNumber = 4;
+
+	let validators = vec![
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+	];
+	let keystore: KeystorePtr = Arc::new(LocalKeystore::in_memory());
+	for validator in validators.iter() {
+		Keystore::sr25519_generate_new(
+			&*keystore,
+			PARACHAIN_KEY_TYPE_ID,
+			Some(&validator.to_seed()),
+		)
+		.unwrap();
+	}
+	let validator_public = validator_pubkeys(&validators);
+
+	let mut genesis =
+		genesis_config(vec![(chain_a, ParaKind::Parachain), (chain_b, ParaKind::Parachain)]);
+	genesis.configuration.config.max_candidates_per_block = Some(1);
+
+	new_test_ext(genesis).execute_with(|| {
+		shared::Pallet::<Test>::set_active_validators_ascending(validator_public.clone());
+		shared::Pallet::<Test>::set_session_index(5);
+
+		run_to_block(5, |_| None);
+
+		let signing_context =
+			SigningContext { parent_hash: System::parent_hash(), session_index: 5 };
+
+		let group_validators = |group_index: GroupIndex| {
+			match group_index {
+				group_index if group_index == GroupIndex::from(0) => Some(vec![0, 1]),
+				group_index if group_index == GroupIndex::from(1) => Some(vec![2, 3]),
+				_ => panic!("Group index out of bounds for 2 parachain cores"),
+			}
+			.map(|vs| vs.into_iter().map(ValidatorIndex).collect::<Vec<_>>())
+		};
+
+		let scheduled = vec![
+			CoreAssignment {
+				core: CoreIndex::from(0),
+				para_id: chain_a,
+				kind: AssignmentKind::Parachain,
+				group_idx: GroupIndex::from(0),
+			},
+			CoreAssignment {
+				core: CoreIndex::from(1),
+				para_id: chain_b,
+				kind: AssignmentKind::Parachain,
+				group_idx: GroupIndex::from(1),
+			},
+		];
+
+		let backed = |para_id, group: GroupIndex, pov: u8| {
+			let mut candidate = TestCandidateBuilder {
+				para_id,
+				relay_parent: System::parent_hash(),
+				pov_hash: Hash::repeat_byte(pov),
+				persisted_validation_data_hash: make_vdata_hash(para_id).unwrap(),
+				hrmp_watermark: RELAY_PARENT_NUM,
+				..Default::default()
+			}
+			.build();
+			collator_sign_candidate(Sr25519Keyring::One, &mut candidate);
+
+			back_candidate(
+				candidate,
+				&validators,
+				group_validators(group).unwrap().as_ref(),
+				&keystore,
+				&signing_context,
+				BackingKind::Threshold,
+			)
+		};
+
+		assert_noop!(
+			ParaInclusion::process_candidates(
+				Default::default(),
+				vec![
+					backed(chain_a, GroupIndex::from(0), 1),
+					backed(chain_b, GroupIndex::from(1), 1),
+				],
+				scheduled.clone(),
+				&group_validators,
+			),
+			Error::<Test>::TooManyCandidates,
+		);
+
+		let mut config = Configuration::config();
+		config.max_candidates_per_block = None;
+		Configuration::force_set_active_config(config);
+		assert_noop!(
+			ParaInclusion::process_candidates(
+				Default::default(),
+				vec![
+					backed(chain_a, GroupIndex::from(0), 1),
+					backed(chain_b, GroupIndex::from(1), 1),
+					backed(chain_a, GroupIndex::from(0), 2),
+				],
+				scheduled.clone(),
+				&group_validators,
+			),
+			Error::<Test>::DuplicateCandidateForPara,
+		);
+
+		ParaInclusion::process_candidates(
+			Default::default(),
+			vec![backed(chain_a, GroupIndex::from(0), 1), backed(chain_b, GroupIndex::from(1), 1)],
+			scheduled,
+			&group_validators,
+		)
+		.expect("one candidate per para is accepted");
+	});
+}
+
+#[test]
+fn failing_to_back_a_candidate_leaves_no_candidate_behind() {
+	let chain_a = ParaId::from(1_u32);
+	let chain_b = ParaId::from(2_u32);
+
+	const RELAY_PARENT_NUM: BlockNumber = 4;
+
+	let validators = vec![
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+	];
+	let keystore: KeystorePtr = Arc::new(LocalKeystore::in_memory());
+	for validator in validators.iter() {
+		Keystore::sr25519_generate_new(
+			&*keystore,
+			PARACHAIN_KEY_TYPE_ID,
+			Some(&validator.to_seed()),
+		)
+		.unwrap();
+	}
+	let validator_public = validator_pubkeys(&validators);
+
+	let mut genesis =
+		genesis_config(vec![(chain_a, ParaKind::Parachain), (chain_b, ParaKind::Parachain)]);
+	genesis.configuration.config.pending_commitments_by_reference = true;
+
+	new_test_ext(genesis).execute_with(|| {
+		shared::Pallet::<Test>::set_active_validators_ascending(validator_public.clone());
+		shared::Pallet::<Test>::set_session_index(5);
+
+		run_to_block(5, |_| None);
+
+		let signing_context =
+			SigningContext { parent_hash: System::parent_hash(), session_index: 5 };
+
+		let group_validators = |group_index: GroupIndex| {
+			match group_index {
+				group_index if group_index == GroupIndex::from(0) => Some(vec![0, 1]),
+				group_index if group_index == GroupIndex::from(1) => Some(vec![2, 3]),
+				_ => panic!("Group index out of bounds for 2 parachain cores"),
+			}
+			.map(|vs| vs.into_iter().map(ValidatorIndex).collect::<Vec<_>>())
+		};
+
+		let scheduled = vec![
+			CoreAssignment {
+				core: CoreIndex::from(0),
+				para_id: chain_a,
+				kind: AssignmentKind::Parachain,
+				group_idx: GroupIndex::from(0),
+			},
+			CoreAssignment {
+				core: CoreIndex::from(1),
+				para_id: chain_b,
+				kind: AssignmentKind::Parachain,
+				group_idx: GroupIndex::from(1),
+			},
+		];
+
+		let backed = |para_id, group: GroupIndex| {
+			let mut candidate = TestCandidateBuilder {
+				para_id,
+				relay_parent: System::parent_hash(),
+				pov_hash: Hash::repeat_byte(1),
+				persisted_validation_data_hash: make_vdata_hash(para_id).unwrap(),
+				hrmp_watermark: RELAY_PARENT_NUM,
+				head_data: HeadData(vec![1, 2, 3]),
+				..Default::default()
+			}
+			.build();
+			collator_sign_candidate(Sr25519Keyring::One, &mut candidate);
+
+			back_candidate(
+				candidate,
+				&validators,
+				group_validators(group).unwrap().as_ref(),
+				&keystore,
+				&signing_context,
+				BackingKind::Threshold,
+			)
+		};
+		let candidates =
+			|| vec![backed(chain_a, GroupIndex::from(0)), backed(chain_b, GroupIndex::from(1))];
+
+		// The candidate of chain A is written before the one of chain B fails, and is rolled back
+		// along with its commitment blobs, event and statistics.
+		set_failing_candidate_backing(Some(chain_b));
+		assert_noop!(
+			ParaInclusion::process_candidates(
+				Default::default(),
+				candidates(),
+				scheduled.clone(),
+				&group_validators,
+			),
+			DispatchError::Other("candidate backing failed"),
+		);
+		assert!(<PendingAvailability<Test>>::iter().next().is_none());
+		assert!(<PendingAvailabilityCommitments<Test>>::iter().next().is_none());
+		assert!(<PendingCommitmentsBlobs<Test>>::iter().next().is_none());
+
+		set_failing_candidate_backing(None);
+		ParaInclusion::process_candidates(
+			Default::default(),
+			candidates(),
+			scheduled,
+			&group_validators,
+		)
+		.expect("both candidates are backed");
+		assert!(<PendingAvailability<Test>>::get(&chain_a, CoreIndex::from(0)).is_some());
+		assert!(<PendingAvailability<Test>>::get(&chain_b, CoreIndex::from(1)).is_some());
+	});
+}
+
+#[test]
+fn paras_occupy_several_cores_only_if_enabled() {
+	let chain_a = ParaId::from(1_u32);
+	let chain_b = ParaId::from(2_u32);
+
+	const RELAY_PARENT_NUM: BlockNumber = 4;
+
+	let validators = vec![
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+	];
+	let keystore: KeystorePtr = Arc::new(LocalKeystore::in_memory());
+	for validator in validators.iter() {
+		Keystore::sr25519_generate_new(
+			&*keystore,
+			PARACHAIN_KEY_TYPE_ID,
+			Some(&validator.to_seed()),
+		)
+		.unwrap();
+	}
+	let validator_public = validator_pubkeys(&validators);
+
+	let genesis =
+		genesis_config(vec![(chain_a, ParaKind::Parachain), (chain_b, ParaKind::Parachain)]);
+
+	new_test_ext(genesis).execute_with(|| {
+		shared::Pallet::<Test>::set_active_validators_ascending(validator_public.clone());
+		shared::Pallet::<Test>::set_session_index(5);
+
+		run_to_block(5, |_| None);
+
+		let signing_context =
+			SigningContext { parent_hash: System::parent_hash(), session_index: 5 };
+
+		let group_validators = |group_index: GroupIndex| {
+			match group_index {
+				group_index if group_index == GroupIndex::from(0) => Some(vec![0, 1]),
+				group_index if group_index == GroupIndex::from(1) => Some(vec![2, 3]),
+				_ => panic!("Group index out of bounds for 2 parachain cores"),
+			}
+			.map(|vs| vs.into_iter().map(ValidatorIndex).collect::<Vec<_>>())
+		};
+
+		// both cores are scheduled for `chain_a`.
+		let scheduled = (0..2)
+			.map(|core| CoreAssignment {
+				core: CoreIndex::from(core),
+				para_id: chain_a,
+				kind: AssignmentKind::Parachain,
+				group_idx: GroupIndex::from(core),
+			})
+			.collect::<Vec<_>>();
+
+		let backed = |group: GroupIndex, pov: u8| {
+			let mut candidate = TestCandidateBuilder {
+				para_id: chain_a,
+				relay_parent: System::parent_hash(),
+				pov_hash: Hash::repeat_byte(pov),
+				persisted_validation_data_hash: make_vdata_hash(chain_a).unwrap(),
+				hrmp_watermark: RELAY_PARENT_NUM,
+				..Default::default()
+			}
+			.build();
+			collator_sign_candidate(Sr25519Keyring::One, &mut candidate);
+
+			back_candidate(
+				candidate,
+				&validators,
+				group_validators(group).unwrap().as_ref(),
+				&keystore,
+				&signing_context,
+				BackingKind::Threshold,
+			)
+		};
+		let candidates = vec![backed(GroupIndex::from(0), 1), backed(GroupIndex::from(1), 2)];
+
+		assert_noop!(
+			ParaInclusion::process_candidates(
+				Default::default(),
+				candidates.clone(),
+				scheduled.clone(),
+				&group_validators,
+			),
+			Error::<Test>::DuplicateCandidateForPara,
+		);
+
+		let mut config = Configuration::config();
+		config.multi_core_paras = true;
+		Configuration::force_set_active_config(config);
+
+		let processed = ParaInclusion::process_candidates(
+			Default::default(),
+			candidates.clone(),
+			scheduled,
+			&group_validators,
+		)
+		.expect("one candidate per scheduled core of the para is accepted");
+		assert_eq!(processed.core_indices, vec![CoreIndex::from(0), CoreIndex::from(1)]);
+
+		for (core, candidate) in candidates.iter().enumerate() {
+			let pending =
+				<PendingAvailability<Test>>::get(&chain_a, CoreIndex::from(core as u32)).unwrap();
+			assert_eq!(pending.hash, candidate.hash());
+			assert!(<PendingAvailabilityCommitments<Test>>::contains_key(
+				&chain_a,
+				CoreIndex::from(core as u32),
+			));
+		}
+		assert_eq!(
+			ParaInclusion::candidate_pending_availability(chain_a),
+			Some(candidates[0].candidate.clone()),
+		);
+	});
+}
+
+#[test]
+fn candidates_of_a_para_are_queued_by_relay_parent() {
+	let chain_a = ParaId::from(1_u32);
+
+	new_test_ext(genesis_config(vec![(chain_a, ParaKind::Parachain)])).execute_with(|| {
+		run_to_block(5, |_| None);
+
+		// the candidate on the higher core is built on the older relay parent.
+		let mut candidates = Vec::new();
+		for (core, relay_parent_number) in [(0, 4), (1, 3), (2, 4)] {
+			let candidate = TestCandidateBuilder {
+				para_id: chain_a,
+				pov_hash: Hash::repeat_byte(core as u8),
+				..Default::default()
+			}
+			.build();
+			<PendingAvailability<Test>>::insert(
+				chain_a,
+				CoreIndex::from(core),
+				CandidatePendingAvailability {
+					core: CoreIndex::from(core),
+					hash: candidate.hash(),
+					descriptor: candidate.descriptor.clone(),
+					availability_votes: default_availability_votes(),
+					relay_parent_number,
+					backed_in_number: 4,
+					backers: backing_bitfield(&[0]),
+					backing_group: GroupIndex::from(core),
+					assignment_source: CoreAssignmentSource::Lease,
+				},
+			);
+			<PendingAvailabilityCommitments<Test>>::insert(
+				chain_a,
+				CoreIndex::from(core),
+				candidate.commitments.clone(),
+			);
+			candidates.push(candidate);
+		}
+
+		// the oldest candidate is the head of the queue.
+		assert_eq!(
+			ParaInclusion::candidate_pending_availability(chain_a),
+			Some(candidates[1].clone()),
+		);
+
+		// among candidates on the same relay parent, the one on the lower core comes first.
+		<PendingAvailability<Test>>::remove(&chain_a, CoreIndex::from(1));
+		<PendingAvailabilityCommitments<Test>>::remove(&chain_a, CoreIndex::from(1));
+		assert_eq!(
+			ParaInclusion::candidate_pending_availability(chain_a),
+			Some(candidates[0].clone()),
+		);
+	});
+}
+
+#[test]
+fn enactment_beyond_the_weight_budget_is_deferred() {
+	let chain_a = ParaId::from(1_u32);
+	let chain_b = ParaId::from(2_u32);
+
+	let paras = vec![(chain_a, ParaKind::Parachain), (chain_b, ParaKind::Parachain)];
+	let validators = vec![Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie];
+	let validator_public = validator_pubkeys(&validators);
+
+	let mut genesis = genesis_config(paras);
+	// Room for a single enactment per block.
+	genesis.configuration.config.max_enactment_weight_per_block =
+		ParaInclusion::max_enactment_weight();
+
+	new_test_ext(genesis).execute_with(|| {
+		shared::Pallet::<Test>::set_active_validators_ascending(validator_public.clone());
+		run_to_block(5, |_| None);
+
+		let mut hashes = Vec::new();
+		for (core, para_id) in [(0, chain_a), (1, chain_b)] {
+			let candidate = TestCandidateBuilder { para_id, ..Default::default() }.build();
+			hashes.push(candidate.hash());
+			<PendingAvailability<Test>>::insert(
+				para_id,
+				CoreIndex::from(core),
+				CandidatePendingAvailability {
+					core: CoreIndex::from(core),
+					hash: candidate.hash(),
+					descriptor: candidate.descriptor.clone(),
+					availability_votes: backing_bitfield(&[0, 1, 2]),
+					relay_parent_number: 3,
+					backed_in_number: 4,
+					backers: backing_bitfield(&[0, 1]),
+					backing_group: GroupIndex::from(core),
+					assignment_source: CoreAssignmentSource::Lease,
+				},
+			);
+			<PendingAvailabilityCommitments<Test>>::insert(
+				para_id,
+				CoreIndex::from(core),
+				candidate.commitments,
+			);
+		}
+
+		let core_lookup = |core| match core {
+			core if core == CoreIndex::from(0) => Some(chain_a),
+			core if core == CoreIndex::from(1) => Some(chain_b),
+			_ => None,
+		};
+		let update = || {
+			ParaInclusion::update_pending_availability_and_get_freed_cores(
+				expected_bits(),
+				&validator_public[..],
+				Vec::new(),
+				Vec::new(),
+				&core_lookup,
+				true,
+			)
+			.0
+		};
+
+		// Both are available, but only the first one fits.
+		assert_eq!(update(), vec![(CoreIndex::from(0), hashes[0])]);
+		assert!(<PendingAvailability<Test>>::get(&chain_a, CoreIndex::from(0)).is_none());
+		assert_eq!(<ReadyToEnact<Test>>::get(), vec![hashes[1]]);
+
+		// The deferred candidate keeps its core and doesn't time out.
+		assert!(ParaInclusion::collect_pending(|_, _| true).is_empty());
+
+		// It is enacted in the next block, even without collecting the votes again.
+		<PendingAvailability<Test>>::mutate(&chain_b, CoreIndex::from(1), |pending| {
+			pending.as_mut().unwrap().availability_votes = default_availability_votes();
+		});
+		run_to_block(6, |_| None);
+		assert_eq!(update(), vec![(CoreIndex::from(1), hashes[1])]);
+		assert!(<PendingAvailability<Test>>::get(&chain_b, CoreIndex::from(1)).is_none());
+		assert!(<ReadyToEnact<Test>>::get().is_empty());
+	});
+}
+
+#[test]
+fn availability_votes_are_reported_per_core() {
+	let chain_a = ParaId::from(1_u32);
+
+	let validators = vec![
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+	];
+	let validator_public = validator_pubkeys(&validators);
+
+	new_test_ext(genesis_config(vec![(chain_a, ParaKind::Parachain)])).execute_with(|| {
+		shared::Pallet::<Test>::set_active_validators_ascending(validator_public.clone());
+		run_to_block(5, |_| None);
+
+		assert!(ParaInclusion::availability_votes(CoreIndex::from(0)).is_none());
+
+		let candidate = TestCandidateBuilder { para_id: chain_a, ..Default::default() }.build();
+		<PendingAvailability<Test>>::insert(
+			chain_a,
+			CoreIndex::from(0),
+			CandidatePendingAvailability {
+				core: CoreIndex::from(0),
+				hash: candidate.hash(),
+				descriptor: candidate.descriptor.clone(),
+				availability_votes: backing_bitfield(&[0, 3]),
+				relay_parent_number: 3,
+				backed_in_number: 4,
+				backers: backing_bitfield(&[0, 1]),
+				backing_group: GroupIndex::from(0),
+				assignment_source: CoreAssignmentSource::Lease,
+			},
+		);
+		scheduler::AvailabilityCores::<Test>::put(vec![Some(CoreOccupied::Parachain)]);
+
+		assert_eq!(
+			ParaInclusion::availability_votes(CoreIndex::from(0)),
+			Some(CoreAvailabilityVotes {
+				candidate_hash: candidate.hash(),
+				votes: 2,
+				threshold: 3,
+			}),
+		);
+		assert!(ParaInclusion::availability_votes(CoreIndex::from(1)).is_none());
+	});
+}
+
+#[test]
+fn pending_availability_out_of_sync_with_commitments_is_repaired() {
+	let chain_a = ParaId::from(1_u32);
+	let chain_b = ParaId::from(2_u32);
+
+	let paras = vec![(chain_a, ParaKind::Parachain), (chain_b, ParaKind::Parachain)];
+	let validators = vec![Sr25519Keyring::Alice, Sr25519Keyring::Bob];
+	let validator_public = validator_pubkeys(&validators);
+
+	new_test_ext(genesis_config(paras)).execute_with(|| {
+		shared::Pallet::<Test>::set_active_validators_ascending(validator_public.clone());
+		run_to_block(5, |_| None);
+
+		// chain A has a candidate pending availability without commitments, chain B commitments
+		// without a candidate.
+		let candidate = TestCandidateBuilder { para_id: chain_a, ..Default::default() }.build();
+		<PendingAvailability<Test>>::insert(
+			chain_a,
+			CoreIndex::from(0),
+			CandidatePendingAvailability {
+				core: CoreIndex::from(0),
+				hash: candidate.hash(),
+				descriptor: candidate.descriptor.clone(),
+				availability_votes: default_availability_votes(),
+				relay_parent_number: 3,
+				backed_in_number: 4,
+				backers: backing_bitfield(&[0]),
+				backing_group: GroupIndex::from(0),
+				assignment_source: CoreAssignmentSource::Lease,
+			},
+		);
+		scheduler::AvailabilityCores::<Test>::put(vec![Some(CoreOccupied::Parachain), None]);
+		let commitments =
+			CandidateCommitments { head_data: vec![1, 2, 3].into(), ..Default::default() };
+		ParaInclusion::insert_pending_commitments(&chain_b, CoreIndex::from(1), commitments, true);
+		assert!(ParaInclusion::do_try_state().is_err());
+
+		run_to_block(6, |_| None);
+
+		assert!(<PendingAvailability<Test>>::get(&chain_a, CoreIndex::from(0)).is_none());
+		assert!(<PendingAvailabilityCommitments<Test>>::get(&chain_b, CoreIndex::from(1)).is_none());
+		assert!(<PendingCommitmentsBlobs<Test>>::iter().next().is_none());
+		assert_eq!(scheduler::Pallet::<Test>::availability_cores(), vec![None, None]);
+		for para_id in [chain_a, chain_b] {
+			System::assert_has_event(Event::<Test>::StorageInconsistencyRepaired(para_id).into());
+		}
+		assert_eq!(ParaInclusion::storage_inconsistencies_repaired(), 2);
+		assert_ok!(ParaInclusion::do_try_state());
+
+		// Nothing is left to repair.
+		run_to_block(7, |_| None);
+		assert_eq!(ParaInclusion::storage_inconsistencies_repaired(), 2);
+	});
+}
+
+#[test]
+fn pending_commitments_can_be_stored_by_reference() {
+	let chain_a = ParaId::from(1_u32);
+	let chain_b = ParaId::from(2_u32);
+
+	let paras = vec![(chain_a, ParaKind::Parachain), (chain_b, ParaKind::Parachain)];
+	new_test_ext(genesis_config(paras)).execute_with(|| {
+		let commitments = CandidateCommitments {
+			head_data: vec![1, 2, 3].into(),
+			new_validation_code: Some(vec![4, 5, 6].into()),
+			..Default::default()
+		};
+		let head_hash = BlakeTwo256::hash(&[1, 2, 3]);
+		let code_hash = BlakeTwo256::hash(&[4, 5, 6]);
+
+		ParaInclusion::insert_pending_commitments(
+			&chain_a,
+			CoreIndex::from(0),
+			commitments.clone(),
+			true,
+		);
+		ParaInclusion::insert_pending_commitments(
+			&chain_b,
+			CoreIndex::from(1),
+			commitments.clone(),
+			true,
+		);
+
+		// Only the light commitments are stored per para, and the blobs are shared.
+		let stored =
+			<PendingAvailabilityCommitments<Test>>::get(&chain_a, CoreIndex::from(0)).unwrap();
+		assert!(stored.head_data.0.is_empty());
+		assert!(stored.new_validation_code.is_none());
+		assert_eq!(
+			<PendingCommitmentsRefs<Test>>::get(&chain_a, CoreIndex::from(0)),
+			Some((head_hash, Some(code_hash)))
+		);
+		assert_eq!(<PendingCommitmentsBlobs<Test>>::get(&head_hash), Some((2, vec![1, 2, 3])));
+		assert_eq!(<PendingCommitmentsBlobs<Test>>::get(&code_hash), Some((2, vec![4, 5, 6])));
+
+		assert_eq!(
+			ParaInclusion::pending_commitments(&chain_a, CoreIndex::from(0)),
+			Some(commitments.clone())
+		);
+		assert_eq!(
+			ParaInclusion::take_pending_commitments(&chain_a, CoreIndex::from(0)),
+			Some(commitments.clone())
+		);
+		assert_eq!(<PendingCommitmentsBlobs<Test>>::get(&head_hash), Some((1, vec![1, 2, 3])));
+
+		assert_eq!(
+			ParaInclusion::take_pending_commitments(&chain_b, CoreIndex::from(1)),
+			Some(commitments.clone())
+		);
+		assert!(<PendingCommitmentsBlobs<Test>>::iter().next().is_none());
+		assert!(<PendingCommitmentsRefs<Test>>::iter().next().is_none());
+
+		// Without the option, the commitments are stored in full.
+		ParaInclusion::insert_pending_commitments(
+			&chain_a,
+			CoreIndex::from(0),
+			commitments.clone(),
+			false,
+		);
+		assert_eq!(
+			<PendingAvailabilityCommitments<Test>>::get(&chain_a, CoreIndex::from(0)),
+			Some(commitments)
+		);
+		assert!(<PendingCommitmentsRefs<Test>>::get(&chain_a, CoreIndex::from(0)).is_none());
+	});
+}
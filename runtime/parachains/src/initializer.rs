@@ -22,7 +22,7 @@
 use crate::{
 	configuration::{self, HostConfiguration},
 	disputes::{self, DisputesHandler as _, SlashingHandler as _},
-	dmp, hrmp, inclusion, paras, scheduler, session_info, shared, ump,
+	dmp, hrmp, inclusion, paras, scheduler, session_info, shared, statistics, ump,
 };
 use frame_support::{
 	traits::{OneSessionHandler, Randomness},
@@ -42,11 +42,16 @@ mod benchmarking;
 
 pub use pallet::*;
 
+/// The maximum number of stages of removing the state of offboarded paras run in a single block.
+pub const MAX_OFFBOARDING_CLEANUP_STAGES_PER_BLOCK: u32 = 4;
+
 /// Information about a session change that has just occurred.
 #[derive(Clone)]
 pub struct SessionChangeNotification<BlockNumber> {
 	/// The new validators in the session.
 	pub validators: Vec<ValidatorId>,
+	/// The validators of the session that just ended.
+	pub prev_validators: Vec<ValidatorId>,
 	/// The queued validators for the following session.
 	pub queued: Vec<ValidatorId>,
 	/// The configuration before handling the session change
@@ -66,6 +71,7 @@ impl<BlockNumber: Default + From<u32>> Default for SessionChangeNotification<Blo
 	fn default() -> Self {
 		Self {
 			validators: Vec::new(),
+			prev_validators: Vec::new(),
 			queued: Vec::new(),
 			prev_config: HostConfiguration::default(),
 			new_config: HostConfiguration::default(),
@@ -82,6 +88,29 @@ struct BufferedSessionChange {
 	session_index: SessionIndex,
 }
 
+/// A hook for other pallets to be notified of a session change.
+///
+/// It is called after all parachains runtime modules have handled the session change, so
+/// implementations observe the state of the new session.
+pub trait OnNewSession<BlockNumber> {
+	fn on_new_session(notification: &SessionChangeNotification<BlockNumber>);
+}
+
+impl<BlockNumber> OnNewSession<BlockNumber> for () {
+	fn on_new_session(_: &SessionChangeNotification<BlockNumber>) {}
+}
+
+impl<BlockNumber, A, B> OnNewSession<BlockNumber> for (A, B)
+where
+	A: OnNewSession<BlockNumber>,
+	B: OnNewSession<BlockNumber>,
+{
+	fn on_new_session(notification: &SessionChangeNotification<BlockNumber>) {
+		A::on_new_session(notification);
+		B::on_new_session(notification);
+	}
+}
+
 pub trait WeightInfo {
 	fn force_approve(d: u32) -> Weight;
 }
@@ -115,6 +144,7 @@ pub mod pallet {
 		+ dmp::Config
 		+ ump::Config
 		+ hrmp::Config
+		+ statistics::Config
 	{
 		/// A randomness beacon.
 		type Randomness: Randomness<Self::Hash, Self::BlockNumber>;
@@ -122,6 +152,8 @@ pub mod pallet {
 		type ForceOrigin: EnsureOrigin<<Self as frame_system::Config>::RuntimeOrigin>;
 		/// Weight information for extrinsics in this pallet.
 		type WeightInfo: WeightInfo;
+		/// Handlers notified of a session change once all parachains modules have applied it.
+		type OnNewSession: OnNewSession<Self::BlockNumber>;
 	}
 
 	/// Whether the parachains modules have been initialized within this block.
@@ -165,11 +197,13 @@ pub mod pallet {
 				scheduler::Pallet::<T>::initializer_initialize(now) +
 				inclusion::Pallet::<T>::initializer_initialize(now) +
 				session_info::Pallet::<T>::initializer_initialize(now) +
+				statistics::Pallet::<T>::initializer_initialize(now) +
 				T::DisputesHandler::initializer_initialize(now) +
 				T::SlashingHandler::initializer_initialize(now) +
 				dmp::Pallet::<T>::initializer_initialize(now) +
 				ump::Pallet::<T>::initializer_initialize(now) +
-				hrmp::Pallet::<T>::initializer_initialize(now);
+				hrmp::Pallet::<T>::initializer_initialize(now) +
+				Self::clean_up_offboarded_paras();
 
 			HasInitialized::<T>::set(Some(()));
 
@@ -183,6 +217,7 @@ pub mod pallet {
 			dmp::Pallet::<T>::initializer_finalize();
 			T::SlashingHandler::initializer_finalize();
 			T::DisputesHandler::initializer_finalize();
+			statistics::Pallet::<T>::initializer_finalize();
 			session_info::Pallet::<T>::initializer_finalize();
 			inclusion::Pallet::<T>::initializer_finalize();
 			scheduler::Pallet::<T>::initializer_finalize();
@@ -226,6 +261,12 @@ pub mod pallet {
 }
 
 impl<T: Config> Pallet<T> {
+	/// Apply a session change to all modules.
+	///
+	/// The order is fixed: the configuration is applied first and the active validators are
+	/// updated by `shared`, then `paras`, `scheduler`, `inclusion`, `session_info`, the disputes
+	/// and slashing handlers and finally the message queues observe the change. Other pallets
+	/// are notified last through `OnNewSession`.
 	fn apply_new_session(
 		session_index: SessionIndex,
 		all_validators: Vec<ValidatorId>,
@@ -245,6 +286,7 @@ impl<T: Config> Pallet<T> {
 			configuration::Pallet::<T>::initializer_on_new_session(&session_index);
 		let new_config = new_config.unwrap_or_else(|| prev_config.clone());
 
+		let prev_validators = shared::Pallet::<T>::active_validator_keys();
 		let validators = shared::Pallet::<T>::initializer_on_new_session(
 			session_index,
 			random_seed,
@@ -254,6 +296,7 @@ impl<T: Config> Pallet<T> {
 
 		let notification = SessionChangeNotification {
 			validators,
+			prev_validators,
 			queued,
 			prev_config,
 			new_config,
@@ -265,11 +308,42 @@ impl<T: Config> Pallet<T> {
 		scheduler::Pallet::<T>::initializer_on_new_session(&notification);
 		inclusion::Pallet::<T>::initializer_on_new_session(&notification);
 		session_info::Pallet::<T>::initializer_on_new_session(&notification);
+		statistics::Pallet::<T>::initializer_on_new_session(&notification);
 		T::DisputesHandler::initializer_on_new_session(&notification);
 		T::SlashingHandler::initializer_on_new_session(session_index);
 		dmp::Pallet::<T>::initializer_on_new_session(&notification, &outgoing_paras);
 		ump::Pallet::<T>::initializer_on_new_session(&notification, &outgoing_paras);
 		hrmp::Pallet::<T>::initializer_on_new_session(&notification, &outgoing_paras);
+		T::OnNewSession::on_new_session(&notification);
+	}
+
+	/// Run the next `MAX_OFFBOARDING_CLEANUP_STAGES_PER_BLOCK` stages of removing the state of the
+	/// paras offboarded at previous session changes from the other modules.
+	///
+	/// The modules remove most of the state of a para as soon as it is offboarded. What they leave
+	/// behind is removed here one stage at a time, to keep the weight of each block bounded.
+	fn clean_up_offboarded_paras() -> Weight {
+		let mut weight = Weight::zero();
+		for _ in 0..MAX_OFFBOARDING_CLEANUP_STAGES_PER_BLOCK {
+			weight = weight.saturating_add(T::DbWeight::get().reads_writes(1, 1));
+			let (para, stage) = match paras::Pallet::<T>::next_offboarding_cleanup() {
+				Some(next) => next,
+				None => break,
+			};
+
+			weight = weight.saturating_add(match stage {
+				paras::OffboardingCleanupStage::PendingAvailability =>
+					inclusion::Pallet::<T>::clean_up_offboarded_para(para),
+				paras::OffboardingCleanupStage::SchedulerClaims =>
+					scheduler::Pallet::<T>::clean_up_offboarded_para(para),
+				paras::OffboardingCleanupStage::HrmpChannels =>
+					hrmp::Pallet::<T>::clean_up_offboarded_para(para),
+				paras::OffboardingCleanupStage::DownwardMessages =>
+					dmp::Pallet::<T>::clean_up_offboarded_para(para),
+			});
+			paras::Pallet::<T>::note_offboarding_cleanup_progress(para, stage);
+		}
+		weight
 	}
 
 	/// Should be called when a new session occurs. Buffers the session notification to be applied
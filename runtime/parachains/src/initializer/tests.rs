@@ -16,8 +16,8 @@
 
 use super::*;
 use crate::mock::{
-	new_test_ext, Configuration, Dmp, Initializer, MockGenesisConfig, Paras, SessionInfo, System,
-	Test,
+	new_test_ext, Configuration, Dmp, Initializer, MockGenesisConfig, Paras, RuntimeEvent,
+	SessionInfo, System, Test,
 };
 use primitives::{HeadData, Id as ParaId};
 use test_helpers::dummy_validation_code;
@@ -66,6 +66,20 @@ fn session_change_applied_on_finalize() {
 	});
 }
 
+#[test]
+fn other_pallets_are_notified_once_session_change_is_applied() {
+	use crate::mock::NEW_SESSIONS;
+
+	new_test_ext(Default::default()).execute_with(|| {
+		Initializer::on_initialize(1);
+		Initializer::on_new_session(false, 1, Vec::new().into_iter(), Some(Vec::new().into_iter()));
+		assert!(NEW_SESSIONS.with(|r| r.borrow().is_empty()));
+
+		Initializer::on_finalize(1);
+		assert_eq!(NEW_SESSIONS.with(|r| r.borrow().clone()), vec![1]);
+	});
+}
+
 #[test]
 fn sets_flag_on_initialize() {
 	new_test_ext(Default::default()).execute_with(|| {
@@ -131,3 +145,88 @@ fn scheduled_cleanup_performed() {
 		assert!(!Dmp::dmq_contents(c).is_empty());
 	});
 }
+
+#[test]
+fn offboarded_paras_are_cleaned_up_over_blocks() {
+	use crate::paras::OffboardingCleanupStage;
+	use sp_runtime::FixedU128;
+
+	let a = ParaId::from(1312);
+	let b = ParaId::from(228);
+
+	let mock_genesis = crate::paras::ParaGenesisArgs {
+		para_kind: ParaKind::Parachain,
+		genesis_head: HeadData(vec![4, 5, 6]),
+		validation_code: dummy_validation_code(),
+	};
+
+	new_test_ext(MockGenesisConfig {
+		paras: crate::paras::GenesisConfig {
+			paras: vec![(a, mock_genesis.clone()), (b, mock_genesis.clone())],
+			..Default::default()
+		},
+		..Default::default()
+	})
+	.execute_with(|| {
+		System::set_block_number(1);
+
+		// State that is left behind when the paras are offboarded.
+		for para in [a, b] {
+			crate::hrmp::HrmpWatermarks::<Test>::insert(para, 1);
+			crate::dmp::DeliveryFeeFactor::<Test>::insert(para, FixedU128::from_u32(2));
+		}
+
+		assert_ok!(Paras::schedule_para_cleanup(a));
+		assert_ok!(Paras::schedule_para_cleanup(b));
+		Initializer::apply_new_session(2, vec![], vec![]);
+
+		assert!(Paras::lifecycle(a).is_none());
+		assert!(Paras::lifecycle(b).is_none());
+		assert!(crate::hrmp::HrmpWatermarks::<Test>::contains_key(a));
+		assert!(crate::hrmp::HrmpWatermarks::<Test>::contains_key(b));
+
+		// Each block runs a bounded number of stages, starting with the para offboarded first.
+		let offboarded_first = Paras::next_offboarding_cleanup().unwrap().0;
+		let offboarded_last = if offboarded_first == a { b } else { a };
+		Initializer::on_initialize(2);
+
+		assert!(!crate::hrmp::HrmpWatermarks::<Test>::contains_key(offboarded_first));
+		assert!(!crate::dmp::DeliveryFeeFactor::<Test>::contains_key(offboarded_first));
+		assert!(crate::hrmp::HrmpWatermarks::<Test>::contains_key(offboarded_last));
+		assert_eq!(
+			Paras::next_offboarding_cleanup(),
+			Some((offboarded_last, OffboardingCleanupStage::PendingAvailability)),
+		);
+
+		Initializer::on_finalize(2);
+		System::set_block_number(3);
+		Initializer::on_initialize(3);
+
+		assert!(!crate::hrmp::HrmpWatermarks::<Test>::contains_key(offboarded_last));
+		assert!(!crate::dmp::DeliveryFeeFactor::<Test>::contains_key(offboarded_last));
+		assert!(Paras::next_offboarding_cleanup().is_none());
+
+		let progress = System::events()
+			.into_iter()
+			.filter_map(|r| match r.event {
+				RuntimeEvent::Paras(e @ crate::paras::Event::OffboardingCleanupProgressed(..)) |
+				RuntimeEvent::Paras(e @ crate::paras::Event::OffboardingCleanupCompleted(..)) =>
+					Some(e),
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+		let mut expected = Vec::new();
+		for para in [offboarded_first, offboarded_last] {
+			for stage in [
+				OffboardingCleanupStage::PendingAvailability,
+				OffboardingCleanupStage::SchedulerClaims,
+				OffboardingCleanupStage::HrmpChannels,
+				OffboardingCleanupStage::DownwardMessages,
+			] {
+				expected.push(crate::paras::Event::OffboardingCleanupProgressed(para, stage));
+			}
+			expected.push(crate::paras::Event::OffboardingCleanupCompleted(para));
+		}
+		assert_eq!(progress, expected);
+	});
+}
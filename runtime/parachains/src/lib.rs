@@ -30,6 +30,7 @@ pub mod hrmp;
 pub mod inclusion;
 pub mod initializer;
 pub mod metrics;
+pub mod migrations;
 pub mod origin;
 pub mod paras;
 pub mod paras_inherent;
@@ -37,6 +38,7 @@ pub mod reward_points;
 pub mod scheduler;
 pub mod session_info;
 pub mod shared;
+pub mod statistics;
 pub mod ump;
 
 pub mod runtime_api_impl;
@@ -47,6 +49,8 @@ mod util;
 mod builder;
 #[cfg(test)]
 mod mock;
+#[cfg(any(feature = "testing", test))]
+pub mod testing;
 
 pub use origin::{ensure_parachain, Origin};
 pub use paras::ParaLifecycle;
@@ -0,0 +1,120 @@
+// Copyright (C) Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Storage migrations of the parachains pallets.
+//!
+//! Every pallet with versioned storage tracks the format of its storage in its own
+//! `StorageVersion`, and keeps a migration to each version in its `migration` module. Each of
+//! these migrations only runs while the on-chain version of its pallet is the one it migrates
+//! from, so runtimes can run all of them on every upgrade through [`Unreleased`], instead of
+//! picking the ones each chain still needs.
+
+use crate::{configuration, inclusion, session_info, ump};
+use frame_support::{
+	traits::{GetStorageVersion, OnRuntimeUpgrade, PalletInfoAccess},
+	weights::Weight,
+};
+use sp_std::prelude::*;
+
+const LOG_TARGET: &str = "runtime::parachains::migrations";
+
+/// The migrations of the parachains pallets that are not released yet, in the order they must
+/// run.
+///
+/// Add the migration to each new storage version here, and move them out once released.
+pub type Unreleased<T> = (
+	configuration::migration::v5::MigrateToV5<T>,
+	configuration::migration::v6::MigrateToV6<T>,
+	// Keys the candidates pending availability by the core they occupy.
+	inclusion::migration::v1::MigrateToV1<T>,
+	inclusion::migration::v2::MigrateToV2<T>,
+	CheckStorageVersions<T>,
+);
+
+/// The parachains pallets whose on-chain storage version is behind the version of their code,
+/// meaning a migration of theirs is missing from the runtime.
+pub fn outdated_pallets<T>() -> Vec<&'static str>
+where
+	T: configuration::Config + inclusion::Config + session_info::Config + ump::Config,
+{
+	fn is_outdated<P: GetStorageVersion + PalletInfoAccess>() -> Option<&'static str> {
+		(P::on_chain_storage_version() < P::current_storage_version()).then(P::name)
+	}
+
+	[
+		is_outdated::<configuration::Pallet<T>>(),
+		is_outdated::<inclusion::Pallet<T>>(),
+		is_outdated::<session_info::Pallet<T>>(),
+		is_outdated::<ump::Pallet<T>>(),
+	]
+	.into_iter()
+	.flatten()
+	.collect()
+}
+
+/// Reports the parachains pallets left with outdated storage once the migrations ran, and fails
+/// `try-runtime` checks if there are any.
+pub struct CheckStorageVersions<T>(sp_std::marker::PhantomData<T>);
+impl<T> OnRuntimeUpgrade for CheckStorageVersions<T>
+where
+	T: configuration::Config + inclusion::Config + session_info::Config + ump::Config,
+{
+	fn on_runtime_upgrade() -> Weight {
+		for pallet in outdated_pallets::<T>() {
+			log::warn!(
+				target: LOG_TARGET,
+				"The storage of {} is outdated, a migration is missing from the runtime",
+				pallet,
+			);
+		}
+
+		T::DbWeight::get().reads(4)
+	}
+
+	#[cfg(feature = "try-runtime")]
+	fn post_upgrade(_state: Vec<u8>) -> Result<(), &'static str> {
+		frame_support::ensure!(
+			outdated_pallets::<T>().is_empty(),
+			"The storage of a parachains pallet is outdated after the migrations",
+		);
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::{new_test_ext, MockGenesisConfig, ParaInclusion, Test};
+	use frame_support::traits::StorageVersion;
+
+	#[test]
+	fn outdated_pallets_are_reported() {
+		new_test_ext(MockGenesisConfig::default()).execute_with(|| {
+			configuration::migration::STORAGE_VERSION.put::<configuration::Pallet<Test>>();
+			inclusion::migration::STORAGE_VERSION.put::<inclusion::Pallet<Test>>();
+			session_info::migration::STORAGE_VERSION.put::<session_info::Pallet<Test>>();
+			ump::migration::STORAGE_VERSION.put::<ump::Pallet<Test>>();
+			assert!(outdated_pallets::<Test>().is_empty());
+
+			StorageVersion::new(1).put::<inclusion::Pallet<Test>>();
+			assert_eq!(outdated_pallets::<Test>(), vec![ParaInclusion::name()]);
+
+			// running the unreleased migrations brings the pallet up to date.
+			Unreleased::<Test>::on_runtime_upgrade();
+			assert!(outdated_pallets::<Test>().is_empty());
+		});
+	}
+}
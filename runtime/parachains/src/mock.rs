@@ -18,12 +18,13 @@
 
 use crate::{
 	configuration, disputes, dmp, hrmp, inclusion, initializer, origin, paras, paras_inherent,
-	scheduler, session_info, shared,
+	scheduler, session_info, shared, statistics,
 	ump::{self, MessageId, UmpSink},
 	ParaId,
 };
 
 use frame_support::{
+	dispatch::DispatchResult,
 	parameter_types,
 	traits::{ConstU32, GenesisBuild, ValidatorSet, ValidatorSetWithIdentification},
 	weights::Weight,
@@ -31,15 +32,15 @@ use frame_support::{
 use frame_support_test::TestRandomness;
 use parity_scale_codec::Decode;
 use primitives::{
-	AuthorityDiscoveryId, Balance, BlockNumber, CandidateHash, Header, Moment, SessionIndex,
-	UpwardMessage, ValidatorIndex,
+	AuthorityDiscoveryId, Balance, BlockNumber, CandidateHash, CoreIndex, Header, Moment,
+	SessionIndex, UpwardMessage, ValidatorIndex,
 };
 use sp_core::H256;
 use sp_io::TestExternalities;
 use sp_runtime::{
 	traits::{BlakeTwo256, IdentityLookup},
 	transaction_validity::TransactionPriority,
-	Permill,
+	DispatchError, Permill,
 };
 use std::{cell::RefCell, collections::HashMap};
 
@@ -66,6 +67,7 @@ frame_support::construct_runtime!(
 		Hrmp: hrmp,
 		ParachainsOrigin: origin,
 		SessionInfo: session_info,
+		Statistics: statistics,
 		Disputes: disputes,
 		Babe: pallet_babe,
 	}
@@ -176,13 +178,42 @@ impl crate::initializer::Config for Test {
 	type Randomness = TestRandomness<Self>;
 	type ForceOrigin = frame_system::EnsureRoot<u64>;
 	type WeightInfo = ();
+	type OnNewSession = Self;
+}
+
+thread_local! {
+	pub static NEW_SESSIONS: RefCell<Vec<SessionIndex>> = RefCell::new(Vec::new());
+}
+
+impl crate::initializer::OnNewSession<BlockNumber> for Test {
+	fn on_new_session(notification: &crate::initializer::SessionChangeNotification<BlockNumber>) {
+		NEW_SESSIONS.with(|r| r.borrow_mut().push(notification.session_index))
+	}
 }
 
 impl crate::configuration::Config for Test {
 	type WeightInfo = crate::configuration::TestWeightInfo;
 }
 
-impl crate::shared::Config for Test {}
+thread_local! {
+	pub static DISABLED_VALIDATORS: RefCell<Vec<u32>> = RefCell::new(Vec::new());
+}
+
+/// Disable the validators with the given indices into the session's validator set.
+pub fn set_disabled_validators(disabled: Vec<u32>) {
+	DISABLED_VALIDATORS.with(|r| *r.borrow_mut() = disabled);
+}
+
+pub struct TestDisabledValidators;
+impl frame_support::traits::DisabledValidators for TestDisabledValidators {
+	fn is_disabled(index: u32) -> bool {
+		DISABLED_VALIDATORS.with(|r| r.borrow().contains(&index))
+	}
+}
+
+impl crate::shared::Config for Test {
+	type DisabledValidators = TestDisabledValidators;
+}
 
 impl origin::Config for Test {}
 
@@ -210,6 +241,7 @@ impl frame_support::traits::EstimateNextSessionRotation<u32> for TestNextSession
 
 impl crate::paras::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
+	type RuntimeOrigin = RuntimeOrigin;
 	type WeightInfo = crate::paras::TestWeightInfo;
 	type UnsignedPriority = ParasUnsignedPriority;
 	type NextSessionRotation = TestNextSessionRotation;
@@ -248,6 +280,8 @@ thread_local! {
 	pub static PUNISH_VALIDATORS_FOR: RefCell<Vec<(SessionIndex, Vec<ValidatorIndex>)>> = RefCell::new(Vec::new());
 	pub static PUNISH_VALIDATORS_AGAINST: RefCell<Vec<(SessionIndex, Vec<ValidatorIndex>)>> = RefCell::new(Vec::new());
 	pub static PUNISH_BACKERS_FOR: RefCell<Vec<(SessionIndex, Vec<ValidatorIndex>)>> = RefCell::new(Vec::new());
+	pub static PUNISH_BACKING_EQUIVOCATION: RefCell<Vec<(SessionIndex, CandidateHash, Vec<ValidatorIndex>)>> = RefCell::new(Vec::new());
+	pub static PUNISH_AVAILABILITY_NO_SHOWS: RefCell<Vec<(SessionIndex, Vec<ValidatorIndex>, bool)>> = RefCell::new(Vec::new());
 }
 
 impl crate::disputes::RewardValidators for Test {
@@ -290,12 +324,105 @@ impl crate::disputes::SlashingHandler<BlockNumber> for Test {
 	fn initializer_on_new_session(_: SessionIndex) {}
 }
 
-impl crate::scheduler::Config for Test {}
+impl crate::inclusion::BackingEquivocationHandler for Test {
+	fn punish_backing_equivocation(
+		session: SessionIndex,
+		candidate_hash: CandidateHash,
+		offenders: impl IntoIterator<Item = ValidatorIndex>,
+	) {
+		PUNISH_BACKING_EQUIVOCATION.with(|r| {
+			r.borrow_mut().push((session, candidate_hash, offenders.into_iter().collect()))
+		})
+	}
+}
+
+impl crate::inclusion::AvailabilityNoShowHandler for Test {
+	fn punish_availability_no_shows(
+		session: SessionIndex,
+		offenders: impl IntoIterator<Item = ValidatorIndex>,
+		repeated: bool,
+	) {
+		PUNISH_AVAILABILITY_NO_SHOWS
+			.with(|r| r.borrow_mut().push((session, offenders.into_iter().collect(), repeated)))
+	}
+}
+
+/// A call of one of the [`crate::inclusion::InclusionHooks`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum InclusionHookCall {
+	CandidateBacked(ParaId, CandidateHash, CoreIndex),
+	CandidateIncluded(ParaId, CandidateHash, CoreIndex),
+	CandidateTimedOut(ParaId, CandidateHash, CoreIndex),
+	BitfieldsProcessed(u32),
+}
+
+thread_local! {
+	pub static INCLUSION_HOOK_CALLS: RefCell<Vec<InclusionHookCall>> = RefCell::new(Vec::new());
+	pub static FAILING_CANDIDATE_BACKING: RefCell<Option<ParaId>> = RefCell::new(None);
+}
+
+pub fn inclusion_hook_calls() -> Vec<InclusionHookCall> {
+	INCLUSION_HOOK_CALLS.with(|r| r.borrow().clone())
+}
+
+/// Make the backing of candidates of the given para fail in
+/// [`crate::inclusion::InclusionHooks::on_candidate_backing`].
+pub fn set_failing_candidate_backing(para_id: Option<ParaId>) {
+	FAILING_CANDIDATE_BACKING.with(|r| *r.borrow_mut() = para_id);
+}
+
+fn note_hook_call(call: InclusionHookCall) {
+	INCLUSION_HOOK_CALLS.with(|r| r.borrow_mut().push(call))
+}
+
+impl crate::inclusion::InclusionHooks for Test {
+	fn on_candidate_backing(
+		para_id: ParaId,
+		_candidate_hash: CandidateHash,
+		_core: CoreIndex,
+	) -> DispatchResult {
+		if FAILING_CANDIDATE_BACKING.with(|r| *r.borrow() == Some(para_id)) {
+			return Err(DispatchError::Other("candidate backing failed"))
+		}
+		Ok(())
+	}
+
+	fn on_candidate_backed(para_id: ParaId, candidate_hash: CandidateHash, core: CoreIndex) {
+		note_hook_call(InclusionHookCall::CandidateBacked(para_id, candidate_hash, core))
+	}
+
+	fn on_candidate_included(para_id: ParaId, candidate_hash: CandidateHash, core: CoreIndex) {
+		note_hook_call(InclusionHookCall::CandidateIncluded(para_id, candidate_hash, core))
+	}
+
+	fn on_candidate_timed_out(para_id: ParaId, candidate_hash: CandidateHash, core: CoreIndex) {
+		note_hook_call(InclusionHookCall::CandidateTimedOut(para_id, candidate_hash, core))
+	}
+
+	fn on_bitfields_processed(count: u32) {
+		note_hook_call(InclusionHookCall::BitfieldsProcessed(count))
+	}
+}
+
+impl crate::scheduler::Config for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type Currency = pallet_balances::Pallet<Test>;
+	type OnBidSettled = ();
+	type OnClaimSlashed = ();
+	type OnDemandFees = ();
+	type WeightInfo = crate::scheduler::TestWeightInfo;
+}
 
 impl crate::inclusion::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
 	type DisputesHandler = Disputes;
 	type RewardValidators = TestRewardValidators;
+	type BackingEquivocationHandler = Self;
+	type AvailabilityNoShowHandler = Self;
+	type InclusionHooks = (Self, Statistics);
+	type MaxValidators = ConstU32<1_000>;
+	type MaxAvailabilityCores = ConstU32<1_000>;
+	type WeightInfo = crate::inclusion::TestWeightInfo;
 }
 
 impl crate::paras_inherent::Config for Test {
@@ -356,6 +483,10 @@ impl crate::session_info::AuthorityDiscoveryConfig for Test {
 	}
 }
 
+impl crate::statistics::Config for Test {
+	type RuntimeEvent = RuntimeEvent;
+}
+
 thread_local! {
 	pub static BACKING_REWARDS: RefCell<HashMap<ValidatorIndex, usize>>
 		= RefCell::new(HashMap::new());
@@ -438,6 +569,12 @@ pub fn new_test_ext(state: MockGenesisConfig) -> TestExternalities {
 
 	BACKING_REWARDS.with(|r| r.borrow_mut().clear());
 	AVAILABILITY_REWARDS.with(|r| r.borrow_mut().clear());
+	PUNISH_BACKING_EQUIVOCATION.with(|r| r.borrow_mut().clear());
+	PUNISH_AVAILABILITY_NO_SHOWS.with(|r| r.borrow_mut().clear());
+	INCLUSION_HOOK_CALLS.with(|r| r.borrow_mut().clear());
+	FAILING_CANDIDATE_BACKING.with(|r| *r.borrow_mut() = None);
+	NEW_SESSIONS.with(|r| r.borrow_mut().clear());
+	DISABLED_VALIDATORS.with(|r| r.borrow_mut().clear());
 
 	let mut t = state.system.build_storage::<Test>().unwrap();
 	state.configuration.assimilate_storage(&mut t).unwrap();
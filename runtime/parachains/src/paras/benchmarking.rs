@@ -185,6 +185,65 @@ benchmarks! {
 		let _ = Pallet::<T>::include_pvf_check_statement(RawOrigin::None.into(), stmt, signature);
 	}
 
+	cancel_code_upgrade {
+		let para_id = ParaId::from(1000);
+		// schedule the upgrade with pre-checking enabled so that the cancellation has to withdraw
+		// it from an active vote.
+		let config = HostConfiguration::<T::BlockNumber> {
+			pvf_checking_enabled: true,
+			..Default::default()
+		};
+		generate_disordered_upgrades::<T>();
+		Pallet::<T>::schedule_code_upgrade(
+			para_id,
+			ValidationCode(vec![0]),
+			frame_system::Pallet::<T>::block_number(),
+			&config,
+		);
+	}: _(RawOrigin::Root, para_id)
+	verify {
+		assert_last_event::<T>(Event::CodeUpgradeCanceled(para_id).into());
+	}
+
+	force_rollback_code {
+		let para_id = ParaId::from(1000);
+		let old_code = ValidationCode(vec![1]);
+		let current_code = ValidationCode(vec![2]);
+		let now = frame_system::Pallet::<T>::block_number();
+		Pallet::<T>::increase_code_ref(&old_code.hash(), &old_code);
+		Pallet::<T>::increase_code_ref(&current_code.hash(), &current_code);
+		CurrentCodeHash::<T>::insert(&para_id, current_code.hash());
+		Pallet::<T>::note_past_code(para_id, now, now, old_code.hash());
+		frame_system::Pallet::<T>::set_block_number(now + One::one());
+
+		// the rollback has to cancel an upgrade that is still pre-checked.
+		let config = HostConfiguration::<T::BlockNumber> {
+			pvf_checking_enabled: true,
+			..Default::default()
+		};
+		generate_disordered_upgrades::<T>();
+		generate_disordered_pruning::<T>();
+		Pallet::<T>::schedule_code_upgrade(
+			para_id,
+			ValidationCode(vec![0]),
+			frame_system::Pallet::<T>::block_number(),
+			&config,
+		);
+	}: _(RawOrigin::Root, para_id, old_code.hash())
+	verify {
+		assert_last_event::<T>(Event::CodeRolledBack(para_id, old_code.hash()).into());
+	}
+
+	set_para_frozen {
+		let para_id = ParaId::from(0);
+		ParaLifecycles::<T>::insert(&para_id, ParaLifecycle::Parachain);
+		// freeze other paras, so that the para is inserted at the front of a long list.
+		FrozenParas::<T>::put((1..=SAMPLE_SIZE).map(ParaId::from).collect::<Vec<_>>());
+	}: _(RawOrigin::Root, para_id, true)
+	verify {
+		assert_last_event::<T>(Event::ParaFrozen(para_id).into());
+	}
+
 	impl_benchmark_test_suite!(
 		Pallet,
 		crate::mock::new_test_ext(Default::default()),
@@ -107,7 +107,7 @@
 //! ```
 //!
 
-use crate::{configuration, initializer::SessionChangeNotification, shared};
+use crate::{configuration, ensure_parachain, initializer::SessionChangeNotification, shared};
 use bitvec::{order::Lsb0 as BitOrderLsb0, vec::BitVec};
 use frame_support::{pallet_prelude::*, traits::EstimateNextSessionRotation};
 use frame_system::pallet_prelude::*;
@@ -119,7 +119,7 @@ use primitives::{
 use scale_info::{Type, TypeInfo};
 use sp_core::RuntimeDebug;
 use sp_runtime::{
-	traits::{AppVerify, One, Saturating},
+	traits::{AppVerify, BadOrigin, One, Saturating, Zero},
 	DispatchResult, SaturatedConversion,
 };
 use sp_std::{cmp, collections::btree_set::BTreeSet, mem, prelude::*};
@@ -128,6 +128,7 @@ use sp_std::{cmp, collections::btree_set::BTreeSet, mem, prelude::*};
 use serde::{Deserialize, Serialize};
 
 pub use crate::Origin as ParachainOrigin;
+pub use primitives::vstaging::ParaLifecycle;
 
 #[cfg(feature = "runtime-benchmarks")]
 pub(crate) mod benchmarking;
@@ -139,6 +140,48 @@ pub use pallet::*;
 
 const LOG_TARGET: &str = "runtime::paras";
 
+/// The state of a code upgrade of a para that was signalled, but not yet applied.
+#[derive(Clone, Copy, Encode, Decode, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum UpgradeState<N> {
+	/// The new code is being pre-checked by the validators.
+	PreChecking,
+	/// The new code was accepted and the upgrade is scheduled.
+	Scheduled {
+		/// The relay-chain block number starting from which a candidate included for the para
+		/// applies the upgrade.
+		expected_at: N,
+		/// The relay-chain block number after which the upgrade is aborted if it was not applied
+		/// yet. `None` if the upgrade never expires.
+		expire_at: Option<N>,
+	},
+}
+
+/// A stage of removing the state of an offboarded para from the other modules, in the order the
+/// stages are run.
+#[derive(Clone, Copy, Encode, Decode, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum OffboardingCleanupStage {
+	/// The candidates of the para still pending availability.
+	PendingAvailability,
+	/// The parathread claims of the para still held by the scheduler, along with their deposits.
+	SchedulerClaims,
+	/// The HRMP channels of the para, along with its watermark and channel digests.
+	HrmpChannels,
+	/// The downward message queue of the para, along with its delivery fee factor.
+	DownwardMessages,
+}
+
+impl OffboardingCleanupStage {
+	/// The stage run after this one, if any.
+	fn next(self) -> Option<Self> {
+		match self {
+			Self::PendingAvailability => Some(Self::SchedulerClaims),
+			Self::SchedulerClaims => Some(Self::HrmpChannels),
+			Self::HrmpChannels => Some(Self::DownwardMessages),
+			Self::DownwardMessages => None,
+		}
+	}
+}
+
 // the two key times necessary to track for every code replacement.
 #[derive(Default, Encode, Decode, TypeInfo)]
 #[cfg_attr(test, derive(Debug, Clone, PartialEq))]
@@ -169,78 +212,6 @@ pub struct ParaPastCodeMeta<N> {
 	last_pruned: Option<N>,
 }
 
-/// The possible states of a para, to take into account delayed lifecycle changes.
-///
-/// If the para is in a "transition state", it is expected that the parachain is
-/// queued in the `ActionsQueue` to transition it into a stable state. Its lifecycle
-/// state will be used to determine the state transition to apply to the para.
-#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, TypeInfo)]
-pub enum ParaLifecycle {
-	/// Para is new and is onboarding as a Parathread or Parachain.
-	Onboarding,
-	/// Para is a Parathread.
-	Parathread,
-	/// Para is a Parachain.
-	Parachain,
-	/// Para is a Parathread which is upgrading to a Parachain.
-	UpgradingParathread,
-	/// Para is a Parachain which is downgrading to a Parathread.
-	DowngradingParachain,
-	/// Parathread is queued to be offboarded.
-	OffboardingParathread,
-	/// Parachain is queued to be offboarded.
-	OffboardingParachain,
-}
-
-impl ParaLifecycle {
-	/// Returns true if parachain is currently onboarding. To learn if the
-	/// parachain is onboarding as a parachain or parathread, look at the
-	/// `UpcomingGenesis` storage item.
-	pub fn is_onboarding(&self) -> bool {
-		matches!(self, ParaLifecycle::Onboarding)
-	}
-
-	/// Returns true if para is in a stable state, i.e. it is currently
-	/// a parachain or parathread, and not in any transition state.
-	pub fn is_stable(&self) -> bool {
-		matches!(self, ParaLifecycle::Parathread | ParaLifecycle::Parachain)
-	}
-
-	/// Returns true if para is currently treated as a parachain.
-	/// This also includes transitioning states, so you may want to combine
-	/// this check with `is_stable` if you specifically want `Paralifecycle::Parachain`.
-	pub fn is_parachain(&self) -> bool {
-		matches!(
-			self,
-			ParaLifecycle::Parachain |
-				ParaLifecycle::DowngradingParachain |
-				ParaLifecycle::OffboardingParachain
-		)
-	}
-
-	/// Returns true if para is currently treated as a parathread.
-	/// This also includes transitioning states, so you may want to combine
-	/// this check with `is_stable` if you specifically want `Paralifecycle::Parathread`.
-	pub fn is_parathread(&self) -> bool {
-		matches!(
-			self,
-			ParaLifecycle::Parathread |
-				ParaLifecycle::UpgradingParathread |
-				ParaLifecycle::OffboardingParathread
-		)
-	}
-
-	/// Returns true if para is currently offboarding.
-	pub fn is_offboarding(&self) -> bool {
-		matches!(self, ParaLifecycle::OffboardingParathread | ParaLifecycle::OffboardingParachain)
-	}
-
-	/// Returns true if para is in any transitionary state.
-	pub fn is_transitioning(&self) -> bool {
-		!Self::is_stable(self)
-	}
-}
-
 impl<N: Ord + Copy + PartialEq> ParaPastCodeMeta<N> {
 	// note a replacement has occurred at a given block number.
 	pub(crate) fn note_replacement(&mut self, expected_at: N, activated_at: N) {
@@ -481,6 +452,9 @@ pub trait WeightInfo {
 	fn include_pvf_check_statement_finalize_onboarding_accept() -> Weight;
 	fn include_pvf_check_statement_finalize_onboarding_reject() -> Weight;
 	fn include_pvf_check_statement() -> Weight;
+	fn cancel_code_upgrade() -> Weight;
+	fn force_rollback_code() -> Weight;
+	fn set_para_frozen() -> Weight;
 }
 
 pub struct TestWeightInfo;
@@ -522,6 +496,15 @@ impl WeightInfo for TestWeightInfo {
 		// This special value is to distinguish from the finalizing variants above in tests.
 		Weight::MAX - Weight::from_parts(1, 1)
 	}
+	fn cancel_code_upgrade() -> Weight {
+		Weight::MAX
+	}
+	fn force_rollback_code() -> Weight {
+		Weight::MAX
+	}
+	fn set_para_frozen() -> Weight {
+		Weight::MAX
+	}
 }
 
 #[frame_support::pallet]
@@ -545,6 +528,11 @@ pub mod pallet {
 	{
 		type RuntimeEvent: From<Event> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
 
+		/// The aggregated origin type must support the `parachains` origin, so that a para can
+		/// cancel its own code upgrade.
+		type RuntimeOrigin: From<<Self as frame_system::Config>::RuntimeOrigin>
+			+ Into<Result<crate::Origin, <Self as Config>::RuntimeOrigin>>;
+
 		#[pallet::constant]
 		type UnsignedPriority: Get<TransactionPriority>;
 
@@ -576,6 +564,28 @@ pub mod pallet {
 		/// The given validation code was rejected by the PVF pre-checking vote.
 		/// `code_hash` `para_id`
 		PvfCheckRejected(ValidationCodeHash, ParaId),
+		/// A para moved to a new lifecycle state. `para_id` `lifecycle`
+		ParaLifecycleChanged(ParaId, ParaLifecycle),
+		/// A para was removed, either offboarded or rejected while onboarding. `para_id`
+		ParaRemoved(ParaId),
+		/// A scheduled code upgrade has been canceled for a Para. `para_id`
+		CodeUpgradeCanceled(ParaId),
+		/// A scheduled code upgrade of a Para was aborted, since no candidate applied it in time.
+		/// `para_id`
+		CodeUpgradeExpired(ParaId),
+		/// The code of a Para has been rolled back to a past code by governance.
+		/// `para_id` `code_hash`
+		CodeRolledBack(ParaId, ValidationCodeHash),
+		/// A para was frozen by governance and will not be scheduled or have candidates backed
+		/// until unfrozen. `para_id`
+		ParaFrozen(ParaId),
+		/// A para frozen by governance was unfrozen. `para_id`
+		ParaUnfrozen(ParaId),
+		/// A stage of removing the state of an offboarded para from the other modules is done.
+		/// `para_id` `stage`
+		OffboardingCleanupProgressed(ParaId, OffboardingCleanupStage),
+		/// All the state of an offboarded para was removed from the other modules. `para_id`
+		OffboardingCleanupCompleted(ParaId),
 	}
 
 	#[pallet::error]
@@ -607,6 +617,12 @@ pub mod pallet {
 		PvfCheckDisabled,
 		/// Parachain cannot currently schedule a code upgrade.
 		CannotUpgradeCode,
+		/// There is no code upgrade scheduled for the para.
+		NoUpgradeScheduled,
+		/// The code to roll back to is not among the past code retained for the para.
+		CodeNotRetained,
+		/// The code to roll back to is already the current code of the para.
+		CodeAlreadyCurrent,
 	}
 
 	/// All currently active PVF pre-checking votes.
@@ -643,6 +659,19 @@ pub mod pallet {
 	#[pallet::getter(fn para_head)]
 	pub(super) type Heads<T: Config> = StorageMap<_, Twox64Concat, ParaId, HeadData>;
 
+	/// Past head-data of paras, by para and the relay-chain block at which it was noted.
+	///
+	/// Kept for `head_data_retention_period` blocks.
+	#[pallet::storage]
+	pub(super) type PastHeads<T: Config> =
+		StorageMap<_, Twox64Concat, (ParaId, T::BlockNumber), HeadData>;
+
+	/// The keys of [`PastHeads`], in the order they need to be pruned. Ordered ascending by block
+	/// number.
+	#[pallet::storage]
+	pub(super) type PastHeadsPruning<T: Config> =
+		StorageValue<_, Vec<(ParaId, T::BlockNumber)>, ValueQuery>;
+
 	/// The validation code hash of every live para.
 	///
 	/// Corresponding code can be retrieved with [`CodeByHash`].
@@ -733,6 +762,45 @@ pub mod pallet {
 	pub(super) type UpcomingUpgrades<T: Config> =
 		StorageValue<_, Vec<(ParaId, T::BlockNumber)>, ValueQuery>;
 
+	/// The state of the code upgrade of a para, if there is one that was not applied yet.
+	#[pallet::storage]
+	#[pallet::getter(fn upgrade_state)]
+	pub(super) type UpgradeStates<T: Config> =
+		StorageMap<_, Twox64Concat, ParaId, UpgradeState<T::BlockNumber>>;
+
+	/// The list of scheduled code upgrades that expire. Each item is a pair of which para performs
+	/// a code upgrade and after which relay-chain block it is aborted if not applied.
+	///
+	/// Ordered ascending by block number.
+	#[pallet::storage]
+	pub(super) type UpgradeExpiries<T: Config> =
+		StorageValue<_, Vec<(ParaId, T::BlockNumber)>, ValueQuery>;
+
+	/// The paras whose code was rolled back by governance in the current block, along with the
+	/// hash of the code they were rolled back from.
+	///
+	/// Taken by the inclusion pallet at the start of the next block, so that candidates built
+	/// against the rolled back code are not enacted.
+	#[pallet::storage]
+	pub(super) type CodeRollbacks<T: Config> =
+		StorageValue<_, Vec<(ParaId, ValidationCodeHash)>, ValueQuery>;
+
+	/// The paras frozen by governance, which are neither scheduled nor allowed to have candidates
+	/// backed.
+	///
+	/// Ordered ascending by para ID.
+	#[pallet::storage]
+	#[pallet::getter(fn frozen_paras)]
+	pub(super) type FrozenParas<T: Config> = StorageValue<_, Vec<ParaId>, ValueQuery>;
+
+	/// The paras offboarded at a session change whose state is still being removed from the other
+	/// modules, along with the next stage of the removal. Oldest first.
+	///
+	/// The stages are run by the initializer over the following blocks.
+	#[pallet::storage]
+	pub(super) type OffboardingCleanup<T: Config> =
+		StorageValue<_, Vec<(ParaId, OffboardingCleanupStage)>, ValueQuery>;
+
 	/// The actions to perform during the start of a specific session index.
 	#[pallet::storage]
 	#[pallet::getter(fn actions_queue)]
@@ -831,6 +899,8 @@ pub mod pallet {
 		}
 
 		/// Schedule an upgrade as if it was scheduled in the given relay parent block.
+		///
+		/// If the para already has an upgrade scheduled that was not applied yet, it is replaced.
 		#[pallet::call_index(2)]
 		#[pallet::weight(<T as Config>::WeightInfo::force_schedule_code_upgrade(new_code.0.len() as u32))]
 		pub fn force_schedule_code_upgrade(
@@ -841,6 +911,7 @@ pub mod pallet {
 		) -> DispatchResult {
 			ensure_root(origin)?;
 			let config = configuration::Pallet::<T>::config();
+			Self::cancel_scheduled_upgrade(para);
 			Self::schedule_code_upgrade(para, new_code, relay_parent_number, &config);
 			Self::deposit_event(Event::CodeUpgradeScheduled(para));
 			Ok(())
@@ -1050,6 +1121,87 @@ pub mod pallet {
 				Ok(Some(<T as Config>::WeightInfo::include_pvf_check_statement()).into())
 			}
 		}
+
+		/// Cancel the code upgrade scheduled for a para that was not applied yet.
+		///
+		/// Any PVF pre-checking started on behalf of the upgrade is abandoned and the upgrade
+		/// restriction is lifted, so that the para can signal a new upgrade right away.
+		///
+		/// Can be called by Root or by the para itself.
+		#[pallet::call_index(8)]
+		#[pallet::weight(<T as Config>::WeightInfo::cancel_code_upgrade())]
+		pub fn cancel_code_upgrade(origin: OriginFor<T>, para: ParaId) -> DispatchResult {
+			Self::ensure_root_or_para(origin, para)?;
+			ensure!(FutureCodeHash::<T>::contains_key(&para), Error::<T>::NoUpgradeScheduled);
+			Self::cancel_scheduled_upgrade(para);
+			Self::deposit_event(Event::CodeUpgradeCanceled(para));
+			Ok(())
+		}
+
+		/// Revert the code of a para to a past code that is still retained, immediately.
+		///
+		/// Any code upgrade scheduled for the para is canceled. The code being rolled back
+		/// becomes past code, and candidates pending availability that were built against it
+		/// are dropped at the start of the next block.
+		#[pallet::call_index(9)]
+		#[pallet::weight(<T as Config>::WeightInfo::force_rollback_code())]
+		pub fn force_rollback_code(
+			origin: OriginFor<T>,
+			para: ParaId,
+			to_code_hash: ValidationCodeHash,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			let current_code_hash =
+				CurrentCodeHash::<T>::get(&para).ok_or(Error::<T>::NotRegistered)?;
+			ensure!(current_code_hash != to_code_hash, Error::<T>::CodeAlreadyCurrent);
+			ensure!(Self::retains_past_code(para, &to_code_hash), Error::<T>::CodeNotRetained);
+			let code = CodeByHash::<T>::get(&to_code_hash).ok_or(Error::<T>::CodeNotRetained)?;
+
+			Self::cancel_scheduled_upgrade(para);
+
+			// The past code keeps its own reference until it is pruned.
+			Self::increase_code_ref(&to_code_hash, &code);
+			CurrentCodeHash::<T>::insert(&para, to_code_hash);
+
+			let now = frame_system::Pallet::<T>::block_number();
+			Self::note_past_code(para, now, now, current_code_hash);
+			CodeRollbacks::<T>::append((para, current_code_hash));
+
+			Self::deposit_event(Event::CodeRolledBack(para, to_code_hash));
+			Ok(())
+		}
+
+		/// Freeze or unfreeze a para in an emergency.
+		///
+		/// A frozen para is not scheduled and candidates for it are not backed, while the
+		/// candidates already pending availability are left untouched. Only paras known to the
+		/// system can be frozen, but any para can be unfrozen.
+		#[pallet::call_index(10)]
+		#[pallet::weight(<T as Config>::WeightInfo::set_para_frozen())]
+		pub fn set_para_frozen(origin: OriginFor<T>, para: ParaId, frozen: bool) -> DispatchResult {
+			ensure_root(origin)?;
+			if frozen {
+				ensure!(ParaLifecycles::<T>::contains_key(&para), Error::<T>::NotRegistered);
+			}
+
+			let changed = FrozenParas::<T>::mutate(|frozen_paras| {
+				match (frozen_paras.binary_search(&para), frozen) {
+					(Err(i), true) => frozen_paras.insert(i, para),
+					(Ok(i), false) => {
+						frozen_paras.remove(i);
+					},
+					_ => return false,
+				}
+				true
+			});
+
+			if changed {
+				let event =
+					if frozen { Event::ParaFrozen(para) } else { Event::ParaUnfrozen(para) };
+				Self::deposit_event(event);
+			}
+			Ok(())
+		}
 	}
 
 	#[pallet::validate_unsigned]
@@ -1163,6 +1315,7 @@ impl<T: Config> Pallet<T> {
 	/// Called by the initializer to finalize the paras pallet.
 	pub(crate) fn initializer_finalize(now: T::BlockNumber) {
 		Self::process_scheduled_upgrade_cooldowns(now);
+		Self::prune_old_heads(now);
 	}
 
 	/// Called by the initializer to note that a new session has started.
@@ -1176,6 +1329,11 @@ impl<T: Config> Pallet<T> {
 		outgoing_paras
 	}
 
+	/// The storage key of the current validation code hash of the given para.
+	pub(crate) fn current_code_hash_key(para_id: ParaId) -> Vec<u8> {
+		CurrentCodeHash::<T>::hashed_key_for(para_id)
+	}
+
 	/// The validation code of live para.
 	pub(crate) fn current_code(para_id: &ParaId) -> Option<ValidationCode> {
 		Self::current_code_hash(para_id).and_then(|code_hash| {
@@ -1218,12 +1376,12 @@ impl<T: Config> Pallet<T> {
 				// Upgrade a parathread to a parachain
 				Some(ParaLifecycle::UpgradingParathread) => {
 					parachains.add(para);
-					ParaLifecycles::<T>::insert(&para, ParaLifecycle::Parachain);
+					Self::set_lifecycle(para, ParaLifecycle::Parachain);
 				},
 				// Downgrade a parachain to a parathread
 				Some(ParaLifecycle::DowngradingParachain) => {
 					parachains.remove(para);
-					ParaLifecycles::<T>::insert(&para, ParaLifecycle::Parathread);
+					Self::set_lifecycle(para, ParaLifecycle::Parathread);
 				},
 				// Offboard a parathread or parachain from the system
 				Some(ParaLifecycle::OffboardingParachain) |
@@ -1232,9 +1390,10 @@ impl<T: Config> Pallet<T> {
 
 					Heads::<T>::remove(&para);
 					FutureCodeUpgrades::<T>::remove(&para);
+					UpgradeStates::<T>::remove(&para);
 					UpgradeGoAheadSignal::<T>::remove(&para);
 					UpgradeRestrictionSignal::<T>::remove(&para);
-					ParaLifecycles::<T>::remove(&para);
+					Self::remove_lifecycle(para);
 					let removed_future_code_hash = FutureCodeHash::<T>::take(&para);
 					if let Some(removed_future_code_hash) = removed_future_code_hash {
 						Self::decrease_code_ref(&removed_future_code_hash);
@@ -1251,7 +1410,8 @@ impl<T: Config> Pallet<T> {
 		}
 
 		if !outgoing.is_empty() {
-			// Filter offboarded parachains from the upcoming upgrades and upgrade cooldowns list.
+			// Filter offboarded parachains from the upcoming upgrades, upgrade expiries and upgrade
+			// cooldowns list.
 			//
 			// We do it after the offboarding to get away with only a single read/write per list.
 			//
@@ -1263,12 +1423,25 @@ impl<T: Config> Pallet<T> {
 					.filter(|(para, _)| !outgoing.contains(para))
 					.collect();
 			});
+			UpgradeExpiries::<T>::mutate(|upgrade_expiries| {
+				*upgrade_expiries = mem::take(upgrade_expiries)
+					.into_iter()
+					.filter(|(para, _)| !outgoing.contains(para))
+					.collect();
+			});
 			UpgradeCooldowns::<T>::mutate(|upgrade_cooldowns| {
 				*upgrade_cooldowns = mem::take(upgrade_cooldowns)
 					.into_iter()
 					.filter(|(para, _)| !outgoing.contains(para))
 					.collect();
 			});
+			FrozenParas::<T>::mutate(|frozen_paras| {
+				frozen_paras.retain(|para| !outgoing.contains(para));
+			});
+			let first_stage = OffboardingCleanupStage::PendingAvailability;
+			OffboardingCleanup::<T>::mutate(|cleanup| {
+				cleanup.extend(outgoing.iter().map(|para| (*para, first_stage)));
+			});
 		}
 
 		// Persist parachains into the storage explicitly.
@@ -1277,6 +1450,32 @@ impl<T: Config> Pallet<T> {
 		return outgoing
 	}
 
+	/// The offboarded para whose state is removed next from the other modules, and the stage of the
+	/// removal to run, if any.
+	pub(crate) fn next_offboarding_cleanup() -> Option<(ParaId, OffboardingCleanupStage)> {
+		OffboardingCleanup::<T>::get().first().copied()
+	}
+
+	/// Note that the given stage of removing the state of the given offboarded para is done,
+	/// moving on to the next stage or to the next para.
+	pub(crate) fn note_offboarding_cleanup_progress(para: ParaId, stage: OffboardingCleanupStage) {
+		OffboardingCleanup::<T>::mutate(|cleanup| {
+			let position = match cleanup.iter().position(|entry| *entry == (para, stage)) {
+				Some(position) => position,
+				None => return,
+			};
+
+			Self::deposit_event(Event::OffboardingCleanupProgressed(para, stage));
+			match stage.next() {
+				Some(next) => cleanup[position].1 = next,
+				None => {
+					cleanup.remove(position);
+					Self::deposit_event(Event::OffboardingCleanupCompleted(para));
+				},
+			}
+		});
+	}
+
 	// note replacement of the code of para with given `id`, which occured in the
 	// context of the given relay-chain block number. provide the replaced code.
 	//
@@ -1363,8 +1562,8 @@ impl<T: Config> Pallet<T> {
 		T::DbWeight::get().reads_writes(1 + pruning_tasks_done, 2 * pruning_tasks_done)
 	}
 
-	/// Process the timers related to upgrades. Specifically, the upgrade go ahead signals toggle
-	/// and the upgrade cooldown restrictions. However, this function does not actually unset
+	/// Process the timers related to upgrades. Specifically, the upgrade go ahead signals toggle,
+	/// the expiry of upgrades that were not applied in time and the upgrade cooldown restrictions. However, this function does not actually unset
 	/// the upgrade restriction, that will happen in the `initializer_finalize` function. However,
 	/// this function does count the number of cooldown timers expired so that we can reserve weight
 	/// for the `initializer_finalize` function.
@@ -1382,6 +1581,19 @@ impl<T: Config> Pallet<T> {
 		);
 		weight += T::DbWeight::get().writes(upgrades_signaled as u64);
 
+		// account weight for `UpgradeExpiries::mutate`.
+		//
+		// An upgrade can still be applied by a candidate included in the block it expires at.
+		weight += T::DbWeight::get().reads_writes(1, 1);
+		let expired = UpgradeExpiries::<T>::mutate(|upgrade_expiries| {
+			let num = upgrade_expiries.iter().take_while(|&(_, at)| at < &now).count();
+			upgrade_expiries.drain(..num).map(|(para, _)| para).collect::<Vec<_>>()
+		});
+		for para in expired {
+			weight += Self::cancel_scheduled_upgrade(para);
+			Self::deposit_event(Event::CodeUpgradeExpired(para));
+		}
+
 		// account weight for `UpgradeCooldowns::get`.
 		weight += T::DbWeight::get().reads(1);
 		let cooldowns_expired =
@@ -1401,7 +1613,9 @@ impl<T: Config> Pallet<T> {
 	/// See `process_scheduled_upgrade_changes` for more details.
 	fn process_scheduled_upgrade_cooldowns(now: T::BlockNumber) {
 		UpgradeCooldowns::<T>::mutate(|upgrade_cooldowns: &mut Vec<(ParaId, T::BlockNumber)>| {
-			for &(para, _) in upgrade_cooldowns.iter().take_while(|&(_, at)| at <= &now) {
+			// Remove all expired signals and also prune the cooldowns.
+			let expired = upgrade_cooldowns.iter().take_while(|&(_, at)| at <= &now).count();
+			for (para, _) in upgrade_cooldowns.drain(..expired) {
 				UpgradeRestrictionSignal::<T>::remove(&para);
 			}
 		});
@@ -1533,6 +1747,22 @@ impl<T: Config> Pallet<T> {
 		weight += T::DbWeight::get().reads_writes(1, 4);
 		FutureCodeUpgrades::<T>::insert(&id, expected_at);
 
+		// The para has to get a candidate applying the upgrade included within
+		// `validation_upgrade_expiry` blocks after `expected_at`, if expiry is enabled.
+		let expire_at = (!cfg.validation_upgrade_expiry.is_zero())
+			.then(|| expected_at + cfg.validation_upgrade_expiry);
+		weight += T::DbWeight::get().writes(1);
+		UpgradeStates::<T>::insert(&id, UpgradeState::Scheduled { expected_at, expire_at });
+		if let Some(expire_at) = expire_at {
+			weight += T::DbWeight::get().reads_writes(1, 1);
+			UpgradeExpiries::<T>::mutate(|upgrade_expiries| {
+				let insert_idx = upgrade_expiries
+					.binary_search_by_key(&expire_at, |&(_, b)| b)
+					.unwrap_or_else(|idx| idx);
+				upgrade_expiries.insert(insert_idx, (id, expire_at));
+			});
+		}
+
 		UpcomingUpgrades::<T>::mutate(|upcoming_upgrades| {
 			let insert_idx = upcoming_upgrades
 				.binary_search_by_key(&expected_at, |&(_, b)| b)
@@ -1570,12 +1800,13 @@ impl<T: Config> Pallet<T> {
 					weight += T::DbWeight::get().writes(3);
 					UpcomingParasGenesis::<T>::remove(&id);
 					CurrentCodeHash::<T>::remove(&id);
-					ParaLifecycles::<T>::remove(&id);
+					Self::remove_lifecycle(id);
 				},
 				PvfCheckCause::Upgrade { id, .. } => {
-					weight += T::DbWeight::get().writes(2);
+					weight += T::DbWeight::get().writes(3);
 					UpgradeGoAheadSignal::<T>::insert(&id, UpgradeGoAhead::Abort);
 					FutureCodeHash::<T>::remove(&id);
+					UpgradeStates::<T>::remove(&id);
 				},
 			}
 		}
@@ -1608,7 +1839,11 @@ impl<T: Config> Pallet<T> {
 		// valid.
 		ensure!(Self::can_schedule_para_initialize(&id), Error::<T>::CannotOnboard);
 		ensure!(!genesis_data.validation_code.0.is_empty(), Error::<T>::CannotOnboard);
-		ParaLifecycles::<T>::insert(&id, ParaLifecycle::Onboarding);
+		Self::set_lifecycle(id, ParaLifecycle::Onboarding);
+
+		// The state left behind by a previous offboarding of the para is no longer removed, as it
+		// would be removed from the para being onboarded.
+		OffboardingCleanup::<T>::mutate(|cleanup| cleanup.retain(|(para, _)| *para != id));
 
 		// HACK: here we are doing something nasty.
 		//
@@ -1691,10 +1926,10 @@ impl<T: Config> Pallet<T> {
 			// If para is not registered, nothing to do!
 			None => return Ok(()),
 			Some(ParaLifecycle::Parathread) => {
-				ParaLifecycles::<T>::insert(&id, ParaLifecycle::OffboardingParathread);
+				Self::set_lifecycle(id, ParaLifecycle::OffboardingParathread);
 			},
 			Some(ParaLifecycle::Parachain) => {
-				ParaLifecycles::<T>::insert(&id, ParaLifecycle::OffboardingParachain);
+				Self::set_lifecycle(id, ParaLifecycle::OffboardingParachain);
 			},
 			_ => return Err(Error::<T>::CannotOffboard)?,
 		}
@@ -1718,7 +1953,7 @@ impl<T: Config> Pallet<T> {
 
 		ensure!(lifecycle == ParaLifecycle::Parathread, Error::<T>::CannotUpgrade);
 
-		ParaLifecycles::<T>::insert(&id, ParaLifecycle::UpgradingParathread);
+		Self::set_lifecycle(id, ParaLifecycle::UpgradingParathread);
 		ActionsQueue::<T>::mutate(scheduled_session, |v| {
 			if let Err(i) = v.binary_search(&id) {
 				v.insert(i, id);
@@ -1737,7 +1972,7 @@ impl<T: Config> Pallet<T> {
 
 		ensure!(lifecycle == ParaLifecycle::Parachain, Error::<T>::CannotDowngrade);
 
-		ParaLifecycles::<T>::insert(&id, ParaLifecycle::DowngradingParachain);
+		Self::set_lifecycle(id, ParaLifecycle::DowngradingParachain);
 		ActionsQueue::<T>::mutate(scheduled_session, |v| {
 			if let Err(i) = v.binary_search(&id) {
 				v.insert(i, id);
@@ -1802,12 +2037,17 @@ impl<T: Config> Pallet<T> {
 		}
 
 		// This is the start of the upgrade process. Prevent any further attempts at upgrading.
-		weight += T::DbWeight::get().writes(2);
+		//
+		// The para may still have an `Abort` signal pending from a canceled upgrade, which does
+		// not apply to this one.
+		weight += T::DbWeight::get().writes(4);
 		FutureCodeHash::<T>::insert(&id, &code_hash);
+		UpgradeStates::<T>::insert(&id, UpgradeState::PreChecking);
 		UpgradeRestrictionSignal::<T>::insert(&id, UpgradeRestriction::Present);
+		UpgradeGoAheadSignal::<T>::remove(&id);
 
 		weight += T::DbWeight::get().reads_writes(1, 1);
-		let next_possible_upgrade_at = relay_parent_number + cfg.validation_upgrade_cooldown;
+		let next_possible_upgrade_at = relay_parent_number + cfg.validation_upgrade_cooldown_of(id);
 		UpgradeCooldowns::<T>::mutate(|upgrade_cooldowns| {
 			let insert_idx = upgrade_cooldowns
 				.binary_search_by_key(&next_possible_upgrade_at, |&(_, b)| b)
@@ -1825,6 +2065,78 @@ impl<T: Config> Pallet<T> {
 		weight
 	}
 
+	/// Cancel the upgrade scheduled for the given para, undoing everything done by
+	/// `schedule_code_upgrade` and, if pre-checking already concluded, by `enact_pvf_accepted`.
+	///
+	/// Noop if there is no upgrade scheduled for the para.
+	fn cancel_scheduled_upgrade(id: ParaId) -> Weight {
+		let mut weight = T::DbWeight::get().reads_writes(1, 1);
+		let code_hash = match FutureCodeHash::<T>::take(&id) {
+			Some(code_hash) => code_hash,
+			None => return weight,
+		};
+
+		// The upgrade may still be pre-checked. Withdraw it from the vote and drop the vote
+		// altogether if no other para is interested in its outcome.
+		weight += T::DbWeight::get().reads(1);
+		if let Some(mut vote_state) = PvfActiveVoteMap::<T>::get(&code_hash) {
+			vote_state.causes.retain(|cause| match cause {
+				PvfCheckCause::Upgrade { id: cause_id, .. } => *cause_id != id,
+				PvfCheckCause::Onboarding(_) => true,
+			});
+
+			weight += T::DbWeight::get().writes(1);
+			if vote_state.causes.is_empty() {
+				PvfActiveVoteMap::<T>::remove(&code_hash);
+				weight += T::DbWeight::get().reads_writes(1, 1);
+				PvfActiveVoteList::<T>::mutate(|l| {
+					if let Ok(i) = l.binary_search(&code_hash) {
+						l.remove(i);
+					}
+				});
+			} else {
+				PvfActiveVoteMap::<T>::insert(&code_hash, vote_state);
+			}
+		}
+
+		// The RC was bumped when the pre-checking was kicked off on behalf of this upgrade.
+		weight += Self::decrease_code_ref(&code_hash);
+
+		// Forget the upgrade in case it was already accepted and let the para know it won't be
+		// enacted.
+		weight += T::DbWeight::get().reads_writes(2, 5);
+		FutureCodeUpgrades::<T>::remove(&id);
+		UpgradeStates::<T>::remove(&id);
+		UpcomingUpgrades::<T>::mutate(|upcoming_upgrades| {
+			upcoming_upgrades.retain(|&(para, _)| para != id);
+		});
+		UpgradeExpiries::<T>::mutate(|upgrade_expiries| {
+			upgrade_expiries.retain(|&(para, _)| para != id);
+		});
+		UpgradeGoAheadSignal::<T>::insert(&id, UpgradeGoAhead::Abort);
+
+		// Lift the restriction so that the para doesn't have to wait out the cooldown before
+		// signalling a new upgrade.
+		weight += T::DbWeight::get().reads_writes(1, 2);
+		UpgradeRestrictionSignal::<T>::remove(&id);
+		UpgradeCooldowns::<T>::mutate(|upgrade_cooldowns| {
+			upgrade_cooldowns.retain(|&(para, _)| para != id);
+		});
+
+		weight
+	}
+
+	/// Ensure the origin is one of Root or the `para` itself.
+	fn ensure_root_or_para(origin: OriginFor<T>, id: ParaId) -> DispatchResult {
+		if let Ok(caller_id) = ensure_parachain(<T as Config>::RuntimeOrigin::from(origin.clone()))
+		{
+			ensure!(caller_id == id, BadOrigin);
+		} else {
+			ensure_root(origin)?;
+		}
+		Ok(())
+	}
+
 	/// Makes sure that the given code hash has passed pre-checking.
 	///
 	/// If the given code hash has already passed pre-checking, then the approval happens
@@ -1918,6 +2230,52 @@ impl<T: Config> Pallet<T> {
 		new_head: HeadData,
 		execution_context: T::BlockNumber,
 	) -> Weight {
+		let weight = Self::note_past_head(id, &new_head);
+		weight + Self::apply_new_head(id, new_head, execution_context)
+	}
+
+	/// Keep the given head of a para for `head_data_retention_period` blocks, if that is non-zero.
+	fn note_past_head(id: ParaId, head: &HeadData) -> Weight {
+		if configuration::Pallet::<T>::config().head_data_retention_period.is_zero() {
+			return T::DbWeight::get().reads(1)
+		}
+
+		let now = <frame_system::Pallet<T>>::block_number();
+		PastHeads::<T>::insert(&(id, now), head);
+		PastHeadsPruning::<T>::mutate(|pruning| {
+			if pruning.last() != Some(&(id, now)) {
+				pruning.push((id, now));
+			}
+		});
+
+		T::DbWeight::get().reads_writes(2, 2)
+	}
+
+	/// The heads of a para noted within the last `head_data_retention_period` blocks, together with
+	/// the block they were noted in, ascending by block number.
+	pub(crate) fn past_heads(id: ParaId) -> Vec<(T::BlockNumber, HeadData)> {
+		PastHeadsPruning::<T>::get()
+			.into_iter()
+			.filter(|(para_id, _)| *para_id == id)
+			.filter_map(|(_, at)| PastHeads::<T>::get(&(id, at)).map(|head| (at, head)))
+			.collect()
+	}
+
+	/// Discard the past heads that were noted `head_data_retention_period` or more blocks ago.
+	fn prune_old_heads(now: T::BlockNumber) {
+		let retention_period = configuration::Pallet::<T>::config().head_data_retention_period;
+		let pruning_height = now.saturating_sub(retention_period);
+
+		PastHeadsPruning::<T>::mutate(|pruning| {
+			let up_to_idx = pruning.iter().take_while(|&(_, at)| at <= &pruning_height).count();
+			for (para_id, at) in pruning.drain(..up_to_idx) {
+				PastHeads::<T>::remove(&(para_id, at));
+			}
+		});
+	}
+
+	/// Store the new head of a para and apply any pending code upgrade it enacts.
+	fn apply_new_head(id: ParaId, new_head: HeadData, execution_context: T::BlockNumber) -> Weight {
 		Heads::<T>::insert(&id, new_head);
 
 		if let Some(expected_at) = FutureCodeUpgrades::<T>::get(&id) {
@@ -1925,12 +2283,23 @@ impl<T: Config> Pallet<T> {
 				FutureCodeUpgrades::<T>::remove(&id);
 				UpgradeGoAheadSignal::<T>::remove(&id);
 
+				// The upgrade is applied, so it can no longer expire.
+				let mut weight = T::DbWeight::get().reads_writes(1, 1);
+				if let Some(UpgradeState::Scheduled { expire_at: Some(_), .. }) =
+					UpgradeStates::<T>::take(&id)
+				{
+					weight += T::DbWeight::get().reads_writes(1, 1);
+					UpgradeExpiries::<T>::mutate(|upgrade_expiries| {
+						upgrade_expiries.retain(|&(para, _)| para != id);
+					});
+				}
+
 				// Both should always be `Some` in this case, since a code upgrade is scheduled.
 				let new_code_hash = if let Some(new_code_hash) = FutureCodeHash::<T>::take(&id) {
 					new_code_hash
 				} else {
 					log::error!(target: LOG_TARGET, "Missing future code hash for {:?}", &id);
-					return T::DbWeight::get().reads_writes(3, 1 + 3)
+					return weight + T::DbWeight::get().reads_writes(3, 1 + 3)
 				};
 				let maybe_prior_code_hash = CurrentCodeHash::<T>::get(&id);
 				CurrentCodeHash::<T>::insert(&id, &new_code_hash);
@@ -1941,12 +2310,11 @@ impl<T: Config> Pallet<T> {
 				// `now` is only used for registering pruning as part of `fn note_past_code`
 				let now = <frame_system::Pallet<T>>::block_number();
 
-				let weight = if let Some(prior_code_hash) = maybe_prior_code_hash {
-					Self::note_past_code(id, expected_at, now, prior_code_hash)
+				if let Some(prior_code_hash) = maybe_prior_code_hash {
+					weight += Self::note_past_code(id, expected_at, now, prior_code_hash);
 				} else {
 					log::error!(target: LOG_TARGET, "Missing prior code hash for para {:?}", &id);
-					Weight::zero()
-				};
+				}
 
 				// add 1 to writes due to heads update.
 				weight + T::DbWeight::get().reads_writes(3, 1 + 3)
@@ -2032,6 +2400,24 @@ impl<T: Config> Pallet<T> {
 		FutureCodeHash::<T>::get(&id).is_none() && UpgradeRestrictionSignal::<T>::get(&id).is_none()
 	}
 
+	/// Whether the given code hash is among the past code of the para that was not pruned yet.
+	fn retains_past_code(id: ParaId, code_hash: &ValidationCodeHash) -> bool {
+		PastCodeMeta::<T>::get(&id).upgrade_times.iter().any(|times| {
+			PastCodeHash::<T>::get(&(id, times.expected_at)).as_ref() == Some(code_hash)
+		})
+	}
+
+	/// Take the paras whose code was rolled back by governance since the last call, along with
+	/// the hash of the code they were rolled back from.
+	pub(crate) fn take_code_rollbacks() -> Vec<(ParaId, ValidationCodeHash)> {
+		CodeRollbacks::<T>::take()
+	}
+
+	/// Whether the para was frozen by governance.
+	pub(crate) fn is_frozen(id: &ParaId) -> bool {
+		FrozenParas::<T>::get().binary_search(id).is_ok()
+	}
+
 	/// Return the session index that should be used for any future scheduled changes.
 	fn scheduled_session() -> SessionIndex {
 		shared::Pallet::<T>::scheduled_session()
@@ -2088,6 +2474,18 @@ impl<T: Config> Pallet<T> {
 		Heads::<T>::insert(para_id, head_data);
 	}
 
+	/// Move a para to the given lifecycle state.
+	fn set_lifecycle(id: ParaId, lifecycle: ParaLifecycle) {
+		ParaLifecycles::<T>::insert(&id, lifecycle.clone());
+		Self::deposit_event(Event::ParaLifecycleChanged(id, lifecycle));
+	}
+
+	/// Forget the lifecycle of a para that is no longer part of the system.
+	fn remove_lifecycle(id: ParaId) {
+		ParaLifecycles::<T>::remove(&id);
+		Self::deposit_event(Event::ParaRemoved(id));
+	}
+
 	/// A low-level function to eagerly initialize a given para.
 	pub(crate) fn initialize_para_now(
 		parachains: &mut ParachainsCache<T>,
@@ -2097,9 +2495,9 @@ impl<T: Config> Pallet<T> {
 		match genesis_data.para_kind {
 			ParaKind::Parachain => {
 				parachains.add(id);
-				ParaLifecycles::<T>::insert(&id, ParaLifecycle::Parachain);
+				Self::set_lifecycle(id, ParaLifecycle::Parachain);
 			},
-			ParaKind::Parathread => ParaLifecycles::<T>::insert(&id, ParaLifecycle::Parathread),
+			ParaKind::Parathread => Self::set_lifecycle(id, ParaLifecycle::Parathread),
 		}
 
 		// HACK: see the notice in `schedule_para_initialize`.
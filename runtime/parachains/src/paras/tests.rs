@@ -26,8 +26,8 @@ use test_helpers::{dummy_head_data, dummy_validation_code};
 use crate::{
 	configuration::HostConfiguration,
 	mock::{
-		new_test_ext, Configuration, MockGenesisConfig, Paras, ParasShared, RuntimeOrigin, System,
-		Test,
+		new_test_ext, Configuration, MockGenesisConfig, Paras, ParasShared, RuntimeEvent,
+		RuntimeOrigin, System, Test,
 	},
 };
 
@@ -150,6 +150,24 @@ impl EventValidator {
 		self
 	}
 
+	fn lifecycle_changed(&mut self, id: ParaId, lifecycle: ParaLifecycle) -> &mut Self {
+		self.events.push(frame_system::EventRecord {
+			phase: frame_system::Phase::Initialization,
+			event: Event::ParaLifecycleChanged(id, lifecycle).into(),
+			topics: vec![],
+		});
+		self
+	}
+
+	fn removed(&mut self, id: ParaId) -> &mut Self {
+		self.events.push(frame_system::EventRecord {
+			phase: frame_system::Phase::Initialization,
+			event: Event::ParaRemoved(id).into(),
+			topics: vec![],
+		});
+		self
+	}
+
 	fn check(&self) {
 		assert_eq!(&frame_system::Pallet::<Test>::events(), &self.events);
 	}
@@ -333,6 +351,47 @@ fn note_new_head_sets_head() {
 	});
 }
 
+#[test]
+fn past_heads_are_kept_for_the_retention_period() {
+	let paras = vec![(
+		0u32.into(),
+		ParaGenesisArgs {
+			para_kind: ParaKind::Parachain,
+			genesis_head: dummy_head_data(),
+			validation_code: dummy_validation_code(),
+		},
+	)];
+
+	let genesis_config = MockGenesisConfig {
+		paras: GenesisConfig { paras, ..Default::default() },
+		configuration: crate::configuration::GenesisConfig {
+			config: HostConfiguration { head_data_retention_period: 2, ..Default::default() },
+			..Default::default()
+		},
+		..Default::default()
+	};
+
+	new_test_ext(genesis_config).execute_with(|| {
+		let id_a = ParaId::from(0u32);
+
+		run_to_block(1, None);
+		Paras::note_new_head(id_a, vec![1].into(), 0);
+		run_to_block(2, None);
+		Paras::note_new_head(id_a, vec![2].into(), 1);
+		assert_eq!(Paras::past_heads(id_a), vec![(1, vec![1].into()), (2, vec![2].into())]);
+
+		// the head noted at block 1 is pruned at the end of block 3.
+		run_to_block(4, None);
+		assert_eq!(Paras::past_heads(id_a), vec![(2, vec![2].into())]);
+		assert!(PastHeads::<Test>::get(&(id_a, 1)).is_none());
+
+		run_to_block(5, None);
+		assert!(Paras::past_heads(id_a).is_empty());
+		assert!(PastHeadsPruning::<Test>::get().is_empty());
+		assert_eq!(Paras::para_head(&id_a), Some(vec![2].into()));
+	});
+}
+
 #[test]
 fn note_past_code_sets_up_pruning_correctly() {
 	let code_retention_period = 10;
@@ -672,6 +731,8 @@ fn upgrade_restriction_elapsed_doesnt_mean_can_upgrade() {
 
 		run_to_block(31, None);
 		assert!(UpgradeRestrictionSignal::<Test>::get(&para_id).is_none());
+		// The expired cooldown is pruned so that it cannot lift a future restriction early.
+		assert!(UpgradeCooldowns::<Test>::get().is_empty());
 
 		// Note the para still cannot upgrade the validation code.
 		assert!(!Paras::can_upgrade_validation_code(para_id));
@@ -682,6 +743,333 @@ fn upgrade_restriction_elapsed_doesnt_mean_can_upgrade() {
 	});
 }
 
+#[test]
+fn cancel_code_upgrade_lifts_upgrade_restriction() {
+	let validation_upgrade_delay = 7;
+	let validation_upgrade_cooldown = 30;
+
+	let paras = vec![(
+		0u32.into(),
+		ParaGenesisArgs {
+			para_kind: ParaKind::Parachain,
+			genesis_head: dummy_head_data(),
+			validation_code: vec![1, 2, 3].into(),
+		},
+	)];
+
+	let genesis_config = MockGenesisConfig {
+		paras: GenesisConfig { paras, ..Default::default() },
+		configuration: crate::configuration::GenesisConfig {
+			config: HostConfiguration {
+				validation_upgrade_delay,
+				validation_upgrade_cooldown,
+				pvf_checking_enabled: false,
+				..Default::default()
+			},
+			..Default::default()
+		},
+		..Default::default()
+	};
+
+	new_test_ext(genesis_config).execute_with(|| {
+		let para_id = ParaId::from(0);
+		let new_code = ValidationCode(vec![4, 5, 6]);
+		let newer_code = ValidationCode(vec![4, 5, 6, 7]);
+
+		run_to_block(1, None);
+		Paras::schedule_code_upgrade(para_id, new_code.clone(), 1, &Configuration::config());
+		assert_eq!(FutureCodeHash::<Test>::get(&para_id), Some(new_code.hash()));
+		assert!(!Paras::can_upgrade_validation_code(para_id));
+
+		// Only root or the para itself can cancel the upgrade.
+		assert_err!(Paras::cancel_code_upgrade(RuntimeOrigin::signed(1), para_id), BadOrigin);
+		assert_err!(
+			Paras::cancel_code_upgrade(crate::Origin::Parachain(1.into()).into(), para_id),
+			BadOrigin,
+		);
+		assert_ok!(Paras::cancel_code_upgrade(crate::Origin::Parachain(para_id).into(), para_id));
+		assert_err!(
+			Paras::cancel_code_upgrade(RuntimeOrigin::root(), para_id),
+			Error::<Test>::NoUpgradeScheduled,
+		);
+
+		// The upgrade is forgotten and the para is told so.
+		assert!(FutureCodeUpgrades::<Test>::get(&para_id).is_none());
+		assert!(UpcomingUpgrades::<Test>::get().is_empty());
+		assert_eq!(UpgradeGoAheadSignal::<Test>::get(&para_id), Some(UpgradeGoAhead::Abort));
+		check_code_is_not_stored(&new_code);
+
+		// The para doesn't have to wait out the cooldown to signal a new upgrade.
+		assert!(UpgradeRestrictionSignal::<Test>::get(&para_id).is_none());
+		assert!(UpgradeCooldowns::<Test>::get().is_empty());
+		assert!(Paras::can_upgrade_validation_code(para_id));
+
+		run_to_block(2, None);
+		Paras::schedule_code_upgrade(para_id, newer_code.clone(), 2, &Configuration::config());
+		assert_eq!(FutureCodeUpgrades::<Test>::get(&para_id), Some(2 + validation_upgrade_delay));
+		assert_eq!(FutureCodeHash::<Test>::get(&para_id), Some(newer_code.hash()));
+		assert!(UpgradeGoAheadSignal::<Test>::get(&para_id).is_none());
+		check_code_is_stored(&newer_code);
+	});
+}
+
+#[test]
+fn code_upgrade_expires_if_not_applied_in_time() {
+	let validation_upgrade_delay = 5;
+	let validation_upgrade_cooldown = 30;
+	let validation_upgrade_expiry = 3;
+
+	let paras = (0u32..2)
+		.map(|i| {
+			(
+				i.into(),
+				ParaGenesisArgs {
+					para_kind: ParaKind::Parachain,
+					genesis_head: dummy_head_data(),
+					validation_code: vec![1, 2, 3, i as u8].into(),
+				},
+			)
+		})
+		.collect();
+
+	let genesis_config = MockGenesisConfig {
+		paras: GenesisConfig { paras, ..Default::default() },
+		configuration: crate::configuration::GenesisConfig {
+			config: HostConfiguration {
+				validation_upgrade_delay,
+				validation_upgrade_cooldown,
+				validation_upgrade_expiry,
+				pvf_checking_enabled: false,
+				..Default::default()
+			},
+			..Default::default()
+		},
+		..Default::default()
+	};
+
+	new_test_ext(genesis_config).execute_with(|| {
+		let (a, b) = (ParaId::from(0), ParaId::from(1));
+		let code_a = ValidationCode(vec![4, 5, 6]);
+		let code_b = ValidationCode(vec![4, 5, 6, 7]);
+
+		run_to_block(2, None);
+		let expected_at = 1 + validation_upgrade_delay;
+		let expire_at = expected_at + validation_upgrade_expiry;
+		Paras::schedule_code_upgrade(a, code_a.clone(), 1, &Configuration::config());
+		Paras::schedule_code_upgrade(b, code_b.clone(), 1, &Configuration::config());
+
+		assert_eq!(
+			Paras::upgrade_state(&a),
+			Some(UpgradeState::Scheduled { expected_at, expire_at: Some(expire_at) }),
+		);
+		assert_eq!(UpgradeExpiries::<Test>::get(), vec![(a, expire_at), (b, expire_at)]);
+
+		// A candidate of `a` applies the upgrade in time.
+		run_to_block(expire_at, None);
+		Paras::note_new_head(a, Default::default(), expected_at);
+		assert_eq!(Paras::current_code(&a), Some(code_a.clone()));
+		assert!(Paras::upgrade_state(&a).is_none());
+		assert_eq!(UpgradeExpiries::<Test>::get(), vec![(b, expire_at)]);
+
+		// `b` didn't get a candidate applying the upgrade included, so it is aborted.
+		run_to_block(expire_at + 1, None);
+		System::assert_last_event(Event::CodeUpgradeExpired(b).into());
+		assert!(Paras::upgrade_state(&b).is_none());
+		assert!(UpgradeExpiries::<Test>::get().is_empty());
+		assert!(FutureCodeUpgrades::<Test>::get(&b).is_none());
+		assert!(FutureCodeHash::<Test>::get(&b).is_none());
+		assert_eq!(UpgradeGoAheadSignal::<Test>::get(&b), Some(UpgradeGoAhead::Abort));
+		check_code_is_not_stored(&code_b);
+
+		// `b` may schedule a new upgrade right away.
+		assert!(Paras::can_upgrade_validation_code(b));
+		assert!(!Paras::can_upgrade_validation_code(a));
+	});
+}
+
+#[test]
+fn cancel_code_upgrade_withdraws_from_pvf_vote() {
+	let a = ParaId::from(111);
+	let b = ParaId::from(222);
+	let new_code: ValidationCode = vec![3, 2, 1].into();
+
+	let paras = vec![
+		(
+			a,
+			ParaGenesisArgs {
+				para_kind: ParaKind::Parachain,
+				genesis_head: Default::default(),
+				validation_code: vec![1].into(),
+			},
+		),
+		(
+			b,
+			ParaGenesisArgs {
+				para_kind: ParaKind::Parachain,
+				genesis_head: Default::default(),
+				validation_code: vec![2].into(),
+			},
+		),
+	];
+
+	let genesis_config = MockGenesisConfig {
+		paras: GenesisConfig { paras, ..Default::default() },
+		configuration: crate::configuration::GenesisConfig {
+			config: HostConfiguration { pvf_checking_enabled: true, ..Default::default() },
+			..Default::default()
+		},
+		..Default::default()
+	};
+
+	new_test_ext(genesis_config).execute_with(|| {
+		run_to_block(2, Some(vec![1]));
+
+		// Both paras upgrade to the same code, which is pre-checked by a single vote.
+		Paras::schedule_code_upgrade(a, new_code.clone(), 1, &Configuration::config());
+		Paras::schedule_code_upgrade(b, new_code.clone(), 1, &Configuration::config());
+		assert_eq!(PvfActiveVoteMap::<Test>::get(&new_code.hash()).unwrap().causes.len(), 2);
+
+		// Canceling one of the upgrades keeps the vote running for the other one.
+		assert_ok!(Paras::cancel_code_upgrade(RuntimeOrigin::root(), a));
+		let vote = PvfActiveVoteMap::<Test>::get(&new_code.hash()).unwrap();
+		assert!(matches!(&vote.causes[..], [PvfCheckCause::Upgrade { id, .. }] if *id == b));
+		assert_eq!(CodeByHashRefs::<Test>::get(&new_code.hash()), 1);
+
+		// Once nobody is interested in the outcome, the vote is dropped along with the code.
+		assert_ok!(Paras::cancel_code_upgrade(RuntimeOrigin::root(), b));
+		assert!(PvfActiveVoteMap::<Test>::get(&new_code.hash()).is_none());
+		assert!(Paras::pvfs_require_precheck().is_empty());
+		check_code_is_not_stored(&new_code);
+
+		System::assert_has_event(Event::CodeUpgradeCanceled(a).into());
+		System::assert_last_event(Event::CodeUpgradeCanceled(b).into());
+	});
+}
+
+#[test]
+fn force_rollback_code_reverts_to_retained_past_code() {
+	let para_id = ParaId::from(0);
+	let old_code = ValidationCode(vec![1, 2, 3]);
+	let new_code = ValidationCode(vec![4, 5, 6]);
+
+	let paras = vec![(
+		para_id,
+		ParaGenesisArgs {
+			para_kind: ParaKind::Parachain,
+			genesis_head: dummy_head_data(),
+			validation_code: old_code.clone(),
+		},
+	)];
+
+	let genesis_config = MockGenesisConfig {
+		paras: GenesisConfig { paras, ..Default::default() },
+		configuration: crate::configuration::GenesisConfig {
+			config: HostConfiguration {
+				code_retention_period: 10,
+				pvf_checking_enabled: false,
+				..Default::default()
+			},
+			..Default::default()
+		},
+		..Default::default()
+	};
+
+	new_test_ext(genesis_config).execute_with(|| {
+		run_to_block(1, None);
+		assert_ok!(Paras::force_set_current_code(RuntimeOrigin::root(), para_id, new_code.clone()));
+
+		run_to_block(2, None);
+		Paras::schedule_code_upgrade(para_id, vec![7].into(), 2, &Configuration::config());
+
+		assert_err!(
+			Paras::force_rollback_code(RuntimeOrigin::signed(1), para_id, old_code.hash()),
+			BadOrigin,
+		);
+		assert_err!(
+			Paras::force_rollback_code(RuntimeOrigin::root(), para_id, new_code.hash()),
+			Error::<Test>::CodeAlreadyCurrent,
+		);
+		assert_err!(
+			Paras::force_rollback_code(
+				RuntimeOrigin::root(),
+				para_id,
+				ValidationCode(vec![8]).hash()
+			),
+			Error::<Test>::CodeNotRetained,
+		);
+
+		assert_ok!(Paras::force_rollback_code(RuntimeOrigin::root(), para_id, old_code.hash()));
+		assert_eq!(CurrentCodeHash::<Test>::get(&para_id), Some(old_code.hash()));
+		System::assert_last_event(Event::CodeRolledBack(para_id, old_code.hash()).into());
+
+		// The pending upgrade is canceled and the rolled back code becomes past code.
+		assert!(FutureCodeHash::<Test>::get(&para_id).is_none());
+		assert_eq!(UpgradeGoAheadSignal::<Test>::get(&para_id), Some(UpgradeGoAhead::Abort));
+		assert_eq!(PastCodeHash::<Test>::get(&(para_id, 2)), Some(new_code.hash()));
+		check_code_is_stored(&old_code);
+		check_code_is_stored(&new_code);
+		check_code_is_not_stored(&vec![7].into());
+
+		// Inclusion learns about the rollback once.
+		assert_eq!(Paras::take_code_rollbacks(), vec![(para_id, new_code.hash())]);
+		assert!(Paras::take_code_rollbacks().is_empty());
+
+		assert_err!(
+			Paras::force_rollback_code(RuntimeOrigin::root(), 1.into(), old_code.hash()),
+			Error::<Test>::NotRegistered,
+		);
+	});
+}
+
+#[test]
+fn set_para_frozen_keeps_frozen_paras_sorted() {
+	let paras = [2, 1]
+		.into_iter()
+		.map(|id| {
+			(
+				ParaId::from(id),
+				ParaGenesisArgs {
+					para_kind: ParaKind::Parachain,
+					genesis_head: dummy_head_data(),
+					validation_code: dummy_validation_code(),
+				},
+			)
+		})
+		.collect();
+
+	let genesis_config = MockGenesisConfig {
+		paras: GenesisConfig { paras, ..Default::default() },
+		..Default::default()
+	};
+
+	new_test_ext(genesis_config).execute_with(|| {
+		let (a, b) = (ParaId::from(1), ParaId::from(2));
+		run_to_block(1, None);
+
+		assert_err!(Paras::set_para_frozen(RuntimeOrigin::signed(1), a, true), BadOrigin);
+		assert_err!(
+			Paras::set_para_frozen(RuntimeOrigin::root(), 3.into(), true),
+			Error::<Test>::NotRegistered,
+		);
+
+		assert_ok!(Paras::set_para_frozen(RuntimeOrigin::root(), b, true));
+		System::assert_last_event(Event::ParaFrozen(b).into());
+		assert_ok!(Paras::set_para_frozen(RuntimeOrigin::root(), a, true));
+		assert_eq!(Paras::frozen_paras(), vec![a, b]);
+		assert!(Paras::is_frozen(&a));
+
+		// Freezing again is a no-op.
+		System::reset_events();
+		assert_ok!(Paras::set_para_frozen(RuntimeOrigin::root(), a, true));
+		assert!(System::events().is_empty());
+
+		assert_ok!(Paras::set_para_frozen(RuntimeOrigin::root(), a, false));
+		System::assert_last_event(Event::ParaUnfrozen(a).into());
+		assert_eq!(Paras::frozen_paras(), vec![b]);
+		assert!(!Paras::is_frozen(&a));
+	});
+}
+
 #[test]
 fn full_parachain_cleanup_storage() {
 	let code_retention_period = 20;
@@ -787,6 +1175,64 @@ fn full_parachain_cleanup_storage() {
 	});
 }
 
+#[test]
+fn shared_code_outlives_offboarding_of_one_user() {
+	let code_retention_period = 20;
+
+	let shared_code = ValidationCode(vec![1, 2, 3]);
+	let paras = vec![
+		(
+			0u32.into(),
+			ParaGenesisArgs {
+				para_kind: ParaKind::Parachain,
+				genesis_head: dummy_head_data(),
+				validation_code: shared_code.clone(),
+			},
+		),
+		(
+			1u32.into(),
+			ParaGenesisArgs {
+				para_kind: ParaKind::Parachain,
+				genesis_head: dummy_head_data(),
+				validation_code: shared_code.clone(),
+			},
+		),
+	];
+
+	let genesis_config = MockGenesisConfig {
+		paras: GenesisConfig { paras, ..Default::default() },
+		configuration: crate::configuration::GenesisConfig {
+			config: HostConfiguration {
+				code_retention_period,
+				pvf_checking_enabled: false,
+				..Default::default()
+			},
+			..Default::default()
+		},
+		..Default::default()
+	};
+
+	new_test_ext(genesis_config).execute_with(|| {
+		let (para_a, para_b) = (ParaId::from(0), ParaId::from(1));
+
+		// Both paras refer to a single copy of the code.
+		assert_eq!(CodeByHashRefs::<Test>::get(shared_code.hash()), 2);
+		assert_eq!(Paras::current_code_hash(&para_a), Some(shared_code.hash()));
+		assert_eq!(Paras::current_code_hash(&para_b), Some(shared_code.hash()));
+
+		run_to_block(2, None);
+		assert_ok!(Paras::schedule_para_cleanup(para_a));
+		run_to_block(4, Some(vec![3, 4]));
+		assert!(Paras::current_code_hash(&para_a).is_none());
+
+		// The offboarded para keeps its reference until the code leaves the retention period.
+		run_to_block(3 + code_retention_period + 1, None);
+		assert_eq!(CodeByHashRefs::<Test>::get(shared_code.hash()), 1);
+		check_code_is_stored(&shared_code);
+		assert_eq!(Paras::current_code(&para_b), Some(shared_code.clone()));
+	});
+}
+
 #[test]
 fn cannot_offboard_ongoing_pvf_check() {
 	let para_id = ParaId::from(0);
@@ -956,6 +1402,54 @@ fn para_incoming_at_session() {
 	})
 }
 
+#[test]
+fn para_lifecycle_changes_are_announced() {
+	let a = ParaId::from(999);
+
+	new_test_ext(Default::default()).execute_with(|| {
+		run_to_block(1, Some(vec![1]));
+
+		assert_ok!(Paras::schedule_para_initialize(
+			a,
+			ParaGenesisArgs {
+				para_kind: ParaKind::Parathread,
+				genesis_head: vec![1].into(),
+				validation_code: vec![1].into(),
+			},
+		));
+		run_to_block(3, Some(vec![2, 3]));
+		assert_eq!(Paras::lifecycle(a), Some(ParaLifecycle::Parathread));
+
+		assert_ok!(Paras::schedule_parathread_upgrade(a));
+		run_to_block(5, Some(vec![4, 5]));
+		assert_eq!(Paras::lifecycle(a), Some(ParaLifecycle::Parachain));
+
+		assert_ok!(Paras::schedule_para_cleanup(a));
+		run_to_block(7, Some(vec![6, 7]));
+		assert_eq!(Paras::lifecycle(a), None);
+
+		let lifecycle_events = System::events()
+			.into_iter()
+			.filter_map(|r| match r.event {
+				RuntimeEvent::Paras(e @ Event::ParaLifecycleChanged(..)) |
+				RuntimeEvent::Paras(e @ Event::ParaRemoved(..)) => Some(e),
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(
+			lifecycle_events,
+			vec![
+				Event::ParaLifecycleChanged(a, ParaLifecycle::Onboarding),
+				Event::ParaLifecycleChanged(a, ParaLifecycle::Parathread),
+				Event::ParaLifecycleChanged(a, ParaLifecycle::UpgradingParathread),
+				Event::ParaLifecycleChanged(a, ParaLifecycle::Parachain),
+				Event::ParaLifecycleChanged(a, ParaLifecycle::OffboardingParachain),
+				Event::ParaRemoved(a),
+			],
+		);
+	});
+}
+
 #[test]
 fn code_hash_at_returns_up_to_end_of_code_retention_period() {
 	let code_retention_period = 10;
@@ -1123,6 +1617,7 @@ fn pvf_check_coalescing_onboarding_and_upgrade() {
 
 		// Verify that the required events were emitted.
 		EventValidator::new()
+			.lifecycle_changed(b, ParaLifecycle::Onboarding)
 			.started(&validation_code, b)
 			.started(&validation_code, a)
 			.accepted(&validation_code, b)
@@ -1182,6 +1677,14 @@ fn pvf_check_onboarding_reject_on_expiry() {
 
 		// Verify that at this point we can again try to initialize the same para.
 		assert!(Paras::can_schedule_para_initialize(&a));
+
+		// Verify that the required events were emitted.
+		EventValidator::new()
+			.lifecycle_changed(a, ParaLifecycle::Onboarding)
+			.started(&validation_code, a)
+			.rejected(&validation_code, a)
+			.removed(a)
+			.check();
 	});
 }
 
@@ -211,6 +211,24 @@ benchmarks! {
 			cores_with_backed.len()
 		);
 	}
+
+	// The weight of an inherent carrying nothing, while no candidate is pending availability. This
+	// is the cost of every block without parachain activity and should be close to zero.
+	enter_empty {
+		let scenario = BenchBuilder::<T>::new().build();
+
+		let mut benchmark = scenario.data.clone();
+		benchmark.bitfields.clear();
+		benchmark.backed_candidates.clear();
+		benchmark.disputes.clear();
+
+		assert!(!inclusion::Pallet::<T>::is_anything_pending());
+	}: enter(RawOrigin::None, benchmark)
+	verify {
+		// Assert that the block was not discarded
+		assert!(Included::<T>::get().is_some());
+		assert_eq!(inclusion::PendingAvailability::<T>::iter().count(), 0);
+	}
 }
 
 impl_benchmark_test_suite!(
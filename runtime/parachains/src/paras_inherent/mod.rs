@@ -41,12 +41,12 @@ use frame_system::pallet_prelude::*;
 use pallet_babe::{self, ParentBlockRandomness};
 use primitives::{
 	BackedCandidate, CandidateHash, CandidateReceipt, CheckedDisputeStatementSet,
-	CheckedMultiDisputeStatementSet, CoreIndex, DisputeStatementSet,
+	CheckedMultiDisputeStatementSet, CoreIndex, DisputeStatementSet, GroupIndex,
 	InherentData as ParachainsInherentData, MultiDisputeStatementSet, ScrapedOnChainVotes,
-	SessionIndex, SigningContext, UncheckedSignedAvailabilityBitfield,
-	UncheckedSignedAvailabilityBitfields, ValidatorId, ValidatorIndex, ValidityAttestation,
-	PARACHAINS_INHERENT_IDENTIFIER,
+	SessionIndex, UncheckedSignedAvailabilityBitfield, UncheckedSignedAvailabilityBitfields,
+	ValidatorId, ValidatorIndex, ValidityAttestation, PARACHAINS_INHERENT_IDENTIFIER,
 };
+use primitives::vstaging::{CandidateDescriptorV2Ext, InclusionDiagnostics, InclusionSimulation};
 use rand::{seq::SliceRandom, SeedableRng};
 
 use scale_info::TypeInfo;
@@ -65,7 +65,8 @@ pub use self::{
 	misc::{IndexedRetain, IsSortedBy},
 	weights::{
 		backed_candidate_weight, backed_candidates_weight, dispute_statement_set_weight,
-		multi_dispute_statement_sets_weight, paras_inherent_total_weight, signed_bitfields_weight,
+		enactment_weight, inclusion_reads_weight, multi_dispute_statement_sets_weight,
+		paras_inherent_total_weight, signed_bitfield_weight, signed_bitfields_weight,
 		TestWeightInfo, WeightInfo,
 	},
 };
@@ -89,7 +90,7 @@ impl From<BitVec<u8, bitvec::order::Lsb0>> for DisputedBitfield {
 	}
 }
 
-#[cfg(test)]
+#[cfg(any(feature = "runtime-benchmarks", test))]
 impl DisputedBitfield {
 	/// Create a new bitfield, where each bit is set to `false`.
 	pub fn zeros(n: usize) -> Self {
@@ -131,6 +132,8 @@ pub mod pallet {
 		DisputeStatementsUnsortedOrDuplicates,
 		/// A dispute statement was invalid.
 		DisputeInvalid,
+		/// A backed candidate would be dropped from the block without failing it.
+		CandidateDropped,
 	}
 
 	/// Whether the paras inherent was included within this block.
@@ -310,7 +313,7 @@ impl<T: Config> Pallet<T> {
 			parent_header,
 			mut disputes,
 		} = data;
-		#[cfg(feature = "runtime-metrics")]
+		#[cfg(any(feature = "runtime-metrics", feature = "runtime-tracing"))]
 		sp_io::init_tracing();
 
 		log::debug!(
@@ -330,9 +333,10 @@ impl<T: Config> Pallet<T> {
 		);
 
 		let now = <frame_system::Pallet<T>>::block_number();
+		let config = <configuration::Pallet<T>>::config();
 
 		let mut candidates_weight = backed_candidates_weight::<T>(&backed_candidates);
-		let mut bitfields_weight = signed_bitfields_weight::<T>(signed_bitfields.len());
+		let mut bitfields_weight = signed_bitfields_weight::<T>(&signed_bitfields);
 		let disputes_weight = multi_dispute_statement_sets_weight::<T, _, _>(&disputes);
 
 		let current_session = <shared::Pallet<T>>::session_index();
@@ -345,9 +349,11 @@ impl<T: Config> Pallet<T> {
 		T::DisputesHandler::assure_deduplicated_and_sorted(&mut disputes)
 			.map_err(|_e| Error::<T>::DisputeStatementsUnsortedOrDuplicates)?;
 
+		let entropy = compute_entropy::<T>(parent_hash);
+		let mut rng = rand_chacha::ChaChaRng::from_seed(entropy.into());
+
 		let (checked_disputes, total_consumed_weight) = {
 			// Obtain config params..
-			let config = <configuration::Pallet<T>>::config();
 			let post_conclusion_acceptance_period =
 				config.dispute_post_conclusion_acceptance_period;
 
@@ -381,9 +387,6 @@ impl<T: Config> Pallet<T> {
 				bitfields_weight = Weight::zero();
 			}
 
-			let entropy = compute_entropy::<T>(parent_hash);
-			let mut rng = rand_chacha::ChaChaRng::from_seed(entropy.into());
-
 			let (checked_disputes, checked_disputes_weight) = limit_and_sanitize_disputes::<T, _>(
 				disputes,
 				&dispute_set_validity_check,
@@ -394,7 +397,8 @@ impl<T: Config> Pallet<T> {
 				checked_disputes,
 				checked_disputes_weight
 					.saturating_add(candidates_weight)
-					.saturating_add(bitfields_weight),
+					.saturating_add(bitfields_weight)
+					.saturating_add(<T as Config>::WeightInfo::enter_empty()),
 			)
 		};
 
@@ -415,6 +419,14 @@ impl<T: Config> Pallet<T> {
 			let _ = T::DisputesHandler::process_checked_multi_dispute_data(&checked_disputes)?;
 			METRICS.on_disputes_imported(checked_disputes.len() as u64);
 
+			// A candidate that lost a dispute must never be included, in whichever session it
+			// is backed again.
+			for set in checked_disputes.iter().map(AsRef::as_ref) {
+				if T::DisputesHandler::concluded_invalid(set.session, set.candidate_hash) {
+					<inclusion::Pallet<T>>::note_disallowed_candidate(set.candidate_hash);
+				}
+			}
+
 			if T::DisputesHandler::is_frozen() {
 				// Relay chain freeze, at this point we will not include any parachain blocks.
 				METRICS.on_relay_chain_freeze();
@@ -470,15 +482,44 @@ impl<T: Config> Pallet<T> {
 
 		METRICS.on_bitfields_processed(signed_bitfields.len() as u64);
 
+		// The validator keys, groups and signing contexts are shared by all signature checks below.
+		let check_context = inclusion::SignatureCheckContext::<T>::load();
+
+		// Bitfields signed in the previous session are accepted within the grace period after a
+		// session change. All signatures are checked to tell them apart.
+		let (signed_bitfields, late_bitfields, bitfields_full_check) =
+			if check_context.accepts_late_bitfields() {
+				let (current, late, invalid) = check_context.split_late_bitfields(signed_bitfields);
+				if let (FullCheck::Yes, Some(validator_index)) = (full_check, invalid.first()) {
+					return Err(<inclusion::Pallet<T>>::rejected(
+						inclusion::Error::<T>::InvalidBitfieldSignature,
+						None,
+						None,
+						Some(*validator_index),
+					)
+					.into())
+				}
+				(current, late, FullCheck::Skip)
+			} else {
+				(signed_bitfields, Vec::new(), full_check)
+			};
+
 		// Process new availability bitfields, yielding any availability cores whose
 		// work has now concluded.
-		let freed_concluded = <inclusion::Pallet<T>>::process_bitfields(
+		let (freed_concluded, enactment_weight) = <inclusion::Pallet<T>>::process_bitfields_in(
+			&check_context,
 			expected_bits,
 			signed_bitfields,
+			late_bitfields,
 			disputed_bitfield,
 			<scheduler::Pallet<T>>::core_para,
-			full_check,
+			bitfields_full_check,
 		)?;
+		// only the candidates actually enacted are charged, the rest of the enactment weight
+		// charged up front is refunded.
+		let total_consumed_weight = total_consumed_weight
+			.saturating_add(enactment_weight)
+			.saturating_add(inclusion_reads_weight::<T>(expected_bits, backed_candidates.len()));
 		// any error in the previous function will cause an invalid block and not include
 		// the `DisputeState` to be written to the storage, hence this is ok.
 		set_scrapable_on_chain_disputes::<T>(current_session, checked_disputes.clone());
@@ -496,7 +537,6 @@ impl<T: Config> Pallet<T> {
 
 		METRICS.on_candidates_processed_total(backed_candidates.len() as u64);
 
-		let scheduled = <scheduler::Pallet<T>>::scheduled();
 		assure_sanity_backed_candidates::<T, _>(
 			parent_hash,
 			&backed_candidates,
@@ -504,21 +544,32 @@ impl<T: Config> Pallet<T> {
 				<T>::DisputesHandler::concluded_invalid(current_session, backed_candidate.hash())
 				// `fn process_candidates` does the verification checks
 			},
-			&scheduled[..],
 		)?;
 
 		METRICS.on_candidates_sanitized(backed_candidates.len() as u64);
 
 		// Process backed candidates according to scheduled cores.
 		let parent_storage_root = *parent_header.state_root();
+		<shared::Pallet<T>>::add_allowed_relay_parent(
+			parent_hash,
+			parent_storage_root,
+			now - One::one(),
+			config.max_candidate_relay_parent_depth,
+		);
+		<shared::Pallet<T>>::note_relay_storage_root(
+			now - One::one(),
+			parent_storage_root,
+			config.relay_storage_roots_retention,
+		);
 		let inclusion::ProcessedCandidates::<<T::Header as HeaderT>::Hash> {
 			core_indices: occupied,
 			candidate_receipt_with_backing_validator_indices,
-		} = <inclusion::Pallet<T>>::process_candidates(
+		} = <inclusion::Pallet<T>>::process_candidates_in(
+			&check_context,
 			parent_storage_root,
 			backed_candidates,
-			scheduled,
-			<scheduler::Pallet<T>>::group_validators,
+			<scheduler::Pallet<T>>::scheduled(),
+			|group_index| check_context.group_validators(group_index),
 		)?;
 
 		METRICS.on_disputes_included(checked_disputes.len() as u64);
@@ -674,26 +725,45 @@ impl<T: Config> Pallet<T> {
 				<scheduler::Pallet<T>>::free_cores(freed_disputed.clone());
 			}
 
+			// Within the grace period after a session change, the bitfields signed in the
+			// previous session are told apart by their signatures, and kept after the others.
+			let check_context = inclusion::SignatureCheckContext::<T>::load();
+			let (bitfields, late_bitfields, bitfields_full_check) =
+				if check_context.accepts_late_bitfields() {
+					let (current, late, _invalid) = check_context.split_late_bitfields(bitfields);
+					(current, late, FullCheck::Skip)
+				} else {
+					(bitfields, Vec::new(), FullCheck::Yes)
+				};
+
 			// The following 3 calls are equiv to a call to `process_bitfields`
 			// but we can retain access to `bitfields`.
-			let bitfields = sanitize_bitfields::<T>(
+			let mut bitfields = sanitize_bitfields::<T>(
 				bitfields,
-				disputed_bitfield,
+				disputed_bitfield.clone(),
 				expected_bits,
 				parent_hash,
 				current_session,
 				&validator_public[..],
-				FullCheck::Yes,
+				bitfields_full_check,
+			);
+			let late_bitfields = <inclusion::Pallet<T>>::sanitize_late_bitfields(
+				late_bitfields,
+				&bitfields,
+				expected_bits,
+				&disputed_bitfield,
 			);
 
-			let freed_concluded =
+			let (freed_concluded, _enactment_weight) =
 				<inclusion::Pallet<T>>::update_pending_availability_and_get_freed_cores::<_>(
 					expected_bits,
 					&validator_public[..],
 					bitfields.clone(),
+					late_bitfields.clone(),
 					<scheduler::Pallet<T>>::core_para,
 					false,
 				);
+			bitfields.extend(late_bitfields.into_iter().map(|late| late.signed));
 
 			let freed = collect_all_freed_cores::<T, _>(freed_concluded.iter().cloned());
 
@@ -703,18 +773,24 @@ impl<T: Config> Pallet<T> {
 
 			let scheduled = <scheduler::Pallet<T>>::scheduled();
 
-			let relay_parent_number = now - One::one();
 			let parent_storage_root = *parent_header.state_root();
+			<shared::Pallet<T>>::add_allowed_relay_parent(
+				parent_hash,
+				parent_storage_root,
+				now - One::one(),
+				config.max_candidate_relay_parent_depth,
+			);
 
-			let check_ctx = CandidateCheckContext::<T>::new(now, relay_parent_number);
-			let backed_candidates = sanitize_backed_candidates::<T, _>(
+			let check_ctx = CandidateCheckContext::<T>::new(now);
+			let (mut backed_candidates, dropped) = sanitize_backed_candidates::<T, _, _>(
 				parent_hash,
 				backed_candidates,
 				move |candidate_idx: usize,
 				      backed_candidate: &BackedCandidate<<T as frame_system::Config>::Hash>|
 				      -> bool {
-					// never include a concluded-invalid candidate
+					// never include a concluded-invalid or otherwise disallowed candidate
 					concluded_invalid_disputes.contains(&backed_candidate.hash()) ||
+						<inclusion::Pallet<T>>::is_disallowed(&backed_candidate.hash()) ||
 							// Instead of checking the candidates with code upgrades twice
 							// move the checking up here and skip it in the training wheels fallback.
 							// That way we avoid possible duplicate checks while assuring all
@@ -724,7 +800,19 @@ impl<T: Config> Pallet<T> {
 								.is_err()
 				},
 				&scheduled[..],
+				<scheduler::Pallet<T>>::group_validators,
 			);
+			for (candidate_hash, reason) in dropped {
+				log::debug!(
+					target: LOG_TARGET,
+					"Dropping backed candidate {:?} from the inherent: {:?}",
+					candidate_hash,
+					reason,
+				);
+			}
+			if let Some(max_candidates) = config.max_candidates_per_block {
+				backed_candidates.truncate(max_candidates as usize);
+			}
 
 			frame_support::storage::TransactionOutcome::Rollback((
 				// filtered backed candidates
@@ -738,11 +826,16 @@ impl<T: Config> Pallet<T> {
 		});
 
 		// Assure the maximum block weight is adhered, by limiting bitfields and backed
-		// candidates. Dispute statement sets were already limited before.
+		// candidates. Dispute statement sets were already limited before, and the enactment of
+		// candidates becoming available and the storage reads of the inclusion module are
+		// charged up front.
+		let reserved_weight = checked_disputes_sets_consumed_weight
+			.saturating_add(enactment_weight::<T>(expected_bits))
+			.saturating_add(inclusion_reads_weight::<T>(expected_bits, backed_candidates.len()));
 		let actual_weight = apply_weight_limit::<T>(
 			&mut backed_candidates,
 			&mut bitfields,
-			max_block_weight.saturating_sub(checked_disputes_sets_consumed_weight),
+			max_block_weight.saturating_sub(reserved_weight),
 			&mut rng,
 		);
 
@@ -762,6 +855,180 @@ impl<T: Config> Pallet<T> {
 			parent_header,
 		})
 	}
+
+	/// Simulate the inclusion of the given bitfields and backed candidates in a child of the
+	/// block with the given header, whose state this is called on, and report whether each of
+	/// them would be accepted. No state is changed.
+	///
+	/// Only the block number, the parent hash and the core layout of the child block are set up,
+	/// the other modules are not initialized for it.
+	pub(crate) fn simulate_inclusion(
+		parent_header: T::Header,
+		bitfields: UncheckedSignedAvailabilityBitfields,
+		backed_candidates: Vec<BackedCandidate<T::Hash>>,
+	) -> InclusionSimulation {
+		frame_support::storage::with_transaction_unchecked(|| {
+			frame_support::storage::TransactionOutcome::Rollback(Self::simulate_inclusion_inner(
+				parent_header,
+				bitfields,
+				backed_candidates,
+			))
+		})
+	}
+
+	fn simulate_inclusion_inner(
+		parent_header: T::Header,
+		bitfields: UncheckedSignedAvailabilityBitfields,
+		backed_candidates: Vec<BackedCandidate<T::Hash>>,
+	) -> InclusionSimulation {
+		let (bitfield_outcomes, check_context, parent_storage_root) =
+			Self::simulate_child_block(parent_header, bitfields);
+
+		// Each candidate is processed on its own, and only kept if it is accepted.
+		let candidate_outcomes = backed_candidates
+			.into_iter()
+			.map(|backed_candidate| {
+				frame_support::storage::with_transaction_unchecked(|| {
+					let outcome = Self::simulate_candidate(
+						&check_context,
+						parent_storage_root,
+						backed_candidate,
+					);
+					if outcome.is_ok() {
+						frame_support::storage::TransactionOutcome::Commit(outcome)
+					} else {
+						frame_support::storage::TransactionOutcome::Rollback(outcome)
+					}
+				})
+			})
+			.collect();
+
+		InclusionSimulation { bitfields: bitfield_outcomes, candidates: candidate_outcomes }
+	}
+
+	fn simulate_candidate(
+		check_context: &inclusion::SignatureCheckContext<T>,
+		parent_storage_root: T::Hash,
+		backed_candidate: BackedCandidate<T::Hash>,
+	) -> DispatchResult {
+		ensure!(
+			!T::DisputesHandler::concluded_invalid(
+				check_context.session_index(),
+				backed_candidate.hash(),
+			),
+			Error::<T>::CandidateConcludedInvalid,
+		);
+
+		let inclusion::ProcessedCandidates::<T::Hash> { core_indices: occupied, .. } =
+			<inclusion::Pallet<T>>::process_candidates_in(
+				check_context,
+				parent_storage_root,
+				vec![backed_candidate],
+				<scheduler::Pallet<T>>::scheduled(),
+				|group_index| check_context.group_validators(group_index),
+			)?;
+		// A candidate without persisted validation data is left out without an error.
+		ensure!(!occupied.is_empty(), Error::<T>::CandidateDropped);
+
+		<scheduler::Pallet<T>>::occupied(&occupied);
+		Ok(())
+	}
+
+	/// Diagnose the given backed candidate as if it was included in a child of the block with
+	/// the given header, whose state this is called on. No state is changed.
+	///
+	/// The child block is set up like in [`Self::simulate_inclusion`], without any bitfields.
+	pub(crate) fn inclusion_diagnostics(
+		parent_header: T::Header,
+		backed_candidate: BackedCandidate<T::Hash>,
+	) -> InclusionDiagnostics {
+		frame_support::storage::with_transaction_unchecked(|| {
+			let (_, check_context, parent_storage_root) =
+				Self::simulate_child_block(parent_header, Vec::new());
+			let diagnostics = <inclusion::Pallet<T>>::diagnose_candidate(
+				&check_context,
+				parent_storage_root,
+				&backed_candidate,
+				&<scheduler::Pallet<T>>::scheduled(),
+				|group_index| check_context.group_validators(group_index),
+			);
+			frame_support::storage::TransactionOutcome::Rollback(diagnostics)
+		})
+	}
+
+	/// Set up a child of the block with the given header for the simulated inclusion of backed
+	/// candidates: apply the given bitfields, checked one by one, and schedule the freed cores.
+	///
+	/// Returns the outcome of each bitfield, along with the signature check context of the child
+	/// and the storage root of its parent.
+	fn simulate_child_block(
+		parent_header: T::Header,
+		bitfields: UncheckedSignedAvailabilityBitfields,
+	) -> (Vec<DispatchResult>, inclusion::SignatureCheckContext<T>, T::Hash) {
+		let parent_hash = parent_header.hash();
+		let now = *parent_header.number() + One::one();
+		<frame_system::Pallet<T>>::initialize(&now, &parent_hash, &Default::default());
+
+		let config = <configuration::Pallet<T>>::config();
+		<inclusion::Pallet<T>>::note_core_layout(&config);
+		let check_context = inclusion::SignatureCheckContext::<T>::load();
+		let current_session = check_context.session_index();
+		let expected_bits = <scheduler::Pallet<T>>::availability_cores().len();
+
+		// Each bitfield is checked on its own, but has to be ordered after the ones accepted
+		// before it.
+		let mut last_index: Option<ValidatorIndex> = None;
+		let mut accepted_bitfields = Vec::with_capacity(bitfields.len());
+		let bitfield_outcomes = bitfields
+			.into_iter()
+			.map(|unchecked_bitfield| -> DispatchResult {
+				let validator_index = unchecked_bitfield.unchecked_validator_index();
+				ensure!(
+					last_index.map_or(true, |last_index| last_index < validator_index),
+					inclusion::Error::<T>::BitfieldDuplicateOrUnordered,
+				);
+				let mut checked = assure_sanity_bitfields::<T>(
+					vec![unchecked_bitfield],
+					DisputedBitfield::zeros(expected_bits),
+					expected_bits,
+					parent_hash,
+					current_session,
+					check_context.validators(),
+					FullCheck::Yes,
+				)?;
+				last_index = Some(validator_index);
+				accepted_bitfields.append(&mut checked);
+				Ok(())
+			})
+			.collect();
+
+		// The accepted bitfields passed all checks, so processing them does not fail.
+		let freed_concluded = <inclusion::Pallet<T>>::process_bitfields_in(
+			&check_context,
+			expected_bits,
+			accepted_bitfields,
+			Vec::new(),
+			DisputedBitfield::zeros(expected_bits),
+			<scheduler::Pallet<T>>::core_para,
+			FullCheck::Skip,
+		)
+		.map(|(freed_concluded, _enactment_weight)| freed_concluded)
+		.unwrap_or_default();
+
+		let freed = collect_all_freed_cores::<T, _>(freed_concluded);
+		<scheduler::Pallet<T>>::clear();
+		<scheduler::Pallet<T>>::schedule(freed, now);
+
+		let parent_storage_root = *parent_header.state_root();
+		<shared::Pallet<T>>::add_allowed_relay_parent(
+			parent_hash,
+			parent_storage_root,
+			now - One::one(),
+			config.max_candidate_relay_parent_depth,
+		);
+
+		(bitfield_outcomes, check_context, parent_storage_root)
+	}
 }
 
 /// Derive a bitfield from dispute
@@ -865,7 +1132,7 @@ fn apply_weight_limit<T: Config + inclusion::Config>(
 ) -> Weight {
 	let total_candidates_weight = backed_candidates_weight::<T>(candidates.as_slice());
 
-	let total_bitfields_weight = signed_bitfields_weight::<T>(bitfields.len());
+	let total_bitfields_weight = signed_bitfields_weight::<T>(bitfields.as_slice());
 
 	let total = total_bitfields_weight.saturating_add(total_candidates_weight);
 
@@ -913,7 +1180,7 @@ fn apply_weight_limit<T: Config + inclusion::Config>(
 		rng,
 		&bitfields,
 		vec![],
-		|_| <<T as Config>::WeightInfo as WeightInfo>::enter_bitfields(),
+		|bitfield| signed_bitfield_weight::<T>(bitfield),
 		max_consumable_weight,
 	);
 
@@ -961,15 +1228,28 @@ pub(crate) fn sanitize_bitfields<T: crate::inclusion::Config>(
 	}
 
 	let all_zeros = BitVec::<u8, bitvec::order::Lsb0>::repeat(false, expected_bits);
-	let signing_context = SigningContext { parent_hash, session_index };
+	let signing_context =
+		<inclusion::Pallet<T>>::bitfield_signing_context(parent_hash, session_index);
 	for unchecked_bitfield in unchecked_bitfields {
 		// Find and skip invalid bitfields.
-		if unchecked_bitfield.unchecked_payload().0.len() != expected_bits {
+		let n_bits = unchecked_bitfield.unchecked_payload().0.len();
+		if n_bits != expected_bits && <scheduler::Pallet<T>>::is_previous_core_layout(n_bits) {
+			log::trace!(
+				target: LOG_TARGET,
+				"[{:?}] bitfield sized to the previous core layout: {} != {:?}",
+				full_check,
+				n_bits,
+				expected_bits,
+			);
+			continue
+		}
+
+		if n_bits != expected_bits {
 			log::trace!(
 				target: LOG_TARGET,
 				"[{:?}] bad bitfield length: {} != {:?}",
 				full_check,
-				unchecked_bitfield.unchecked_payload().0.len(),
+				n_bits,
 				expected_bits,
 			);
 			continue
@@ -1050,22 +1330,27 @@ pub(crate) fn assure_sanity_bitfields<T: crate::inclusion::Config>(
 
 	let mut bitfields = Vec::with_capacity(unchecked_bitfields.len());
 
-	let signing_context = SigningContext { parent_hash, session_index };
+	let signing_context =
+		<inclusion::Pallet<T>>::bitfield_signing_context(parent_hash, session_index);
 	for unchecked_bitfield in unchecked_bitfields {
+		let validator_index = unchecked_bitfield.unchecked_validator_index();
+		let rejected =
+			|error| <inclusion::Pallet<T>>::rejected(error, None, None, Some(validator_index));
+
 		// Find and skip invalid bitfields.
+		let n_bits = unchecked_bitfield.unchecked_payload().0.len();
 		ensure!(
-			unchecked_bitfield.unchecked_payload().0.len() == expected_bits,
-			Error::<T>::WrongBitfieldSize
+			n_bits == expected_bits || !<scheduler::Pallet<T>>::is_previous_core_layout(n_bits),
+			rejected(Error::<T>::OutdatedBitfieldSize)
 		);
-
-		let validator_index = unchecked_bitfield.unchecked_validator_index();
+		ensure!(n_bits == expected_bits, rejected(Error::<T>::WrongBitfieldSize));
 
 		if !last_index.map_or(true, |last_index: ValidatorIndex| last_index < validator_index) {
-			return Err(Error::<T>::UnsortedOrDuplicateValidatorIndices)
+			return Err(rejected(Error::<T>::UnsortedOrDuplicateValidatorIndices))
 		}
 
 		if unchecked_bitfield.unchecked_validator_index().0 as usize >= validators.len() {
-			return Err(Error::<T>::ValidatorIndexOutOfBounds)
+			return Err(rejected(Error::<T>::ValidatorIndexOutOfBounds))
 		}
 
 		let validator_public = &validators[validator_index.0 as usize];
@@ -1077,7 +1362,7 @@ pub(crate) fn assure_sanity_bitfields<T: crate::inclusion::Config>(
 			{
 				bitfields.push(signed_bitfield.into_unchecked());
 			} else {
-				return Err(Error::<T>::InvalidBitfieldSignature)
+				return Err(rejected(Error::<T>::InvalidBitfieldSignature))
 			}
 		} else {
 			bitfields.push(unchecked_bitfield);
@@ -1088,7 +1373,40 @@ pub(crate) fn assure_sanity_bitfields<T: crate::inclusion::Config>(
 	Ok(bitfields)
 }
 
-/// Filter out any candidates that have a concluded invalid dispute.
+/// The reason `sanitize_backed_candidates` dropped a backed candidate from the inherent.
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub(crate) enum BackedCandidateDropReason {
+	/// The candidate concluded invalid in a dispute, is disallowed, or failed verification.
+	ConcludedInvalidOrInvalid,
+	/// The relay parent of the candidate is not allowed.
+	RelayParentNotAllowed,
+	/// The para of the candidate is not scheduled on a free core.
+	NotScheduled,
+	/// The backing votes of the candidate don't fit the size of any group assigned to a core the
+	/// para is scheduled on.
+	BackingGroupMismatch,
+	/// Other candidates of the para were kept for all the cores it is scheduled on, or for the
+	/// core the candidate names.
+	Duplicate,
+}
+
+/// Whether to keep the given candidate, noting it in `dropped` along with the reason if not.
+fn keep_or_note_dropped<H: Clone + Encode>(
+	dropped: &mut Vec<(CandidateHash, BackedCandidateDropReason)>,
+	backed_candidate: &BackedCandidate<H>,
+	reason: Option<BackedCandidateDropReason>,
+) -> bool {
+	match reason {
+		Some(reason) => {
+			dropped.push((backed_candidate.hash(), reason));
+			false
+		},
+		None => true,
+	}
+}
+
+/// Filter out any candidates that have a concluded invalid dispute, or that would otherwise
+/// make the runtime reject the whole inherent.
 ///
 /// `scheduled` follows the same naming scheme as provided in the
 /// guide: Currently `free` but might become `occupied`.
@@ -1098,20 +1416,36 @@ pub(crate) fn assure_sanity_bitfields<T: crate::inclusion::Config>(
 /// `candidate_has_concluded_invalid_dispute` must return `true` if the candidate
 /// is disputed, false otherwise. The passed `usize` is the candidate index.
 ///
-/// The returned `Vec` is sorted according to the occupied core index.
+/// `group_validators` looks up the validators of the groups assigned in `scheduled`.
+///
+/// The returned `Vec` of kept candidates is sorted according to the occupied core index. The
+/// dropped candidates are returned along with the reason they were dropped for, in the order
+/// they were dropped in.
 fn sanitize_backed_candidates<
 	T: crate::inclusion::Config,
 	F: FnMut(usize, &BackedCandidate<T::Hash>) -> bool,
+	G: Fn(GroupIndex) -> Option<Vec<ValidatorIndex>>,
 >(
 	relay_parent: T::Hash,
 	mut backed_candidates: Vec<BackedCandidate<T::Hash>>,
 	mut candidate_has_concluded_invalid_dispute_or_is_invalid: F,
 	scheduled: &[CoreAssignment],
-) -> Vec<BackedCandidate<T::Hash>> {
+	group_validators: G,
+) -> (Vec<BackedCandidate<T::Hash>>, Vec<(CandidateHash, BackedCandidateDropReason)>) {
+	use BackedCandidateDropReason::*;
+
+	let mut dropped = Vec::new();
+
 	// Remove any candidates that were concluded invalid.
 	// This does not assume sorting.
-	backed_candidates.indexed_retain(move |candidate_idx, backed_candidate| {
-		!candidate_has_concluded_invalid_dispute_or_is_invalid(candidate_idx, backed_candidate)
+	backed_candidates.indexed_retain(|candidate_idx, backed_candidate| {
+		let invalid =
+			candidate_has_concluded_invalid_dispute_or_is_invalid(candidate_idx, backed_candidate);
+		keep_or_note_dropped(
+			&mut dropped,
+			backed_candidate,
+			invalid.then_some(ConcludedInvalidOrInvalid),
+		)
 	});
 
 	let scheduled_paras_to_core_idx = scheduled
@@ -1125,8 +1459,37 @@ fn sanitize_backed_candidates<
 
 	backed_candidates.retain(|backed_candidate| {
 		let desc = backed_candidate.descriptor();
-		desc.relay_parent == relay_parent &&
-			scheduled_paras_to_core_idx.get(&desc.para_id).is_some()
+		let reason = if !is_allowed_relay_parent::<T>(relay_parent, desc.relay_parent) {
+			Some(RelayParentNotAllowed)
+		} else if scheduled_paras_to_core_idx.get(&desc.para_id).is_none() {
+			Some(NotScheduled)
+		} else {
+			None
+		};
+		keep_or_note_dropped(&mut dropped, backed_candidate, reason)
+	});
+
+	// A backing that doesn't fit the group of the core would make `process_candidates` reject the
+	// whole inherent.
+	let mut scheduled_group_sizes = BTreeMap::<ParaId, Vec<usize>>::new();
+	for core_assignment in scheduled {
+		if let Some(group) = group_validators(core_assignment.group_idx) {
+			scheduled_group_sizes
+				.entry(core_assignment.para_id)
+				.or_default()
+				.push(group.len());
+		}
+	}
+	backed_candidates.retain(|backed_candidate| {
+		let group_len = backed_candidate.validator_indices.len();
+		let fits = scheduled_group_sizes
+			.get(&backed_candidate.descriptor().para_id)
+			.map_or(false, |sizes| sizes.contains(&group_len));
+		keep_or_note_dropped(
+			&mut dropped,
+			backed_candidate,
+			(!fits).then_some(BackingGroupMismatch),
+		)
 	});
 
 	// Sort the `Vec` last, once there is a guarantee that these
@@ -1140,10 +1503,40 @@ fn sanitize_backed_candidates<
 			.cmp(&scheduled_paras_to_core_idx[&y.descriptor().para_id])
 	});
 
-	backed_candidates
+	if <configuration::Pallet<T>>::config().multi_core_paras {
+		// One candidate of each para can be included per core the para is scheduled on, as long
+		// as the candidates that name their core name different ones.
+		let mut cores_left = BTreeMap::<ParaId, usize>::new();
+		for core_assignment in scheduled {
+			*cores_left.entry(core_assignment.para_id).or_default() += 1;
+		}
+		let mut named_cores = BTreeSet::new();
+		backed_candidates.retain(|backed_candidate| {
+			let desc = backed_candidate.descriptor();
+			let keep = desc.core_index().map_or(true, |core| named_cores.insert(core)) &&
+				match cores_left.get_mut(&desc.para_id) {
+					Some(left) if *left > 0 => {
+						*left -= 1;
+						true
+					},
+					_ => false,
+				};
+			keep_or_note_dropped(&mut dropped, backed_candidate, (!keep).then_some(Duplicate))
+		});
+	} else {
+		// Only the first candidate of each para can be included.
+		let mut previous_para = None;
+		backed_candidates.retain(|backed_candidate| {
+			let para_id = backed_candidate.descriptor().para_id;
+			let duplicate = previous_para.replace(para_id) == Some(para_id);
+			keep_or_note_dropped(&mut dropped, backed_candidate, duplicate.then_some(Duplicate))
+		});
+	}
+
+	(backed_candidates, dropped)
 }
 
-/// Assumes sorted candidates.
+/// Candidates may be in any order, `process_candidates` matches them up with their cores.
 pub(crate) fn assure_sanity_backed_candidates<
 	T: crate::inclusion::Config,
 	F: FnMut(usize, &BackedCandidate<T::Hash>) -> bool,
@@ -1151,7 +1544,6 @@ pub(crate) fn assure_sanity_backed_candidates<
 	relay_parent: T::Hash,
 	backed_candidates: &[BackedCandidate<T::Hash>],
 	mut candidate_has_concluded_invalid_dispute_or_is_invalid: F,
-	scheduled: &[CoreAssignment],
 ) -> Result<(), crate::inclusion::Error<T>> {
 	use crate::inclusion::Error;
 
@@ -1163,26 +1555,24 @@ pub(crate) fn assure_sanity_backed_candidates<
 		// This holds under the assumption that `Scheduler::schedule` is called _before_.
 		// Also checks the candidate references the correct relay parent.
 		let desc = backed_candidate.descriptor();
-		if desc.relay_parent != relay_parent {
+		if !is_allowed_relay_parent::<T>(relay_parent, desc.relay_parent) {
 			return Err(Error::<T>::UnexpectedRelayParent)
 		}
 	}
 
-	let scheduled_paras_to_core_idx = scheduled
-		.into_iter()
-		.map(|core_assignment| (core_assignment.para_id, core_assignment.core))
-		.collect::<BTreeMap<ParaId, CoreIndex>>();
-
-	if !IsSortedBy::is_sorted_by(backed_candidates, |x, y| {
-		// Never panics, since we would have early returned on those in the above loop.
-		scheduled_paras_to_core_idx[&x.descriptor().para_id]
-			.cmp(&scheduled_paras_to_core_idx[&y.descriptor().para_id])
-	}) {
-		return Err(Error::<T>::UnsortedOrDuplicateBackedCandidates)
-	}
 	Ok(())
 }
 
+/// Whether a candidate may use `candidate_relay_parent`, being either the parent block or one of
+/// the recent blocks before it that are still allowed as relay parents.
+fn is_allowed_relay_parent<T: shared::Config>(
+	parent_hash: T::Hash,
+	candidate_relay_parent: T::Hash,
+) -> bool {
+	candidate_relay_parent == parent_hash ||
+		<shared::Pallet<T>>::allowed_relay_parent(candidate_relay_parent).is_some()
+}
+
 /// Derive entropy from babe provided per block randomness.
 ///
 /// In the odd case none is available, uses the `parent_hash` and
@@ -136,6 +136,116 @@ mod enter {
 		});
 	}
 
+	#[test]
+	fn simulate_inclusion_reports_each_item_without_changing_state() {
+		new_test_ext(MockGenesisConfig::default()).execute_with(|| {
+			let mut backed_and_concluding = BTreeMap::new();
+			backed_and_concluding.insert(0, 1);
+			backed_and_concluding.insert(1, 1);
+
+			let scenario = make_inherent_data(TestConfig {
+				dispute_statements: BTreeMap::new(),
+				dispute_sessions: vec![],
+				backed_and_concluding,
+				num_validators_per_core: 1,
+				code_upgrade: None,
+			});
+
+			// The last bitfield and candidate are given twice.
+			let mut bitfields = scenario.data.bitfields.clone();
+			bitfields.push(bitfields.last().unwrap().clone());
+			let mut backed_candidates = scenario.data.backed_candidates.clone();
+			backed_candidates.push(backed_candidates.last().unwrap().clone());
+
+			let pending_before = inclusion::PendingAvailability::<Test>::iter().count();
+			let simulation = Pallet::<Test>::simulate_inclusion(
+				scenario.data.parent_header.clone(),
+				bitfields,
+				backed_candidates,
+			);
+
+			assert_eq!(
+				simulation.bitfields,
+				vec![
+					Ok(()),
+					Ok(()),
+					Err(inclusion::Error::<Test>::BitfieldDuplicateOrUnordered.into())
+				],
+			);
+			assert_eq!(simulation.candidates.len(), 3);
+			assert_eq!(simulation.candidates[..2], [Ok(()), Ok(())]);
+			assert!(simulation.candidates[2].is_err());
+
+			// Nothing was written.
+			assert_eq!(<scheduler::Pallet<Test>>::scheduled(), vec![]);
+			assert_eq!(inclusion::PendingAvailability::<Test>::iter().count(), pending_before);
+
+			// The block author can include the accepted items.
+			assert_ok!(Pallet::<Test>::enter(
+				frame_system::RawOrigin::None.into(),
+				scenario.data.clone()
+			));
+		});
+	}
+
+	#[test]
+	fn inclusion_diagnostics_report_each_check_without_changing_state() {
+		new_test_ext(MockGenesisConfig::default()).execute_with(|| {
+			let mut backed_and_concluding = BTreeMap::new();
+			backed_and_concluding.insert(0, 1);
+			backed_and_concluding.insert(1, 1);
+
+			let scenario = make_inherent_data(TestConfig {
+				dispute_statements: BTreeMap::new(),
+				dispute_sessions: vec![],
+				backed_and_concluding,
+				num_validators_per_core: 1,
+				code_upgrade: None,
+			});
+
+			// Free the cores, so that the paras of the candidates are scheduled.
+			let mut bitfields_only = scenario.data.clone();
+			bitfields_only.backed_candidates.clear();
+			assert_ok!(Pallet::<Test>::enter(frame_system::RawOrigin::None.into(), bitfields_only));
+			let scheduled = <scheduler::Pallet<Test>>::scheduled();
+			let pending_before = inclusion::PendingAvailability::<Test>::iter().count();
+
+			let backed_candidate = scenario.data.backed_candidates[0].clone();
+			let diagnostics = Pallet::<Test>::inclusion_diagnostics(
+				scenario.data.parent_header.clone(),
+				backed_candidate.clone(),
+			);
+			assert!(diagnostics.collator_signature_ok);
+			assert!(diagnostics.scheduled_core.is_some());
+			assert!(diagnostics.upgrade_allowed);
+			assert_eq!(
+				diagnostics.expected_validation_data_hash,
+				Some(diagnostics.validation_data_hash),
+			);
+			assert!(diagnostics.backing_threshold.is_some());
+			assert_eq!(diagnostics.backing_votes, diagnostics.backing_threshold);
+
+			// A candidate committing to other validation data fails several checks at once.
+			let mut mismatching = backed_candidate;
+			mismatching.candidate.descriptor.persisted_validation_data_hash = Default::default();
+			let diagnostics = Pallet::<Test>::inclusion_diagnostics(
+				scenario.data.parent_header.clone(),
+				mismatching,
+			);
+			assert!(!diagnostics.collator_signature_ok);
+			assert!(diagnostics.scheduled_core.is_some());
+			assert_ne!(
+				diagnostics.expected_validation_data_hash,
+				Some(diagnostics.validation_data_hash),
+			);
+			assert_eq!(diagnostics.backing_votes, None);
+
+			// Nothing was written.
+			assert_eq!(<scheduler::Pallet<Test>>::scheduled(), scheduled);
+			assert_eq!(inclusion::PendingAvailability::<Test>::iter().count(), pending_before);
+		});
+	}
+
 	#[test]
 	fn test_session_is_tracked_in_on_chain_scraping() {
 		use crate::disputes::run_to_block;
@@ -710,8 +820,7 @@ mod enter {
 
 		let multi_dispute_statement_sets_weight =
 			multi_dispute_statement_sets_weight::<Test, _, _>(&inherent_data.disputes);
-		let signed_bitfields_weight =
-			signed_bitfields_weight::<Test>(inherent_data.bitfields.len());
+		let signed_bitfields_weight = signed_bitfields_weight::<Test>(&inherent_data.bitfields);
 		let backed_candidates_weight =
 			backed_candidates_weight::<Test>(&inherent_data.backed_candidates);
 
@@ -878,11 +987,11 @@ mod sanitizers {
 	use bitvec::order::Lsb0;
 	use primitives::{
 		AvailabilityBitfield, GroupIndex, Hash, Id as ParaId, SignedAvailabilityBitfield,
-		ValidatorIndex,
+		SigningContext, ValidatorIndex,
 	};
 	use sp_core::crypto::UncheckedFrom;
 
-	use crate::mock::Test;
+	use crate::mock::{new_test_ext, MockGenesisConfig, Test};
 	use keyring::Sr25519Keyring;
 	use primitives::PARACHAIN_KEY_TYPE_ID;
 	use sc_keystore::LocalKeystore;
@@ -1130,6 +1239,29 @@ mod sanitizers {
 				&unchecked_bitfields[..]
 			);
 		}
+
+		// a single bad signature only drops that bitfield, the rest are kept
+		{
+			let mut unchecked_bitfields = unchecked_bitfields.clone();
+
+			unchecked_bitfields
+				.get_mut(1)
+				.and_then(|u| Some(u.set_signature(UncheckedFrom::unchecked_from([1u8; 64]))))
+				.expect("we are accessing a valid index");
+			let expected = vec![unchecked_bitfields[0].clone(), unchecked_bitfields[2].clone()];
+			assert_eq!(
+				sanitize_bitfields::<Test>(
+					unchecked_bitfields.clone(),
+					disputed_bitfield.clone(),
+					expected_bits,
+					parent_hash,
+					session_index,
+					&validator_public[..],
+					FullCheck::Yes
+				),
+				expected
+			);
+		}
 	}
 
 	#[test]
@@ -1213,65 +1345,106 @@ mod sanitizers {
 			})
 			.collect::<Vec<_>>();
 
-		// happy path
-		assert_eq!(
-			sanitize_backed_candidates::<Test, _>(
-				relay_parent,
-				backed_candidates.clone(),
-				has_concluded_invalid,
-				scheduled
-			),
-			backed_candidates
-		);
-
-		// nothing is scheduled, so no paraids match, thus all backed candidates are skipped
-		{
-			let scheduled = &[][..];
-			assert!(sanitize_backed_candidates::<Test, _>(
+		let sanitize = |backed_candidates, relay_parent, scheduled: &[CoreAssignment]| {
+			sanitize_backed_candidates::<Test, _, _>(
 				relay_parent,
-				backed_candidates.clone(),
+				backed_candidates,
 				has_concluded_invalid,
-				scheduled
+				scheduled,
+				group_validators,
 			)
-			.is_empty());
-		}
+		};
+		let dropped = |candidates: &[BackedCandidate], reason| {
+			candidates.iter().map(|c| (c.hash(), reason)).collect::<Vec<_>>()
+		};
 
-		// relay parent mismatch
-		{
-			let relay_parent = Hash::repeat_byte(0xFA);
-			assert!(sanitize_backed_candidates::<Test, _>(
-				relay_parent,
-				backed_candidates.clone(),
-				has_concluded_invalid,
-				scheduled
-			)
-			.is_empty());
-		}
+		new_test_ext(MockGenesisConfig::default()).execute_with(|| {
+			// happy path
+			assert_eq!(
+				sanitize(backed_candidates.clone(), relay_parent, scheduled),
+				(backed_candidates.clone(), Vec::new())
+			);
 
-		// candidates that have concluded as invalid are filtered out
-		{
-			// mark every second one as concluded invalid
-			let set = {
-				let mut set = std::collections::HashSet::new();
-				for (idx, backed_candidate) in backed_candidates.iter().enumerate() {
-					if idx & 0x01 == 0 {
-						set.insert(backed_candidate.hash().clone());
+			// nothing is scheduled, so no paraids match, thus all backed candidates are skipped
+			{
+				let scheduled = &[][..];
+				assert_eq!(
+					sanitize(backed_candidates.clone(), relay_parent, scheduled),
+					(
+						Vec::new(),
+						dropped(&backed_candidates, BackedCandidateDropReason::NotScheduled)
+					)
+				);
+			}
+
+			// relay parent mismatch
+			{
+				let relay_parent = Hash::repeat_byte(0xFA);
+				assert_eq!(
+					sanitize(backed_candidates.clone(), relay_parent, scheduled),
+					(
+						Vec::new(),
+						dropped(
+							&backed_candidates,
+							BackedCandidateDropReason::RelayParentNotAllowed
+						)
+					)
+				);
+			}
+
+			// candidates that have concluded as invalid are filtered out
+			{
+				// mark every second one as concluded invalid
+				let set = {
+					let mut set = std::collections::HashSet::new();
+					for (idx, backed_candidate) in backed_candidates.iter().enumerate() {
+						if idx & 0x01 == 0 {
+							set.insert(backed_candidate.hash().clone());
+						}
 					}
-				}
-				set
-			};
-			let has_concluded_invalid =
-				|_idx: usize, candidate: &BackedCandidate| set.contains(&candidate.hash());
-			assert_eq!(
-				sanitize_backed_candidates::<Test, _>(
+					set
+				};
+				let has_concluded_invalid =
+					|_idx: usize, candidate: &BackedCandidate| set.contains(&candidate.hash());
+				let (kept, dropped) = sanitize_backed_candidates::<Test, _, _>(
 					relay_parent,
 					backed_candidates.clone(),
 					has_concluded_invalid,
-					scheduled
-				)
-				.len(),
-				backed_candidates.len() / 2
-			);
-		}
+					scheduled,
+					group_validators,
+				);
+				assert_eq!(kept.len(), backed_candidates.len() / 2);
+				assert_eq!(dropped.len(), backed_candidates.len() / 2);
+				let invalid = BackedCandidateDropReason::ConcludedInvalidOrInvalid;
+				assert!(dropped.iter().all(|(candidate_hash, _)| set.contains(candidate_hash)));
+				assert!(dropped.iter().all(|(_, reason)| *reason == invalid));
+			}
+
+			// only the first candidate of each para is kept
+			{
+				let mut duplicated = backed_candidates.clone();
+				duplicated.push(backed_candidates[0].clone());
+				assert_eq!(
+					sanitize(duplicated, relay_parent, scheduled),
+					(
+						backed_candidates.clone(),
+						dropped(&backed_candidates[..1], BackedCandidateDropReason::Duplicate)
+					)
+				);
+			}
+
+			// backing votes that don't fit the size of the group of the core are dropped
+			{
+				let mut mismatched = backed_candidates.clone();
+				mismatched[1].validator_indices.push(false);
+				assert_eq!(
+					sanitize(mismatched.clone(), relay_parent, scheduled),
+					(
+						backed_candidates[..1].to_vec(),
+						dropped(&mismatched[1..], BackedCandidateDropReason::BackingGroupMismatch)
+					)
+				);
+			}
+		});
 	}
 }
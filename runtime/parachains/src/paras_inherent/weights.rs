@@ -13,9 +13,19 @@
 
 // You should have received a copy of the GNU General Public License
 // along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+//! The weights of the paras inherent.
+//!
+//! The benchmarked weights of the individual bitfields, backed candidates and dispute statement
+//! sets include the proof size of the storage they share, such as the validator set, the host
+//! configuration and the candidates pending availability. Their proof size is replaced by their
+//! encoded size, as they are part of the block, and the shared storage reads are accounted once
+//! per block by [`inclusion_reads_weight`].
 use super::{
-	BackedCandidate, Config, DisputeStatementSet, UncheckedSignedAvailabilityBitfield, Weight,
+	inclusion, BackedCandidate, Config, DisputeStatementSet, UncheckedSignedAvailabilityBitfield,
+	Weight,
 };
+use parity_scale_codec::Encode;
+use primitives::ValidityAttestation;
 
 pub trait WeightInfo {
 	/// Variant over `v`, the count of dispute statements in a dispute statement set. This gives the
@@ -28,6 +38,9 @@ pub trait WeightInfo {
 	fn enter_backed_candidates_variable(v: u32) -> Weight;
 	/// The weight of a single backed candidate with a code upgrade.
 	fn enter_backed_candidate_code_upgrade() -> Weight;
+	/// The weight of an inherent without bitfields, backed candidates and disputes, while no
+	/// candidate is pending availability.
+	fn enter_empty() -> Weight;
 }
 
 pub struct TestWeightInfo;
@@ -50,6 +63,9 @@ impl WeightInfo for TestWeightInfo {
 	fn enter_backed_candidate_code_upgrade() -> Weight {
 		Weight::zero()
 	}
+	fn enter_empty() -> Weight {
+		Weight::zero()
+	}
 }
 // To simplify benchmarks running as tests, we set all the weights to 0. `enter` will exit early
 // when if the data causes it to be over weight, but we don't want that to block a benchmark from
@@ -68,6 +84,9 @@ impl WeightInfo for TestWeightInfo {
 	fn enter_backed_candidate_code_upgrade() -> Weight {
 		Weight::zero()
 	}
+	fn enter_empty() -> Weight {
+		Weight::zero()
+	}
 }
 
 pub fn paras_inherent_total_weight<T: Config>(
@@ -75,16 +94,53 @@ pub fn paras_inherent_total_weight<T: Config>(
 	bitfields: &[UncheckedSignedAvailabilityBitfield],
 	disputes: &[DisputeStatementSet],
 ) -> Weight {
-	backed_candidates_weight::<T>(backed_candidates)
-		.saturating_add(signed_bitfields_weight::<T>(bitfields.len()))
+	// every bitfield has one bit per availability core.
+	let n_cores = bitfields.first().map_or(0, |bitfield| bitfield.unchecked_payload().0.len());
+	<<T as Config>::WeightInfo as WeightInfo>::enter_empty()
+		.saturating_add(backed_candidates_weight::<T>(backed_candidates))
+		.saturating_add(signed_bitfields_weight::<T>(bitfields))
 		.saturating_add(multi_dispute_statement_sets_weight::<T, _, _>(disputes))
+		.saturating_add(enactment_weight::<T>(n_cores))
+		.saturating_add(inclusion_reads_weight::<T>(n_cores, backed_candidates.len()))
+}
+
+/// The proof size of the storage read by the inclusion module when processing the bitfields for
+/// `n_cores` availability cores and `n_candidates` backed candidates, such as the validator set,
+/// the host configuration and the candidates pending availability.
+///
+/// It is taken from the benchmarks of the inclusion module, and has no reference time component.
+pub fn inclusion_reads_weight<T: Config>(n_cores: usize, n_candidates: usize) -> Weight {
+	let bitfields =
+		<<T as inclusion::Config>::WeightInfo as inclusion::WeightInfo>::process_bitfields(
+			n_cores as u32,
+		);
+	let candidates =
+		<<T as inclusion::Config>::WeightInfo as inclusion::WeightInfo>::process_candidates(
+			n_candidates as u32,
+		);
+	Weight::from_parts(0, bitfields.proof_size().saturating_add(candidates.proof_size()))
+}
+
+/// Replace the proof size of `weight` by the encoded size of `arg`.
+fn set_proof_size_to_encoded_size<Arg: Encode + ?Sized>(weight: Weight, arg: &Arg) -> Weight {
+	weight.set_proof_size(arg.encoded_size() as u64)
+}
+
+/// The weight of enacting a candidate on each of `n_cores` availability cores.
+///
+/// It is charged up front, and refunded for the candidates that don't become available.
+pub fn enactment_weight<T: Config>(n_cores: usize) -> Weight {
+	<inclusion::Pallet<T>>::max_enactment_weight().saturating_mul(n_cores as u64)
 }
 
 pub fn dispute_statement_set_weight<T: Config, S: AsRef<DisputeStatementSet>>(
 	statement_set: S,
 ) -> Weight {
-	<<T as Config>::WeightInfo as WeightInfo>::enter_variable_disputes(
-		statement_set.as_ref().statements.len() as u32,
+	set_proof_size_to_encoded_size(
+		<<T as Config>::WeightInfo as WeightInfo>::enter_variable_disputes(
+			statement_set.as_ref().statements.len() as u32,
+		),
+		statement_set.as_ref(),
 	)
 }
 
@@ -102,21 +158,41 @@ pub fn multi_dispute_statement_sets_weight<
 		.fold(Weight::zero(), |acc_weight, weight| acc_weight.saturating_add(weight))
 }
 
-pub fn signed_bitfields_weight<T: Config>(bitfields_len: usize) -> Weight {
-	<<T as Config>::WeightInfo as WeightInfo>::enter_bitfields()
-		.saturating_mul(bitfields_len as u64)
+pub fn signed_bitfield_weight<T: Config>(bitfield: &UncheckedSignedAvailabilityBitfield) -> Weight {
+	set_proof_size_to_encoded_size(
+		<<T as Config>::WeightInfo as WeightInfo>::enter_bitfields(),
+		bitfield,
+	)
+}
+
+pub fn signed_bitfields_weight<T: Config>(
+	bitfields: &[UncheckedSignedAvailabilityBitfield],
+) -> Weight {
+	set_proof_size_to_encoded_size(
+		<<T as Config>::WeightInfo as WeightInfo>::enter_bitfields()
+			.saturating_mul(bitfields.len() as u64),
+		bitfields,
+	)
 }
 
 pub fn backed_candidate_weight<T: frame_system::Config + Config>(
 	candidate: &BackedCandidate<T::Hash>,
 ) -> Weight {
-	if candidate.candidate.commitments.new_validation_code.is_some() {
+	let weight = if candidate.candidate.commitments.new_validation_code.is_some() {
 		<<T as Config>::WeightInfo as WeightInfo>::enter_backed_candidate_code_upgrade()
 	} else {
 		<<T as Config>::WeightInfo as WeightInfo>::enter_backed_candidates_variable(
 			candidate.validity_votes.len() as u32,
 		)
-	}
+	};
+	// every implicit vote seconds the candidate, which is recorded to detect equivocations.
+	let seconders = candidate
+		.validity_votes
+		.iter()
+		.filter(|vote| matches!(vote, ValidityAttestation::Implicit(_)))
+		.count();
+	set_proof_size_to_encoded_size(weight, candidate)
+		.saturating_add(<inclusion::Pallet<T>>::note_seconded_weight(seconders))
 }
 
 pub fn backed_candidates_weight<T: frame_system::Config + Config>(
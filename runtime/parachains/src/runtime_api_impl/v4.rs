@@ -60,7 +60,11 @@ pub fn availability_cores<T: initializer::Config>() -> Vec<CoreState<T::Hash, T:
 
 	let rotation_info = <scheduler::Pallet<T>>::group_rotation_info(now);
 
-	let time_out_at = |backed_in_number, availability_period| {
+	let time_out_at = |backed_in_number, availability_period, core_index| {
+		// governance may have extended the availability timeout of the core.
+		let availability_period =
+			<scheduler::Pallet<T>>::availability_timeout_extension(core_index) +
+				availability_period;
 		let time_out_at = backed_in_number + availability_period;
 
 		let current_window = rotation_info.last_rotation_at() + availability_period;
@@ -105,7 +109,7 @@ pub fn availability_cores<T: initializer::Config>() -> Vec<CoreState<T::Hash, T:
 				CoreOccupied::Parachain => {
 					let para_id = parachains[i];
 					let pending_availability =
-						<inclusion::Pallet<T>>::pending_availability(para_id)
+						<inclusion::Pallet<T>>::pending_availability(para_id, CoreIndex(i as u32))
 							.expect("Occupied core always has pending availability; qed");
 
 					let backed_in_number = *pending_availability.backed_in_number();
@@ -116,12 +120,13 @@ pub fn availability_cores<T: initializer::Config>() -> Vec<CoreState<T::Hash, T:
 						occupied_since: backed_in_number,
 						time_out_at: time_out_at(
 							backed_in_number,
-							config.chain_availability_period,
+							config.chain_availability_period_of(para_id),
+							CoreIndex(i as u32),
 						),
 						next_up_on_time_out: <scheduler::Pallet<T>>::next_up_on_time_out(
 							CoreIndex(i as u32),
 						),
-						availability: pending_availability.availability_votes().clone(),
+						availability: pending_availability.availability_votes().to_bitvec(),
 						group_responsible: group_responsible_for(
 							backed_in_number,
 							pending_availability.core_occupied(),
@@ -133,7 +138,7 @@ pub fn availability_cores<T: initializer::Config>() -> Vec<CoreState<T::Hash, T:
 				CoreOccupied::Parathread(p) => {
 					let para_id = p.claim.0;
 					let pending_availability =
-						<inclusion::Pallet<T>>::pending_availability(para_id)
+						<inclusion::Pallet<T>>::pending_availability(para_id, CoreIndex(i as u32))
 							.expect("Occupied core always has pending availability; qed");
 
 					let backed_in_number = *pending_availability.backed_in_number();
@@ -144,12 +149,13 @@ pub fn availability_cores<T: initializer::Config>() -> Vec<CoreState<T::Hash, T:
 						occupied_since: backed_in_number,
 						time_out_at: time_out_at(
 							backed_in_number,
-							config.thread_availability_period,
+							config.thread_availability_period_of(para_id),
+							CoreIndex(i as u32),
 						),
 						next_up_on_time_out: <scheduler::Pallet<T>>::next_up_on_time_out(
 							CoreIndex(i as u32),
 						),
-						availability: pending_availability.availability_votes().clone(),
+						availability: pending_availability.availability_votes().to_bitvec(),
 						group_responsible: group_responsible_for(
 							backed_in_number,
 							pending_availability.core_occupied(),
@@ -201,7 +207,7 @@ where
 		},
 		OccupiedCoreAssumption::TimedOut => build(),
 		OccupiedCoreAssumption::Free => {
-			if <inclusion::Pallet<Config>>::pending_availability(para_id).is_some() {
+			if <inclusion::Pallet<Config>>::is_pending_availability(para_id) {
 				None
 			} else {
 				build()
@@ -245,10 +251,11 @@ pub fn assumed_validation_data<T: initializer::Config>(
 	let persisted_validation_data = make_validation_data().or_else(|| {
 		// Try again with force enacting the core. This check only makes sense if
 		// the core is occupied.
-		<inclusion::Pallet<T>>::pending_availability(para_id).and_then(|_| {
-			<inclusion::Pallet<T>>::force_enact(para_id);
-			make_validation_data()
-		})
+		if !<inclusion::Pallet<T>>::is_pending_availability(para_id) {
+			return None
+		}
+		<inclusion::Pallet<T>>::force_enact(para_id);
+		make_validation_data()
 	});
 	// If we were successful, also query current validation code hash.
 	persisted_validation_data.zip(<paras::Pallet<T>>::current_code_hash(&para_id))
@@ -334,9 +341,9 @@ where
 		.map(|event| match event {
 			RawEvent::<T>::CandidateBacked(c, h, core, group) =>
 				CandidateEvent::CandidateBacked(c, h, core, group),
-			RawEvent::<T>::CandidateIncluded(c, h, core, group) =>
+			RawEvent::<T>::CandidateIncluded(c, h, core, group, ..) =>
 				CandidateEvent::CandidateIncluded(c, h, core, group),
-			RawEvent::<T>::CandidateTimedOut(c, h, core) =>
+			RawEvent::<T>::CandidateTimedOut(c, h, core, _, _) =>
 				CandidateEvent::CandidateTimedOut(c, h, core),
 			RawEvent::<T>::__Ignore(_, _) => unreachable!("__Ignore cannot be used"),
 		})
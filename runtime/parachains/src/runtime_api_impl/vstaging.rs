@@ -15,3 +15,197 @@
 // along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
 
 //! Put implementations of functions from staging APIs here.
+
+use crate::{
+	configuration, disputes, inclusion, initializer, paras, paras_inherent, runtime_api_impl::v4,
+	scheduler, shared, statistics,
+};
+use primitives::{
+	vstaging::{
+		slashing, ApiFeatures, CoreAssignmentSource, CoreAvailabilityVotes, CoreBitfieldLayout,
+		HostConfigurationInfo, IncludedCandidate, InclusionDiagnostics, InclusionSimulation,
+		ParaLifecycle, SessionCandidateStatistics, SessionCollatorHeadCounts,
+	},
+	BackedCandidate, CandidateHash, CommittedCandidateReceipt, CoreIndex, GroupIndex, Hash,
+	HeadData, Id as ParaId, OccupiedCoreAssumption, PersistedValidationData, SessionIndex,
+	UncheckedSignedAvailabilityBitfields,
+};
+use sp_runtime::traits::One;
+use sp_std::{collections::btree_map::BTreeMap, prelude::*};
+
+/// Implementation for the `included_candidates` function of the runtime API.
+pub fn included_candidates<T: inclusion::Config>(
+	block_number: T::BlockNumber,
+) -> Vec<IncludedCandidate<T::Hash>> {
+	<inclusion::Pallet<T>>::included_candidates(block_number)
+}
+
+/// Implementation for the `persisted_validation_data_with_relay_state` function of the runtime API.
+pub fn persisted_validation_data_with_relay_state<T: initializer::Config>(
+	para_id: ParaId,
+	assumption: OccupiedCoreAssumption,
+) -> Option<(PersistedValidationData<T::Hash, T::BlockNumber>, Vec<Vec<u8>>)> {
+	let keys = crate::util::relay_state_proof_keys::<T>(para_id);
+	// Read all keys before applying the assumption, which may modify the state, so that a proof
+	// recorded while executing this call covers them as of the relay parent.
+	for key in &keys {
+		let _ = sp_io::storage::get(key);
+	}
+
+	v4::persisted_validation_data::<T>(para_id, assumption).map(|data| (data, keys))
+}
+
+/// Implementation for the `para_lifecycle` function of the runtime API.
+pub fn para_lifecycle<T: paras::Config>(para_id: ParaId) -> Option<ParaLifecycle> {
+	<paras::Pallet<T>>::lifecycle(para_id)
+}
+
+/// Implementation for the `candidate_statistics` function of the runtime API.
+pub fn candidate_statistics<T: statistics::Config>() -> SessionCandidateStatistics {
+	<statistics::Pallet<T>>::candidate_statistics()
+}
+
+/// Implementation for the `collator_head_counts` function of the runtime API.
+pub fn collator_head_counts<T: statistics::Config>() -> SessionCollatorHeadCounts {
+	<statistics::Pallet<T>>::collator_head_counts()
+}
+
+/// Implementation for the `candidates_pending_availability` function of the runtime API.
+pub fn candidates_pending_availability<T: inclusion::Config>(
+) -> Vec<CommittedCandidateReceipt<T::Hash>> {
+	<inclusion::Pallet<T>>::candidates_pending_availability()
+}
+
+/// Implementation for the `group_responsibilities` function of the runtime API.
+pub fn group_responsibilities<T: initializer::Config>() -> Vec<(CoreIndex, GroupIndex)> {
+	let now = <frame_system::Pallet<T>>::block_number() + One::one();
+	let n_cores = <scheduler::Pallet<T>>::availability_cores().len() as u32;
+
+	(0..n_cores)
+		.map(CoreIndex)
+		.filter_map(|core| {
+			<scheduler::Pallet<T>>::group_assigned_to_core(core, now).map(|group| (core, group))
+		})
+		.collect()
+}
+
+/// Implementation for the `past_para_heads` function of the runtime API.
+pub fn past_para_heads<T: paras::Config>(para_id: ParaId) -> Vec<(T::BlockNumber, HeadData)> {
+	<paras::Pallet<T>>::past_heads(para_id)
+}
+
+/// Implementation for the `host_configuration` function of the runtime API.
+pub fn host_configuration<T: configuration::Config>() -> HostConfigurationInfo {
+	let next_session = <shared::Pallet<T>>::session_index() + 1;
+	HostConfigurationInfo {
+		active: <configuration::Pallet<T>>::config().abridged(),
+		active_since: <configuration::Pallet<T>>::active_config_session(),
+		next_session: <configuration::Pallet<T>>::pending_config(next_session)
+			.map(|config| config.abridged()),
+	}
+}
+
+/// Implementation for the `candidate_included_at` function of the runtime API.
+pub fn candidate_included_at<T: inclusion::Config>(
+	candidate_hash: CandidateHash,
+) -> Option<(T::BlockNumber, CoreIndex)> {
+	<inclusion::Pallet<T>>::candidate_included_at(&candidate_hash)
+}
+
+/// Implementation for the `availability_votes` function of the runtime API.
+pub fn availability_votes<T: inclusion::Config>(core: CoreIndex) -> Option<CoreAvailabilityVotes> {
+	<inclusion::Pallet<T>>::availability_votes(core)
+}
+
+/// Implementation for the `claim_queue` function of the runtime API.
+pub fn claim_queue<T: scheduler::Config>() -> BTreeMap<CoreIndex, Vec<ParaId>> {
+	<scheduler::Pallet<T>>::claim_queue()
+}
+
+/// Implementation for the `parathread_starvation` function of the runtime API.
+pub fn parathread_starvation<T: scheduler::Config>() -> Vec<(ParaId, T::BlockNumber)> {
+	<scheduler::Pallet<T>>::parathread_starvation()
+}
+
+/// Implementation for the `storage_inconsistencies_repaired` function of the runtime API.
+pub fn storage_inconsistencies_repaired<T: inclusion::Config>() -> u32 {
+	<inclusion::Pallet<T>>::storage_inconsistencies_repaired()
+}
+
+/// Implementation for the `simulate_inclusion` function of the runtime API.
+pub fn simulate_inclusion<T: paras_inherent::Config>(
+	parent_header: T::Header,
+	bitfields: UncheckedSignedAvailabilityBitfields,
+	backed_candidates: Vec<BackedCandidate<T::Hash>>,
+) -> InclusionSimulation {
+	<paras_inherent::Pallet<T>>::simulate_inclusion(parent_header, bitfields, backed_candidates)
+}
+
+/// Implementation for the `inclusion_diagnostics` function of the runtime API.
+pub fn inclusion_diagnostics<T: paras_inherent::Config>(
+	parent_header: T::Header,
+	backed_candidate: BackedCandidate<T::Hash>,
+) -> InclusionDiagnostics {
+	<paras_inherent::Pallet<T>>::inclusion_diagnostics(parent_header, backed_candidate)
+}
+
+/// Implementation for the `api_features` function of the runtime API.
+pub fn api_features<T: configuration::Config>() -> ApiFeatures {
+	let features = ApiFeatures::DISPUTES
+		.with(ApiFeatures::SESSION_INFO)
+		.with(ApiFeatures::EXECUTOR_PARAMS);
+	if <configuration::Pallet<T>>::config().max_candidate_relay_parent_depth > 0 {
+		features.with(ApiFeatures::ASYNC_BACKING)
+	} else {
+		features
+	}
+}
+
+/// Implementation for the `relay_storage_roots` function of the runtime API.
+pub fn relay_storage_roots<T: shared::Config>() -> Vec<(T::BlockNumber, T::Hash)> {
+	<shared::Pallet<T>>::relay_storage_roots()
+}
+
+/// Implementation for the `unapplied_slashes` function of the runtime API.
+pub fn unapplied_slashes<T: disputes::slashing::Config>(
+) -> Vec<(SessionIndex, CandidateHash, slashing::PendingSlashes)> {
+	<disputes::slashing::Pallet<T>>::unapplied_slashes()
+}
+
+/// Implementation for the `submit_report_dispute_lost` function of the runtime API.
+pub fn submit_unsigned_slashing_report<T: disputes::slashing::Config>(
+	dispute_proof: slashing::DisputeProof,
+	key_ownership_proof: T::KeyOwnerProof,
+) -> Option<()> {
+	<disputes::slashing::Pallet<T>>::submit_unsigned_slashing_report(
+		dispute_proof,
+		key_ownership_proof,
+	)
+}
+
+/// Implementation for the `core_assignment_sources` function of the runtime API.
+pub fn core_assignment_sources<T: inclusion::Config>() -> Vec<(CoreIndex, CoreAssignmentSource)> {
+	<inclusion::Pallet<T>>::core_assignment_sources()
+}
+
+/// Implementation for the `core_bitfield_layout_version` function of the runtime API.
+pub fn core_bitfield_layout_version() -> u32 {
+	CoreBitfieldLayout::VERSION
+}
+
+/// Implementation for the `bitfield_core_layout_hash` function of the runtime API.
+pub fn bitfield_core_layout_hash<T: inclusion::Config>() -> Option<Hash> {
+	<inclusion::Pallet<T>>::next_bitfield_core_layout_hash()
+}
+
+/// Implementation for the `minimum_backing_votes` function of the runtime API.
+pub fn minimum_backing_votes<T: configuration::Config>(group_size: u32) -> u32 {
+	let configured_minimum = <configuration::Pallet<T>>::config().minimum_backing_votes;
+	inclusion::minimum_backing_votes(group_size as usize, configured_minimum) as u32
+}
+
+/// Implementation for the `availability_threshold` function of the runtime API.
+pub fn availability_threshold<T: configuration::Config>(n_validators: u32) -> u32 {
+	let config = <configuration::Pallet<T>>::config();
+	inclusion::availability_threshold(&config, n_validators as usize) as u32
+}
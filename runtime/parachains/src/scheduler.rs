@@ -35,22 +35,69 @@
 //! number of groups as availability cores. Validator groups will be assigned to different availability cores
 //! over time.
 
-use frame_support::pallet_prelude::*;
+use frame_support::{
+	pallet_prelude::*,
+	traits::{Currency, ExistenceRequirement, OnUnbalanced, ReservableCurrency, WithdrawReasons},
+};
+use frame_system::pallet_prelude::*;
 use primitives::{
-	CollatorId, CoreIndex, CoreOccupied, GroupIndex, GroupRotationInfo, Id as ParaId,
-	ParathreadClaim, ParathreadEntry, ScheduledCore, ValidatorIndex,
+	vstaging::CoreAssignmentSource, Balance, CollatorId, CoreIndex, CoreOccupied, GroupIndex,
+	GroupRotationInfo, Id as ParaId, ParathreadClaim, ParathreadEntry, ScheduledCore,
+	ValidatorIndex,
 };
 use scale_info::TypeInfo;
-use sp_runtime::traits::{One, Saturating};
-use sp_std::prelude::*;
+use sp_runtime::{
+	traits::{One, Saturating, UniqueSaturatedInto, Zero},
+	Perbill,
+};
+use sp_std::{collections::btree_map::BTreeMap, prelude::*};
 
-use crate::{configuration, initializer::SessionChangeNotification, paras};
+use crate::{configuration, initializer::SessionChangeNotification, paras, statistics};
 
 pub use pallet::*;
 
 #[cfg(test)]
 mod tests;
 
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+
+pub trait WeightInfo {
+	fn place_parathread_claim() -> Weight;
+	fn place_parathread_claim_with_collators(c: u32) -> Weight;
+	fn place_parathread_bid(c: u32) -> Weight;
+	fn place_order(c: u32) -> Weight;
+}
+
+/// A weight info that is only suitable for testing.
+pub struct TestWeightInfo;
+
+impl WeightInfo for TestWeightInfo {
+	fn place_parathread_claim() -> Weight {
+		Weight::MAX
+	}
+	fn place_parathread_claim_with_collators(_: u32) -> Weight {
+		Weight::MAX
+	}
+	fn place_parathread_bid(_: u32) -> Weight {
+		Weight::MAX
+	}
+	fn place_order(_: u32) -> Weight {
+		Weight::MAX
+	}
+}
+
+/// The maximum number of collators a parathread claim may allow.
+pub const MAX_PARATHREAD_CLAIM_COLLATORS: u32 = 8;
+
+/// The collators a parathread claim allows, bounded by [`MAX_PARATHREAD_CLAIM_COLLATORS`].
+pub type ParathreadClaimCollatorsOf =
+	BoundedVec<CollatorId, ConstU32<MAX_PARATHREAD_CLAIM_COLLATORS>>;
+
+type NegativeImbalanceOf<T> = <<T as Config>::Currency as Currency<
+	<T as frame_system::Config>::AccountId,
+>>::NegativeImbalance;
+
 /// A queued parathread entry, pre-assigned to a core.
 #[derive(Encode, Decode, TypeInfo)]
 #[cfg_attr(test, derive(PartialEq, Debug))]
@@ -71,24 +118,90 @@ pub struct ParathreadClaimQueue {
 impl ParathreadClaimQueue {
 	/// Queue a parathread entry to be processed.
 	///
-	/// Provide the entry and the number of parathread cores, which must be greater than 0.
-	fn enqueue_entry(&mut self, entry: ParathreadEntry, n_parathread_cores: u32) {
-		let core_offset = self.next_core_offset;
-		self.next_core_offset = (self.next_core_offset + 1) % n_parathread_cores;
-
+	/// Provide the entry, the number of parathread cores, which must be greater than 0, and which
+	/// core offsets the entry may be queued on.
+	fn enqueue_entry(
+		&mut self,
+		entry: ParathreadEntry,
+		n_parathread_cores: u32,
+		core_allowed: impl Fn(u32) -> bool,
+	) {
+		let core_offset = self.next_allowed_core_offset(n_parathread_cores, core_allowed);
 		self.queue.push(QueuedParathread { claim: entry, core_offset })
 	}
 
+	/// Take the next core offset in turn which is allowed, or just the next one if none is.
+	fn next_allowed_core_offset(
+		&mut self,
+		n_parathread_cores: u32,
+		core_allowed: impl Fn(u32) -> bool,
+	) -> u32 {
+		let core_offset = (0..n_parathread_cores)
+			.map(|i| (self.next_core_offset + i) % n_parathread_cores)
+			.find(|core_offset| core_allowed(*core_offset))
+			.unwrap_or(self.next_core_offset);
+		self.next_core_offset = (core_offset + 1) % n_parathread_cores;
+
+		core_offset
+	}
+
+	/// Get the position of the next queued entry with given core offset, if any.
+	///
+	/// That is the entry with the highest `bid`, or the earliest queued one among equal bids.
+	fn next_on_core(
+		&self,
+		core_offset: u32,
+		bid: impl Fn(&ParathreadEntry) -> Balance,
+	) -> Option<usize> {
+		let mut next: Option<(usize, Balance)> = None;
+		for (i, queued) in self.queue.iter().enumerate() {
+			if queued.core_offset != core_offset {
+				continue
+			}
+
+			let queued_bid = bid(&queued.claim);
+			if next.map_or(true, |(_, next_bid)| queued_bid > next_bid) {
+				next = Some((i, queued_bid));
+			}
+		}
+
+		next.map(|(i, _)| i)
+	}
+
 	/// Take next queued entry with given core offset, if any.
-	fn take_next_on_core(&mut self, core_offset: u32) -> Option<ParathreadEntry> {
-		let pos = self.queue.iter().position(|queued| queued.core_offset == core_offset);
-		pos.map(|i| self.queue.remove(i).claim)
+	fn take_next_on_core(
+		&mut self,
+		core_offset: u32,
+		bid: impl Fn(&ParathreadEntry) -> Balance,
+	) -> Option<ParathreadEntry> {
+		self.next_on_core(core_offset, bid).map(|i| self.queue.remove(i).claim)
 	}
 
 	/// Get the next queued entry with given core offset, if any.
-	fn get_next_on_core(&self, core_offset: u32) -> Option<&ParathreadEntry> {
-		let pos = self.queue.iter().position(|queued| queued.core_offset == core_offset);
-		pos.map(|i| &self.queue[i].claim)
+	fn get_next_on_core(
+		&self,
+		core_offset: u32,
+		bid: impl Fn(&ParathreadEntry) -> Balance,
+	) -> Option<&ParathreadEntry> {
+		self.next_on_core(core_offset, bid).map(|i| &self.queue[i].claim)
+	}
+
+	/// Get all queued entries with given core offset, in the order they will be taken.
+	fn ordered_on_core(
+		&self,
+		core_offset: u32,
+		bid: impl Fn(&ParathreadEntry) -> Balance,
+	) -> Vec<&ParathreadEntry> {
+		let mut on_core: Vec<_> = self
+			.queue
+			.iter()
+			.filter(|queued| queued.core_offset == core_offset)
+			.map(|queued| (bid(&queued.claim), &queued.claim))
+			.collect();
+
+		// the sort is stable, so the earliest queued entry comes first among equal bids.
+		on_core.sort_by(|(a, _), (b, _)| b.cmp(a));
+		on_core.into_iter().map(|(_, entry)| entry).collect()
 	}
 }
 
@@ -99,7 +212,7 @@ impl Default for ParathreadClaimQueue {
 }
 
 /// Reasons a core might be freed
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo)]
 pub enum FreedReason {
 	/// The core's work concluded and the parablock assigned to it is considered available.
 	Concluded,
@@ -162,7 +275,77 @@ pub mod pallet {
 	pub struct Pallet<T>(_);
 
 	#[pallet::config]
-	pub trait Config: frame_system::Config + configuration::Config + paras::Config {}
+	pub trait Config:
+		frame_system::Config + configuration::Config + paras::Config + statistics::Config
+	{
+		/// The outer event type.
+		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
+		/// An interface for reserving deposits for parathread claims.
+		///
+		/// NOTE that this Currency instance will be charged with the amounts defined in the
+		/// `Configuration` pallet. Specifically, that means that the `Balance` of the `Currency`
+		/// implementation should be the same as `Balance` as used in the `Configuration`.
+		type Currency: ReservableCurrency<Self::AccountId>;
+
+		/// The handler for the bids of parathread claims once they were served, e.g. the treasury.
+		type OnBidSettled: OnUnbalanced<NegativeImbalanceOf<Self>>;
+
+		/// The handler for the slashed deposits of expired parathread claims, e.g. the treasury.
+		type OnClaimSlashed: OnUnbalanced<NegativeImbalanceOf<Self>>;
+
+		/// The handler for the fees paid for on-demand orders, e.g. the treasury.
+		type OnDemandFees: OnUnbalanced<NegativeImbalanceOf<Self>>;
+
+		/// Weight information for extrinsics in this pallet.
+		type WeightInfo: WeightInfo;
+	}
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// A parathread claim was placed and the deposit reserved. `[para_id, who, deposit]`
+		ParathreadClaimPlaced(ParaId, T::AccountId, Balance),
+		/// A parathread claim left the queue and its deposit was returned. `[para_id, who]`
+		ParathreadClaimReleased(ParaId, T::AccountId),
+		/// An occupied core was freed and can be scheduled again. `[core, reason]`
+		CoreFreed(CoreIndex, FreedReason),
+		/// A parathread claim was placed with a bid, which was reserved. `[para_id, who, bid]`
+		ParathreadBidPlaced(ParaId, T::AccountId, Balance),
+		/// The candidate of a parathread claim was included and its bid was paid. `[para_id, who, bid]`
+		ParathreadBidSettled(ParaId, T::AccountId, Balance),
+		/// A parathread claim ran out of retries without its candidate being included, and a part
+		/// of its deposit was slashed. `[para_id, who, slashed]`
+		ClaimExpired(ParaId, T::AccountId, Balance),
+		/// An on-demand order was placed for a parathread and its spot price paid.
+		/// `[para_id, who, price]`
+		OnDemandOrderPlaced(ParaId, T::AccountId, Balance),
+		/// The availability timeout of an occupied core was extended by governance until the given
+		/// block. `[core, extension, expires_at]`
+		AvailabilityTimeoutExtended(CoreIndex, T::BlockNumber, T::BlockNumber),
+		/// The extension of the availability timeout of a core expired. `[core]`
+		AvailabilityTimeoutExtensionExpired(CoreIndex),
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The para is not a live parathread.
+		NotParathread,
+		/// The parathread claim queue is full.
+		ParathreadQueueFull,
+		/// There is already a claim for this parathread in the queue or on a core.
+		ParathreadClaimExists,
+		/// A parathread claim must allow at least one collator.
+		NoCollators,
+		/// The core affinity of the parathread does not allow any of the parathread cores.
+		NoAllowedCore,
+		/// The spot price of an on-demand order is higher than the maximum amount given.
+		SpotPriceHigherThanMaxAmount,
+		/// The parathread is frozen by governance.
+		ParaFrozen,
+		/// An availability timeout extension must extend the timeout for at least one block.
+		InvalidAvailabilityTimeoutExtension,
+	}
 
 	/// All the validator groups. One for each core. Indices are into `ActiveValidators` - not the
 	/// broader set of Polkadot validators, but instead just the subset used for parachains during
@@ -220,12 +403,203 @@ pub mod pallet {
 	#[pallet::getter(fn scheduled)]
 	pub(crate) type Scheduled<T> = StorageValue<_, Vec<CoreAssignment>, ValueQuery>;
 	// sorted ascending by CoreIndex.
+
+	/// The account and the amount reserved for each parathread claim that was placed via
+	/// `place_parathread_claim`.
+	///
+	/// Invariant: every entry here has a corresponding entry in `ParathreadClaimIndex`.
+	#[pallet::storage]
+	pub(crate) type ParathreadClaimDeposits<T: Config> =
+		StorageMap<_, Twox64Concat, ParaId, (T::AccountId, Balance)>;
+
+	/// The collators allowed to author the block of a parathread claim, besides the collator in
+	/// the claim itself.
+	///
+	/// Invariant: every entry here has a corresponding entry in `ParathreadClaimIndex`.
+	#[pallet::storage]
+	pub(crate) type ParathreadClaimCollators<T: Config> =
+		StorageMap<_, Twox64Concat, ParaId, ParathreadClaimCollatorsOf>;
+
+	/// The paras whose claim was placed as an on-demand order via `place_order`.
+	///
+	/// Invariant: every entry here has a corresponding entry in `ParathreadClaimIndex`.
+	#[pallet::storage]
+	pub(crate) type OnDemandOrders<T: Config> = StorageMap<_, Twox64Concat, ParaId, ()>;
+
+	/// The version of the layout of the availability cores, which is bumped whenever the number of
+	/// cores changes at a session boundary.
+	#[pallet::storage]
+	#[pallet::getter(fn core_layout_version)]
+	pub(crate) type CoreLayoutVersion<T> = StorageValue<_, u32, ValueQuery>;
+
+	/// The number of availability cores before the last change of the core layout.
+	#[pallet::storage]
+	pub(crate) type PreviousCoreCount<T> = StorageValue<_, u32, ValueQuery>;
+
+	/// The bid reserved for each parathread claim that was placed via `place_parathread_claim` or
+	/// `place_parathread_bid`, along with the block number it was placed at.
+	///
+	/// Invariant: every entry here has a corresponding entry in `ParathreadClaimDeposits`.
+	#[pallet::storage]
+	pub(crate) type ParathreadClaimBids<T: Config> =
+		StorageMap<_, Twox64Concat, ParaId, (Balance, T::BlockNumber)>;
+
+	/// The paras that are going to be scheduled on each core for the next `scheduling_lookahead`
+	/// times the core is free, starting with the para currently scheduled on it.
+	///
+	/// Parachain cores repeat their parachain. Parathread cores list the scheduled claim followed
+	/// by the queued claims in the order they are taken. Cores without upcoming claims are left
+	/// out. Updated whenever cores are scheduled or occupied.
+	///
+	/// Bounded by the number of cores times `scheduling_lookahead`.
+	#[pallet::storage]
+	#[pallet::getter(fn claim_queue)]
+	pub(crate) type ClaimQueue<T> = StorageValue<_, BTreeMap<CoreIndex, Vec<ParaId>>, ValueQuery>;
+
+	/// The block each parathread has been waiting since for one of its candidates to be included,
+	/// which is when its first claim after its last included candidate was placed.
+	///
+	/// Removed once a candidate of the parathread is included, or it stops being a parathread.
+	#[pallet::storage]
+	pub(crate) type ParathreadWaitingSince<T: Config> =
+		StorageMap<_, Twox64Concat, ParaId, T::BlockNumber>;
+
+	/// The number of blocks the availability timeout of each core is extended by, along with the
+	/// block the extension expires at.
+	///
+	/// Set by governance through `extend_availability_timeouts`, and removed once expired or at the
+	/// next session change. Bounded by the number of cores.
+	#[pallet::storage]
+	pub(crate) type AvailabilityTimeoutExtensions<T: Config> =
+		StorageMap<_, Twox64Concat, CoreIndex, (T::BlockNumber, T::BlockNumber)>;
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Place a claim for the given parathread to be scheduled with the given collator.
+		///
+		/// The origin must provide `parathread_claim_deposit`, which is held until the claim
+		/// either gets included, runs out of retries or the para stops being a parathread.
+		#[pallet::call_index(0)]
+		#[pallet::weight(<T as Config>::WeightInfo::place_parathread_claim())]
+		pub fn place_parathread_claim(
+			origin: OriginFor<T>,
+			para_id: ParaId,
+			collator: CollatorId,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let collators = BoundedVec::truncate_from(vec![collator]);
+			Self::do_place_parathread_claim(who, para_id, collators, 0)
+		}
+
+		/// Place a claim for the given parathread, which any of the given collators may author.
+		///
+		/// Allows parathreads to run redundant collators. Otherwise the same as
+		/// `place_parathread_claim`, which is the first collator in the list.
+		#[pallet::call_index(1)]
+		#[pallet::weight(<T as Config>::WeightInfo::place_parathread_claim_with_collators(
+			collators.len() as u32,
+		))]
+		pub fn place_parathread_claim_with_collators(
+			origin: OriginFor<T>,
+			para_id: ParaId,
+			collators: ParathreadClaimCollatorsOf,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::do_place_parathread_claim(who, para_id, collators, 0)
+		}
+
+		/// Place a claim for the given parathread, which any of the given collators may author,
+		/// along with a bid for the parathread core time.
+		///
+		/// Claims queued on the same parathread core are scheduled by their bid, which grows by
+		/// `parathread_bid_aging_bonus` for every block the claim waits. The bid is reserved along
+		/// with `parathread_claim_deposit`, and paid once the candidate of the claim is included.
+		#[pallet::call_index(2)]
+		#[pallet::weight(<T as Config>::WeightInfo::place_parathread_bid(collators.len() as u32))]
+		pub fn place_parathread_bid(
+			origin: OriginFor<T>,
+			para_id: ParaId,
+			collators: ParathreadClaimCollatorsOf,
+			bid: Balance,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::do_place_parathread_claim(who, para_id, collators, bid)
+		}
+
+		/// Order a single slot of on-demand core time for the given parathread, which any of the
+		/// given collators may author.
+		///
+		/// The origin pays the current spot price, see [`Pallet::spot_price`], as long as it is at
+		/// most `max_amount`. Unlike the other claims, no deposit is held and the fee is not
+		/// returned if the candidate of the claim is never included.
+		#[pallet::call_index(3)]
+		#[pallet::weight(<T as Config>::WeightInfo::place_order(collators.len() as u32))]
+		pub fn place_order(
+			origin: OriginFor<T>,
+			para_id: ParaId,
+			collators: ParathreadClaimCollatorsOf,
+			max_amount: Balance,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::do_place_order(who, para_id, collators, max_amount)
+		}
+
+		/// Extend the availability timeout of every currently occupied core by `extension` blocks,
+		/// for the next `duration` blocks.
+		///
+		/// Meant for incidents that keep candidates from becoming available in time, such as an
+		/// outage of availability distribution. The extension applies to any candidate pending
+		/// availability on the cores until it expires, and replaces earlier extensions of them.
+		#[pallet::call_index(4)]
+		#[pallet::weight((
+			T::DbWeight::get().reads_writes(
+				2,
+				AvailabilityCores::<T>::decode_len().unwrap_or(0) as u64,
+			),
+			DispatchClass::Operational,
+		))]
+		pub fn extend_availability_timeouts(
+			origin: OriginFor<T>,
+			extension: T::BlockNumber,
+			duration: T::BlockNumber,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			ensure!(
+				!extension.is_zero() && !duration.is_zero(),
+				Error::<T>::InvalidAvailabilityTimeoutExtension,
+			);
+
+			let expires_at = <frame_system::Pallet<T>>::block_number().saturating_add(duration);
+			for (i, core) in AvailabilityCores::<T>::get().iter().enumerate() {
+				if core.is_some() {
+					let core = CoreIndex(i as u32);
+					AvailabilityTimeoutExtensions::<T>::insert(core, (extension, expires_at));
+					Self::deposit_event(Event::AvailabilityTimeoutExtended(
+						core, extension, expires_at,
+					));
+				}
+			}
+
+			Ok(())
+		}
+	}
 }
 
 impl<T: Config> Pallet<T> {
 	/// Called by the initializer to initialize the scheduler pallet.
-	pub(crate) fn initializer_initialize(_now: T::BlockNumber) -> Weight {
-		Weight::zero()
+	pub(crate) fn initializer_initialize(now: T::BlockNumber) -> Weight {
+		let mut extensions = 0;
+		let expired = AvailabilityTimeoutExtensions::<T>::iter()
+			.inspect(|_| extensions += 1)
+			.filter(|(_, (_, expires_at))| *expires_at <= now)
+			.map(|(core, _)| core)
+			.collect::<Vec<_>>();
+		for core in &expired {
+			AvailabilityTimeoutExtensions::<T>::remove(core);
+			Self::deposit_event(Event::AvailabilityTimeoutExtensionExpired(*core));
+		}
+
+		T::DbWeight::get().reads_writes(extensions + 1, expired.len() as u64)
 	}
 
 	/// Called by the initializer to finalize the scheduler pallet.
@@ -261,9 +635,19 @@ impl<T: Config> Pallet<T> {
 				}
 			}
 
+			if cores.len() != n_cores as usize {
+				PreviousCoreCount::<T>::set(cores.len() as u32);
+				CoreLayoutVersion::<T>::mutate(|version| *version = version.wrapping_add(1));
+			}
+
 			cores.resize(n_cores as _, None);
 		});
 
+		// all cores are freed, so are the extensions of their availability timeouts.
+		for (core, _) in AvailabilityTimeoutExtensions::<T>::drain() {
+			Self::deposit_event(Event::AvailabilityTimeoutExtensionExpired(core));
+		}
+
 		// shuffle validators into groups.
 		if n_cores == 0 || validators.is_empty() {
 			ValidatorGroups::<T>::set(Vec::new());
@@ -304,7 +688,9 @@ impl<T: Config> Pallet<T> {
 			// wipe all parathread metadata if no parathread cores are configured.
 			if config.parathread_cores == 0 {
 				thread_queue = ParathreadClaimQueue { queue: Vec::new(), next_core_offset: 0 };
-				claim_index.clear();
+				for para_id in claim_index.drain(..) {
+					Self::release_claim(para_id);
+				}
 				return
 			}
 
@@ -320,23 +706,36 @@ impl<T: Config> Pallet<T> {
 					if let Ok(i) = claim_index.binary_search(&claim_para) {
 						claim_index.remove(i);
 					}
+					Self::release_claim(claim_para);
 				}
 
 				will_keep
 			});
 
-			// do re-balancing of claims.
+			// do re-balancing of claims, respecting the core affinity of each parathread.
 			{
-				for (i, queued) in thread_queue.queue.iter_mut().enumerate() {
-					queued.core_offset = (i as u32) % config.parathread_cores;
+				let mut queue = sp_std::mem::take(&mut thread_queue.queue);
+				thread_queue.next_core_offset = 0;
+				for queued in queue.iter_mut() {
+					let para_id = queued.claim.claim.0;
+					queued.core_offset =
+						thread_queue.next_allowed_core_offset(config.parathread_cores, |offset| {
+							Self::parathread_core_allowed(config, n_parachains, para_id, offset)
+						});
 				}
-
-				thread_queue.next_core_offset =
-					((thread_queue.queue.len()) as u32) % config.parathread_cores;
+				thread_queue.queue = queue;
 			}
 		});
 		ParathreadQueue::<T>::set(thread_queue);
 
+		// forget how long paras that are no longer parathreads have been waiting.
+		let no_longer_parathreads: Vec<_> = ParathreadWaitingSince::<T>::iter_keys()
+			.filter(|para_id| !<paras::Pallet<T>>::is_parathread(*para_id))
+			.collect();
+		for para_id in no_longer_parathreads {
+			ParathreadWaitingSince::<T>::remove(&para_id);
+		}
+
 		let now = <frame_system::Pallet<T>>::block_number() + One::one();
 		<SessionStartBlock<T>>::set(now);
 	}
@@ -347,20 +746,35 @@ impl<T: Config> Pallet<T> {
 	/// Fails if the claim does not correspond to any live parathread.
 	#[allow(unused)]
 	pub fn add_parathread_claim(claim: ParathreadClaim) {
+		let _ = Self::try_add_parathread_claim(claim);
+	}
+
+	/// Same as [`Self::add_parathread_claim`], but reports the reason the claim was not queued.
+	pub(crate) fn try_add_parathread_claim(claim: ParathreadClaim) -> Result<(), Error<T>> {
 		if !<paras::Pallet<T>>::is_parathread(claim.0) {
-			return
+			return Err(Error::<T>::NotParathread)
+		}
+		if <paras::Pallet<T>>::is_frozen(&claim.0) {
+			return Err(Error::<T>::ParaFrozen)
 		}
 
 		let config = <configuration::Pallet<T>>::config();
 		let queue_max_size = config.parathread_cores * config.scheduling_lookahead;
+		let n_parachains = <paras::Pallet<T>>::parachains().len() as u32;
+		let para_id = claim.0;
+		let core_allowed = |core_offset| {
+			Self::parathread_core_allowed(&config, n_parachains, para_id, core_offset)
+		};
+
+		if !(0..config.parathread_cores).any(core_allowed) {
+			return Err(Error::<T>::NoAllowedCore)
+		}
 
 		ParathreadQueue::<T>::mutate(|queue| {
 			if queue.queue.len() >= queue_max_size as usize {
-				return
+				return Err(Error::<T>::ParathreadQueueFull)
 			}
 
-			let para_id = claim.0;
-
 			let competes_with_another =
 				ParathreadClaimIndex::<T>::mutate(|index| match index.binary_search(&para_id) {
 					Ok(_) => true,
@@ -371,18 +785,246 @@ impl<T: Config> Pallet<T> {
 				});
 
 			if competes_with_another {
-				return
+				return Err(Error::<T>::ParathreadClaimExists)
 			}
 
 			let entry = ParathreadEntry { claim, retries: 0 };
-			queue.enqueue_entry(entry, config.parathread_cores);
-		})
+			queue.enqueue_entry(entry, config.parathread_cores, core_allowed);
+			Ok(())
+		})?;
+
+		if !ParathreadWaitingSince::<T>::contains_key(&para_id) {
+			let now = <frame_system::Pallet<T>>::block_number();
+			ParathreadWaitingSince::<T>::insert(&para_id, now);
+		}
+		Ok(())
+	}
+
+	/// Whether a bitfield with `n_bits` bits is sized to the layout of the availability cores
+	/// before the last change of the number of cores.
+	pub(crate) fn is_previous_core_layout(n_bits: usize) -> bool {
+		let previous_n_cores = PreviousCoreCount::<T>::get() as usize;
+		previous_n_cores != 0 && previous_n_cores == n_bits
+	}
+
+	/// Whether the core affinity in `config` allows the parathread on the parathread core at
+	/// `core_offset`, with `n_parachains` cores preceding the parathread cores.
+	fn parathread_core_allowed(
+		config: &configuration::HostConfiguration<T::BlockNumber>,
+		n_parachains: u32,
+		para_id: ParaId,
+		core_offset: u32,
+	) -> bool {
+		config.core_affinity_allows(para_id, CoreIndex(n_parachains + core_offset))
+	}
+
+	/// Reserve the claim deposit and the bid of `who` and queue a claim for the given parathread,
+	/// which the given collators may author.
+	fn do_place_parathread_claim(
+		who: T::AccountId,
+		para_id: ParaId,
+		mut collators: ParathreadClaimCollatorsOf,
+		bid: Balance,
+	) -> DispatchResult {
+		ensure!(!collators.is_empty(), Error::<T>::NoCollators);
+
+		let deposit = <configuration::Pallet<T>>::config().parathread_claim_deposit;
+		let reserved = deposit.saturating_add(bid);
+		let collator = collators.remove(0);
+
+		T::Currency::reserve(&who, reserved.unique_saturated_into())?;
+		if let Err(e) = Self::try_add_parathread_claim(ParathreadClaim(para_id, collator)) {
+			T::Currency::unreserve(&who, reserved.unique_saturated_into());
+			return Err(e.into())
+		}
+
+		if !collators.is_empty() {
+			ParathreadClaimCollators::<T>::insert(&para_id, collators);
+		}
+		ParathreadClaimDeposits::<T>::insert(&para_id, (who.clone(), deposit));
+		ParathreadClaimBids::<T>::insert(
+			&para_id,
+			(bid, <frame_system::Pallet<T>>::block_number()),
+		);
+		Self::deposit_event(Event::ParathreadClaimPlaced(para_id, who.clone(), deposit));
+		if bid > 0 {
+			Self::deposit_event(Event::ParathreadBidPlaced(para_id, who, bid));
+		}
+		Ok(())
+	}
+
+	/// Charge `who` the spot price, if it is at most `max_amount`, and queue a claim for the given
+	/// parathread, which the given collators may author.
+	fn do_place_order(
+		who: T::AccountId,
+		para_id: ParaId,
+		mut collators: ParathreadClaimCollatorsOf,
+		max_amount: Balance,
+	) -> DispatchResult {
+		ensure!(!collators.is_empty(), Error::<T>::NoCollators);
+
+		let price = Self::spot_price();
+		ensure!(price <= max_amount, Error::<T>::SpotPriceHigherThanMaxAmount);
+		let collator = collators.remove(0);
+
+		let fee = T::Currency::withdraw(
+			&who,
+			price.unique_saturated_into(),
+			WithdrawReasons::FEE,
+			ExistenceRequirement::KeepAlive,
+		)?;
+		if let Err(e) = Self::try_add_parathread_claim(ParathreadClaim(para_id, collator)) {
+			T::Currency::resolve_creating(&who, fee);
+			return Err(e.into())
+		}
+		T::OnDemandFees::on_unbalanced(fee);
+
+		OnDemandOrders::<T>::insert(&para_id, ());
+		if !collators.is_empty() {
+			ParathreadClaimCollators::<T>::insert(&para_id, collators);
+		}
+		Self::deposit_event(Event::OnDemandOrderPlaced(para_id, who, price));
+		Ok(())
+	}
+
+	/// The price of an on-demand order placed now.
+	///
+	/// This is `on_demand_base_fee` while the parathread claim queue is empty, and rises linearly
+	/// with the length of the queue, up to twice the base fee when the queue is full.
+	pub fn spot_price() -> Balance {
+		let config = <configuration::Pallet<T>>::config();
+		let queue_max_size = config.parathread_cores * config.scheduling_lookahead;
+		let queue_len = ParathreadQueue::<T>::get().queue.len() as u32;
+		let surcharge =
+			Perbill::from_rational(queue_len, queue_max_size) * config.on_demand_base_fee;
+		config.on_demand_base_fee.saturating_add(surcharge)
+	}
+
+	/// Return the deposit and any unpaid bid held for the claim of the given parathread, if any,
+	/// and forget the collators it allowed.
+	///
+	/// This should be called whenever the para is removed from the `ParathreadClaimIndex`.
+	fn release_claim(para_id: ParaId) {
+		ParathreadClaimCollators::<T>::remove(&para_id);
+		OnDemandOrders::<T>::remove(&para_id);
+		let bid = ParathreadClaimBids::<T>::take(&para_id).map_or(0, |(bid, _)| bid);
+		if let Some((who, deposit)) = ParathreadClaimDeposits::<T>::take(&para_id) {
+			T::Currency::unreserve(&who, deposit.saturating_add(bid).unique_saturated_into());
+			Self::deposit_event(Event::ParathreadClaimReleased(para_id, who));
+		}
+	}
+
+	/// Release the claim still held for the given offboarded para, if any, and forget how long it
+	/// has been waiting.
+	pub(crate) fn clean_up_offboarded_para(para_id: ParaId) -> Weight {
+		// The claims of paras that are no longer parathreads were pruned from the queue and the
+		// index at the session change.
+		Self::release_claim(para_id);
+		ParathreadWaitingSince::<T>::remove(&para_id);
+
+		T::DbWeight::get().reads_writes(3, 6)
+	}
+
+	/// Slash `slash` of the deposit of the claim of the given parathread to `T::OnClaimSlashed`,
+	/// after it ran out of retries, and release the claim.
+	fn expire_claim(para_id: ParaId, slash: Perbill) {
+		if let Some((who, deposit)) = ParathreadClaimDeposits::<T>::get(&para_id) {
+			let slashed = slash * deposit;
+			let (imbalance, _) = T::Currency::slash_reserved(&who, slashed.unique_saturated_into());
+			T::OnClaimSlashed::on_unbalanced(imbalance);
+			ParathreadClaimDeposits::<T>::insert(&para_id, (who.clone(), deposit - slashed));
+			Self::deposit_event(Event::ClaimExpired(para_id, who, slashed));
+		}
+		Self::release_claim(para_id);
+	}
+
+	/// Pay the bid of the claim of the given parathread to `T::OnBidSettled`, after its candidate
+	/// was included.
+	fn settle_bid(para_id: ParaId) {
+		let bid = match ParathreadClaimBids::<T>::take(&para_id) {
+			Some((bid, _)) if bid > 0 => bid,
+			_ => return,
+		};
+
+		if let Some((who, _)) = ParathreadClaimDeposits::<T>::get(&para_id) {
+			let (imbalance, _) = T::Currency::slash_reserved(&who, bid.unique_saturated_into());
+			T::OnBidSettled::on_unbalanced(imbalance);
+			Self::deposit_event(Event::ParathreadBidSettled(para_id, who, bid));
+		}
+	}
+
+	/// The effective bid of queued parathread claims at block `now`, which is their bid plus
+	/// `aging_bonus` for every block since they were placed, plus `starvation_bonus` for every
+	/// block their parathread has been waiting for one of its candidates to be included.
+	fn effective_bid(
+		aging_bonus: Balance,
+		starvation_bonus: Balance,
+		now: T::BlockNumber,
+	) -> impl Fn(&ParathreadEntry) -> Balance {
+		move |entry| {
+			let para_id = entry.claim.0;
+			let bid = ParathreadClaimBids::<T>::get(&para_id).map_or(0, |(bid, placed_at)| {
+				let waited: Balance = now.saturating_sub(placed_at).unique_saturated_into();
+				bid.saturating_add(aging_bonus.saturating_mul(waited))
+			});
+			if starvation_bonus == 0 {
+				return bid
+			}
+
+			let starved: Balance = ParathreadWaitingSince::<T>::get(&para_id)
+				.map_or(0, |since| now.saturating_sub(since).unique_saturated_into());
+			bid.saturating_add(starvation_bonus.saturating_mul(starved))
+		}
+	}
+
+	/// The number of blocks each parathread has been waiting for one of its candidates to be
+	/// included as of the next block, sorted by para id. See `ParathreadWaitingSince`.
+	pub(crate) fn parathread_starvation() -> Vec<(ParaId, T::BlockNumber)> {
+		let next = <frame_system::Pallet<T>>::block_number() + One::one();
+		let mut starvation: Vec<_> = ParathreadWaitingSince::<T>::iter()
+			.map(|(para_id, since)| (para_id, next.saturating_sub(since)))
+			.collect();
+		starvation.sort_by_key(|(para_id, _)| *para_id);
+		starvation
+	}
+
+	/// Whether the given collator may author the candidate for the given assignment.
+	///
+	/// Parachains may be collated by anyone. Parathread claims may be authored by the collator in
+	/// the claim, or by any of the other collators the claim allows.
+	pub(crate) fn is_allowed_collator(assignment: &CoreAssignment, collator: &CollatorId) -> bool {
+		match assignment.required_collator() {
+			None => true,
+			Some(required_collator) => {
+				required_collator == collator ||
+					ParathreadClaimCollators::<T>::get(&assignment.para_id)
+						.map_or(false, |collators| collators.contains(collator))
+			},
+		}
+	}
+
+	/// The number of blocks the availability timeout of the given core is currently extended by.
+	pub(crate) fn availability_timeout_extension(core: CoreIndex) -> T::BlockNumber {
+		AvailabilityTimeoutExtensions::<T>::get(core)
+			.map_or_else(Zero::zero, |(extension, _)| extension)
+	}
+
+	/// Where the given assignment came from.
+	pub(crate) fn assignment_source(assignment: &CoreAssignment) -> CoreAssignmentSource {
+		let para_id = &assignment.para_id;
+		match assignment.kind {
+			AssignmentKind::Parachain => CoreAssignmentSource::Lease,
+			AssignmentKind::Parathread(..) if OnDemandOrders::<T>::contains_key(para_id) =>
+				CoreAssignmentSource::OnDemandOrder,
+			AssignmentKind::Parathread(..) => CoreAssignmentSource::ParathreadClaim,
+		}
 	}
 
 	/// Free unassigned cores. Provide a list of cores that should be considered newly-freed along with the reason
 	/// for them being freed. The list is assumed to be sorted in ascending order by core index.
 	pub(crate) fn free_cores(just_freed_cores: impl IntoIterator<Item = (CoreIndex, FreedReason)>) {
 		let config = <configuration::Pallet<T>>::config();
+		let n_parachains = <paras::Pallet<T>>::parachains().len() as u32;
 
 		AvailabilityCores::<T>::mutate(|cores| {
 			for (freed_index, freed_reason) in just_freed_cores {
@@ -399,18 +1041,36 @@ impl<T: Config> Pallet<T> {
 										if let Ok(i) = index.binary_search(&entry.claim.0) {
 											index.remove(i);
 										}
-									})
+									});
+									Self::settle_bid(entry.claim.0);
+									Self::release_claim(entry.claim.0);
+									ParathreadWaitingSince::<T>::remove(&entry.claim.0);
 								},
 								FreedReason::TimedOut => {
 									// If a parathread candidate times out, it's not the collator's fault,
 									// so we don't increment retries.
+									let para_id = entry.claim.0;
 									ParathreadQueue::<T>::mutate(|queue| {
-										queue.enqueue_entry(entry, config.parathread_cores);
+										queue.enqueue_entry(
+											entry,
+											config.parathread_cores,
+											|core_offset| {
+												Self::parathread_core_allowed(
+													&config,
+													n_parachains,
+													para_id,
+													core_offset,
+												)
+											},
+										)
 									})
 								},
 							}
 						},
 					}
+
+					Self::deposit_event(Event::<T>::CoreFreed(freed_index, freed_reason));
+					<statistics::Pallet<T>>::note_core_freed();
 				}
 			}
 		})
@@ -425,10 +1085,17 @@ impl<T: Config> Pallet<T> {
 	) {
 		Self::free_cores(just_freed_cores);
 
+		let config = <configuration::Pallet<T>>::config();
 		let cores = AvailabilityCores::<T>::get();
 		let parachains = <paras::Pallet<T>>::parachains();
+		let frozen = <paras::Pallet<T>>::frozen_paras();
 		let mut scheduled = Scheduled::<T>::get();
 		let mut parathread_queue = ParathreadQueue::<T>::get();
+		let bid = Self::effective_bid(
+			config.parathread_bid_aging_bonus,
+			config.parathread_starvation_bonus,
+			now,
+		);
 
 		if ValidatorGroups::<T>::get().is_empty() {
 			return
@@ -478,7 +1145,14 @@ impl<T: Config> Pallet<T> {
 				let core = CoreIndex(core_index as u32);
 
 				let core_assignment = if core_index < parachains.len() {
-					// parachain core.
+					// parachain core, unless the parachain is frozen or its core affinity rules it
+					// out.
+					if frozen.binary_search(&parachains[core_index]).is_ok() ||
+						!config.core_affinity_allows(parachains[core_index], core)
+					{
+						continue
+					}
+
 					Some(CoreAssignment {
 						kind: AssignmentKind::Parachain,
 						para_id: parachains[core_index],
@@ -492,14 +1166,25 @@ impl<T: Config> Pallet<T> {
 					// parathread core offset, rel. to beginning.
 					let core_offset = (core_index - parachains.len()) as u32;
 
-					parathread_queue.take_next_on_core(core_offset).map(|entry| CoreAssignment {
-						kind: AssignmentKind::Parathread(entry.claim.1, entry.retries),
-						para_id: entry.claim.0,
-						core,
-						group_idx: Self::group_assigned_to_core(core, now).expect(
-							"core is not out of bounds and we are guaranteed \
+					// entries are queued on cores their affinity allows, but the affinity may
+					// have changed since.
+					let next_allowed = parathread_queue
+						.get_next_on_core(core_offset, &bid)
+						.map_or(false, |entry| config.core_affinity_allows(entry.claim.0, core));
+					if !next_allowed {
+						continue
+					}
+
+					parathread_queue.take_next_on_core(core_offset, &bid).map(|entry| {
+						CoreAssignment {
+							kind: AssignmentKind::Parathread(entry.claim.1, entry.retries),
+							para_id: entry.claim.0,
+							core,
+							group_idx: Self::group_assigned_to_core(core, now).expect(
+								"core is not out of bounds and we are guaranteed \
 									to be after the most recent session start; qed",
-						),
+							),
+						}
 					})
 				};
 
@@ -526,10 +1211,66 @@ impl<T: Config> Pallet<T> {
 			// insertions.
 		}
 
+		Self::update_claim_queue(&config, &parachains, &scheduled, &parathread_queue, now);
 		Scheduled::<T>::set(scheduled);
 		ParathreadQueue::<T>::set(parathread_queue);
 	}
 
+	/// Recompute `ClaimQueue` from the given scheduled assignments and parathread queue.
+	fn update_claim_queue(
+		config: &configuration::HostConfiguration<T::BlockNumber>,
+		parachains: &[ParaId],
+		scheduled: &[CoreAssignment],
+		parathread_queue: &ParathreadClaimQueue,
+		now: T::BlockNumber,
+	) {
+		let lookahead = config.scheduling_lookahead as usize;
+		let n_cores = AvailabilityCores::<T>::decode_len().unwrap_or(0);
+		let bid = Self::effective_bid(
+			config.parathread_bid_aging_bonus,
+			config.parathread_starvation_bonus,
+			now,
+		);
+		let frozen = <paras::Pallet<T>>::frozen_paras();
+
+		let mut claim_queue = BTreeMap::new();
+		for core_index in 0..n_cores {
+			let core = CoreIndex(core_index as u32);
+			let claims: Vec<ParaId> = if core_index < parachains.len() {
+				let para_id = parachains[core_index];
+				if frozen.binary_search(&para_id).is_ok() ||
+					!config.core_affinity_allows(para_id, core)
+				{
+					continue
+				}
+				vec![para_id; lookahead]
+			} else {
+				let core_offset = (core_index - parachains.len()) as u32;
+				let scheduled_para =
+					scheduled.iter().find(|assignment| assignment.core == core).map(|a| a.para_id);
+
+				// a queued claim the core affinity no longer allows blocks the ones behind it.
+				scheduled_para
+					.into_iter()
+					.chain(
+						parathread_queue
+							.ordered_on_core(core_offset, &bid)
+							.into_iter()
+							.map(|entry| entry.claim.0)
+							.take_while(|para_id| config.core_affinity_allows(*para_id, core)),
+					)
+					.take(lookahead)
+					.collect()
+			};
+
+			if !claims.is_empty() {
+				claim_queue.insert(core, claims);
+			}
+		}
+
+		ClaimQueue::<T>::set(claim_queue);
+	}
+
 	/// Note that the given cores have become occupied. Behavior undefined if any of the given cores were not scheduled
 	/// or the slice is not sorted ascending by core index.
 	///
@@ -565,6 +1306,32 @@ impl<T: Config> Pallet<T> {
 		});
 
 		AvailabilityCores::<T>::set(availability_cores);
+		Self::update_claim_queue(
+			&<configuration::Pallet<T>>::config(),
+			&<paras::Pallet<T>>::parachains(),
+			&Scheduled::<T>::get(),
+			&ParathreadQueue::<T>::get(),
+			<frame_system::Pallet<T>>::block_number(),
+		);
+	}
+
+	/// Occupy a free parachain core again with a candidate pending availability that was carried
+	/// over from the previous session.
+	///
+	/// Returns `false` and leaves the core untouched if it does not exist, is already occupied or
+	/// is no longer assigned to the given parachain.
+	pub(crate) fn reoccupy_parachain_core(core: CoreIndex, para_id: ParaId) -> bool {
+		if <paras::Pallet<T>>::parachains().get(core.0 as usize) != Some(&para_id) {
+			return false
+		}
+
+		AvailabilityCores::<T>::mutate(|cores| match cores.get_mut(core.0 as usize) {
+			Some(occupied @ None) => {
+				*occupied = Some(CoreOccupied::Parachain);
+				true
+			},
+			_ => false,
+		})
 	}
 
 	/// Get the para (chain or thread) ID assigned to a particular core or index, if any. Core indices
@@ -623,7 +1390,9 @@ impl<T: Config> Pallet<T> {
 	/// If `None`, no timing-out should be done. The predicate accepts the index of the core, and the
 	/// block number since which it has been occupied, and the respective parachain and parathread
 	/// timeouts, i.e. only within `max(config.chain_availability_period, config.thread_availability_period)`
-	/// of the last rotation would this return `Some`, unless there are no rotations.
+	/// of the last rotation would this return `Some`, unless there are no rotations. Paras with an
+	/// overridden availability period are timed out according to it, and extend this window if it
+	/// is longer.
 	///
 	/// This really should not be a box, but is working around a compiler limitation filed here:
 	/// https://github.com/rust-lang/rust/issues/73226
@@ -638,30 +1407,47 @@ impl<T: Config> Pallet<T> {
 		let blocks_since_last_rotation =
 			blocks_since_session_start % config.group_rotation_frequency;
 
-		let absolute_cutoff =
-			sp_std::cmp::max(config.chain_availability_period, config.thread_availability_period);
+		let parachains = <paras::Pallet<T>>::parachains();
+		let extensions = AvailabilityTimeoutExtensions::<T>::iter().collect::<BTreeMap<_, _>>();
+
+		// the availability period of the para occupying each core, if any, including the extension
+		// of the core.
+		let availability_periods: Vec<Option<T::BlockNumber>> = AvailabilityCores::<T>::get()
+			.iter()
+			.enumerate()
+			.map(|(i, core)| {
+				let period = match core.as_ref()? {
+					CoreOccupied::Parachain =>
+						parachains.get(i).map_or(config.chain_availability_period, |para| {
+							config.chain_availability_period_of(*para)
+						}),
+					CoreOccupied::Parathread(entry) =>
+						config.thread_availability_period_of(entry.claim.0),
+				};
+				let extension = extensions
+					.get(&CoreIndex(i as u32))
+					.map_or_else(Zero::zero, |(extension, _)| *extension);
+				Some(period.saturating_add(extension))
+			})
+			.collect();
 
-		let availability_cores = AvailabilityCores::<T>::get();
+		let absolute_cutoff = availability_periods.iter().flatten().fold(
+			sp_std::cmp::max(config.chain_availability_period, config.thread_availability_period),
+			|cutoff, period| sp_std::cmp::max(cutoff, *period),
+		);
 
 		if blocks_since_last_rotation >= absolute_cutoff {
 			None
 		} else {
 			Some(Box::new(move |core_index: CoreIndex, pending_since| {
-				match availability_cores.get(core_index.0 as usize) {
+				match availability_periods.get(core_index.0 as usize) {
 					None => true,       // out-of-bounds, doesn't really matter what is returned.
 					Some(None) => true, // core not occupied, still doesn't really matter.
-					Some(Some(CoreOccupied::Parachain)) => {
-						if blocks_since_last_rotation >= config.chain_availability_period {
-							false // no pruning except recently after rotation.
-						} else {
-							now.saturating_sub(pending_since) >= config.chain_availability_period
-						}
-					},
-					Some(Some(CoreOccupied::Parathread(_))) => {
-						if blocks_since_last_rotation >= config.thread_availability_period {
+					Some(Some(availability_period)) => {
+						if blocks_since_last_rotation >= *availability_period {
 							false // no pruning except recently after rotation.
 						} else {
-							now.saturating_sub(pending_since) >= config.thread_availability_period
+							now.saturating_sub(pending_since) >= *availability_period
 						}
 					},
 				}
@@ -678,6 +1464,16 @@ impl<T: Config> Pallet<T> {
 		GroupRotationInfo { session_start_block, now, group_rotation_frequency }
 	}
 
+	/// The effective bid of queued parathread claims when scheduling the next block.
+	fn next_block_bid() -> impl Fn(&ParathreadEntry) -> Balance {
+		let config = <configuration::Pallet<T>>::config();
+		Self::effective_bid(
+			config.parathread_bid_aging_bonus,
+			config.parathread_starvation_bonus,
+			<frame_system::Pallet<T>>::block_number() + One::one(),
+		)
+	}
+
 	/// Return the next thing that will be scheduled on this core assuming it is currently
 	/// occupied and the candidate occupying it became available.
 	///
@@ -691,10 +1487,12 @@ impl<T: Config> Pallet<T> {
 		} else {
 			let queue = ParathreadQueue::<T>::get();
 			let core_offset = (core.0 as usize - parachains.len()) as u32;
-			queue.get_next_on_core(core_offset).map(|entry| ScheduledCore {
-				para_id: entry.claim.0,
-				collator: Some(entry.claim.1.clone()),
-			})
+			queue
+				.get_next_on_core(core_offset, Self::next_block_bid())
+				.map(|entry| ScheduledCore {
+					para_id: entry.claim.0,
+					collator: Some(entry.claim.1.clone()),
+				})
 		}
 	}
 
@@ -715,7 +1513,7 @@ impl<T: Config> Pallet<T> {
 			// This is the next scheduled para on this core.
 			let core_offset = (core.0 as usize - parachains.len()) as u32;
 			queue
-				.get_next_on_core(core_offset)
+				.get_next_on_core(core_offset, Self::next_block_bid())
 				.map(|entry| ScheduledCore {
 					para_id: entry.claim.0,
 					collator: Some(entry.claim.1.clone()),
@@ -738,12 +1536,23 @@ impl<T: Config> Pallet<T> {
 	}
 
 	// Free all scheduled cores and return parathread claims to queue, with retries incremented.
+	//
+	// Claims which ran out of retries, which no longer belong to a parathread or whose parathread
+	// is frozen are dropped from the claim index and their deposits are returned. Claims which ran
+	// out of retries lose `parathread_claim_slash` of their deposit first.
 	pub(crate) fn clear() {
 		let config = <configuration::Pallet<T>>::config();
+		let n_parachains = <paras::Pallet<T>>::parachains().len() as u32;
+		let frozen = <paras::Pallet<T>>::frozen_paras();
+		let mut dropped = Vec::new();
+		let mut expired = Vec::new();
 		ParathreadQueue::<T>::mutate(|queue| {
 			for core_assignment in Scheduled::<T>::take() {
 				if let AssignmentKind::Parathread(collator, retries) = core_assignment.kind {
-					if !<paras::Pallet<T>>::is_parathread(core_assignment.para_id) {
+					if !<paras::Pallet<T>>::is_parathread(core_assignment.para_id) ||
+						frozen.binary_search(&core_assignment.para_id).is_ok()
+					{
+						dropped.push(core_assignment.para_id);
 						continue
 					}
 
@@ -753,10 +1562,49 @@ impl<T: Config> Pallet<T> {
 					};
 
 					if entry.retries <= config.parathread_retries {
-						queue.enqueue_entry(entry, config.parathread_cores);
+						let para_id = core_assignment.para_id;
+						queue.enqueue_entry(entry, config.parathread_cores, |core_offset| {
+							Self::parathread_core_allowed(
+								&config,
+								n_parachains,
+								para_id,
+								core_offset,
+							)
+						});
+					} else {
+						expired.push(core_assignment.para_id);
 					}
 				}
 			}
+
+			if !frozen.is_empty() {
+				queue.queue.retain(|queued| {
+					let para_id = queued.claim.claim.0;
+					let is_frozen = frozen.binary_search(&para_id).is_ok();
+					if is_frozen {
+						dropped.push(para_id);
+					}
+					!is_frozen
+				});
+			}
 		});
+
+		if dropped.is_empty() && expired.is_empty() {
+			return
+		}
+
+		ParathreadClaimIndex::<T>::mutate(|index| {
+			for para_id in dropped.iter().chain(&expired) {
+				if let Ok(i) = index.binary_search(para_id) {
+					index.remove(i);
+				}
+			}
+		});
+		for para_id in dropped {
+			Self::release_claim(para_id);
+		}
+		for para_id in expired {
+			Self::expire_claim(para_id, config.parathread_claim_slash);
+		}
 	}
 }
@@ -0,0 +1,116 @@
+// Copyright (C) Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::*;
+use crate::{
+	configuration::HostConfiguration,
+	paras::{Pallet as Paras, ParaGenesisArgs, ParaKind, ParachainsCache},
+};
+use frame_benchmarking::{account, benchmarks};
+use frame_system::RawOrigin;
+use sp_core::sr25519;
+use sp_runtime::traits::Bounded;
+
+type BalanceOf<T> =
+	<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+
+fn assert_last_event<T: Config>(generic_event: <T as Config>::RuntimeEvent) {
+	let events = frame_system::Pallet::<T>::events();
+	let system_event: <T as frame_system::Config>::RuntimeEvent = generic_event.into();
+	// compare to the last event record
+	let frame_system::EventRecord { event, .. } = &events[events.len() - 1];
+	assert_eq!(event, &system_event);
+}
+
+/// Onboard the given parathread with room for its claim on a parathread core, and return a funded
+/// account to place the claim with.
+fn setup_parathread<T: Config>(para_id: ParaId) -> T::AccountId {
+	configuration::Pallet::<T>::force_set_active_config(HostConfiguration {
+		parathread_cores: 1,
+		scheduling_lookahead: 1,
+		parathread_claim_deposit: 1,
+		on_demand_base_fee: 1,
+		..Default::default()
+	});
+
+	let mut parachains = ParachainsCache::new();
+	Paras::<T>::initialize_para_now(
+		&mut parachains,
+		para_id,
+		&ParaGenesisArgs {
+			para_kind: ParaKind::Parathread,
+			genesis_head: vec![1].into(),
+			validation_code: vec![1].into(),
+		},
+	);
+	drop(parachains);
+
+	let caller: T::AccountId = account("caller", 0, 0);
+	T::Currency::make_free_balance_be(&caller, BalanceOf::<T>::max_value() / 2u32.into());
+	caller
+}
+
+/// `c` distinct collators for a parathread claim.
+fn collators(c: u32) -> ParathreadClaimCollatorsOf {
+	let collators = (0..c)
+		.map(|i| CollatorId::from(sr25519::Public::from_raw([i as u8; 32])))
+		.collect::<Vec<_>>();
+	BoundedVec::truncate_from(collators)
+}
+
+benchmarks! {
+	place_parathread_claim {
+		let para_id = ParaId::from(1000);
+		let caller = setup_parathread::<T>(para_id);
+		let collator = CollatorId::from(sr25519::Public::from_raw([0; 32]));
+	}: _(RawOrigin::Signed(caller.clone()), para_id, collator)
+	verify {
+		assert_last_event::<T>(Event::ParathreadClaimPlaced(para_id, caller, 1).into());
+	}
+
+	place_parathread_claim_with_collators {
+		let c in 1 .. MAX_PARATHREAD_CLAIM_COLLATORS;
+		let para_id = ParaId::from(1000);
+		let caller = setup_parathread::<T>(para_id);
+	}: _(RawOrigin::Signed(caller.clone()), para_id, collators(c))
+	verify {
+		assert_last_event::<T>(Event::ParathreadClaimPlaced(para_id, caller, 1).into());
+	}
+
+	place_parathread_bid {
+		let c in 1 .. MAX_PARATHREAD_CLAIM_COLLATORS;
+		let para_id = ParaId::from(1000);
+		let caller = setup_parathread::<T>(para_id);
+	}: _(RawOrigin::Signed(caller.clone()), para_id, collators(c), 1)
+	verify {
+		assert_last_event::<T>(Event::ParathreadBidPlaced(para_id, caller, 1).into());
+	}
+
+	place_order {
+		let c in 1 .. MAX_PARATHREAD_CLAIM_COLLATORS;
+		let para_id = ParaId::from(1000);
+		let caller = setup_parathread::<T>(para_id);
+	}: _(RawOrigin::Signed(caller.clone()), para_id, collators(c), Balance::MAX)
+	verify {
+		assert_last_event::<T>(Event::OnDemandOrderPlaced(para_id, caller, 1).into());
+	}
+
+	impl_benchmark_test_suite!(
+		Pallet,
+		crate::mock::new_test_ext(Default::default()),
+		crate::mock::Test
+	);
+}
@@ -16,15 +16,17 @@
 
 use super::*;
 
-use frame_support::assert_ok;
+use frame_support::{assert_noop, assert_ok, bounded_vec, traits::Currency};
 use keyring::Sr25519Keyring;
 use primitives::{BlockNumber, CollatorId, SessionIndex, ValidatorId};
+use sp_runtime::DispatchError;
 
 use crate::{
-	configuration::HostConfiguration,
+	configuration::{HostConfiguration, ParaConfigOverrides},
 	initializer::SessionChangeNotification,
 	mock::{
-		new_test_ext, Configuration, MockGenesisConfig, Paras, ParasShared, Scheduler, System, Test,
+		new_test_ext, Balances, Configuration, MockGenesisConfig, Paras, ParasShared, RuntimeEvent,
+		RuntimeOrigin, Scheduler, System, Test,
 	},
 	paras::{ParaGenesisArgs, ParaKind},
 };
@@ -255,6 +257,7 @@ fn session_change_prunes_cores_beyond_retries_and_those_from_non_live_parathread
 					retries: max_parathread_retries + 1,
 				},
 				4,
+				|_| true,
 			);
 
 			// Will not be pruned.
@@ -264,18 +267,21 @@ fn session_change_prunes_cores_beyond_retries_and_those_from_non_live_parathread
 					retries: max_parathread_retries,
 				},
 				4,
+				|_| true,
 			);
 
 			// Will not be pruned.
 			queue.enqueue_entry(
 				ParathreadEntry { claim: ParathreadClaim(thread_c, collator.clone()), retries: 0 },
 				4,
+				|_| true,
 			);
 
 			// Will be pruned: not a live parathread.
 			queue.enqueue_entry(
 				ParathreadEntry { claim: ParathreadClaim(thread_d, collator.clone()), retries: 0 },
 				4,
+				|_| true,
 			);
 
 			queue
@@ -375,6 +381,7 @@ fn session_change_takes_only_max_per_core() {
 		let mut config = default_config();
 		config.parathread_cores = 0;
 		config.max_validators_per_core = Some(1);
+		config.minimum_backing_votes = 1;
 		config
 	};
 
@@ -659,6 +666,22 @@ fn schedule_schedules_including_just_freed() {
 			3,
 		);
 
+		let freed_events = System::events()
+			.into_iter()
+			.filter_map(|record| match record.event {
+				RuntimeEvent::Scheduler(Event::CoreFreed(core, reason)) => Some((core, reason)),
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(
+			freed_events,
+			vec![
+				(CoreIndex(0), FreedReason::Concluded),
+				(CoreIndex(2), FreedReason::Concluded),
+				(CoreIndex(3), FreedReason::TimedOut),
+			],
+		);
+
 		{
 			let scheduled = Scheduler::scheduled();
 
@@ -901,6 +924,52 @@ fn schedule_rotates_groups() {
 	});
 }
 
+#[test]
+fn group_rotation_info_matches_on_chain_assignment() {
+	let config = default_config();
+	let rotation_frequency = config.group_rotation_frequency;
+
+	let genesis_config = MockGenesisConfig {
+		configuration: crate::configuration::GenesisConfig {
+			config: config.clone(),
+			..Default::default()
+		},
+		..Default::default()
+	};
+
+	new_test_ext(genesis_config).execute_with(|| {
+		// no paras, so all of the cores are parathread multiplexers.
+		run_to_block(1, |number| match number {
+			1 => Some(SessionChangeNotification {
+				new_config: config.clone(),
+				validators: vec![
+					ValidatorId::from(Sr25519Keyring::Alice.public()),
+					ValidatorId::from(Sr25519Keyring::Bob.public()),
+					ValidatorId::from(Sr25519Keyring::Charlie.public()),
+					ValidatorId::from(Sr25519Keyring::Dave.public()),
+					ValidatorId::from(Sr25519Keyring::Eve.public()),
+				],
+				..Default::default()
+			}),
+			_ => None,
+		});
+
+		let n_cores = Scheduler::availability_cores().len();
+		assert_eq!(n_cores, config.parathread_cores as usize);
+
+		// The rotation info exposed through the runtime API must give the same answer as the
+		// assignment the runtime itself uses, for every block over several rotations.
+		for now in 1..(rotation_frequency * 4) {
+			let rotation_info = Scheduler::group_rotation_info(now);
+			for core in (0..n_cores as u32).map(CoreIndex) {
+				let group = Scheduler::group_assigned_to_core(core, now).unwrap();
+				assert_eq!(rotation_info.group_for_core(core, n_cores), group);
+				assert_eq!(rotation_info.core_for_group(group, n_cores), core);
+			}
+		}
+	});
+}
+
 #[test]
 fn parathread_claims_are_pruned_after_retries() {
 	let max_retries = default_config().parathread_retries;
@@ -951,6 +1020,166 @@ fn parathread_claims_are_pruned_after_retries() {
 	});
 }
 
+#[test]
+fn place_parathread_claim_holds_deposit_until_concluded() {
+	let genesis_config = MockGenesisConfig {
+		configuration: crate::configuration::GenesisConfig {
+			config: HostConfiguration { parathread_claim_deposit: 50, ..default_config() },
+			..Default::default()
+		},
+		..Default::default()
+	};
+
+	let thread_a = ParaId::from(1_u32);
+	let not_a_thread = ParaId::from(2_u32);
+	let collator = CollatorId::from(Sr25519Keyring::Alice.public());
+
+	new_test_ext(genesis_config).execute_with(|| {
+		schedule_blank_para(thread_a, ParaKind::Parathread);
+
+		run_to_block(1, |number| match number {
+			1 => Some(SessionChangeNotification {
+				new_config: default_config(),
+				validators: vec![
+					ValidatorId::from(Sr25519Keyring::Alice.public()),
+					ValidatorId::from(Sr25519Keyring::Eve.public()),
+				],
+				..Default::default()
+			}),
+			_ => None,
+		});
+
+		Balances::make_free_balance_be(&1, 100);
+
+		assert_ok!(Scheduler::place_parathread_claim(
+			RuntimeOrigin::signed(1),
+			thread_a,
+			collator.clone(),
+		));
+		assert_eq!(Balances::reserved_balance(&1), 50);
+
+		assert_noop!(
+			Scheduler::place_parathread_claim(RuntimeOrigin::signed(1), thread_a, collator.clone()),
+			Error::<Test>::ParathreadClaimExists,
+		);
+		assert_noop!(
+			Scheduler::place_parathread_claim(
+				RuntimeOrigin::signed(1),
+				not_a_thread,
+				collator.clone(),
+			),
+			Error::<Test>::NotParathread,
+		);
+
+		run_to_block(2, |_| None);
+		assert_eq!(Scheduler::scheduled().len(), 1);
+		let core = Scheduler::scheduled()[0].core;
+		Scheduler::occupied(&[core]);
+
+		// still held while the candidate is pending availability.
+		assert_eq!(Balances::reserved_balance(&1), 50);
+
+		Scheduler::free_cores(vec![(core, FreedReason::Concluded)]);
+		assert_eq!(Balances::reserved_balance(&1), 0);
+		assert!(ParathreadClaimIndex::<Test>::get().is_empty());
+		assert!(ParathreadClaimDeposits::<Test>::get(&thread_a).is_none());
+	});
+}
+
+#[test]
+fn parathread_claim_deposit_is_returned_after_retries() {
+	let max_retries = default_config().parathread_retries;
+
+	let genesis_config = MockGenesisConfig {
+		configuration: crate::configuration::GenesisConfig {
+			config: HostConfiguration { parathread_claim_deposit: 50, ..default_config() },
+			..Default::default()
+		},
+		..Default::default()
+	};
+
+	let thread_a = ParaId::from(1_u32);
+	let collator = CollatorId::from(Sr25519Keyring::Alice.public());
+
+	new_test_ext(genesis_config).execute_with(|| {
+		schedule_blank_para(thread_a, ParaKind::Parathread);
+
+		run_to_block(1, |number| match number {
+			1 => Some(SessionChangeNotification {
+				new_config: default_config(),
+				validators: vec![
+					ValidatorId::from(Sr25519Keyring::Alice.public()),
+					ValidatorId::from(Sr25519Keyring::Eve.public()),
+				],
+				..Default::default()
+			}),
+			_ => None,
+		});
+
+		Balances::make_free_balance_be(&1, 100);
+		assert_ok!(Scheduler::place_parathread_claim(RuntimeOrigin::signed(1), thread_a, collator));
+
+		run_to_block(2 + max_retries, |_| None);
+		assert_eq!(Scheduler::scheduled().len(), 1);
+		assert_eq!(Balances::reserved_balance(&1), 50);
+
+		run_to_block(2 + max_retries + 1, |_| None);
+		assert!(Scheduler::scheduled().is_empty());
+		assert!(ParathreadClaimIndex::<Test>::get().is_empty());
+		assert_eq!(Balances::reserved_balance(&1), 0);
+	});
+}
+
+#[test]
+fn parathread_claim_deposit_is_partly_slashed_when_the_claim_expires() {
+	let max_retries = default_config().parathread_retries;
+	let config = HostConfiguration {
+		parathread_claim_deposit: 50,
+		parathread_claim_slash: Perbill::from_percent(20),
+		..default_config()
+	};
+
+	let genesis_config = MockGenesisConfig {
+		configuration: crate::configuration::GenesisConfig {
+			config: config.clone(),
+			..Default::default()
+		},
+		..Default::default()
+	};
+
+	let thread_a = ParaId::from(1_u32);
+	let collator = CollatorId::from(Sr25519Keyring::Alice.public());
+
+	new_test_ext(genesis_config).execute_with(|| {
+		schedule_blank_para(thread_a, ParaKind::Parathread);
+
+		run_to_block(1, |number| match number {
+			1 => Some(SessionChangeNotification {
+				new_config: config.clone(),
+				validators: vec![
+					ValidatorId::from(Sr25519Keyring::Alice.public()),
+					ValidatorId::from(Sr25519Keyring::Eve.public()),
+				],
+				..Default::default()
+			}),
+			_ => None,
+		});
+
+		Balances::make_free_balance_be(&1, 100);
+		assert_ok!(Scheduler::place_parathread_claim(RuntimeOrigin::signed(1), thread_a, collator));
+
+		// No candidate is ever backed, so the claim runs out of retries.
+		run_to_block(2 + max_retries + 1, |_| None);
+		assert!(Scheduler::scheduled().is_empty());
+		assert!(ParathreadClaimIndex::<Test>::get().is_empty());
+		assert!(ParathreadClaimDeposits::<Test>::get(&thread_a).is_none());
+		assert_eq!(Balances::reserved_balance(&1), 0);
+		assert_eq!(Balances::free_balance(&1), 90);
+		let expired = RuntimeEvent::Scheduler(Event::ClaimExpired(thread_a, 1, 10));
+		assert!(System::events().iter().any(|record| record.event == expired));
+	});
+}
+
 #[test]
 fn availability_predicate_works() {
 	let genesis_config = MockGenesisConfig {
@@ -1056,6 +1285,167 @@ fn availability_predicate_works() {
 	});
 }
 
+#[test]
+fn availability_predicate_uses_overridden_availability_period() {
+	let chain_a = ParaId::from(1_u32);
+	let thread_a = ParaId::from(2_u32);
+
+	let mut config = default_config();
+	config.minimum_validation_upgrade_delay = 8;
+	config.para_config_overrides =
+		vec![(chain_a, ParaConfigOverrides { availability_period: Some(7), ..Default::default() })];
+
+	let genesis_config = MockGenesisConfig {
+		configuration: crate::configuration::GenesisConfig {
+			config: config.clone(),
+			..Default::default()
+		},
+		..Default::default()
+	};
+
+	let HostConfiguration { group_rotation_frequency, thread_availability_period, .. } =
+		config.clone();
+	let collator = CollatorId::from(Sr25519Keyring::Alice.public());
+
+	new_test_ext(genesis_config).execute_with(|| {
+		schedule_blank_para(chain_a, ParaKind::Parachain);
+		schedule_blank_para(thread_a, ParaKind::Parathread);
+
+		// start a new session with our chain & thread registered.
+		run_to_block(1, |number| match number {
+			1 => Some(SessionChangeNotification {
+				new_config: config.clone(),
+				validators: vec![
+					ValidatorId::from(Sr25519Keyring::Alice.public()),
+					ValidatorId::from(Sr25519Keyring::Bob.public()),
+					ValidatorId::from(Sr25519Keyring::Charlie.public()),
+					ValidatorId::from(Sr25519Keyring::Dave.public()),
+					ValidatorId::from(Sr25519Keyring::Eve.public()),
+				],
+				..Default::default()
+			}),
+			_ => None,
+		});
+
+		AvailabilityCores::<Test>::mutate(|cores| {
+			cores[0] = Some(CoreOccupied::Parachain);
+			cores[1] = Some(CoreOccupied::Parathread(ParathreadEntry {
+				claim: ParathreadClaim(thread_a, collator),
+				retries: 0,
+			}))
+		});
+
+		// past the global periods, but still within the overridden one of the chain.
+		run_to_block(1 + group_rotation_frequency + thread_availability_period, |_| None);
+
+		{
+			let pred = Scheduler::availability_timeout_predicate()
+				.expect("predicate exists within the overridden availability period");
+
+			let now = System::block_number();
+			assert!(pred(CoreIndex(0), now - 7));
+			assert!(!pred(CoreIndex(0), now - 6));
+			// threads can't be timed out now.
+			assert!(!pred(CoreIndex(1), now - 7));
+		}
+
+		run_to_block(1 + group_rotation_frequency + 7, |_| None);
+
+		assert!(Scheduler::availability_timeout_predicate().is_none());
+	});
+}
+
+#[test]
+fn availability_timeouts_can_be_extended_until_expiry() {
+	let chain_a = ParaId::from(1_u32);
+	let thread_a = ParaId::from(2_u32);
+
+	let genesis_config = MockGenesisConfig {
+		configuration: crate::configuration::GenesisConfig {
+			config: default_config(),
+			..Default::default()
+		},
+		..Default::default()
+	};
+
+	let HostConfiguration { group_rotation_frequency, thread_availability_period, .. } =
+		default_config();
+	let collator = CollatorId::from(Sr25519Keyring::Alice.public());
+
+	new_test_ext(genesis_config).execute_with(|| {
+		schedule_blank_para(chain_a, ParaKind::Parachain);
+		schedule_blank_para(thread_a, ParaKind::Parathread);
+
+		// start a new session with our chain & thread registered.
+		run_to_block(1, |number| match number {
+			1 => Some(SessionChangeNotification {
+				new_config: default_config(),
+				validators: vec![
+					ValidatorId::from(Sr25519Keyring::Alice.public()),
+					ValidatorId::from(Sr25519Keyring::Bob.public()),
+					ValidatorId::from(Sr25519Keyring::Charlie.public()),
+					ValidatorId::from(Sr25519Keyring::Dave.public()),
+					ValidatorId::from(Sr25519Keyring::Eve.public()),
+				],
+				..Default::default()
+			}),
+			_ => None,
+		});
+
+		AvailabilityCores::<Test>::mutate(|cores| {
+			cores[0] = Some(CoreOccupied::Parachain);
+			cores[1] = Some(CoreOccupied::Parathread(ParathreadEntry {
+				claim: ParathreadClaim(thread_a, collator),
+				retries: 0,
+			}))
+		});
+
+		assert_noop!(
+			Scheduler::extend_availability_timeouts(RuntimeOrigin::signed(1), 4, 15),
+			DispatchError::BadOrigin,
+		);
+		assert_noop!(
+			Scheduler::extend_availability_timeouts(RuntimeOrigin::root(), 0, 15),
+			Error::<Test>::InvalidAvailabilityTimeoutExtension,
+		);
+		assert_noop!(
+			Scheduler::extend_availability_timeouts(RuntimeOrigin::root(), 4, 0),
+			Error::<Test>::InvalidAvailabilityTimeoutExtension,
+		);
+
+		// only the occupied cores are extended.
+		assert_ok!(Scheduler::extend_availability_timeouts(RuntimeOrigin::root(), 4, 15));
+		for core in [CoreIndex(0), CoreIndex(1)] {
+			let extended = RuntimeEvent::Scheduler(Event::AvailabilityTimeoutExtended(core, 4, 16));
+			assert!(System::events().iter().any(|record| record.event == extended));
+		}
+		assert_eq!(Scheduler::availability_timeout_extension(CoreIndex(2)), 0);
+
+		run_to_block(1 + group_rotation_frequency, |_| None);
+
+		{
+			let pred = Scheduler::availability_timeout_predicate()
+				.expect("predicate exists recently after rotation");
+
+			let now = System::block_number();
+			assert!(pred(CoreIndex(0), now - 7));
+			assert!(!pred(CoreIndex(0), now - 6));
+			assert!(pred(CoreIndex(1), now - 9));
+			assert!(!pred(CoreIndex(1), now - 8));
+		}
+
+		// the extensions expire after 15 blocks, along with the window they opened.
+		run_to_block(15, |_| None);
+		assert_eq!(Scheduler::availability_timeout_extension(CoreIndex(0)), 4);
+		run_to_block(1 + group_rotation_frequency + thread_availability_period, |_| None);
+		assert_eq!(Scheduler::availability_timeout_extension(CoreIndex(0)), 0);
+		let expired =
+			RuntimeEvent::Scheduler(Event::AvailabilityTimeoutExtensionExpired(CoreIndex(1)));
+		assert!(System::events().iter().any(|record| record.event == expired));
+		assert!(Scheduler::availability_timeout_predicate().is_none());
+	});
+}
+
 #[test]
 fn next_up_on_available_uses_next_scheduled_or_none_for_thread() {
 	let mut config = default_config();
@@ -1116,7 +1506,7 @@ fn next_up_on_available_uses_next_scheduled_or_none_for_thread() {
 
 			let queue = ParathreadQueue::<Test>::get();
 			assert_eq!(
-				queue.get_next_on_core(0).unwrap().claim,
+				queue.get_next_on_core(0, |_| 0).unwrap().claim,
 				ParathreadClaim(thread_b, collator.clone()),
 			);
 
@@ -1183,7 +1573,7 @@ fn next_up_on_time_out_reuses_claim_if_nothing_queued() {
 			}
 
 			let queue = ParathreadQueue::<Test>::get();
-			assert!(queue.get_next_on_core(0).is_none());
+			assert!(queue.get_next_on_core(0, |_| 0).is_none());
 			assert_eq!(
 				Scheduler::next_up_on_time_out(CoreIndex(0)).unwrap(),
 				ScheduledCore { para_id: thread_a, collator: Some(collator.clone()) }
@@ -1193,7 +1583,7 @@ fn next_up_on_time_out_reuses_claim_if_nothing_queued() {
 
 			let queue = ParathreadQueue::<Test>::get();
 			assert_eq!(
-				queue.get_next_on_core(0).unwrap().claim,
+				queue.get_next_on_core(0, |_| 0).unwrap().claim,
 				ParathreadClaim(thread_b, collator.clone()),
 			);
 
@@ -1449,3 +1839,637 @@ fn parathread_claims_are_pruned_after_deregistration() {
 		assert_eq!(Scheduler::scheduled().len(), 1);
 	});
 }
+
+#[test]
+fn parathread_claim_with_collators_allows_each_of_them() {
+	let thread_a = ParaId::from(1_u32);
+	let alice = CollatorId::from(Sr25519Keyring::Alice.public());
+	let bob = CollatorId::from(Sr25519Keyring::Bob.public());
+	let charlie = CollatorId::from(Sr25519Keyring::Charlie.public());
+
+	new_test_ext(MockGenesisConfig {
+		configuration: crate::configuration::GenesisConfig {
+			config: default_config(),
+			..Default::default()
+		},
+		..Default::default()
+	})
+	.execute_with(|| {
+		schedule_blank_para(thread_a, ParaKind::Parathread);
+
+		run_to_block(1, |number| match number {
+			1 => Some(SessionChangeNotification {
+				new_config: default_config(),
+				validators: vec![
+					ValidatorId::from(Sr25519Keyring::Alice.public()),
+					ValidatorId::from(Sr25519Keyring::Eve.public()),
+				],
+				..Default::default()
+			}),
+			_ => None,
+		});
+
+		assert_noop!(
+			Scheduler::place_parathread_claim_with_collators(
+				RuntimeOrigin::signed(1),
+				thread_a,
+				bounded_vec![],
+			),
+			Error::<Test>::NoCollators,
+		);
+		// claims allowing too many collators are rejected when the call is decoded.
+		let too_many = vec![alice.clone(); MAX_PARATHREAD_CLAIM_COLLATORS as usize + 1];
+		assert!(ParathreadClaimCollatorsOf::decode(&mut &too_many.encode()[..]).is_err());
+
+		assert_ok!(Scheduler::place_parathread_claim_with_collators(
+			RuntimeOrigin::signed(1),
+			thread_a,
+			bounded_vec![alice.clone(), bob.clone()],
+		));
+
+		run_to_block(2, |_| None);
+		let assignment = Scheduler::scheduled()[0].clone();
+		assert_eq!(assignment.required_collator(), Some(&alice));
+		assert!(Scheduler::is_allowed_collator(&assignment, &alice));
+		assert!(Scheduler::is_allowed_collator(&assignment, &bob));
+		assert!(!Scheduler::is_allowed_collator(&assignment, &charlie));
+
+		Scheduler::occupied(&[assignment.core]);
+		Scheduler::free_cores(vec![(assignment.core, FreedReason::Concluded)]);
+		assert!(ParathreadClaimCollators::<Test>::get(&thread_a).is_none());
+	});
+}
+
+#[test]
+fn core_affinity_restricts_scheduled_cores() {
+	let chain_a = ParaId::from(1_u32);
+	let chain_b = ParaId::from(2_u32);
+
+	let thread_a = ParaId::from(3_u32);
+	let thread_b = ParaId::from(4_u32);
+	let thread_c = ParaId::from(5_u32);
+
+	let collator = CollatorId::from(Sr25519Keyring::Alice.public());
+
+	// chain A may only use a parathread core, thread A only the last core and thread C only a
+	// parachain core.
+	let config = HostConfiguration {
+		core_affinity: vec![
+			(chain_a, vec![CoreIndex(2)]),
+			(thread_a, vec![CoreIndex(4)]),
+			(thread_c, vec![CoreIndex(0)]),
+		],
+		..default_config()
+	};
+
+	new_test_ext(MockGenesisConfig {
+		configuration: crate::configuration::GenesisConfig {
+			config: config.clone(),
+			..Default::default()
+		},
+		..Default::default()
+	})
+	.execute_with(|| {
+		schedule_blank_para(chain_a, ParaKind::Parachain);
+		schedule_blank_para(chain_b, ParaKind::Parachain);
+		schedule_blank_para(thread_a, ParaKind::Parathread);
+		schedule_blank_para(thread_b, ParaKind::Parathread);
+		schedule_blank_para(thread_c, ParaKind::Parathread);
+
+		// start a new session to activate, 5 validators for 5 cores.
+		run_to_block(1, |number| match number {
+			1 => Some(SessionChangeNotification {
+				new_config: config.clone(),
+				validators: vec![
+					ValidatorId::from(Sr25519Keyring::Alice.public()),
+					ValidatorId::from(Sr25519Keyring::Bob.public()),
+					ValidatorId::from(Sr25519Keyring::Charlie.public()),
+					ValidatorId::from(Sr25519Keyring::Dave.public()),
+					ValidatorId::from(Sr25519Keyring::Eve.public()),
+				],
+				..Default::default()
+			}),
+			_ => None,
+		});
+
+		assert!(matches!(
+			Scheduler::try_add_parathread_claim(ParathreadClaim(thread_c, collator.clone())),
+			Err(Error::<Test>::NoAllowedCore)
+		));
+		assert_ok!(Scheduler::try_add_parathread_claim(ParathreadClaim(
+			thread_a,
+			collator.clone()
+		)));
+		assert_ok!(Scheduler::try_add_parathread_claim(ParathreadClaim(
+			thread_b,
+			collator.clone()
+		)));
+
+		run_to_block(2, |_| None);
+
+		let scheduled = Scheduler::scheduled()
+			.into_iter()
+			.map(|assignment| (assignment.core, assignment.para_id))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			scheduled,
+			vec![(CoreIndex(1), chain_b), (CoreIndex(2), thread_b), (CoreIndex(4), thread_a)],
+		);
+	});
+}
+
+#[test]
+fn parathread_bids_order_claims_on_a_core_and_are_paid_once_included() {
+	let thread_a = ParaId::from(1_u32);
+	let thread_b = ParaId::from(2_u32);
+	let thread_c = ParaId::from(3_u32);
+
+	let collator = CollatorId::from(Sr25519Keyring::Alice.public());
+
+	let config = HostConfiguration {
+		parathread_cores: 1,
+		scheduling_lookahead: 3,
+		parathread_claim_deposit: 50,
+		parathread_bid_aging_bonus: 5,
+		..default_config()
+	};
+
+	new_test_ext(MockGenesisConfig {
+		configuration: crate::configuration::GenesisConfig {
+			config: config.clone(),
+			..Default::default()
+		},
+		..Default::default()
+	})
+	.execute_with(|| {
+		schedule_blank_para(thread_a, ParaKind::Parathread);
+		schedule_blank_para(thread_b, ParaKind::Parathread);
+		schedule_blank_para(thread_c, ParaKind::Parathread);
+
+		run_to_block(1, |number| match number {
+			1 => Some(SessionChangeNotification {
+				new_config: config.clone(),
+				validators: vec![ValidatorId::from(Sr25519Keyring::Alice.public())],
+				..Default::default()
+			}),
+			_ => None,
+		});
+
+		for who in 1..=3 {
+			Balances::make_free_balance_be(&who, 100);
+		}
+
+		assert_noop!(
+			Scheduler::place_parathread_bid(
+				RuntimeOrigin::signed(1),
+				thread_a,
+				bounded_vec![collator.clone()],
+				60,
+			),
+			pallet_balances::Error::<Test, _>::InsufficientBalance,
+		);
+		assert_ok!(Scheduler::place_parathread_bid(
+			RuntimeOrigin::signed(1),
+			thread_a,
+			bounded_vec![collator.clone()],
+			0,
+		));
+		assert_ok!(Scheduler::place_parathread_bid(
+			RuntimeOrigin::signed(3),
+			thread_c,
+			bounded_vec![collator.clone()],
+			30,
+		));
+		assert_eq!(Balances::reserved_balance(&3), 80);
+
+		// the highest bid is scheduled first, even though it was queued later.
+		run_to_block(2, |_| None);
+		assert_eq!(Scheduler::scheduled().len(), 1);
+		assert_eq!(Scheduler::scheduled()[0].para_id, thread_c);
+		let core = Scheduler::scheduled()[0].core;
+		Scheduler::occupied(&[core]);
+
+		run_to_block(6, |_| None);
+		assert_ok!(Scheduler::place_parathread_bid(
+			RuntimeOrigin::signed(2),
+			thread_b,
+			bounded_vec![collator.clone()],
+			10,
+		));
+
+		// the bid is paid once the candidate is included, and the deposit returned.
+		let issuance = Balances::total_issuance();
+		Scheduler::free_cores(vec![(core, FreedReason::Concluded)]);
+		assert_eq!(Balances::reserved_balance(&3), 0);
+		assert_eq!(Balances::free_balance(&3), 70);
+		assert_eq!(Balances::total_issuance(), issuance - 30);
+		assert!(ParathreadClaimBids::<Test>::get(&thread_c).is_none());
+
+		// thread A has waited long enough to outbid the newer claim of thread B.
+		run_to_block(7, |_| None);
+		assert_eq!(Scheduler::scheduled().len(), 1);
+		assert_eq!(Scheduler::scheduled()[0].para_id, thread_a);
+		assert_eq!(Balances::reserved_balance(&2), 60);
+	});
+}
+
+#[test]
+fn parathreads_waiting_for_an_inclusion_are_boosted() {
+	let thread_a = ParaId::from(1_u32);
+	let thread_b = ParaId::from(2_u32);
+
+	let collator = CollatorId::from(Sr25519Keyring::Alice.public());
+
+	let config = HostConfiguration {
+		parathread_cores: 1,
+		parathread_starvation_bonus: 10,
+		..default_config()
+	};
+
+	new_test_ext(MockGenesisConfig {
+		configuration: crate::configuration::GenesisConfig {
+			config: config.clone(),
+			..Default::default()
+		},
+		..Default::default()
+	})
+	.execute_with(|| {
+		schedule_blank_para(thread_a, ParaKind::Parathread);
+		schedule_blank_para(thread_b, ParaKind::Parathread);
+
+		run_to_block(1, |number| match number {
+			1 => Some(SessionChangeNotification {
+				new_config: config.clone(),
+				validators: vec![ValidatorId::from(Sr25519Keyring::Alice.public())],
+				..Default::default()
+			}),
+			_ => None,
+		});
+
+		Scheduler::add_parathread_claim(ParathreadClaim(thread_a, collator.clone()));
+		assert_eq!(Scheduler::parathread_starvation(), vec![(thread_a, 1)]);
+
+		run_to_block(2, |_| None);
+		assert_eq!(Scheduler::scheduled()[0].para_id, thread_a);
+		let core = Scheduler::scheduled()[0].core;
+		Scheduler::occupied(&[core]);
+
+		// the candidate of thread A times out, so its claim is queued again, behind a bid for
+		// thread B that it would lose against on its own.
+		run_to_block(4, |_| None);
+		Balances::make_free_balance_be(&1, 100);
+		assert_ok!(Scheduler::place_parathread_bid(
+			RuntimeOrigin::signed(1),
+			thread_b,
+			bounded_vec![collator.clone()],
+			15,
+		));
+		Scheduler::free_cores(vec![(core, FreedReason::TimedOut)]);
+		assert_eq!(Scheduler::parathread_starvation(), vec![(thread_a, 4), (thread_b, 1)]);
+
+		run_to_block(5, |_| None);
+		assert_eq!(Scheduler::scheduled()[0].para_id, thread_a);
+		Scheduler::occupied(&[core]);
+
+		// once a candidate of thread A is included, it starts over.
+		Scheduler::free_cores(vec![(core, FreedReason::Concluded)]);
+		assert_eq!(Scheduler::parathread_starvation(), vec![(thread_b, 2)]);
+	});
+}
+
+#[test]
+fn on_demand_orders_pay_a_spot_price_rising_with_the_queue_length() {
+	let thread_a = ParaId::from(1_u32);
+	let thread_b = ParaId::from(2_u32);
+
+	let alice = CollatorId::from(Sr25519Keyring::Alice.public());
+	let bob = CollatorId::from(Sr25519Keyring::Bob.public());
+
+	let config = HostConfiguration {
+		parathread_cores: 1,
+		scheduling_lookahead: 4,
+		on_demand_base_fee: 20,
+		..default_config()
+	};
+
+	new_test_ext(MockGenesisConfig {
+		configuration: crate::configuration::GenesisConfig {
+			config: config.clone(),
+			..Default::default()
+		},
+		..Default::default()
+	})
+	.execute_with(|| {
+		schedule_blank_para(thread_a, ParaKind::Parathread);
+		schedule_blank_para(thread_b, ParaKind::Parathread);
+
+		run_to_block(1, |number| match number {
+			1 => Some(SessionChangeNotification {
+				new_config: config.clone(),
+				validators: vec![ValidatorId::from(Sr25519Keyring::Alice.public())],
+				..Default::default()
+			}),
+			_ => None,
+		});
+
+		for who in 1..=2 {
+			Balances::make_free_balance_be(&who, 100);
+		}
+
+		assert_eq!(Scheduler::spot_price(), 20);
+		assert_noop!(
+			Scheduler::place_order(
+				RuntimeOrigin::signed(1),
+				thread_a,
+				bounded_vec![alice.clone()],
+				19,
+			),
+			Error::<Test>::SpotPriceHigherThanMaxAmount,
+		);
+
+		// the fee is paid right away and nothing is held.
+		let issuance = Balances::total_issuance();
+		assert_ok!(Scheduler::place_order(
+			RuntimeOrigin::signed(1),
+			thread_a,
+			bounded_vec![alice.clone(), bob.clone()],
+			20,
+		));
+		assert_eq!(Balances::free_balance(&1), 80);
+		assert_eq!(Balances::reserved_balance(&1), 0);
+		assert_eq!(Balances::total_issuance(), issuance - 20);
+		assert_eq!(
+			ParathreadClaimCollators::<Test>::get(&thread_a).map(|c| c.into_inner()),
+			Some(vec![bob.clone()]),
+		);
+		assert!(ParathreadClaimDeposits::<Test>::get(&thread_a).is_none());
+
+		// a quarter of the queue is taken.
+		assert_eq!(Scheduler::spot_price(), 25);
+
+		// the fee is refunded if the claim cannot be queued.
+		assert_eq!(
+			Scheduler::place_order(
+				RuntimeOrigin::signed(2),
+				thread_a,
+				bounded_vec![alice.clone()],
+				25,
+			),
+			Err(Error::<Test>::ParathreadClaimExists.into()),
+		);
+		assert_eq!(Balances::free_balance(&2), 100);
+
+		run_to_block(2, |_| None);
+		assert_eq!(Scheduler::scheduled().len(), 1);
+		assert_eq!(Scheduler::scheduled()[0].para_id, thread_a);
+		assert!(Scheduler::is_allowed_collator(&Scheduler::scheduled()[0], &bob));
+		assert_eq!(Scheduler::spot_price(), 20);
+	});
+}
+
+#[test]
+fn core_assignments_record_their_source() {
+	let chain_a = ParaId::from(1_u32);
+	let thread_a = ParaId::from(2_u32);
+	let thread_b = ParaId::from(3_u32);
+
+	let collator = CollatorId::from(Sr25519Keyring::Alice.public());
+
+	let config =
+		HostConfiguration { parathread_cores: 2, on_demand_base_fee: 20, ..default_config() };
+
+	new_test_ext(MockGenesisConfig {
+		configuration: crate::configuration::GenesisConfig {
+			config: config.clone(),
+			..Default::default()
+		},
+		..Default::default()
+	})
+	.execute_with(|| {
+		schedule_blank_para(chain_a, ParaKind::Parachain);
+		schedule_blank_para(thread_a, ParaKind::Parathread);
+		schedule_blank_para(thread_b, ParaKind::Parathread);
+
+		run_to_block(1, |number| match number {
+			1 => Some(SessionChangeNotification {
+				new_config: config.clone(),
+				validators: vec![
+					ValidatorId::from(Sr25519Keyring::Alice.public()),
+					ValidatorId::from(Sr25519Keyring::Bob.public()),
+					ValidatorId::from(Sr25519Keyring::Charlie.public()),
+				],
+				..Default::default()
+			}),
+			_ => None,
+		});
+
+		Balances::make_free_balance_be(&1, 100);
+		Scheduler::add_parathread_claim(ParathreadClaim(thread_a, collator.clone()));
+		assert_ok!(Scheduler::place_order(
+			RuntimeOrigin::signed(1),
+			thread_b,
+			bounded_vec![collator.clone()],
+			20,
+		));
+
+		run_to_block(2, |_| None);
+		let mut sources = Scheduler::scheduled()
+			.iter()
+			.map(|assignment| (assignment.para_id, Scheduler::assignment_source(assignment)))
+			.collect::<Vec<_>>();
+		sources.sort_by_key(|(para_id, _)| *para_id);
+		assert_eq!(
+			sources,
+			vec![
+				(chain_a, CoreAssignmentSource::Lease),
+				(thread_a, CoreAssignmentSource::ParathreadClaim),
+				(thread_b, CoreAssignmentSource::OnDemandOrder),
+			],
+		);
+	});
+}
+
+#[test]
+fn claim_queue_looks_ahead_on_every_core() {
+	let chain_a = ParaId::from(1_u32);
+	let thread_a = ParaId::from(2_u32);
+	let thread_b = ParaId::from(3_u32);
+	let thread_c = ParaId::from(4_u32);
+
+	let collator = CollatorId::from(Sr25519Keyring::Alice.public());
+
+	let config =
+		HostConfiguration { parathread_cores: 1, scheduling_lookahead: 3, ..default_config() };
+
+	new_test_ext(MockGenesisConfig {
+		configuration: crate::configuration::GenesisConfig {
+			config: config.clone(),
+			..Default::default()
+		},
+		..Default::default()
+	})
+	.execute_with(|| {
+		schedule_blank_para(chain_a, ParaKind::Parachain);
+		schedule_blank_para(thread_a, ParaKind::Parathread);
+		schedule_blank_para(thread_b, ParaKind::Parathread);
+		schedule_blank_para(thread_c, ParaKind::Parathread);
+
+		run_to_block(1, |number| match number {
+			1 => Some(SessionChangeNotification {
+				new_config: config.clone(),
+				validators: vec![
+					ValidatorId::from(Sr25519Keyring::Alice.public()),
+					ValidatorId::from(Sr25519Keyring::Bob.public()),
+				],
+				..Default::default()
+			}),
+			_ => None,
+		});
+
+		for thread in [thread_a, thread_b, thread_c] {
+			Scheduler::add_parathread_claim(ParathreadClaim(thread, collator.clone()));
+		}
+
+		// the head of each core's queue is what is scheduled on it.
+		run_to_block(2, |_| None);
+		assert_eq!(Scheduler::scheduled()[1].para_id, thread_a);
+		assert_eq!(
+			Scheduler::claim_queue(),
+			BTreeMap::from([
+				(CoreIndex(0), vec![chain_a; 3]),
+				(CoreIndex(1), vec![thread_a, thread_b, thread_c]),
+			]),
+		);
+
+		// occupying the cores moves the parathread core on to the next claim.
+		Scheduler::occupied(&[CoreIndex(0), CoreIndex(1)]);
+		assert_eq!(
+			Scheduler::claim_queue(),
+			BTreeMap::from([
+				(CoreIndex(0), vec![chain_a; 3]),
+				(CoreIndex(1), vec![thread_b, thread_c]),
+			]),
+		);
+	});
+}
+
+#[test]
+fn frozen_paras_are_not_scheduled() {
+	let chain_a = ParaId::from(1_u32);
+	let chain_b = ParaId::from(2_u32);
+
+	let thread_a = ParaId::from(3_u32);
+	let thread_b = ParaId::from(4_u32);
+
+	let collator = CollatorId::from(Sr25519Keyring::Alice.public());
+
+	let config = HostConfiguration { parathread_cores: 1, ..default_config() };
+
+	new_test_ext(MockGenesisConfig {
+		configuration: crate::configuration::GenesisConfig {
+			config: config.clone(),
+			..Default::default()
+		},
+		..Default::default()
+	})
+	.execute_with(|| {
+		schedule_blank_para(chain_a, ParaKind::Parachain);
+		schedule_blank_para(chain_b, ParaKind::Parachain);
+		schedule_blank_para(thread_a, ParaKind::Parathread);
+		schedule_blank_para(thread_b, ParaKind::Parathread);
+
+		// start a new session to activate, 3 validators for 3 cores.
+		run_to_block(1, |number| match number {
+			1 => Some(SessionChangeNotification {
+				new_config: config.clone(),
+				validators: vec![
+					ValidatorId::from(Sr25519Keyring::Alice.public()),
+					ValidatorId::from(Sr25519Keyring::Bob.public()),
+					ValidatorId::from(Sr25519Keyring::Charlie.public()),
+				],
+				..Default::default()
+			}),
+			_ => None,
+		});
+
+		assert_ok!(Scheduler::try_add_parathread_claim(ParathreadClaim(
+			thread_b,
+			collator.clone()
+		)));
+		assert_ok!(Scheduler::try_add_parathread_claim(ParathreadClaim(
+			thread_a,
+			collator.clone()
+		)));
+
+		// the queued claim of a frozen parathread is dropped.
+		assert_ok!(Paras::set_para_frozen(RuntimeOrigin::root(), chain_a, true));
+		assert_ok!(Paras::set_para_frozen(RuntimeOrigin::root(), thread_b, true));
+		run_to_block(2, |_| None);
+
+		let scheduled = Scheduler::scheduled()
+			.into_iter()
+			.map(|assignment| (assignment.core, assignment.para_id))
+			.collect::<Vec<_>>();
+		assert_eq!(scheduled, vec![(CoreIndex(1), chain_b), (CoreIndex(2), thread_a)]);
+		assert_eq!(ParathreadClaimIndex::<Test>::get(), vec![thread_a]);
+		assert!(!Scheduler::claim_queue().contains_key(&CoreIndex(0)));
+		assert!(matches!(
+			Scheduler::try_add_parathread_claim(ParathreadClaim(thread_b, collator.clone())),
+			Err(Error::<Test>::ParaFrozen)
+		));
+
+		assert_ok!(Paras::set_para_frozen(RuntimeOrigin::root(), chain_a, false));
+		run_to_block(3, |_| None);
+		assert_eq!(Scheduler::scheduled()[0].para_id, chain_a);
+	});
+}
+
+#[test]
+fn core_layout_version_is_bumped_when_the_number_of_cores_changes() {
+	let validators = vec![
+		ValidatorId::from(Sr25519Keyring::Alice.public()),
+		ValidatorId::from(Sr25519Keyring::Bob.public()),
+		ValidatorId::from(Sr25519Keyring::Charlie.public()),
+	];
+	let fewer_cores = HostConfiguration { parathread_cores: 2, ..default_config() };
+
+	new_test_ext(MockGenesisConfig {
+		configuration: crate::configuration::GenesisConfig {
+			config: default_config(),
+			..Default::default()
+		},
+		..Default::default()
+	})
+	.execute_with(|| {
+		let session_change = |new_config: &HostConfiguration<BlockNumber>| {
+			Some(SessionChangeNotification {
+				new_config: new_config.clone(),
+				validators: validators.clone(),
+				..Default::default()
+			})
+		};
+
+		run_to_block(1, |number| match number {
+			1 => session_change(&default_config()),
+			_ => None,
+		});
+		assert_eq!(Scheduler::core_layout_version(), 1);
+		assert!(!Scheduler::is_previous_core_layout(0));
+
+		run_to_block(2, |number| match number {
+			2 => session_change(&fewer_cores),
+			_ => None,
+		});
+		assert_eq!(Scheduler::availability_cores().len(), 2);
+		assert_eq!(Scheduler::core_layout_version(), 2);
+		assert!(Scheduler::is_previous_core_layout(3));
+		assert!(!Scheduler::is_previous_core_layout(2));
+
+		// the same number of cores keeps the layout.
+		run_to_block(3, |number| match number {
+			3 => session_change(&fewer_cores),
+			_ => None,
+		});
+		assert_eq!(Scheduler::core_layout_version(), 2);
+	});
+}
@@ -27,7 +27,10 @@ use frame_support::{
 	pallet_prelude::*,
 	traits::{OneSessionHandler, ValidatorSet, ValidatorSetWithIdentification},
 };
-use primitives::{AssignmentId, AuthorityDiscoveryId, ExecutorParams, SessionIndex, SessionInfo};
+use primitives::{
+	AssignmentId, AuthorityDiscoveryId, ExecutorParams, Hash, SessionIndex, SessionInfo,
+};
+use sp_runtime::traits::{BlakeTwo256, Hash as HashT};
 use sp_std::vec::Vec;
 
 pub use pallet::*;
@@ -107,6 +110,15 @@ pub mod pallet {
 	#[pallet::getter(fn session_executor_params)]
 	pub(crate) type SessionExecutorParams<T: Config> =
 		StorageMap<_, Identity, SessionIndex, ExecutorParams>;
+
+	/// The index of the current session and the hash of its validator set, in the order of
+	/// `SessionInfo::validators`.
+	///
+	/// Only kept while any para is listed in `config.validator_set_hash_subscribers`, whose relay
+	/// chain state proofs include it.
+	#[pallet::storage]
+	#[pallet::getter(fn validator_set_hash)]
+	pub(crate) type ValidatorSetHash<T: Config> = StorageValue<_, (SessionIndex, Hash)>;
 }
 
 /// An abstraction for the authority discovery pallet
@@ -191,9 +203,21 @@ impl<T: Config> Pallet<T> {
 		};
 		Sessions::<T>::insert(&new_session_index, &new_session_info);
 
+		if config.validator_set_hash_subscribers.is_empty() {
+			ValidatorSetHash::<T>::kill();
+		} else {
+			let validators_hash = BlakeTwo256::hash_of(&new_session_info.validators);
+			ValidatorSetHash::<T>::put((new_session_index, validators_hash));
+		}
+
 		SessionExecutorParams::<T>::insert(&new_session_index, config.executor_params);
 	}
 
+	/// The storage key of the hash of the current validator set, see `ValidatorSetHash`.
+	pub(crate) fn validator_set_hash_key() -> Vec<u8> {
+		ValidatorSetHash::<T>::hashed_key().to_vec()
+	}
+
 	/// Called by the initializer to initialize the session info pallet.
 	pub(crate) fn initializer_initialize(_now: T::BlockNumber) -> Weight {
 		Weight::zero()
@@ -25,7 +25,7 @@ use crate::{
 	util::take_active_subset,
 };
 use keyring::Sr25519Keyring;
-use primitives::{BlockNumber, ValidatorId, ValidatorIndex};
+use primitives::{BlockNumber, Id as ParaId, ValidatorId, ValidatorIndex};
 
 fn run_to_block(
 	to: BlockNumber,
@@ -159,6 +159,16 @@ fn session_info_is_based_on_config() {
 		run_to_block(3, new_session_every_block);
 		let session = Sessions::<Test>::get(&3).unwrap();
 		assert_eq!(session.needed_approvals, 42);
+
+		// the remaining approval-checking params follow the config as well
+		Configuration::set_relay_vrf_modulo_samples(RuntimeOrigin::root(), 6).unwrap();
+		Configuration::set_n_delay_tranches(RuntimeOrigin::root(), 89).unwrap();
+		Configuration::set_zeroth_delay_tranche_width(RuntimeOrigin::root(), 2).unwrap();
+		run_to_block(5, new_session_every_block);
+		let session = Sessions::<Test>::get(&5).unwrap();
+		assert_eq!(session.relay_vrf_modulo_samples, 6);
+		assert_eq!(session.n_delay_tranches, 89);
+		assert_eq!(session.zeroth_delay_tranche_width, 2);
 	})
 }
 
@@ -212,3 +222,35 @@ fn session_info_active_subsets() {
 		);
 	})
 }
+
+#[test]
+fn validator_set_hash_is_kept_while_paras_subscribe() {
+	let validators: Vec<ValidatorId> =
+		vec![Sr25519Keyring::Alice.public().into(), Sr25519Keyring::Bob.public().into()];
+	let notify = |session_index| {
+		SessionInfo::initializer_on_new_session(&SessionChangeNotification {
+			session_index,
+			validators: validators.clone(),
+			..Default::default()
+		})
+	};
+
+	new_test_ext(genesis_config()).execute_with(|| {
+		notify(1);
+		assert!(ValidatorSetHash::<Test>::get().is_none());
+
+		let mut config = default_config();
+		config.validator_set_hash_subscribers = vec![ParaId::from(100)];
+		configuration::ActiveConfig::<Test>::put(config);
+
+		notify(2);
+		assert_eq!(
+			SessionInfo::validator_set_hash(),
+			Some((2, BlakeTwo256::hash_of(&Sessions::<Test>::get(&2).unwrap().validators))),
+		);
+
+		configuration::ActiveConfig::<Test>::put(default_config());
+		notify(3);
+		assert!(ValidatorSetHash::<Test>::get().is_none());
+	})
+}
@@ -21,6 +21,8 @@
 
 use frame_support::pallet_prelude::*;
 use primitives::{SessionIndex, ValidatorId, ValidatorIndex};
+use scale_info::TypeInfo;
+use sp_runtime::traits::{Saturating, Zero};
 use sp_std::vec::Vec;
 
 use rand::{seq::SliceRandom, SeedableRng};
@@ -35,9 +37,30 @@ pub use pallet::*;
 // which guarantees that at least one full session has passed before any changes are applied.
 pub(crate) const SESSION_DELAY: SessionIndex = 2;
 
+/// The maximum `max_candidate_relay_parent_depth` the host configuration may set.
+pub const MAX_CANDIDATE_RELAY_PARENT_DEPTH: u32 = 16;
+
 #[cfg(test)]
 mod tests;
 
+/// A recent relay chain block that candidates are allowed to use as their relay parent.
+#[derive(Encode, Decode, Clone, PartialEq, RuntimeDebug, TypeInfo)]
+pub struct AllowedRelayParent<Hash, BlockNumber> {
+	/// The hash of the block.
+	pub hash: Hash,
+	/// The storage root of the block.
+	pub state_root: Hash,
+	/// The number of the block.
+	pub number: BlockNumber,
+}
+
+/// The relay parents candidates are allowed to use: the most recent one and at most
+/// [`MAX_CANDIDATE_RELAY_PARENT_DEPTH`] older ones.
+pub type AllowedRelayParentsOf<T> = BoundedVec<
+	AllowedRelayParent<<T as frame_system::Config>::Hash, <T as frame_system::Config>::BlockNumber>,
+	ConstU32<{ MAX_CANDIDATE_RELAY_PARENT_DEPTH + 1 }>,
+>;
+
 #[frame_support::pallet]
 pub mod pallet {
 	use super::*;
@@ -47,7 +70,10 @@ pub mod pallet {
 	pub struct Pallet<T>(_);
 
 	#[pallet::config]
-	pub trait Config: frame_system::Config {}
+	pub trait Config: frame_system::Config {
+		/// The validators disabled for the rest of the session, e.g. after an offence.
+		type DisabledValidators: frame_support::traits::DisabledValidators;
+	}
 
 	/// The current session index.
 	#[pallet::storage]
@@ -67,6 +93,22 @@ pub mod pallet {
 	#[pallet::getter(fn active_validator_keys)]
 	pub(super) type ActiveValidatorKeys<T: Config> = StorageValue<_, Vec<ValidatorId>, ValueQuery>;
 
+	/// The recent relay chain blocks candidates may use as their relay parent, oldest first.
+	///
+	/// The most recent entry is the parent of the block that last processed the inclusion
+	/// inherent.
+	#[pallet::storage]
+	pub(super) type AllowedRelayParents<T: Config> =
+		StorageValue<_, AllowedRelayParentsOf<T>, ValueQuery>;
+
+	/// The storage roots of recent relay chain blocks, by block number.
+	///
+	/// Unlike `AllowedRelayParents`, entries are kept across sessions, for
+	/// `relay_storage_roots_retention` blocks.
+	#[pallet::storage]
+	pub(super) type RelayStorageRoots<T: Config> =
+		StorageMap<_, Twox64Concat, T::BlockNumber, T::Hash>;
+
 	#[pallet::call]
 	impl<T: Config> Pallet<T> {}
 }
@@ -109,9 +151,92 @@ impl<T: Config> Pallet<T> {
 		ActiveValidatorIndices::<T>::set(shuffled_indices);
 		ActiveValidatorKeys::<T>::set(active_validator_keys.clone());
 
+		// Candidates built on blocks of the previous session were backed by a different set of
+		// validators.
+		AllowedRelayParents::<T>::kill();
+
+		// The retention period may have shrunk with the new configuration, in which case noting
+		// new roots would never reach the entries that are now out of the window.
+		let now = <frame_system::Pallet<T>>::block_number();
+		let retention = new_config.relay_storage_roots_retention;
+		let stale = RelayStorageRoots::<T>::iter_keys()
+			.filter(|number| retention.is_zero() || number.saturating_add(retention) < now)
+			.collect::<Vec<_>>();
+		for number in stale {
+			RelayStorageRoots::<T>::remove(number);
+		}
+
 		active_validator_keys
 	}
 
+	/// Returns the indices of the active validators that are disabled for the rest of the session.
+	pub(crate) fn disabled_validators() -> Vec<ValidatorIndex> {
+		use frame_support::traits::DisabledValidators;
+
+		Self::active_validator_indices()
+			.into_iter()
+			.enumerate()
+			.filter(|(_, index)| T::DisabledValidators::is_disabled(index.0))
+			.map(|(active_index, _)| ValidatorIndex(active_index as _))
+			.collect()
+	}
+
+	/// Note a new relay parent that candidates may be built on, keeping at most `max_depth`
+	/// older ones around.
+	///
+	/// `max_depth` is capped at [`MAX_CANDIDATE_RELAY_PARENT_DEPTH`], which the configuration
+	/// module doesn't allow to exceed in the first place.
+	pub(crate) fn add_allowed_relay_parent(
+		hash: T::Hash,
+		state_root: T::Hash,
+		number: T::BlockNumber,
+		max_depth: u32,
+	) {
+		AllowedRelayParents::<T>::mutate(|parents| {
+			let mut entries = sp_std::mem::take(parents).into_inner();
+			entries.push(AllowedRelayParent { hash, state_root, number });
+
+			let keep = max_depth.min(MAX_CANDIDATE_RELAY_PARENT_DEPTH) as usize + 1;
+			if entries.len() > keep {
+				entries.drain(..entries.len() - keep);
+			}
+			*parents = BoundedVec::truncate_from(entries);
+		});
+	}
+
+	/// Note the storage root of the relay chain block with the given number, keeping the roots of
+	/// the last `retention` blocks around.
+	pub(crate) fn note_relay_storage_root(
+		number: T::BlockNumber,
+		state_root: T::Hash,
+		retention: T::BlockNumber,
+	) {
+		if retention.is_zero() {
+			return
+		}
+		RelayStorageRoots::<T>::insert(number, state_root);
+		if number >= retention {
+			RelayStorageRoots::<T>::remove(number - retention);
+		}
+	}
+
+	/// The storage roots of the recent relay chain blocks, ascending by block number.
+	pub fn relay_storage_roots() -> Vec<(T::BlockNumber, T::Hash)> {
+		let mut roots = RelayStorageRoots::<T>::iter().collect::<Vec<_>>();
+		roots.sort_unstable_by_key(|(number, _)| *number);
+		roots
+	}
+
+	/// Look up a relay parent candidates are currently allowed to use.
+	pub(crate) fn allowed_relay_parent(
+		hash: T::Hash,
+	) -> Option<AllowedRelayParent<T::Hash, T::BlockNumber>> {
+		AllowedRelayParents::<T>::get()
+			.into_iter()
+			.rev()
+			.find(|parent| parent.hash == hash)
+	}
+
 	/// Return the session index that should be used for any future scheduled changes.
 	pub fn scheduled_session() -> SessionIndex {
 		Self::session_index().saturating_add(SESSION_DELAY)
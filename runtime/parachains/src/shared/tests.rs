@@ -17,9 +17,10 @@
 use super::*;
 use crate::{
 	configuration::HostConfiguration,
-	mock::{new_test_ext, MockGenesisConfig, ParasShared},
+	mock::{new_test_ext, MockGenesisConfig, ParasShared, System},
 };
 use keyring::Sr25519Keyring;
+use primitives::Hash;
 
 fn validator_pubkeys(val_ids: &[Sr25519Keyring]) -> Vec<ValidatorId> {
 	val_ids.iter().map(|v| v.public().into()).collect()
@@ -97,3 +98,83 @@ fn sets_truncates_and_shuffles_validators() {
 		);
 	});
 }
+
+#[test]
+fn allowed_relay_parents_are_kept_within_depth() {
+	new_test_ext(MockGenesisConfig::default()).execute_with(|| {
+		let hash = |n: u8| Hash::repeat_byte(n);
+
+		for n in 1..=4 {
+			ParasShared::add_allowed_relay_parent(hash(n), hash(n + 100), n as u32, 2);
+		}
+
+		assert!(ParasShared::allowed_relay_parent(hash(1)).is_none());
+		for n in 2..=4 {
+			assert_eq!(
+				ParasShared::allowed_relay_parent(hash(n)),
+				Some(AllowedRelayParent {
+					hash: hash(n),
+					state_root: hash(n + 100),
+					number: n as u32
+				}),
+			);
+		}
+
+		// Relay parents of the previous session are not allowed anymore.
+		ParasShared::initializer_on_new_session(1, [1; 32], &HostConfiguration::default(), vec![]);
+		assert!(ParasShared::allowed_relay_parent(hash(4)).is_none());
+	});
+}
+
+#[test]
+fn allowed_relay_parents_are_bounded() {
+	new_test_ext(MockGenesisConfig::default()).execute_with(|| {
+		let hash = |n: u32| Hash::from_low_u64_be(n as u64);
+
+		let total = MAX_CANDIDATE_RELAY_PARENT_DEPTH + 4;
+		for n in 1..=total {
+			ParasShared::add_allowed_relay_parent(hash(n), hash(n), n, u32::MAX);
+		}
+
+		// A depth beyond the bound keeps as many relay parents as the bound allows.
+		assert_eq!(
+			AllowedRelayParents::<Test>::get().len(),
+			MAX_CANDIDATE_RELAY_PARENT_DEPTH as usize + 1
+		);
+		let oldest = total - MAX_CANDIDATE_RELAY_PARENT_DEPTH;
+		assert!(ParasShared::allowed_relay_parent(hash(oldest - 1)).is_none());
+		assert!(ParasShared::allowed_relay_parent(hash(oldest)).is_some());
+		assert!(ParasShared::allowed_relay_parent(hash(total)).is_some());
+	});
+}
+
+#[test]
+fn relay_storage_roots_are_kept_for_the_retention_period() {
+	new_test_ext(MockGenesisConfig::default()).execute_with(|| {
+		let root = |n: u32| Hash::repeat_byte(n as u8);
+
+		// Nothing is recorded without a retention period.
+		ParasShared::note_relay_storage_root(1, root(1), 0);
+		assert!(ParasShared::relay_storage_roots().is_empty());
+
+		for n in 1..=5 {
+			ParasShared::note_relay_storage_root(n, root(n), 3);
+		}
+		assert_eq!(
+			ParasShared::relay_storage_roots(),
+			vec![(3, root(3)), (4, root(4)), (5, root(5))]
+		);
+
+		// Unlike the allowed relay parents, the roots are kept across sessions, unless the
+		// retention period shrinks.
+		System::set_block_number(6);
+		let mut config = HostConfiguration::default();
+		config.relay_storage_roots_retention = 3;
+		ParasShared::initializer_on_new_session(1, [1; 32], &config, vec![]);
+		assert_eq!(ParasShared::relay_storage_roots().len(), 3);
+
+		config.relay_storage_roots_retention = 1;
+		ParasShared::initializer_on_new_session(2, [1; 32], &config, vec![]);
+		assert_eq!(ParasShared::relay_storage_roots(), vec![(5, root(5))]);
+	});
+}
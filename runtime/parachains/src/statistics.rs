@@ -0,0 +1,193 @@
+// Copyright (C) Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The statistics pallet counts the candidates backed and included during the current session,
+//! per para and per backing group, as well as the included candidates per collator.
+//!
+//! The counters are reset on every session change. They are not used by the runtime itself, but
+//! exposed to block explorers and meant to help calibrating validator and collator rewards.
+//!
+//! The parachain activity of each block is also counted, and summarized in a
+//! [`Event::ParaActivity`] event when the block is finalized.
+
+use crate::{inclusion::InclusionHooks, initializer::SessionChangeNotification, shared};
+use frame_support::pallet_prelude::*;
+use primitives::{
+	vstaging::{
+		CandidateCounts, ParaActivity, SessionCandidateStatistics, SessionCollatorHeadCounts,
+	},
+	CollatorId, GroupIndex, Id as ParaId,
+};
+use sp_std::prelude::*;
+
+pub use pallet::*;
+
+#[cfg(test)]
+mod tests;
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::*;
+
+	#[pallet::pallet]
+	#[pallet::without_storage_info]
+	pub struct Pallet<T>(_);
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config + shared::Config {
+		/// The outer event type.
+		type RuntimeEvent: From<Event> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+	}
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event {
+		/// The parachain activity of the block. Blocks without any activity don't emit this
+		/// event. `[activity]`
+		ParaActivity(ParaActivity),
+	}
+
+	/// The candidates backed and included in the current session, per para.
+	#[pallet::storage]
+	pub(super) type ParaCandidateCounts<T: Config> =
+		StorageMap<_, Twox64Concat, ParaId, CandidateCounts, ValueQuery>;
+
+	/// The candidates backed and included in the current session, per backing group.
+	#[pallet::storage]
+	pub(super) type GroupCandidateCounts<T: Config> =
+		StorageMap<_, Twox64Concat, GroupIndex, CandidateCounts, ValueQuery>;
+
+	/// The candidates included in the current session, per collator.
+	#[pallet::storage]
+	pub(super) type CollatorHeadCounts<T: Config> =
+		StorageMap<_, Blake2_128Concat, CollatorId, u32, ValueQuery>;
+
+	/// The parachain activity of the current block, deposited as an event and removed when the
+	/// block is finalized.
+	#[pallet::storage]
+	pub(super) type BlockParaActivity<T: Config> = StorageValue<_, ParaActivity, ValueQuery>;
+}
+
+impl<T: Config> Pallet<T> {
+	/// Called by the initializer to initialize the statistics pallet.
+	pub(crate) fn initializer_initialize(_now: T::BlockNumber) -> Weight {
+		// The activity of the block is taken when finalizing it.
+		T::DbWeight::get().reads_writes(1, 1)
+	}
+
+	/// Called by the initializer to finalize the statistics pallet.
+	///
+	/// Deposits the parachain activity of the block as an event, if there was any.
+	pub(crate) fn initializer_finalize() {
+		let activity = BlockParaActivity::<T>::take();
+		if activity != ParaActivity::default() {
+			Self::deposit_event(Event::ParaActivity(activity));
+		}
+	}
+
+	/// Called by the initializer to note that a new session has started.
+	///
+	/// Resets all counters.
+	pub(crate) fn initializer_on_new_session(
+		_notification: &SessionChangeNotification<T::BlockNumber>,
+	) {
+		// There is at most one entry per core, so the maps are small enough to be cleared at once.
+		let _ = ParaCandidateCounts::<T>::clear(u32::MAX, None);
+		let _ = GroupCandidateCounts::<T>::clear(u32::MAX, None);
+		// There is at most one entry per included candidate, which are bounded by the cores and
+		// the length of the session.
+		let _ = CollatorHeadCounts::<T>::clear(u32::MAX, None);
+	}
+
+	/// Note that a candidate of the given para was backed by the given group.
+	pub(crate) fn note_backed(para_id: ParaId, group: GroupIndex) {
+		BlockParaActivity::<T>::mutate(|activity| {
+			activity.backed = activity.backed.saturating_add(1)
+		});
+		ParaCandidateCounts::<T>::mutate(para_id, |counts| {
+			counts.backed = counts.backed.saturating_add(1)
+		});
+		GroupCandidateCounts::<T>::mutate(group, |counts| {
+			counts.backed = counts.backed.saturating_add(1)
+		});
+	}
+
+	/// Note that a candidate of the given para, backed by the given group, was included.
+	pub(crate) fn note_included(para_id: ParaId, group: GroupIndex) {
+		BlockParaActivity::<T>::mutate(|activity| {
+			activity.included = activity.included.saturating_add(1)
+		});
+		ParaCandidateCounts::<T>::mutate(para_id, |counts| {
+			counts.included = counts.included.saturating_add(1)
+		});
+		GroupCandidateCounts::<T>::mutate(group, |counts| {
+			counts.included = counts.included.saturating_add(1)
+		});
+	}
+
+	/// Note that a candidate produced by the given collator was included.
+	pub(crate) fn note_collator_head(collator: CollatorId) {
+		CollatorHeadCounts::<T>::mutate(collator, |count| *count = count.saturating_add(1));
+	}
+
+	/// Note that a candidate pending availability timed out in the current block.
+	pub(crate) fn note_timed_out() {
+		BlockParaActivity::<T>::mutate(|activity| {
+			activity.timed_out = activity.timed_out.saturating_add(1)
+		});
+	}
+
+	/// Note that a core was freed in the current block.
+	pub(crate) fn note_core_freed() {
+		BlockParaActivity::<T>::mutate(|activity| {
+			activity.freed_cores = activity.freed_cores.saturating_add(1)
+		});
+	}
+
+	/// The counters of the current session, sorted by para ID and group index respectively.
+	pub fn candidate_statistics() -> SessionCandidateStatistics {
+		let mut paras: Vec<_> = ParaCandidateCounts::<T>::iter().collect();
+		paras.sort_unstable_by_key(|(para_id, _)| *para_id);
+		let mut groups: Vec<_> = GroupCandidateCounts::<T>::iter().collect();
+		groups.sort_unstable_by_key(|(group, _)| group.0);
+
+		SessionCandidateStatistics {
+			session_index: <shared::Pallet<T>>::session_index(),
+			paras,
+			groups,
+		}
+	}
+
+	/// The included candidates per collator in the current session, sorted by collator ID.
+	pub fn collator_head_counts() -> SessionCollatorHeadCounts {
+		let mut collators: Vec<_> = CollatorHeadCounts::<T>::iter().collect();
+		collators.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+
+		SessionCollatorHeadCounts { session_index: <shared::Pallet<T>>::session_index(), collators }
+	}
+}
+
+/// Counts the availability bitfields of the block, when set as (one of) the
+/// [`crate::inclusion::Config::InclusionHooks`].
+impl<T: Config> InclusionHooks for Pallet<T> {
+	fn on_bitfields_processed(count: u32) {
+		if count > 0 {
+			BlockParaActivity::<T>::mutate(|activity| {
+				activity.bitfields = activity.bitfields.saturating_add(count)
+			});
+		}
+	}
+}
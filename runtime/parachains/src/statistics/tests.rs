@@ -0,0 +1,129 @@
+// Copyright (C) Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::*;
+use crate::mock::{new_test_ext, ParasShared, RuntimeEvent, Statistics, System, Test};
+use keyring::Sr25519Keyring;
+
+#[test]
+fn candidates_are_counted_per_para_and_group() {
+	new_test_ext(Default::default()).execute_with(|| {
+		let chain_a = ParaId::from(2);
+		let chain_b = ParaId::from(1);
+
+		Statistics::note_backed(chain_a, GroupIndex(1));
+		Statistics::note_backed(chain_b, GroupIndex(0));
+		Statistics::note_backed(chain_a, GroupIndex(0));
+		Statistics::note_included(chain_a, GroupIndex(1));
+
+		assert_eq!(
+			Statistics::candidate_statistics(),
+			SessionCandidateStatistics {
+				session_index: 0,
+				paras: vec![
+					(chain_b, CandidateCounts { backed: 1, included: 0 }),
+					(chain_a, CandidateCounts { backed: 2, included: 1 }),
+				],
+				groups: vec![
+					(GroupIndex(0), CandidateCounts { backed: 2, included: 0 }),
+					(GroupIndex(1), CandidateCounts { backed: 1, included: 1 }),
+				],
+			},
+		);
+	});
+}
+
+#[test]
+fn counters_are_reset_on_new_session() {
+	new_test_ext(Default::default()).execute_with(|| {
+		Statistics::note_backed(ParaId::from(1), GroupIndex(0));
+		Statistics::note_included(ParaId::from(1), GroupIndex(0));
+
+		ParasShared::set_session_index(1);
+		Statistics::initializer_on_new_session(&Default::default());
+
+		assert_eq!(
+			Statistics::candidate_statistics(),
+			SessionCandidateStatistics { session_index: 1, paras: vec![], groups: vec![] },
+		);
+	});
+}
+
+#[test]
+fn included_candidates_are_counted_per_collator() {
+	new_test_ext(Default::default()).execute_with(|| {
+		let alice = CollatorId::from(Sr25519Keyring::Alice.public());
+		let bob = CollatorId::from(Sr25519Keyring::Bob.public());
+
+		Statistics::note_collator_head(bob.clone());
+		Statistics::note_collator_head(alice.clone());
+		Statistics::note_collator_head(bob.clone());
+
+		let mut expected = vec![(alice, 1), (bob, 2)];
+		expected.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+		assert_eq!(
+			Statistics::collator_head_counts(),
+			SessionCollatorHeadCounts { session_index: 0, collators: expected },
+		);
+
+		ParasShared::set_session_index(1);
+		Statistics::initializer_on_new_session(&Default::default());
+
+		assert_eq!(
+			Statistics::collator_head_counts(),
+			SessionCollatorHeadCounts { session_index: 1, collators: vec![] },
+		);
+	});
+}
+
+#[test]
+fn block_activity_is_deposited_as_an_event() {
+	new_test_ext(Default::default()).execute_with(|| {
+		// No events are deposited in the genesis block.
+		System::set_block_number(1);
+
+		// Nothing happened, so nothing is deposited.
+		Statistics::initializer_finalize();
+		assert!(System::events().is_empty());
+
+		Statistics::note_backed(ParaId::from(1), GroupIndex(0));
+		Statistics::note_backed(ParaId::from(2), GroupIndex(1));
+		Statistics::note_included(ParaId::from(1), GroupIndex(0));
+		Statistics::note_timed_out();
+		<Statistics as InclusionHooks>::on_bitfields_processed(3);
+		Statistics::note_core_freed();
+		Statistics::note_core_freed();
+		Statistics::initializer_finalize();
+
+		let activity =
+			ParaActivity { backed: 2, included: 1, timed_out: 1, bitfields: 3, freed_cores: 2 };
+		assert_eq!(
+			System::events().into_iter().map(|record| record.event).collect::<Vec<_>>(),
+			vec![RuntimeEvent::Statistics(Event::ParaActivity(activity))],
+		);
+		// Nothing ends up in the header.
+		assert!(System::digest().logs.is_empty());
+		// The counters start over with the next block, unlike the session counters.
+		assert_eq!(BlockParaActivity::<Test>::get(), ParaActivity::default());
+		assert_eq!(
+			Statistics::candidate_statistics().paras,
+			vec![
+				(ParaId::from(1), CandidateCounts { backed: 1, included: 1 }),
+				(ParaId::from(2), CandidateCounts { backed: 1, included: 0 }),
+			],
+		);
+	});
+}
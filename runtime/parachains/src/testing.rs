@@ -0,0 +1,238 @@
+// Copyright (C) Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Helpers for runtimes embedding the parachains modules to set up the state of these modules in
+//! their own tests.
+//!
+//! Only available with the `testing` feature.
+
+use crate::{
+	configuration::{self, HostConfiguration},
+	inclusion::{self, CandidatePendingAvailability},
+	initializer::SessionChangeNotification,
+	paras::{self, ParaGenesisArgs, ParaKind},
+	scheduler, shared,
+};
+use bitvec::{bitvec, order::Lsb0 as BitOrderLsb0};
+use frame_support::traits::GenesisBuild;
+use primitives::{
+	vstaging::CoreAssignmentSource, CandidateCommitments, CandidateDescriptor, CandidateReceipt,
+	CoreIndex, CoreOccupied, GroupIndex, Id as ParaId, ValidationCode, ValidatorId,
+};
+
+/// Builds [`sp_io::TestExternalities`] with the genesis state of the parachains modules, plus a
+/// first session of validators and candidates pending availability.
+///
+/// ```ignore
+/// let mut ext = TestExternalitiesBuilder::<Runtime>::new()
+/// 	.with_para(2000.into(), ParaKind::Parachain)
+/// 	.with_validators(validators)
+/// 	.with_pending_candidate(2000.into(), descriptor, commitments)
+/// 	.build();
+/// ```
+pub struct TestExternalitiesBuilder<T: inclusion::Config> {
+	system: frame_system::GenesisConfig,
+	config: HostConfiguration<T::BlockNumber>,
+	paras: Vec<(ParaId, ParaGenesisArgs)>,
+	validators: Vec<ValidatorId>,
+	pending: Vec<(ParaId, CandidateDescriptor<T::Hash>, CandidateCommitments)>,
+}
+
+impl<T: inclusion::Config> Default for TestExternalitiesBuilder<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T: inclusion::Config> TestExternalitiesBuilder<T> {
+	/// A builder for the default host configuration, without any paras or validators.
+	pub fn new() -> Self {
+		Self {
+			system: Default::default(),
+			config: HostConfiguration::default(),
+			paras: Vec::new(),
+			validators: Vec::new(),
+			pending: Vec::new(),
+		}
+	}
+
+	/// Use the given genesis configuration of `frame_system`.
+	pub fn with_system(mut self, system: frame_system::GenesisConfig) -> Self {
+		self.system = system;
+		self
+	}
+
+	/// Use the given host configuration.
+	pub fn with_config(mut self, config: HostConfiguration<T::BlockNumber>) -> Self {
+		self.config = config;
+		self
+	}
+
+	/// Register a para of the given kind at genesis, with an empty head and a dummy validation
+	/// code.
+	pub fn with_para(self, id: ParaId, para_kind: ParaKind) -> Self {
+		let args = ParaGenesisArgs {
+			genesis_head: Vec::new().into(),
+			validation_code: ValidationCode(vec![1, 2, 3]),
+			para_kind,
+		};
+		self.with_para_genesis(id, args)
+	}
+
+	/// Register a para with the given genesis arguments at genesis.
+	pub fn with_para_genesis(mut self, id: ParaId, args: ParaGenesisArgs) -> Self {
+		self.paras.push((id, args));
+		self
+	}
+
+	/// Start the first session with the given validators, which are all active and partitioned
+	/// into groups by the scheduler.
+	pub fn with_validators(mut self, validators: Vec<ValidatorId>) -> Self {
+		self.validators = validators;
+		self
+	}
+
+	/// Occupy the core of the given parachain with a candidate pending availability, which has
+	/// no availability votes yet.
+	///
+	/// The candidate is considered backed at genesis by the group assigned to the core.
+	pub fn with_pending_candidate(
+		mut self,
+		para_id: ParaId,
+		descriptor: CandidateDescriptor<T::Hash>,
+		commitments: CandidateCommitments,
+	) -> Self {
+		self.pending.push((para_id, descriptor, commitments));
+		self
+	}
+
+	/// Build the test externalities.
+	///
+	/// Panics if a candidate pending availability is given for a para which is not a parachain
+	/// registered at genesis.
+	pub fn build(self) -> sp_io::TestExternalities {
+		let Self { system, config, paras, validators, pending } = self;
+
+		let mut storage = system.build_storage::<T>().expect("system genesis is valid; qed");
+		GenesisBuild::<T>::assimilate_storage(
+			&configuration::GenesisConfig::<T> { config: config.clone() },
+			&mut storage,
+		)
+		.expect("configuration genesis is valid; qed");
+		GenesisBuild::<T>::assimilate_storage(&paras::GenesisConfig { paras }, &mut storage)
+			.expect("paras genesis is valid; qed");
+
+		let mut ext = sp_io::TestExternalities::new(storage);
+		ext.execute_with(|| {
+			let n_validators = validators.len();
+			shared::Pallet::<T>::set_active_validators_ascending(validators.clone());
+			scheduler::Pallet::<T>::initializer_on_new_session(&SessionChangeNotification {
+				validators,
+				new_config: config,
+				..Default::default()
+			});
+
+			let parachains = paras::Pallet::<T>::parachains();
+			let now = frame_system::Pallet::<T>::block_number();
+			for (para_id, descriptor, commitments) in pending {
+				let core = parachains
+					.binary_search(&para_id)
+					.map(|i| CoreIndex(i as u32))
+					.expect("candidates pending availability must be for genesis parachains");
+				let backing_group = GroupIndex(core.0);
+				let group_size = scheduler::Pallet::<T>::group_validators(backing_group)
+					.map_or(0, |group| group.len());
+				let hash = CandidateReceipt {
+					descriptor: descriptor.clone(),
+					commitments_hash: commitments.hash(),
+				}
+				.hash();
+
+				scheduler::AvailabilityCores::<T>::mutate(|cores| {
+					cores[core.0 as usize] = Some(CoreOccupied::Parachain);
+				});
+				inclusion::PendingAvailability::<T>::insert(
+					para_id,
+					core,
+					CandidatePendingAvailability::new(
+						core,
+						hash,
+						descriptor,
+						bitvec![u8, BitOrderLsb0; 0; n_validators],
+						bitvec![u8, BitOrderLsb0; 1; group_size],
+						now,
+						now,
+						backing_group,
+						CoreAssignmentSource::Lease,
+					),
+				);
+				inclusion::PendingAvailabilityCommitments::<T>::insert(para_id, core, commitments);
+			}
+		});
+		ext
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::{ParaInclusion, Paras, Scheduler, Test};
+	use keyring::Sr25519Keyring;
+	use primitives::CandidateHash;
+	use test_helpers::dummy_candidate_descriptor;
+
+	#[test]
+	fn builds_paras_validators_and_pending_candidates() {
+		let chain = ParaId::from(1_u32);
+		let thread = ParaId::from(2_u32);
+		let validators = [Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie]
+			.iter()
+			.map(|k| ValidatorId::from(k.public()))
+			.collect();
+
+		let mut config = HostConfiguration::default();
+		config.parathread_cores = 1;
+
+		TestExternalitiesBuilder::<Test>::new()
+			.with_config(config)
+			.with_para(chain, ParaKind::Parachain)
+			.with_para(thread, ParaKind::Parathread)
+			.with_validators(validators)
+			.with_pending_candidate(
+				chain,
+				dummy_candidate_descriptor(Default::default()),
+				CandidateCommitments::default(),
+			)
+			.build()
+			.execute_with(|| {
+				assert_eq!(Paras::parachains(), vec![chain]);
+				assert!(Paras::is_parathread(thread));
+				assert_eq!(shared::Pallet::<Test>::active_validator_keys().len(), 3);
+				assert_eq!(
+					Scheduler::availability_cores(),
+					vec![Some(CoreOccupied::Parachain), None],
+				);
+
+				let pending = ParaInclusion::pending_availability(chain, CoreIndex(0)).unwrap();
+				assert_eq!(pending.core_occupied(), CoreIndex(0));
+				assert_ne!(pending.candidate_hash(), CandidateHash(Default::default()));
+				assert!(inclusion::PendingAvailabilityCommitments::<Test>::contains_key(
+					chain,
+					CoreIndex(0),
+				));
+			});
+	}
+}
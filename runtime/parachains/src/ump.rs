@@ -22,7 +22,13 @@ use frame_support::{pallet_prelude::*, traits::EnsureOrigin};
 use frame_system::pallet_prelude::*;
 use polkadot_parachain::primitives::UpwardMessages;
 use primitives::{Id as ParaId, UpwardMessage};
-use sp_std::{collections::btree_map::BTreeMap, fmt, marker::PhantomData, mem, prelude::*};
+use sp_std::{
+	collections::{btree_map::BTreeMap, btree_set::BTreeSet},
+	fmt,
+	marker::PhantomData,
+	mem,
+	prelude::*,
+};
 use xcm::latest::Outcome;
 
 pub use pallet::*;
@@ -257,6 +263,9 @@ pub mod pallet {
 		/// The weight limit for handling upward messages was reached.
 		/// \[ id, remaining, required \]
 		WeightExhausted(MessageId, Weight, Weight),
+		/// The weight budget of a para for handling upward messages in this block was reached.
+		/// \[ para, id, remaining, required \]
+		ParaWeightExhausted(ParaId, MessageId, Weight, Weight),
 		/// Some upward messages have been received and will be processed.
 		/// \[ para, count, size \]
 		UpwardMessagesReceived(ParaId, u32, u32),
@@ -507,6 +516,12 @@ impl<T: Config> Pallet<T> {
 		let mut cursor = NeedsDispatchCursor::new::<T>();
 		let mut queue_cache = QueueCache::new();
 
+		// The weight each para may use in this block, if limited, and what they used so far.
+		let para_budget = (config.ump_max_weight_per_para != Weight::zero())
+			.then_some(config.ump_max_weight_per_para);
+		let mut para_weight_used = BTreeMap::<ParaId, Weight>::new();
+		let mut exhausted_paras = BTreeSet::<ParaId>::new();
+
 		while let Some(dispatchee) = cursor.peek() {
 			if weight_used.any_gte(config.ump_service_total_weight) ||
 				messages_processed >= MAX_MESSAGES_PER_BLOCK
@@ -531,20 +546,45 @@ impl<T: Config> Pallet<T> {
 				config.ump_service_total_weight - weight_used
 			};
 
+			if exhausted_paras.contains(&dispatchee) {
+				// the paras that still have messages all used up their budget for this block.
+				if exhausted_paras.len() >= cursor.len() {
+					break
+				}
+				cursor.advance();
+				continue
+			}
+			let para_max_weight = match para_budget {
+				Some(budget) => {
+					let used = para_weight_used.get(&dispatchee).copied().unwrap_or_default();
+					max_weight.min(budget.saturating_sub(used))
+				},
+				None => max_weight,
+			};
+
 			// attempt to process the next message from the queue of the dispatchee; if not beyond
 			// our remaining weight limit, then consume it.
 			let maybe_next = queue_cache.peek_front::<T>(dispatchee);
 			if let Some(upward_message) = maybe_next {
 				messages_processed += 1;
-				match T::UmpSink::process_upward_message(dispatchee, upward_message, max_weight) {
+				match T::UmpSink::process_upward_message(
+					dispatchee,
+					upward_message,
+					para_max_weight,
+				) {
 					Ok(used) => {
 						weight_used += used;
+						para_weight_used.entry(dispatchee).or_default().saturating_accrue(used);
 						let _ = queue_cache.consume_front::<T>(dispatchee);
 					},
 					Err((id, required)) => {
 						let is_under_limit = Overweight::<T>::count() < MAX_OVERWEIGHT_MESSAGES;
 						weight_used.saturating_accrue(T::DbWeight::get().reads(1));
-						if required.any_gt(config.ump_max_individual_weight) && is_under_limit {
+						// a message that needs more than a para may ever use in a block could never
+						// be dispatched, so it is treated as overweight as well.
+						let is_overweight = required.any_gt(config.ump_max_individual_weight) ||
+							para_budget.map_or(false, |budget| required.any_gt(budget));
+						if is_overweight && is_under_limit {
 							// overweight - add to overweight queue and continue with message
 							// execution consuming the message.
 							let upward_message = queue_cache.consume_front::<T>(dispatchee).expect(
@@ -556,6 +596,16 @@ impl<T: Config> Pallet<T> {
 							Self::deposit_event(Event::OverweightEnqueued(
 								dispatchee, id, index, required,
 							));
+						} else if !required.any_gt(max_weight) {
+							// the message would fit into this block, but not into what is left of
+							// the para's budget. Let the other paras go ahead.
+							Self::deposit_event(Event::ParaWeightExhausted(
+								dispatchee,
+								id,
+								para_max_weight,
+								required,
+							));
+							exhausted_paras.insert(dispatchee);
 						} else {
 							// we process messages in order and don't drop them if we run out of weight,
 							// so need to break here without calling `consume_front`.
@@ -727,6 +777,11 @@ impl NeedsDispatchCursor {
 		Self { needs_dispatch, index: initial_index }
 	}
 
+	/// Returns the number of items the cursor iterates over.
+	fn len(&self) -> usize {
+		self.needs_dispatch.len()
+	}
+
 	/// Returns the item the cursor points to.
 	fn peek(&self) -> Option<ParaId> {
 		self.needs_dispatch.get(self.index).cloned()
@@ -17,7 +17,7 @@
 use super::*;
 use crate::mock::{
 	assert_last_event, new_test_ext, take_processed, Configuration, MockGenesisConfig,
-	RuntimeOrigin, System, Test, Ump,
+	RuntimeEvent, RuntimeOrigin, System, Test, Ump,
 };
 use frame_support::{assert_noop, assert_ok, weights::Weight};
 use std::collections::HashSet;
@@ -29,6 +29,7 @@ pub(super) struct GenesisConfigBuilder {
 	max_upward_queue_size: u32,
 	ump_service_total_weight: Weight,
 	ump_max_individual_weight: Weight,
+	ump_max_weight_per_para: Weight,
 }
 
 impl Default for GenesisConfigBuilder {
@@ -40,6 +41,7 @@ impl Default for GenesisConfigBuilder {
 			max_upward_queue_size: 64,
 			ump_service_total_weight: Weight::from_parts(1000, 1000),
 			ump_max_individual_weight: Weight::from_parts(100, 100),
+			ump_max_weight_per_para: Weight::zero(),
 		}
 	}
 }
@@ -55,6 +57,7 @@ impl GenesisConfigBuilder {
 		config.max_upward_queue_size = self.max_upward_queue_size;
 		config.ump_service_total_weight = self.ump_service_total_weight;
 		config.ump_max_individual_weight = self.ump_max_individual_weight;
+		config.ump_max_weight_per_para = self.ump_max_weight_per_para;
 		genesis
 	}
 }
@@ -230,6 +233,64 @@ fn dispatch_keeps_message_after_weight_exhausted() {
 	});
 }
 
+#[test]
+fn dispatch_respects_per_para_weight_budget() {
+	let a = ParaId::from(128);
+	let b = ParaId::from(228);
+
+	let a_msg_1 = (200u32, "a_msg_1").encode();
+	let a_msg_2 = (200u32, "a_msg_2").encode();
+	let a_msg_3 = (400u32, "a_msg_3").encode();
+	let b_msg_1 = (100u32, "b_msg_1").encode();
+
+	new_test_ext(
+		GenesisConfigBuilder {
+			ump_service_total_weight: Weight::from_parts(1000, 1000),
+			ump_max_individual_weight: Weight::from_parts(500, 500),
+			ump_max_weight_per_para: Weight::from_parts(300, 300),
+			..Default::default()
+		}
+		.build(),
+	)
+	.execute_with(|| {
+		// Start with the block number 1, so that events are not wiped.
+		System::set_block_number(1);
+
+		queue_upward_msg(a, a_msg_1.clone());
+		queue_upward_msg(a, a_msg_2.clone());
+		queue_upward_msg(a, a_msg_3.clone());
+		queue_upward_msg(b, b_msg_1.clone());
+
+		// `a` can only dispatch one message within its budget, but that doesn't stop `b`.
+		Ump::process_pending_upward_messages();
+		assert_eq!(take_processed(), vec![(a, a_msg_1), (b, b_msg_1)]);
+		assert!(System::events().iter().any(|record| record.event ==
+			RuntimeEvent::Ump(Event::ParaWeightExhausted(
+				a,
+				upward_message_id(&a_msg_2[..]),
+				Weight::from_parts(100, 100),
+				Weight::from_parts(200, 200),
+			))));
+		assert_storage_consistency_exhaustive();
+
+		// A message that needs more than the whole budget of a para is stashed as overweight,
+		// even though it is within `ump_max_individual_weight`.
+		Ump::process_pending_upward_messages();
+		assert_eq!(take_processed(), vec![(a, a_msg_2)]);
+		assert_last_event(
+			Event::OverweightEnqueued(
+				a,
+				upward_message_id(&a_msg_3[..]),
+				0,
+				Weight::from_parts(400, 400),
+			)
+			.into(),
+		);
+		assert_storage_consistency_exhaustive();
+		assert!(NeedsDispatch::<Test>::get().is_empty());
+	});
+}
+
 #[test]
 fn dispatch_correctly_handle_remove_of_latest() {
 	let a = ParaId::from(1991);
@@ -17,10 +17,26 @@
 //! Utilities that don't belong to any particular module but may draw
 //! on all modules.
 
-use primitives::{Id as ParaId, PersistedValidationData, ValidatorIndex};
-use sp_std::{collections::btree_set::BTreeSet, vec::Vec};
+use bitvec::{order::Lsb0 as BitOrderLsb0, slice::BitSlice, vec::BitVec};
+use frame_support::{
+	traits::Get, CloneNoBound, DefaultNoBound, EqNoBound, PartialEqNoBound, RuntimeDebugNoBound,
+};
+use parity_scale_codec::{Compact, Decode, Encode, EncodeLike, Input, MaxEncodedLen, Output};
+use primitives::{
+	well_known_keys, Hash, HrmpChannelId, Id as ParaId, InboundDownwardMessage, InboundHrmpMessage,
+	PersistedValidationData, ValidatorIndex,
+};
+use scale_info::{Type, TypeInfo};
+use sp_runtime::traits::{BlakeTwo256, Hash as HashT};
+use sp_std::{
+	collections::btree_set::BTreeSet,
+	marker::PhantomData,
+	ops::{Deref, DerefMut},
+	vec,
+	vec::Vec,
+};
 
-use crate::{configuration, hrmp, paras};
+use crate::{configuration, hrmp, paras, session_info};
 
 /// Make the persisted validation data for a particular parachain, a specified relay-parent and it's
 /// storage root.
@@ -41,6 +57,69 @@ pub fn make_persisted_validation_data<T: paras::Config + hrmp::Config>(
 	})
 }
 
+/// The relay chain storage keys a parachain needs a proof of to follow its relay chain context.
+///
+/// These cover the active configuration, the current slot, the para's head and code hash, its
+/// upgrade signals, and the state of its downward and horizontal message queues. Paras subscribed
+/// to it are also given the hash of the current validator set.
+pub fn relay_state_proof_keys<T: paras::Config + hrmp::Config + session_info::Config>(
+	para_id: ParaId,
+) -> Vec<Vec<u8>> {
+	let mut keys = vec![
+		well_known_keys::ACTIVE_CONFIG.to_vec(),
+		well_known_keys::CURRENT_SLOT.to_vec(),
+		well_known_keys::para_head(para_id),
+		<paras::Pallet<T>>::current_code_hash_key(para_id),
+		well_known_keys::upgrade_go_ahead_signal(para_id),
+		well_known_keys::upgrade_restriction_signal(para_id),
+		well_known_keys::dmq_mqc_head(para_id),
+		well_known_keys::relay_dispatch_queue_size(para_id),
+		well_known_keys::hrmp_ingress_channel_index(para_id),
+		well_known_keys::hrmp_egress_channel_index(para_id),
+	];
+
+	keys.extend(hrmp::HrmpIngressChannelsIndex::<T>::get(para_id).into_iter().map(|sender| {
+		well_known_keys::hrmp_channels(HrmpChannelId { sender, recipient: para_id })
+	}));
+	keys.extend(hrmp::HrmpEgressChannelsIndex::<T>::get(para_id).into_iter().map(|recipient| {
+		well_known_keys::hrmp_channels(HrmpChannelId { sender: para_id, recipient })
+	}));
+
+	let config = <configuration::Pallet<T>>::config();
+	if config.validator_set_hash_subscribers.binary_search(&para_id).is_ok() {
+		keys.push(<session_info::Pallet<T>>::validator_set_hash_key());
+	}
+
+	keys
+}
+
+/// Extend the message queue chain of a downward message queue, starting at `head`, with the given
+/// messages and return the resulting head.
+///
+/// Collators can use this together with the `dmq_contents` runtime API to check the messages they
+/// ingest against the head proven from relay chain storage.
+pub fn extend_dmq_mqc_head<T: frame_system::Config>(
+	head: Hash,
+	messages: &[InboundDownwardMessage<T::BlockNumber>],
+) -> Hash {
+	messages.iter().fold(head, |head, inbound| {
+		BlakeTwo256::hash_of(&(head, inbound.sent_at, T::Hashing::hash_of(&inbound.msg)))
+	})
+}
+
+/// Extend the message queue chain of an HRMP channel, starting at `head`, with the given messages
+/// and return the resulting head.
+///
+/// The counterpart of [`extend_dmq_mqc_head`] for the `inbound_hrmp_channels_contents` runtime API.
+pub fn extend_hrmp_mqc_head<T: frame_system::Config>(
+	head: Hash,
+	messages: &[InboundHrmpMessage<T::BlockNumber>],
+) -> Hash {
+	messages.iter().fold(head, |head, inbound| {
+		BlakeTwo256::hash_of(&(head, inbound.sent_at, T::Hashing::hash_of(&inbound.data)))
+	})
+}
+
 /// Take an active subset of a set containing all validators.
 ///
 /// First item in pair will be all items in set have indices found in the `active` indices set (in
@@ -97,13 +176,108 @@ pub fn take_active_subset<T: Clone>(active: &[ValidatorIndex], set: &[T]) -> Vec
 	subset
 }
 
+/// A bitvec of at most `S` bits, e.g. one bit per validator for at most `S` validators.
+///
+/// It encodes like a plain `BitVec`, so that storage items can switch to it without a migration.
+/// Its length is fixed once created, its bits can be changed through `DerefMut`.
+#[derive(CloneNoBound, DefaultNoBound, PartialEqNoBound, EqNoBound, RuntimeDebugNoBound)]
+pub struct BoundedBitVec<S>(BitVec<u8, BitOrderLsb0>, PhantomData<S>);
+
+impl<S> BoundedBitVec<S> {
+	/// The bits, without the bound.
+	pub fn into_inner(self) -> BitVec<u8, BitOrderLsb0> {
+		self.0
+	}
+}
+
+impl<S: Get<u32>> TryFrom<BitVec<u8, BitOrderLsb0>> for BoundedBitVec<S> {
+	type Error = BitVec<u8, BitOrderLsb0>;
+
+	fn try_from(bits: BitVec<u8, BitOrderLsb0>) -> Result<Self, Self::Error> {
+		if bits.len() <= S::get() as usize {
+			Ok(BoundedBitVec(bits, PhantomData))
+		} else {
+			Err(bits)
+		}
+	}
+}
+
+impl<S> Deref for BoundedBitVec<S> {
+	type Target = BitSlice<u8, BitOrderLsb0>;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl<S> DerefMut for BoundedBitVec<S> {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.0
+	}
+}
+
+impl<S> PartialEq<BitVec<u8, BitOrderLsb0>> for BoundedBitVec<S> {
+	fn eq(&self, other: &BitVec<u8, BitOrderLsb0>) -> bool {
+		self.0 == *other
+	}
+}
+
+impl<S> Encode for BoundedBitVec<S> {
+	fn size_hint(&self) -> usize {
+		self.0.size_hint()
+	}
+
+	fn encode_to<O: Output + ?Sized>(&self, dest: &mut O) {
+		self.0.encode_to(dest)
+	}
+}
+
+impl<S> EncodeLike<BitVec<u8, BitOrderLsb0>> for BoundedBitVec<S> {}
+
+impl<S: Get<u32>> Decode for BoundedBitVec<S> {
+	fn decode<I: Input>(input: &mut I) -> Result<Self, parity_scale_codec::Error> {
+		let bits = BitVec::<u8, BitOrderLsb0>::decode(input)?;
+		Self::try_from(bits).map_err(|_| "BoundedBitVec exceeds its limit".into())
+	}
+}
+
+impl<S: Get<u32>> MaxEncodedLen for BoundedBitVec<S> {
+	fn max_encoded_len() -> usize {
+		// The number of bits, followed by the bytes holding them.
+		Compact::<u32>::max_encoded_len() + (S::get() as usize + 7) / 8
+	}
+}
+
+impl<S: 'static> TypeInfo for BoundedBitVec<S> {
+	type Identity = BitVec<u8, BitOrderLsb0>;
+
+	fn type_info() -> Type {
+		BitVec::<u8, BitOrderLsb0>::type_info()
+	}
+}
+
 #[cfg(test)]
 mod tests {
 
 	use sp_std::vec::Vec;
 
-	use crate::util::{split_active_subset, take_active_subset};
-	use primitives::ValidatorIndex;
+	use crate::{
+		configuration, hrmp,
+		mock::{new_test_ext, Test},
+		session_info,
+		util::{
+			extend_dmq_mqc_head, extend_hrmp_mqc_head, relay_state_proof_keys, split_active_subset,
+			take_active_subset, BoundedBitVec,
+		},
+	};
+	use bitvec::{order::Lsb0 as BitOrderLsb0, vec::BitVec};
+	use frame_support::traits::ConstU32;
+	use hex_literal::hex;
+	use parity_scale_codec::{Decode, Encode, MaxEncodedLen};
+	use primitives::{
+		well_known_keys, Hash, HrmpChannelId, Id as ParaId, InboundDownwardMessage,
+		InboundHrmpMessage, ValidatorIndex,
+	};
 
 	#[test]
 	fn take_active_subset_is_compatible_with_split_active_subset() {
@@ -115,4 +289,88 @@ mod tests {
 		assert_eq!(unselected, vec![9, 6, 4, 5, 2, 0, 8]);
 		assert_eq!(selected, vec![1, 3, 7]);
 	}
+
+	#[test]
+	fn relay_state_proof_keys_cover_hrmp_channels() {
+		let para = ParaId::from(1);
+		let sender = ParaId::from(2);
+		let recipient = ParaId::from(3);
+
+		new_test_ext(Default::default()).execute_with(|| {
+			hrmp::HrmpIngressChannelsIndex::<Test>::insert(para, vec![sender]);
+			hrmp::HrmpEgressChannelsIndex::<Test>::insert(para, vec![recipient]);
+
+			let keys = relay_state_proof_keys::<Test>(para);
+			assert!(keys.contains(&well_known_keys::ACTIVE_CONFIG.to_vec()));
+			assert!(keys.contains(&well_known_keys::para_head(para)));
+			assert!(keys.contains(&well_known_keys::hrmp_channels(HrmpChannelId {
+				sender,
+				recipient: para,
+			})));
+			assert!(keys.contains(&well_known_keys::hrmp_channels(HrmpChannelId {
+				sender: para,
+				recipient,
+			})));
+		});
+	}
+
+	#[test]
+	fn relay_state_proof_keys_cover_the_validator_set_hash_of_subscribers() {
+		let subscriber = ParaId::from(1);
+		let other = ParaId::from(2);
+
+		new_test_ext(Default::default()).execute_with(|| {
+			let mut config = configuration::ActiveConfig::<Test>::get();
+			config.validator_set_hash_subscribers = vec![subscriber];
+			configuration::ActiveConfig::<Test>::put(config);
+
+			let key = session_info::ValidatorSetHash::<Test>::hashed_key().to_vec();
+			assert!(relay_state_proof_keys::<Test>(subscriber).contains(&key));
+			assert!(!relay_state_proof_keys::<Test>(other).contains(&key));
+		});
+	}
+
+	#[test]
+	fn mqc_heads_are_extended_like_the_message_queues() {
+		let downward = vec![
+			InboundDownwardMessage { sent_at: 2, msg: vec![1, 2, 3] },
+			InboundDownwardMessage { sent_at: 3, msg: vec![4, 5, 6] },
+		];
+		// the same fixture as the DMP module's `dmp_mqc_head_fixture`.
+		assert_eq!(
+			extend_dmq_mqc_head::<Test>(Hash::zero(), &downward),
+			hex!["88dc00db8cc9d22aa62b87807705831f164387dfa49f80a8600ed1cbe1704b6b"].into(),
+		);
+
+		// extending in steps is the same as extending at once.
+		let horizontal = vec![
+			InboundHrmpMessage { sent_at: 2, data: vec![1, 2, 3] },
+			InboundHrmpMessage { sent_at: 3, data: vec![4, 5, 6] },
+		];
+		let head = extend_hrmp_mqc_head::<Test>(Hash::zero(), &horizontal[..1]);
+		assert_eq!(
+			extend_hrmp_mqc_head::<Test>(head, &horizontal[1..]),
+			extend_hrmp_mqc_head::<Test>(Hash::zero(), &horizontal),
+		);
+		assert_eq!(extend_hrmp_mqc_head::<Test>(head, &[]), head);
+	}
+
+	#[test]
+	fn bounded_bitvec_encodes_like_a_bitvec() {
+		let bits = bitvec::bitvec![u8, BitOrderLsb0; 1, 0, 1, 1, 0, 0, 0, 0, 1, 1];
+		let bounded = BoundedBitVec::<ConstU32<10>>::try_from(bits.clone()).unwrap();
+		assert_eq!(bounded.encode(), bits.encode());
+		assert_eq!(
+			BoundedBitVec::<ConstU32<10>>::decode(&mut &bits.encode()[..]).unwrap(),
+			bounded,
+		);
+		assert!(bits.encode().len() <= BoundedBitVec::<ConstU32<10>>::max_encoded_len());
+
+		// Bitvecs over the limit are neither created nor decoded.
+		assert_eq!(BoundedBitVec::<ConstU32<9>>::try_from(bits.clone()), Err(bits.clone()));
+		assert!(BoundedBitVec::<ConstU32<9>>::decode(&mut &bits.encode()[..]).is_err());
+
+		let full = BitVec::<u8, BitOrderLsb0>::repeat(true, u16::MAX as usize);
+		assert!(full.encode().len() <= BoundedBitVec::<ConstU32<65535>>::max_encoded_len());
+	}
 }
@@ -55,7 +55,9 @@ pub mod runtime_common_slots;
 pub mod runtime_parachains_configuration;
 pub mod runtime_parachains_disputes;
 pub mod runtime_parachains_hrmp;
+pub mod runtime_parachains_inclusion;
 pub mod runtime_parachains_initializer;
 pub mod runtime_parachains_paras;
 pub mod runtime_parachains_paras_inherent;
+pub mod runtime_parachains_scheduler;
 pub mod runtime_parachains_ump;
@@ -0,0 +1,53 @@
+// Copyright (C) Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+//! Weights for `runtime_parachains::scheduler`
+//!
+//! The scheduler is not benchmarked for this runtime yet, so its calls charge a full block, which
+//! keeps them out of it. This file must be replaced by the output of the benchmark CLI before they
+//! are enabled:
+//!
+//! ./target/production/polkadot benchmark pallet --chain=polkadot-dev --steps=50 --repeat=20
+//!     --pallet=runtime_parachains::scheduler --extrinsic=* --execution=wasm
+//!     --wasm-execution=compiled --header=./file_header.txt
+//!     --output=./runtime/polkadot/src/weights/runtime_parachains_scheduler.rs
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::Weight};
+use sp_std::marker::PhantomData;
+
+/// Weight functions for `runtime_parachains::scheduler`.
+pub struct WeightInfo<T>(PhantomData<T>);
+impl<T: frame_system::Config> runtime_parachains::scheduler::WeightInfo for WeightInfo<T> {
+	/// Not benchmarked for this runtime: charges a full block, so that the call can't be included.
+	fn place_parathread_claim() -> Weight {
+		T::BlockWeights::get().max_block
+	}
+	/// Not benchmarked for this runtime: charges a full block, so that the call can't be included.
+	fn place_parathread_claim_with_collators(_c: u32, ) -> Weight {
+		T::BlockWeights::get().max_block
+	}
+	/// Not benchmarked for this runtime: charges a full block, so that the call can't be included.
+	fn place_parathread_bid(_c: u32, ) -> Weight {
+		T::BlockWeights::get().max_block
+	}
+	/// Not benchmarked for this runtime: charges a full block, so that the call can't be included.
+	fn place_order(_c: u32, ) -> Weight {
+		T::BlockWeights::get().max_block
+	}
+}
@@ -20,6 +20,9 @@
 //! WORST CASE MAP SIZE: `1000000`
 //! HOSTNAME: `bm6`, CPU: `Intel(R) Core(TM) i7-7700K CPU @ 4.20GHz`
 //! EXECUTION: Some(Wasm), WASM-EXECUTION: Compiled, CHAIN: Some("rococo-dev"), DB CACHE: 1024
+//!
+//! NOTE: the weight functions documented as placeholders below were written by hand and
+//! are not part of the generated output.
 
 // Executed Command:
 // ./target/production/polkadot
@@ -285,4 +288,49 @@ impl<T: frame_system::Config> runtime_parachains::paras::WeightInfo for WeightIn
 			.saturating_add(T::DbWeight::get().reads(4))
 			.saturating_add(T::DbWeight::get().writes(1))
 	}
+	/// Not benchmarked: a hand-written, conservative placeholder until this file is regenerated.
+	/// Storage: Paras FutureCodeHash (r:1 w:1)
+	/// Storage: Paras PvfActiveVoteMap (r:1 w:1)
+	/// Storage: Paras PvfActiveVoteList (r:1 w:1)
+	/// Storage: Paras CodeByHashRefs (r:1 w:1)
+	/// Storage: Paras CodeByHash (r:0 w:1)
+	/// Storage: Paras FutureCodeUpgrades (r:0 w:1)
+	/// Storage: Paras UpcomingUpgrades (r:1 w:1)
+	/// Storage: Paras UpgradeGoAheadSignal (r:0 w:1)
+	/// Storage: Paras UpgradeRestrictionSignal (r:0 w:1)
+	/// Storage: Paras UpgradeCooldowns (r:1 w:1)
+	fn cancel_code_upgrade() -> Weight {
+		Weight::from_parts(88_000_000, 230_000)
+			.saturating_add(T::DbWeight::get().reads(6))
+			.saturating_add(T::DbWeight::get().writes(10))
+	}
+	/// Not benchmarked: a hand-written, conservative placeholder until this file is regenerated.
+	/// Storage: Paras CurrentCodeHash (r:1 w:1)
+	/// Storage: Paras PastCodeMeta (r:1 w:1)
+	/// Storage: Paras PastCodeHash (r:1 w:1)
+	/// Storage: Paras CodeByHash (r:1 w:1)
+	/// Storage: Paras FutureCodeHash (r:1 w:1)
+	/// Storage: Paras PvfActiveVoteMap (r:1 w:1)
+	/// Storage: Paras PvfActiveVoteList (r:1 w:1)
+	/// Storage: Paras CodeByHashRefs (r:2 w:2)
+	/// Storage: Paras FutureCodeUpgrades (r:0 w:1)
+	/// Storage: Paras UpcomingUpgrades (r:1 w:1)
+	/// Storage: Paras UpgradeGoAheadSignal (r:0 w:1)
+	/// Storage: Paras UpgradeRestrictionSignal (r:0 w:1)
+	/// Storage: Paras UpgradeCooldowns (r:1 w:1)
+	/// Storage: Paras PastCodePruning (r:1 w:1)
+	/// Storage: Paras CodeRollbacks (r:1 w:1)
+	fn force_rollback_code() -> Weight {
+		Weight::from_parts(130_000_000, 380_000)
+			.saturating_add(T::DbWeight::get().reads(13))
+			.saturating_add(T::DbWeight::get().writes(16))
+	}
+	/// Not benchmarked: a hand-written, conservative placeholder until this file is regenerated.
+	/// Storage: Paras ParaLifecycles (r:1 w:0)
+	/// Storage: Paras FrozenParas (r:1 w:1)
+	fn set_para_frozen() -> Weight {
+		Weight::from_parts(28_000_000, 20_000)
+			.saturating_add(T::DbWeight::get().reads(2))
+			.saturating_add(T::DbWeight::get().writes(1))
+	}
 }
@@ -0,0 +1,105 @@
+// Copyright (C) Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+//! Placeholder weights for `runtime_parachains::scheduler`
+//!
+//! THESE WEIGHTS HAVE NOT BEEN BENCHMARKED. They are hand-written, deliberately conservative
+//! upper bounds on the storage accesses and execution time of each call, and must be replaced by
+//! the output of the benchmark CLI before this runtime is released:
+//!
+//! ./target/production/polkadot benchmark pallet --chain=rococo-dev --steps=50 --repeat=20
+//!     --pallet=runtime_parachains::scheduler --extrinsic=* --execution=wasm
+//!     --wasm-execution=compiled --header=./file_header.txt
+//!     --output=./runtime/rococo/src/weights/runtime_parachains_scheduler.rs
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::Weight};
+use sp_std::marker::PhantomData;
+
+/// Weight functions for `runtime_parachains::scheduler`.
+pub struct WeightInfo<T>(PhantomData<T>);
+impl<T: frame_system::Config> runtime_parachains::scheduler::WeightInfo for WeightInfo<T> {
+	/// Storage: Configuration ActiveConfig (r:1 w:0)
+	/// Storage: System Account (r:1 w:1)
+	/// Storage: Paras ParaLifecycles (r:1 w:0)
+	/// Storage: Paras FrozenParas (r:1 w:0)
+	/// Storage: Paras Parachains (r:1 w:0)
+	/// Storage: ParaScheduler ParathreadQueue (r:1 w:1)
+	/// Storage: ParaScheduler ParathreadClaimIndex (r:1 w:1)
+	/// Storage: ParaScheduler ParathreadWaitingSince (r:1 w:1)
+	/// Storage: ParaScheduler ParathreadClaimDeposits (r:0 w:1)
+	/// Storage: ParaScheduler ParathreadClaimBids (r:0 w:1)
+	fn place_parathread_claim() -> Weight {
+		Weight::from_parts(80_000_000, 40_000)
+			.saturating_add(T::DbWeight::get().reads(8))
+			.saturating_add(T::DbWeight::get().writes(6))
+	}
+	/// Storage: Configuration ActiveConfig (r:1 w:0)
+	/// Storage: System Account (r:1 w:1)
+	/// Storage: Paras ParaLifecycles (r:1 w:0)
+	/// Storage: Paras FrozenParas (r:1 w:0)
+	/// Storage: Paras Parachains (r:1 w:0)
+	/// Storage: ParaScheduler ParathreadQueue (r:1 w:1)
+	/// Storage: ParaScheduler ParathreadClaimIndex (r:1 w:1)
+	/// Storage: ParaScheduler ParathreadWaitingSince (r:1 w:1)
+	/// Storage: ParaScheduler ParathreadClaimCollators (r:0 w:1)
+	/// Storage: ParaScheduler ParathreadClaimDeposits (r:0 w:1)
+	/// Storage: ParaScheduler ParathreadClaimBids (r:0 w:1)
+	/// The range of component `c` is `[1, 8]`.
+	fn place_parathread_claim_with_collators(c: u32, ) -> Weight {
+		Weight::from_parts(80_000_000, 40_000)
+			.saturating_add(Weight::from_parts(1_000_000, 100).saturating_mul(c.into()))
+			.saturating_add(T::DbWeight::get().reads(8))
+			.saturating_add(T::DbWeight::get().writes(7))
+	}
+	/// Storage: Configuration ActiveConfig (r:1 w:0)
+	/// Storage: System Account (r:1 w:1)
+	/// Storage: Paras ParaLifecycles (r:1 w:0)
+	/// Storage: Paras FrozenParas (r:1 w:0)
+	/// Storage: Paras Parachains (r:1 w:0)
+	/// Storage: ParaScheduler ParathreadQueue (r:1 w:1)
+	/// Storage: ParaScheduler ParathreadClaimIndex (r:1 w:1)
+	/// Storage: ParaScheduler ParathreadWaitingSince (r:1 w:1)
+	/// Storage: ParaScheduler ParathreadClaimCollators (r:0 w:1)
+	/// Storage: ParaScheduler ParathreadClaimDeposits (r:0 w:1)
+	/// Storage: ParaScheduler ParathreadClaimBids (r:0 w:1)
+	/// The range of component `c` is `[1, 8]`.
+	fn place_parathread_bid(c: u32, ) -> Weight {
+		Weight::from_parts(80_000_000, 40_000)
+			.saturating_add(Weight::from_parts(1_000_000, 100).saturating_mul(c.into()))
+			.saturating_add(T::DbWeight::get().reads(8))
+			.saturating_add(T::DbWeight::get().writes(7))
+	}
+	/// Storage: Configuration ActiveConfig (r:1 w:0)
+	/// Storage: System Account (r:2 w:2)
+	/// Storage: Paras ParaLifecycles (r:1 w:0)
+	/// Storage: Paras FrozenParas (r:1 w:0)
+	/// Storage: Paras Parachains (r:1 w:0)
+	/// Storage: ParaScheduler ParathreadQueue (r:1 w:1)
+	/// Storage: ParaScheduler ParathreadClaimIndex (r:1 w:1)
+	/// Storage: ParaScheduler ParathreadWaitingSince (r:1 w:1)
+	/// Storage: ParaScheduler ParathreadClaimCollators (r:0 w:1)
+	/// Storage: ParaScheduler OnDemandOrders (r:0 w:1)
+	/// The range of component `c` is `[1, 8]`.
+	fn place_order(c: u32, ) -> Weight {
+		Weight::from_parts(100_000_000, 50_000)
+			.saturating_add(Weight::from_parts(1_000_000, 100).saturating_mul(c.into()))
+			.saturating_add(T::DbWeight::get().reads(9))
+			.saturating_add(T::DbWeight::get().writes(7))
+	}
+}
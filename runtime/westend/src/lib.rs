@@ -50,12 +50,15 @@ use runtime_common::{
 };
 use runtime_parachains::{
 	configuration as parachains_configuration, disputes as parachains_disputes,
-	disputes::slashing as parachains_slashing, dmp as parachains_dmp, hrmp as parachains_hrmp,
-	inclusion as parachains_inclusion, initializer as parachains_initializer,
-	origin as parachains_origin, paras as parachains_paras,
+	disputes::slashing as parachains_slashing,
+	dmp as parachains_dmp, hrmp as parachains_hrmp, inclusion as parachains_inclusion,
+	initializer as parachains_initializer, origin as parachains_origin, paras as parachains_paras,
 	paras_inherent as parachains_paras_inherent, reward_points as parachains_reward_points,
-	runtime_api_impl::v4 as parachains_runtime_api_impl, scheduler as parachains_scheduler,
-	session_info as parachains_session_info, shared as parachains_shared, ump as parachains_ump,
+	runtime_api_impl::{
+		v4 as parachains_runtime_api_impl, vstaging as parachains_staging_runtime_api_impl,
+	},
+	scheduler as parachains_scheduler, session_info as parachains_session_info,
+	shared as parachains_shared, statistics as parachains_statistics, ump as parachains_ump,
 };
 use scale_info::TypeInfo;
 use sp_core::{OpaqueMetadata, RuntimeDebug};
@@ -884,16 +887,29 @@ impl parachains_configuration::Config for Runtime {
 	type WeightInfo = weights::runtime_parachains_configuration::WeightInfo<Runtime>;
 }
 
-impl parachains_shared::Config for Runtime {}
+impl parachains_shared::Config for Runtime {
+	type DisabledValidators = Session;
+}
 
 impl parachains_session_info::Config for Runtime {
 	type ValidatorSet = Historical;
 }
 
+impl parachains_statistics::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+}
+
 impl parachains_inclusion::Config for Runtime {
 	type RuntimeEvent = RuntimeEvent;
 	type DisputesHandler = ParasDisputes;
 	type RewardValidators = parachains_reward_points::RewardValidatorsWithEraPoints<Runtime>;
+	type BackingEquivocationHandler =
+		parachains_slashing::SlashValidatorsForDisputes<ParasSlashing>;
+	type AvailabilityNoShowHandler = parachains_slashing::SlashValidatorsForDisputes<ParasSlashing>;
+	type InclusionHooks = ParaStatistics;
+	type MaxValidators = MaxAuthorities;
+	type MaxAvailabilityCores = ConstU32<1_000>;
+	type WeightInfo = weights::runtime_parachains_inclusion::WeightInfo<Runtime>;
 }
 
 parameter_types! {
@@ -902,6 +918,7 @@ parameter_types! {
 
 impl parachains_paras::Config for Runtime {
 	type RuntimeEvent = RuntimeEvent;
+	type RuntimeOrigin = RuntimeOrigin;
 	type WeightInfo = weights::runtime_parachains_paras::WeightInfo<Runtime>;
 	type UnsignedPriority = ParasUnsignedPriority;
 	type NextSessionRotation = Babe;
@@ -933,12 +950,20 @@ impl parachains_paras_inherent::Config for Runtime {
 	type WeightInfo = weights::runtime_parachains_paras_inherent::WeightInfo<Runtime>;
 }
 
-impl parachains_scheduler::Config for Runtime {}
+impl parachains_scheduler::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type Currency = Balances;
+	type OnBidSettled = ();
+	type OnClaimSlashed = ();
+	type OnDemandFees = ();
+	type WeightInfo = weights::runtime_parachains_scheduler::WeightInfo<Runtime>;
+}
 
 impl parachains_initializer::Config for Runtime {
 	type Randomness = pallet_babe::RandomnessFromOneEpochAgo<Runtime>;
 	type ForceOrigin = EnsureRoot<AccountId>;
 	type WeightInfo = weights::runtime_parachains_initializer::WeightInfo<Runtime>;
+	type OnNewSession = ();
 }
 
 impl paras_sudo_wrapper::Config for Runtime {}
@@ -1157,7 +1182,7 @@ construct_runtime! {
 		ParasShared: parachains_shared::{Pallet, Call, Storage} = 43,
 		ParaInclusion: parachains_inclusion::{Pallet, Call, Storage, Event<T>} = 44,
 		ParaInherent: parachains_paras_inherent::{Pallet, Call, Storage, Inherent} = 45,
-		ParaScheduler: parachains_scheduler::{Pallet, Storage} = 46,
+		ParaScheduler: parachains_scheduler::{Pallet, Call, Storage, Event<T>} = 46,
 		Paras: parachains_paras::{Pallet, Call, Storage, Event, Config, ValidateUnsigned} = 47,
 		Initializer: parachains_initializer::{Pallet, Call, Storage} = 48,
 		Dmp: parachains_dmp::{Pallet, Storage} = 49,
@@ -1166,6 +1191,7 @@ construct_runtime! {
 		ParaSessionInfo: parachains_session_info::{Pallet, Storage} = 52,
 		ParasDisputes: parachains_disputes::{Pallet, Call, Storage, Event<T>} = 53,
 		ParasSlashing: parachains_slashing::{Pallet, Call, Storage, ValidateUnsigned} = 54,
+		ParaStatistics: parachains_statistics::{Pallet, Storage, Event} = 55,
 
 		// Parachain Onboarding Pallets. Start indices at 60 to leave room.
 		Registrar: paras_registrar::{Pallet, Call, Storage, Event<T>, Config} = 60,
@@ -1223,7 +1249,7 @@ pub type Migrations = (
 	>,
 	pallet_nomination_pools::migration::v5::MigrateToV5<Runtime>,
 	// Unreleased - add new migrations here:
-	parachains_configuration::migration::v5::MigrateToV5<Runtime>,
+	runtime_parachains::migrations::Unreleased<Runtime>,
 	pallet_offences::migration::v1::MigrateToV1<Runtime>,
 );
 
@@ -1260,9 +1286,11 @@ mod benches {
 		[runtime_parachains::disputes, ParasDisputes]
 		[runtime_parachains::disputes::slashing, ParasSlashing]
 		[runtime_parachains::hrmp, Hrmp]
+		[runtime_parachains::inclusion, ParaInclusion]
 		[runtime_parachains::initializer, Initializer]
 		[runtime_parachains::paras, Paras]
 		[runtime_parachains::paras_inherent, ParaInherent]
+		[runtime_parachains::scheduler, ParaScheduler]
 		[runtime_parachains::ump, Ump]
 		// Substrate
 		[pallet_bags_list, VoterList]
@@ -1361,6 +1389,7 @@ sp_api::impl_runtime_apis! {
 		}
 	}
 
+	#[api_version(5)]
 	impl primitives::runtime_api::ParachainHost<Block, Hash, BlockNumber> for Runtime {
 		fn validators() -> Vec<ValidatorId> {
 			parachains_runtime_api_impl::validators::<Runtime>()
@@ -1466,6 +1495,155 @@ sp_api::impl_runtime_apis! {
 		fn disputes() -> Vec<(SessionIndex, CandidateHash, DisputeState<BlockNumber>)> {
 			parachains_runtime_api_impl::get_session_disputes::<Runtime>()
 		}
+
+		fn included_candidates(
+			block_number: BlockNumber,
+		) -> Vec<primitives::vstaging::IncludedCandidate<Hash>> {
+			parachains_staging_runtime_api_impl::included_candidates::<Runtime>(block_number)
+		}
+
+		fn persisted_validation_data_with_relay_state(
+			para_id: ParaId,
+			assumption: OccupiedCoreAssumption,
+		) -> Option<(PersistedValidationData<Hash, BlockNumber>, Vec<Vec<u8>>)> {
+			parachains_staging_runtime_api_impl::persisted_validation_data_with_relay_state::<Runtime>(
+				para_id,
+				assumption,
+			)
+		}
+
+		fn para_lifecycle(para_id: ParaId) -> Option<primitives::vstaging::ParaLifecycle> {
+			parachains_staging_runtime_api_impl::para_lifecycle::<Runtime>(para_id)
+		}
+
+		fn candidate_statistics() -> primitives::vstaging::SessionCandidateStatistics {
+			parachains_staging_runtime_api_impl::candidate_statistics::<Runtime>()
+		}
+
+		fn collator_head_counts() -> primitives::vstaging::SessionCollatorHeadCounts {
+			parachains_staging_runtime_api_impl::collator_head_counts::<Runtime>()
+		}
+
+		fn candidates_pending_availability() -> Vec<CommittedCandidateReceipt<Hash>> {
+			parachains_staging_runtime_api_impl::candidates_pending_availability::<Runtime>()
+		}
+
+		fn group_responsibilities() -> Vec<(primitives::CoreIndex, primitives::GroupIndex)> {
+			parachains_staging_runtime_api_impl::group_responsibilities::<Runtime>()
+		}
+
+		fn past_para_heads(para_id: ParaId) -> Vec<(BlockNumber, primitives::HeadData)> {
+			parachains_staging_runtime_api_impl::past_para_heads::<Runtime>(para_id)
+		}
+
+		fn host_configuration() -> primitives::vstaging::HostConfigurationInfo {
+			parachains_staging_runtime_api_impl::host_configuration::<Runtime>()
+		}
+
+		fn key_ownership_proof(
+			session_index: SessionIndex,
+			validator_id: ValidatorId,
+		) -> Option<primitives::vstaging::OpaqueKeyOwnershipProof> {
+			use parity_scale_codec::Encode;
+
+			if session_index != Session::current_index() {
+				return None
+			}
+
+			Historical::prove((primitives::PARACHAIN_KEY_TYPE_ID, validator_id))
+				.map(|p| p.encode())
+				.map(primitives::vstaging::OpaqueKeyOwnershipProof::new)
+		}
+
+		fn candidate_included_at(
+			candidate_hash: CandidateHash,
+		) -> Option<(BlockNumber, primitives::CoreIndex)> {
+			parachains_staging_runtime_api_impl::candidate_included_at::<Runtime>(candidate_hash)
+		}
+
+		fn availability_votes(
+			core: primitives::CoreIndex,
+		) -> Option<primitives::vstaging::CoreAvailabilityVotes> {
+			parachains_staging_runtime_api_impl::availability_votes::<Runtime>(core)
+		}
+
+		fn claim_queue() -> BTreeMap<primitives::CoreIndex, Vec<ParaId>> {
+			parachains_staging_runtime_api_impl::claim_queue::<Runtime>()
+		}
+
+		fn parathread_starvation() -> Vec<(ParaId, BlockNumber)> {
+			parachains_staging_runtime_api_impl::parathread_starvation::<Runtime>()
+		}
+
+		fn storage_inconsistencies_repaired() -> u32 {
+			parachains_staging_runtime_api_impl::storage_inconsistencies_repaired::<Runtime>()
+		}
+
+		fn simulate_inclusion(
+			parent_header: Header,
+			bitfields: primitives::UncheckedSignedAvailabilityBitfields,
+			backed_candidates: Vec<primitives::BackedCandidate<Hash>>,
+		) -> primitives::vstaging::InclusionSimulation {
+			parachains_staging_runtime_api_impl::simulate_inclusion::<Runtime>(
+				parent_header,
+				bitfields,
+				backed_candidates,
+			)
+		}
+
+		fn api_features() -> primitives::vstaging::ApiFeatures {
+			parachains_staging_runtime_api_impl::api_features::<Runtime>()
+		}
+
+		fn relay_storage_roots() -> Vec<(BlockNumber, Hash)> {
+			parachains_staging_runtime_api_impl::relay_storage_roots::<Runtime>()
+		}
+
+		fn inclusion_diagnostics(
+			parent_header: Header,
+			backed_candidate: primitives::BackedCandidate<Hash>,
+		) -> primitives::vstaging::InclusionDiagnostics {
+			parachains_staging_runtime_api_impl::inclusion_diagnostics::<Runtime>(
+				parent_header,
+				backed_candidate,
+			)
+		}
+
+		fn unapplied_slashes(
+		) -> Vec<(SessionIndex, CandidateHash, primitives::vstaging::slashing::PendingSlashes)> {
+			parachains_staging_runtime_api_impl::unapplied_slashes::<Runtime>()
+		}
+
+		fn submit_report_dispute_lost(
+			dispute_proof: primitives::vstaging::slashing::DisputeProof,
+			key_ownership_proof: primitives::vstaging::OpaqueKeyOwnershipProof,
+		) -> Option<()> {
+			parachains_staging_runtime_api_impl::submit_unsigned_slashing_report::<Runtime>(
+				dispute_proof,
+				key_ownership_proof.decode()?,
+			)
+		}
+
+		fn core_assignment_sources(
+		) -> Vec<(primitives::CoreIndex, primitives::vstaging::CoreAssignmentSource)> {
+			parachains_staging_runtime_api_impl::core_assignment_sources::<Runtime>()
+		}
+
+		fn core_bitfield_layout_version() -> u32 {
+			parachains_staging_runtime_api_impl::core_bitfield_layout_version()
+		}
+
+		fn bitfield_core_layout_hash() -> Option<Hash> {
+			parachains_staging_runtime_api_impl::bitfield_core_layout_hash::<Runtime>()
+		}
+
+		fn minimum_backing_votes(group_size: u32) -> u32 {
+			parachains_staging_runtime_api_impl::minimum_backing_votes::<Runtime>(group_size)
+		}
+
+		fn availability_threshold(n_validators: u32) -> u32 {
+			parachains_staging_runtime_api_impl::availability_threshold::<Runtime>(n_validators)
+		}
 	}
 
 	impl beefy_primitives::BeefyApi<Block> for Runtime {
@@ -0,0 +1,95 @@
+// Copyright (C) Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+//! Placeholder weights for `runtime_parachains::inclusion`
+//!
+//! THESE WEIGHTS HAVE NOT BEEN BENCHMARKED. They are hand-written, deliberately conservative
+//! upper bounds on the storage accesses and execution time of each call, and must be replaced by
+//! the output of the benchmark CLI before this runtime is released:
+//!
+//! ./target/production/polkadot benchmark pallet --chain=westend-dev --steps=50 --repeat=20
+//!     --pallet=runtime_parachains::inclusion --extrinsic=* --execution=wasm
+//!     --wasm-execution=compiled --header=./file_header.txt
+//!     --output=./runtime/westend/src/weights/runtime_parachains_inclusion.rs
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::Weight};
+use sp_std::marker::PhantomData;
+
+/// Weight functions for `runtime_parachains::inclusion`.
+pub struct WeightInfo<T>(PhantomData<T>);
+impl<T: frame_system::Config> runtime_parachains::inclusion::WeightInfo for WeightInfo<T> {
+	/// Storage: ParasShared ActiveValidatorKeys (r:1 w:0)
+	/// Storage: ParasShared CurrentSessionIndex (r:1 w:0)
+	/// Storage: ParaScheduler AvailabilityCores (r:1 w:0)
+	/// Storage: ParaScheduler CoreLayoutVersion (r:1 w:0)
+	/// Storage: Configuration ActiveConfig (r:1 w:0)
+	/// Storage: ParaInclusion PendingAvailability (r:2 w:1)
+	/// Storage: ParaInclusion PendingAvailabilityCommitments (r:1 w:1)
+	/// Storage: ParaInclusion AvailabilityBitfields (r:0 w:200)
+	/// Storage: ParaInclusion CandidateIncludedAt (r:0 w:1)
+	/// Storage: ParaInclusion CandidatesIncludedInSession (r:1 w:1)
+	/// Storage: Paras Heads (r:0 w:1)
+	/// Storage: Paras MostRecentContext (r:0 w:1)
+	/// The range of component `c` is `[1, 40]`.
+	fn process_bitfields(c: u32, ) -> Weight {
+		Weight::from_parts(700_000_000, 1_200_000)
+			.saturating_add(Weight::from_parts(25_000_000, 20_000).saturating_mul(c.into()))
+			.saturating_add(T::DbWeight::get().reads(8))
+			.saturating_add(T::DbWeight::get().reads((6_u64).saturating_mul(c.into())))
+			.saturating_add(T::DbWeight::get().writes(201))
+			.saturating_add(T::DbWeight::get().writes((9_u64).saturating_mul(c.into())))
+	}
+	/// Storage: ParasShared CurrentSessionIndex (r:1 w:0)
+	/// Storage: ParasShared AllowedRelayParents (r:1 w:0)
+	/// Storage: ParaScheduler ValidatorGroups (r:1 w:0)
+	/// Storage: ParaSessionInfo AccountKeys (r:1 w:0)
+	/// Storage: Configuration ActiveConfig (r:1 w:0)
+	/// Storage: Paras CurrentCodeHash (r:1 w:0)
+	/// Storage: Paras Heads (r:1 w:0)
+	/// Storage: Paras FutureCodeUpgrades (r:1 w:0)
+	/// Storage: Dmp DownwardMessageQueues (r:1 w:0)
+	/// Storage: Hrmp HrmpWatermarks (r:1 w:0)
+	/// Storage: ParaInclusion PendingAvailability (r:1 w:1)
+	/// Storage: ParaInclusion SecondedCandidates (r:1 w:1)
+	/// Storage: ParaInclusion PendingAvailabilityCommitments (r:0 w:1)
+	/// The range of component `c` is `[1, 40]`.
+	fn process_candidates(c: u32, ) -> Weight {
+		Weight::from_parts(50_000_000, 700_000)
+			.saturating_add(Weight::from_parts(1_200_000_000, 32_000).saturating_mul(c.into()))
+			.saturating_add(T::DbWeight::get().reads(5))
+			.saturating_add(T::DbWeight::get().reads((8_u64).saturating_mul(c.into())))
+			.saturating_add(T::DbWeight::get().writes((7_u64).saturating_mul(c.into())))
+	}
+	/// Storage: ParaInclusion PendingAvailability (r:1 w:1)
+	/// Storage: ParaInclusion PendingAvailabilityCommitments (r:1 w:1)
+	/// Storage: ParaScheduler AvailabilityCores (r:1 w:1)
+	/// Storage: ParaScheduler ParathreadClaimIndex (r:1 w:0)
+	fn force_clear_pending() -> Weight {
+		Weight::from_parts(60_000_000, 16_000)
+			.saturating_add(T::DbWeight::get().reads(4))
+			.saturating_add(T::DbWeight::get().writes(3))
+	}
+	/// Storage: ParaInclusion DisallowedCandidates (r:1 w:1)
+	/// Storage: ParasShared CurrentSessionIndex (r:1 w:0)
+	fn disallow_candidate() -> Weight {
+		Weight::from_parts(25_000_000, 3_500)
+			.saturating_add(T::DbWeight::get().reads(2))
+			.saturating_add(T::DbWeight::get().writes(1))
+	}
+}